@@ -5,9 +5,7 @@ use smithay::{
         element::{solid::SolidColorRenderElement, surface::WaylandSurfaceRenderElement, AsRenderElements},
         ImportAll, ImportMem, Renderer, Texture,
     },
-    desktop::{
-        space::SpaceElement, utils::OutputPresentationFeedback, Window, WindowSurface, WindowSurfaceType,
-    },
+    desktop::{space::SpaceElement, Window, WindowSurface, WindowSurfaceType},
     input::{
         pointer::{
             AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
@@ -18,10 +16,7 @@ use smithay::{
         Seat,
     },
     output::Output,
-    reexports::{
-        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
-        wayland_server::protocol::wl_surface::WlSurface,
-    },
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
     render_elements,
     utils::{user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial},
     wayland::{compositor::SurfaceData as WlSurfaceData, dmabuf::DmabufFeedback, seat::WaylandFocus},
@@ -95,22 +90,6 @@ impl WindowElement {
             .send_dmabuf_feedback(output, primary_scan_out_output, select_dmabuf_feedback)
     }
 
-    pub fn take_presentation_feedback<F1, F2>(
-        &self,
-        output_feedback: &mut OutputPresentationFeedback,
-        primary_scan_out_output: F1,
-        presentation_feedback_flags: F2,
-    ) where
-        F1: FnMut(&WlSurface, &WlSurfaceData) -> Option<Output> + Copy,
-        F2: FnMut(&WlSurface, &WlSurfaceData) -> wp_presentation_feedback::Kind + Copy,
-    {
-        self.0.take_presentation_feedback(
-            output_feedback,
-            primary_scan_out_output,
-            presentation_feedback_flags,
-        )
-    }
-
     #[cfg(feature = "xwayland")]
     #[inline]
     pub fn is_x11(&self) -> bool {
@@ -133,6 +112,13 @@ impl WindowElement {
     }
 }
 
+impl WaylandFocus for WindowElement {
+    #[inline]
+    fn wl_surface(&self) -> Option<Cow<'_, WlSurface>> {
+        self.0.wl_surface()
+    }
+}
+
 impl IsAlive for WindowElement {
     #[inline]
     fn alive(&self) -> bool {
@@ -140,6 +126,13 @@ impl IsAlive for WindowElement {
     }
 }
 
+impl AsRef<Window> for WindowElement {
+    #[inline]
+    fn as_ref(&self) -> &Window {
+        &self.0
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SSD(WindowElement);
 
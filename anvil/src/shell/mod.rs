@@ -9,8 +9,8 @@ use smithay::wayland::drm_syncobj::DrmSyncobjCachedState;
 use smithay::{
     backend::renderer::utils::on_commit_buffer_handler,
     desktop::{
-        layer_map_for_output, space::SpaceElement, LayerSurface, PopupKind, PopupManager, Space,
-        WindowSurfaceType,
+        layer_map_for_output, rules::RuleAction, space::SpaceElement, LayerSurface, PopupKind, PopupManager,
+        Space, WindowSurfaceType,
     },
     input::pointer::{CursorImageStatus, CursorImageSurfaceData},
     output::Output,
@@ -53,7 +53,6 @@ mod x11;
 mod xdg;
 
 pub use self::element::*;
-pub use self::grabs::*;
 
 fn fullscreen_output_geometry(
     wl_surface: &WlSurface,
@@ -182,6 +181,14 @@ impl<BackendData: Backend> CompositorHandler for AnvilState<BackendData> {
             if let Some(window) = self.window_for_surface(&root) {
                 window.0.on_commit();
 
+                let actions = self
+                    .window_rules
+                    .on_commit(&window.0)
+                    .map(|actions| actions.into_iter().cloned().collect::<Vec<_>>());
+                if let Some(actions) = actions {
+                    self.apply_window_rules(&window, actions);
+                }
+
                 if &root == surface {
                     let buffer_offset = with_states(surface, |states| {
                         states
@@ -223,21 +230,6 @@ impl<BackendData: Backend> CompositorHandler for AnvilState<BackendData> {
             });
         }
 
-        if matches!(&self.dnd_icon, Some(icon) if &icon.surface == surface) {
-            let dnd_icon = self.dnd_icon.as_mut().unwrap();
-            with_states(&dnd_icon.surface, |states| {
-                let buffer_delta = states
-                    .cached_state
-                    .get::<SurfaceAttributes>()
-                    .current()
-                    .buffer_delta
-                    .take()
-                    .unwrap_or_default();
-                tracing::trace!(offset = ?dnd_icon.offset, ?buffer_delta, "moving dnd offset");
-                dnd_icon.offset += buffer_delta;
-            });
-        }
-
         ensure_initial_configure(surface, &self.space, &mut self.popups)
     }
 }
@@ -277,6 +269,25 @@ impl<BackendData: Backend> WlrLayerShellHandler for AnvilState<BackendData> {
 }
 
 impl<BackendData: Backend> AnvilState<BackendData> {
+    fn apply_window_rules(&mut self, window: &WindowElement, actions: Vec<RuleAction>) {
+        for action in actions {
+            match action {
+                RuleAction::Opacity(alpha) => self.space.set_element_opacity(window, alpha),
+                RuleAction::Size(size) => {
+                    if let Some(toplevel) = window.0.toplevel() {
+                        toplevel.with_pending_state(|state| state.size = Some(size));
+                        // the initial configure is sent after the commit anyway
+                        if toplevel.is_initial_configure_sent() {
+                            toplevel.send_pending_configure();
+                        }
+                    }
+                }
+                // anvil neither tiles windows nor has named workspaces
+                RuleAction::Floating(_) | RuleAction::Workspace(_) | RuleAction::Custom(_) => {}
+            }
+        }
+    }
+
     pub fn window_for_surface(&self, surface: &WlSurface) -> Option<WindowElement> {
         self.space
             .elements()
@@ -288,7 +299,6 @@ impl<BackendData: Backend> AnvilState<BackendData> {
 #[derive(Default)]
 pub struct SurfaceData {
     pub geometry: Option<Rectangle<i32, Logical>>,
-    pub resize_state: ResizeState,
 }
 
 fn ensure_initial_configure(surface: &WlSurface, space: &Space<WindowElement>, popups: &mut PopupManager) {
@@ -326,19 +336,6 @@ fn ensure_initial_configure(surface: &WlSurface, space: &Space<WindowElement>, p
             }
         }
 
-        with_states(surface, |states| {
-            let mut data = states
-                .data_map
-                .get::<RefCell<SurfaceData>>()
-                .unwrap()
-                .borrow_mut();
-
-            // Finish resizing.
-            if let ResizeState::WaitingForCommit(_) = data.resize_state {
-                data.resize_state = ResizeState::NotResizing;
-            }
-        });
-
         return;
     }
 
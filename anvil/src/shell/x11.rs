@@ -1,11 +1,10 @@
 use std::{cell::RefCell, os::unix::io::OwnedFd};
 
 use smithay::{
-    desktop::{space::SpaceElement, Window},
+    desktop::{PointerMoveGrab, PointerResizeGrab, TouchMoveGrab, Window},
     input::pointer::Focus,
-    utils::{Logical, Rectangle, SERIAL_COUNTER},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
     wayland::{
-        compositor::with_states,
         selection::{
             data_device::{
                 clear_data_device_selection, current_data_device_selection_userdata,
@@ -28,10 +27,7 @@ use tracing::{error, trace};
 
 use crate::{focus::KeyboardFocusTarget, state::Backend, AnvilState};
 
-use super::{
-    place_new_window, FullscreenSurface, PointerMoveSurfaceGrab, PointerResizeSurfaceGrab, ResizeData,
-    ResizeState, SurfaceData, TouchMoveSurfaceGrab, WindowElement,
-};
+use super::{place_new_window, FullscreenSurface, WindowElement};
 
 #[derive(Debug, Default)]
 struct OldGeometry(RefCell<Option<Rectangle<i32, Logical>>>);
@@ -222,30 +218,8 @@ impl<BackendData: Backend> XwmHandler for AnvilState<BackendData> {
             return;
         };
 
-        let geometry = element.geometry();
-        let loc = self.space.element_location(element).unwrap();
-        let (initial_window_location, initial_window_size) = (loc, geometry.size);
-
-        with_states(&element.wl_surface().unwrap(), move |states| {
-            states
-                .data_map
-                .get::<RefCell<SurfaceData>>()
-                .unwrap()
-                .borrow_mut()
-                .resize_state = ResizeState::Resizing(ResizeData {
-                edges: edges.into(),
-                initial_window_location,
-                initial_window_size,
-            });
-        });
-
-        let grab = PointerResizeSurfaceGrab {
-            start_data,
-            window: element.clone(),
-            edges: edges.into(),
-            initial_window_location,
-            initial_window_size,
-            last_window_size: initial_window_size,
+        let Some(grab) = PointerResizeGrab::new(self, start_data, element.clone(), edges.into()) else {
+            return;
         };
 
         let pointer = self.pointer.clone();
@@ -353,14 +327,13 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                     .elements()
                     .find(|e| matches!(e.0.x11_surface(), Some(w) if w == window));
 
-                if let Some(element) = element {
-                    let mut initial_window_location = self.space.element_location(element).unwrap();
-
+                if let Some(element) = element.cloned() {
                     // If surface is maximized then unmaximize it
                     if window.is_maximized() {
                         window.set_maximized(false).unwrap();
                         let pos = start_data.location;
-                        initial_window_location = (pos.x as i32, pos.y as i32).into();
+                        let initial_window_location: Point<i32, Logical> =
+                            (pos.x as i32, pos.y as i32).into();
                         if let Some(old_geo) = window
                             .user_data()
                             .get::<OldGeometry>()
@@ -373,15 +346,13 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                                 ))
                                 .unwrap();
                         }
+                        self.space
+                            .map_element(element.clone(), initial_window_location, true);
                     }
 
-                    let grab = TouchMoveSurfaceGrab {
-                        start_data,
-                        window: element.clone(),
-                        initial_window_location,
-                    };
-
-                    touch.set_grab(self, grab, SERIAL_COUNTER.next_serial());
+                    if let Some(grab) = TouchMoveGrab::new(self, start_data, element) {
+                        touch.set_grab(self, grab, SERIAL_COUNTER.next_serial());
+                    }
                     return;
                 }
             }
@@ -396,17 +367,16 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             .space
             .elements()
             .find(|e| matches!(e.0.x11_surface(), Some(w) if w == window))
+            .cloned()
         else {
             return;
         };
 
-        let mut initial_window_location = self.space.element_location(element).unwrap();
-
         // If surface is maximized then unmaximize it
         if window.is_maximized() {
             window.set_maximized(false).unwrap();
             let pos = self.pointer.current_location();
-            initial_window_location = (pos.x as i32, pos.y as i32).into();
+            let initial_window_location: Point<i32, Logical> = (pos.x as i32, pos.y as i32).into();
             if let Some(old_geo) = window
                 .user_data()
                 .get::<OldGeometry>()
@@ -419,12 +389,12 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                     ))
                     .unwrap();
             }
+            self.space
+                .map_element(element.clone(), initial_window_location, true);
         }
 
-        let grab = PointerMoveSurfaceGrab {
-            start_data,
-            window: element.clone(),
-            initial_window_location,
+        let Some(grab) = PointerMoveGrab::new(self, start_data, element) else {
+            return;
         };
 
         let pointer = self.pointer.clone();
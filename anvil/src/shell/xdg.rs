@@ -1,10 +1,8 @@
-use std::cell::RefCell;
-
 use smithay::{
     desktop::{
-        find_popup_root_surface, get_popup_toplevel_coords, layer_map_for_output, space::SpaceElement,
-        PopupKeyboardGrab, PopupKind, PopupPointerGrab, PopupUngrabStrategy, Space, Window,
-        WindowSurfaceType,
+        find_popup_root_surface, handle_resize_commit, layer_map_for_output, PointerMoveGrab,
+        PointerResizeGrab, PopupKeyboardGrab, PopupKind, PopupPointerGrab, PopupUngrabStrategy, Space,
+        TouchMoveGrab, TouchResizeGrab, Window, WindowSurfaceType,
     },
     input::{pointer::Focus, Seat},
     output::Output,
@@ -15,13 +13,12 @@ use smithay::{
             Resource,
         },
     },
-    utils::{Logical, Point, Serial},
+    utils::Serial,
     wayland::{
-        compositor::{self, with_states},
+        compositor,
         seat::WaylandFocus,
         shell::xdg::{
             Configure, PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
-            XdgToplevelSurfaceData,
         },
     },
 };
@@ -29,14 +26,10 @@ use tracing::{trace, warn};
 
 use crate::{
     focus::KeyboardFocusTarget,
-    shell::{TouchMoveSurfaceGrab, TouchResizeSurfaceGrab},
     state::{AnvilState, Backend},
 };
 
-use super::{
-    fullscreen_output_geometry, place_new_window, FullscreenSurface, PointerMoveSurfaceGrab,
-    PointerResizeSurfaceGrab, ResizeData, ResizeEdge, ResizeState, SurfaceData, WindowElement,
-};
+use super::{fullscreen_output_geometry, place_new_window, FullscreenSurface, WindowElement};
 
 impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState {
@@ -115,30 +108,8 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
                     tracing::info!("different surface");
                     return;
                 }
-                let geometry = window.geometry();
-                let loc = self.space.element_location(&window).unwrap();
-                let (initial_window_location, initial_window_size) = (loc, geometry.size);
-
-                with_states(surface.wl_surface(), move |states| {
-                    states
-                        .data_map
-                        .get::<RefCell<SurfaceData>>()
-                        .unwrap()
-                        .borrow_mut()
-                        .resize_state = ResizeState::Resizing(ResizeData {
-                        edges: edges.into(),
-                        initial_window_location,
-                        initial_window_size,
-                    });
-                });
-
-                let grab = TouchResizeSurfaceGrab {
-                    start_data,
-                    window,
-                    edges: edges.into(),
-                    initial_window_location,
-                    initial_window_size,
-                    last_window_size: initial_window_size,
+                let Some(grab) = TouchResizeGrab::new(self, start_data, window, edges.into()) else {
+                    return;
                 };
 
                 touch.set_grab(self, grab, serial);
@@ -169,30 +140,8 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
             return;
         }
 
-        let geometry = window.geometry();
-        let loc = self.space.element_location(&window).unwrap();
-        let (initial_window_location, initial_window_size) = (loc, geometry.size);
-
-        with_states(surface.wl_surface(), move |states| {
-            states
-                .data_map
-                .get::<RefCell<SurfaceData>>()
-                .unwrap()
-                .borrow_mut()
-                .resize_state = ResizeState::Resizing(ResizeData {
-                edges: edges.into(),
-                initial_window_location,
-                initial_window_size,
-            });
-        });
-
-        let grab = PointerResizeSurfaceGrab {
-            start_data,
-            window,
-            edges: edges.into(),
-            initial_window_location,
-            initial_window_size,
-            last_window_size: initial_window_size,
+        let Some(grab) = PointerResizeGrab::new(self, start_data, window, edges.into()) else {
+            return;
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
@@ -200,58 +149,13 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
 
     fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
         if let Configure::Toplevel(configure) = configure {
-            if let Some(serial) = with_states(&surface, |states| {
-                if let Some(data) = states.data_map.get::<RefCell<SurfaceData>>() {
-                    if let ResizeState::WaitingForFinalAck(_, serial) = data.borrow().resize_state {
-                        return Some(serial);
-                    }
-                }
-
-                None
-            }) {
-                // When the resize grab is released the surface
-                // resize state will be set to WaitingForFinalAck
-                // and the client will receive a configure request
-                // without the resize state to inform the client
-                // resizing has finished. Here we will wait for
-                // the client to acknowledge the end of the
-                // resizing. To check if the surface was resizing
-                // before sending the configure we need to use
-                // the current state as the received acknowledge
-                // will no longer have the resize state set
-                let is_resizing = with_states(&surface, |states| {
-                    states
-                        .data_map
-                        .get::<XdgToplevelSurfaceData>()
-                        .unwrap()
-                        .lock()
-                        .unwrap()
-                        .current
-                        .states
-                        .contains(xdg_toplevel::State::Resizing)
-                });
-
-                if configure.serial >= serial && is_resizing {
-                    with_states(&surface, |states| {
-                        let mut data = states
-                            .data_map
-                            .get::<RefCell<SurfaceData>>()
-                            .unwrap()
-                            .borrow_mut();
-                        if let ResizeState::WaitingForFinalAck(resize_data, _) = data.resize_state {
-                            data.resize_state = ResizeState::WaitingForCommit(resize_data);
-                        } else {
-                            unreachable!()
-                        }
-                    });
-                }
-            }
-
             let window = self
                 .space
                 .elements()
                 .find(|element| element.wl_surface().as_deref() == Some(&surface));
             if let Some(window) = window {
+                window.0.ack_resize_configure(configure.serial);
+
                 use xdg_decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode;
                 let is_ssd = configure
                     .state
@@ -466,8 +370,6 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                     return;
                 }
 
-                let mut initial_window_location = self.space.element_location(&window).unwrap();
-
                 // If surface is maximized then unmaximize it
                 let current_state = surface.current_state();
                 if current_state.states.contains(xdg_toplevel::State::Maximized) {
@@ -489,13 +391,12 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                     // 4) by doing that, drag will look a lot more natural
                     //
                     // but for anvil needs setting location to pointer location is fine
-                    initial_window_location = start_data.location.to_i32_round();
+                    self.space
+                        .map_element(window.clone(), start_data.location.to_i32_round(), true);
                 }
 
-                let grab = TouchMoveSurfaceGrab {
-                    start_data,
-                    window,
-                    initial_window_location,
+                let Some(grab) = TouchMoveGrab::new(self, start_data, window) else {
+                    return;
                 };
 
                 touch.set_grab(self, grab, serial);
@@ -530,8 +431,6 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             return;
         }
 
-        let mut initial_window_location = self.space.element_location(&window).unwrap();
-
         // If surface is maximized then unmaximize it
         let current_state = surface.current_state();
         if current_state.states.contains(xdg_toplevel::State::Maximized) {
@@ -554,13 +453,12 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             //
             // but for anvil needs setting location to pointer location is fine
             let pos = pointer.current_location();
-            initial_window_location = (pos.x as i32, pos.y as i32).into();
+            self.space
+                .map_element(window.clone(), (pos.x as i32, pos.y as i32), true);
         }
 
-        let grab = PointerMoveSurfaceGrab {
-            start_data,
-            window,
-            initial_window_location,
+        let Some(grab) = PointerMoveGrab::new(self, start_data, window) else {
+            return;
         };
 
         pointer.set_grab(self, grab, serial, Focus::Clear);
@@ -590,15 +488,11 @@ impl<BackendData: Backend> AnvilState<BackendData> {
 
         let window_geo = self.space.element_geometry(&window).unwrap();
 
-        // The target geometry for the positioner should be relative to its parent's geometry, so
-        // we will compute that here.
+        // The target geometry for the positioner should be relative to the root window's geometry.
         let mut target = outputs_geo;
-        target.loc -= get_popup_toplevel_coords(&PopupKind::Xdg(popup.clone()));
         target.loc -= window_geo.loc;
 
-        popup.with_pending_state(|state| {
-            state.geometry = state.positioner.get_unconstrained_geometry(target);
-        });
+        PopupKind::Xdg(popup.clone()).unconstrain(target);
     }
 }
 
@@ -609,46 +503,8 @@ fn handle_toplevel_commit(space: &mut Space<WindowElement>, surface: &WlSurface)
         .find(|w| w.wl_surface().as_deref() == Some(surface))
         .cloned()?;
 
-    let mut window_loc = space.element_location(&window)?;
-    let geometry = window.geometry();
-
-    let new_loc: Point<Option<i32>, Logical> = with_states(window.wl_surface().as_deref()?, |states| {
-        let data = states.data_map.get::<RefCell<SurfaceData>>()?.borrow_mut();
-
-        if let ResizeState::Resizing(resize_data) = data.resize_state {
-            let edges = resize_data.edges;
-            let loc = resize_data.initial_window_location;
-            let size = resize_data.initial_window_size;
-
-            // If the window is being resized by top or left, its location must be adjusted
-            // accordingly.
-            edges.intersects(ResizeEdge::TOP_LEFT).then(|| {
-                let new_x = edges
-                    .intersects(ResizeEdge::LEFT)
-                    .then_some(loc.x + (size.w - geometry.size.w));
-
-                let new_y = edges
-                    .intersects(ResizeEdge::TOP)
-                    .then_some(loc.y + (size.h - geometry.size.h));
-
-                (new_x, new_y).into()
-            })
-        } else {
-            None
-        }
-    })?;
-
-    if let Some(new_x) = new_loc.x {
-        window_loc.x = new_x;
-    }
-    if let Some(new_y) = new_loc.y {
-        window_loc.y = new_y;
-    }
-
-    if new_loc.x.is_some() || new_loc.y.is_some() {
-        // If TOP or LEFT side of the window got resized, we have to move it
-        space.map_element(window, window_loc, false);
-    }
+    // If TOP or LEFT side of the window got resized, we have to move it
+    handle_resize_commit(space, &window);
 
     Some(())
 }
@@ -20,16 +20,15 @@ use smithay::{
     delegate_shm, delegate_tablet_manager, delegate_text_input_manager, delegate_viewporter,
     delegate_virtual_keyboard_manager, delegate_xdg_activation, delegate_xdg_decoration, delegate_xdg_shell,
     desktop::{
+        rules::WindowRules,
+        snap::EdgeSnapping,
         space::SpaceElement,
-        utils::{
-            surface_presentation_feedback_flags_from_states, surface_primary_scanout_output,
-            update_surface_primary_scanout_output, OutputPresentationFeedback,
-        },
+        utils::{surface_primary_scanout_output, update_surface_primary_scanout_output},
         PopupKind, PopupManager, Space,
     },
     input::{
         keyboard::{Keysym, LedState, XkbConfig},
-        pointer::{CursorImageStatus, CursorImageSurfaceData, PointerHandle},
+        pointer::{CursorImageStatus, PointerHandle},
         Seat, SeatHandler, SeatState,
     },
     output::Output,
@@ -40,7 +39,7 @@ use smithay::{
         },
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
-            protocol::{wl_data_source::WlDataSource, wl_surface::WlSurface},
+            protocol::wl_surface::WlSurface,
             Display, DisplayHandle, Resource,
         },
     },
@@ -82,6 +81,7 @@ use smithay::{
         single_pixel_buffer::SinglePixelBufferState,
         socket::ListeningSocketSource,
         tablet_manager::{TabletManagerState, TabletSeatHandler},
+        tearing_control::TearingControlState,
         text_input::TextInputManagerState,
         viewporter::ViewporterState,
         virtual_keyboard::VirtualKeyboardManagerState,
@@ -131,6 +131,8 @@ pub struct AnvilState<BackendData: Backend + 'static> {
     // desktop
     pub space: Space<WindowElement>,
     pub popups: PopupManager,
+    pub snapping: EdgeSnapping,
+    pub window_rules: WindowRules,
 
     // smithay state
     pub compositor_state: CompositorState,
@@ -153,8 +155,6 @@ pub struct AnvilState<BackendData: Backend + 'static> {
     pub xwayland_shell_state: xwayland_shell::XWaylandShellState,
     pub single_pixel_buffer_state: SinglePixelBufferState,
 
-    pub dnd_icon: Option<DndIcon>,
-
     // input-related fields
     pub suppressed_keys: Vec<Keysym>,
     pub cursor_status: CursorImageStatus,
@@ -174,12 +174,6 @@ pub struct AnvilState<BackendData: Backend + 'static> {
     pub show_window_preview: bool,
 }
 
-#[derive(Debug)]
-pub struct DndIcon {
-    pub surface: WlSurface,
-    pub offset: Point<i32, Logical>,
-}
-
 delegate_compositor!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
 
 impl<BackendData: Backend> DataDeviceHandler for AnvilState<BackendData> {
@@ -188,28 +182,7 @@ impl<BackendData: Backend> DataDeviceHandler for AnvilState<BackendData> {
     }
 }
 
-impl<BackendData: Backend> ClientDndGrabHandler for AnvilState<BackendData> {
-    fn started(&mut self, _source: Option<WlDataSource>, icon: Option<WlSurface>, _seat: Seat<Self>) {
-        let offset = if let CursorImageStatus::Surface(ref surface) = self.cursor_status {
-            with_states(surface, |states| {
-                let hotspot = states
-                    .data_map
-                    .get::<CursorImageSurfaceData>()
-                    .unwrap()
-                    .lock()
-                    .unwrap()
-                    .hotspot;
-                Point::from((-hotspot.x, -hotspot.y))
-            })
-        } else {
-            (0, 0).into()
-        };
-        self.dnd_icon = icon.map(|surface| DndIcon { surface, offset });
-    }
-    fn dropped(&mut self, _seat: Seat<Self>) {
-        self.dnd_icon = None;
-    }
-}
+impl<BackendData: Backend> ClientDndGrabHandler for AnvilState<BackendData> {}
 impl<BackendData: Backend> ServerDndGrabHandler for AnvilState<BackendData> {
     fn send(&mut self, _mime_type: String, _fd: OwnedFd, _seat: Seat<Self>) {
         unreachable!("Anvil doesn't do server-side grabs");
@@ -565,6 +538,8 @@ smithay::delegate_xdg_foreign!(@<BackendData: Backend + 'static> AnvilState<Back
 
 smithay::delegate_single_pixel_buffer!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
 
+smithay::delegate_tearing_control!(@<BackendData: Backend + 'static> AnvilState<BackendData>);
+
 impl<BackendData: Backend + 'static> AnvilState<BackendData> {
     pub fn init(
         display: Display<AnvilState<BackendData>>,
@@ -627,6 +602,7 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
         let fractional_scale_manager_state = FractionalScaleManagerState::new::<Self>(&dh);
         let xdg_foreign_state = XdgForeignState::new::<Self>(&dh);
         let single_pixel_buffer_state = SinglePixelBufferState::new::<Self>(&dh);
+        TearingControlState::new::<Self>(&dh);
         TextInputManagerState::new::<Self>(&dh);
         InputMethodManagerState::new::<Self, _>(&dh, |_client| true);
         VirtualKeyboardManagerState::new::<Self, _>(&dh, |_client| true);
@@ -669,6 +645,8 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             handle,
             space: Space::default(),
             popups: PopupManager::default(),
+            snapping: EdgeSnapping::default(),
+            window_rules: WindowRules::new(),
             compositor_state,
             data_device_state,
             layer_shell_state,
@@ -686,7 +664,6 @@ impl<BackendData: Backend + 'static> AnvilState<BackendData> {
             fractional_scale_manager_state,
             xdg_foreign_state,
             single_pixel_buffer_state,
-            dnd_icon: None,
             suppressed_keys: Vec::new(),
             cursor_status: CursorImageStatus::default_named(),
             seat_name,
@@ -778,20 +755,15 @@ pub fn post_repaint(
     let throttle = Some(Duration::from_secs(1));
 
     space.elements().for_each(|window| {
+        // the preferred scale of windows is sent by `Space::send_preferred_scales`
         window.with_surfaces(|surface, states| {
-            let primary_scanout_output = update_surface_primary_scanout_output(
+            update_surface_primary_scanout_output(
                 surface,
                 output,
                 states,
                 render_element_states,
                 default_primary_scanout_output_compare,
             );
-
-            if let Some(output) = primary_scanout_output {
-                with_fractional_scale(states, |fraction_scale| {
-                    fraction_scale.set_preferred_scale(output.current_scale().fractional_scale());
-                });
-            }
         });
 
         if space.outputs_for_element(window).contains(output) {
@@ -840,35 +812,6 @@ pub fn post_repaint(
     }
 }
 
-#[profiling::function]
-pub fn take_presentation_feedback(
-    output: &Output,
-    space: &Space<WindowElement>,
-    render_element_states: &RenderElementStates,
-) -> OutputPresentationFeedback {
-    let mut output_presentation_feedback = OutputPresentationFeedback::new(output);
-
-    space.elements().for_each(|window| {
-        if space.outputs_for_element(window).contains(output) {
-            window.take_presentation_feedback(
-                &mut output_presentation_feedback,
-                surface_primary_scanout_output,
-                |surface, _| surface_presentation_feedback_flags_from_states(surface, render_element_states),
-            );
-        }
-    });
-    let map = smithay::desktop::layer_map_for_output(output);
-    for layer_surface in map.layers() {
-        layer_surface.take_presentation_feedback(
-            &mut output_presentation_feedback,
-            surface_primary_scanout_output,
-            |surface, _| surface_presentation_feedback_flags_from_states(surface, render_element_states),
-        );
-    }
-
-    output_presentation_feedback
-}
-
 pub trait Backend {
     const HAS_RELATIVE_MOTION: bool = false;
     const HAS_GESTURES: bool = false;
@@ -6,12 +6,12 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::state::{DndIcon, SurfaceDmabufFeedback};
+use crate::state::SurfaceDmabufFeedback;
 use crate::{
     drawing::*,
     render::*,
-    shell::WindowElement,
-    state::{post_repaint, take_presentation_feedback, AnvilState, Backend},
+    shell::{FullscreenSurface, WindowElement},
+    state::{post_repaint, AnvilState, Backend},
 };
 #[cfg(feature = "renderer_sync")]
 use smithay::backend::drm::compositor::PrimaryPlaneElement;
@@ -32,6 +32,7 @@ use smithay::{
             DrmEvent, DrmEventMetadata, DrmNode, DrmSurface, GbmBufferedSurface, NodeType,
         },
         egl::{self, context::ContextPriority, EGLDevice, EGLDisplay},
+        frame_scheduler::FrameScheduler,
         input::InputEvent,
         libinput::{LibinputInputBackend, LibinputSessionInterface},
         renderer::{
@@ -44,16 +45,14 @@ use smithay::{
         },
         session::{
             libseat::{self, LibSeatSession},
+            logind::{LogindSleepNotifier, SleepEvent},
             Event as SessionEvent, Session,
         },
         udev::{all_gpus, primary_gpu, UdevBackend, UdevEvent},
         SwapBuffersError,
     },
     delegate_dmabuf, delegate_drm_lease,
-    desktop::{
-        space::{Space, SurfaceTree},
-        utils::OutputPresentationFeedback,
-    },
+    desktop::{space::Space, utils::OutputPresentationFeedback},
     input::{
         keyboard::LedState,
         pointer::{CursorImageAttributes, CursorImageStatus},
@@ -70,10 +69,7 @@ use smithay::{
         },
         input::{DeviceCapability, Libinput},
         rustix::fs::OFlags,
-        wayland_protocols::wp::{
-            linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
-            presentation_time::server::wp_presentation_feedback,
-        },
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
         wayland_server::{backend::GlobalId, protocol::wl_surface, Display, DisplayHandle},
     },
     utils::{Clock, DeviceFd, IsAlive, Logical, Monotonic, Physical, Point, Rectangle, Scale, Transform},
@@ -86,6 +82,8 @@ use smithay::{
             DrmLease, DrmLeaseBuilder, DrmLeaseHandler, DrmLeaseRequest, DrmLeaseState, LeaseRejected,
         },
         drm_syncobj::{supports_syncobj_eventfd, DrmSyncobjHandler, DrmSyncobjState},
+        selection::data_device::{dnd_icon, DndIcon},
+        tearing_control,
     },
 };
 use smithay_drm_extras::{
@@ -244,7 +242,8 @@ pub fn run_udev() {
     };
     info!("Using {} as primary gpu.", primary_gpu);
 
-    let gpus = GpuManager::new(GbmGlesBackend::with_context_priority(ContextPriority::High)).unwrap();
+    let mut gpus = GpuManager::new(GbmGlesBackend::with_context_priority(ContextPriority::High)).unwrap();
+    gpus.set_primary_node(Some(primary_gpu));
 
     let data = UdevData {
         dh: display_handle.clone(),
@@ -360,6 +359,40 @@ pub fn run_udev() {
         })
         .unwrap();
 
+    // many drivers lose the output state across suspend, restore it once we resume
+    match LogindSleepNotifier::new("anvil") {
+        Ok(sleep_notifier) => {
+            let handle = event_loop.handle();
+            event_loop
+                .handle()
+                .insert_source(sleep_notifier, move |event, &mut (), data| match event {
+                    SleepEvent::PrepareForSleep => info!("preparing for sleep"),
+                    SleepEvent::Resumed => {
+                        info!("resumed from sleep");
+
+                        for (node, backend) in data
+                            .backend_data
+                            .backends
+                            .iter_mut()
+                            .map(|(handle, backend)| (*handle, backend))
+                        {
+                            if let Err(err) = backend.drm.restore_state() {
+                                warn!("Failed to restore drm device state: {}", err);
+                            }
+                            for surface in backend.surfaces.values_mut() {
+                                if let Err(err) = surface.compositor.restore_state() {
+                                    warn!("Failed to restore drm surface state: {}", err);
+                                }
+                            }
+                            handle.insert_idle(move |data| data.render(node, None));
+                        }
+                    }
+                })
+                .unwrap();
+        }
+        Err(err) => warn!("Failed to listen for sleep events: {}", err),
+    }
+
     for (device_id, path) in udev_backend.device_list() {
         if let Err(err) = DrmNode::from_dev_id(device_id)
             .map_err(DeviceAddError::DrmNode)
@@ -494,6 +527,7 @@ pub fn run_udev() {
             state.running.store(false, Ordering::SeqCst);
         } else {
             state.space.refresh();
+            state.space.send_preferred_scales();
             state.popups.cleanup();
             display_handle.flush_clients().unwrap();
         }
@@ -630,6 +664,12 @@ impl SurfaceComposition {
         }
     }
 
+    fn use_tearing(&mut self, enabled: bool) {
+        if let SurfaceComposition::Compositor(c) = self {
+            c.use_tearing(enabled);
+        }
+    }
+
     fn reset_buffers(&mut self) {
         match self {
             SurfaceComposition::Compositor(c) => c.reset_buffers(),
@@ -647,6 +687,19 @@ impl SurfaceComposition {
         }
     }
 
+    fn restore_state(&mut self) -> Result<(), SwapBuffersError> {
+        match self {
+            SurfaceComposition::Compositor(c) => c.restore_state().map_err(Into::<SwapBuffersError>::into),
+            SurfaceComposition::Surface { surface, .. } => {
+                surface.reset_buffers();
+                surface
+                    .surface()
+                    .restore_state()
+                    .map_err(Into::<SwapBuffersError>::into)
+            }
+        }
+    }
+
     #[profiling::function]
     fn queue_frame(
         &mut self,
@@ -755,6 +808,7 @@ struct SurfaceData {
     render_node: DrmNode,
     global: Option<GlobalId>,
     compositor: SurfaceComposition,
+    frame_scheduler: FrameScheduler,
     #[cfg(feature = "debug")]
     fps: fps_ticker::Fps,
     #[cfg(feature = "debug")]
@@ -812,21 +866,6 @@ fn get_surface_dmabuf_feedback(
         .collect::<FormatSet>();
 
     let surface = composition.surface();
-    let planes = surface.planes().clone();
-
-    // We limit the scan-out tranche to formats we can also render from
-    // so that there is always a fallback render path available in case
-    // the supplied buffer can not be scanned out directly
-    let planes_formats = surface
-        .plane_info()
-        .formats
-        .iter()
-        .copied()
-        .chain(planes.overlay.into_iter().flat_map(|p| p.formats))
-        .collect::<FormatSet>()
-        .intersection(&all_render_formats)
-        .copied()
-        .collect::<FormatSet>();
 
     let builder = DmabufFeedbackBuilder::new(primary_gpu.dev_id(), primary_formats);
     let render_feedback = builder
@@ -835,12 +874,11 @@ fn get_surface_dmabuf_feedback(
         .build()
         .unwrap();
 
+    // We limit the scan-out tranche to formats we can also render from
+    // so that there is always a fallback render path available in case
+    // the supplied buffer can not be scanned out directly
     let scanout_feedback = builder
-        .add_preference_tranche(
-            surface.device_fd().dev_id().unwrap(),
-            Some(zwp_linux_dmabuf_feedback_v1::TrancheFlags::Scanout),
-            planes_formats,
-        )
+        .add_surface_scanout_tranche(surface, all_render_formats)
         .add_preference_tranche(render_node.dev_id(), None, render_formats)
         .build()
         .unwrap();
@@ -894,6 +932,7 @@ impl AnvilState<UdevData> {
             .as_mut()
             .add_node(render_node, gbm.clone())
             .map_err(DeviceAddError::AddNode)?;
+        self.backend_data.gpus.add_display_node(render_node);
 
         self.backend_data.backends.insert(
             node,
@@ -968,6 +1007,14 @@ impl AnvilState<UdevData> {
             .and_then(|info| info.model())
             .unwrap_or_else(|| "Unknown".into());
 
+        let serial_number = device
+            .drm
+            .connector_edid(connector.handle())
+            .ok()
+            .flatten()
+            .and_then(|edid| edid.serial())
+            .unwrap_or_else(|| "Unknown".into());
+
         if non_desktop {
             info!("Connector {} is non-desktop, setting up for leasing", output_name);
             device.non_desktop_connectors.push((connector.handle(), crtc));
@@ -1004,6 +1051,7 @@ impl AnvilState<UdevData> {
                     subpixel: connector.subpixel().into(),
                     make,
                     model,
+                    serial_number,
                 },
             );
             let global = output.create_global::<AnvilState<UdevData>>(&self.display_handle);
@@ -1110,6 +1158,7 @@ impl AnvilState<UdevData> {
                 render_node: device.render_node,
                 global: Some(global),
                 compositor,
+                frame_scheduler: FrameScheduler::from_refresh_rate(wl_mode.refresh),
                 #[cfg(feature = "debug")]
                 fps: fps_ticker::Fps::default(),
                 #[cfg(feature = "debug")]
@@ -1225,6 +1274,9 @@ impl AnvilState<UdevData> {
                 .gpus
                 .as_mut()
                 .remove_node(&backend_data.render_node);
+            self.backend_data
+                .gpus
+                .remove_display_node(&backend_data.render_node);
 
             self.handle.remove(backend_data.registration_token);
 
@@ -1272,11 +1324,15 @@ impl AnvilState<UdevData> {
             .map_err(Into::<SwapBuffersError>::into)
         {
             Ok(user_data) => {
+                let tp = metadata.as_ref().and_then(|metadata| match metadata.time {
+                    smithay::backend::drm::DrmEventTime::Monotonic(tp) => Some(tp),
+                    smithay::backend::drm::DrmEventTime::Realtime(_) => None,
+                });
+                surface
+                    .frame_scheduler
+                    .presented(tp.map(Into::into).unwrap_or_else(|| self.clock.now()));
+
                 if let Some(mut feedback) = user_data.flatten() {
-                    let tp = metadata.as_ref().and_then(|metadata| match metadata.time {
-                        smithay::backend::drm::DrmEventTime::Monotonic(tp) => Some(tp),
-                        smithay::backend::drm::DrmEventTime::Realtime(_) => None,
-                    });
                     let seq = metadata.as_ref().map(|metadata| metadata.sequence).unwrap_or(0);
 
                     let (clock, flags) = if let Some(tp) = tp {
@@ -1327,55 +1383,16 @@ impl AnvilState<UdevData> {
         };
 
         if schedule_render {
-            let output_refresh = match output.current_mode() {
-                Some(mode) => mode.refresh,
-                None => return,
-            };
-            // What are we trying to solve by introducing a delay here:
-            //
-            // Basically it is all about latency of client provided buffers.
-            // A client driven by frame callbacks will wait for a frame callback
-            // to repaint and submit a new buffer. As we send frame callbacks
-            // as part of the repaint in the compositor the latency would always
-            // be approx. 2 frames. By introducing a delay before we repaint in
-            // the compositor we can reduce the latency to approx. 1 frame + the
-            // remaining duration from the repaint to the next VBlank.
-            //
-            // With the delay it is also possible to further reduce latency if
-            // the client is driven by presentation feedback. As the presentation
-            // feedback is directly sent after a VBlank the client can submit a
-            // new buffer during the repaint delay that can hit the very next
-            // VBlank, thus reducing the potential latency to below one frame.
-            //
-            // Choosing a good delay is a topic on its own so we just implement
-            // a simple strategy here. We just split the duration between two
-            // VBlanks into two steps, one for the client repaint and one for the
-            // compositor repaint. Theoretically the repaint in the compositor should
-            // be faster so we give the client a bit more time to repaint. On a typical
-            // modern system the repaint in the compositor should not take more than 2ms
-            // so this should be safe for refresh rates up to at least 120 Hz. For 120 Hz
-            // this results in approx. 3.33ms time for repainting in the compositor.
-            // A too big delay could result in missing the next VBlank in the compositor.
-            //
-            // A more complete solution could work on a sliding window analyzing past repaints
-            // and do some prediction for the next repaint.
-            let repaint_delay =
-                Duration::from_millis(((1_000_000f32 / output_refresh as f32) * 0.6f32) as u64);
-
-            let timer = if self.backend_data.primary_gpu != surface.render_node {
-                // However, if we need to do a copy, that might not be enough.
-                // (And without actual comparision to previous frames we cannot really know.)
-                // So lets ignore that in those cases to avoid thrashing performance.
-                trace!("scheduling repaint timer immediately on {:?}", crtc);
-                Timer::immediate()
-            } else {
-                trace!(
-                    "scheduling repaint timer with delay {:?} on {:?}",
-                    repaint_delay,
-                    crtc
-                );
-                Timer::from_duration(repaint_delay)
-            };
+            // Repainting right after the VBlank would add a frame of latency for clients driven
+            // by frame callbacks or presentation feedback, as their new buffers would only be picked
+            // up by the next repaint. So we repaint as late as the past repaints of this surface allow.
+            let repaint_delay = surface.frame_scheduler.time_until_render(self.clock.now());
+            trace!(
+                "scheduling repaint timer with delay {:?} on {:?}",
+                repaint_delay,
+                crtc
+            );
+            let timer = Timer::from_duration(repaint_delay);
 
             self.handle
                 .insert_source(timer, move |_, _, data| {
@@ -1428,18 +1445,6 @@ impl AnvilState<UdevData> {
             .pointer_image
             .get_image(1 /*scale*/, self.clock.now().into());
 
-        let render_node = surface.render_node;
-        let primary_gpu = self.backend_data.primary_gpu;
-        let mut renderer = if primary_gpu == render_node {
-            self.backend_data.gpus.single_renderer(&render_node)
-        } else {
-            let format = surface.compositor.format();
-            self.backend_data
-                .gpus
-                .renderer(&primary_gpu, &render_node, format)
-        }
-        .unwrap();
-
         let pointer_images = &mut self.backend_data.pointer_images;
         let pointer_image = pointer_images
             .iter()
@@ -1476,6 +1481,27 @@ impl AnvilState<UdevData> {
             return;
         };
 
+        // let fullscreen clients opt into tearing
+        let tearing = output
+            .user_data()
+            .get::<FullscreenSurface>()
+            .and_then(|fullscreen| fullscreen.get())
+            .and_then(|window| {
+                window
+                    .wl_surface()
+                    .map(|surface| tearing_control::wants_tearing(&surface))
+            })
+            .unwrap_or(false);
+        surface.compositor.use_tearing(tearing);
+
+        let format = surface.compositor.format();
+        let mut renderer = self
+            .backend_data
+            .gpus
+            .output_renderer(&output, &surface.render_node, format)
+            .unwrap();
+
+        surface.frame_scheduler.render_started(self.clock.now());
         let result = render_surface(
             surface,
             &mut renderer,
@@ -1484,11 +1510,12 @@ impl AnvilState<UdevData> {
             self.pointer.current_location(),
             &pointer_image,
             &mut self.backend_data.pointer_element,
-            &self.dnd_icon,
+            dnd_icon(&self.seat),
             &mut self.cursor_status,
             &self.clock,
             self.show_window_preview,
         );
+        surface.frame_scheduler.render_finished(self.clock.now());
         let reschedule = match &result {
             Ok(has_rendered) => !has_rendered,
             Err(err) => {
@@ -1594,7 +1621,7 @@ fn render_surface<'a>(
     pointer_location: Point<f64, Logical>,
     pointer_image: &MemoryRenderBuffer,
     pointer_element: &mut PointerElement,
-    dnd_icon: &Option<DndIcon>,
+    dnd_icon: Option<DndIcon>,
     cursor_status: &mut CursorImageStatus,
     clock: &Clock<Monotonic>,
     show_window_preview: bool,
@@ -1649,21 +1676,8 @@ fn render_surface<'a>(
         );
 
         // draw the dnd icon if applicable
-        {
-            if let Some(icon) = dnd_icon.as_ref() {
-                let dnd_icon_pos = (cursor_pos + icon.offset.to_f64())
-                    .to_physical(scale)
-                    .to_i32_round();
-                if icon.surface.alive() {
-                    custom_elements.extend(AsRenderElements::<UdevRenderer<'a>>::render_elements(
-                        &SurfaceTree::from_surface(&icon.surface),
-                        renderer,
-                        dnd_icon_pos,
-                        scale,
-                        1.0,
-                    ));
-                }
-            }
+        if let Some(icon) = dnd_icon.as_ref() {
+            custom_elements.extend(icon.render_elements(renderer, cursor_pos, scale, 1.0));
         }
     }
 
@@ -1700,7 +1714,7 @@ fn render_surface<'a>(
     );
 
     if rendered {
-        let output_presentation_feedback = take_presentation_feedback(output, space, &states);
+        let output_presentation_feedback = space.take_presentation_feedback(output, &states);
         let damage = damage.cloned();
         surface
             .compositor
@@ -7,7 +7,10 @@ use std::{
 use smithay::backend::renderer::ImportEgl;
 #[cfg(feature = "debug")]
 use smithay::{
-    backend::{allocator::Fourcc, renderer::ImportMem},
+    backend::{
+        allocator::Fourcc,
+        renderer::{gles::GlesTexture, ImportMem},
+    },
     reexports::winit::raw_window_handle::{HasWindowHandle, RawWindowHandle},
 };
 
@@ -21,20 +24,25 @@ use smithay::{
             gles::GlesRenderer,
             ImportDma, ImportMemWl,
         },
-        winit::{self, WinitEvent, WinitGraphicsBackend},
+        winit::{self, WinitEvent, WinitGraphicsBackend, WinitWindowSurface},
         SwapBuffersError,
     },
     delegate_dmabuf,
+    desktop::Space,
     input::{
         keyboard::LedState,
         pointer::{CursorImageAttributes, CursorImageStatus},
     },
-    output::{Mode, Output, PhysicalProperties, Subpixel},
+    output::{Mode, Output, PhysicalProperties, Scale as OutputScale, Subpixel},
     reexports::{
         calloop::EventLoop,
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
-        wayland_server::{protocol::wl_surface, Display},
-        winit::platform::pump_events::PumpStatus,
+        wayland_server::{backend::GlobalId, protocol::wl_surface, Display, DisplayHandle},
+        winit::{
+            dpi::LogicalSize,
+            platform::pump_events::PumpStatus,
+            window::{Window as WinitWindow, WindowId},
+        },
     },
     utils::{IsAlive, Scale, Transform},
     wayland::{
@@ -42,24 +50,44 @@ use smithay::{
         dmabuf::{
             DmabufFeedback, DmabufFeedbackBuilder, DmabufGlobal, DmabufHandler, DmabufState, ImportNotifier,
         },
+        selection::data_device::dnd_icon,
     },
 };
 use tracing::{error, info, warn};
 
-use crate::state::{post_repaint, take_presentation_feedback, AnvilState, Backend};
-use crate::{drawing::*, render::*};
+use crate::state::{post_repaint, AnvilState, Backend};
+use crate::{drawing::*, render::*, shell::WindowElement};
 
 pub const OUTPUT_NAME: &str = "winit";
 
+/// A window of the winit backend acting as an output
+struct WinitOutput {
+    /// Additional window, `None` for the window of the graphics backend
+    surface: Option<WinitWindowSurface>,
+    window_id: WindowId,
+    output: Output,
+    global: GlobalId,
+    damage_tracker: OutputDamageTracker,
+    scale_factor: f64,
+    full_redraw: u8,
+}
+
 pub struct WinitData {
     backend: WinitGraphicsBackend<GlesRenderer>,
-    damage_tracker: OutputDamageTracker,
+    outputs: Vec<WinitOutput>,
     dmabuf_state: (DmabufState, DmabufGlobal, Option<DmabufFeedback>),
-    full_redraw: u8,
     #[cfg(feature = "debug")]
     pub fps: fps_ticker::Fps,
 }
 
+impl WinitData {
+    fn output_mut(&mut self, window_id: WindowId) -> Option<&mut WinitOutput> {
+        self.outputs
+            .iter_mut()
+            .find(|output| output.window_id == window_id)
+    }
+}
+
 impl DmabufHandler for AnvilState<WinitData> {
     fn dmabuf_state(&mut self) -> &mut DmabufState {
         &mut self.backend_data.dmabuf_state.0
@@ -85,8 +113,10 @@ impl Backend for WinitData {
     fn seat_name(&self) -> String {
         String::from("winit")
     }
-    fn reset_buffers(&mut self, _output: &Output) {
-        self.full_redraw = 4;
+    fn reset_buffers(&mut self, output: &Output) {
+        if let Some(winit_output) = self.outputs.iter_mut().find(|o| &o.output == output) {
+            winit_output.full_redraw = 4;
+        }
     }
     fn early_import(&mut self, _surface: &wl_surface::WlSurface) {}
     fn update_led_state(&mut self, _led_state: LedState) {}
@@ -105,24 +135,32 @@ pub fn run_winit() {
             return;
         }
     };
-    let size = backend.window_size();
 
-    let mode = Mode {
-        size,
-        refresh: 60_000,
-    };
-    let output = Output::new(
+    let window_count = std::env::var("ANVIL_WINIT_OUTPUTS")
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let mut outputs = vec![create_output(
+        &display.handle(),
+        backend.window(),
         OUTPUT_NAME.to_string(),
-        PhysicalProperties {
-            size: (0, 0).into(),
-            subpixel: Subpixel::Unknown,
-            make: "Smithay".into(),
-            model: "Winit".into(),
-        },
-    );
-    let _global = output.create_global::<AnvilState<WinitData>>(&display.handle());
-    output.change_current_state(Some(mode), Some(Transform::Flipped180), None, Some((0, 0).into()));
-    output.set_preferred(mode);
+    )];
+    for idx in 1..window_count {
+        let name = format!("{}-{}", OUTPUT_NAME, idx);
+        let attributes = WinitWindow::default_attributes()
+            .with_inner_size(LogicalSize::new(1280.0, 800.0))
+            .with_title(format!("Smithay ({})", name))
+            .with_visible(true);
+        match backend.create_window(&mut winit, attributes) {
+            Ok(surface) => {
+                let mut winit_output = create_output(&display.handle(), surface.window(), name);
+                winit_output.surface = Some(surface);
+                outputs.push(winit_output);
+            }
+            Err(err) => warn!("Failed to create additional window: {}", err),
+        }
+    }
 
     #[cfg(feature = "debug")]
     let fps_image =
@@ -185,23 +223,18 @@ pub fn run_winit() {
         info!("EGL hardware-acceleration enabled");
     };
 
-    let data = {
-        let damage_tracker = OutputDamageTracker::from_output(&output);
-
-        WinitData {
-            backend,
-            damage_tracker,
-            dmabuf_state,
-            full_redraw: 0,
-            #[cfg(feature = "debug")]
-            fps: fps_ticker::Fps::default(),
-        }
+    let data = WinitData {
+        backend,
+        outputs,
+        dmabuf_state,
+        #[cfg(feature = "debug")]
+        fps: fps_ticker::Fps::default(),
     };
     let mut state = AnvilState::init(display, event_loop.handle(), data, true);
     state
         .shm_state
         .update_formats(state.backend_data.backend.renderer().shm_formats());
-    state.space.map_output(&output, (0, 0));
+    arrange_outputs(&mut state.space, &state.backend_data.outputs);
 
     #[cfg(feature = "xwayland")]
     state.start_xwayland();
@@ -211,20 +244,57 @@ pub fn run_winit() {
     let mut pointer_element = PointerElement::default();
 
     while state.running.load(Ordering::SeqCst) {
-        let status = winit.dispatch_new_events(|event| match event {
-            WinitEvent::Resized { size, .. } => {
-                // We only have one output
-                let output = state.space.outputs().next().unwrap().clone();
-                state.space.map_output(&output, (0, 0));
+        let status = winit.dispatch_new_window_events(|window_id, event| match event {
+            WinitEvent::Resized { size, scale_factor } => {
+                let Some(winit_output) = state.backend_data.output_mut(window_id) else {
+                    return;
+                };
                 let mode = Mode {
                     size,
                     refresh: 60_000,
                 };
-                output.change_current_state(Some(mode), None, None, None);
+                // Keep scale changes done by the user unless the scale factor of the window changed
+                let scale = (scale_factor != winit_output.scale_factor)
+                    .then_some(OutputScale::Fractional(scale_factor));
+                winit_output.scale_factor = scale_factor;
+                let output = &winit_output.output;
+                output.change_current_state(Some(mode), None, scale, None);
                 output.set_preferred(mode);
+
+                arrange_outputs(&mut state.space, &state.backend_data.outputs);
+                crate::shell::fixup_positions(&mut state.space, state.pointer.current_location());
+            }
+            WinitEvent::CloseRequested => {
+                // The window of the graphics backend stays open, like before additional windows existed
+                let outputs = &mut state.backend_data.outputs;
+                let Some(idx) = outputs
+                    .iter()
+                    .position(|o| o.window_id == window_id && o.surface.is_some())
+                else {
+                    return;
+                };
+                let winit_output = outputs.remove(idx);
+                state.space.unmap_output(&winit_output.output);
+                state
+                    .display_handle
+                    .remove_global::<AnvilState<WinitData>>(winit_output.global);
+
+                arrange_outputs(&mut state.space, &state.backend_data.outputs);
                 crate::shell::fixup_positions(&mut state.space, state.pointer.current_location());
             }
-            WinitEvent::Input(event) => state.process_input_event_windowed(event, OUTPUT_NAME),
+            WinitEvent::Input(event) => {
+                // Events of unknown windows are routed to the first output
+                let outputs = &state.backend_data.outputs;
+                let Some(winit_output) = outputs
+                    .iter()
+                    .find(|o| o.window_id == window_id)
+                    .or(outputs.first())
+                else {
+                    return;
+                };
+                let output_name = winit_output.output.name();
+                state.process_input_event_windowed(event, &output_name)
+            }
             _ => (),
         });
 
@@ -235,9 +305,6 @@ pub fn run_winit() {
 
         // drawing logic
         {
-            let backend = &mut state.backend_data.backend;
-
-            // draw the cursor as relevant
             // reset the cursor if the surface is no longer alive
             let mut reset = false;
             if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
@@ -246,8 +313,6 @@ pub fn run_winit() {
             if reset {
                 state.cursor_status = CursorImageStatus::default_named();
             }
-            let cursor_visible = !matches!(state.cursor_status, CursorImageStatus::Surface(_));
-
             pointer_element.set_status(state.cursor_status.clone());
 
             #[cfg(feature = "debug")]
@@ -255,175 +320,14 @@ pub fn run_winit() {
             #[cfg(feature = "debug")]
             fps_element.update_fps(fps);
 
-            let full_redraw = &mut state.backend_data.full_redraw;
-            *full_redraw = full_redraw.saturating_sub(1);
-            let space = &mut state.space;
-            let damage_tracker = &mut state.backend_data.damage_tracker;
-            let show_window_preview = state.show_window_preview;
-
-            let dnd_icon = state.dnd_icon.as_ref();
-
-            let scale = Scale::from(output.current_scale().fractional_scale());
-            let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
-                compositor::with_states(surface, |states| {
-                    states
-                        .data_map
-                        .get::<Mutex<CursorImageAttributes>>()
-                        .unwrap()
-                        .lock()
-                        .unwrap()
-                        .hotspot
-                })
-            } else {
-                (0, 0).into()
-            };
-            let cursor_pos = state.pointer.current_location();
-
-            #[cfg(feature = "debug")]
-            let mut renderdoc = state.renderdoc.as_mut();
-            let render_res = backend.bind().and_then(|_| {
-                #[cfg(feature = "debug")]
-                if let Some(renderdoc) = renderdoc.as_mut() {
-                    renderdoc.start_frame_capture(
-                        backend.renderer().egl_context().get_context_handle(),
-                        backend
-                            .window()
-                            .window_handle()
-                            .map(|handle| {
-                                if let RawWindowHandle::Wayland(handle) = handle.as_raw() {
-                                    handle.surface.as_ptr()
-                                } else {
-                                    std::ptr::null_mut()
-                                }
-                            })
-                            .unwrap_or_else(|_| std::ptr::null_mut()),
-                    );
-                }
-                let age = if *full_redraw > 0 {
-                    0
-                } else {
-                    backend.buffer_age().unwrap_or(0)
-                };
-
-                let renderer = backend.renderer();
-
-                let mut elements = Vec::<CustomRenderElements<GlesRenderer>>::new();
-
-                elements.extend(
-                    pointer_element.render_elements(
-                        renderer,
-                        (cursor_pos - cursor_hotspot.to_f64())
-                            .to_physical(scale)
-                            .to_i32_round(),
-                        scale,
-                        1.0,
-                    ),
-                );
-
-                // draw the dnd icon if any
-                if let Some(icon) = dnd_icon {
-                    let dnd_icon_pos = (cursor_pos + icon.offset.to_f64())
-                        .to_physical(scale)
-                        .to_i32_round();
-                    if icon.surface.alive() {
-                        elements.extend(AsRenderElements::<GlesRenderer>::render_elements(
-                            &smithay::desktop::space::SurfaceTree::from_surface(&icon.surface),
-                            renderer,
-                            dnd_icon_pos,
-                            scale,
-                            1.0,
-                        ));
-                    }
-                }
-
-                #[cfg(feature = "debug")]
-                elements.push(CustomRenderElements::Fps(fps_element.clone()));
-
-                render_output(
-                    &output,
-                    space,
-                    elements,
-                    renderer,
-                    damage_tracker,
-                    age,
-                    show_window_preview,
-                )
-                .map_err(|err| match err {
-                    OutputDamageTrackerError::Rendering(err) => err.into(),
-                    _ => unreachable!(),
-                })
-            });
-
-            match render_res {
-                Ok(render_output_result) => {
-                    let has_rendered = render_output_result.damage.is_some();
-                    if let Some(damage) = render_output_result.damage {
-                        if let Err(err) = backend.submit(Some(damage)) {
-                            warn!("Failed to submit buffer: {}", err);
-                        }
-                    }
-
+            for idx in 0..state.backend_data.outputs.len() {
+                render(
+                    &mut state,
+                    idx,
+                    &mut pointer_element,
                     #[cfg(feature = "debug")]
-                    if let Some(renderdoc) = renderdoc.as_mut() {
-                        renderdoc.end_frame_capture(
-                            backend.renderer().egl_context().get_context_handle(),
-                            backend
-                                .window()
-                                .window_handle()
-                                .map(|handle| {
-                                    if let RawWindowHandle::Wayland(handle) = handle.as_raw() {
-                                        handle.surface.as_ptr()
-                                    } else {
-                                        std::ptr::null_mut()
-                                    }
-                                })
-                                .unwrap_or_else(|_| std::ptr::null_mut()),
-                        );
-                    }
-
-                    backend.window().set_cursor_visible(cursor_visible);
-
-                    // Send frame events so that client start drawing their next frame
-                    let time = state.clock.now();
-                    post_repaint(&output, &render_output_result.states, &state.space, None, time);
-
-                    if has_rendered {
-                        let mut output_presentation_feedback =
-                            take_presentation_feedback(&output, &state.space, &render_output_result.states);
-                        output_presentation_feedback.presented(
-                            time,
-                            output
-                                .current_mode()
-                                .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
-                                .unwrap_or_default(),
-                            0,
-                            wp_presentation_feedback::Kind::Vsync,
-                        )
-                    }
-                }
-                Err(SwapBuffersError::ContextLost(err)) => {
-                    #[cfg(feature = "debug")]
-                    if let Some(renderdoc) = renderdoc.as_mut() {
-                        renderdoc.discard_frame_capture(
-                            backend.renderer().egl_context().get_context_handle(),
-                            backend
-                                .window()
-                                .window_handle()
-                                .map(|handle| {
-                                    if let RawWindowHandle::Wayland(handle) = handle.as_raw() {
-                                        handle.surface.as_ptr()
-                                    } else {
-                                        std::ptr::null_mut()
-                                    }
-                                })
-                                .unwrap_or_else(|_| std::ptr::null_mut()),
-                        );
-                    }
-
-                    error!("Critical Rendering Error: {}", err);
-                    state.running.store(false, Ordering::SeqCst);
-                }
-                Err(err) => warn!("Rendering error: {}", err),
+                    &fps_element,
+                );
             }
         }
 
@@ -432,6 +336,7 @@ pub fn run_winit() {
             state.running.store(false, Ordering::SeqCst);
         } else {
             state.space.refresh();
+            state.space.send_preferred_scales();
             state.popups.cleanup();
             display_handle.flush_clients().unwrap();
         }
@@ -440,3 +345,229 @@ pub fn run_winit() {
         state.backend_data.fps.tick();
     }
 }
+
+fn create_output(display_handle: &DisplayHandle, window: &WinitWindow, name: String) -> WinitOutput {
+    let (w, h): (i32, i32) = window.inner_size().into();
+    let mode = Mode {
+        size: (w, h).into(),
+        refresh: 60_000,
+    };
+    let scale_factor = window.scale_factor();
+    let output = Output::new(
+        name,
+        PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "Smithay".into(),
+            model: "Winit".into(),
+            serial_number: "Unknown".into(),
+        },
+    );
+    let global = output.create_global::<AnvilState<WinitData>>(display_handle);
+    output.change_current_state(
+        Some(mode),
+        Some(Transform::Flipped180),
+        Some(OutputScale::Fractional(scale_factor)),
+        None,
+    );
+    output.set_preferred(mode);
+    let damage_tracker = OutputDamageTracker::from_output(&output);
+
+    WinitOutput {
+        surface: None,
+        window_id: window.id(),
+        output,
+        global,
+        damage_tracker,
+        scale_factor,
+        full_redraw: 0,
+    }
+}
+
+/// Places the outputs next to each other, from left to right
+fn arrange_outputs(space: &mut Space<WindowElement>, outputs: &[WinitOutput]) {
+    let mut x = 0;
+    for winit_output in outputs {
+        winit_output
+            .output
+            .change_current_state(None, None, None, Some((x, 0).into()));
+        space.map_output(&winit_output.output, (x, 0));
+        x += space.output_geometry(&winit_output.output).unwrap().size.w;
+    }
+}
+
+#[cfg(feature = "debug")]
+fn window_surface_ptr(window: &WinitWindow) -> *mut std::ffi::c_void {
+    window
+        .window_handle()
+        .map(|handle| {
+            if let RawWindowHandle::Wayland(handle) = handle.as_raw() {
+                handle.surface.as_ptr()
+            } else {
+                std::ptr::null_mut()
+            }
+        })
+        .unwrap_or_else(|_| std::ptr::null_mut())
+}
+
+fn render(
+    state: &mut AnvilState<WinitData>,
+    idx: usize,
+    pointer_element: &mut PointerElement,
+    #[cfg(feature = "debug")] fps_element: &FpsElement<GlesTexture>,
+) {
+    let cursor_visible = !matches!(state.cursor_status, CursorImageStatus::Surface(_));
+
+    let WinitData { backend, outputs, .. } = &mut state.backend_data;
+    let winit_output = &mut outputs[idx];
+    let output = winit_output.output.clone();
+    winit_output.full_redraw = winit_output.full_redraw.saturating_sub(1);
+    let full_redraw = winit_output.full_redraw;
+    let space = &mut state.space;
+    let show_window_preview = state.show_window_preview;
+
+    let dnd_icon = dnd_icon(&state.seat);
+
+    let output_geometry = space.output_geometry(&output).unwrap();
+    let pointer_location = state.pointer.current_location();
+    let pointer_visible = output_geometry.to_f64().contains(pointer_location);
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
+        compositor::with_states(surface, |states| {
+            states
+                .data_map
+                .get::<Mutex<CursorImageAttributes>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .hotspot
+        })
+    } else {
+        (0, 0).into()
+    };
+    let cursor_pos = pointer_location - output_geometry.loc.to_f64();
+
+    #[cfg(feature = "debug")]
+    let mut renderdoc = state.renderdoc.as_mut();
+    #[cfg(feature = "debug")]
+    let window_ptr = window_surface_ptr(match winit_output.surface.as_ref() {
+        Some(surface) => surface.window(),
+        None => backend.window(),
+    });
+    let render_res = match winit_output.surface.as_mut() {
+        Some(surface) => surface.bind(backend.renderer()),
+        None => backend.bind(),
+    };
+    let render_res = render_res.map(|_| {
+        #[cfg(feature = "debug")]
+        if let Some(renderdoc) = renderdoc.as_mut() {
+            renderdoc.start_frame_capture(backend.renderer().egl_context().get_context_handle(), window_ptr);
+        }
+        if full_redraw > 0 {
+            0
+        } else {
+            match winit_output.surface.as_ref() {
+                Some(surface) => surface.buffer_age(),
+                None => backend.buffer_age(),
+            }
+            .unwrap_or(0)
+        }
+    });
+    let render_res = render_res.and_then(|age| {
+        let renderer = backend.renderer();
+
+        let mut elements = Vec::<CustomRenderElements<GlesRenderer>>::new();
+
+        if pointer_visible {
+            elements.extend(
+                pointer_element.render_elements(
+                    renderer,
+                    (cursor_pos - cursor_hotspot.to_f64())
+                        .to_physical(scale)
+                        .to_i32_round(),
+                    scale,
+                    1.0,
+                ),
+            );
+
+            // draw the dnd icon if any
+            if let Some(icon) = dnd_icon.as_ref() {
+                elements.extend(icon.render_elements(renderer, cursor_pos, scale, 1.0));
+            }
+        }
+
+        #[cfg(feature = "debug")]
+        elements.push(CustomRenderElements::Fps(fps_element.clone()));
+
+        render_output(
+            &output,
+            space,
+            elements,
+            renderer,
+            &mut winit_output.damage_tracker,
+            age,
+            show_window_preview,
+        )
+        .map_err(|err| match err {
+            OutputDamageTrackerError::Rendering(err) => err.into(),
+            _ => unreachable!(),
+        })
+    });
+
+    match render_res {
+        Ok(render_output_result) => {
+            let has_rendered = render_output_result.damage.is_some();
+            if let Some(damage) = render_output_result.damage {
+                let res = match winit_output.surface.as_mut() {
+                    Some(surface) => surface.submit(Some(damage)),
+                    None => backend.submit(Some(damage)),
+                };
+                if let Err(err) = res {
+                    warn!("Failed to submit buffer: {}", err);
+                }
+            }
+
+            #[cfg(feature = "debug")]
+            if let Some(renderdoc) = renderdoc.as_mut() {
+                renderdoc
+                    .end_frame_capture(backend.renderer().egl_context().get_context_handle(), window_ptr);
+            }
+
+            match winit_output.surface.as_ref() {
+                Some(surface) => surface.window(),
+                None => backend.window(),
+            }
+            .set_cursor_visible(cursor_visible);
+
+            // Send frame events so that client start drawing their next frame
+            let time = state.clock.now();
+            post_repaint(&output, &render_output_result.states, &state.space, None, time);
+
+            if has_rendered {
+                let mut output_presentation_feedback = state
+                    .space
+                    .take_presentation_feedback(&output, &render_output_result.states);
+                output_presentation_feedback.presented(
+                    time,
+                    output
+                        .current_mode()
+                        .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
+                        .unwrap_or_default(),
+                    0,
+                    wp_presentation_feedback::Kind::Vsync,
+                )
+            }
+        }
+        Err(SwapBuffersError::ContextLost(err)) => {
+            #[cfg(feature = "debug")]
+            if let Some(renderdoc) = renderdoc.as_mut() {
+                renderdoc
+                    .discard_frame_capture(backend.renderer().egl_context().get_context_handle(), window_ptr);
+            }
+
+            error!("Critical Rendering Error: {}", err);
+            state.running.store(false, Ordering::SeqCst);
+        }
+        Err(err) => warn!("Rendering error: {}", err),
+    }
+}
@@ -6,12 +6,16 @@ use std::{
 use crate::{
     drawing::*,
     render::*,
-    state::{post_repaint, take_presentation_feedback, AnvilState, Backend},
+    shell::WindowElement,
+    state::{post_repaint, AnvilState, Backend},
 };
 #[cfg(feature = "egl")]
 use smithay::backend::renderer::ImportEgl;
 #[cfg(feature = "debug")]
-use smithay::backend::{allocator::Fourcc, renderer::ImportMem};
+use smithay::backend::{
+    allocator::Fourcc,
+    renderer::{gles::GlesTexture, ImportMem},
+};
 
 use smithay::{
     backend::{
@@ -26,9 +30,10 @@ use smithay::{
             ImportMemWl,
         },
         vulkan::{version::Version, Instance, PhysicalDevice},
-        x11::{WindowBuilder, X11Backend, X11Event, X11Surface},
+        x11::{Window, WindowBuilder, X11Backend, X11Event, X11Surface},
     },
     delegate_dmabuf,
+    desktop::{utils::OutputPresentationFeedback, Space},
     input::{
         keyboard::LedState,
         pointer::{CursorImageAttributes, CursorImageStatus},
@@ -39,7 +44,7 @@ use smithay::{
         calloop::EventLoop,
         gbm,
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
-        wayland_server::{protocol::wl_surface, Display},
+        wayland_server::{backend::GlobalId, protocol::wl_surface, Display},
     },
     utils::{DeviceFd, IsAlive, Scale},
     wayland::{
@@ -47,26 +52,44 @@ use smithay::{
         dmabuf::{
             DmabufFeedback, DmabufFeedbackBuilder, DmabufGlobal, DmabufHandler, DmabufState, ImportNotifier,
         },
+        selection::data_device::dnd_icon,
     },
 };
 use tracing::{error, info, trace, warn};
 
 pub const OUTPUT_NAME: &str = "x11";
 
+/// A window of the X11 backend acting as an output
 #[derive(Debug)]
-pub struct X11Data {
+struct X11Output {
     render: bool,
-    mode: Mode,
+    window: Window,
+    surface: X11Surface,
+    output: Output,
+    global: GlobalId,
+    damage_tracker: OutputDamageTracker,
+    pending_feedback: Option<OutputPresentationFeedback>,
+    #[cfg(feature = "debug")]
+    fps: fps_ticker::Fps,
+}
+
+#[derive(Debug)]
+pub struct X11Data {
     // FIXME: If GlesRenderer is dropped before X11Surface, then the MakeCurrent call inside Gles2Renderer will
     // fail because the X11Surface is keeping gbm alive.
     renderer: GlesRenderer,
-    damage_tracker: OutputDamageTracker,
-    surface: X11Surface,
+    outputs: Vec<X11Output>,
     dmabuf_state: DmabufState,
     _dmabuf_global: DmabufGlobal,
     _dmabuf_default_feedback: DmabufFeedback,
-    #[cfg(feature = "debug")]
-    fps: fps_ticker::Fps,
+}
+
+impl X11Data {
+    fn output_mut(&mut self, window_id: u32) -> Option<&mut X11Output> {
+        self.outputs
+            .iter_mut()
+            .find(|output| output.window.id() == window_id)
+    }
 }
 
 impl DmabufHandler for AnvilState<X11Data> {
@@ -88,8 +111,10 @@ impl Backend for X11Data {
     fn seat_name(&self) -> String {
         "x11".to_owned()
     }
-    fn reset_buffers(&mut self, _output: &Output) {
-        self.surface.reset_buffers();
+    fn reset_buffers(&mut self, output: &Output) {
+        if let Some(x11_output) = self.outputs.iter_mut().find(|o| &o.output == output) {
+            x11_output.surface.reset_buffers();
+        }
     }
     fn early_import(&mut self, _surface: &wl_surface::WlSurface) {}
     fn update_led_state(&mut self, _led_state: LedState) {}
@@ -115,10 +140,20 @@ pub fn run_x11() {
     // Create the OpenGL context
     let context = EGLContext::new(&egl).expect("Failed to create EGLContext");
 
-    let window = WindowBuilder::new()
-        .title("Anvil")
-        .build(&handle)
-        .expect("Failed to create first window");
+    // Every window acts as an independent output
+    let window_count = std::env::var("ANVIL_X11_OUTPUTS")
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let windows = (0..window_count)
+        .map(|idx| {
+            WindowBuilder::new()
+                .title(&format!("Anvil ({})", output_name(idx)))
+                .build(&handle)
+                .expect("Failed to create window")
+        })
+        .collect::<Vec<_>>();
 
     let skip_vulkan = std::env::var("ANVIL_NO_VULKAN")
         .map(|x| {
@@ -126,7 +161,7 @@ pub fn run_x11() {
         })
         .unwrap_or(false);
 
-    let vulkan_allocator = if !skip_vulkan {
+    let vulkan_device = if !skip_vulkan {
         Instance::new(Version::VERSION_1_2, None)
             .ok()
             .and_then(|instance| {
@@ -139,40 +174,42 @@ pub fn run_x11() {
                         })
                 })
             })
-            .and_then(|physical_device| {
-                VulkanAllocator::new(
-                    &physical_device,
-                    ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED,
-                )
-                .ok()
-            })
     } else {
         None
     };
 
-    let surface = match vulkan_allocator {
-        // Create the surface for the window.
-        Some(vulkan_allocator) => handle
-            .create_surface(
-                &window,
-                DmabufAllocator(vulkan_allocator),
-                context
-                    .dmabuf_render_formats()
-                    .iter()
-                    .map(|format| format.modifier),
-            )
-            .expect("Failed to create X11 surface"),
-        None => handle
-            .create_surface(
-                &window,
-                DmabufAllocator(GbmAllocator::new(device, GbmBufferFlags::RENDERING)),
-                context
-                    .dmabuf_render_formats()
-                    .iter()
-                    .map(|format| format.modifier),
-            )
-            .expect("Failed to create X11 surface"),
-    };
+    let modifiers = context
+        .dmabuf_render_formats()
+        .iter()
+        .map(|format| format.modifier)
+        .collect::<Vec<_>>();
+    let surfaces = windows
+        .iter()
+        .map(|window| {
+            let vulkan_allocator = vulkan_device.as_ref().and_then(|physical_device| {
+                VulkanAllocator::new(
+                    physical_device,
+                    ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::SAMPLED,
+                )
+                .ok()
+            });
+
+            match vulkan_allocator {
+                // Create the surface for the window.
+                Some(vulkan_allocator) => handle.create_surface(
+                    window,
+                    DmabufAllocator(vulkan_allocator),
+                    modifiers.iter().copied(),
+                ),
+                None => handle.create_surface(
+                    window,
+                    DmabufAllocator(GbmAllocator::new(device.clone(), GbmBufferFlags::RENDERING)),
+                    modifiers.iter().copied(),
+                ),
+            }
+            .expect("Failed to create X11 surface")
+        })
+        .collect::<Vec<_>>();
 
     #[cfg_attr(not(feature = "egl"), allow(unused_mut))]
     let mut renderer = unsafe { GlesRenderer::new(context) }.expect("Failed to initialize renderer");
@@ -192,17 +229,6 @@ pub fn run_x11() {
         &dmabuf_default_feedback,
     );
 
-    let size = {
-        let s = window.size();
-
-        (s.w as i32, s.h as i32).into()
-    };
-
-    let mode = Mode {
-        size,
-        refresh: 60_000,
-    };
-
     #[cfg(feature = "debug")]
     let fps_image =
         image::ImageReader::with_format(std::io::Cursor::new(FPS_NUMBERS_PNG), image::ImageFormat::Png)
@@ -219,66 +245,133 @@ pub fn run_x11() {
         .expect("Unable to upload FPS texture");
     #[cfg(feature = "debug")]
     let mut fps_element = FpsElement::new(fps_texture);
-    let output = Output::new(
-        OUTPUT_NAME.to_string(),
-        PhysicalProperties {
-            size: (0, 0).into(),
-            subpixel: Subpixel::Unknown,
-            make: "Smithay".into(),
-            model: "X11".into(),
-        },
-    );
-    let _global = output.create_global::<AnvilState<X11Data>>(&display.handle());
-    output.change_current_state(Some(mode), None, None, Some((0, 0).into()));
-    output.set_preferred(mode);
 
-    let damage_tracker = OutputDamageTracker::from_output(&output);
+    let outputs = windows
+        .into_iter()
+        .zip(surfaces)
+        .enumerate()
+        .map(|(idx, (window, surface))| {
+            let mode = window_mode(&window);
+            let output = Output::new(
+                output_name(idx),
+                PhysicalProperties {
+                    size: (0, 0).into(),
+                    subpixel: Subpixel::Unknown,
+                    make: "Smithay".into(),
+                    model: "X11".into(),
+                    serial_number: "Unknown".into(),
+                },
+            );
+            let global = output.create_global::<AnvilState<X11Data>>(&display.handle());
+            output.change_current_state(Some(mode), None, None, None);
+            output.set_preferred(mode);
+
+            X11Output {
+                render: true,
+                damage_tracker: OutputDamageTracker::from_output(&output),
+                window,
+                surface,
+                output,
+                global,
+                pending_feedback: None,
+                #[cfg(feature = "debug")]
+                fps: fps_ticker::Fps::default(),
+            }
+        })
+        .collect();
 
     let data = X11Data {
-        render: true,
-        mode,
-        surface,
         renderer,
-        damage_tracker,
+        outputs,
         dmabuf_state,
         _dmabuf_global: dmabuf_global,
         _dmabuf_default_feedback: dmabuf_default_feedback,
-        #[cfg(feature = "debug")]
-        fps: fps_ticker::Fps::default(),
     };
 
     let mut state = AnvilState::init(display, event_loop.handle(), data, true);
     state
         .shm_state
         .update_formats(state.backend_data.renderer.shm_formats());
-    state.space.map_output(&output, (0, 0));
+    arrange_outputs(&mut state.space, &state.backend_data.outputs);
 
-    let output_clone = output.clone();
     event_loop
         .handle()
         .insert_source(backend, move |event, _, data| match event {
-            X11Event::CloseRequested { .. } => {
-                data.running.store(false, Ordering::SeqCst);
-            }
-            X11Event::Resized { new_size, .. } => {
-                let output = &output_clone;
-                let size = { (new_size.w as i32, new_size.h as i32).into() };
+            X11Event::CloseRequested { window_id } => {
+                let backend_data = &mut data.backend_data;
+                if let Some(idx) = backend_data
+                    .outputs
+                    .iter()
+                    .position(|o| o.window.id() == window_id)
+                {
+                    let x11_output = backend_data.outputs.remove(idx);
+                    data.space.unmap_output(&x11_output.output);
+                    data.display_handle
+                        .remove_global::<AnvilState<X11Data>>(x11_output.global);
+                }
 
-                data.backend_data.mode = Mode {
-                    size,
-                    refresh: 60_000,
+                if data.backend_data.outputs.is_empty() {
+                    data.running.store(false, Ordering::SeqCst);
+                } else {
+                    arrange_outputs(&mut data.space, &data.backend_data.outputs);
+                    crate::shell::fixup_positions(&mut data.space, data.pointer.current_location());
+                }
+            }
+            X11Event::Resized { window_id, .. } => {
+                let Some(x11_output) = data.backend_data.output_mut(window_id) else {
+                    return;
                 };
+                let output = &x11_output.output;
+                let mode = window_mode(&x11_output.window);
                 output.delete_mode(output.current_mode().unwrap());
-                output.change_current_state(Some(data.backend_data.mode), None, None, None);
-                output.set_preferred(data.backend_data.mode);
-                crate::shell::fixup_positions(&mut data.space, data.pointer.current_location());
+                output.change_current_state(Some(mode), None, None, None);
+                output.set_preferred(mode);
+                x11_output.render = true;
 
-                data.backend_data.render = true;
+                arrange_outputs(&mut data.space, &data.backend_data.outputs);
+                crate::shell::fixup_positions(&mut data.space, data.pointer.current_location());
+            }
+            X11Event::PresentCompleted {
+                window_id,
+                time,
+                sequence,
+            } => {
+                if let Some(x11_output) = data.backend_data.output_mut(window_id) {
+                    x11_output.render = true;
+                    if let Some(mut feedback) = x11_output.pending_feedback.take() {
+                        let refresh = x11_output
+                            .output
+                            .current_mode()
+                            .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
+                            .unwrap_or_default();
+                        feedback.presented(
+                            time,
+                            refresh,
+                            sequence,
+                            wp_presentation_feedback::Kind::Vsync
+                                | wp_presentation_feedback::Kind::HwClock
+                                | wp_presentation_feedback::Kind::HwCompletion,
+                        );
+                    }
+                }
             }
-            X11Event::PresentCompleted { .. } | X11Event::Refresh { .. } => {
-                data.backend_data.render = true;
+            X11Event::Refresh { window_id } => {
+                if let Some(x11_output) = data.backend_data.output_mut(window_id) {
+                    x11_output.render = true;
+                }
+            }
+            X11Event::Input { event, window_id } => {
+                // Events not targeting a specific window are routed to the first output
+                let outputs = &data.backend_data.outputs;
+                let Some(x11_output) = window_id
+                    .and_then(|id| outputs.iter().find(|o| o.window.id() == id))
+                    .or(outputs.first())
+                else {
+                    return;
+                };
+                let output_name = x11_output.output.name();
+                data.process_input_event_windowed(event, &output_name)
             }
-            X11Event::Input { event, .. } => data.process_input_event_windowed(event, OUTPUT_NAME),
             X11Event::Focus { focused: false, .. } => {
                 data.release_all_keys();
             }
@@ -294,175 +387,220 @@ pub fn run_x11() {
     let mut pointer_element = PointerElement::default();
 
     while state.running.load(Ordering::SeqCst) {
-        if state.backend_data.render {
-            profiling::scope!("render_frame");
+        for idx in 0..state.backend_data.outputs.len() {
+            if state.backend_data.outputs[idx].render {
+                render(
+                    &mut state,
+                    idx,
+                    &mut pointer_element,
+                    #[cfg(feature = "debug")]
+                    &mut fps_element,
+                );
+            }
+        }
 
-            let backend_data = &mut state.backend_data;
-            // We need to borrow everything we want to refer to inside the renderer callback otherwise rustc is unhappy.
-            #[cfg(feature = "debug")]
-            let fps = backend_data.fps.avg().round() as u32;
-            #[cfg(feature = "debug")]
-            fps_element.update_fps(fps);
+        let result = event_loop.dispatch(Some(Duration::from_millis(16)), &mut state);
+        if result.is_err() {
+            state.running.store(false, Ordering::SeqCst);
+        } else {
+            state.space.refresh();
+            state.space.send_preferred_scales();
+            state.popups.cleanup();
+            display_handle.flush_clients().unwrap();
+        }
+    }
+}
 
-            let (buffer, age) = backend_data.surface.buffer().expect("gbm device was destroyed");
-            if let Err(err) = backend_data.renderer.bind(buffer) {
-                error!("Error while binding buffer: {}", err);
-                profiling::finish_frame!();
-                continue;
-            }
+fn output_name(idx: usize) -> String {
+    if idx == 0 {
+        OUTPUT_NAME.to_string()
+    } else {
+        format!("{}-{}", OUTPUT_NAME, idx)
+    }
+}
 
-            #[cfg(feature = "debug")]
-            if let Some(renderdoc) = state.renderdoc.as_mut() {
-                renderdoc.start_frame_capture(
-                    backend_data.renderer.egl_context().get_context_handle(),
-                    std::ptr::null(),
-                );
-            }
+fn window_mode(window: &Window) -> Mode {
+    let size = window.size();
+    Mode {
+        size: (size.w as i32, size.h as i32).into(),
+        refresh: 60_000,
+    }
+}
 
-            let mut elements: Vec<CustomRenderElements<GlesRenderer>> = Vec::new();
+/// Places the outputs next to each other, from left to right
+fn arrange_outputs(space: &mut Space<WindowElement>, outputs: &[X11Output]) {
+    let mut x = 0;
+    for x11_output in outputs {
+        x11_output
+            .output
+            .change_current_state(None, None, None, Some((x, 0).into()));
+        space.map_output(&x11_output.output, (x, 0));
+        x += space.output_geometry(&x11_output.output).unwrap().size.w;
+    }
+}
 
-            // draw the cursor as relevant
-            // reset the cursor if the surface is no longer alive
-            let mut reset = false;
-            if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
-                reset = !surface.alive();
-            }
-            if reset {
-                state.cursor_status = CursorImageStatus::default_named();
-            }
-            let cursor_visible = !matches!(state.cursor_status, CursorImageStatus::Surface(_));
-
-            let scale = Scale::from(output.current_scale().fractional_scale());
-            let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
-                compositor::with_states(surface, |states| {
-                    states
-                        .data_map
-                        .get::<Mutex<CursorImageAttributes>>()
-                        .unwrap()
-                        .lock()
-                        .unwrap()
-                        .hotspot
-                })
-            } else {
-                (0, 0).into()
-            };
-            let cursor_pos = state.pointer.current_location();
-
-            pointer_element.set_status(state.cursor_status.clone());
-            elements.extend(
-                pointer_element.render_elements(
-                    &mut backend_data.renderer,
-                    (cursor_pos - cursor_hotspot.to_f64())
-                        .to_physical(scale)
-                        .to_i32_round(),
-                    scale,
-                    1.0,
-                ),
-            );
+fn render(
+    state: &mut AnvilState<X11Data>,
+    idx: usize,
+    pointer_element: &mut PointerElement,
+    #[cfg(feature = "debug")] fps_element: &mut FpsElement<GlesTexture>,
+) {
+    profiling::scope!("render_frame");
+
+    let backend_data = &mut state.backend_data;
+    let x11_output = &mut backend_data.outputs[idx];
+    let output = x11_output.output.clone();
+    // We need to borrow everything we want to refer to inside the renderer callback otherwise rustc is unhappy.
+    #[cfg(feature = "debug")]
+    let fps = x11_output.fps.avg().round() as u32;
+    #[cfg(feature = "debug")]
+    fps_element.update_fps(fps);
 
-            // draw the dnd icon if any
-            if let Some(icon) = state.dnd_icon.as_ref() {
-                let dnd_icon_pos = (cursor_pos + icon.offset.to_f64())
-                    .to_physical(scale)
-                    .to_i32_round();
-                if icon.surface.alive() {
-                    elements.extend(AsRenderElements::<GlesRenderer>::render_elements(
-                        &smithay::desktop::space::SurfaceTree::from_surface(&icon.surface),
-                        &mut backend_data.renderer,
-                        dnd_icon_pos,
-                        scale,
-                        1.0,
-                    ));
-                }
-            }
+    let (buffer, age) = x11_output.surface.buffer().expect("gbm device was destroyed");
+    if let Err(err) = backend_data.renderer.bind(buffer) {
+        error!("Error while binding buffer: {}", err);
+        profiling::finish_frame!();
+        return;
+    }
 
-            #[cfg(feature = "debug")]
-            elements.push(CustomRenderElements::Fps(fps_element.clone()));
+    #[cfg(feature = "debug")]
+    if let Some(renderdoc) = state.renderdoc.as_mut() {
+        renderdoc.start_frame_capture(
+            backend_data.renderer.egl_context().get_context_handle(),
+            std::ptr::null(),
+        );
+    }
+
+    let mut elements: Vec<CustomRenderElements<GlesRenderer>> = Vec::new();
+
+    // draw the cursor as relevant
+    // reset the cursor if the surface is no longer alive
+    let mut reset = false;
+    if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
+        reset = !surface.alive();
+    }
+    if reset {
+        state.cursor_status = CursorImageStatus::default_named();
+    }
+    let cursor_visible = !matches!(state.cursor_status, CursorImageStatus::Surface(_));
+
+    let output_geometry = state.space.output_geometry(&output).unwrap();
+    let pointer_location = state.pointer.current_location();
+    if output_geometry.to_f64().contains(pointer_location) {
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let cursor_hotspot = if let CursorImageStatus::Surface(ref surface) = state.cursor_status {
+            compositor::with_states(surface, |states| {
+                states
+                    .data_map
+                    .get::<Mutex<CursorImageAttributes>>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .hotspot
+            })
+        } else {
+            (0, 0).into()
+        };
+        let cursor_pos = pointer_location - output_geometry.loc.to_f64();
 
-            let render_res = render_output(
-                &output,
-                &state.space,
-                elements,
+        pointer_element.set_status(state.cursor_status.clone());
+        elements.extend(
+            pointer_element.render_elements(
                 &mut backend_data.renderer,
-                &mut backend_data.damage_tracker,
-                age.into(),
-                state.show_window_preview,
-            );
+                (cursor_pos - cursor_hotspot.to_f64())
+                    .to_physical(scale)
+                    .to_i32_round(),
+                scale,
+                1.0,
+            ),
+        );
+
+        // draw the dnd icon if any
+        if let Some(icon) = dnd_icon(&state.seat) {
+            elements.extend(icon.render_elements(&mut backend_data.renderer, cursor_pos, scale, 1.0));
+        }
+    }
 
-            match render_res {
-                Ok(render_output_result) => {
-                    trace!("Finished rendering");
-                    let submitted = if let Err(err) = backend_data.surface.submit() {
-                        backend_data.surface.reset_buffers();
-                        warn!("Failed to submit buffer: {}. Retrying", err);
-                        false
-                    } else {
-                        true
-                    };
-
-                    // Send frame events so that client start drawing their next frame
-                    let time = state.clock.now();
-                    post_repaint(&output, &render_output_result.states, &state.space, None, time);
-
-                    if render_output_result.damage.is_some() {
-                        let mut output_presentation_feedback =
-                            take_presentation_feedback(&output, &state.space, &render_output_result.states);
-                        output_presentation_feedback.presented(
-                            time,
-                            output
-                                .current_mode()
-                                .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
-                                .unwrap_or_default(),
-                            0,
-                            wp_presentation_feedback::Kind::Vsync,
-                        )
-                    }
+    #[cfg(feature = "debug")]
+    elements.push(CustomRenderElements::Fps(fps_element.clone()));
+
+    let render_res = render_output(
+        &output,
+        &state.space,
+        elements,
+        &mut backend_data.renderer,
+        &mut x11_output.damage_tracker,
+        age.into(),
+        state.show_window_preview,
+    );
 
-                    #[cfg(feature = "debug")]
-                    if render_output_result.damage.is_some() {
-                        if let Some(renderdoc) = state.renderdoc.as_mut() {
-                            renderdoc.end_frame_capture(
-                                state.backend_data.renderer.egl_context().get_context_handle(),
-                                std::ptr::null(),
-                            );
-                        }
-                    } else if let Some(renderdoc) = state.renderdoc.as_mut() {
-                        renderdoc.discard_frame_capture(
-                            state.backend_data.renderer.egl_context().get_context_handle(),
-                            std::ptr::null(),
-                        );
-                    }
+    match render_res {
+        Ok(render_output_result) => {
+            trace!("Finished rendering");
+            // Only the damaged regions need to be presented, the window keeps the rest
+            let damage = render_output_result.damage.map(Vec::as_slice).unwrap_or_default();
+            let submitted = if let Err(err) = x11_output.surface.submit_with_damage(damage) {
+                x11_output.surface.reset_buffers();
+                warn!("Failed to submit buffer: {}. Retrying", err);
+                false
+            } else {
+                true
+            };
 
-                    state.backend_data.render = !submitted;
-                }
-                Err(err) => {
-                    #[cfg(feature = "debug")]
-                    if let Some(renderdoc) = state.renderdoc.as_mut() {
-                        renderdoc.discard_frame_capture(
-                            backend_data.renderer.egl_context().get_context_handle(),
-                            std::ptr::null(),
-                        );
+            // Send frame events so that client start drawing their next frame
+            let time = state.clock.now();
+            post_repaint(&output, &render_output_result.states, &state.space, None, time);
+
+            if render_output_result.damage.is_some() {
+                let mut output_presentation_feedback = state
+                    .space
+                    .take_presentation_feedback(&output, &render_output_result.states);
+                if submitted {
+                    // Sent once the X server reports the presentation
+                    if let Some(mut previous) =
+                        x11_output.pending_feedback.replace(output_presentation_feedback)
+                    {
+                        previous.discarded();
                     }
-
-                    backend_data.surface.reset_buffers();
-                    error!("Rendering error: {}", err);
-                    // TODO: convert RenderError into SwapBuffersError and skip temporary (will retry) and panic on ContextLost or recreate
+                } else {
+                    output_presentation_feedback.discarded();
                 }
             }
 
             #[cfg(feature = "debug")]
-            state.backend_data.fps.tick();
-            window.set_cursor_visible(cursor_visible);
-            profiling::finish_frame!();
+            if render_output_result.damage.is_some() {
+                if let Some(renderdoc) = state.renderdoc.as_mut() {
+                    renderdoc.end_frame_capture(
+                        backend_data.renderer.egl_context().get_context_handle(),
+                        std::ptr::null(),
+                    );
+                }
+            } else if let Some(renderdoc) = state.renderdoc.as_mut() {
+                renderdoc.discard_frame_capture(
+                    backend_data.renderer.egl_context().get_context_handle(),
+                    std::ptr::null(),
+                );
+            }
+
+            x11_output.render = !submitted;
         }
+        Err(err) => {
+            #[cfg(feature = "debug")]
+            if let Some(renderdoc) = state.renderdoc.as_mut() {
+                renderdoc.discard_frame_capture(
+                    backend_data.renderer.egl_context().get_context_handle(),
+                    std::ptr::null(),
+                );
+            }
 
-        let result = event_loop.dispatch(Some(Duration::from_millis(16)), &mut state);
-        if result.is_err() {
-            state.running.store(false, Ordering::SeqCst);
-        } else {
-            state.space.refresh();
-            state.popups.cleanup();
-            display_handle.flush_clients().unwrap();
+            x11_output.surface.reset_buffers();
+            error!("Rendering error: {}", err);
+            // TODO: convert RenderError into SwapBuffersError and skip temporary (will retry) and panic on ContextLost or recreate
         }
     }
+
+    #[cfg(feature = "debug")]
+    x11_output.fps.tick();
+    x11_output.window.set_cursor_visible(cursor_visible);
+    profiling::finish_frame!();
 }
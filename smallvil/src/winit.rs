@@ -35,6 +35,7 @@ pub fn init_winit(
             subpixel: Subpixel::Unknown,
             make: "Smithay".into(),
             model: "Winit".into(),
+            serial_number: "Unknown".into(),
         },
     );
     let _global = output.create_global::<Smallvil>(display_handle);
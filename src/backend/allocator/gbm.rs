@@ -3,9 +3,24 @@
 //! The re-exported [`GbmDevice`](gbm::Device) implements the [`Allocator`] trait
 //! and [`GbmBuffer`](gbm::BufferObject) satisfies the [`Buffer`] trait while also allowing
 //! conversions to and from [dmabufs](super::dmabuf).
+//!
+//! ## Modifier selection
+//!
+//! Buffers are allocated with one of the modifiers passed to [`Allocator::create_buffer`].
+//! All explicit modifiers are offered to the driver at once first, so it can choose the optimal one.
+//! If that fails, which happens on some drivers for specific combinations of modifiers and usage flags,
+//! the modifiers are retried individually before falling back to an implicit modifier, if
+//! [`Modifier::Invalid`] or [`Modifier::Linear`] is allowed.
+//! The modifier that was chosen is available through [`Buffer::format`] of the resulting [`GbmBuffer`].
+//!
+//! The modifiers usable for a buffer usually depend on its use-case. The [`GbmAllocator`] can store
+//! a set of formats for every [`GbmBufferUsage`], e.g. the intersection of the render formats of a renderer
+//! and the formats of a drm plane for [`GbmBufferUsage::Scanout`], which is used to filter the requested
+//! modifiers in [`GbmAllocator::create_buffer_for_usage`].
 
 use super::{
     dmabuf::{AsDmabuf, Dmabuf, DmabufFlags, MAX_PLANES},
+    format::FormatSet,
     Allocator, Buffer, Format, Fourcc, Modifier,
 };
 #[cfg(feature = "backend_drm")]
@@ -15,8 +30,11 @@ use crate::utils::{Buffer as BufferCoords, Size};
 use drm::buffer::PlanarBuffer;
 use gbm::BufferObject;
 pub use gbm::{BufferObjectFlags as GbmBufferFlags, Device as GbmDevice};
-use std::os::unix::io::{AsFd, BorrowedFd};
-use tracing::instrument;
+use std::{
+    collections::HashMap,
+    os::unix::io::{AsFd, BorrowedFd},
+};
+use tracing::{debug, instrument, trace};
 
 /// A GBM buffer object
 #[derive(Debug)]
@@ -123,11 +141,34 @@ impl std::ops::DerefMut for GbmBuffer {
     }
 }
 
+/// Use-case of a buffer allocated by a [`GbmAllocator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GbmBufferUsage {
+    /// The buffer is rendered to
+    Render,
+    /// The buffer is rendered to and scanned out by a drm plane
+    Scanout,
+    /// The buffer is only sampled from as a texture
+    Texture,
+}
+
+impl GbmBufferUsage {
+    /// Usage flags matching the use-case
+    pub fn flags(&self) -> GbmBufferFlags {
+        match self {
+            GbmBufferUsage::Render => GbmBufferFlags::RENDERING,
+            GbmBufferUsage::Scanout => GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT,
+            GbmBufferUsage::Texture => GbmBufferFlags::empty(),
+        }
+    }
+}
+
 /// Light wrapper around an [`GbmDevice`] to implement the [`Allocator`]-trait
 #[derive(Clone, Debug)]
 pub struct GbmAllocator<A: AsFd + 'static> {
     device: GbmDevice<A>,
     default_flags: GbmBufferFlags,
+    usage_formats: HashMap<GbmBufferUsage, FormatSet>,
 }
 
 impl<A: AsFd + 'static> AsRef<GbmDevice<A>> for GbmAllocator<A> {
@@ -158,9 +199,69 @@ impl<A: AsFd + 'static> GbmAllocator<A> {
         GbmAllocator {
             device,
             default_flags,
+            usage_formats: HashMap::new(),
         }
     }
 
+    /// Restrict the formats used for buffers of a specific use-case
+    ///
+    /// The `formats` are typically the intersection of the formats supported by all parties
+    /// accessing the buffer, e.g. the render formats of a renderer and the formats of a drm plane.
+    pub fn set_usage_formats(&mut self, usage: GbmBufferUsage, formats: impl IntoIterator<Item = Format>) {
+        self.usage_formats.insert(usage, formats.into_iter().collect());
+    }
+
+    /// Returns the formats buffers of a specific use-case are restricted to, if any
+    pub fn usage_formats(&self, usage: GbmBufferUsage) -> Option<&FormatSet> {
+        self.usage_formats.get(&usage)
+    }
+
+    /// Remove the restriction of formats for a specific use-case
+    pub fn clear_usage_formats(&mut self, usage: GbmBufferUsage) {
+        self.usage_formats.remove(&usage);
+    }
+
+    /// Alternative to [`Allocator::create_buffer`] for a buffer with a specific use-case.
+    ///
+    /// The `modifiers` are filtered by the formats set with [`GbmAllocator::set_usage_formats`]
+    /// and the buffer is allocated with the flags of the `usage`.
+    #[instrument(level = "trace", skip(self), fields(self.device = ?self.device, err))]
+    #[profiling::function]
+    pub fn create_buffer_for_usage(
+        &mut self,
+        width: u32,
+        height: u32,
+        fourcc: Fourcc,
+        modifiers: &[Modifier],
+        usage: GbmBufferUsage,
+    ) -> Result<GbmBuffer, std::io::Error> {
+        let modifiers = match self.usage_formats.get(&usage) {
+            Some(formats) => modifiers
+                .iter()
+                .copied()
+                .filter(|modifier| {
+                    formats.contains(&Format {
+                        code: fourcc,
+                        modifier: *modifier,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            None => modifiers.to_vec(),
+        };
+
+        if modifiers.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "none of the requested modifiers for {} are usable for {:?}",
+                    fourcc, usage
+                ),
+            ));
+        }
+
+        self.create_buffer_with_flags(width, height, fourcc, &modifiers, usage.flags())
+    }
+
     /// Alternative to [`Allocator::create_buffer`], if you need a one-off buffer with
     /// a different set of usage flags.
     #[instrument(level = "trace", skip(self), fields(self.device = ?self.device, err))]
@@ -172,43 +273,112 @@ impl<A: AsFd + 'static> GbmAllocator<A> {
         fourcc: Fourcc,
         modifiers: &[Modifier],
         flags: GbmBufferFlags,
+    ) -> Result<GbmBuffer, std::io::Error> {
+        let explicit = modifiers
+            .iter()
+            .copied()
+            .filter(|modifier| *modifier != Modifier::Invalid)
+            .collect::<Vec<_>>();
+
+        let mut errors = Vec::new();
+        for subset in modifier_subsets(&explicit) {
+            match self.create_buffer_with_modifiers(width, height, fourcc, &subset, flags) {
+                Ok(buffer) => {
+                    debug!(
+                        "Allocated {}x{} {} buffer with modifier {:?}",
+                        width, height, fourcc, buffer.format.modifier
+                    );
+                    return Ok(buffer);
+                }
+                Err(err) => {
+                    trace!("Failed to allocate buffer with modifiers {:?}: {}", subset, err);
+                    errors.push(err);
+                }
+            }
+        }
+
+        if modifiers.contains(&Modifier::Invalid) || modifiers.contains(&Modifier::Linear) {
+            return self
+                .device
+                .create_buffer_object(width, height, fourcc, flags)
+                .map(|bo| {
+                    debug!(
+                        "Allocated {}x{} {} buffer with implicit modifier",
+                        width, height, fourcc
+                    );
+                    GbmBuffer::from_bo(bo, true)
+                });
+        }
+
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!(
+                "failed to allocate {} buffer with any of the modifiers {:?}: {}",
+                fourcc,
+                modifiers,
+                errors
+                    .last()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| String::from("no explicit modifier requested"))
+            ),
+        ))
+    }
+
+    fn create_buffer_with_modifiers(
+        &mut self,
+        width: u32,
+        height: u32,
+        fourcc: Fourcc,
+        modifiers: &[Modifier],
+        flags: GbmBufferFlags,
     ) -> Result<GbmBuffer, std::io::Error> {
         #[cfg(feature = "backend_gbm_has_create_with_modifiers2")]
-        let result = self
-            .device
-            .create_buffer_object_with_modifiers2(width, height, fourcc, modifiers.iter().copied(), flags)
-            .map(|bo| GbmBuffer::from_bo(bo, false));
+        let result = self.device.create_buffer_object_with_modifiers2(
+            width,
+            height,
+            fourcc,
+            modifiers.iter().copied(),
+            flags,
+        );
 
         #[cfg(not(feature = "backend_gbm_has_create_with_modifiers2"))]
         let result = if (flags & !(GbmBufferFlags::SCANOUT | GbmBufferFlags::RENDERING)).is_empty() {
             self.device
                 .create_buffer_object_with_modifiers(width, height, fourcc, modifiers.iter().copied())
-                .map(|bo| GbmBuffer::from_bo(bo, false))
-        } else if modifiers.contains(&Modifier::Invalid) || modifiers.contains(&Modifier::Linear) {
-            return self
-                .device
-                .create_buffer_object(width, height, fourcc, flags)
-                .map(|bo| GbmBuffer::from_bo(bo, true));
         } else {
-            return Err(std::io::Error::new(
+            Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "unsupported combination of flags and modifiers",
-            ));
+            ))
         };
 
-        match result {
-            Ok(bo) => Ok(bo),
-            Err(err) => {
-                if modifiers.contains(&Modifier::Invalid) || modifiers.contains(&Modifier::Linear) {
-                    self.device
-                        .create_buffer_object(width, height, fourcc, flags)
-                        .map(|bo| GbmBuffer::from_bo(bo, true))
-                } else {
-                    Err(err)
-                }
-            }
+        result.map(|bo| GbmBuffer::from_bo(bo, false))
+    }
+}
+
+// Subsets of explicit modifiers to try allocating with, in order
+//
+// All modifiers are tried at once first, letting the driver choose. Drivers may however fail
+// to allocate for some of the modifiers, so every modifier is retried on its own afterwards,
+// keeping linear as the last resort.
+fn modifier_subsets(modifiers: &[Modifier]) -> Vec<Vec<Modifier>> {
+    if modifiers.is_empty() {
+        return Vec::new();
+    }
+
+    let mut subsets = vec![modifiers.to_vec()];
+    if modifiers.len() > 1 {
+        subsets.extend(
+            modifiers
+                .iter()
+                .filter(|modifier| **modifier != Modifier::Linear)
+                .map(|modifier| vec![*modifier]),
+        );
+        if modifiers.contains(&Modifier::Linear) {
+            subsets.push(vec![Modifier::Linear]);
         }
     }
+    subsets
 }
 
 impl<A: AsFd + 'static> Allocator for GbmAllocator<A> {
@@ -389,3 +559,26 @@ impl Dmabuf {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{modifier_subsets, Modifier};
+
+    #[test]
+    fn modifier_subsets_fall_back_to_linear_last() {
+        let tiled = Modifier::I915_x_tiled;
+        let compressed = Modifier::I915_y_tiled_ccs;
+
+        assert!(modifier_subsets(&[]).is_empty());
+        assert_eq!(modifier_subsets(&[tiled]), vec![vec![tiled]]);
+        assert_eq!(
+            modifier_subsets(&[Modifier::Linear, tiled, compressed]),
+            vec![
+                vec![Modifier::Linear, tiled, compressed],
+                vec![tiled],
+                vec![compressed],
+                vec![Modifier::Linear],
+            ]
+        );
+    }
+}
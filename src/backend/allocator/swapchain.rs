@@ -10,10 +10,12 @@ use std::{
 use tracing::instrument;
 
 use crate::backend::allocator::{Allocator, Buffer, Fourcc, Modifier};
+use crate::backend::renderer::damage::BufferAge;
 use crate::utils::user_data::UserDataMap;
 
 use super::dmabuf::{AsDmabuf, Dmabuf};
 
+/// Default maximum number of buffers of a [`Swapchain`]
 pub const SLOT_CAP: usize = 4;
 
 /// Swapchain handling a fixed set of re-usable buffers e.g. for scan-out.
@@ -43,6 +45,16 @@ pub const SLOT_CAP: usize = 4;
 /// If you have associated resources for each buffer that can be reused (e.g. framebuffer `Handle`s for a `DrmDevice`),
 /// you can store then in the `Slot`s userdata field. If a buffer is re-used, its userdata is preserved for the next time
 /// it is returned by `acquire()`.
+///
+/// ## Buffer count
+///
+/// By default the swapchain allocates up to [`SLOT_CAP`] buffers. Buffers are only allocated once all
+/// existing buffers are in use, so the swapchain only grows as far as necessary. The maximum can be changed
+/// with [`set_max_buffers`](Swapchain::set_max_buffers), e.g. to enforce double-buffering for lower
+/// latency or to allow triple-buffering for higher throughput.
+///
+/// Buffers that have not been submitted for a while can be released automatically by setting a
+/// [`release_age`](Swapchain::set_release_age), shrinking the swapchain back if fewer buffers are needed.
 pub struct Swapchain<A: Allocator> {
     /// Allocator used by the swapchain
     pub allocator: A,
@@ -52,7 +64,9 @@ pub struct Swapchain<A: Allocator> {
     fourcc: Fourcc,
     modifiers: Vec<Modifier>,
 
-    slots: [Arc<InternalSlot<A::Buffer>>; SLOT_CAP],
+    slots: Vec<Arc<InternalSlot<A::Buffer>>>,
+    max_buffers: usize,
+    release_age: Option<u8>,
 }
 
 impl<A: Allocator> fmt::Debug for Swapchain<A> {
@@ -62,6 +76,8 @@ impl<A: Allocator> fmt::Debug for Swapchain<A> {
             .field("height", &self.height)
             .field("fourcc", &self.fourcc)
             .field("modifiers", &self.modifiers)
+            .field("max_buffers", &self.max_buffers)
+            .field("release_age", &self.release_age)
             .finish_non_exhaustive()
     }
 }
@@ -93,6 +109,13 @@ impl<B: Buffer> Slot<B> {
     }
 }
 
+impl<B: Buffer> BufferAge for Slot<B> {
+    #[inline]
+    fn buffer_age(&self) -> Option<usize> {
+        Some(self.age() as usize)
+    }
+}
+
 impl<B: Buffer> Default for InternalSlot<B> {
     fn default() -> Self {
         InternalSlot {
@@ -149,17 +172,25 @@ where
             height,
             fourcc,
             modifiers,
-            slots: Default::default(),
+            slots: Vec::with_capacity(SLOT_CAP),
+            max_buffers: SLOT_CAP,
+            release_age: None,
         }
     }
 
     /// Acquire a new slot from the swapchain, if one is still free.
     ///
-    /// The swapchain has an internal maximum of four re-usable buffers.
-    /// This function returns the first free one.
+    /// The swapchain has a maximum of [`max_buffers`](Swapchain::max_buffers) re-usable buffers.
+    /// This function returns the first free one, allocating a new buffer if necessary.
     #[instrument(level = "trace", skip_all, err)]
     #[profiling::function]
     pub fn acquire(&mut self) -> Result<Option<Slot<A::Buffer>>, A::Error> {
+        if !self.slots.iter().any(|s| !s.acquired.load(Ordering::SeqCst))
+            && self.slots.len() < self.max_buffers
+        {
+            self.slots.push(Default::default());
+        }
+
         if let Some(free_slot) = self
             .slots
             .iter_mut()
@@ -213,8 +244,15 @@ where
                             Some(0)
                         }
                     });
-                // If the age overflows the slot was not used for a long time. Lets clear it
-                if res.is_err() {
+                // If the age overflows or exceeds the release age the slot was not used for a long time.
+                // Lets clear it
+                let expired = match (res, self.release_age) {
+                    // `fetch_update` returns the age before it was increased
+                    (Ok(age), Some(release_age)) => age > 0 && age >= release_age,
+                    (Ok(_), None) => false,
+                    (Err(_), _) => true,
+                };
+                if expired {
                     *other_slot = Default::default();
                 }
             }
@@ -231,14 +269,67 @@ where
 
         self.width = width;
         self.height = height;
-        self.slots = Default::default();
+        self.slots.clear();
+    }
+
+    /// Change the format and allowed modifiers of newly returned buffers.
+    ///
+    /// If either changed, all internally cached buffers are invalidated.
+    /// Already obtained buffers are unaffected and will be cleaned up on drop.
+    pub fn set_format(&mut self, fourcc: Fourcc, modifiers: Vec<Modifier>) {
+        if self.fourcc == fourcc && self.modifiers == modifiers {
+            return;
+        }
+
+        self.fourcc = fourcc;
+        self.modifiers = modifiers;
+        self.slots.clear();
+    }
+
+    /// Set the maximum number of buffers of the swapchain
+    ///
+    /// If the swapchain currently holds more buffers, buffers not currently in use are released
+    /// first. Already obtained buffers stay valid, but are no longer tracked by the swapchain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    pub fn set_max_buffers(&mut self, count: usize) {
+        assert!(count > 0, "A swapchain needs at least one buffer");
+
+        self.max_buffers = count;
+        while self.slots.len() > count {
+            let idx = self
+                .slots
+                .iter()
+                .rposition(|slot| !slot.acquired.load(Ordering::SeqCst))
+                .unwrap_or(self.slots.len() - 1);
+            self.slots.remove(idx);
+        }
+    }
+
+    /// Returns the maximum number of buffers of the swapchain
+    pub fn max_buffers(&self) -> usize {
+        self.max_buffers
+    }
+
+    /// Returns the number of buffers currently allocated by the swapchain
+    pub fn allocated_buffers(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.buffer.is_some()).count()
+    }
+
+    /// Release buffers, which exceed the given age without being submitted again
+    ///
+    /// The age should be larger than the number of buffers in use, otherwise buffers are
+    /// released while still cycling through the swapchain.
+    /// `None` only releases buffers once their age overflows, which is the default.
+    pub fn set_release_age(&mut self, age: Option<u8>) {
+        self.release_age = age;
     }
 
     /// Remove all internally cached buffers.
     pub fn reset_buffers(&mut self) {
-        for slot in &mut self.slots {
-            *slot = Default::default();
-        }
+        self.slots.clear();
     }
 
     /// Reset the age for each buffer.
@@ -258,4 +349,86 @@ where
     pub fn format(&self) -> Fourcc {
         self.fourcc
     }
+
+    /// Get the set of allowed modifiers
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::allocator::{Allocator, Buffer, Format, Fourcc, Modifier};
+    use crate::utils::{Buffer as BufferCoords, Size};
+
+    use super::Swapchain;
+
+    #[derive(Debug)]
+    struct DummyBuffer(Size<i32, BufferCoords>, Format);
+
+    impl Buffer for DummyBuffer {
+        fn size(&self) -> Size<i32, BufferCoords> {
+            self.0
+        }
+
+        fn format(&self) -> Format {
+            self.1
+        }
+    }
+
+    #[derive(Debug)]
+    struct DummyAllocator;
+
+    impl Allocator for DummyAllocator {
+        type Buffer = DummyBuffer;
+        type Error = std::convert::Infallible;
+
+        fn create_buffer(
+            &mut self,
+            width: u32,
+            height: u32,
+            fourcc: Fourcc,
+            modifiers: &[Modifier],
+        ) -> Result<DummyBuffer, Self::Error> {
+            let format = Format {
+                code: fourcc,
+                modifier: modifiers[0],
+            };
+            Ok(DummyBuffer((width as i32, height as i32).into(), format))
+        }
+    }
+
+    #[test]
+    fn swapchain_buffer_count_and_release() {
+        let mut swapchain = Swapchain::new(DummyAllocator, 64, 64, Fourcc::Argb8888, vec![Modifier::Linear]);
+        swapchain.set_max_buffers(2);
+
+        let first = swapchain.acquire().unwrap().unwrap();
+        let second = swapchain.acquire().unwrap().unwrap();
+        assert!(swapchain.acquire().unwrap().is_none());
+        assert_eq!(swapchain.allocated_buffers(), 2);
+
+        // growing the swapchain allows triple-buffering
+        swapchain.set_max_buffers(3);
+        let third = swapchain.acquire().unwrap().unwrap();
+        assert_eq!(swapchain.allocated_buffers(), 3);
+
+        // only `first` keeps being submitted, the other buffers are released once they get too old
+        swapchain.set_release_age(Some(3));
+        swapchain.submitted(&second);
+        swapchain.submitted(&third);
+        drop((second, third));
+        swapchain.submitted(&first);
+        assert_eq!(swapchain.allocated_buffers(), 3);
+        swapchain.submitted(&first);
+        assert_eq!(swapchain.allocated_buffers(), 2);
+        swapchain.submitted(&first);
+        assert_eq!(swapchain.allocated_buffers(), 1);
+
+        // changing the format invalidates all buffers
+        swapchain.set_format(Fourcc::Xrgb8888, vec![Modifier::Linear]);
+        assert_eq!(swapchain.allocated_buffers(), 0);
+        let slot = swapchain.acquire().unwrap().unwrap();
+        assert_eq!(slot.format().code, Fourcc::Xrgb8888);
+    }
 }
@@ -15,6 +15,13 @@
 //!
 //! To get the required extensions a device must support to use the Vulkan allocator, use
 //! [`VulkanAllocator::required_extensions`].
+//!
+//! Buffers are always allocated with an explicit modifier. The allocator picks one of the modifiers
+//! passed to [`Allocator::create_buffer`] that the driver supports for the requested size and usage,
+//! so the allocator can be used in place of a [`GbmAllocator`](super::gbm::GbmAllocator) for
+//! a [`Swapchain`](super::Swapchain) or the [`DrmCompositor`](crate::backend::drm::compositor::DrmCompositor).
+//! The format and modifier combinations which may be allocated can be queried with
+//! [`VulkanAllocator::supported_formats`] to negotiate formats with a renderer or drm planes.
 
 #![forbid(unsafe_op_in_unsafe_fn)]
 
@@ -44,6 +51,7 @@ use crate::{
 
 use super::{
     dmabuf::{AsDmabuf, Dmabuf, MAX_PLANES},
+    format::FormatSet,
     Allocator, Buffer,
 };
 
@@ -116,6 +124,14 @@ pub enum Error {
     #[error("format is not supported")]
     UnsupportedFormat,
 
+    /// The maximum number of allocations supported by the device has been reached.
+    #[error("too many allocations")]
+    TooManyAllocations,
+
+    /// The device offers no memory type the buffer could be allocated from.
+    #[error("no suitable memory type")]
+    NoMemoryType,
+
     /// Some error from the Vulkan driver.
     #[error(transparent)]
     Vk(#[from] vk::Result),
@@ -127,6 +143,7 @@ pub struct VulkanAllocator {
     images: Vec<ImageInner>,
     default_usage: ImageUsageFlags,
     remaining_allocations: u32,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
     extension_fns: ExtensionFns,
     dropped_recv: mpsc::Receiver<ImageInner>,
     dropped_sender: mpsc::Sender<ImageInner>,
@@ -246,6 +263,7 @@ impl VulkanAllocator {
             khr_external_memory_fd: khr::external_memory_fd::Device::new(instance, &device),
         };
 
+        let memory_properties = unsafe { instance.get_physical_device_memory_properties(phd.handle()) };
         let (dropped_sender, dropped_recv) = mpsc::channel();
 
         #[cfg(feature = "backend_drm")]
@@ -260,6 +278,7 @@ impl VulkanAllocator {
             images: Vec::new(),
             default_usage,
             remaining_allocations: phd.limits().max_memory_allocation_count,
+            memory_properties,
             extension_fns,
             dropped_recv,
             dropped_sender,
@@ -290,6 +309,18 @@ impl VulkanAllocator {
             .is_some()
     }
 
+    /// Returns the format and modifier combinations buffers with the usage flags may be allocated with.
+    ///
+    /// All returned formats use an explicit modifier, the allocator is unable to allocate buffers with
+    /// [`DrmModifier::Invalid`].
+    pub fn supported_formats(&self, usage: ImageUsageFlags) -> FormatSet {
+        self.formats
+            .iter()
+            .map(|entry| entry.format)
+            .filter(|format| self.is_format_supported(*format, usage))
+            .collect()
+    }
+
     /// Try to create a buffer with the given dimensions, pixel format and usage flags.
     ///
     /// This may return [`Err`] for one of the following reasons:
@@ -298,6 +329,7 @@ impl VulkanAllocator {
     /// - All of the allowed `modifiers` are not supported.
     /// - The size of the buffer is too large for the `usage`, `fourcc` format or `modifiers`.
     /// - The `fourcc` format and `modifiers` do not support the specified usage.
+    /// - The maximum number of allocations of the device has been reached.
     #[instrument(level = "trace", err)]
     #[profiling::function]
     pub fn create_buffer_with_usage(
@@ -341,7 +373,12 @@ impl VulkanAllocator {
             return Err(Error::UnsupportedFormat);
         }
 
-        Ok(unsafe { self.create_image(width, height, vk_format, vk_usage, fourcc, &modifiers[..]) }?)
+        // VUID-vkAllocateMemory-maxMemoryAllocationCount-04101
+        if self.remaining_allocations == 0 {
+            return Err(Error::TooManyAllocations);
+        }
+
+        unsafe { self.create_image(width, height, vk_format, vk_usage, fourcc, &modifiers[..]) }
     }
 
     /// Returns the [`PhysicalDevice`] this allocator was created with.
@@ -659,6 +696,7 @@ impl VulkanAllocator {
     ///
     /// * The list of modifiers must be supported for the given format and image usage flags.
     /// * The extent of the image must be within the maximum extents Vulkan tells.
+    /// * There must be at least one remaining allocation.
     unsafe fn create_image(
         &mut self,
         width: u32,
@@ -667,14 +705,10 @@ impl VulkanAllocator {
         vk_usage: vk::ImageUsageFlags,
         fourcc: DrmFourcc,
         modifiers: &[u64],
-    ) -> Result<VulkanImage, vk::Result> {
+    ) -> Result<VulkanImage, Error> {
         assert!(width > 0);
         assert!(height > 0);
-
-        // Ensure maximum allocations are not exceeded.
-        if self.remaining_allocations == 0 {
-            todo!()
-        }
+        assert!(self.remaining_allocations > 0);
 
         // Now that the list of valid modifiers is known, create an image using one of the modifiers.
         let mut modifier_list =
@@ -747,11 +781,14 @@ impl VulkanAllocator {
 
         // Allocate image memory
         let memory_reqs = unsafe { self.device.get_image_memory_requirements(guard.image) };
-        // TODO: Memory type index
+        let memory_type_index = select_memory_type(&self.memory_properties, memory_reqs.memory_type_bits)
+            .ok_or(Error::NoMemoryType)?;
         let mut export_memory_allocate_info = vk::ExportMemoryAllocateInfo::default()
             .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
         let alloc_create_info = vk::MemoryAllocateInfo::default()
             .allocation_size(memory_reqs.size)
+            // VUID-vkAllocateMemory-pAllocateInfo-01714
+            .memory_type_index(memory_type_index)
             .push_next(&mut export_memory_allocate_info);
 
         unsafe {
@@ -812,3 +849,40 @@ impl VulkanAllocator {
         })
     }
 }
+
+/// Returns the index of the memory type an image with the `memory_type_bits` requirement should be allocated
+/// from, preferring device local memory.
+fn select_memory_type(properties: &vk::PhysicalDeviceMemoryProperties, memory_type_bits: u32) -> Option<u32> {
+    let allowed = (0..properties.memory_type_count).filter(|idx| memory_type_bits & (1 << idx) != 0);
+
+    allowed
+        .clone()
+        .find(|&idx| {
+            properties.memory_types[idx as usize]
+                .property_flags
+                .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+        })
+        .or_else(|| allowed.min())
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::select_memory_type;
+
+    #[test]
+    fn memory_type_prefers_device_local() {
+        let mut properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 3,
+            ..Default::default()
+        };
+        properties.memory_types[0].property_flags = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        properties.memory_types[2].property_flags = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+
+        assert_eq!(select_memory_type(&properties, 0b111), Some(2));
+        assert_eq!(select_memory_type(&properties, 0b011), Some(0));
+        // types beyond the memory type count are never used
+        assert_eq!(select_memory_type(&properties, 0b1000), None);
+    }
+}
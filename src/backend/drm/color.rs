@@ -0,0 +1,214 @@
+//! Hardware color pipeline of a crtc
+//!
+//! Most display controllers can correct the colors of the composited content while scanning it out.
+//! The pipeline consists of up to three optional stages, applied in order:
+//!
+//! - `DEGAMMA_LUT`, a [`Lut`] usually decoding the content into linear light
+//! - `CTM`, a color transformation matrix ([`Ctm`]) applied on the output of the first stage
+//! - `GAMMA_LUT`, a [`Lut`] usually encoding the result for the display
+//!
+//! Offloading corrections like a night light ([`ColorFilter`]) or ICC-derived calibration curves to these
+//! stages saves an additional composition pass. The stages supported by a crtc and the sizes of their lookup
+//! tables can be queried with [`DrmSurface::color_pipeline`]. Legacy devices only support a gamma lookup
+//! table, which is applied immediately.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmSurface, color::Lut};
+//! # use smithay::backend::renderer::color_transform::ColorFilter;
+//! # let surface: DrmSurface = todo!();
+//! let filters = [ColorFilter::Temperature(4000.0)];
+//! if let Some(size) = surface.color_pipeline().unwrap().gamma_lut_size {
+//!     // night light without rendering
+//!     let lut = Lut::from_color_filters(&filters, size as usize).unwrap();
+//!     surface.set_gamma_lut(Some(&lut)).unwrap();
+//! }
+//! ```
+//!
+//! [`DrmSurface::color_pipeline`]: super::DrmSurface::color_pipeline
+
+use crate::backend::renderer::color_transform::ColorFilter;
+
+/// Color pipeline stages supported by a crtc
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorPipeline {
+    /// Number of entries of the degamma lookup table, if supported
+    pub degamma_lut_size: Option<u32>,
+    /// Whether a color transformation matrix is supported
+    pub ctm: bool,
+    /// Number of entries of the gamma lookup table, if supported
+    pub gamma_lut_size: Option<u32>,
+}
+
+/// One-dimensional lookup table with a separate curve for every channel
+///
+/// The input range is divided evenly between the entries, the output range of every entry
+/// is `0..=u16::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Lut {
+    red: Vec<u16>,
+    green: Vec<u16>,
+    blue: Vec<u16>,
+}
+
+impl Lut {
+    /// Create a lookup table with `size` entries not altering any colors
+    pub fn identity(size: usize) -> Self {
+        Lut::from_fn(size, |rgb| rgb)
+    }
+
+    /// Create a lookup table with `size` entries by evaluating `func` for every entry
+    ///
+    /// `func` is called with the same value for all channels in the range `0.0..=1.0` and returns
+    /// the resulting channel values, which are clamped to the same range.
+    pub fn from_fn(size: usize, func: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let max = size.saturating_sub(1).max(1) as f32;
+        let mut lut = Lut {
+            red: Vec::with_capacity(size),
+            green: Vec::with_capacity(size),
+            blue: Vec::with_capacity(size),
+        };
+        for i in 0..size {
+            let [r, g, b] = func([i as f32 / max; 3]).map(encode);
+            lut.red.push(r);
+            lut.green.push(g);
+            lut.blue.push(b);
+        }
+        lut
+    }
+
+    /// Create a lookup table from the curves of the individual channels
+    ///
+    /// Returns `None` if the curves differ in length.
+    pub fn from_ramps(red: Vec<u16>, green: Vec<u16>, blue: Vec<u16>) -> Option<Self> {
+        (red.len() == green.len() && red.len() == blue.len()).then_some(Lut { red, green, blue })
+    }
+
+    /// Create a lookup table with `size` entries applying the given filters
+    ///
+    /// Returns `None` if any filter is not [separable](ColorFilter::is_separable)
+    /// and thus can not be applied by a lookup table.
+    pub fn from_color_filters(filters: &[ColorFilter], size: usize) -> Option<Self> {
+        let [red, green, blue] = ColorFilter::gamma_ramps(filters, size)?;
+        Lut::from_ramps(red, green, blue)
+    }
+
+    /// Number of entries of the lookup table
+    pub fn len(&self) -> usize {
+        self.red.len()
+    }
+
+    /// Returns whether the lookup table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.red.is_empty()
+    }
+
+    /// Curve of the red channel
+    pub fn red(&self) -> &[u16] {
+        &self.red
+    }
+
+    /// Curve of the green channel
+    pub fn green(&self) -> &[u16] {
+        &self.green
+    }
+
+    /// Curve of the blue channel
+    pub fn blue(&self) -> &[u16] {
+        &self.blue
+    }
+
+    pub(super) fn to_raw(&self) -> Vec<drm_ffi::drm_color_lut> {
+        self.red
+            .iter()
+            .zip(&self.green)
+            .zip(&self.blue)
+            .map(|((&red, &green), &blue)| drm_ffi::drm_color_lut {
+                red,
+                green,
+                blue,
+                reserved: 0,
+            })
+            .collect()
+    }
+}
+
+fn encode(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+/// Color transformation matrix applied by a crtc
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ctm {
+    matrix: [[f64; 3]; 3],
+}
+
+impl Ctm {
+    /// Matrix not altering any colors
+    pub const IDENTITY: Ctm = Ctm {
+        matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+
+    /// Create a color transformation matrix from its rows
+    ///
+    /// The matrix is multiplied with the column vector of the red, green and blue channel.
+    pub fn new(matrix: [[f64; 3]; 3]) -> Self {
+        Ctm { matrix }
+    }
+
+    /// Rows of the matrix
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        self.matrix
+    }
+
+    pub(super) fn to_raw(self) -> drm_ffi::drm_color_ctm {
+        let mut raw = drm_ffi::drm_color_ctm::default();
+        for (raw, value) in raw.matrix.iter_mut().zip(self.matrix.iter().flatten()) {
+            *raw = to_sign_magnitude(*value);
+        }
+        raw
+    }
+}
+
+impl Default for Ctm {
+    fn default() -> Self {
+        Ctm::IDENTITY
+    }
+}
+
+impl From<[[f32; 3]; 3]> for Ctm {
+    fn from(matrix: [[f32; 3]; 3]) -> Self {
+        Ctm::new(matrix.map(|row| row.map(f64::from)))
+    }
+}
+
+// S31.32 sign-magnitude fixed point as used by `drm_color_ctm`
+fn to_sign_magnitude(value: f64) -> u64 {
+    let magnitude = (value.abs() * (1u64 << 32) as f64).min((1u64 << 63) as f64 - 1.0) as u64;
+    if value.is_sign_negative() && magnitude != 0 {
+        magnitude | (1 << 63)
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ctm, Lut};
+    use crate::backend::renderer::color_transform::ColorFilter;
+
+    #[test]
+    fn luts_and_ctms_are_encoded_for_drm() {
+        let lut = Lut::identity(256);
+        assert_eq!((lut.red()[0], lut.red()[255]), (0, u16::MAX));
+        assert_eq!(lut.red()[128], 32896);
+        assert_eq!(lut.to_raw().len(), 256);
+        assert!(Lut::from_color_filters(&[ColorFilter::Grayscale], 256).is_none());
+        assert!(Lut::from_ramps(vec![0; 4], vec![0; 4], vec![0; 3]).is_none());
+
+        let ctm = Ctm::new([[0.5, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -2.0]]).to_raw();
+        assert_eq!(ctm.matrix[0], 1 << 31);
+        assert_eq!(ctm.matrix[1], 0);
+        assert_eq!(ctm.matrix[4], 1 << 32);
+        assert_eq!(ctm.matrix[8], (1 << 63) | (2 << 32));
+    }
+}
@@ -73,6 +73,7 @@
 //!         size: Size::from((800, 600)),
 //!         make: "N/A".into(),
 //!         model: "N/A".into(),
+//!         serial_number: "N/A".into(),
 //!         subpixel: Subpixel::Unknown,
 //!     },
 //! );
@@ -172,7 +173,11 @@ use crate::{
     wayland::{shm, single_pixel_buffer},
 };
 
-use super::{error::AccessError, DrmDeviceFd, DrmSurface, Framebuffer, PlaneClaim, PlaneInfo, Planes};
+use super::{
+    error::AccessError,
+    writeback::{WritebackConnector, WritebackJob},
+    DrmDeviceFd, DrmSurface, Framebuffer, PlaneClaim, PlaneInfo, Planes,
+};
 
 pub mod dumb;
 mod elements;
@@ -767,12 +772,15 @@ impl<B: Framebuffer> FrameState<B> {
         supports_fencing: bool,
         allow_partial_update: bool,
         event: bool,
+        async_flip: bool,
     ) -> Result<(), crate::backend::drm::error::Error> {
         debug_assert!(!self.planes.iter().any(|(_, state)| state.needs_test));
-        surface.page_flip(
-            self.build_planes(surface, supports_fencing, allow_partial_update),
-            event,
-        )
+        let planes = self.build_planes(surface, supports_fencing, allow_partial_update);
+        if async_flip {
+            surface.page_flip_async(planes, event).map(|_| ())
+        } else {
+            surface.page_flip(planes, event)
+        }
     }
 
     #[profiling::function]
@@ -1025,6 +1033,11 @@ pub struct RenderFrameResult<'a, B: Buffer, F: Framebuffer, E> {
 
 impl<'a, B: Buffer, F: Framebuffer, E> RenderFrameResult<'a, B, F, E> {
     /// Returns if synchronization with kms submission can't be guaranteed through the available apis.
+    ///
+    /// In this case rendering has to be finished before calling [`DrmCompositor::queue_frame`].
+    /// To not stall the event loop, callers can defer queueing the frame until the `sync` point of
+    /// the [`PrimaryPlaneElement::Swapchain`] element is reached, e.g. by polling the fence
+    /// returned by [`SyncPoint::export`] if it is exportable.
     pub fn needs_sync(&self) -> bool {
         if let PrimaryPlaneElement::Swapchain(ref element) = self.primary_element {
             !self.supports_fencing || !element.sync.is_exportable()
@@ -1032,6 +1045,14 @@ impl<'a, B: Buffer, F: Framebuffer, E> RenderFrameResult<'a, B, F, E> {
             false
         }
     }
+
+    /// Returns if composition was bypassed entirely for this frame
+    ///
+    /// This is the case if an element was assigned to the primary plane for direct scan-out
+    /// and no elements had to be rendered.
+    pub fn is_direct_scanout(&self) -> bool {
+        matches!(self.primary_element, PrimaryPlaneElement::Element(_))
+    }
 }
 
 struct SwapchainElement<'a, 'b, B: Buffer> {
@@ -1146,6 +1167,13 @@ where
             FrameResultDamageElement::Swapchain(e) => e.opaque_regions(scale),
         }
     }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        match self {
+            FrameResultDamageElement::Element(e) => e.backdrop_region(scale),
+            FrameResultDamageElement::Swapchain(e) => e.backdrop_region(scale),
+        }
+    }
 }
 
 /// Error for [`RenderFrameResult::blit_frame_result`]
@@ -1535,6 +1563,7 @@ enum PreparedFrameKind {
 struct PreparedFrame<A: Allocator, F: ExportFramebuffer<<A as Allocator>::Buffer>> {
     frame: Frame<A, F>,
     kind: PreparedFrameKind,
+    async_flip: bool,
 }
 
 impl<A: Allocator, F: ExportFramebuffer<<A as Allocator>::Buffer>> PreparedFrame<A, F> {
@@ -1557,10 +1586,27 @@ where
         f.debug_struct("PreparedFrame")
             .field("frame", &self.frame)
             .field("kind", &self.kind)
+            .field("async_flip", &self.async_flip)
             .finish()
     }
 }
 
+/// Policy for enabling variable refresh rate (adaptive sync) on a [`DrmCompositor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VrrPolicy {
+    /// Variable refresh rate is controlled manually with [`DrmSurface::use_vrr`]
+    #[default]
+    Manual,
+    /// Variable refresh rate is always enabled, if supported
+    Always,
+    /// Variable refresh rate is only enabled while an element is directly scanned out
+    /// on the primary plane
+    ///
+    /// This is usually the case for fullscreen games or video players, while avoiding
+    /// flickering or stuttering of the cursor and animations of the desktop otherwise.
+    OnDirectScanout,
+}
+
 /// Composite an output using a combination of planes and rendering
 ///
 /// see the [`module docs`](crate::backend::drm::compositor) for more information
@@ -1582,6 +1628,10 @@ where
     primary_plane_damage_bag: DamageBag<i32, BufferCoords>,
     supports_fencing: bool,
     direct_scanout: bool,
+    overlay_planes: bool,
+    cursor_plane: bool,
+    vrr_policy: VrrPolicy,
+    tearing: bool,
     reset_pending: bool,
     signaled_fence: Option<Arc<OwnedFd>>,
 
@@ -1769,6 +1819,10 @@ where
                         primary_plane_damage_bag: DamageBag::new(4),
                         primary_is_opaque: is_opaque,
                         direct_scanout: true,
+                        overlay_planes: true,
+                        cursor_plane: true,
+                        vrr_policy: VrrPolicy::default(),
+                        tearing: false,
                         reset_pending: true,
                         signaled_fence,
                         current_frame,
@@ -1812,6 +1866,61 @@ where
         self.direct_scanout = enabled;
     }
 
+    /// Enable or disable the use of overlay planes.
+    ///
+    /// When disabled all elements not scanned out on the primary or cursor plane are composited
+    /// on the primary plane. Enabled by default.
+    pub fn use_overlay_planes(&mut self, enabled: bool) {
+        self.overlay_planes = enabled;
+    }
+
+    /// Enable or disable the use of the cursor plane.
+    ///
+    /// When disabled cursor elements are composited on the primary plane or assigned to an overlay plane.
+    /// Enabled by default.
+    pub fn use_cursor_plane(&mut self, enabled: bool) {
+        self.cursor_plane = enabled;
+    }
+
+    /// Set the policy for enabling variable refresh rate
+    ///
+    /// The policy is evaluated on every [`render_frame`](DrmCompositor::render_frame) and applied
+    /// with the next queued frame. Defaults to [`VrrPolicy::Manual`].
+    pub fn set_vrr_policy(&mut self, policy: VrrPolicy) {
+        self.vrr_policy = policy;
+    }
+
+    /// Returns the current policy for enabling variable refresh rate
+    pub fn vrr_policy(&self) -> VrrPolicy {
+        self.vrr_policy
+    }
+
+    /// Queues a capture of the next queued frame by a writeback connector into the given framebuffer
+    ///
+    /// The capture contains the complete output of the crtc including all planes. The next call to
+    /// [`render_frame`](DrmCompositor::render_frame) will not be empty to make sure a frame is queued.
+    /// See [`DrmSurface::queue_writeback`] for details.
+    pub fn queue_writeback(
+        &mut self,
+        connector: &WritebackConnector,
+        framebuffer: framebuffer::Handle,
+    ) -> Result<WritebackJob, DrmError> {
+        let job = self.surface.queue_writeback(connector, framebuffer)?;
+        self.reset_pending = true;
+        Ok(job)
+    }
+
+    /// Allow or disallow tearing
+    ///
+    /// When allowed, frames directly scanning out an element on the primary plane, e.g. a fullscreen
+    /// surface requesting asynchronous presentation through the
+    /// [tearing control protocol](crate::wayland::tearing_control), are presented with an
+    /// [asynchronous page flip](DrmSurface::page_flip_async) if supported by the driver.
+    /// Other frames and drivers rejecting the flip fall back to vsync. Disabled by default.
+    pub fn use_tearing(&mut self, enabled: bool) {
+        self.tearing = enabled;
+    }
+
     fn find_supported_format(
         drm: Arc<DrmSurface>,
         supports_fencing: bool,
@@ -2662,12 +2771,25 @@ where
             PrimaryPlaneElement::Element(primary_plane_scanout_element.unwrap())
         };
 
+        let vrr = match self.vrr_policy {
+            VrrPolicy::Manual => None,
+            VrrPolicy::Always => Some(true),
+            VrrPolicy::OnDirectScanout => Some(!render),
+        };
+        if let Some(vrr) = vrr.filter(|_| self.surface.vrr_supported()) {
+            if let Err(err) = self.surface.use_vrr(vrr) {
+                debug!(?err, "failed to update variable refresh rate");
+            }
+        }
+
         let next_frame = PreparedFrame {
             kind: if allow_partial_update {
                 PreparedFrameKind::Partial
             } else {
                 PreparedFrameKind::Full
             },
+            // drivers only accept asynchronous flips of the primary plane framebuffer
+            async_flip: self.tearing && !render,
             frame: next_frame_state,
         };
         let frame_reference: RenderFrameResult<'a, A::Buffer, F::Framebuffer, E> = RenderFrameResult {
@@ -2755,6 +2877,16 @@ where
         Ok(())
     }
 
+    /// Forces the next frame to restore the full state of the crtc and re-render all planes
+    ///
+    /// It is recommended to call this function after the system resumed from suspend,
+    /// see [`DrmSurface::restore_state`].
+    pub fn restore_state(&mut self) -> Result<(), DrmError> {
+        self.surface.restore_state()?;
+        self.reset_pending = true;
+        Ok(())
+    }
+
     #[profiling::function]
     fn submit(&mut self) -> FrameResult<(), A, F> {
         let QueuedFrame {
@@ -2768,9 +2900,13 @@ where
                 .frame
                 .commit(&self.surface, self.supports_fencing, allow_partial_update, true)
         } else {
-            prepared_frame
-                .frame
-                .page_flip(&self.surface, self.supports_fencing, allow_partial_update, true)
+            prepared_frame.frame.page_flip(
+                &self.surface,
+                self.supports_fencing,
+                allow_partial_update,
+                true,
+                prepared_frame.async_flip,
+            )
         };
 
         match flip {
@@ -2838,6 +2974,15 @@ where
         self.swapchain.reset_buffers();
     }
 
+    /// Set the maximum number of buffers used for rendering
+    ///
+    /// Defaults to [`SLOT_CAP`](crate::backend::allocator::SLOT_CAP). Using two buffers
+    /// (double-buffering) reduces latency, but rendering a new frame is only possible once
+    /// the pending frame has been presented, otherwise [`FrameError::NoFreeSlotsError`] is returned.
+    pub fn set_max_buffers(&mut self, count: usize) {
+        self.swapchain.set_max_buffers(count);
+    }
+
     /// Reset the age for all buffers.
     ///
     /// This can be used to efficiently clear the damage history without having to
@@ -3165,6 +3310,11 @@ where
         R: Renderer,
         E: RenderElement<R>,
     {
+        if !self.cursor_plane {
+            trace!("cursor plane disabled, skipping cursor rendering");
+            return None;
+        }
+
         let Some(cursor_state) = self.cursor_state.as_mut() else {
             trace!("no cursor state, skipping cursor rendering");
             return None;
@@ -3872,6 +4022,11 @@ where
     {
         let element_id = element.id();
 
+        if !self.overlay_planes {
+            trace!("overlay planes disabled, skipping element {:?}", element_id);
+            return Err(None);
+        }
+
         // Check if we have a free plane, otherwise we can exit early
         if self
             .planes
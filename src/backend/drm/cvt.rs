@@ -0,0 +1,186 @@
+//! Generation of custom modes following the VESA Coordinated Video Timings standard
+//!
+//! Displays with broken EDIDs or virtual outputs may need modes not advertised by any connector.
+//! [`cvt_mode`] computes the timings of such a mode from its resolution and refresh rate,
+//! either with the standard blanking suited for CRTs or the [reduced blanking](Blanking::Reduced)
+//! supported by virtually every digital display.
+//!
+//! The resulting mode can be set with [`DrmSurface::use_custom_mode`], which lets the driver validate it.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmSurface, cvt::{cvt_mode, Blanking}};
+//! # let surface: DrmSurface = todo!();
+//! let mode = cvt_mode(2560, 1440, 75.0, Blanking::Reduced).expect("Invalid mode");
+//! surface.use_custom_mode(mode).expect("Mode not supported by the driver");
+//! ```
+//!
+//! [`DrmSurface::use_custom_mode`]: super::DrmSurface::use_custom_mode
+
+use drm::control::Mode;
+
+/// Blanking intervals of a CVT mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Blanking {
+    /// Standard blanking, needed by CRTs
+    Standard,
+    /// Reduced blanking (CVT-RB), requiring a lower pixel clock for the same refresh rate
+    Reduced,
+}
+
+// granularity of horizontal timings in pixels
+const CELL_GRAN: u16 = 8;
+// granularity of the pixel clock in kHz
+const CLOCK_STEP: u32 = 250;
+const MIN_V_PORCH: u16 = 3;
+const MIN_V_BPORCH: u16 = 6;
+// standard blanking
+const MIN_VSYNC_BP: f64 = 550.0;
+const HSYNC_PERCENTAGE: u32 = 8;
+const C_PRIME: f64 = 30.0;
+const M_PRIME: f64 = 300.0;
+// reduced blanking
+const RB_MIN_VBLANK: f64 = 460.0;
+const RB_H_SYNC: u16 = 32;
+const RB_H_BLANK: u16 = 160;
+
+/// Compute a progressive mode with the given resolution and refresh rate in Hz
+///
+/// The width is rounded down to a multiple of 8 as required by the standard.
+/// Returns `None` for empty resolutions, non-positive refresh rates or timings not representable by drm.
+pub fn cvt_mode(width: u16, height: u16, refresh: f64, blanking: Blanking) -> Option<Mode> {
+    let hdisplay = width - width % CELL_GRAN;
+    if hdisplay == 0 || height == 0 || refresh.is_nan() || refresh <= 0.0 {
+        return None;
+    }
+    let vsync = vsync_width(width, height);
+    let frame_period = 1_000_000.0 / refresh;
+
+    let mut mode = drm_ffi::drm_mode_modeinfo {
+        hdisplay,
+        vdisplay: height,
+        vsync_start: height.checked_add(MIN_V_PORCH)?,
+        vsync_end: height.checked_add(MIN_V_PORCH + vsync)?,
+        type_: drm_ffi::DRM_MODE_TYPE_USERDEF,
+        ..Default::default()
+    };
+    let clock = match blanking {
+        Blanking::Standard => {
+            // estimated duration of a line in µs
+            let hperiod = (frame_period - MIN_VSYNC_BP) / (height + MIN_V_PORCH) as f64;
+            if hperiod <= 0.0 {
+                return None;
+            }
+            let vsync_bp = ((MIN_VSYNC_BP / hperiod) as u16)
+                .checked_add(1)?
+                .max(vsync + MIN_V_PORCH);
+            mode.vtotal = height.checked_add(vsync_bp.checked_add(MIN_V_PORCH)?)?;
+
+            let duty_cycle = (C_PRIME - M_PRIME * hperiod / 1000.0).max(20.0);
+            let hblank = (hdisplay as f64 * duty_cycle / (100.0 - duty_cycle)) as u16;
+            let hblank = hblank - hblank % (2 * CELL_GRAN);
+            mode.htotal = hdisplay.checked_add(hblank)?;
+            mode.hsync_end = hdisplay + hblank / 2;
+            let hsync_start = mode.hsync_end - (mode.htotal as u32 * HSYNC_PERCENTAGE / 100) as u16;
+            mode.hsync_start = hsync_start + CELL_GRAN - hsync_start % CELL_GRAN;
+            mode.flags = drm_ffi::DRM_MODE_FLAG_NHSYNC | drm_ffi::DRM_MODE_FLAG_PVSYNC;
+
+            mode.htotal as f64 * 1000.0 / hperiod
+        }
+        Blanking::Reduced => {
+            let hperiod = (frame_period - RB_MIN_VBLANK) / height as f64;
+            if hperiod <= 0.0 {
+                return None;
+            }
+            let vblank = ((RB_MIN_VBLANK / hperiod) as u16)
+                .checked_add(1)?
+                .max(MIN_V_PORCH + vsync + MIN_V_BPORCH);
+            mode.vtotal = height.checked_add(vblank)?;
+
+            mode.htotal = hdisplay.checked_add(RB_H_BLANK)?;
+            mode.hsync_end = hdisplay + RB_H_BLANK / 2;
+            mode.hsync_start = mode.hsync_end - RB_H_SYNC;
+            mode.flags = drm_ffi::DRM_MODE_FLAG_PHSYNC | drm_ffi::DRM_MODE_FLAG_NVSYNC;
+
+            refresh * mode.vtotal as f64 * mode.htotal as f64 / 1000.0
+        }
+    };
+    mode.clock = clock as u32 - clock as u32 % CLOCK_STEP;
+
+    Some(finish_mode(mode, ""))
+}
+
+// Width of the vertical sync pulse in lines, which encodes the aspect ratio
+fn vsync_width(width: u16, height: u16) -> u16 {
+    let (width, height) = (width as u32, height as u32);
+    if height % 3 == 0 && height * 4 / 3 == width {
+        4
+    } else if height % 9 == 0 && height * 16 / 9 == width {
+        5
+    } else if height % 10 == 0 && height * 16 / 10 == width {
+        6
+    } else if (height % 4 == 0 && height * 5 / 4 == width) || (height % 9 == 0 && height * 15 / 9 == width) {
+        7
+    } else {
+        10
+    }
+}
+
+/// Fills in the refresh rate and name of a mode, the name is the resolution followed by the `suffix`
+pub(super) fn finish_mode(mut mode: drm_ffi::drm_mode_modeinfo, suffix: &str) -> Mode {
+    let pixels = mode.htotal as u64 * mode.vtotal as u64;
+    if pixels != 0 {
+        mode.vrefresh = (mode.clock as f64 * 1000.0 / pixels as f64).round() as u32;
+    }
+
+    let name = format!("{}x{}{}", mode.hdisplay, mode.vdisplay, suffix);
+    // keep the terminating nul byte
+    let len = mode.name.len() - 1;
+    for (dst, src) in mode.name[..len].iter_mut().zip(name.bytes()) {
+        *dst = src as _;
+    }
+    mode.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cvt_mode, Blanking, MIN_VSYNC_BP, RB_MIN_VBLANK};
+    use drm::control::Mode;
+
+    fn timings(mode: Mode) -> (u32, [u16; 8]) {
+        let (h, v) = (mode.hsync(), mode.vsync());
+        let (width, height) = mode.size();
+        (mode.clock(), [width, h.0, h.1, h.2, height, v.0, v.1, v.2])
+    }
+
+    #[test]
+    fn cvt_modes_match_reference_modelines() {
+        // cvt 1920 1080 60
+        let mode = cvt_mode(1920, 1080, 60.0, Blanking::Standard).unwrap();
+        assert_eq!(
+            timings(mode),
+            (173000, [1920, 2048, 2248, 2576, 1080, 1083, 1088, 1120])
+        );
+        assert_eq!(mode.vrefresh(), 60);
+        assert_eq!(mode.name().to_str(), Ok("1920x1080"));
+
+        // cvt -r 1920 1080 60
+        let mode = cvt_mode(1920, 1080, 60.0, Blanking::Reduced).unwrap();
+        assert_eq!(
+            timings(mode),
+            (138500, [1920, 1968, 2000, 2080, 1080, 1083, 1088, 1111])
+        );
+
+        assert_eq!(
+            cvt_mode(1366, 768, 60.0, Blanking::Reduced).unwrap().size(),
+            (1360, 768)
+        );
+        assert!(cvt_mode(1920, 1080, 0.0, Blanking::Standard).is_none());
+        assert!(cvt_mode(4, 1080, 60.0, Blanking::Standard).is_none());
+
+        // refresh rates just below the blanking limit leave no room for a single line
+        let refresh = 1_000_000.0 / (MIN_VSYNC_BP + 1e-6);
+        assert!(cvt_mode(640, 480, refresh, Blanking::Standard).is_none());
+        let refresh = 1_000_000.0 / (RB_MIN_VBLANK + 1e-6);
+        assert!(cvt_mode(640, 480, refresh, Blanking::Reduced).is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::os::unix::io::{AsFd, BorrowedFd, OwnedFd};
+use std::sync::{Arc, Mutex};
+
+use drm::control::{connector, crtc, plane, Device as ControlDevice, RawResourceHandle};
+use rustix::fs::OFlags;
+
+use super::{DrmDeviceFd, PlaneClaim};
+use crate::backend::drm::error::{AccessError, Error};
+use crate::utils::DevPath;
+
+/// Bookkeeping of the resources of a device currently leased to other clients
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LeaseStorage {
+    leases: Arc<Mutex<HashMap<NonZeroU32, Vec<RawResourceHandle>>>>,
+}
+
+impl LeaseStorage {
+    pub(crate) fn is_leased(&self, handle: RawResourceHandle) -> bool {
+        self.leases
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .any(|leased| *leased == handle)
+    }
+
+    pub(crate) fn leased(&self) -> Vec<RawResourceHandle> {
+        self.leases.lock().unwrap().values().flatten().copied().collect()
+    }
+
+    pub(crate) fn is_active(&self, id: NonZeroU32) -> bool {
+        self.leases.lock().unwrap().contains_key(&id)
+    }
+
+    /// Leases the given objects, unless any of them is already part of another lease
+    pub(crate) fn create(
+        &self,
+        drm: &DrmDeviceFd,
+        objects: &[RawResourceHandle],
+    ) -> Result<(NonZeroU32, OwnedFd), Error> {
+        let mut leases = self.leases.lock().unwrap();
+        if let Some(handle) = find_leased(&leases, objects) {
+            return Err(Error::ResourceLeased(handle));
+        }
+
+        let (id, fd) = drm
+            .create_lease(objects, OFlags::CLOEXEC.bits())
+            .map_err(|source| {
+                Error::Access(AccessError {
+                    errmsg: "Failed to create lease",
+                    dev: drm.dev_path(),
+                    source,
+                })
+            })?;
+        leases.insert(id, objects.to_vec());
+        Ok((id, fd))
+    }
+
+    /// Revokes the lease, if it has not already ended
+    pub(crate) fn revoke(&self, drm: &DrmDeviceFd, id: NonZeroU32) -> Result<(), Error> {
+        if self.leases.lock().unwrap().remove(&id).is_none() {
+            return Ok(());
+        }
+
+        drm.revoke_lease(id).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Failed to revoke lease",
+                dev: drm.dev_path(),
+                source,
+            })
+        })
+    }
+
+    /// Forgets about leases the kernel does not consider active anymore and returns their ids
+    pub(crate) fn refresh(&self, drm: &DrmDeviceFd) -> Result<Vec<NonZeroU32>, Error> {
+        let lessees = drm.list_lessees().map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Failed to list lessees",
+                dev: drm.dev_path(),
+                source,
+            })
+        })?;
+        Ok(self.retain(&lessees))
+    }
+
+    fn retain(&self, lessees: &[NonZeroU32]) -> Vec<NonZeroU32> {
+        let mut leases = self.leases.lock().unwrap();
+        let ended = leases
+            .keys()
+            .filter(|id| !lessees.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        for id in &ended {
+            leases.remove(id);
+        }
+        ended
+    }
+}
+
+fn find_leased(
+    leases: &HashMap<NonZeroU32, Vec<RawResourceHandle>>,
+    objects: &[RawResourceHandle],
+) -> Option<RawResourceHandle> {
+    objects
+        .iter()
+        .find(|handle| leases.values().flatten().any(|leased| leased == *handle))
+        .copied()
+}
+
+/// Lease of resources of a [`DrmDevice`](super::DrmDevice)
+///
+/// The leased resources are controlled by the holder of the lease file descriptor until the lease
+/// is revoked or the lessee closes the file descriptor. In the meantime the device refuses to drive them.
+///
+/// Dropping the lease revokes it.
+#[derive(Debug)]
+pub struct DrmDeviceLease {
+    drm: DrmDeviceFd,
+    id: NonZeroU32,
+    fd: Option<OwnedFd>,
+    connectors: Vec<connector::Handle>,
+    crtcs: Vec<crtc::Handle>,
+    planes: Vec<PlaneClaim>,
+    storage: LeaseStorage,
+}
+
+impl DrmDeviceLease {
+    pub(super) fn new(
+        drm: &DrmDeviceFd,
+        storage: &LeaseStorage,
+        connectors: &[connector::Handle],
+        crtcs: &[crtc::Handle],
+        planes: Vec<PlaneClaim>,
+    ) -> Result<Self, Error> {
+        let objects = connectors
+            .iter()
+            .copied()
+            .map(RawResourceHandle::from)
+            .chain(crtcs.iter().copied().map(Into::into))
+            .chain(planes.iter().map(|claim| claim.plane().into()))
+            .collect::<Vec<_>>();
+        let (id, fd) = storage.create(drm, &objects)?;
+
+        Ok(DrmDeviceLease {
+            drm: drm.clone(),
+            id,
+            fd: Some(fd),
+            connectors: connectors.to_vec(),
+            crtcs: crtcs.to_vec(),
+            planes,
+            storage: storage.clone(),
+        })
+    }
+
+    /// Id of the lessee
+    pub fn id(&self) -> u32 {
+        self.id.get()
+    }
+
+    /// Connectors being leased
+    pub fn connectors(&self) -> &[connector::Handle] {
+        &self.connectors
+    }
+
+    /// CRTCs being leased
+    pub fn crtcs(&self) -> &[crtc::Handle] {
+        &self.crtcs
+    }
+
+    /// Planes being leased
+    pub fn planes(&self) -> impl Iterator<Item = plane::Handle> + '_ {
+        self.planes.iter().map(|claim| claim.plane())
+    }
+
+    /// File descriptor to be handed to the lessee, if not taken already
+    pub fn fd(&self) -> Option<BorrowedFd<'_>> {
+        self.fd.as_ref().map(|fd| fd.as_fd())
+    }
+
+    /// Takes the file descriptor to be handed to the lessee
+    pub fn take_fd(&mut self) -> Option<OwnedFd> {
+        self.fd.take()
+    }
+
+    /// Returns whether the lease is still active
+    ///
+    /// A lease ended by the lessee is only noticed after
+    /// [`DrmDevice::refresh_leases`](super::DrmDevice::refresh_leases).
+    pub fn is_active(&self) -> bool {
+        self.storage.is_active(self.id)
+    }
+
+    /// Revokes the lease, making the resources available to the device again
+    pub fn revoke(self) -> Result<(), Error> {
+        self.storage.revoke(&self.drm, self.id)
+    }
+}
+
+impl Drop for DrmDeviceLease {
+    fn drop(&mut self) {
+        if let Err(err) = self.storage.revoke(&self.drm, self.id) {
+            tracing::warn!(?err, lease = self.id.get(), "Error revoking lease");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_leased, LeaseStorage};
+    use drm::control::RawResourceHandle;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn ended_leases_release_their_resources() {
+        let handle = |id| RawResourceHandle::new(id).unwrap();
+        let storage = LeaseStorage::default();
+        let (first, second) = (NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap());
+        {
+            let mut leases = storage.leases.lock().unwrap();
+            leases.insert(first, vec![handle(10), handle(11)]);
+            leases.insert(second, vec![handle(20)]);
+            assert_eq!(find_leased(&leases, &[handle(12), handle(11)]), Some(handle(11)));
+            assert_eq!(find_leased(&leases, &[handle(12)]), None);
+        }
+
+        assert!(storage.is_leased(handle(10)));
+        assert_eq!(storage.retain(&[second]), vec![first]);
+        assert!(!storage.is_leased(handle(10)));
+        assert!(storage.is_leased(handle(20)));
+        assert!(storage.is_active(second));
+    }
+}
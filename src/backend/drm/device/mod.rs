@@ -6,19 +6,31 @@ use std::sync::{atomic::AtomicBool, Arc, Mutex, Weak};
 use std::time::{Duration, SystemTime};
 
 use calloop::{EventSource, Interest, Poll, PostAction, Readiness, Token, TokenFactory};
-use drm::control::{connector, crtc, plane, Device as ControlDevice, Event, Mode, ResourceHandles};
+use drm::control::{
+    connector, crtc, plane, Device as ControlDevice, Event, Mode, RawResourceHandle, ResourceHandles,
+};
 use drm::{ClientCapability, Device as BasicDevice, DriverCapability};
 use libc::dev_t;
 
 pub(super) mod atomic;
 mod fd;
 pub use self::fd::DrmDeviceFd;
+mod lease;
 pub(super) mod legacy;
+pub use self::lease::DrmDeviceLease;
+pub(crate) use self::lease::LeaseStorage;
 use crate::utils::{Buffer, DevPath, Size};
 
 use super::error::AccessError;
 use super::surface::{atomic::AtomicDrmSurface, legacy::LegacyDrmSurface, DrmSurface, DrmSurfaceInternal};
-use super::{error::Error, planes, Planes};
+use super::{
+    edid::{connector_edid_blob, Edid},
+    error::Error,
+    hotplug::{ConnectorEvent, ConnectorTracker},
+    planes,
+    writeback::{writeback_connectors, WritebackConnector},
+    Planes,
+};
 use atomic::AtomicDrmDevice;
 use legacy::LegacyDrmDevice;
 
@@ -116,6 +128,8 @@ pub struct DrmDevice {
     cursor_size: Size<u32, Buffer>,
     resources: ResourceHandles,
     plane_claim_storage: PlaneClaimStorage,
+    leases: LeaseStorage,
+    connectors: ConnectorTracker,
     surfaces: Vec<Weak<DrmSurfaceInternal>>,
 }
 
@@ -227,6 +241,8 @@ impl DrmDevice {
                 cursor_size,
                 resources,
                 plane_claim_storage: Default::default(),
+                leases: Default::default(),
+                connectors: Default::default(),
                 surfaces: Default::default(),
             },
             DrmDeviceNotifier {
@@ -287,6 +303,122 @@ impl DrmDevice {
         self.plane_claim_storage.claim(plane, crtc)
     }
 
+    /// Lease connectors, crtcs and planes of the device to another client,
+    /// e.g. for the `wp-drm-lease` protocol
+    ///
+    /// Surfaces driving any of the resources should be dropped beforehand. Until the lease ends
+    /// the device refuses to create surfaces for or attach connectors to the leased resources.
+    ///
+    /// Returns [`Error::ResourceLeased`] if any of the resources is already part of another lease.
+    pub fn create_lease(
+        &self,
+        connectors: &[connector::Handle],
+        crtcs: &[crtc::Handle],
+        planes: impl IntoIterator<Item = PlaneClaim>,
+    ) -> Result<DrmDeviceLease, Error> {
+        DrmDeviceLease::new(
+            self.device_fd(),
+            &self.leases,
+            connectors,
+            crtcs,
+            planes.into_iter().collect(),
+        )
+    }
+
+    /// Returns whether the given connector, crtc or plane is currently leased
+    pub fn is_leased(&self, handle: impl Into<RawResourceHandle>) -> bool {
+        self.leases.is_leased(handle.into())
+    }
+
+    /// Returns the connectors which are currently leased
+    pub fn leased_connectors(&self) -> Vec<connector::Handle> {
+        self.leased()
+            .filter(|handle| self.resources.connectors().contains(handle))
+            .collect()
+    }
+
+    /// Returns the crtcs which are currently leased
+    pub fn leased_crtcs(&self) -> Vec<crtc::Handle> {
+        self.leased()
+            .filter(|handle| self.resources.crtcs().contains(handle))
+            .collect()
+    }
+
+    fn leased<T: From<RawResourceHandle>>(&self) -> impl Iterator<Item = T> {
+        self.leases.leased().into_iter().map(T::from)
+    }
+
+    /// Forgets about leases, which were ended by their lessee closing the lease file descriptor
+    ///
+    /// This should be called on hotplug events and before reclaiming leased resources.
+    /// Returns the ids of the ended leases.
+    pub fn refresh_leases(&self) -> Result<Vec<u32>, Error> {
+        let ended = self.leases.refresh(self.device_fd())?;
+        Ok(ended.into_iter().map(|id| id.get()).collect())
+    }
+
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) fn lease_storage(&self) -> &LeaseStorage {
+        &self.leases
+    }
+
+    /// Scan the connectors of the device and return what changed since the last scan
+    ///
+    /// Should be called initially and on every udev change event of the device. The first scan reports all
+    /// connected connectors as added. See the [`hotplug`](super::hotplug) module for details.
+    pub fn scan_connectors(&mut self) -> Result<Vec<ConnectorEvent>, Error> {
+        // connectors of DisplayPort MST hubs come and go
+        self.resources = self.resource_handles().map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Error loading resource handles",
+                dev: self.dev_path(),
+                source,
+            })
+        })?;
+        let fd = self.internal.device_fd();
+        self.connectors.scan(fd).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Error scanning connectors",
+                dev: fd.dev_path(),
+                source,
+            })
+        })
+    }
+
+    /// Returns the parsed EDID of the display connected to the given connector, if available
+    pub fn connector_edid(&self, connector: connector::Handle) -> Result<Option<Edid>, Error> {
+        let edid = connector_edid_blob(self, connector)?;
+        Ok(edid.and_then(|edid| Edid::parse(&edid)))
+    }
+
+    /// Returns the connected connectors as of the last [`DrmDevice::scan_connectors`]
+    pub fn connectors(&self) -> &ConnectorTracker {
+        &self.connectors
+    }
+
+    /// Enables and returns the writeback connectors of the device
+    ///
+    /// Writeback connectors require atomic modesetting, the list is empty for legacy devices and drivers
+    /// without writeback support. Once enabled they are part of the resource handles of the device.
+    /// See the [`writeback`](super::writeback) module for details.
+    pub fn writeback_connectors(&self) -> Result<Vec<WritebackConnector>, Error> {
+        if !self.is_atomic()
+            || self
+                .set_client_capability(ClientCapability::WritebackConnectors, true)
+                .is_err()
+        {
+            return Ok(Vec::new());
+        }
+
+        writeback_connectors(self).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Error loading writeback connectors",
+                dev: self.dev_path(),
+                source,
+            })
+        })
+    }
+
     /// Returns the size of the hardware cursor
     ///
     /// Note: In case of universal planes this is the
@@ -327,6 +459,13 @@ impl DrmDevice {
             return Err(Error::DeviceInactive);
         }
 
+        if let Some(handle) = std::iter::once(RawResourceHandle::from(crtc))
+            .chain(connectors.iter().map(|conn| (*conn).into()))
+            .find(|handle| self.leases.is_leased(*handle))
+        {
+            return Err(Error::ResourceLeased(handle));
+        }
+
         let planes = self.planes(&crtc)?;
 
         let selected_primary_plane = planes.primary.iter().find_map(|plane| {
@@ -396,6 +535,7 @@ impl DrmDevice {
             planes,
             internal,
             plane_claim_storage: self.plane_claim_storage.clone(),
+            leases: self.leases.clone(),
             primary_plane: (plane, claim),
         })
     }
@@ -482,6 +622,26 @@ impl DrmDevice {
         Ok(())
     }
 
+    /// Forces all known surfaces to restore their full state with the next commit
+    ///
+    /// Call this after the system resumed from suspend, as many drivers lose the state of
+    /// the display pipeline in the meantime.
+    /// See [`DrmSurface::restore_state`](super::DrmSurface::restore_state).
+    pub fn restore_state(&mut self) -> Result<(), Error> {
+        if !self.is_active() {
+            return Err(Error::DeviceInactive);
+        }
+
+        self.surfaces.retain(|surface| surface.strong_count() != 0);
+        for surface in self.surfaces.iter().filter_map(|surface| surface.upgrade()) {
+            match &*surface {
+                DrmSurfaceInternal::Atomic(surf) => surf.restore_state(),
+                DrmSurfaceInternal::Legacy(surf) => surf.restore_state()?,
+            }
+        }
+        Ok(())
+    }
+
     fn set_active(&self, active: bool) -> bool {
         match &*self.internal {
             DrmDeviceInternal::Atomic(internal) => internal.active.swap(active, Ordering::SeqCst),
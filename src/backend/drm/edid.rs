@@ -0,0 +1,312 @@
+//! Parser for the EDID of displays
+//!
+//! Displays describe themselves through their *Extended Display Identification Data*, which the kernel
+//! exposes as the `EDID` property of a connector. [`Edid`] decodes the identification of the display,
+//! needed for stable make, model and serial strings of outputs, as well as its physical size,
+//! color primaries, detailed timings and HDR capabilities.
+//!
+//! The EDID of a connector can be read with [`DrmDevice::connector_edid`] or [`DrmSurface::connector_edid`]
+//! and is also part of the [`ConnectorInfo`] of hotplug events.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::DrmDevice;
+//! # use smithay::output::{Output, Subpixel};
+//! # let device: DrmDevice = todo!();
+//! # let connector = todo!();
+//! if let Some(edid) = device.connector_edid(connector).unwrap() {
+//!     let output = Output::new("DP-1".into(), edid.physical_properties(Subpixel::Unknown));
+//!     let mode = edid.preferred_mode();
+//! }
+//! ```
+//!
+//! [`DrmDevice::connector_edid`]: super::DrmDevice::connector_edid
+//! [`DrmSurface::connector_edid`]: super::DrmSurface::connector_edid
+//! [`ConnectorInfo`]: super::hotplug::ConnectorInfo
+
+use drm::control::{connector, Device as ControlDevice, Mode};
+
+use super::{
+    error::{AccessError, Error},
+    hdr::HdrCapabilities,
+};
+use crate::{
+    backend::renderer::color_transform::Primaries,
+    output::{PhysicalProperties, Subpixel},
+    utils::DevPath,
+};
+
+/// Decoded EDID of a display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edid {
+    /// Three letter PNP id of the manufacturer, e.g. `DEL`
+    pub manufacturer: String,
+    /// Product code assigned by the manufacturer
+    pub product_code: u16,
+    /// Numeric serial number, if set
+    pub serial_number: Option<u32>,
+    /// Serial number from the display descriptors, if present
+    pub serial: Option<String>,
+    /// Product name from the display descriptors, if present
+    pub name: Option<String>,
+    /// Year of manufacture or the model year, if known
+    pub year: Option<u16>,
+    /// Version and revision of the EDID structure
+    pub version: (u8, u8),
+    /// Physical size of the display in millimeters, if known
+    pub physical_size: Option<(u32, u32)>,
+    /// Chromaticity coordinates of the primaries and the white point of the display
+    pub primaries: Primaries,
+    /// Modes described by the detailed timings of the base block, the first one is the preferred mode
+    pub modes: Vec<Mode>,
+    /// HDR capabilities advertised by the CTA-861 extension, if any
+    pub hdr: Option<HdrCapabilities>,
+}
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const EDID_BLOCK_SIZE: usize = 128;
+const DESCRIPTOR_SERIAL: u8 = 0xff;
+const DESCRIPTOR_NAME: u8 = 0xfc;
+
+impl Edid {
+    /// Parse an EDID blob
+    ///
+    /// Returns `None` if the blob does not start with a valid EDID base block.
+    /// Checksums are not verified, as quite a few displays get them wrong.
+    pub fn parse(edid: &[u8]) -> Option<Edid> {
+        let base = edid.get(..EDID_BLOCK_SIZE)?;
+        if base[..8] != EDID_HEADER {
+            return None;
+        }
+
+        let id = u16::from_be_bytes([base[8], base[9]]);
+        let manufacturer = [10, 5, 0]
+            .into_iter()
+            .map(|shift| char::from(b'A' - 1 + ((id >> shift) & 0x1f) as u8))
+            .collect();
+        let serial_number =
+            Some(u32::from_le_bytes([base[12], base[13], base[14], base[15]])).filter(|s| *s != 0);
+        let year = (base[17] != 0).then_some(1990 + base[17] as u16);
+        let physical_size =
+            (base[21] != 0 && base[22] != 0).then_some((base[21] as u32 * 10, base[22] as u32 * 10));
+
+        let mut parsed = Edid {
+            manufacturer,
+            product_code: u16::from_le_bytes([base[10], base[11]]),
+            serial_number,
+            serial: None,
+            name: None,
+            year,
+            version: (base[18], base[19]),
+            physical_size,
+            primaries: decode_primaries(&base[25..35]),
+            modes: Vec::new(),
+            hdr: HdrCapabilities::from_edid(edid),
+        };
+        for descriptor in base[54..126].chunks_exact(18) {
+            if descriptor[0] != 0 || descriptor[1] != 0 {
+                parsed.modes.extend(decode_detailed_timing(descriptor));
+                continue;
+            }
+            match descriptor[3] {
+                DESCRIPTOR_SERIAL => parsed.serial = decode_string(&descriptor[5..]),
+                DESCRIPTOR_NAME => parsed.name = decode_string(&descriptor[5..]),
+                _ => {}
+            }
+        }
+        Some(parsed)
+    }
+
+    /// Make of the display, the PNP id of the manufacturer
+    pub fn make(&self) -> String {
+        self.manufacturer.clone()
+    }
+
+    /// Model of the display, the product name or the product code, if no name is set
+    pub fn model(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("0x{:04X}", self.product_code))
+    }
+
+    /// Serial number of the display, preferring the serial number descriptor
+    pub fn serial(&self) -> Option<String> {
+        self.serial
+            .clone()
+            .or_else(|| self.serial_number.map(|serial| serial.to_string()))
+    }
+
+    /// Mode preferred by the display
+    pub fn preferred_mode(&self) -> Option<Mode> {
+        self.modes.first().copied()
+    }
+
+    /// Physical properties of an [`Output`](crate::output::Output) for the display
+    pub fn physical_properties(&self, subpixel: Subpixel) -> PhysicalProperties {
+        let (w, h) = self.physical_size.unwrap_or((0, 0));
+        PhysicalProperties {
+            size: (w as i32, h as i32).into(),
+            subpixel,
+            make: self.make(),
+            model: self.model(),
+            serial_number: self.serial().unwrap_or_else(|| "Unknown".into()),
+        }
+    }
+}
+
+// Chromaticity coordinates are stored as 10 bit fractions, the two low bits of all eight values come first
+fn decode_primaries(data: &[u8]) -> Primaries {
+    let coordinate = |index: usize| {
+        let low = (data[index / 4] >> (6 - 2 * (index % 4))) & 0b11;
+        ((data[2 + index] as u16) << 2 | low as u16) as f64 / 1024.0
+    };
+    Primaries {
+        red: (coordinate(0), coordinate(1)),
+        green: (coordinate(2), coordinate(3)),
+        blue: (coordinate(4), coordinate(5)),
+        white: (coordinate(6), coordinate(7)),
+    }
+}
+
+fn decode_string(data: &[u8]) -> Option<String> {
+    let end = data.iter().position(|b| *b == b'\n').unwrap_or(data.len());
+    let string = String::from_utf8_lossy(&data[..end]).trim().to_string();
+    (!string.is_empty()).then_some(string)
+}
+
+fn decode_detailed_timing(dtd: &[u8]) -> Option<Mode> {
+    let clock = u16::from_le_bytes([dtd[0], dtd[1]]) as u32 * 10;
+    let hdisplay = dtd[2] as u16 | ((dtd[4] as u16 & 0xf0) << 4);
+    let hblank = dtd[3] as u16 | ((dtd[4] as u16 & 0x0f) << 8);
+    let vdisplay = dtd[5] as u16 | ((dtd[7] as u16 & 0xf0) << 4);
+    let vblank = dtd[6] as u16 | ((dtd[7] as u16 & 0x0f) << 8);
+    let hsync_offset = dtd[8] as u16 | ((dtd[11] as u16 & 0xc0) << 2);
+    let hsync_width = dtd[9] as u16 | ((dtd[11] as u16 & 0x30) << 4);
+    let vsync_offset = (dtd[10] as u16 >> 4) | ((dtd[11] as u16 & 0x0c) << 2);
+    let vsync_width = (dtd[10] as u16 & 0x0f) | ((dtd[11] as u16 & 0x03) << 4);
+    if hdisplay == 0 || vdisplay == 0 {
+        return None;
+    }
+
+    let mut mode = drm_ffi::drm_mode_modeinfo {
+        clock,
+        hdisplay,
+        hsync_start: hdisplay + hsync_offset,
+        hsync_end: hdisplay + hsync_offset + hsync_width,
+        htotal: hdisplay + hblank,
+        vdisplay,
+        vsync_start: vdisplay + vsync_offset,
+        vsync_end: vdisplay + vsync_offset + vsync_width,
+        vtotal: vdisplay + vblank,
+        type_: drm_ffi::DRM_MODE_TYPE_DRIVER,
+        ..Default::default()
+    };
+    // digital separate sync signals their polarities, analog ones are negative
+    let flags = dtd[17];
+    if flags & 0x18 == 0x18 {
+        mode.flags |= if flags & 0x02 != 0 {
+            drm_ffi::DRM_MODE_FLAG_PHSYNC
+        } else {
+            drm_ffi::DRM_MODE_FLAG_NHSYNC
+        };
+        mode.flags |= if flags & 0x04 != 0 {
+            drm_ffi::DRM_MODE_FLAG_PVSYNC
+        } else {
+            drm_ffi::DRM_MODE_FLAG_NVSYNC
+        };
+    } else {
+        mode.flags |= drm_ffi::DRM_MODE_FLAG_NHSYNC | drm_ffi::DRM_MODE_FLAG_NVSYNC;
+    }
+    // interlaced timings describe a single field
+    if flags & 0x80 != 0 {
+        mode.flags |= drm_ffi::DRM_MODE_FLAG_INTERLACE;
+        mode.vdisplay *= 2;
+        mode.vsync_start *= 2;
+        mode.vsync_end *= 2;
+        mode.vtotal = (mode.vtotal * 2) | 1;
+    }
+    let suffix = if flags & 0x80 != 0 { "i" } else { "" };
+    Some(super::cvt::finish_mode(mode, suffix))
+}
+
+/// Reads the raw EDID blob of a connector, if the connector exposes one
+pub(super) fn connector_edid_blob(
+    drm: &(impl ControlDevice + DevPath),
+    connector: connector::Handle,
+) -> Result<Option<Vec<u8>>, Error> {
+    let access_error = |errmsg| {
+        move |source| {
+            Error::Access(AccessError {
+                errmsg,
+                dev: drm.dev_path(),
+                source,
+            })
+        }
+    };
+
+    let props = drm
+        .get_properties(connector)
+        .map_err(access_error("Failed to get properties of connector"))?;
+    let (ids, vals) = props.as_props_and_values();
+    for (&id, &val) in ids.iter().zip(vals.iter()) {
+        let info = drm
+            .get_property(id)
+            .map_err(access_error("Failed to get property info"))?;
+        if info.name().to_str() != Ok("EDID") {
+            continue;
+        }
+        let Some(blob) = info
+            .value_type()
+            .convert_value(val)
+            .as_blob()
+            .filter(|blob| *blob != 0)
+        else {
+            return Ok(None);
+        };
+        return drm
+            .get_property_blob(blob)
+            .map(Some)
+            .map_err(access_error("Failed to get EDID blob"));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Edid;
+
+    #[test]
+    fn edid_identification_and_timings_are_parsed() {
+        let mut edid = [0u8; 128];
+        edid[..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+        // "DEL", product code 0xa0ec, serial 1234, 2020
+        edid[8..18].copy_from_slice(&[0x10, 0xac, 0xec, 0xa0, 0xd2, 0x04, 0x00, 0x00, 0x01, 0x1e]);
+        edid[18..20].copy_from_slice(&[1, 4]);
+        edid[21..23].copy_from_slice(&[60, 34]);
+        // red x = 0.640 (655 / 1024)
+        edid[25] = 0b1100_0000;
+        edid[27] = 0xa3;
+        // 1920x1080@60 with positive sync
+        let timing = [
+            0x02, 0x3a, 0x80, 0x18, 0x71, 0x38, 0x2d, 0x40, 0x58, 0x2c, 0x45, 0x00, 0x56, 0x50, 0x21, 0x00,
+            0x00, 0x1e,
+        ];
+        edid[54..72].copy_from_slice(&timing);
+        edid[72..77].copy_from_slice(&[0, 0, 0, 0xfc, 0]);
+        edid[77..90].copy_from_slice(b"DELL U2720Q\n ");
+
+        let parsed = Edid::parse(&edid).unwrap();
+        assert_eq!(parsed.make(), "DEL");
+        assert_eq!(parsed.model(), "DELL U2720Q");
+        assert_eq!(parsed.serial(), Some("1234".into()));
+        assert_eq!(parsed.year, Some(2020));
+        assert_eq!(parsed.physical_size, Some((600, 340)));
+        assert_eq!(parsed.primaries.red.0, 655.0 / 1024.0);
+
+        let mode = parsed.preferred_mode().unwrap();
+        assert_eq!(mode.size(), (1920, 1080));
+        assert_eq!(mode.vrefresh(), 60);
+        assert_eq!(mode.clock(), 148500);
+        assert_eq!(mode.name().to_str(), Ok("1920x1080"));
+        assert!(Edid::parse(&edid[..100]).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::backend::SwapBuffersError;
+use crate::backend::{drm::hdr::Colorspace, SwapBuffersError};
 use drm::control::{connector, crtc, plane, Mode, RawResourceHandle};
 use std::{
     io::{self, ErrorKind},
@@ -74,6 +74,18 @@ pub enum Error {
         /// Property name
         name: &'static str,
     },
+    /// The colorspace is not supported by the connector
+    #[error("Colorspace `{1:?}` is not supported by connector `{0:?}`")]
+    UnsupportedColorspace(connector::Handle, Colorspace),
+    /// The size of a lookup table does not match the size supported by the crtc
+    #[error("Lookup table with {1} entries does not match the supported size of {0} entries")]
+    InvalidLutSize(u32, usize),
+    /// Writeback connectors are not supported by legacy devices
+    #[error("Writeback connectors are not supported by legacy devices")]
+    WritebackNotSupported,
+    /// The resource is leased to another client
+    #[error("Resource `{0:?}` is leased to another client")]
+    ResourceLeased(RawResourceHandle),
     /// Atomic Test failed for new properties
     #[error("Atomic Test failed for new properties on crtc ({0:?})")]
     TestFailed(crtc::Handle),
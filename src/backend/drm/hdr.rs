@@ -0,0 +1,332 @@
+//! Types for signalling HDR content to displays
+//!
+//! [`HdrMetadata`] describes the mastering display and the light levels of the content shown on an output.
+//! It is sent to the display through the `HDR_OUTPUT_METADATA` connector property, while the
+//! [`Colorspace`] tells the display how to interpret the pixels, see [`DrmSurface::set_hdr_metadata`]
+//! and [`DrmSurface::set_colorspace`]. Both require a modeset, so the changes are applied with the next
+//! [`commit`](DrmSurface::commit).
+//!
+//! What a display supports can be queried with [`DrmSurface::connector_hdr_capabilities`], which parses
+//! the CTA-861 extension blocks of the EDID of the display.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmSurface, hdr::{Colorspace, Eotf, HdrMetadata}};
+//! # let surface: DrmSurface = todo!();
+//! # let connector = todo!();
+//! let capabilities = surface.connector_hdr_capabilities(connector).unwrap();
+//! if capabilities.is_some_and(|caps| caps.supports_eotf(Eotf::Pq) && caps.bt2020) {
+//!     surface.set_hdr_metadata(Some(HdrMetadata::new(Eotf::Pq))).unwrap();
+//!     surface.set_colorspace(Colorspace::Bt2020Rgb).unwrap();
+//! }
+//! ```
+//!
+//! [`DrmSurface::set_hdr_metadata`]: super::DrmSurface::set_hdr_metadata
+//! [`DrmSurface::set_colorspace`]: super::DrmSurface::set_colorspace
+//! [`DrmSurface::connector_hdr_capabilities`]: super::DrmSurface::connector_hdr_capabilities
+//! [`DrmSurface::commit`]: super::DrmSurface::commit
+
+use crate::backend::renderer::color_transform::Primaries;
+
+/// Electro-optical transfer function of the content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Eotf {
+    /// Traditional gamma with the luminance range of sdr displays
+    TraditionalSdr,
+    /// Traditional gamma with the luminance range of hdr displays
+    TraditionalHdr,
+    /// SMPTE ST 2084, also known as perceptual quantizer
+    Pq,
+    /// Hybrid log-gamma as defined by ITU-R BT.2100
+    Hlg,
+}
+
+impl Eotf {
+    fn from_raw(raw: u8) -> Option<Eotf> {
+        match raw {
+            0 => Some(Eotf::TraditionalSdr),
+            1 => Some(Eotf::TraditionalHdr),
+            2 => Some(Eotf::Pq),
+            3 => Some(Eotf::Hlg),
+            _ => None,
+        }
+    }
+
+    fn to_raw(self) -> u8 {
+        match self {
+            Eotf::TraditionalSdr => 0,
+            Eotf::TraditionalHdr => 1,
+            Eotf::Pq => 2,
+            Eotf::Hlg => 3,
+        }
+    }
+}
+
+/// Static HDR metadata of the content shown on an output
+///
+/// Luminance values are given in cd/m², values of `0` are treated as unknown by displays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrMetadata {
+    /// Transfer function of the content
+    pub eotf: Eotf,
+    /// Primaries and white point of the mastering display
+    pub primaries: Primaries,
+    /// Maximum luminance of the mastering display
+    pub max_mastering_luminance: f64,
+    /// Minimum luminance of the mastering display
+    pub min_mastering_luminance: f64,
+    /// Maximum luminance of a single pixel of the content (MaxCLL)
+    pub max_content_light_level: f64,
+    /// Maximum average luminance of a single frame of the content (MaxFALL)
+    pub max_frame_average_light_level: f64,
+}
+
+impl HdrMetadata {
+    /// Create metadata for content using the given transfer function with BT.2020 primaries
+    /// and unknown luminance values
+    pub fn new(eotf: Eotf) -> Self {
+        HdrMetadata {
+            eotf,
+            primaries: Primaries::BT2020,
+            max_mastering_luminance: 0.0,
+            min_mastering_luminance: 0.0,
+            max_content_light_level: 0.0,
+            max_frame_average_light_level: 0.0,
+        }
+    }
+
+    pub(super) fn to_raw(self) -> drm_ffi::hdr_output_metadata {
+        // chromaticity coordinates are encoded in units of 0.00002
+        let xy = |(x, y): (f64, f64)| {
+            let encode = |c: f64| (c.clamp(0.0, 1.0) * 50000.0).round() as u16;
+            (encode(x), encode(y))
+        };
+        let primary = |c| {
+            let (x, y) = xy(c);
+            drm_ffi::hdr_metadata_infoframe__bindgen_ty_1 { x, y }
+        };
+        let (white_x, white_y) = xy(self.primaries.white);
+        let luminance = |l: f64| l.clamp(0.0, u16::MAX as f64).round() as u16;
+
+        drm_ffi::hdr_output_metadata {
+            // HDMI_STATIC_METADATA_TYPE1
+            metadata_type: 0,
+            __bindgen_anon_1: drm_ffi::hdr_output_metadata__bindgen_ty_1 {
+                hdmi_metadata_type1: drm_ffi::hdr_metadata_infoframe {
+                    eotf: self.eotf.to_raw(),
+                    metadata_type: 0,
+                    display_primaries: [
+                        primary(self.primaries.red),
+                        primary(self.primaries.green),
+                        primary(self.primaries.blue),
+                    ],
+                    white_point: drm_ffi::hdr_metadata_infoframe__bindgen_ty_2 {
+                        x: white_x,
+                        y: white_y,
+                    },
+                    max_display_mastering_luminance: luminance(self.max_mastering_luminance),
+                    // the minimum luminance is encoded in units of 0.0001 cd/m²
+                    min_display_mastering_luminance: luminance(self.min_mastering_luminance * 10000.0),
+                    max_cll: luminance(self.max_content_light_level),
+                    max_fall: luminance(self.max_frame_average_light_level),
+                },
+            },
+        }
+    }
+
+    pub(super) fn from_raw(raw: &drm_ffi::hdr_output_metadata) -> Option<Self> {
+        if raw.metadata_type != 0 {
+            return None;
+        }
+        // SAFETY: the union only has a single variant for metadata type 1
+        let info = unsafe { raw.__bindgen_anon_1.hdmi_metadata_type1 };
+        let xy = |x: u16, y: u16| (x as f64 / 50000.0, y as f64 / 50000.0);
+        let [red, green, blue] = info.display_primaries.map(|p| xy(p.x, p.y));
+        Some(HdrMetadata {
+            eotf: Eotf::from_raw(info.eotf)?,
+            primaries: Primaries {
+                red,
+                green,
+                blue,
+                white: xy(info.white_point.x, info.white_point.y),
+            },
+            max_mastering_luminance: info.max_display_mastering_luminance as f64,
+            min_mastering_luminance: info.min_display_mastering_luminance as f64 / 10000.0,
+            max_content_light_level: info.max_cll as f64,
+            max_frame_average_light_level: info.max_fall as f64,
+        })
+    }
+}
+
+/// Colorspace of the pixels sent to a display, set through the `Colorspace` connector property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Colorspace {
+    /// Colorspace chosen by the driver, usually sRGB
+    #[default]
+    Default,
+    /// ITU-R BT.709 YCbCr
+    Bt709Ycc,
+    /// ITU-R BT.2020 RGB
+    Bt2020Rgb,
+    /// ITU-R BT.2020 YCbCr
+    Bt2020Ycc,
+    /// DCI-P3 RGB with a D65 white point
+    DciP3RgbD65,
+    /// opRGB as defined by IEC 61966-2-5
+    OpRgb,
+}
+
+impl Colorspace {
+    /// Name of the colorspace as used by the `Colorspace` connector property
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colorspace::Default => "Default",
+            Colorspace::Bt709Ycc => "BT709_YCC",
+            Colorspace::Bt2020Rgb => "BT2020_RGB",
+            Colorspace::Bt2020Ycc => "BT2020_YCC",
+            Colorspace::DciP3RgbD65 => "DCI-P3_RGB_D65",
+            Colorspace::OpRgb => "opRGB",
+        }
+    }
+
+    /// Returns the colorspace with the given property name
+    pub fn from_name(name: &str) -> Option<Colorspace> {
+        [
+            Colorspace::Default,
+            Colorspace::Bt709Ycc,
+            Colorspace::Bt2020Rgb,
+            Colorspace::Bt2020Ycc,
+            Colorspace::DciP3RgbD65,
+            Colorspace::OpRgb,
+        ]
+        .into_iter()
+        .find(|colorspace| colorspace.name() == name)
+    }
+}
+
+/// HDR capabilities of a display as advertised in its EDID
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HdrCapabilities {
+    /// Transfer functions supported by the display
+    pub eotfs: Vec<Eotf>,
+    /// Desired maximum luminance of the content in cd/m², if known
+    pub max_luminance: Option<f64>,
+    /// Desired maximum frame-average luminance of the content in cd/m², if known
+    pub max_frame_average_luminance: Option<f64>,
+    /// Desired minimum luminance of the content in cd/m², if known
+    pub min_luminance: Option<f64>,
+    /// Whether the display supports the BT.2020 RGB colorimetry
+    pub bt2020: bool,
+    /// Whether the display supports the DCI-P3 colorimetry
+    pub dci_p3: bool,
+}
+
+impl HdrCapabilities {
+    /// Parse the HDR capabilities from the CTA-861 extension blocks of an EDID
+    ///
+    /// Returns `None` if the EDID does not contain a HDR static metadata block.
+    pub fn from_edid(edid: &[u8]) -> Option<Self> {
+        let mut capabilities = HdrCapabilities::default();
+        let mut found = false;
+        for (tag, data) in cta_data_blocks(edid) {
+            match tag {
+                CTA_COLORIMETRY_BLOCK => {
+                    capabilities.bt2020 = data.first().is_some_and(|b| b & 0x80 != 0);
+                    capabilities.dci_p3 = data.get(1).is_some_and(|b| b & 0x80 != 0);
+                }
+                CTA_HDR_STATIC_METADATA_BLOCK if !data.is_empty() => {
+                    found = true;
+                    capabilities.eotfs = (0..4)
+                        .filter(|bit| data[0] & (1 << bit) != 0)
+                        .filter_map(Eotf::from_raw)
+                        .collect();
+                    let max = data.get(2).filter(|&&cv| cv != 0).map(|&cv| decode_luminance(cv));
+                    capabilities.max_luminance = max;
+                    capabilities.max_frame_average_luminance =
+                        data.get(3).filter(|&&cv| cv != 0).map(|&cv| decode_luminance(cv));
+                    capabilities.min_luminance = max.zip(data.get(4)).map(|(max, &cv)| {
+                        let cv = cv as f64 / 255.0;
+                        max * cv * cv / 100.0
+                    });
+                }
+                _ => {}
+            }
+        }
+        found.then_some(capabilities)
+    }
+
+    /// Returns whether the display supports the given transfer function
+    pub fn supports_eotf(&self, eotf: Eotf) -> bool {
+        self.eotfs.contains(&eotf)
+    }
+}
+
+const EDID_BLOCK_SIZE: usize = 128;
+const CTA_EXTENSION_TAG: u8 = 0x02;
+const CTA_EXTENDED_TAG: u8 = 7;
+const CTA_COLORIMETRY_BLOCK: u8 = 5;
+const CTA_HDR_STATIC_METADATA_BLOCK: u8 = 6;
+
+// Luminance encoded as in the HDR static metadata block of CTA-861
+fn decode_luminance(cv: u8) -> f64 {
+    50.0 * 2f64.powf(cv as f64 / 32.0)
+}
+
+// Iterate over the extended data blocks of all CTA-861 extensions, yielding the extended tag and payload
+fn cta_data_blocks(edid: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    edid.chunks_exact(EDID_BLOCK_SIZE)
+        .skip(1)
+        .filter(|block| block[0] == CTA_EXTENSION_TAG)
+        .flat_map(|block| {
+            // the data block collection ends where the detailed timing descriptors start
+            let end = (block[2] as usize).clamp(4, EDID_BLOCK_SIZE - 1);
+            let mut data = &block[4..end];
+            std::iter::from_fn(move || loop {
+                let (&header, rest) = data.split_first()?;
+                let len = (header & 0x1f) as usize;
+                if rest.len() < len {
+                    return None;
+                }
+                let (payload, rest) = rest.split_at(len);
+                data = rest;
+                if header >> 5 == CTA_EXTENDED_TAG && !payload.is_empty() {
+                    return Some((payload[0], &payload[1..]));
+                }
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Eotf, HdrCapabilities, HdrMetadata};
+
+    #[test]
+    fn hdr_capabilities_are_parsed_from_edid() {
+        let mut edid = vec![0u8; 256];
+        let cta = &mut edid[128..];
+        cta[0] = 0x02;
+        cta[1] = 0x03;
+        // colorimetry block with BT2020_RGB
+        let colorimetry = [0xe3, 0x05, 0x80, 0x00];
+        // hdr static metadata block with sdr, pq and hlg
+        let hdr = [0xe6, 0x06, 0x0d, 0x01, 0x60, 0x40, 0x00];
+        cta[4..8].copy_from_slice(&colorimetry);
+        cta[8..15].copy_from_slice(&hdr);
+        cta[2] = 15;
+
+        let caps = HdrCapabilities::from_edid(&edid).unwrap();
+        assert_eq!(caps.eotfs, vec![Eotf::TraditionalSdr, Eotf::Pq, Eotf::Hlg]);
+        assert!(caps.bt2020 && !caps.dci_p3);
+        assert_eq!(caps.max_luminance, Some(400.0));
+        assert_eq!(caps.max_frame_average_luminance, Some(200.0));
+        assert_eq!(caps.min_luminance, Some(0.0));
+        assert!(HdrCapabilities::from_edid(&edid[..128]).is_none());
+
+        let metadata = HdrMetadata {
+            max_mastering_luminance: 1000.0,
+            min_mastering_luminance: 0.005,
+            max_content_light_level: 800.0,
+            ..HdrMetadata::new(Eotf::Pq)
+        };
+        assert_eq!(HdrMetadata::from_raw(&metadata.to_raw()), Some(metadata));
+    }
+}
@@ -0,0 +1,275 @@
+//! Typed connector hotplug events
+//!
+//! Udev only reports that *something* changed on a drm device. The [`ConnectorTracker`] compares the
+//! connectors of a device with the state seen on the previous scan and turns the difference into
+//! [`ConnectorEvent`]s, carrying a [`ConnectorInfo`] with the modes and [EDID](super::edid) of the connector.
+//!
+//! A [`DrmDevice`](super::DrmDevice) keeps a tracker for its connectors, which is scanned with
+//! [`DrmDevice::scan_connectors`](super::DrmDevice::scan_connectors). The first scan reports all
+//! connected connectors as added.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmDevice, hotplug::ConnectorEvent};
+//! # use smithay::backend::udev::UdevEvent;
+//! # let mut device: DrmDevice = todo!();
+//! # let event: UdevEvent = todo!();
+//! if let UdevEvent::Changed { .. } = event {
+//!     for event in device.scan_connectors().unwrap() {
+//!         match event {
+//!             ConnectorEvent::Added(info) => {
+//!                 // create an output using `info.preferred_mode()`
+//!             }
+//!             ConnectorEvent::Changed { old, new } => {
+//!                 // e.g. a different monitor was plugged in, update the output
+//!             }
+//!             ConnectorEvent::Removed(info) => {
+//!                 // destroy the output of `info.handle()`
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+
+use drm::control::{connector, property, Device as ControlDevice, Mode, ModeTypeFlags};
+
+use super::edid::Edid;
+
+/// Snapshot of a connected connector
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectorInfo {
+    handle: connector::Handle,
+    interface: connector::Interface,
+    interface_id: u32,
+    modes: Vec<Mode>,
+    physical_size: Option<(u32, u32)>,
+    subpixel: connector::SubPixel,
+    edid: Option<Vec<u8>>,
+    non_desktop: bool,
+}
+
+impl ConnectorInfo {
+    fn new(drm: &impl ControlDevice, info: &connector::Info) -> Self {
+        ConnectorInfo {
+            handle: info.handle(),
+            interface: info.interface(),
+            interface_id: info.interface_id(),
+            modes: info.modes().to_vec(),
+            physical_size: info.size(),
+            subpixel: info.subpixel(),
+            edid: connector_property(drm, info.handle(), "EDID")
+                .and_then(|(info, value)| info.value_type().convert_value(value).as_blob())
+                .and_then(|blob| drm.get_property_blob(blob).ok()),
+            non_desktop: connector_property(drm, info.handle(), "non-desktop")
+                .is_some_and(|(_, value)| value != 0),
+        }
+    }
+
+    /// Handle of the connector
+    pub fn handle(&self) -> connector::Handle {
+        self.handle
+    }
+
+    /// Type of the connector
+    pub fn interface(&self) -> connector::Interface {
+        self.interface
+    }
+
+    /// Index of the connector among the connectors of the same type
+    pub fn interface_id(&self) -> u32 {
+        self.interface_id
+    }
+
+    /// Name of the connector as used by the kernel, e.g. `DP-1`
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.interface.as_str(), self.interface_id)
+    }
+
+    /// Modes supported by the connected display
+    pub fn modes(&self) -> &[Mode] {
+        &self.modes
+    }
+
+    /// Mode preferred by the connected display, falling back to the first mode
+    pub fn preferred_mode(&self) -> Option<Mode> {
+        self.modes
+            .iter()
+            .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+            .or_else(|| self.modes.first())
+            .copied()
+    }
+
+    /// Physical size of the connected display in millimeters, if known
+    pub fn physical_size(&self) -> Option<(u32, u32)> {
+        self.physical_size
+    }
+
+    /// Subpixel layout of the connected display
+    pub fn subpixel(&self) -> connector::SubPixel {
+        self.subpixel
+    }
+
+    /// Raw EDID blob of the connected display, if available
+    pub fn edid(&self) -> Option<&[u8]> {
+        self.edid.as_deref()
+    }
+
+    /// Parsed EDID of the connected display, if available
+    pub fn parsed_edid(&self) -> Option<Edid> {
+        self.edid.as_deref().and_then(Edid::parse)
+    }
+
+    /// Returns whether the connected display should not be used for the desktop, e.g. a VR headset
+    ///
+    /// Such displays are usually offered for leasing instead.
+    pub fn non_desktop(&self) -> bool {
+        self.non_desktop
+    }
+}
+
+fn connector_property(
+    drm: &impl ControlDevice,
+    connector: connector::Handle,
+    name: &str,
+) -> Option<(property::Info, property::RawValue)> {
+    let props = drm.get_properties(connector).ok()?;
+    let (handles, values) = props.as_props_and_values();
+    handles.iter().zip(values).find_map(|(handle, value)| {
+        let info = drm.get_property(*handle).ok()?;
+        (info.name().to_str() == Ok(name)).then_some((info, *value))
+    })
+}
+
+/// Change of a connector reported by [`ConnectorTracker::scan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectorEvent {
+    /// A display was connected
+    Added(ConnectorInfo),
+    /// The display of a connector was disconnected or the connector vanished, e.g. a DisplayPort MST connector
+    Removed(ConnectorInfo),
+    /// The modes or the EDID of a connected display changed without an intermediate disconnect
+    Changed {
+        /// Previous state of the connector
+        old: ConnectorInfo,
+        /// New state of the connector
+        new: ConnectorInfo,
+    },
+}
+
+impl ConnectorEvent {
+    /// Handle of the connector the event refers to
+    pub fn handle(&self) -> connector::Handle {
+        match self {
+            ConnectorEvent::Added(info) | ConnectorEvent::Removed(info) => info.handle(),
+            ConnectorEvent::Changed { new, .. } => new.handle(),
+        }
+    }
+}
+
+/// Tracks the connected connectors of a device between scans
+#[derive(Debug, Default)]
+pub struct ConnectorTracker {
+    connected: HashMap<connector::Handle, ConnectorInfo>,
+}
+
+impl ConnectorTracker {
+    /// Create a tracker without any known connectors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan the connectors of a device and return what changed since the last scan
+    ///
+    /// Should be called on every udev change event of the device. Connectors are probed, which may
+    /// take some time for displays requiring their EDID to be read.
+    pub fn scan(&mut self, drm: &impl ControlDevice) -> io::Result<Vec<ConnectorEvent>> {
+        let resources = drm.resource_handles()?;
+        let connected = resources
+            .connectors()
+            .iter()
+            .filter_map(|conn| drm.get_connector(*conn, true).ok())
+            .filter(|info| {
+                info.state() == connector::State::Connected
+                    && info.interface() != connector::Interface::Writeback
+            })
+            .map(|info| ConnectorInfo::new(drm, &info))
+            .collect();
+        Ok(self.update(connected))
+    }
+
+    fn update(&mut self, connected: Vec<ConnectorInfo>) -> Vec<ConnectorEvent> {
+        let mut previous = std::mem::take(&mut self.connected);
+        let mut events = Vec::new();
+        for info in connected {
+            match previous.remove(&info.handle) {
+                Some(old) if old != info => events.push(ConnectorEvent::Changed {
+                    old,
+                    new: info.clone(),
+                }),
+                Some(_) => {}
+                None => events.push(ConnectorEvent::Added(info.clone())),
+            }
+            self.connected.insert(info.handle, info);
+        }
+        // report removals first, so their crtcs are available to added connectors
+        events.splice(0..0, previous.into_values().map(ConnectorEvent::Removed));
+        events
+    }
+
+    /// Returns the connected connectors as of the last scan
+    pub fn connectors(&self) -> impl Iterator<Item = &ConnectorInfo> {
+        self.connected.values()
+    }
+
+    /// Returns the state of a connector as of the last scan, if it was connected
+    pub fn get(&self, connector: connector::Handle) -> Option<&ConnectorInfo> {
+        self.connected.get(&connector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectorEvent, ConnectorInfo, ConnectorTracker};
+    use drm::control::{connector, RawResourceHandle};
+
+    fn info(id: u32, edid: &[u8]) -> ConnectorInfo {
+        ConnectorInfo {
+            handle: connector::Handle::from(RawResourceHandle::new(id).unwrap()),
+            interface: connector::Interface::DisplayPort,
+            interface_id: id,
+            modes: Vec::new(),
+            physical_size: None,
+            subpixel: connector::SubPixel::Unknown,
+            edid: Some(edid.to_vec()),
+            non_desktop: false,
+        }
+    }
+
+    #[test]
+    fn scans_report_connector_changes() {
+        let mut tracker = ConnectorTracker::new();
+        assert_eq!(
+            tracker.update(vec![info(1, b"a"), info(2, b"b")]).len(),
+            2,
+            "initially connected connectors are added"
+        );
+        assert!(tracker.update(vec![info(1, b"a"), info(2, b"b")]).is_empty());
+
+        let events = tracker.update(vec![info(2, b"c"), info(3, b"d")]);
+        assert_eq!(
+            events,
+            vec![
+                ConnectorEvent::Removed(info(1, b"a")),
+                ConnectorEvent::Changed {
+                    old: info(2, b"b"),
+                    new: info(2, b"c"),
+                },
+                ConnectorEvent::Added(info(3, b"d")),
+            ]
+        );
+        assert_eq!(info(2, b"c").name(), "DP-2");
+        assert_eq!(tracker.connectors().count(), 2);
+    }
+}
@@ -63,6 +63,16 @@
 //! using hardware composition.
 //! See the [`compositor`] module docs for more information on that topic.
 //!
+//! Compositors wanting to assign planes manually can query the plane topology of a surface with
+//! [`DrmSurface::planes`], stage the state of the planes for the next frame in a [`PlaneFrame`] and
+//! check it with [`DrmSurface::test_frame`] before committing it.
+//!
+//! ### Hotplug
+//!
+//! Instead of rescanning the resources of a device on every udev event, [`DrmDevice::scan_connectors`]
+//! reports typed [`ConnectorEvent`](hotplug::ConnectorEvent)s for displays being connected, disconnected or
+//! changed. See the [`hotplug`] module for details.
+//!
 //! ## [`DrmNode`]
 //!
 //! A drm node refers to a drm device and the capabilities that may be performed using the node.
@@ -70,23 +80,29 @@
 //! to allocate buffers for use in X11 or Wayland. If you need to do mode setting, you should use
 //! [`DrmDevice`] instead.
 
+pub mod color;
 #[cfg(all(feature = "wayland_frontend", feature = "backend_gbm"))]
 pub mod compositor;
+pub mod cvt;
 pub(crate) mod device;
 #[cfg(feature = "backend_drm")]
 pub mod dumb;
+pub mod edid;
 mod error;
 #[cfg(feature = "backend_gbm")]
 pub mod gbm;
+pub mod hdr;
+pub mod hotplug;
 
 mod surface;
+pub mod writeback;
 
 use std::sync::Once;
 
 use crate::utils::{DevPath, Physical, Size};
 pub use device::{
-    DrmDevice, DrmDeviceFd, DrmDeviceNotifier, DrmEvent, EventMetadata as DrmEventMetadata, PlaneClaim,
-    Time as DrmEventTime,
+    DrmDevice, DrmDeviceFd, DrmDeviceLease, DrmDeviceNotifier, DrmEvent, EventMetadata as DrmEventMetadata,
+    PlaneClaim, Time as DrmEventTime,
 };
 pub use drm::node::{CreateDrmNodeError, DrmNode, NodeType};
 use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
@@ -95,7 +111,7 @@ pub use error::Error as DrmError;
 use indexmap::IndexSet;
 #[cfg(feature = "backend_gbm")]
 pub use surface::gbm::{Error as GbmBufferedSurfaceError, GbmBufferedSurface};
-pub use surface::{DrmSurface, PlaneConfig, PlaneDamageClips, PlaneState};
+pub use surface::{DrmSurface, PlaneConfig, PlaneDamageClips, PlaneFrame, PlaneState};
 
 use drm::{
     control::{crtc, framebuffer, plane, Device as ControlDevice, PlaneType},
@@ -131,7 +147,39 @@ pub struct Planes {
     pub overlay: Vec<PlaneInfo>,
 }
 
+impl Planes {
+    /// Iterate over all planes, starting with the primary planes followed by the cursor and overlay planes
+    pub fn iter(&self) -> impl Iterator<Item = &PlaneInfo> {
+        self.primary
+            .iter()
+            .chain(self.cursor.iter())
+            .chain(self.overlay.iter())
+    }
+
+    /// Returns the info of the plane with the given handle, if it is available for the crtc
+    pub fn get(&self, plane: plane::Handle) -> Option<&PlaneInfo> {
+        self.iter().find(|info| info.handle == plane)
+    }
+
+    /// Returns whether the plane with the given handle is available for the crtc
+    pub fn contains(&self, plane: plane::Handle) -> bool {
+        self.get(plane).is_some()
+    }
+
+    /// Overlay planes placed below the given plane, usually the primary plane
+    ///
+    /// Content on these planes is only visible through transparent parts of the given plane.
+    pub fn underlays<'a>(&'a self, plane: &'a PlaneInfo) -> impl Iterator<Item = &'a PlaneInfo> + 'a {
+        self.overlay
+            .iter()
+            .filter(move |info| info.zpos.unwrap_or_default() < plane.zpos.unwrap_or_default())
+    }
+}
+
 /// Info about a single plane
+///
+/// KMS does not expose the scaling limits of a plane, configurations with scaling have to be
+/// checked with [`DrmSurface::test_frame`] or [`DrmSurface::test_state`].
 #[derive(Debug, Clone)]
 pub struct PlaneInfo {
     /// Handle of the plane
@@ -140,6 +188,10 @@ pub struct PlaneInfo {
     pub type_: PlaneType,
     /// z-position of the plane if available
     pub zpos: Option<i32>,
+    /// Range of z-positions supported by the plane, if available
+    ///
+    /// Both bounds are the same, if the z-position of the plane is fixed.
+    pub zpos_range: Option<(i32, i32)>,
     /// Formats supported by this plane
     pub formats: FormatSet,
     /// Recommended plane size in order of preference
@@ -181,7 +233,7 @@ fn planes(
         })?;
         let filter = info.possible_crtcs();
         if resources.filter_crtcs(filter).contains(crtc) {
-            let zpos = plane_zpos(dev, plane).ok().flatten();
+            let (zpos, zpos_range) = plane_zpos(dev, plane).ok().flatten().unzip();
             let type_ = plane_type(dev, plane)?;
             let formats = plane_formats(dev, plane)?;
             let size_hints = plane_size_hints(dev, plane)?;
@@ -189,6 +241,7 @@ fn planes(
                 handle: plane,
                 type_,
                 zpos,
+                zpos_range,
                 formats,
                 size_hints,
             };
@@ -241,7 +294,10 @@ fn plane_type(dev: &(impl ControlDevice + DevPath), plane: plane::Handle) -> Res
     unreachable!()
 }
 
-fn plane_zpos(dev: &(impl ControlDevice + DevPath), plane: plane::Handle) -> Result<Option<i32>, DrmError> {
+fn plane_zpos(
+    dev: &(impl ControlDevice + DevPath),
+    plane: plane::Handle,
+) -> Result<Option<(i32, (i32, i32))>, DrmError> {
     let props = dev.get_properties(plane).map_err(|source| {
         DrmError::Access(AccessError {
             errmsg: "Failed to get properties of plane",
@@ -267,7 +323,19 @@ fn plane_zpos(dev: &(impl ControlDevice + DevPath), plane: plane::Handle) -> Res
                 drm::control::property::Value::Boolean(b) => Some(b.into()),
                 _ => None,
             };
-            return Ok(plane_zpos);
+            let range = match info.value_type() {
+                _ if !info.mutable() => None,
+                drm::control::property::ValueType::UnsignedRange(min, max) => {
+                    Some((min.min(i32::MAX as u64) as i32, max.min(i32::MAX as u64) as i32))
+                }
+                drm::control::property::ValueType::SignedRange(min, max) => Some((
+                    min.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                    max.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                )),
+                drm::control::property::ValueType::Boolean => Some((0, 1)),
+                _ => None,
+            };
+            return Ok(plane_zpos.map(|zpos| (zpos, range.unwrap_or((zpos, zpos)))));
         }
     }
     Ok(None)
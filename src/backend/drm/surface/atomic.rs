@@ -5,7 +5,7 @@ use drm::control::{
 };
 
 use std::collections::HashSet;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsFd, AsRawFd};
 use std::sync::Mutex;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -13,6 +13,11 @@ use std::sync::{
 };
 
 use crate::backend::drm::error::AccessError;
+use crate::backend::drm::{
+    color::{ColorPipeline, Ctm, Lut},
+    hdr::{Colorspace, HdrMetadata},
+    writeback::{WritebackConnector, WritebackJob, WritebackState},
+};
 use crate::utils::{Coordinate, Point, Rectangle, Transform};
 use crate::{
     backend::{
@@ -31,18 +36,39 @@ use tracing::{debug, info, info_span, instrument, trace, warn};
 
 use super::{PlaneConfig, PlaneState};
 
+// Properties of the crtc, which can be changed without a modeset
+//
+// Lookup tables and the matrix are stored as property blobs, `0` means unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CrtcState {
+    pub vrr: bool,
+    pub degamma_lut: u64,
+    pub ctm: u64,
+    pub gamma_lut: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct State {
     pub active: bool,
     pub mode: Mode,
     pub blob: property::Value<'static>,
     pub connectors: HashSet<connector::Handle>,
+    pub crtc: CrtcState,
+    pub hdr_metadata: Option<HdrMetadata>,
+    pub hdr_blob: u64,
+    pub colorspace: Colorspace,
 }
 
 impl PartialEq for State {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        self.active == other.active && self.mode == other.mode && self.connectors == other.connectors
+        // the crtc state does not require a modeset and is applied with the next page flip,
+        // so it is not considered here
+        self.active == other.active
+            && self.mode == other.mode
+            && self.connectors == other.connectors
+            && self.hdr_metadata == other.hdr_metadata
+            && self.colorspace == other.colorspace
     }
 }
 
@@ -114,17 +140,33 @@ impl State {
         //
         // Changing a CRTC to active might require a modeset
         let mut active = None;
+        let mut crtc_state = CrtcState::default();
         if let Ok(props) = fd.get_properties(crtc) {
-            let active_prop = prop_mapping.crtcs.get(&crtc).and_then(|m| m.get("ACTIVE"));
+            let crtc_props = prop_mapping.crtcs.get(&crtc);
+            let prop = |name| crtc_props.and_then(|m| m.get(name));
             let (ids, vals) = props.as_props_and_values();
             for (&id, &val) in ids.iter().zip(vals.iter()) {
-                if Some(&id) == active_prop {
+                if Some(&id) == prop("ACTIVE") {
                     active = property::ValueType::Boolean.convert_value(val).as_boolean();
-                    break;
+                } else if Some(&id) == prop("VRR_ENABLED") {
+                    crtc_state.vrr = val != 0;
+                } else if Some(&id) == prop("DEGAMMA_LUT") {
+                    crtc_state.degamma_lut = val;
+                } else if Some(&id) == prop("CTM") {
+                    crtc_state.ctm = val;
+                } else if Some(&id) == prop("GAMMA_LUT") {
+                    crtc_state.gamma_lut = val;
                 }
             }
         }
 
+        // The color state of all connectors of a crtc is expected to match
+        let (hdr_metadata, hdr_blob, colorspace) = current_connectors
+            .iter()
+            .next()
+            .map(|conn| current_color_state(fd, *conn, prop_mapping))
+            .unwrap_or_default();
+
         Ok(State {
             // If we don't know the active state we just assume off.
             // This is highly unlikely, but having a false negative should do no harm.
@@ -132,6 +174,10 @@ impl State {
             mode: current_mode,
             blob: current_blob,
             connectors: current_connectors,
+            crtc: crtc_state,
+            hdr_metadata,
+            hdr_blob,
+            colorspace,
         })
     }
 
@@ -153,6 +199,7 @@ pub struct AtomicDrmSurface {
     prop_mapping: Arc<RwLock<PropMapping>>,
     state: RwLock<State>,
     pending: RwLock<State>,
+    writeback: Mutex<WritebackState>,
     pub(super) span: tracing::Span,
 }
 
@@ -187,6 +234,10 @@ impl AtomicDrmSurface {
             mode,
             blob,
             connectors: connectors.iter().copied().collect(),
+            crtc: state.crtc,
+            hdr_metadata: state.hdr_metadata,
+            hdr_blob: state.hdr_blob,
+            colorspace: state.colorspace,
         };
 
         drop(_guard);
@@ -196,6 +247,7 @@ impl AtomicDrmSurface {
             crtc,
             plane,
             used_planes: Mutex::new(HashSet::new()),
+            writeback: Mutex::new(WritebackState::default()),
             prop_mapping,
             state: RwLock::new(state),
             pending: RwLock::new(pending),
@@ -338,6 +390,8 @@ impl AtomicDrmSurface {
                     }),
                 }],
                 Some(pending.blob),
+                None,
+                None,
             )?;
             self.fd
                 .atomic_commit(
@@ -390,6 +444,8 @@ impl AtomicDrmSurface {
                 }),
             }],
             Some(pending.blob),
+            None,
+            None,
         )?;
         self.fd
             .atomic_commit(
@@ -444,6 +500,8 @@ impl AtomicDrmSurface {
                 }),
             }],
             Some(pending.blob),
+            None,
+            None,
         )?;
 
         self.fd
@@ -496,6 +554,8 @@ impl AtomicDrmSurface {
                 }),
             }],
             Some(new_blob),
+            None,
+            None,
         )?;
         if let Err(err) = self
             .fd
@@ -516,8 +576,230 @@ impl AtomicDrmSurface {
         Ok(())
     }
 
+    pub fn vrr_supported(&self) -> bool {
+        self.prop_mapping
+            .read()
+            .unwrap()
+            .crtc_prop_handle(self.crtc, "VRR_ENABLED")
+            .is_ok()
+    }
+
+    pub fn vrr_enabled(&self) -> bool {
+        self.state.read().unwrap().crtc.vrr
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn use_vrr(&self, enabled: bool) -> Result<(), Error> {
+        if enabled && !self.vrr_supported() {
+            return Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name: "VRR_ENABLED",
+            });
+        }
+        self.pending.write().unwrap().crtc.vrr = enabled;
+        Ok(())
+    }
+
+    pub fn color_pipeline(&self) -> Result<ColorPipeline, Error> {
+        let prop_mapping = self.prop_mapping.read().unwrap();
+        let size = |name| -> Result<Option<u32>, Error> {
+            let Ok(prop) = prop_mapping.crtc_prop_handle(self.crtc, name) else {
+                return Ok(None);
+            };
+            let props = self.fd.get_properties(self.crtc).map_err(|source| {
+                Error::Access(AccessError {
+                    errmsg: "Failed to get properties of crtc",
+                    dev: self.fd.dev_path(),
+                    source,
+                })
+            })?;
+            let (ids, vals) = props.as_props_and_values();
+            Ok(ids
+                .iter()
+                .zip(vals.iter())
+                .find(|(id, _)| **id == prop)
+                .map(|(_, val)| *val as u32)
+                .filter(|size| *size > 0))
+        };
+        Ok(ColorPipeline {
+            degamma_lut_size: size("DEGAMMA_LUT_SIZE")?
+                .filter(|_| prop_mapping.crtc_prop_handle(self.crtc, "DEGAMMA_LUT").is_ok()),
+            ctm: prop_mapping.crtc_prop_handle(self.crtc, "CTM").is_ok(),
+            gamma_lut_size: size("GAMMA_LUT_SIZE")?
+                .filter(|_| prop_mapping.crtc_prop_handle(self.crtc, "GAMMA_LUT").is_ok()),
+        })
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self, lut))]
+    pub fn set_degamma_lut(&self, lut: Option<&Lut>) -> Result<(), Error> {
+        let size = self.color_pipeline()?.degamma_lut_size;
+        let blob = self.create_lut_blob("DEGAMMA_LUT", size, lut)?;
+        self.replace_crtc_blob(|state| &mut state.degamma_lut, blob);
+        Ok(())
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_ctm(&self, ctm: Option<&Ctm>) -> Result<(), Error> {
+        let blob = match ctm {
+            Some(ctm) => {
+                self.prop_mapping
+                    .read()
+                    .unwrap()
+                    .crtc_prop_handle(self.crtc, "CTM")?;
+                self.create_blob(&mut bytes_of(&[ctm.to_raw()]))?
+            }
+            None => 0,
+        };
+        self.replace_crtc_blob(|state| &mut state.ctm, blob);
+        Ok(())
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self, lut))]
+    pub fn set_gamma_lut(&self, lut: Option<&Lut>) -> Result<(), Error> {
+        let size = self.color_pipeline()?.gamma_lut_size;
+        let blob = self.create_lut_blob("GAMMA_LUT", size, lut)?;
+        self.replace_crtc_blob(|state| &mut state.gamma_lut, blob);
+        Ok(())
+    }
+
+    fn create_lut_blob(
+        &self,
+        name: &'static str,
+        size: Option<u32>,
+        lut: Option<&Lut>,
+    ) -> Result<u64, Error> {
+        let Some(lut) = lut else {
+            return Ok(0);
+        };
+        let Some(size) = size else {
+            return Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name,
+            });
+        };
+        if lut.len() != size as usize {
+            return Err(Error::InvalidLutSize(size, lut.len()));
+        }
+        self.create_blob(&mut bytes_of(&lut.to_raw()))
+    }
+
+    fn create_blob(&self, data: &mut [u8]) -> Result<u64, Error> {
+        drm_ffi::mode::create_property_blob(self.fd.as_fd(), data)
+            .map(|blob| blob.blob_id as u64)
+            .map_err(|source| {
+                Error::Access(AccessError {
+                    errmsg: "Failed to create Property Blob for color pipeline",
+                    dev: self.fd.dev_path(),
+                    source,
+                })
+            })
+    }
+
+    // Replace a pending blob, destroying the previously pending one if it is not in use
+    fn replace_crtc_blob(&self, field: impl Fn(&mut CrtcState) -> &mut u64, blob: u64) {
+        let mut current = self.state.read().unwrap().crtc;
+        let mut pending = self.pending.write().unwrap();
+        let old = std::mem::replace(field(&mut pending.crtc), blob);
+        if old != 0 && old != *field(&mut current) {
+            if let Err(err) = self.fd.destroy_property_blob(old) {
+                warn!("Failed to destroy pending color pipeline property blob: {}", err);
+            }
+        }
+    }
+
+    // Destroy the blobs of the current state, which are replaced by the new state
+    fn release_crtc_blobs(&self, current: &CrtcState, new: &CrtcState) {
+        for (old, new) in [
+            (current.degamma_lut, new.degamma_lut),
+            (current.ctm, new.ctm),
+            (current.gamma_lut, new.gamma_lut),
+        ] {
+            if old != 0 && old != new {
+                if let Err(err) = self.fd.destroy_property_blob(old) {
+                    warn!("Failed to destroy old color pipeline property blob: {}", err);
+                }
+            }
+        }
+    }
+
+    pub fn pending_hdr_metadata(&self) -> Option<HdrMetadata> {
+        self.pending.read().unwrap().hdr_metadata
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_hdr_metadata(&self, metadata: Option<HdrMetadata>) -> Result<(), Error> {
+        let current = self.state.read().unwrap();
+        let mut pending = self.pending.write().unwrap();
+        if pending.hdr_metadata == metadata {
+            return Ok(());
+        }
+        if metadata.is_some() {
+            let prop_mapping = self.prop_mapping.read().unwrap();
+            for conn in pending.connectors.iter() {
+                prop_mapping.conn_prop_handle(*conn, "HDR_OUTPUT_METADATA")?;
+            }
+        }
+
+        let blob = match metadata {
+            Some(metadata) => self
+                .fd
+                .create_property_blob(&metadata.to_raw())
+                .map_err(|source| {
+                    Error::Access(AccessError {
+                        errmsg: "Failed to create Property Blob for hdr metadata",
+                        dev: self.fd.dev_path(),
+                        source,
+                    })
+                })?
+                .into(),
+            None => 0,
+        };
+        if pending.hdr_blob != 0 && pending.hdr_blob != current.hdr_blob {
+            if let Err(err) = self.fd.destroy_property_blob(pending.hdr_blob) {
+                warn!("Failed to destroy pending hdr metadata property blob: {}", err);
+            }
+        }
+        pending.hdr_metadata = metadata;
+        pending.hdr_blob = blob;
+        Ok(())
+    }
+
+    pub fn pending_colorspace(&self) -> Colorspace {
+        self.pending.read().unwrap().colorspace
+    }
+
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_colorspace(&self, colorspace: Colorspace) -> Result<(), Error> {
+        let mut pending = self.pending.write().unwrap();
+        if colorspace != Colorspace::Default {
+            let prop_mapping = self.prop_mapping.read().unwrap();
+            for conn in pending.connectors.iter() {
+                let prop = prop_mapping.conn_prop_handle(*conn, "Colorspace")?;
+                colorspace_value(&*self.fd, *conn, prop, colorspace)?;
+            }
+        }
+        pending.colorspace = colorspace;
+        Ok(())
+    }
+
+    // Forces the next commit to enable the crtc and all connectors again with the pending state
+    pub fn restore_state(&self) {
+        let mut current = self.state.write().unwrap();
+        current.active = false;
+        current.connectors.clear();
+    }
+
     pub fn commit_pending(&self) -> bool {
         *self.pending.read().unwrap() != *self.state.read().unwrap()
+            || self.writeback.lock().unwrap().modeset_pending()
+    }
+
+    pub fn queue_writeback(
+        &self,
+        connector: &WritebackConnector,
+        framebuffer: framebuffer::Handle,
+    ) -> WritebackJob {
+        self.writeback.lock().unwrap().queue(connector, framebuffer)
     }
 
     #[instrument(level = "trace", parent = &self.span, skip(self, planes))]
@@ -541,7 +823,14 @@ impl AtomicDrmSurface {
         let mut removed = current_conns.difference(&pending_conns);
         let mut added = pending_conns.difference(&current_conns);
 
-        let req = self.build_request(&mut added, &mut removed, &*planes, Some(pending.blob))?;
+        let req = self.build_request(
+            &mut added,
+            &mut removed,
+            &*planes,
+            Some(pending.blob),
+            Some(pending.crtc),
+            allow_modeset.then_some(&*pending),
+        )?;
 
         let flags = if allow_modeset {
             AtomicCommitFlags::ALLOW_MODESET | AtomicCommitFlags::TEST_ONLY
@@ -572,6 +861,8 @@ impl AtomicDrmSurface {
         let mut current = self.state.write().unwrap();
         let mut used_planes = self.used_planes.lock().unwrap();
         let pending = self.pending.read().unwrap();
+        let mut writeback = self.writeback.lock().unwrap();
+        let mut out_fence = -1;
 
         debug!(current = ?*current, pending = ?*pending, ?planes, "Preparing Commit",);
 
@@ -605,7 +896,15 @@ impl AtomicDrmSurface {
 
         // test the new config and return the request if it would be accepted by the driver.
         let req = {
-            let req = self.build_request(&mut added, &mut removed, &*planes, Some(pending.blob))?;
+            let mut req = self.build_request(
+                &mut added,
+                &mut removed,
+                &*planes,
+                Some(pending.blob),
+                Some(pending.crtc),
+                Some(&*pending),
+            )?;
+            writeback.add_to_commit(&mut req, self.crtc, &mut out_fence);
 
             if let Err(err) = self.fd.atomic_commit(
                 AtomicCommitFlags::ALLOW_MODESET | AtomicCommitFlags::TEST_ONLY,
@@ -655,6 +954,13 @@ impl AtomicDrmSurface {
             });
 
         if result.is_ok() {
+            writeback.committed(out_fence);
+            if current.hdr_blob != 0 && current.hdr_blob != pending.hdr_blob {
+                if let Err(err) = self.fd.destroy_property_blob(current.hdr_blob) {
+                    warn!("Failed to destroy old hdr metadata property blob: {}", err);
+                }
+            }
+            self.release_crtc_blobs(&current.crtc, &pending.crtc);
             *current = pending.clone();
             for plane in planes.iter() {
                 if plane.config.is_some() {
@@ -674,6 +980,7 @@ impl AtomicDrmSurface {
         &self,
         planes: impl IntoIterator<Item = PlaneState<'a>>,
         event: bool,
+        async_flip: bool,
     ) -> Result<(), Error> {
         if !self.active.load(Ordering::SeqCst) {
             return Err(Error::DeviceInactive);
@@ -681,33 +988,50 @@ impl AtomicDrmSurface {
 
         let mut used_planes = self.used_planes.lock().unwrap();
         let planes = planes.into_iter().collect::<Vec<_>>();
+        let crtc_state = self.pending.read().unwrap().crtc;
+        let mut writeback = self.writeback.lock().unwrap();
+        let mut out_fence = -1;
 
         // page flips work just like commits with fewer parameters..
-        let req = self.build_request(&mut [].iter(), &mut [].iter(), &*planes, None)?;
+        let mut req = self.build_request(
+            &mut [].iter(),
+            &mut [].iter(),
+            &*planes,
+            None,
+            Some(crtc_state),
+            None,
+        )?;
 
         // .. and without `AtomicCommitFlags::AllowModeset`.
         // If we would set anything here, that would require a modeset, this would fail,
         // indicating a problem in our assumptions.
-        trace!(?planes, "Queueing page flip: {:?}", req);
-        let res = self
-            .fd
-            .atomic_commit(
-                if event {
-                    AtomicCommitFlags::PAGE_FLIP_EVENT | AtomicCommitFlags::NONBLOCK
-                } else {
-                    AtomicCommitFlags::NONBLOCK
-                },
-                req,
-            )
-            .map_err(|source| {
-                Error::Access(AccessError {
-                    errmsg: "Page flip commit failed",
-                    dev: self.fd.dev_path(),
-                    source,
-                })
-            });
+        // Writebacks are only added through already routed connectors and asynchronous flips
+        // can not change connector properties at all, so they wait for the next regular flip.
+        let with_writeback = !async_flip && writeback.add_to_flip(&mut req, &mut out_fence);
+
+        trace!(?planes, async_flip, "Queueing page flip: {:?}", req);
+        let mut flags = AtomicCommitFlags::NONBLOCK;
+        if event {
+            flags |= AtomicCommitFlags::PAGE_FLIP_EVENT;
+        }
+        if async_flip {
+            flags |= AtomicCommitFlags::PAGE_FLIP_ASYNC;
+        }
+        let res = self.fd.atomic_commit(flags, req).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Page flip commit failed",
+                dev: self.fd.dev_path(),
+                source,
+            })
+        });
 
         if res.is_ok() {
+            if with_writeback {
+                writeback.flipped(out_fence);
+            }
+            let mut current = self.state.write().unwrap();
+            self.release_crtc_blobs(&current.crtc, &crtc_state);
+            current.crtc = crtc_state;
             for plane in planes.iter() {
                 if plane.config.is_some() {
                     used_planes.insert(plane.handle);
@@ -729,6 +1053,8 @@ impl AtomicDrmSurface {
         removed_connectors: &mut dyn Iterator<Item = &connector::Handle>,
         planes: impl IntoIterator<Item = &'a PlaneState<'a>>,
         blob: Option<property::Value<'static>>,
+        crtc_state: Option<CrtcState>,
+        color_state: Option<&State>,
     ) -> Result<AtomicModeReq, Error> {
         let prop_mapping = self.prop_mapping.read().unwrap();
 
@@ -775,6 +1101,44 @@ impl AtomicDrmSurface {
             property::Value::Boolean(true),
         );
 
+        if let Some(state) = color_state {
+            for conn in state.connectors.iter() {
+                match prop_mapping.conn_prop_handle(*conn, "HDR_OUTPUT_METADATA") {
+                    Ok(prop) => req.add_property(*conn, prop, property::Value::Blob(state.hdr_blob)),
+                    Err(err) if state.hdr_blob != 0 => return Err(err),
+                    Err(_) => {}
+                }
+                match prop_mapping.conn_prop_handle(*conn, "Colorspace") {
+                    Ok(prop) => {
+                        let value = colorspace_value(&*self.fd, *conn, prop, state.colorspace)?;
+                        req.add_property(*conn, prop, property::Value::UnsignedRange(value));
+                    }
+                    Err(err) if state.colorspace != Colorspace::Default => return Err(err),
+                    Err(_) => {}
+                }
+            }
+        }
+
+        if let Some(crtc_state) = crtc_state {
+            match prop_mapping.crtc_prop_handle(self.crtc, "VRR_ENABLED") {
+                Ok(prop) => req.add_property(self.crtc, prop, property::Value::Boolean(crtc_state.vrr)),
+                // without the property vrr is always disabled
+                Err(err) if crtc_state.vrr => return Err(err),
+                Err(_) => {}
+            }
+            for (name, blob) in [
+                ("DEGAMMA_LUT", crtc_state.degamma_lut),
+                ("CTM", crtc_state.ctm),
+                ("GAMMA_LUT", crtc_state.gamma_lut),
+            ] {
+                match prop_mapping.crtc_prop_handle(self.crtc, name) {
+                    Ok(prop) => req.add_property(self.crtc, prop, property::Value::Blob(blob)),
+                    Err(err) if blob != 0 => return Err(err),
+                    Err(_) => {}
+                }
+            }
+        }
+
         for plane_state in planes.into_iter() {
             let handle = &plane_state.handle;
 
@@ -949,6 +1313,8 @@ impl AtomicDrmSurface {
                 .expect("Unknown property CRTC_ID");
             req.add_property(*conn, *prop, property::Value::CRTC(None));
         }
+        let mut writeback = self.writeback.lock().unwrap();
+        writeback.add_to_clear(&mut req);
         let active_prop = prop_mapping
             .crtcs
             .get(&self.crtc)
@@ -980,6 +1346,7 @@ impl AtomicDrmSurface {
         if res.is_ok() {
             self.used_planes.lock().unwrap().clear();
             self.state.write().unwrap().clear();
+            writeback.cleared();
         }
 
         res
@@ -1087,6 +1454,73 @@ impl AtomicDrmSurface {
     }
 }
 
+// Raw bytes of a slice of plain ffi structs for creating property blobs
+fn bytes_of<T: Copy>(data: &[T]) -> Vec<u8> {
+    // SAFETY: the ffi structs are plain integers without padding
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }.to_vec()
+}
+
+// Reads the hdr metadata, its blob and the colorspace currently set on a connector
+fn current_color_state<A: DevPath + ControlDevice>(
+    fd: &A,
+    conn: connector::Handle,
+    prop_mapping: &PropMapping,
+) -> (Option<HdrMetadata>, u64, Colorspace) {
+    let mut color_state = (None, 0, Colorspace::Default);
+    let (Some(conn_props), Ok(props)) = (prop_mapping.connectors.get(&conn), fd.get_properties(conn)) else {
+        return color_state;
+    };
+    let hdr_prop = conn_props.get("HDR_OUTPUT_METADATA");
+    let colorspace_prop = conn_props.get("Colorspace");
+    let (ids, vals) = props.as_props_and_values();
+    for (&id, &val) in ids.iter().zip(vals.iter()) {
+        if Some(&id) == hdr_prop && val != 0 {
+            let Ok(data) = fd.get_property_blob(val) else {
+                continue;
+            };
+            if data.len() >= std::mem::size_of::<drm_ffi::hdr_output_metadata>() {
+                // the blob has no alignment guarantees
+                let raw = unsafe { (data.as_ptr() as *const drm_ffi::hdr_output_metadata).read_unaligned() };
+                color_state.0 = HdrMetadata::from_raw(&raw);
+                color_state.1 = val;
+            }
+        } else if Some(&id) == colorspace_prop {
+            if let Ok(property::ValueType::Enum(values)) = fd.get_property(id).map(|info| info.value_type()) {
+                color_state.2 = values
+                    .get_value_from_raw_value(val)
+                    .and_then(|value| value.name().to_str().ok())
+                    .and_then(Colorspace::from_name)
+                    .unwrap_or_default();
+            }
+        }
+    }
+    color_state
+}
+
+// Looks up the raw value of a colorspace for the `Colorspace` property of a connector
+fn colorspace_value(
+    fd: &impl ControlDevice,
+    conn: connector::Handle,
+    prop: property::Handle,
+    colorspace: Colorspace,
+) -> Result<u64, Error> {
+    let values = match fd.get_property(prop).map(|info| info.value_type()) {
+        Ok(property::ValueType::Enum(values)) => values,
+        _ => {
+            return Err(Error::UnknownProperty {
+                handle: conn.into(),
+                name: "Colorspace",
+            })
+        }
+    };
+    let (_, values) = values.values();
+    values
+        .iter()
+        .find(|value| value.name().to_str() == Ok(colorspace.name()))
+        .map(|value| value.value())
+        .ok_or(Error::UnsupportedColorspace(conn, colorspace))
+}
+
 struct TestBuffer {
     fd: Arc<DrmDeviceInternal>,
     db: DumbBuffer,
@@ -6,7 +6,7 @@ use std::sync::{
     Arc, Mutex, RwLock,
 };
 
-use crate::backend::drm::error::AccessError;
+use crate::backend::drm::{color::Lut, error::AccessError};
 use crate::{
     backend::drm::{
         device::legacy::set_connector_state, device::DrmDeviceInternal, error::Error, DrmDeviceFd,
@@ -87,6 +87,8 @@ pub struct LegacyDrmSurface {
     state: RwLock<State>,
     pending: RwLock<State>,
     dpms: Mutex<bool>,
+    // legacy gamma is not part of the state, remember it to restore it
+    gamma_lut: Mutex<Option<Lut>>,
     pub(super) span: tracing::Span,
 }
 
@@ -116,6 +118,7 @@ impl LegacyDrmSurface {
             state: RwLock::new(state),
             pending: RwLock::new(pending),
             dpms: Mutex::new(true),
+            gamma_lut: Mutex::new(None),
             span,
         };
 
@@ -222,6 +225,18 @@ impl LegacyDrmSurface {
         Ok(())
     }
 
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn use_custom_mode(&self, mode: Mode) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+
+        // legacy drivers offer no way to test a mode, it gets validated on the next commit
+        self.pending.write().unwrap().mode = mode;
+
+        Ok(())
+    }
+
     pub fn commit_pending(&self) -> bool {
         *self.pending.read().unwrap() != *self.state.read().unwrap()
     }
@@ -333,8 +348,13 @@ impl LegacyDrmSurface {
 
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
-    pub fn page_flip(&self, framebuffer: framebuffer::Handle, event: bool) -> Result<(), Error> {
-        trace!("Queueing Page flip");
+    pub fn page_flip(
+        &self,
+        framebuffer: framebuffer::Handle,
+        event: bool,
+        async_flip: bool,
+    ) -> Result<(), Error> {
+        trace!(async_flip, "Queueing Page flip");
 
         if !self.active.load(Ordering::SeqCst) {
             return Err(Error::DeviceInactive);
@@ -347,18 +367,14 @@ impl LegacyDrmSurface {
             *dpms = true;
         }
 
-        ControlDevice::page_flip(
-            &*self.fd,
-            self.crtc,
-            framebuffer,
-            if event {
-                PageFlipFlags::EVENT
-            } else {
-                PageFlipFlags::empty()
-            },
-            None,
-        )
-        .map_err(|source| {
+        let mut flags = PageFlipFlags::empty();
+        if event {
+            flags |= PageFlipFlags::EVENT;
+        }
+        if async_flip {
+            flags |= PageFlipFlags::ASYNC;
+        }
+        ControlDevice::page_flip(&*self.fd, self.crtc, framebuffer, flags, None).map_err(|source| {
             Error::Access(AccessError {
                 errmsg: "Failed to page flip",
                 dev: self.fd.dev_path(),
@@ -469,6 +485,72 @@ impl LegacyDrmSurface {
         self.fd.device_fd()
     }
 
+    pub fn gamma_lut_size(&self) -> Result<Option<u32>, Error> {
+        let info = self.fd.get_crtc(self.crtc).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Error loading crtc info",
+                dev: self.fd.dev_path(),
+                source,
+            })
+        })?;
+        Ok(Some(info.gamma_length()).filter(|size| *size > 0))
+    }
+
+    // Legacy gamma is applied immediately
+    #[instrument(level = "debug", parent = &self.span, skip(self, lut))]
+    pub fn set_gamma_lut(&self, lut: Option<&Lut>) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+        let Some(size) = self.gamma_lut_size()? else {
+            return match lut {
+                Some(_) => Err(Error::UnknownProperty {
+                    handle: self.crtc.into(),
+                    name: "GAMMA_LUT",
+                }),
+                None => Ok(()),
+            };
+        };
+        let identity;
+        let lut = match lut {
+            Some(lut) => lut,
+            None => {
+                identity = Lut::identity(size as usize);
+                &identity
+            }
+        };
+        if lut.len() != size as usize {
+            return Err(Error::InvalidLutSize(size, lut.len()));
+        }
+        self.fd
+            .set_gamma(self.crtc, lut.red(), lut.green(), lut.blue())
+            .map_err(|source| {
+                Error::Access(AccessError {
+                    errmsg: "Failed to set gamma",
+                    dev: self.fd.dev_path(),
+                    source,
+                })
+            })?;
+        *self.gamma_lut.lock().unwrap() = Some(lut.clone());
+        Ok(())
+    }
+
+    // Forces the next commit to modeset and re-applies the last gamma lookup table
+    pub fn restore_state(&self) -> Result<(), Error> {
+        {
+            let mut current = self.state.write().unwrap();
+            current.mode = drm_ffi::drm_mode_modeinfo::default().into();
+            current.connectors.clear();
+            *self.dpms.lock().unwrap() = false;
+        }
+
+        let lut = self.gamma_lut.lock().unwrap().clone();
+        match lut {
+            Some(lut) => self.set_gamma_lut(Some(&lut)),
+            None => Ok(()),
+        }
+    }
+
     pub fn clear(&self) -> Result<(), Error> {
         let current = self.state.read().unwrap();
         let mut dpms = self.dpms.lock().unwrap();
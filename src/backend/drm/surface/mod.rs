@@ -3,8 +3,8 @@ use std::os::unix::io::{AsFd, BorrowedFd};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use drm::control::{connector, crtc, framebuffer, plane, Device as ControlDevice, Mode};
-use drm::Device as BasicDevice;
+use drm::control::{connector, crtc, framebuffer, plane, property, Device as ControlDevice, Mode};
+use drm::{Device as BasicDevice, DriverCapability};
 
 use libc::dev_t;
 
@@ -13,9 +13,16 @@ pub(super) mod atomic;
 pub(super) mod gbm;
 pub(super) mod legacy;
 use super::{
-    device::PlaneClaimStorage, error::Error, plane_type, DrmDeviceFd, PlaneClaim, PlaneInfo, PlaneType,
-    Planes,
+    color::{ColorPipeline, Ctm, Lut},
+    device::{LeaseStorage, PlaneClaimStorage},
+    edid::{connector_edid_blob, Edid},
+    error::{AccessError, Error},
+    hdr::{Colorspace, HdrCapabilities, HdrMetadata},
+    plane_type,
+    writeback::{WritebackConnector, WritebackJob},
+    DrmDeviceFd, PlaneClaim, PlaneInfo, PlaneType, Planes,
 };
+use crate::backend::allocator::format::FormatSet;
 use crate::utils::DevPath;
 use crate::utils::{Buffer, Physical, Point, Rectangle, Transform};
 use atomic::AtomicDrmSurface;
@@ -31,6 +38,7 @@ pub struct DrmSurface {
     pub(super) planes: Planes,
     pub(super) internal: Arc<DrmSurfaceInternal>,
     pub(super) plane_claim_storage: PlaneClaimStorage,
+    pub(super) leases: LeaseStorage,
     pub(super) primary_plane: (PlaneInfo, PlaneClaim),
 }
 
@@ -128,7 +136,7 @@ impl Clone for PlaneDamageClips {
 }
 
 /// State of a single plane
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlaneState<'a> {
     /// Handle of the plane
     pub handle: plane::Handle,
@@ -139,7 +147,7 @@ pub struct PlaneState<'a> {
 }
 
 /// Configuration for a single plane
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlaneConfig<'a> {
     /// Source [`Rectangle`] of the attached framebuffer
     pub src: Rectangle<f64, Buffer>,
@@ -157,6 +165,76 @@ pub struct PlaneConfig<'a> {
     pub fence: Option<BorrowedFd<'a>>,
 }
 
+/// State of the planes of a [`DrmSurface`] staged for the next frame
+///
+/// Planes not staged in the frame keep their current state. The frame can be checked with
+/// [`DrmSurface::test_frame`] and passed to [`DrmSurface::commit`] or [`DrmSurface::page_flip`].
+#[derive(Debug, Clone, Default)]
+pub struct PlaneFrame<'a> {
+    planes: Vec<PlaneState<'a>>,
+}
+
+impl<'a> PlaneFrame<'a> {
+    /// Create a new empty frame
+    pub fn new() -> Self {
+        PlaneFrame::default()
+    }
+
+    /// Stage the state of a plane, replacing any state previously staged for the same plane
+    pub fn stage(&mut self, state: PlaneState<'a>) {
+        match self.planes.iter_mut().find(|plane| plane.handle == state.handle) {
+            Some(plane) => *plane = state,
+            None => self.planes.push(state),
+        }
+    }
+
+    /// Stage a configuration for a plane
+    pub fn set(&mut self, plane: plane::Handle, config: PlaneConfig<'a>) {
+        self.stage(PlaneState {
+            handle: plane,
+            config: Some(config),
+        });
+    }
+
+    /// Stage disabling a plane
+    pub fn clear(&mut self, plane: plane::Handle) {
+        self.stage(PlaneState {
+            handle: plane,
+            config: None,
+        });
+    }
+
+    /// Remove the staged state of a plane, returning it if it was staged
+    pub fn unstage(&mut self, plane: plane::Handle) -> Option<PlaneState<'a>> {
+        let idx = self.planes.iter().position(|state| state.handle == plane)?;
+        Some(self.planes.remove(idx))
+    }
+
+    /// Returns the staged state of a plane
+    pub fn get(&self, plane: plane::Handle) -> Option<&PlaneState<'a>> {
+        self.planes.iter().find(|state| state.handle == plane)
+    }
+
+    /// Returns the staged states of all planes in the order they were staged first
+    pub fn states(&self) -> &[PlaneState<'a>] {
+        &self.planes
+    }
+
+    /// Returns whether no plane is staged
+    pub fn is_empty(&self) -> bool {
+        self.planes.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for PlaneFrame<'a> {
+    type Item = PlaneState<'a>;
+    type IntoIter = std::vec::IntoIter<PlaneState<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.planes.into_iter()
+    }
+}
+
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum DrmSurfaceInternal {
@@ -233,7 +311,9 @@ impl DrmSurface {
     /// (e.g. no suitable [`encoder`](drm::control::encoder) may be found)
     /// or is not compatible with the currently pending
     /// [`Mode`](drm::control::Mode).
+    /// Leased connectors are rejected with [`Error::ResourceLeased`].
     pub fn add_connector(&self, connector: connector::Handle) -> Result<(), Error> {
+        self.ensure_not_leased(&[connector])?;
         match &*self.internal {
             DrmSurfaceInternal::Atomic(surf) => surf.add_connector(connector),
             DrmSurfaceInternal::Legacy(surf) => surf.add_connector(connector),
@@ -255,13 +335,25 @@ impl DrmSurface {
     /// (e.g. no suitable [`encoder`](drm::control::encoder) may be found)
     /// or is not compatible with the currently pending
     /// [`Mode`](drm::control::Mode).
+    /// Leased connectors are rejected with [`Error::ResourceLeased`].
     pub fn set_connectors(&self, connectors: &[connector::Handle]) -> Result<(), Error> {
+        self.ensure_not_leased(connectors)?;
         match &*self.internal {
             DrmSurfaceInternal::Atomic(surf) => surf.set_connectors(connectors),
             DrmSurfaceInternal::Legacy(surf) => surf.set_connectors(connectors),
         }
     }
 
+    fn ensure_not_leased(&self, connectors: &[connector::Handle]) -> Result<(), Error> {
+        match connectors
+            .iter()
+            .find(|conn| self.leases.is_leased((**conn).into()))
+        {
+            Some(conn) => Err(Error::ResourceLeased((*conn).into())),
+            None => Ok(()),
+        }
+    }
+
     /// Returns the currently active [`Mode`](drm::control::Mode)
     /// of the underlying [`crtc`](drm::control::crtc)
     pub fn current_mode(&self) -> Mode {
@@ -293,6 +385,210 @@ impl DrmSurface {
         }
     }
 
+    /// Tries to set a custom [`Mode`](drm::control::Mode) not advertised by the connectors,
+    /// e.g. one generated by [`cvt_mode`](crate::backend::drm::cvt::cvt_mode),
+    /// to be used after the next commit.
+    ///
+    /// Unlike [`use_mode`](DrmSurface::use_mode) the mode is not checked against the modes of the
+    /// pending [`connector`](drm::control::connector)s. Atomic drivers validate it with a test commit,
+    /// legacy drivers can only reject it on the next commit.
+    pub fn use_custom_mode(&self, mode: Mode) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.use_mode(mode),
+            DrmSurfaceInternal::Legacy(surf) => surf.use_custom_mode(mode),
+        }
+    }
+
+    /// Returns whether the crtc supports variable refresh rate (adaptive sync)
+    ///
+    /// Always returns `false` for legacy devices. Whether vrr is actually used also depends on the
+    /// connected display, see [`connector_vrr_capable`](DrmSurface::connector_vrr_capable).
+    pub fn vrr_supported(&self) -> bool {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.vrr_supported(),
+            DrmSurfaceInternal::Legacy(_) => false,
+        }
+    }
+
+    /// Returns whether the given connector and the display connected to it are capable of
+    /// variable refresh rate
+    pub fn connector_vrr_capable(&self, connector: connector::Handle) -> Result<bool, Error> {
+        Ok(self
+            .connector_property(connector, "vrr_capable")?
+            .is_some_and(|(_, value)| value != 0))
+    }
+
+    /// Returns whether variable refresh rate is currently enabled
+    pub fn vrr_enabled(&self) -> bool {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.vrr_enabled(),
+            DrmSurfaceInternal::Legacy(_) => false,
+        }
+    }
+
+    /// Enable or disable variable refresh rate (adaptive sync) with the next commit or page flip
+    ///
+    /// Changing this does not require a modeset. Fails if vrr is enabled on a crtc not
+    /// [supporting](DrmSurface::vrr_supported) it.
+    pub fn use_vrr(&self, enabled: bool) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.use_vrr(enabled),
+            DrmSurfaceInternal::Legacy(_) if enabled => Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name: "VRR_ENABLED",
+            }),
+            DrmSurfaceInternal::Legacy(_) => Ok(()),
+        }
+    }
+
+    /// Returns the stages of the hardware color pipeline supported by the crtc
+    ///
+    /// Legacy devices only support a gamma lookup table.
+    pub fn color_pipeline(&self) -> Result<ColorPipeline, Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.color_pipeline(),
+            DrmSurfaceInternal::Legacy(surf) => Ok(ColorPipeline {
+                gamma_lut_size: surf.gamma_lut_size()?,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Set the degamma lookup table applied with the next commit or page flip
+    ///
+    /// `None` removes the lookup table. Fails if the crtc does not support a degamma lookup table
+    /// or the size of `lut` does not match the [supported size](ColorPipeline::degamma_lut_size).
+    pub fn set_degamma_lut(&self, lut: Option<&Lut>) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_degamma_lut(lut),
+            DrmSurfaceInternal::Legacy(_) if lut.is_some() => Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name: "DEGAMMA_LUT",
+            }),
+            DrmSurfaceInternal::Legacy(_) => Ok(()),
+        }
+    }
+
+    /// Set the color transformation matrix applied with the next commit or page flip
+    ///
+    /// `None` removes the matrix. Fails if the crtc does not support a color transformation matrix.
+    pub fn set_ctm(&self, ctm: Option<&Ctm>) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_ctm(ctm),
+            DrmSurfaceInternal::Legacy(_) if ctm.is_some() => Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name: "CTM",
+            }),
+            DrmSurfaceInternal::Legacy(_) => Ok(()),
+        }
+    }
+
+    /// Set the gamma lookup table applied with the next commit or page flip
+    ///
+    /// `None` removes the lookup table. Fails if the crtc does not support a gamma lookup table
+    /// or the size of `lut` does not match the [supported size](ColorPipeline::gamma_lut_size).
+    ///
+    /// *Note*: On legacy devices the lookup table is applied immediately.
+    pub fn set_gamma_lut(&self, lut: Option<&Lut>) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_gamma_lut(lut),
+            DrmSurfaceInternal::Legacy(surf) => surf.set_gamma_lut(lut),
+        }
+    }
+
+    /// Returns the HDR capabilities advertised in the EDID of the display connected to the given connector
+    ///
+    /// Returns `None` if the display does not support HDR or has no EDID.
+    pub fn connector_hdr_capabilities(
+        &self,
+        connector: connector::Handle,
+    ) -> Result<Option<HdrCapabilities>, Error> {
+        let edid = connector_edid_blob(self, connector)?;
+        Ok(edid.and_then(|edid| HdrCapabilities::from_edid(&edid)))
+    }
+
+    /// Returns the parsed EDID of the display connected to the given connector, if available
+    pub fn connector_edid(&self, connector: connector::Handle) -> Result<Option<Edid>, Error> {
+        let edid = connector_edid_blob(self, connector)?;
+        Ok(edid.and_then(|edid| Edid::parse(&edid)))
+    }
+
+    /// Returns the currently pending HDR metadata to be used after the next commit
+    pub fn pending_hdr_metadata(&self) -> Option<HdrMetadata> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.pending_hdr_metadata(),
+            DrmSurfaceInternal::Legacy(_) => None,
+        }
+    }
+
+    /// Set the HDR metadata sent to the connected displays after the next commit
+    ///
+    /// `None` disables sending HDR metadata. Fails if any pending connector does not support the
+    /// `HDR_OUTPUT_METADATA` property, which is always the case for legacy devices.
+    pub fn set_hdr_metadata(&self, metadata: Option<HdrMetadata>) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_hdr_metadata(metadata),
+            DrmSurfaceInternal::Legacy(_) if metadata.is_some() => Err(Error::UnknownProperty {
+                handle: self.crtc.into(),
+                name: "HDR_OUTPUT_METADATA",
+            }),
+            DrmSurfaceInternal::Legacy(_) => Ok(()),
+        }
+    }
+
+    /// Returns the currently pending [`Colorspace`] to be used after the next commit
+    pub fn pending_colorspace(&self) -> Colorspace {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.pending_colorspace(),
+            DrmSurfaceInternal::Legacy(_) => Colorspace::Default,
+        }
+    }
+
+    /// Set the [`Colorspace`] signalled to the connected displays after the next commit
+    ///
+    /// Fails if any pending connector does not support the `Colorspace` property,
+    /// which is always the case for legacy devices.
+    pub fn set_colorspace(&self, colorspace: Colorspace) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_colorspace(colorspace),
+            DrmSurfaceInternal::Legacy(_) if colorspace != Colorspace::Default => {
+                Err(Error::UnknownProperty {
+                    handle: self.crtc.into(),
+                    name: "Colorspace",
+                })
+            }
+            DrmSurfaceInternal::Legacy(_) => Ok(()),
+        }
+    }
+
+    fn connector_property(
+        &self,
+        connector: connector::Handle,
+        name: &str,
+    ) -> Result<Option<(property::Info, property::RawValue)>, Error> {
+        let props = self.get_properties(connector).map_err(|source| {
+            Error::Access(AccessError {
+                errmsg: "Failed to get properties of connector",
+                dev: self.dev_path(),
+                source,
+            })
+        })?;
+        let (ids, vals) = props.as_props_and_values();
+        for (&id, &val) in ids.iter().zip(vals.iter()) {
+            let info = self.get_property(id).map_err(|source| {
+                Error::Access(AccessError {
+                    errmsg: "Failed to get property info",
+                    dev: self.dev_path(),
+                    source,
+                })
+            })?;
+            if info.name().to_str().map(|x| x == name).unwrap_or(false) {
+                return Ok(Some((info, val)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Disables the given plane.
     ///
     /// Errors if the plane is not supported by this crtc or if the underlying
@@ -310,6 +606,9 @@ impl DrmSurface {
     /// - [`add_connector`](DrmSurface::add_connector)
     /// - [`remove_connector`](DrmSurface::remove_connector)
     /// - [`use_mode`](DrmSurface::use_mode)
+    /// - [`restore_state`](DrmSurface::restore_state)
+    /// - [`queue_writeback`](DrmSurface::queue_writeback), or the first frame after a capture, as long as the
+    ///   writeback connector needs to be attached to or detached from the crtc
     pub fn commit_pending(&self) -> bool {
         match &*self.internal {
             DrmSurfaceInternal::Atomic(surf) => surf.commit_pending(),
@@ -344,6 +643,22 @@ impl DrmSurface {
         }
     }
 
+    /// Test the planes staged in a [`PlaneFrame`] together with the pending state
+    ///
+    /// Returns [`Error::PlaneNotCompatible`] if a staged plane is not available for this surface.
+    /// See [`test_state`](DrmSurface::test_state) for details.
+    #[profiling::function]
+    pub fn test_frame(&self, frame: &PlaneFrame<'_>, allow_modeset: bool) -> Result<(), Error> {
+        if let Some(state) = frame
+            .states()
+            .iter()
+            .find(|state| !self.planes.contains(state.handle))
+        {
+            return Err(Error::PlaneNotCompatible(self.crtc, state.handle));
+        }
+        self.test_state(frame.states().iter().cloned(), allow_modeset)
+    }
+
     /// Commit the pending state rendering a given set of framebuffers.
     ///
     /// *Note*: This will trigger a full modeset on the underlying device,
@@ -383,11 +698,79 @@ impl DrmSurface {
         event: bool,
     ) -> Result<(), Error> {
         match &*self.internal {
-            DrmSurfaceInternal::Atomic(surf) => surf.page_flip(planes, event),
+            DrmSurfaceInternal::Atomic(surf) => surf.page_flip(planes, event, false),
             DrmSurfaceInternal::Legacy(surf) => {
                 let fb = ensure_legacy_planes(self, planes)?;
-                surf.page_flip(fb, event)
+                surf.page_flip(fb, event, false)
+            }
+        }
+    }
+
+    /// Returns whether the driver supports asynchronous page flips on this surface
+    ///
+    /// See [`page_flip_async`](DrmSurface::page_flip_async).
+    pub fn async_page_flip_supported(&self) -> bool {
+        let cap = match &*self.internal {
+            DrmSurfaceInternal::Atomic(_) => DriverCapability::AtomicASyncPageFlip,
+            DrmSurfaceInternal::Legacy(_) => DriverCapability::ASyncPageFlip,
+        };
+        self.get_driver_capability(cap).is_ok_and(|value| value == 1)
+    }
+
+    /// Page-flip the underlying [`crtc`](drm::control::crtc) to a new given set of [`framebuffer`]s
+    /// immediately instead of waiting for the next vblank.
+    ///
+    /// This reduces latency at the cost of visible tearing. Drivers usually only accept asynchronous
+    /// flips changing nothing but the framebuffer of the primary plane, if the driver does not support
+    /// them or rejects the flip, a regular flip synchronized to the vblank is done instead.
+    ///
+    /// Returns whether the flip was done asynchronously. Just like for [`page_flip`](DrmSurface::page_flip)
+    /// a `vblank` event is produced once the flip happened.
+    #[profiling::function]
+    pub fn page_flip_async<'a>(
+        &self,
+        planes: impl IntoIterator<Item = PlaneState<'a>>,
+        event: bool,
+    ) -> Result<bool, Error> {
+        if !self.async_page_flip_supported() {
+            return self.page_flip(planes, event).map(|_| false);
+        }
+
+        let planes = planes.into_iter().collect::<Vec<_>>();
+        let res = match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.page_flip(planes.iter().cloned(), event, true),
+            DrmSurfaceInternal::Legacy(surf) => {
+                let fb = ensure_legacy_planes(self, planes.iter().cloned())?;
+                surf.page_flip(fb, event, true)
+            }
+        };
+        match res {
+            Ok(()) => Ok(true),
+            Err(Error::Access(ref access)) if access.source.kind() == io::ErrorKind::InvalidInput => {
+                tracing::debug!(crtc = ?self.crtc, "Asynchronous page flip rejected, falling back to vsync");
+                self.page_flip(planes, event).map(|_| false)
             }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Queues a capture of the next frame by a writeback connector into the given framebuffer
+    ///
+    /// The capture is submitted with the next [`commit`](DrmSurface::commit) or
+    /// [`page_flip`](DrmSurface::page_flip), replacing any capture queued before. Page flips only submit it
+    /// if the connector is already attached to the crtc by a previous commit, asynchronous page flips
+    /// postpone it to the next regular flip. See the [`writeback`](super::writeback) module for details.
+    ///
+    /// Returns [`Error::WritebackNotSupported`] for legacy devices.
+    pub fn queue_writeback(
+        &self,
+        connector: &WritebackConnector,
+        framebuffer: framebuffer::Handle,
+    ) -> Result<WritebackJob, Error> {
+        self.ensure_not_leased(&[connector.handle()])?;
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => Ok(surf.queue_writeback(connector, framebuffer)),
+            DrmSurfaceInternal::Legacy(_) => Err(Error::WritebackNotSupported),
         }
     }
 
@@ -396,15 +779,24 @@ impl DrmSurface {
         &self.planes
     }
 
+    /// Returns the formats that can be scanned out on this surface
+    ///
+    /// This includes the formats of the primary plane and of all overlay planes.
+    pub fn scanout_formats(&self) -> FormatSet {
+        self.plane_info()
+            .formats
+            .iter()
+            .chain(self.planes.overlay.iter().flat_map(|plane| plane.formats.iter()))
+            .copied()
+            .collect()
+    }
+
     /// Claim a plane so that it won't be used by a different crtc
     ///  
     /// Returns `None` if the plane could not be claimed
     pub fn claim_plane(&self, plane: plane::Handle) -> Option<PlaneClaim> {
         // Validate that we are called with an plane that belongs to us
-        if self.planes.primary.iter().any(|p| p.handle == plane)
-            || self.planes.cursor.iter().any(|p| p.handle == plane)
-            || self.planes.overlay.iter().any(|p| p.handle == plane)
-        {
+        if self.planes.contains(plane) {
             self.plane_claim_storage.claim(plane, self.crtc)
         } else {
             None
@@ -426,6 +818,24 @@ impl DrmSurface {
         }
     }
 
+    /// Forces the next commit to restore the full state of the crtc.
+    ///
+    /// Many drivers lose the state of the display pipeline across a system suspend,
+    /// while still reporting it unchanged. Calling this function after resuming makes
+    /// [`commit_pending`](DrmSurface::commit_pending) return `true`, so the mode, connectors,
+    /// color management state and planes get committed again with the next frame.
+    ///
+    /// *Note*: On legacy devices the last gamma lookup table is re-applied immediately.
+    pub fn restore_state(&self) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => {
+                surf.restore_state();
+                Ok(())
+            }
+            DrmSurfaceInternal::Legacy(surf) => surf.restore_state(),
+        }
+    }
+
     /// Returns if the underlying device is currently paused or not.
     pub fn is_active(&self) -> bool {
         match &*self.internal {
@@ -495,3 +905,44 @@ fn ensure_legacy_planes<'a>(
 
     Ok(config.fb)
 }
+
+#[cfg(test)]
+mod tests {
+    use drm::control::{framebuffer, plane};
+
+    use super::{PlaneConfig, PlaneFrame};
+    use crate::utils::{Rectangle, Transform};
+
+    #[test]
+    fn staging_a_plane_replaces_its_state() {
+        let primary = drm::control::from_u32::<plane::Handle>(1).unwrap();
+        let overlay = drm::control::from_u32::<plane::Handle>(2).unwrap();
+        let config = |fb| PlaneConfig {
+            src: Rectangle::from_loc_and_size((0.0, 0.0), (64.0, 64.0)),
+            dst: Rectangle::from_loc_and_size((0, 0), (64, 64)),
+            transform: Transform::Normal,
+            alpha: 1.0,
+            damage_clips: None,
+            fb: drm::control::from_u32::<framebuffer::Handle>(fb).unwrap(),
+            fence: None,
+        };
+
+        let mut frame = PlaneFrame::new();
+        frame.set(primary, config(10));
+        frame.set(overlay, config(11));
+        frame.set(primary, config(12));
+        frame.clear(overlay);
+
+        let states = frame.states();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].handle, primary);
+        assert_eq!(
+            states[0].config.as_ref().map(|config| config.fb),
+            drm::control::from_u32(12)
+        );
+        assert!(frame.get(overlay).is_some_and(|state| state.config.is_none()));
+
+        assert!(frame.unstage(overlay).is_some());
+        assert_eq!(frame.into_iter().count(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! Capturing the output of a crtc with writeback connectors
+//!
+//! Some display controllers are able to write the composited output of a crtc back into memory.
+//! This is exposed as virtual connectors of type [`Writeback`](drm::control::connector::Interface::Writeback),
+//! which are only visible after enabling them with [`DrmDevice::writeback_connectors`].
+//!
+//! Compared to rendering the output a second time this is free for the gpu and captures exactly what
+//! is scanned out, including overlay and cursor planes. This makes it an alternative frame source for
+//! screencopy or recording.
+//!
+//! A capture is queued on a [`DrmSurface`](super::DrmSurface) with a framebuffer of a format
+//! supported by the connector, e.g. created from a [`Dmabuf`](crate::backend::allocator::dmabuf::Dmabuf).
+//! It is written with the next commit or page flip of the surface. The returned [`WritebackJob`]
+//! provides a [`SyncPoint`] signaled once the framebuffer contains the frame.
+//!
+//! Routing a writeback connector to a crtc or removing it again is a modeset. The connector is therefore
+//! only attached by a [`commit`](super::DrmSurface::commit) and detached by the first commit without a
+//! queued capture, while page flips only capture through an already attached connector.
+//! [`DrmSurface::commit_pending`](super::DrmSurface::commit_pending) reports when such a commit is required.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::{DrmDevice, DrmSurface};
+//! # use smithay::reexports::drm::control::framebuffer;
+//! # let device: DrmDevice = todo!();
+//! # let surface: DrmSurface = todo!();
+//! # let framebuffer: framebuffer::Handle = todo!();
+//! let connector = device
+//!     .writeback_connectors()
+//!     .expect("Failed to query writeback connectors")
+//!     .into_iter()
+//!     .next()
+//!     .expect("No writeback support");
+//!
+//! let job = surface.queue_writeback(&connector, framebuffer).unwrap();
+//!
+//! // ...commit or page flip the surface
+//!
+//! if let Some(sync) = job.sync_point() {
+//!     // the framebuffer can be read once `sync` is reached
+//! }
+//! ```
+//!
+//! [`DrmDevice::writeback_connectors`]: super::DrmDevice::writeback_connectors
+
+use std::io;
+use std::os::unix::io::{FromRawFd, OwnedFd};
+use std::sync::{Arc, Mutex};
+
+use drm::control::{atomic::AtomicModeReq, connector, crtc, framebuffer, property, Device as ControlDevice};
+
+use crate::backend::allocator::Fourcc;
+use crate::backend::renderer::sync::{SyncFile, SyncPoint};
+
+/// Writeback connector of a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WritebackConnector {
+    handle: connector::Handle,
+    formats: Vec<Fourcc>,
+    crtc_prop: property::Handle,
+    fb_prop: property::Handle,
+    out_fence_prop: property::Handle,
+}
+
+impl WritebackConnector {
+    fn new(drm: &impl ControlDevice, handle: connector::Handle) -> io::Result<Option<Self>> {
+        let props = drm.get_properties(handle)?;
+        let (handles, values) = props.as_props_and_values();
+
+        let (mut crtc_prop, mut fb_prop, mut out_fence_prop, mut formats) = (None, None, None, Vec::new());
+        for (prop, value) in handles.iter().zip(values) {
+            let info = drm.get_property(*prop)?;
+            match info.name().to_str() {
+                Ok("CRTC_ID") => crtc_prop = Some(*prop),
+                Ok("WRITEBACK_FB_ID") => fb_prop = Some(*prop),
+                Ok("WRITEBACK_OUT_FENCE_PTR") => out_fence_prop = Some(*prop),
+                Ok("WRITEBACK_PIXEL_FORMATS") => {
+                    if let Some(blob) = info.value_type().convert_value(*value).as_blob() {
+                        formats = parse_formats(&drm.get_property_blob(blob)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(crtc_prop
+            .zip(fb_prop)
+            .zip(out_fence_prop)
+            .map(|((crtc_prop, fb_prop), out_fence_prop)| WritebackConnector {
+                handle,
+                formats,
+                crtc_prop,
+                fb_prop,
+                out_fence_prop,
+            }))
+    }
+
+    /// Handle of the connector
+    pub fn handle(&self) -> connector::Handle {
+        self.handle
+    }
+
+    /// Formats of framebuffers the connector is able to write into
+    pub fn formats(&self) -> &[Fourcc] {
+        &self.formats
+    }
+
+    fn add_routing(&self, req: &mut AtomicModeReq, crtc: Option<crtc::Handle>) {
+        req.add_property(self.handle, self.crtc_prop, property::Value::CRTC(crtc));
+        if crtc.is_none() {
+            req.add_property(self.handle, self.fb_prop, property::Value::Framebuffer(None));
+        }
+    }
+}
+
+// The blob is an array of native endian fourcc codes
+fn parse_formats(blob: &[u8]) -> Vec<Fourcc> {
+    blob.chunks_exact(4)
+        .filter_map(|code| Fourcc::try_from(u32::from_ne_bytes(code.try_into().unwrap())).ok())
+        .collect()
+}
+
+/// Lists the writeback connectors of a device, which must have the writeback client capability enabled
+pub(super) fn writeback_connectors(drm: &impl ControlDevice) -> io::Result<Vec<WritebackConnector>> {
+    let mut connectors = Vec::new();
+    for handle in drm.resource_handles()?.connectors() {
+        let info = drm.get_connector(*handle, false)?;
+        if info.interface() != connector::Interface::Writeback {
+            continue;
+        }
+        if let Some(connector) = WritebackConnector::new(drm, *handle)? {
+            connectors.push(connector);
+        }
+    }
+    Ok(connectors)
+}
+
+#[derive(Debug)]
+enum JobState {
+    Queued,
+    Submitted(SyncPoint),
+    Cancelled,
+}
+
+/// Capture of a single frame of a surface by a [`WritebackConnector`]
+///
+/// Obtained from [`DrmSurface::queue_writeback`](super::DrmSurface::queue_writeback).
+/// The framebuffer must be kept alive until the capture is done.
+#[derive(Debug, Clone)]
+pub struct WritebackJob {
+    connector: connector::Handle,
+    framebuffer: framebuffer::Handle,
+    state: Arc<Mutex<JobState>>,
+}
+
+impl WritebackJob {
+    /// Connector writing the frame
+    pub fn connector(&self) -> connector::Handle {
+        self.connector
+    }
+
+    /// Framebuffer the frame is written into
+    pub fn framebuffer(&self) -> framebuffer::Handle {
+        self.framebuffer
+    }
+
+    /// Returns whether the capture was submitted to the device as part of a commit or page flip
+    pub fn is_submitted(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), JobState::Submitted(_))
+    }
+
+    /// Returns whether the capture was replaced by a newer one before being submitted
+    pub fn is_cancelled(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), JobState::Cancelled)
+    }
+
+    /// Sync point signaled once the frame was written into the framebuffer
+    ///
+    /// Returns `None` until the capture is submitted.
+    pub fn sync_point(&self) -> Option<SyncPoint> {
+        match &*self.state.lock().unwrap() {
+            JobState::Submitted(sync) => Some(sync.clone()),
+            _ => None,
+        }
+    }
+
+    fn set_state(&self, state: JobState) {
+        *self.state.lock().unwrap() = state;
+    }
+}
+
+/// Capture waiting for the next commit or page flip of a surface
+#[derive(Debug)]
+pub(crate) struct PendingWriteback {
+    connector: WritebackConnector,
+    job: WritebackJob,
+}
+
+impl PendingWriteback {
+    pub(crate) fn new(connector: &WritebackConnector, framebuffer: framebuffer::Handle) -> Self {
+        PendingWriteback {
+            connector: connector.clone(),
+            job: WritebackJob {
+                connector: connector.handle,
+                framebuffer,
+                state: Arc::new(Mutex::new(JobState::Queued)),
+            },
+        }
+    }
+
+    pub(crate) fn job(&self) -> &WritebackJob {
+        &self.job
+    }
+
+    // `out_fence` has to stay valid until the request is committed
+    fn add_framebuffer(&self, req: &mut AtomicModeReq, out_fence: &mut i32) {
+        req.add_property(
+            self.connector.handle,
+            self.connector.fb_prop,
+            property::Value::Framebuffer(Some(self.job.framebuffer)),
+        );
+        req.add_property(
+            self.connector.handle,
+            self.connector.out_fence_prop,
+            property::Value::SignedRange(out_fence as *mut i32 as i64),
+        );
+    }
+
+    /// Marks the capture as submitted with the fence returned by the kernel
+    fn submitted(self, out_fence: i32) {
+        let sync = if out_fence >= 0 {
+            // SAFETY: the kernel created a new fence fd for us
+            SyncPoint::from(SyncFile::new(unsafe { OwnedFd::from_raw_fd(out_fence) }))
+        } else {
+            SyncPoint::signaled()
+        };
+        self.job.set_state(JobState::Submitted(sync));
+    }
+
+    fn cancel(self) {
+        self.job.set_state(JobState::Cancelled);
+    }
+}
+
+/// Writeback state of a crtc
+#[derive(Debug, Default)]
+pub(crate) struct WritebackState {
+    pending: Option<PendingWriteback>,
+    // connector currently routed to the crtc
+    routed: Option<WritebackConnector>,
+}
+
+impl WritebackState {
+    /// Queues a capture, replacing any capture queued before
+    pub(crate) fn queue(
+        &mut self,
+        connector: &WritebackConnector,
+        framebuffer: framebuffer::Handle,
+    ) -> WritebackJob {
+        let writeback = PendingWriteback::new(connector, framebuffer);
+        let job = writeback.job().clone();
+        if let Some(previous) = self.pending.replace(writeback) {
+            previous.cancel();
+        }
+        job
+    }
+
+    /// Returns whether the routing of the writeback connectors has to change with a modeset
+    pub(crate) fn modeset_pending(&self) -> bool {
+        self.pending.as_ref().map(|pending| &pending.connector) != self.routed.as_ref()
+    }
+
+    /// Adds the queued capture to a request allowing modesets and routes the connectors accordingly
+    pub(crate) fn add_to_commit(&self, req: &mut AtomicModeReq, crtc: crtc::Handle, out_fence: &mut i32) {
+        let pending = self.pending.as_ref();
+        if let Some(routed) = self
+            .routed
+            .as_ref()
+            .filter(|routed| pending.map(|pending| &pending.connector) != Some(*routed))
+        {
+            routed.add_routing(req, None);
+        }
+        if let Some(pending) = pending {
+            pending.connector.add_routing(req, Some(crtc));
+            pending.add_framebuffer(req, out_fence);
+        }
+    }
+
+    /// Adds the queued capture to a request without modesets, if its connector is already routed
+    ///
+    /// Returns whether the capture was added.
+    pub(crate) fn add_to_flip(&self, req: &mut AtomicModeReq, out_fence: &mut i32) -> bool {
+        match self.pending.as_ref() {
+            Some(pending) if self.routed.as_ref() == Some(&pending.connector) => {
+                pending.add_framebuffer(req, out_fence);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Updates the state after a request built by [`WritebackState::add_to_commit`] was committed
+    pub(crate) fn committed(&mut self, out_fence: i32) {
+        self.routed = self.pending.as_ref().map(|pending| pending.connector.clone());
+        if let Some(pending) = self.pending.take() {
+            pending.submitted(out_fence);
+        }
+    }
+
+    /// Updates the state after a request with a capture added by [`WritebackState::add_to_flip`] was committed
+    pub(crate) fn flipped(&mut self, out_fence: i32) {
+        if let Some(pending) = self.pending.take() {
+            pending.submitted(out_fence);
+        }
+    }
+
+    /// Removes the routed connector from the crtc as part of disabling it
+    pub(crate) fn add_to_clear(&self, req: &mut AtomicModeReq) {
+        if let Some(routed) = self.routed.as_ref() {
+            routed.add_routing(req, None);
+        }
+    }
+
+    /// Updates the state after the crtc was disabled
+    pub(crate) fn cleared(&mut self) {
+        self.routed = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_formats, Fourcc, PendingWriteback, WritebackConnector, WritebackState};
+    use drm::control::{atomic::AtomicModeReq, connector, framebuffer, property, RawResourceHandle};
+
+    #[test]
+    fn writeback_jobs_track_submission() {
+        let blob = [Fourcc::Xrgb8888 as u32, 0, Fourcc::Abgr2101010 as u32]
+            .iter()
+            .flat_map(|code| code.to_ne_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(parse_formats(&blob), vec![Fourcc::Xrgb8888, Fourcc::Abgr2101010]);
+
+        let handle = |id| RawResourceHandle::new(id).unwrap();
+        let connector = WritebackConnector {
+            handle: connector::Handle::from(handle(1)),
+            formats: parse_formats(&blob),
+            crtc_prop: property::Handle::from(handle(2)),
+            fb_prop: property::Handle::from(handle(3)),
+            out_fence_prop: property::Handle::from(handle(4)),
+        };
+        let framebuffer = framebuffer::Handle::from(handle(5));
+
+        let cancelled = PendingWriteback::new(&connector, framebuffer);
+        let job = cancelled.job().clone();
+        cancelled.cancel();
+        assert!(job.is_cancelled() && job.sync_point().is_none());
+
+        let pending = PendingWriteback::new(&connector, framebuffer);
+        let job = pending.job().clone();
+        assert!(!job.is_submitted());
+        pending.submitted(-1);
+        assert!(job.is_submitted());
+        assert!(job.sync_point().unwrap().is_reached());
+    }
+
+    #[test]
+    fn writeback_routing_requires_commits() {
+        let handle = |id| RawResourceHandle::new(id).unwrap();
+        let connector = WritebackConnector {
+            handle: connector::Handle::from(handle(1)),
+            formats: vec![Fourcc::Xrgb8888],
+            crtc_prop: property::Handle::from(handle(2)),
+            fb_prop: property::Handle::from(handle(3)),
+            out_fence_prop: property::Handle::from(handle(4)),
+        };
+        let framebuffer = framebuffer::Handle::from(handle(5));
+        let mut out_fence = -1;
+        let mut state = WritebackState::default();
+        assert!(!state.modeset_pending());
+
+        // the first capture has to route the connector with a commit
+        let job = state.queue(&connector, framebuffer);
+        assert!(state.modeset_pending());
+        assert!(!state.add_to_flip(&mut AtomicModeReq::new(), &mut out_fence));
+        state.committed(-1);
+        assert!(job.is_submitted());
+
+        // following captures can be done by page flips..
+        let job = state.queue(&connector, framebuffer);
+        assert!(!state.modeset_pending());
+        assert!(state.add_to_flip(&mut AtomicModeReq::new(), &mut out_fence));
+        state.flipped(-1);
+        assert!(job.is_submitted());
+
+        // ..until the connector has to be removed from the crtc again
+        assert!(state.modeset_pending());
+        state.committed(-1);
+        assert!(!state.modeset_pending());
+    }
+}
@@ -1,5 +1,6 @@
 //! EGL context related structs
 use std::{
+    marker::PhantomData,
     os::raw::c_int,
     sync::{atomic::Ordering, Arc},
 };
@@ -413,6 +414,34 @@ impl EGLContext {
         .map_err(Into::into)
     }
 
+    /// Makes the OpenGL context the current context in the current thread with no surface bound,
+    /// until the returned guard is dropped.
+    ///
+    /// Dropping the guard restores the previously current context and surfaces of the thread.
+    /// This allows running custom GL code with a context [shared](EGLContext::new_shared) with a
+    /// renderer without disturbing the state of the renderer.
+    ///
+    /// Unlike [`EGLContext::make_current`] this is safe, as the guard can neither be sent
+    /// to another thread nor outlive the borrow of the context.
+    #[instrument(level = "trace", skip_all, parent = &self.span, err)]
+    #[profiling::function]
+    pub fn make_current_scoped(&self) -> Result<CurrentContextGuard<'_>, MakeCurrentError> {
+        let previous = unsafe {
+            (
+                ffi::egl::GetCurrentDisplay(),
+                ffi::egl::GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint),
+                ffi::egl::GetCurrentSurface(ffi::egl::READ as ffi::egl::types::EGLint),
+                ffi::egl::GetCurrentContext(),
+            )
+        };
+        unsafe { self.make_current()? };
+        Ok(CurrentContextGuard {
+            context: self,
+            previous,
+            _not_send: PhantomData,
+        })
+    }
+
     /// Returns true if the OpenGL context is the current one in the thread.
     pub fn is_current(&self) -> bool {
         unsafe { ffi::egl::GetCurrentContext() == self.context as *const _ }
@@ -476,6 +505,39 @@ impl EGLContext {
     pub fn get_context_handle(&self) -> ffi::egl::types::EGLContext {
         self.context
     }
+
+    /// Creates an `EGLImage` from a GL texture of this context
+    ///
+    /// The image has to be destroyed using `eglDestroyImageKHR` once it is not needed anymore.
+    ///
+    /// # Safety
+    ///
+    /// `texture` has to be the name of a valid 2D texture object of this context.
+    pub unsafe fn create_image_from_texture(&self, texture: u32) -> Result<ffi::egl::types::EGLImage, Error> {
+        if !self
+            .display
+            .extensions()
+            .iter()
+            .any(|ext| ext == "EGL_KHR_gl_texture_2D_image")
+        {
+            return Err(Error::EglExtensionNotSupported(&["EGL_KHR_gl_texture_2D_image"]));
+        }
+
+        let attributes = [ffi::egl::GL_TEXTURE_LEVEL as c_int, 0, ffi::egl::NONE as c_int];
+        let image = ffi::egl::CreateImageKHR(
+            **self.display.get_display_handle(),
+            self.context,
+            ffi::egl::GL_TEXTURE_2D,
+            texture as usize as ffi::egl::types::EGLClientBuffer,
+            attributes.as_ptr(),
+        );
+
+        if image == ffi::egl::NO_IMAGE_KHR {
+            Err(Error::EGLImageCreationFailed)
+        } else {
+            Ok(image)
+        }
+    }
 }
 
 impl Drop for EGLContext {
@@ -492,6 +554,44 @@ impl Drop for EGLContext {
     }
 }
 
+/// Guard returned by [`EGLContext::make_current_scoped`]
+///
+/// Keeps the context current in the current thread, until the guard is dropped.
+#[derive(Debug)]
+pub struct CurrentContextGuard<'a> {
+    context: &'a EGLContext,
+    previous: (
+        ffi::egl::types::EGLDisplay,
+        ffi::egl::types::EGLSurface,
+        ffi::egl::types::EGLSurface,
+        ffi::egl::types::EGLContext,
+    ),
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<'a> CurrentContextGuard<'a> {
+    /// Returns the context, that is current while this guard exists
+    pub fn context(&self) -> &'a EGLContext {
+        self.context
+    }
+}
+
+impl Drop for CurrentContextGuard<'_> {
+    fn drop(&mut self) {
+        let (display, draw, read, context) = self.previous;
+        let res = if context == ffi::egl::NO_CONTEXT {
+            self.context.unbind()
+        } else {
+            wrap_egl_call_bool(|| unsafe { ffi::egl::MakeCurrent(display, draw, read, context) })
+                .map(|_| ())
+                .map_err(Into::into)
+        };
+        if let Err(err) = res {
+            warn!(?err, "Failed to restore the previously current context");
+        }
+    }
+}
+
 /// Attributes to use when creating an OpenGL context.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct GlAttributes {
@@ -724,6 +724,36 @@ impl EGLDisplay {
     #[instrument(level = "trace", skip(self), parent = &self.span, err)]
     #[profiling::function]
     pub fn create_image_from_dmabuf(&self, dmabuf: &Dmabuf) -> Result<EGLImage, Error> {
+        let planes = (0..dmabuf.num_planes()).collect::<Vec<_>>();
+        self.create_image_from_dmabuf_planes(dmabuf, dmabuf.format().code, dmabuf.size(), &planes)
+    }
+
+    /// Imports a single plane of a multi-planar [`Dmabuf`] as an [`EGLImage`]
+    ///
+    /// The plane is interpreted as a buffer of the given `format` and `size`, e.g. the chroma plane
+    /// of a [`Fourcc::Nv12`] buffer can be imported as a [`Fourcc::Gr88`] image of half the size.
+    #[instrument(level = "trace", skip(self), parent = &self.span, err)]
+    #[profiling::function]
+    pub fn create_image_from_dmabuf_plane(
+        &self,
+        dmabuf: &Dmabuf,
+        plane: usize,
+        format: Fourcc,
+        size: Size<i32, BufferCoords>,
+    ) -> Result<EGLImage, Error> {
+        if plane >= dmabuf.num_planes() {
+            return Err(Error::EGLImageCreationFailed);
+        }
+        self.create_image_from_dmabuf_planes(dmabuf, format, size, &[plane])
+    }
+
+    fn create_image_from_dmabuf_planes(
+        &self,
+        dmabuf: &Dmabuf,
+        format: Fourcc,
+        size: Size<i32, BufferCoords>,
+        planes: &[usize],
+    ) -> Result<EGLImage, Error> {
         if !self.extensions.iter().any(|s| s == "EGL_KHR_image_base")
             && !self
                 .extensions
@@ -751,11 +781,11 @@ impl EGLDisplay {
 
         out.extend([
             ffi::egl::WIDTH as i32,
-            dmabuf.width() as i32,
+            size.w,
             ffi::egl::HEIGHT as i32,
-            dmabuf.height() as i32,
+            size.h,
             ffi::egl::LINUX_DRM_FOURCC_EXT as i32,
-            dmabuf.format().code as u32 as i32,
+            format as u32 as i32,
         ]);
 
         let names = [
@@ -794,6 +824,9 @@ impl EGLDisplay {
             .zip(dmabuf.offsets())
             .zip(dmabuf.strides())
             .enumerate()
+            .filter(|(plane, _)| planes.contains(plane))
+            .map(|(_, plane)| plane)
+            .enumerate()
         {
             out.extend([
                 names[i][0] as i32,
@@ -12,6 +12,7 @@ use crate::backend::egl::{
     native::EGLNativeSurface,
     EGLError, SwapBuffersError,
 };
+use crate::backend::renderer::damage::BufferAge;
 use crate::utils::{Physical, Rectangle, Size};
 
 use tracing::{debug, info_span, instrument};
@@ -251,6 +252,13 @@ impl EGLSurface {
     }
 }
 
+impl BufferAge for EGLSurface {
+    #[inline]
+    fn buffer_age(&self) -> Option<usize> {
+        EGLSurface::buffer_age(self).map(|age| age.max(0) as usize)
+    }
+}
+
 impl Drop for EGLSurface {
     fn drop(&mut self) {
         unsafe {
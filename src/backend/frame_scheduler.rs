@@ -0,0 +1,211 @@
+//! Predictive scheduling of output repaints
+//!
+//! Repainting an output right after a vblank gives clients almost no chance to get their next buffer
+//! into the following frame, adding a frame of latency. Repainting too late misses the vblank instead.
+//!
+//! A [`FrameScheduler`] predicts the vblanks of an output from the last presentation and the refresh
+//! interval and estimates the render time of the compositor from a sliding window of past repaints.
+//! Composition is scheduled as late as possible before the next reachable vblank, keeping a configurable
+//! slack as a safety margin. The predicted presentation time can be used as the target time for
+//! clients, e.g. for presentation feedback or the commit-timing protocol.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use smithay::backend::frame_scheduler::FrameScheduler;
+//! # use smithay::utils::{Clock, Monotonic};
+//! let clock = Clock::<Monotonic>::new();
+//! let mut scheduler = FrameScheduler::new(Duration::from_micros(16_667));
+//!
+//! // on every vblank or page flip event
+//! scheduler.presented(clock.now());
+//! let delay = scheduler.time_until_render(clock.now());
+//!
+//! // ...once the timer of `delay` fired
+//! scheduler.render_started(clock.now());
+//! // ...render and queue the frame, ideally waiting for rendering to complete
+//! scheduler.render_finished(clock.now());
+//! ```
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::utils::{Monotonic, Time};
+
+// Number of past repaints used to predict the render time
+const RENDER_TIME_WINDOW: usize = 32;
+const DEFAULT_SLACK: Duration = Duration::from_millis(1);
+
+/// Schedules the repaints of a single output
+#[derive(Debug, Clone)]
+pub struct FrameScheduler {
+    refresh: Duration,
+    slack: Duration,
+    last_presentation: Option<Duration>,
+    render_start: Option<Duration>,
+    render_times: VecDeque<Duration>,
+}
+
+impl FrameScheduler {
+    /// Create a scheduler for an output with the given refresh interval
+    pub fn new(refresh: Duration) -> Self {
+        FrameScheduler {
+            refresh,
+            slack: DEFAULT_SLACK,
+            last_presentation: None,
+            render_start: None,
+            render_times: VecDeque::with_capacity(RENDER_TIME_WINDOW),
+        }
+    }
+
+    /// Create a scheduler for an output with the given refresh rate in mHz, as used by output modes
+    pub fn from_refresh_rate(refresh_rate: i32) -> Self {
+        Self::new(refresh_interval(refresh_rate))
+    }
+
+    /// Returns the refresh interval of the output
+    pub fn refresh(&self) -> Duration {
+        self.refresh
+    }
+
+    /// Update the refresh interval, e.g. after a mode change
+    ///
+    /// Predictions are based on the next presentation afterwards.
+    pub fn set_refresh(&mut self, refresh: Duration) {
+        if refresh != self.refresh {
+            self.refresh = refresh;
+            self.last_presentation = None;
+        }
+    }
+
+    /// Returns the safety margin kept between the end of a repaint and the vblank
+    pub fn slack(&self) -> Duration {
+        self.slack
+    }
+
+    /// Set the safety margin kept between the end of a repaint and the vblank
+    ///
+    /// A larger slack makes missed vblanks less likely at the cost of latency. Defaults to 1ms.
+    pub fn set_slack(&mut self, slack: Duration) {
+        self.slack = slack;
+    }
+
+    /// Notify the scheduler about a presentation, usually the timestamp of a vblank or page flip event
+    pub fn presented(&mut self, time: Time<Monotonic>) {
+        self.last_presentation = Some(time.into());
+    }
+
+    /// Notify the scheduler that a repaint started
+    pub fn render_started(&mut self, time: Time<Monotonic>) {
+        self.render_start = Some(time.into());
+    }
+
+    /// Notify the scheduler that the repaint started last finished
+    ///
+    /// For accurate predictions this should include the time the gpu needs to finish rendering.
+    pub fn render_finished(&mut self, time: Time<Monotonic>) {
+        if let Some(start) = self.render_start.take() {
+            self.add_render_time(Duration::from(time).saturating_sub(start));
+        }
+    }
+
+    /// Add the duration of a repaint measured by other means
+    pub fn add_render_time(&mut self, duration: Duration) {
+        if self.render_times.len() == RENDER_TIME_WINDOW {
+            self.render_times.pop_front();
+        }
+        self.render_times.push_back(duration);
+    }
+
+    /// Predicted duration of the next repaint
+    ///
+    /// This is the longest of the recent repaints, so a single fast frame does not cause the next one
+    /// to miss its vblank.
+    pub fn predicted_render_time(&self) -> Duration {
+        self.render_times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Predicted presentation time of a repaint starting at `now`
+    ///
+    /// Returns `None` until the first presentation is known.
+    pub fn next_presentation_time(&self, now: Time<Monotonic>) -> Option<Time<Monotonic>> {
+        let earliest = Duration::from(now) + self.predicted_render_time() + self.slack;
+        self.vblank_at_or_after(earliest).map(Time::from)
+    }
+
+    /// Latest time to start the repaint for the next reachable vblank
+    ///
+    /// Returns `now` if the presentation times are not known yet.
+    pub fn next_render_time(&self, now: Time<Monotonic>) -> Time<Monotonic> {
+        self.next_presentation_time(now)
+            .map(|presentation| {
+                let deadline =
+                    Duration::from(presentation).saturating_sub(self.predicted_render_time() + self.slack);
+                Time::from(deadline.max(now.into()))
+            })
+            .unwrap_or(now)
+    }
+
+    /// Delay from `now` until the repaint should start
+    pub fn time_until_render(&self, now: Time<Monotonic>) -> Duration {
+        Time::elapsed(&now, self.next_render_time(now))
+    }
+
+    fn vblank_at_or_after(&self, time: Duration) -> Option<Duration> {
+        let last = self.last_presentation?;
+        if time <= last || self.refresh.is_zero() {
+            return Some(last.max(time));
+        }
+        let refresh = self.refresh.as_nanos();
+        let frames = ((time - last).as_nanos() + refresh - 1) / refresh;
+        Some(last + Duration::from_nanos((frames * refresh) as u64))
+    }
+}
+
+/// Refresh interval of a refresh rate in mHz
+pub(crate) fn refresh_interval(refresh_rate: i32) -> Duration {
+    if refresh_rate > 0 {
+        Duration::from_nanos(1_000_000_000_000 / refresh_rate as u64)
+    } else {
+        Duration::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameScheduler;
+    use std::time::Duration;
+
+    use crate::utils::{Monotonic, Time};
+
+    fn ms(ms: u64) -> Time<Monotonic> {
+        Time::from(Duration::from_millis(ms))
+    }
+
+    #[test]
+    fn repaints_start_as_late_as_possible() {
+        let mut scheduler = FrameScheduler::new(Duration::from_millis(10));
+        assert_eq!(scheduler.time_until_render(ms(1000)), Duration::ZERO);
+
+        scheduler.presented(ms(1000));
+        scheduler.render_started(ms(1000));
+        scheduler.render_finished(ms(1002));
+        scheduler.add_render_time(Duration::from_millis(3));
+        assert_eq!(scheduler.predicted_render_time(), Duration::from_millis(3));
+
+        // 3ms render time and 1ms slack before the vblank at 1010ms
+        assert_eq!(scheduler.next_presentation_time(ms(1001)), Some(ms(1010)));
+        assert_eq!(scheduler.time_until_render(ms(1001)), Duration::from_millis(5));
+        // too late for the next vblank
+        assert_eq!(scheduler.next_presentation_time(ms(1008)), Some(ms(1020)));
+        assert_eq!(scheduler.next_render_time(ms(1008)), ms(1016));
+
+        scheduler.set_slack(Duration::from_millis(7));
+        assert_eq!(scheduler.next_presentation_time(ms(1000)), Some(ms(1010)));
+        assert_eq!(scheduler.next_render_time(ms(1000)), ms(1000));
+
+        assert_eq!(
+            FrameScheduler::from_refresh_rate(60_000).refresh(),
+            Duration::from_nanos(16_666_666)
+        );
+    }
+}
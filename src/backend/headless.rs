@@ -0,0 +1,426 @@
+//! Headless backend with virtual outputs
+//!
+//! Remote sessions or automated tests of a compositor need outputs without any display hardware.
+//! A [`HeadlessBackend`] manages a set of [`VirtualOutput`]s, which can be added and removed at
+//! runtime and use arbitrary modes.
+//!
+//! Every virtual output is driven by a [`VBlankSource`], a [`calloop`] timer emitting a [`VBlank`]
+//! event per refresh interval of its mode, similar to the vblank events of a real display. A frame
+//! queued with [`VirtualOutput::queue_frame`] is considered presented with the next vblank.
+//!
+//! Optionally a virtual output can render into dmabuf-backed framebuffers of a swapchain, set with
+//! [`VirtualOutput::set_allocator`]. The buffer of the last presented frame remains available through
+//! [`VirtualOutput::front_buffer`], e.g. for screencasting or comparing the output in tests.
+//!
+//! ```no_run
+//! use smithay::backend::headless::HeadlessBackend;
+//! use smithay::output::Mode;
+//!
+//! # struct State { headless: HeadlessBackend }
+//! let mut event_loop = calloop::EventLoop::<State>::try_new().unwrap();
+//! let mut state = State { headless: HeadlessBackend::new() };
+//!
+//! let mode = Mode { size: (1920, 1080).into(), refresh: 60_000 };
+//! let (output, vblanks) = state.headless.add_output("HEADLESS-1", mode);
+//! // ...advertise `output.output()` to clients
+//!
+//! event_loop
+//!     .handle()
+//!     .insert_source(vblanks, |vblank, output, _state| {
+//!         if vblank.presented {
+//!             // ...send frame callbacks and render the next frame
+//!             output.queue_frame(None);
+//!         }
+//!     })
+//!     .unwrap();
+//! output.queue_frame(None);
+//!
+//! // ...later
+//! state.headless.remove_output(&output);
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug, info, info_span};
+
+use crate::{
+    backend::allocator::{
+        dmabuf::{AnyError, Dmabuf},
+        Allocator, Fourcc, Modifier, Slot, Swapchain,
+    },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    utils::{Clock, Monotonic, Time},
+};
+
+use super::frame_scheduler::refresh_interval;
+
+// refresh interval used for modes without a refresh rate
+const DEFAULT_REFRESH: Duration = Duration::from_nanos(16_666_667);
+
+type DmabufSwapchain = Swapchain<Box<dyn Allocator<Buffer = Dmabuf, Error = AnyError>>>;
+
+/// Backend managing virtual outputs
+#[derive(Debug)]
+pub struct HeadlessBackend {
+    outputs: Vec<VirtualOutput>,
+    span: tracing::Span,
+}
+
+impl Default for HeadlessBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeadlessBackend {
+    /// Create a backend without any outputs
+    pub fn new() -> HeadlessBackend {
+        HeadlessBackend {
+            outputs: Vec::new(),
+            span: info_span!("backend_headless"),
+        }
+    }
+
+    /// Add a virtual output with the given name and mode
+    ///
+    /// The returned [`VBlankSource`] drives the output and needs to be inserted into the event loop.
+    pub fn add_output(&mut self, name: impl Into<String>, mode: Mode) -> (VirtualOutput, VBlankSource) {
+        let _guard = self.span.enter();
+        let name = name.into();
+        info!(name, ?mode, "Adding virtual output");
+
+        let output = Output::new(
+            name.clone(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Virtual".into(),
+                serial_number: name.clone(),
+            },
+        );
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+
+        let virtual_output = VirtualOutput {
+            inner: Rc::new(RefCell::new(VirtualOutputState {
+                name,
+                mode,
+                output,
+                removed: false,
+                sequence: 0,
+                pending: None,
+                swapchain: None,
+                front_buffer: None,
+            })),
+        };
+        self.outputs.push(virtual_output.clone());
+
+        let source = VBlankSource {
+            output: virtual_output.clone(),
+            timer: Timer::from_duration(refresh(mode)),
+            clock: Clock::new(),
+        };
+        (virtual_output, source)
+    }
+
+    /// Remove a virtual output
+    ///
+    /// Its [`VBlankSource`] removes itself from the event loop with the next refresh.
+    pub fn remove_output(&mut self, output: &VirtualOutput) {
+        let _guard = self.span.enter();
+        if let Some(idx) = self.outputs.iter().position(|o| o == output) {
+            let output = self.outputs.remove(idx);
+            let mut state = output.inner.borrow_mut();
+            info!(name = state.name, "Removing virtual output");
+            state.removed = true;
+        }
+    }
+
+    /// Iterate over the virtual outputs of the backend
+    pub fn outputs(&self) -> impl Iterator<Item = &VirtualOutput> {
+        self.outputs.iter()
+    }
+}
+
+struct VirtualOutputState {
+    name: String,
+    mode: Mode,
+    output: Output,
+    removed: bool,
+    sequence: u64,
+    // frame waiting for the next vblank, optionally with its buffer
+    pending: Option<Option<Slot<Dmabuf>>>,
+    swapchain: Option<DmabufSwapchain>,
+    front_buffer: Option<Slot<Dmabuf>>,
+}
+
+impl fmt::Debug for VirtualOutputState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualOutputState")
+            .field("name", &self.name)
+            .field("mode", &self.mode)
+            .field("removed", &self.removed)
+            .field("sequence", &self.sequence)
+            .field("swapchain", &self.swapchain)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Output of a [`HeadlessBackend`] not backed by any display
+#[derive(Debug, Clone)]
+pub struct VirtualOutput {
+    inner: Rc<RefCell<VirtualOutputState>>,
+}
+
+impl PartialEq for VirtualOutput {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for VirtualOutput {}
+
+impl VirtualOutput {
+    /// Name of the output
+    pub fn name(&self) -> String {
+        self.inner.borrow().name.clone()
+    }
+
+    /// [`Output`] representing the virtual output
+    pub fn output(&self) -> Output {
+        self.inner.borrow().output.clone()
+    }
+
+    /// Current mode of the output
+    pub fn mode(&self) -> Mode {
+        self.inner.borrow().mode
+    }
+
+    /// Change the mode of the output
+    ///
+    /// The new refresh rate is used starting with the next vblank. Buffers of the swapchain
+    /// are reallocated with the new size.
+    pub fn set_mode(&self, mode: Mode) {
+        let mut state = self.inner.borrow_mut();
+        debug!(name = state.name, ?mode, "Changing mode of virtual output");
+        state.mode = mode;
+        state.output.add_mode(mode);
+        state.output.change_current_state(Some(mode), None, None, None);
+        state.output.set_preferred(mode);
+        if let Some(swapchain) = state.swapchain.as_mut() {
+            swapchain.resize(mode.size.w as u32, mode.size.h as u32);
+        }
+    }
+
+    /// Returns whether the output was removed from its backend
+    pub fn is_removed(&self) -> bool {
+        self.inner.borrow().removed
+    }
+
+    /// Number of vblanks of the output so far
+    pub fn sequence(&self) -> u64 {
+        self.inner.borrow().sequence
+    }
+
+    /// Render into dmabufs created by `allocator` with the given format
+    ///
+    /// Allocators producing other buffer types can be wrapped into a
+    /// [`DmabufAllocator`](crate::backend::allocator::dmabuf::DmabufAllocator).
+    pub fn set_allocator<A>(&self, allocator: A, fourcc: Fourcc, modifiers: Vec<Modifier>)
+    where
+        A: Allocator<Buffer = Dmabuf, Error = AnyError> + 'static,
+    {
+        let mut state = self.inner.borrow_mut();
+        let size = state.mode.size;
+        state.swapchain = Some(Swapchain::new(
+            Box::new(allocator),
+            size.w as u32,
+            size.h as u32,
+            fourcc,
+            modifiers,
+        ));
+    }
+
+    /// Acquire the buffer to render the next frame into
+    ///
+    /// Returns `None` if no [allocator](VirtualOutput::set_allocator) is set
+    /// or all buffers are still in use. A reference to the slot can be bound by the dmabuf capable
+    /// renderers, [`OutputDamageTracker::render_output_to`] uses the age of the slot to only redraw
+    /// what changed since the buffer was last used.
+    ///
+    /// [`OutputDamageTracker::render_output_to`]: crate::backend::renderer::damage::OutputDamageTracker::render_output_to
+    pub fn next_buffer(&self) -> Result<Option<Slot<Dmabuf>>, AnyError> {
+        match self.inner.borrow_mut().swapchain.as_mut() {
+            Some(swapchain) => swapchain.acquire(),
+            None => Ok(None),
+        }
+    }
+
+    /// Queue a frame to be presented with the next vblank
+    ///
+    /// `buffer` is the buffer the frame was rendered into, if any. Queueing a new frame
+    /// before the next vblank replaces the previous one.
+    pub fn queue_frame(&self, buffer: Option<Slot<Dmabuf>>) {
+        self.inner.borrow_mut().pending = Some(buffer);
+    }
+
+    /// Returns whether a frame is waiting for the next vblank
+    pub fn frame_pending(&self) -> bool {
+        self.inner.borrow().pending.is_some()
+    }
+
+    /// Buffer of the last presented frame
+    pub fn front_buffer(&self) -> Option<Dmabuf> {
+        self.inner.borrow().front_buffer.as_deref().cloned()
+    }
+}
+
+/// Vblank of a [`VirtualOutput`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VBlank {
+    /// Number of the vblank, starting at 1
+    pub sequence: u64,
+    /// Time of the vblank
+    pub time: Time<Monotonic>,
+    /// Whether a queued frame was presented with this vblank
+    pub presented: bool,
+}
+
+/// Timer driving the vblanks of a [`VirtualOutput`]
+///
+/// The virtual output is passed as the metadata of the events.
+#[derive(Debug)]
+pub struct VBlankSource {
+    output: VirtualOutput,
+    timer: Timer,
+    clock: Clock<Monotonic>,
+}
+
+impl EventSource for VBlankSource {
+    type Event = VBlank;
+    type Metadata = VirtualOutput;
+    type Ret = ();
+    type Error = <Timer as EventSource>::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(VBlank, &mut VirtualOutput),
+    {
+        let VBlankSource { output, timer, clock } = self;
+        timer.process_events(readiness, token, |deadline, _| {
+            let (vblank, refresh) = {
+                let mut state = output.inner.borrow_mut();
+                if state.removed {
+                    return TimeoutAction::Drop;
+                }
+
+                state.sequence += 1;
+                let presented = match state.pending.take() {
+                    Some(buffer) => {
+                        if let (Some(swapchain), Some(buffer)) = (state.swapchain.as_mut(), buffer.as_ref()) {
+                            swapchain.submitted(buffer);
+                        }
+                        if buffer.is_some() {
+                            state.front_buffer = buffer;
+                        }
+                        true
+                    }
+                    None => false,
+                };
+                let vblank = VBlank {
+                    sequence: state.sequence,
+                    time: clock.now(),
+                    presented,
+                };
+                (vblank, refresh(state.mode))
+            };
+
+            callback(vblank, &mut output.clone());
+
+            // skip vblanks missed while the event loop was busy
+            let now = Instant::now();
+            let mut next = deadline + refresh;
+            if next <= now {
+                let missed = (now - deadline).as_nanos() / refresh.as_nanos();
+                next = deadline + refresh * (missed as u32 + 1);
+            }
+            TimeoutAction::ToInstant(next)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+fn refresh(mode: Mode) -> Duration {
+    Some(refresh_interval(mode.refresh))
+        .filter(|refresh| !refresh.is_zero())
+        .unwrap_or(DEFAULT_REFRESH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeadlessBackend;
+    use crate::output::Mode;
+    use std::time::Duration;
+
+    #[test]
+    fn virtual_outputs_emit_vblanks() {
+        let mut event_loop = calloop::EventLoop::<Vec<(u64, bool)>>::try_new().unwrap();
+        let mut backend = HeadlessBackend::new();
+        let mode = Mode {
+            size: (64, 32).into(),
+            refresh: 1_000_000,
+        };
+        let (output, source) = backend.add_output("HEADLESS-1", mode);
+        event_loop
+            .handle()
+            .insert_source(source, |vblank, _, vblanks| {
+                vblanks.push((vblank.sequence, vblank.presented))
+            })
+            .unwrap();
+
+        let mut vblanks = Vec::new();
+        output.queue_frame(None);
+        while vblanks.len() < 2 {
+            event_loop
+                .dispatch(Some(Duration::from_millis(100)), &mut vblanks)
+                .unwrap();
+        }
+        assert_eq!(vblanks[..2], [(1, true), (2, false)]);
+        assert_eq!(output.sequence(), 2);
+        assert!(output.front_buffer().is_none());
+
+        backend.remove_output(&output);
+        assert!(output.is_removed());
+        assert_eq!(backend.outputs().count(), 0);
+        event_loop
+            .dispatch(Some(Duration::from_millis(100)), &mut vblanks)
+            .unwrap();
+        assert_eq!(output.sequence(), 2);
+    }
+}
@@ -4,8 +4,10 @@ use std::path::PathBuf;
 
 pub use xkbcommon::xkb::Keycode;
 
+mod seat_assignment;
 mod tablet;
 
+pub use seat_assignment::{DeviceRule, SeatAssignment};
 pub use tablet::{
     ProximityState, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolCapabilities, TabletToolDescriptor,
     TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
@@ -37,7 +39,7 @@ pub trait Device: PartialEq + Eq + std::hash::Hash {
 }
 
 /// Set of input types a device may provide
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(missing_docs)] // self explainatory
 pub enum DeviceCapability {
     Keyboard,
@@ -813,7 +815,7 @@ pub enum InputEvent<B: InputBackend> {
 /// Converts an xorg mouse button to the format used by libinput.
 ///
 /// Taken from https://sources.debian.org/src/xserver-xorg-input-libinput/1.1.0-1/src/xf86libinput.c/?hl=1508#L236-L252
-#[cfg(any(feature = "backend_winit", feature = "backend_x11"))]
+#[cfg(any(feature = "backend_winit", feature = "backend_x11", feature = "backend_vnc"))]
 pub(crate) fn xorg_mouse_to_libinput(xorg: u32) -> u32 {
     match xorg {
         0 => 0,
@@ -0,0 +1,333 @@
+//! Assignment of input devices and outputs to multiple seats
+//!
+//! Compositors for kiosks or multi-user setups may drive several logical seats at once,
+//! each with its own keyboard focus, pointer and set of outputs. A [`SeatAssignment`] decides
+//! which seat an input device belongs to and remembers the association for later events.
+//!
+//! Devices are matched in the following order:
+//! - explicit [`DeviceRule`]s, in the order they were added,
+//! - the seat tag of the device, e.g. the logical seat name set through the `WL_SEAT` udev property
+//!   for devices of the [libinput backend](crate::backend::libinput),
+//! - the physical seat of the device, set through the `ID_SEAT` udev property, if the
+//!   [libinput backend](crate::backend::libinput::LibinputInputBackend::new_with_udev_seats) tracks
+//!   multiple physical seats,
+//! - the default seat, if any.
+//!
+//! Devices not matching any seat are meant to be ignored.
+//!
+//! The seat type is up to the compositor, e.g. a [`Seat`](crate::input::Seat) or just its name.
+//!
+//! ```no_run
+//! # use smithay::backend::input::{DeviceCapability, DeviceRule, InputEvent, SeatAssignment};
+//! # use smithay::backend::libinput::LibinputInputBackend;
+//! # let event: InputEvent<LibinputInputBackend> = todo!();
+//! let mut seats = SeatAssignment::new();
+//! seats.set_default(Some("seat0"));
+//! seats.map_seat_tag("kiosk", "seat1");
+//! seats.map_physical_seat("seat-desk2", "seat2");
+//! seats.add_rule(DeviceRule::Name("Barcode Scanner".into()), "seat1");
+//!
+//! match event {
+//!     InputEvent::DeviceAdded { device } => {
+//!         if let Some(seat) = seats.assign_libinput_device(&device) {
+//!             // ...add capabilities of the device to `seat`
+//!         }
+//!     }
+//!     InputEvent::DeviceRemoved { device } => {
+//!         seats.remove_device(&device);
+//!     }
+//!     event => {
+//!         // ...route the event to `seats.device_seat(&event.device())`
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{Device, DeviceCapability};
+use crate::output::{Output, WeakOutput};
+
+/// Explicit rule matching input devices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceRule {
+    /// Matches devices by their human-readable [name](Device::name)
+    Name(String),
+    /// Matches devices by their [id](Device::id), e.g. the kernel sysname like `event3`
+    Id(String),
+    /// Matches devices whose [syspath](Device::syspath) starts with the given path
+    ///
+    /// This allows assigning all devices connected to a specific usb hub or port.
+    Syspath(PathBuf),
+    /// Matches devices by their [usb (product, vendor) id](Device::usb_id)
+    UsbId(u32, u32),
+    /// Matches devices with the given capability
+    Capability(DeviceCapability),
+}
+
+impl DeviceRule {
+    /// Returns whether the rule matches the given device
+    pub fn matches<D: Device>(&self, device: &D) -> bool {
+        match self {
+            DeviceRule::Name(name) => device.name() == *name,
+            DeviceRule::Id(id) => device.id() == *id,
+            DeviceRule::Syspath(path) => device.syspath().is_some_and(|syspath| syspath.starts_with(path)),
+            DeviceRule::UsbId(product, vendor) => device.usb_id() == Some((*product, *vendor)),
+            DeviceRule::Capability(capability) => device.has_capability(*capability),
+        }
+    }
+}
+
+/// Maps input devices and outputs to seats
+#[derive(Debug)]
+pub struct SeatAssignment<S> {
+    default: Option<S>,
+    rules: Vec<(DeviceRule, S)>,
+    tags: HashMap<String, S>,
+    physical_seats: HashMap<String, S>,
+    devices: HashMap<String, S>,
+    outputs: Vec<(WeakOutput, S)>,
+}
+
+impl<S> Default for SeatAssignment<S> {
+    fn default() -> Self {
+        SeatAssignment {
+            default: None,
+            rules: Vec::new(),
+            tags: HashMap::new(),
+            physical_seats: HashMap::new(),
+            devices: HashMap::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> SeatAssignment<S> {
+    /// Create an empty assignment, ignoring all devices
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seat receiving devices not matched by any rule or seat tag
+    pub fn default_seat(&self) -> Option<&S> {
+        self.default.as_ref()
+    }
+
+    /// Set the seat receiving devices not matched by any rule or seat tag
+    ///
+    /// With `None` unmatched devices are ignored.
+    pub fn set_default(&mut self, seat: Option<S>) {
+        self.default = seat;
+    }
+
+    /// Assign devices matching `rule` to `seat`
+    ///
+    /// Rules take precedence over seat tags and are checked in the order they were added.
+    pub fn add_rule(&mut self, rule: DeviceRule, seat: S) {
+        self.rules.push((rule, seat));
+    }
+
+    /// Remove all rules
+    pub fn clear_rules(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Assign devices tagged with the seat `tag` to `seat`
+    pub fn map_seat_tag(&mut self, tag: impl Into<String>, seat: S) {
+        self.tags.insert(tag.into(), seat);
+    }
+
+    /// Assign devices of the physical seat `name` to `seat`
+    ///
+    /// Seat tags take precedence over physical seats.
+    pub fn map_physical_seat(&mut self, name: impl Into<String>, seat: S) {
+        self.physical_seats.insert(name.into(), seat);
+    }
+
+    /// Seat a new device should be assigned to, without remembering the result
+    pub fn resolve<D: Device>(&self, device: &D, tag: Option<&str>) -> Option<&S> {
+        self.resolve_with_physical_seat(device, tag, None)
+    }
+
+    fn resolve_with_physical_seat<D: Device>(
+        &self,
+        device: &D,
+        tag: Option<&str>,
+        physical_seat: Option<&str>,
+    ) -> Option<&S> {
+        self.rules
+            .iter()
+            .find(|(rule, _)| rule.matches(device))
+            .map(|(_, seat)| seat)
+            .or_else(|| tag.and_then(|tag| self.tags.get(tag)))
+            .or_else(|| physical_seat.and_then(|name| self.physical_seats.get(name)))
+            .or(self.default.as_ref())
+    }
+
+    /// Assign a new device with an optional seat tag
+    ///
+    /// Returns the seat of the device or `None` if it should be ignored.
+    pub fn assign_device<D: Device>(&mut self, device: &D, tag: Option<&str>) -> Option<S> {
+        self.assign_with_physical_seat(device, tag, None)
+    }
+
+    fn assign_with_physical_seat<D: Device>(
+        &mut self,
+        device: &D,
+        tag: Option<&str>,
+        physical_seat: Option<&str>,
+    ) -> Option<S> {
+        let seat = self
+            .resolve_with_physical_seat(device, tag, physical_seat)
+            .cloned();
+        match &seat {
+            Some(seat) => self.devices.insert(device.id(), seat.clone()),
+            None => self.devices.remove(&device.id()),
+        };
+        seat
+    }
+
+    /// Assign a new libinput device, using its logical seat name as the seat tag
+    ///
+    /// The logical seat name is set by the `WL_SEAT` udev property and defaults to `default`,
+    /// the physical seat name is set by the `ID_SEAT` udev property and defaults to `seat0`.
+    #[cfg(feature = "backend_libinput")]
+    pub fn assign_libinput_device(&mut self, device: &input::Device) -> Option<S> {
+        let seat = device.seat();
+        let (tag, physical_seat) = (seat.logical_name().to_owned(), seat.physical_name().to_owned());
+        self.assign_with_physical_seat(device, Some(&tag), Some(&physical_seat))
+    }
+
+    /// Seat a device was assigned to
+    pub fn device_seat<D: Device>(&self, device: &D) -> Option<&S> {
+        self.devices.get(&device.id())
+    }
+
+    /// Forget a removed device, returning the seat it was assigned to
+    pub fn remove_device<D: Device>(&mut self, device: &D) -> Option<S> {
+        self.devices.remove(&device.id())
+    }
+
+    /// Ids of the devices assigned to `seat`
+    pub fn devices<'a>(&'a self, seat: &'a S) -> impl Iterator<Item = &'a str> + 'a {
+        self.devices
+            .iter()
+            .filter(move |(_, s)| *s == seat)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// Associate an output with `seat`, replacing any previous association
+    ///
+    /// An output belongs to at most one seat. Outputs are only weakly referenced.
+    pub fn assign_output(&mut self, output: &Output, seat: S) {
+        self.outputs.retain(|(o, _)| o.is_alive() && o != output);
+        self.outputs.push((output.downgrade(), seat));
+    }
+
+    /// Seat an output is associated with
+    pub fn output_seat(&self, output: &Output) -> Option<&S> {
+        self.outputs
+            .iter()
+            .find(|(o, _)| o == output)
+            .map(|(_, seat)| seat)
+    }
+
+    /// Remove the association of an output, returning its seat
+    pub fn remove_output(&mut self, output: &Output) -> Option<S> {
+        let idx = self.outputs.iter().position(|(o, _)| o == output)?;
+        Some(self.outputs.remove(idx).1)
+    }
+
+    /// Outputs associated with `seat`
+    pub fn outputs<'a>(&'a self, seat: &'a S) -> impl Iterator<Item = Output> + 'a {
+        self.outputs
+            .iter()
+            .filter(move |(_, s)| s == seat)
+            .filter_map(|(output, _)| output.upgrade())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceRule, SeatAssignment};
+    use crate::backend::input::{Device, DeviceCapability};
+    use crate::output::{Output, PhysicalProperties, Subpixel};
+    use std::path::PathBuf;
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct TestDevice(&'static str, DeviceCapability);
+
+    impl Device for TestDevice {
+        fn id(&self) -> String {
+            self.0.into()
+        }
+        fn name(&self) -> String {
+            format!("{} device", self.0)
+        }
+        fn has_capability(&self, capability: DeviceCapability) -> bool {
+            self.1 == capability
+        }
+        fn usb_id(&self) -> Option<(u32, u32)> {
+            None
+        }
+        fn syspath(&self) -> Option<PathBuf> {
+            Some(PathBuf::from("/sys/devices/usb1").join(self.0))
+        }
+    }
+
+    #[test]
+    fn devices_follow_rules_tags_and_default() {
+        let mut seats = SeatAssignment::new();
+        let keyboard = TestDevice("event0", DeviceCapability::Keyboard);
+        let touch = TestDevice("event1", DeviceCapability::Touch);
+        let pointer = TestDevice("event2", DeviceCapability::Pointer);
+
+        assert_eq!(seats.assign_device(&keyboard, None), None);
+
+        seats.set_default(Some("seat0"));
+        seats.map_seat_tag("kiosk", "seat1");
+        seats.add_rule(DeviceRule::Capability(DeviceCapability::Touch), "seat2");
+        seats.add_rule(DeviceRule::Name("event1 device".into()), "seat1");
+
+        assert_eq!(seats.assign_device(&keyboard, Some("kiosk")), Some("seat1"));
+        assert_eq!(seats.assign_device(&touch, Some("kiosk")), Some("seat2"));
+        assert_eq!(seats.assign_device(&pointer, Some("default")), Some("seat0"));
+        assert_eq!(seats.device_seat(&keyboard), Some(&"seat1"));
+        assert_eq!(seats.devices(&"seat1").collect::<Vec<_>>(), vec!["event0"]);
+
+        assert_eq!(seats.remove_device(&touch), Some("seat2"));
+        assert_eq!(seats.device_seat(&touch), None);
+
+        // physical seats are matched after seat tags
+        seats.map_physical_seat("seat-desk2", "seat3");
+        assert_eq!(
+            seats.assign_with_physical_seat(&pointer, Some("default"), Some("seat-desk2")),
+            Some("seat3")
+        );
+        assert_eq!(
+            seats.assign_with_physical_seat(&keyboard, Some("kiosk"), Some("seat-desk2")),
+            Some("seat1")
+        );
+        assert_eq!(
+            seats.assign_with_physical_seat(&pointer, Some("default"), Some("seat0")),
+            Some("seat0")
+        );
+
+        let output = Output::new(
+            "HDMI-A-1".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        seats.assign_output(&output, "seat0");
+        seats.assign_output(&output, "seat1");
+        assert_eq!(seats.output_seat(&output), Some(&"seat1"));
+        assert_eq!(seats.outputs(&"seat0").count(), 0);
+        assert_eq!(seats.outputs(&"seat1").collect::<Vec<_>>(), vec![output.clone()]);
+        assert_eq!(seats.remove_output(&output), Some("seat1"));
+    }
+}
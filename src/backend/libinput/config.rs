@@ -0,0 +1,445 @@
+//! Typed configuration of libinput devices
+
+use input as libinput;
+
+/// Pointer acceleration profile of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccelProfile {
+    /// Pointer motion is accelerated by a constant factor
+    Flat,
+    /// Pointer acceleration depends on the input speed
+    Adaptive,
+}
+
+/// Method used to generate scroll events instead of pointer motion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollMethod {
+    /// Never send scroll events instead of pointer motion events
+    NoScroll,
+    /// Scroll while two fingers are down
+    TwoFinger,
+    /// Scroll while a finger moves along the bottom or right edge
+    Edge,
+    /// Scroll while a button is held down
+    OnButtonDown,
+}
+
+/// Method used to emulate buttons on devices without physical buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClickMethod {
+    /// The area of the touchpad decides which button is pressed
+    ButtonAreas,
+    /// The number of fingers decides which button is pressed
+    Clickfinger,
+}
+
+/// A configuration option of a libinput device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigOption {
+    /// Tap-to-click
+    TapToClick,
+    /// Natural scrolling
+    NaturalScroll,
+    /// Pointer acceleration profile
+    AccelProfile,
+    /// Pointer acceleration speed
+    AccelSpeed,
+    /// Scroll method
+    ScrollMethod,
+    /// Click method
+    ClickMethod,
+    /// Disable-while-typing
+    DisableWhileTyping,
+    /// Left-handed button mapping
+    LeftHanded,
+}
+
+/// Errors thrown when configuring a libinput device
+#[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The option or value is not supported by the device
+    #[error("{0:?} is not supported by the device")]
+    Unsupported(ConfigOption),
+    /// The value is out of range
+    #[error("Invalid value for {0:?}")]
+    Invalid(ConfigOption),
+}
+
+/// Configuration options supported by a libinput device
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigCapabilities {
+    /// Whether tap-to-click can be configured
+    pub tap_to_click: bool,
+    /// Whether natural scrolling can be configured
+    pub natural_scroll: bool,
+    /// Supported acceleration profiles
+    pub accel_profiles: Vec<AccelProfile>,
+    /// Whether the acceleration speed can be configured
+    pub accel_speed: bool,
+    /// Supported scroll methods, besides [`ScrollMethod::NoScroll`]
+    pub scroll_methods: Vec<ScrollMethod>,
+    /// Supported click methods
+    pub click_methods: Vec<ClickMethod>,
+    /// Whether disable-while-typing can be configured
+    pub disable_while_typing: bool,
+    /// Whether left-handed button mapping can be configured
+    pub left_handed: bool,
+}
+
+/// Configuration of a libinput device
+///
+/// Options set to `None` are left untouched when applying the configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceConfig {
+    /// Tap-to-click
+    pub tap_to_click: Option<bool>,
+    /// Natural scrolling
+    pub natural_scroll: Option<bool>,
+    /// Pointer acceleration profile
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer acceleration speed, in the range of `[-1.0, 1.0]`
+    pub accel_speed: Option<f64>,
+    /// Scroll method
+    pub scroll_method: Option<ScrollMethod>,
+    /// Click method
+    pub click_method: Option<ClickMethod>,
+    /// Disable-while-typing
+    pub disable_while_typing: Option<bool>,
+    /// Left-handed button mapping
+    pub left_handed: Option<bool>,
+}
+
+impl DeviceConfig {
+    /// Check that every option set by this configuration is supported
+    pub fn validate(&self, capabilities: &ConfigCapabilities) -> Result<(), ConfigError> {
+        let check = |set: bool, supported: bool, option| {
+            if set && !supported {
+                Err(ConfigError::Unsupported(option))
+            } else {
+                Ok(())
+            }
+        };
+
+        check(
+            self.tap_to_click.is_some(),
+            capabilities.tap_to_click,
+            ConfigOption::TapToClick,
+        )?;
+        check(
+            self.natural_scroll.is_some(),
+            capabilities.natural_scroll,
+            ConfigOption::NaturalScroll,
+        )?;
+        if let Some(profile) = self.accel_profile {
+            check(
+                true,
+                capabilities.accel_profiles.contains(&profile),
+                ConfigOption::AccelProfile,
+            )?;
+        }
+        if let Some(speed) = self.accel_speed {
+            check(true, capabilities.accel_speed, ConfigOption::AccelSpeed)?;
+            if !(-1.0..=1.0).contains(&speed) {
+                return Err(ConfigError::Invalid(ConfigOption::AccelSpeed));
+            }
+        }
+        if let Some(method) = self.scroll_method {
+            check(
+                method != ScrollMethod::NoScroll,
+                capabilities.scroll_methods.contains(&method),
+                ConfigOption::ScrollMethod,
+            )?;
+        }
+        if let Some(method) = self.click_method {
+            check(
+                true,
+                capabilities.click_methods.contains(&method),
+                ConfigOption::ClickMethod,
+            )?;
+        }
+        check(
+            self.disable_while_typing.is_some(),
+            capabilities.disable_while_typing,
+            ConfigOption::DisableWhileTyping,
+        )?;
+        check(
+            self.left_handed.is_some(),
+            capabilities.left_handed,
+            ConfigOption::LeftHanded,
+        )
+    }
+}
+
+/// Typed configuration of a libinput device
+///
+/// Implemented for [`libinput::Device`], the device type of the
+/// [`LibinputInputBackend`](super::LibinputInputBackend).
+pub trait DeviceConfigExt {
+    /// Configuration options supported by this device
+    fn config_capabilities(&self) -> ConfigCapabilities;
+
+    /// Current configuration of this device, `None` for unsupported options
+    fn config(&self) -> DeviceConfig;
+
+    /// Default configuration of this device, `None` for unsupported options
+    fn default_config(&self) -> DeviceConfig;
+
+    /// Apply the options set by the given configuration
+    ///
+    /// The configuration is validated first, so nothing is changed if an option is not supported.
+    fn apply_config(&mut self, config: &DeviceConfig) -> Result<(), ConfigError>;
+}
+
+impl DeviceConfigExt for libinput::Device {
+    fn config_capabilities(&self) -> ConfigCapabilities {
+        let accel = self.config_accel_is_available();
+        ConfigCapabilities {
+            tap_to_click: self.config_tap_finger_count() > 0,
+            natural_scroll: self.config_scroll_has_natural_scroll(),
+            accel_profiles: if accel {
+                self.config_accel_profiles()
+                    .into_iter()
+                    .filter_map(|profile| profile.try_into().ok())
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            accel_speed: accel,
+            scroll_methods: self
+                .config_scroll_methods()
+                .into_iter()
+                .filter_map(|method| method.try_into().ok())
+                .filter(|method| *method != ScrollMethod::NoScroll)
+                .collect(),
+            click_methods: self
+                .config_click_methods()
+                .into_iter()
+                .filter_map(|method| method.try_into().ok())
+                .collect(),
+            disable_while_typing: self.config_dwt_is_available(),
+            left_handed: self.config_left_handed_is_available(),
+        }
+    }
+
+    fn config(&self) -> DeviceConfig {
+        let capabilities = self.config_capabilities();
+        DeviceConfig {
+            tap_to_click: capabilities.tap_to_click.then(|| self.config_tap_enabled()),
+            natural_scroll: capabilities
+                .natural_scroll
+                .then(|| self.config_scroll_natural_scroll_enabled()),
+            accel_profile: self
+                .config_accel_profile()
+                .and_then(|profile| profile.try_into().ok()),
+            accel_speed: capabilities.accel_speed.then(|| self.config_accel_speed()),
+            scroll_method: self
+                .config_scroll_method()
+                .and_then(|method| method.try_into().ok()),
+            click_method: self
+                .config_click_method()
+                .and_then(|method| method.try_into().ok()),
+            disable_while_typing: capabilities
+                .disable_while_typing
+                .then(|| self.config_dwt_enabled()),
+            left_handed: capabilities.left_handed.then(|| self.config_left_handed()),
+        }
+    }
+
+    fn default_config(&self) -> DeviceConfig {
+        let capabilities = self.config_capabilities();
+        DeviceConfig {
+            tap_to_click: capabilities
+                .tap_to_click
+                .then(|| self.config_tap_default_enabled()),
+            natural_scroll: capabilities
+                .natural_scroll
+                .then(|| self.config_scroll_default_natural_scroll_enabled()),
+            accel_profile: self
+                .config_accel_default_profile()
+                .and_then(|profile| profile.try_into().ok()),
+            accel_speed: capabilities
+                .accel_speed
+                .then(|| self.config_accel_default_speed()),
+            scroll_method: self
+                .config_scroll_default_method()
+                .and_then(|method| method.try_into().ok()),
+            click_method: self
+                .config_click_default_method()
+                .and_then(|method| method.try_into().ok()),
+            disable_while_typing: capabilities
+                .disable_while_typing
+                .then(|| self.config_dwt_default_enabled()),
+            left_handed: capabilities
+                .left_handed
+                .then(|| self.config_left_handed_default()),
+        }
+    }
+
+    fn apply_config(&mut self, config: &DeviceConfig) -> Result<(), ConfigError> {
+        config.validate(&self.config_capabilities())?;
+
+        let map_err = |option| {
+            move |err| match err {
+                libinput::DeviceConfigError::Unsupported => ConfigError::Unsupported(option),
+                libinput::DeviceConfigError::Invalid => ConfigError::Invalid(option),
+            }
+        };
+
+        if let Some(enabled) = config.tap_to_click {
+            self.config_tap_set_enabled(enabled)
+                .map_err(map_err(ConfigOption::TapToClick))?;
+        }
+        if let Some(enabled) = config.natural_scroll {
+            self.config_scroll_set_natural_scroll_enabled(enabled)
+                .map_err(map_err(ConfigOption::NaturalScroll))?;
+        }
+        if let Some(profile) = config.accel_profile {
+            self.config_accel_set_profile(profile.into())
+                .map_err(map_err(ConfigOption::AccelProfile))?;
+        }
+        if let Some(speed) = config.accel_speed {
+            self.config_accel_set_speed(speed)
+                .map_err(map_err(ConfigOption::AccelSpeed))?;
+        }
+        if let Some(method) = config.scroll_method {
+            self.config_scroll_set_method(method.into())
+                .map_err(map_err(ConfigOption::ScrollMethod))?;
+        }
+        if let Some(method) = config.click_method {
+            self.config_click_set_method(method.into())
+                .map_err(map_err(ConfigOption::ClickMethod))?;
+        }
+        if let Some(enabled) = config.disable_while_typing {
+            self.config_dwt_set_enabled(enabled)
+                .map_err(map_err(ConfigOption::DisableWhileTyping))?;
+        }
+        if let Some(enabled) = config.left_handed {
+            self.config_left_handed_set(enabled)
+                .map_err(map_err(ConfigOption::LeftHanded))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<AccelProfile> for libinput::AccelProfile {
+    #[inline]
+    fn from(profile: AccelProfile) -> libinput::AccelProfile {
+        match profile {
+            AccelProfile::Flat => libinput::AccelProfile::Flat,
+            AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+        }
+    }
+}
+
+impl TryFrom<libinput::AccelProfile> for AccelProfile {
+    type Error = ();
+
+    #[inline]
+    fn try_from(profile: libinput::AccelProfile) -> Result<AccelProfile, ()> {
+        match profile {
+            libinput::AccelProfile::Flat => Ok(AccelProfile::Flat),
+            libinput::AccelProfile::Adaptive => Ok(AccelProfile::Adaptive),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<ScrollMethod> for libinput::ScrollMethod {
+    #[inline]
+    fn from(method: ScrollMethod) -> libinput::ScrollMethod {
+        match method {
+            ScrollMethod::NoScroll => libinput::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => libinput::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => libinput::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => libinput::ScrollMethod::OnButtonDown,
+        }
+    }
+}
+
+impl TryFrom<libinput::ScrollMethod> for ScrollMethod {
+    type Error = ();
+
+    #[inline]
+    fn try_from(method: libinput::ScrollMethod) -> Result<ScrollMethod, ()> {
+        match method {
+            libinput::ScrollMethod::NoScroll => Ok(ScrollMethod::NoScroll),
+            libinput::ScrollMethod::TwoFinger => Ok(ScrollMethod::TwoFinger),
+            libinput::ScrollMethod::Edge => Ok(ScrollMethod::Edge),
+            libinput::ScrollMethod::OnButtonDown => Ok(ScrollMethod::OnButtonDown),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<ClickMethod> for libinput::ClickMethod {
+    #[inline]
+    fn from(method: ClickMethod) -> libinput::ClickMethod {
+        match method {
+            ClickMethod::ButtonAreas => libinput::ClickMethod::ButtonAreas,
+            ClickMethod::Clickfinger => libinput::ClickMethod::Clickfinger,
+        }
+    }
+}
+
+impl TryFrom<libinput::ClickMethod> for ClickMethod {
+    type Error = ();
+
+    #[inline]
+    fn try_from(method: libinput::ClickMethod) -> Result<ClickMethod, ()> {
+        match method {
+            libinput::ClickMethod::ButtonAreas => Ok(ClickMethod::ButtonAreas),
+            libinput::ClickMethod::Clickfinger => Ok(ClickMethod::Clickfinger),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccelProfile, ConfigCapabilities, ConfigError, ConfigOption, DeviceConfig, ScrollMethod};
+
+    #[test]
+    fn validate_against_capabilities() {
+        let capabilities = ConfigCapabilities {
+            tap_to_click: true,
+            accel_profiles: vec![AccelProfile::Adaptive],
+            accel_speed: true,
+            scroll_methods: vec![ScrollMethod::TwoFinger],
+            ..Default::default()
+        };
+
+        let config = DeviceConfig {
+            tap_to_click: Some(true),
+            accel_profile: Some(AccelProfile::Adaptive),
+            accel_speed: Some(-0.5),
+            scroll_method: Some(ScrollMethod::NoScroll),
+            ..Default::default()
+        };
+        assert_eq!(config.validate(&capabilities), Ok(()));
+
+        let config = DeviceConfig {
+            accel_profile: Some(AccelProfile::Flat),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(&capabilities),
+            Err(ConfigError::Unsupported(ConfigOption::AccelProfile))
+        );
+        let config = DeviceConfig {
+            accel_speed: Some(1.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(&capabilities),
+            Err(ConfigError::Invalid(ConfigOption::AccelSpeed))
+        );
+        let config = DeviceConfig {
+            left_handed: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(&capabilities),
+            Err(ConfigError::Unsupported(ConfigOption::LeftHanded))
+        );
+    }
+}
@@ -1,4 +1,7 @@
 //! Implementation of input backend trait for types provided by `libinput`
+//!
+//! Devices reported by the [`LibinputInputBackend`] can be configured using the [`DeviceConfigExt`] trait,
+//! e.g. to enable tap-to-click or natural scrolling, after checking the options supported by the device.
 
 use crate::backend::input::{
     self as backend, Axis, AxisRelativeDirection, AxisSource, InputBackend, InputEvent,
@@ -20,16 +23,25 @@ use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, T
 
 use tracing::{debug_span, info, trace};
 
+mod config;
 mod tablet;
 
+pub use self::config::*;
+
 /// Libinput based [`InputBackend`].
 ///
 /// Tracks input of all devices given manually or via a udev seat to a provided libinput
 /// context.
+///
+/// A libinput context only sees the devices of a single physical seat. For multi-seat setups
+/// contexts for further seats can be added with [`LibinputInputBackend::add_context`], the physical
+/// seat of a device is available through [`libinput::Device::seat`].
 #[derive(Debug)]
 pub struct LibinputInputBackend {
     context: libinput::Libinput,
-    token: Option<Token>,
+    // contexts of additional seats
+    seats: Vec<libinput::Libinput>,
+    tokens: Vec<Token>,
     span: tracing::Span,
 }
 
@@ -45,15 +57,63 @@ impl LibinputInputBackend {
         drop(_guard);
         LibinputInputBackend {
             context,
-            token: None,
+            seats: Vec::new(),
+            tokens: Vec::new(),
             span,
         }
     }
 
+    /// Initialize a new [`LibinputInputBackend`] tracking the devices of several physical seats
+    ///
+    /// One udev based libinput context is created per seat, opening devices through `session`.
+    /// The available seats can be queried with [`input_seats`](crate::backend::udev::input_seats).
+    #[cfg(all(feature = "backend_session", feature = "backend_udev"))]
+    pub fn new_with_udev_seats<S, I>(session: S, seats: I) -> io::Result<Self>
+    where
+        S: Session + Clone + 'static,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut contexts = seats.into_iter().map(|seat| -> io::Result<libinput::Libinput> {
+            let mut context =
+                libinput::Libinput::new_with_udev(LibinputSessionInterface::from(session.clone()));
+            context.udev_assign_seat(seat.as_ref()).map_err(|()| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Failed to assign seat {} to libinput context", seat.as_ref()),
+                )
+            })?;
+            Ok(context)
+        });
+        let context = contexts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No seat given"))??;
+        let mut backend = LibinputInputBackend::new(context);
+        for context in contexts {
+            backend.add_context(context?);
+        }
+        Ok(backend)
+    }
+
     /// Returns a reference to the underlying libinput context
+    ///
+    /// For backends tracking multiple seats this is the context of the first seat.
     pub fn context(&self) -> &libinput::Libinput {
         &self.context
     }
+
+    /// Track the devices of another libinput context, usually assigned to another physical seat
+    ///
+    /// Contexts added after the backend was inserted into an event loop are only polled
+    /// once the backend is re-registered.
+    pub fn add_context(&mut self, context: libinput::Libinput) {
+        self.seats.push(context);
+    }
+
+    /// Returns all libinput contexts of the backend, e.g. to suspend or resume them
+    pub fn contexts(&self) -> impl Iterator<Item = &libinput::Libinput> {
+        std::iter::once(&self.context).chain(self.seats.iter())
+    }
 }
 
 impl backend::Device for libinput::Device {
@@ -715,11 +775,17 @@ impl EventSource for LibinputInputBackend {
     where
         F: FnMut(Self::Event, &mut ()) -> Self::Ret,
     {
-        if Some(token) == self.token {
+        if self.tokens.contains(&token) {
             let _guard = self.span.enter();
             self.context.dispatch()?;
+            for context in &mut self.seats {
+                context.dispatch()?;
+            }
 
-            for event in &mut self.context {
+            let events = std::iter::once(&mut self.context)
+                .chain(self.seats.iter_mut())
+                .flatten();
+            for event in events {
                 match event {
                     libinput::Event::Device(device_event) => match device_event {
                         event::DeviceEvent::Added(device_added_event) => {
@@ -874,18 +940,39 @@ impl EventSource for LibinputInputBackend {
     }
 
     fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
-        self.token = Some(factory.token());
-        // Safety: the FD cannot be closed without removing the LibinputInputBackend from the event loop
-        unsafe { poll.register(self.as_fd(), Interest::READ, Mode::Level, self.token.unwrap()) }
+        self.tokens.clear();
+        for context in std::iter::once(&self.context).chain(self.seats.iter()) {
+            let token = factory.token();
+            self.tokens.push(token);
+            // Safety: the FD cannot be closed without removing the LibinputInputBackend from the event loop
+            unsafe { poll.register(context.as_fd(), Interest::READ, Mode::Level, token)? };
+        }
+        Ok(())
     }
 
     fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
-        self.token = Some(factory.token());
-        poll.reregister(self.as_fd(), Interest::READ, Mode::Level, self.token.unwrap())
+        let registered = std::mem::take(&mut self.tokens).len();
+        for (idx, context) in std::iter::once(&self.context)
+            .chain(self.seats.iter())
+            .enumerate()
+        {
+            let token = factory.token();
+            self.tokens.push(token);
+            if idx < registered {
+                poll.reregister(context.as_fd(), Interest::READ, Mode::Level, token)?;
+            } else {
+                // Safety: see `register`
+                unsafe { poll.register(context.as_fd(), Interest::READ, Mode::Level, token)? };
+            }
+        }
+        Ok(())
     }
 
     fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
-        self.token = None;
-        poll.unregister(self.as_fd())
+        self.tokens.clear();
+        for context in std::iter::once(&self.context).chain(self.seats.iter()) {
+            poll.unregister(context.as_fd())?;
+        }
+        Ok(())
     }
 }
@@ -78,8 +78,27 @@
 //! development and debugging. That backend is both a renderer and an input provider, and is
 //! accessible in the [`winit`] module, gated by the `backend_winit` cargo feature.
 //!
+//! ## Headless backend
+//!
+//! For remote sessions or testing a compositor without any display, the [`headless`] module
+//! provides virtual outputs with arbitrary modes driven by timers.
+//!
+//! ## VNC backend
+//!
+//! The [`vnc`] module serves a framebuffer to remote VNC clients and is also an input provider,
+//! gated by the `backend_vnc` cargo feature. Combined with the headless backend this allows
+//! remote sessions without any display hardware.
+//!
+//! ## RDP backend
+//!
+//! The [`rdp`] module serves a framebuffer to remote desktop clients, including their clipboard
+//! and multiple monitors, and is also an input provider. It is gated by the `backend_rdp` cargo
+//! feature and, like the VNC backend, meant to be combined with the headless backend.
+//!
 
 pub mod allocator;
+pub mod frame_scheduler;
+pub mod headless;
 pub mod input;
 pub mod renderer;
 
@@ -94,6 +113,11 @@ pub mod session;
 #[cfg(feature = "backend_udev")]
 pub mod udev;
 
+#[cfg(feature = "backend_rdp")]
+pub mod rdp;
+#[cfg(feature = "backend_vnc")]
+pub mod vnc;
+
 #[cfg(feature = "backend_vulkan")]
 pub mod vulkan;
 
@@ -0,0 +1,274 @@
+//! Input backend implementation for the RDP backend.
+
+use crate::{
+    backend::input::{
+        self, AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, InputBackend, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent,
+        PointerButtonEvent, PointerMotionAbsoluteEvent, UnusedEvent,
+    },
+    utils::{Physical, Size},
+};
+
+/// Marker used to define the `InputBackend` types for the RDP backend.
+#[derive(Debug)]
+pub struct RdpInput;
+
+/// Virtual input device of a single RDP client
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RdpVirtualDevice {
+    pub(crate) client: u64,
+}
+
+impl RdpVirtualDevice {
+    /// Id of the client, as used by [`RdpEvent`](super::RdpEvent)
+    pub fn client(&self) -> u64 {
+        self.client
+    }
+}
+
+impl Device for RdpVirtualDevice {
+    fn id(&self) -> String {
+        format!("rdp-{}", self.client)
+    }
+
+    fn name(&self) -> String {
+        format!("rdp virtual input {}", self.client)
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(capability, DeviceCapability::Keyboard | DeviceCapability::Pointer)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// RDP-Backend internal event wrapping a key event of a client into a [`KeyboardKeyEvent`]
+#[derive(Debug, Clone)]
+pub struct RdpKeyboardInputEvent {
+    pub(crate) time: u64,
+    pub(crate) device: RdpVirtualDevice,
+    pub(crate) key: Keycode,
+    pub(crate) count: u32,
+    pub(crate) state: KeyState,
+}
+
+impl input::Event<RdpInput> for RdpKeyboardInputEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RdpVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<RdpInput> for RdpKeyboardInputEvent {
+    fn key_code(&self) -> Keycode {
+        self.key
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// RDP-Backend internal event wrapping a wheel rotation of a client into a [`PointerAxisEvent`]
+#[derive(Debug, Clone)]
+pub struct RdpMouseWheelEvent {
+    pub(crate) time: u64,
+    pub(crate) device: RdpVirtualDevice,
+    pub(crate) axis: Axis,
+    pub(crate) amount_v120: f64,
+}
+
+impl input::Event<RdpInput> for RdpMouseWheelEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RdpVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerAxisEvent<RdpInput> for RdpMouseWheelEvent {
+    fn amount(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            Some(self.amount_v120)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+/// RDP-Backend internal event wrapping a button of a client into a [`PointerButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct RdpMouseInputEvent {
+    pub(crate) time: u64,
+    pub(crate) device: RdpVirtualDevice,
+    pub(crate) button: u32,
+    pub(crate) state: ButtonState,
+}
+
+impl input::Event<RdpInput> for RdpMouseInputEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RdpVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerButtonEvent<RdpInput> for RdpMouseInputEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// RDP-Backend internal event wrapping a pointer position of a client into a [`PointerMotionAbsoluteEvent`]
+#[derive(Debug, Clone)]
+pub struct RdpMouseMovedEvent {
+    pub(crate) time: u64,
+    pub(crate) device: RdpVirtualDevice,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<i32, Physical>,
+}
+
+impl input::Event<RdpInput> for RdpMouseMovedEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> RdpVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerMotionAbsoluteEvent<RdpInput> for RdpMouseMovedEvent {}
+impl AbsolutePositionEvent<RdpInput> for RdpMouseMovedEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl InputBackend for RdpInput {
+    type Device = RdpVirtualDevice;
+    type KeyboardKeyEvent = RdpKeyboardInputEvent;
+    type PointerAxisEvent = RdpMouseWheelEvent;
+    type PointerButtonEvent = RdpMouseInputEvent;
+
+    type PointerMotionEvent = UnusedEvent;
+
+    type PointerMotionAbsoluteEvent = RdpMouseMovedEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Translates a set 1 scancode sent by a client into a key code
+///
+/// Scancodes without prefix mostly match the evdev codes, the extended ones are looked up.
+pub(super) fn scancode_to_keycode(code: u8, extended: bool) -> Option<Keycode> {
+    let evdev = match (extended, code) {
+        (false, 0x01..=0x58) => code as u32,
+        (false, 0x70) => 93,  // KEY_KATAKANAHIRAGANA
+        (false, 0x73) => 89,  // KEY_RO
+        (false, 0x79) => 92,  // KEY_HENKAN
+        (false, 0x7b) => 94,  // KEY_MUHENKAN
+        (false, 0x7d) => 124, // KEY_YEN
+        (false, _) => return None,
+        (true, code) => match code {
+            0x10 => 165, // KEY_PREVIOUSSONG
+            0x19 => 163, // KEY_NEXTSONG
+            0x1c => 96,  // KEY_KPENTER
+            0x1d => 97,  // KEY_RIGHTCTRL
+            0x20 => 113, // KEY_MUTE
+            0x22 => 164, // KEY_PLAYPAUSE
+            0x24 => 166, // KEY_STOPCD
+            0x2e => 114, // KEY_VOLUMEDOWN
+            0x30 => 115, // KEY_VOLUMEUP
+            0x35 => 98,  // KEY_KPSLASH
+            0x37 => 99,  // KEY_SYSRQ
+            0x38 => 100, // KEY_RIGHTALT
+            0x47 => 102, // KEY_HOME
+            0x48 => 103, // KEY_UP
+            0x49 => 104, // KEY_PAGEUP
+            0x4b => 105, // KEY_LEFT
+            0x4d => 106, // KEY_RIGHT
+            0x4f => 107, // KEY_END
+            0x50 => 108, // KEY_DOWN
+            0x51 => 109, // KEY_PAGEDOWN
+            0x52 => 110, // KEY_INSERT
+            0x53 => 111, // KEY_DELETE
+            0x5b => 125, // KEY_LEFTMETA
+            0x5c => 126, // KEY_RIGHTMETA
+            0x5d => 127, // KEY_COMPOSE
+            0x5e => 116, // KEY_POWER
+            0x5f => 142, // KEY_SLEEP
+            0x63 => 143, // KEY_WAKEUP
+            _ => return None,
+        },
+    };
+    // xkb key codes are offset by 8
+    Some(Keycode::new(evdev + 8))
+}
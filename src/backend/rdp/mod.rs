@@ -0,0 +1,1111 @@
+//! RDP backend exposing an output over the remote desktop protocol
+//!
+//! Windows and most remote desktop clients speak RDP rather than VNC. A [`RdpBackend`] listens
+//! for RDP clients and sends them the contents of a framebuffer, e.g. the output of the
+//! [headless backend](crate::backend::headless), read back after rendering.
+//!
+//! The backend is a [`calloop`] event source:
+//! - Input of the clients is translated into [`InputEvent`]s of the [`RdpInput`] backend. Every client
+//!   is represented by its own [`RdpVirtualDevice`]. Scancodes are mapped to evdev key codes, unicode
+//!   key events are mapped to key codes using a keymap, which defaults to the default xkb keymap and
+//!   can be changed with [`RdpBackend::set_keymap`].
+//! - The desktop size requested by a connecting client is emitted as [`RdpEvent::ResizeRequested`].
+//!   It is up to the compositor to accept the request, e.g. by changing the mode of the output and
+//!   calling [`RdpBackend::resize`].
+//! - Clients requesting the `cliprdr` channel share their clipboard. Their clipboard contents are
+//!   announced with [`RdpEvent::ClipboardFormats`] and can be fetched with
+//!   [`RdpBackend::request_clipboard_data`]. The compositor offers its own clipboard with
+//!   [`RdpBackend::set_clipboard_formats`] and answers [`RdpEvent::ClipboardDataRequested`] with
+//!   [`RdpBackend::send_clipboard_data`].
+//!
+//! After rendering, the damaged regions of the framebuffer are updated with [`RdpBackend::update`].
+//! Clients receive the regions damaged since their last update as uncompressed bitmaps. Multiple
+//! outputs can be placed side by side in the framebuffer and presented as separate monitors of the
+//! client with [`RdpBackend::set_monitors`].
+//!
+//! Only standard RDP security without encryption is supported, clients have to allow it (e.g.
+//! `/sec:rdp` for FreeRDP). Connections are neither authenticated nor encrypted, so the backend
+//! should only listen on trusted networks or behind a tunnel.
+//!
+//! ```no_run
+//! use smithay::backend::rdp::{RdpBackend, RdpEvent};
+//!
+//! # struct State;
+//! let mut event_loop = calloop::EventLoop::<State>::try_new().unwrap();
+//! let rdp = RdpBackend::bind("127.0.0.1:3389", (1280, 800).into()).unwrap();
+//!
+//! event_loop
+//!     .handle()
+//!     .insert_source(rdp, |event, _, _state| match event {
+//!         RdpEvent::Input(event) => {
+//!             // ...process the input event
+//!         }
+//!         RdpEvent::ResizeRequested { size, .. } => {
+//!             // ...change the size of the output and call `RdpBackend::resize`
+//!         }
+//!         _ => {
+//!             // ...exchange clipboard contents
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! // after rendering a frame
+//! # use smithay::utils::{Physical, Rectangle};
+//! # let rdp: &mut RdpBackend = todo!();
+//! # let (pixels, stride, damage): (Vec<u8>, usize, Vec<Rectangle<i32, Physical>>) = todo!();
+//! rdp.update(&pixels, stride, &damage);
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug, info, info_span, warn};
+use xkbcommon::xkb;
+
+use crate::{
+    backend::input::{Axis, ButtonState, InputEvent, KeyState, Keycode},
+    utils::{Clock, Monotonic, Physical, Rectangle, Size},
+};
+
+mod input;
+mod protocol;
+
+pub use self::input::*;
+use self::protocol::{ChannelData, ClientPdu, ClipboardPdu, InputMessage, SharePdu, MCS_IO_CHANNEL};
+
+// Number of damaged rectangles per client before they are merged into their bounding box
+const MAX_DAMAGE_RECTS: usize = 32;
+// Maximum number of unprocessed bytes buffered per client, large enough for any PDU
+const MAX_INPUT_LEN: usize = 1 << 20;
+// Maximum size of a clipboard message of a client
+const MAX_CLIPBOARD_LEN: usize = 1 << 24;
+
+// pointer flags of mouse events
+const PTRFLAGS_HWHEEL: u16 = 0x0400;
+const PTRFLAGS_WHEEL: u16 = 0x0200;
+const PTRFLAGS_WHEEL_NEGATIVE: u16 = 0x0100;
+const PTRFLAGS_DOWN: u16 = 0x8000;
+const PTRFLAGS_BUTTON1: u16 = 0x1000;
+const PTRFLAGS_BUTTON2: u16 = 0x2000;
+const PTRFLAGS_BUTTON3: u16 = 0x4000;
+const PTRXFLAGS_BUTTON1: u16 = 0x0001;
+const PTRXFLAGS_BUTTON2: u16 = 0x0002;
+
+// linux button codes
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+// the pause key is sent as a prefixed left control followed by num lock
+const SCANCODE_NUMLOCK: u8 = 0x45;
+const SCANCODE_CONTROL: u8 = 0x1d;
+const KEY_PAUSE: u32 = 119;
+
+/// Events generated by the [`RdpBackend`]
+#[derive(Debug)]
+pub enum RdpEvent {
+    /// An input event of a client occurred.
+    Input(InputEvent<RdpInput>),
+
+    /// A connecting client requested a different desktop size.
+    ///
+    /// Accept the request with [`RdpBackend::resize`] or ignore it.
+    ResizeRequested {
+        /// Id of the requesting client, see [`RdpVirtualDevice::client`]
+        client: u64,
+        /// The requested size
+        size: Size<i32, Physical>,
+    },
+
+    /// The clipboard of a client changed.
+    ///
+    /// Its contents can be fetched with [`RdpBackend::request_clipboard_data`].
+    ClipboardFormats {
+        /// Id of the client
+        client: u64,
+        /// Formats the clipboard contents are available in
+        formats: Vec<RdpClipboardFormat>,
+    },
+
+    /// A client requested the contents of the clipboard set with [`RdpBackend::set_clipboard_formats`].
+    ///
+    /// Every request has to be answered with [`RdpBackend::send_clipboard_data`].
+    ClipboardDataRequested {
+        /// Id of the client
+        client: u64,
+        /// Id of the requested format
+        format: u32,
+    },
+
+    /// A client sent the clipboard contents requested by [`RdpBackend::request_clipboard_data`].
+    ClipboardData {
+        /// Id of the client
+        client: u64,
+        /// Id of the requested format
+        format: u32,
+        /// The contents, or `None` if the client failed to provide them
+        data: Option<Vec<u8>>,
+    },
+}
+
+/// Format of clipboard contents exchanged with RDP clients
+///
+/// Formats are identified by the ids of the Windows clipboard, like [`RdpClipboardFormat::UNICODE_TEXT`].
+/// Registered formats use ids above `0xc000` and are identified by their name instead, e.g. `HTML Format`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RdpClipboardFormat {
+    /// Id of the format
+    pub id: u32,
+    /// Name of registered formats, otherwise empty
+    ///
+    /// Names are truncated to 15 characters when sent to clients.
+    pub name: String,
+}
+
+impl RdpClipboardFormat {
+    /// Null-terminated UTF-16 text
+    pub const UNICODE_TEXT: u32 = 13;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientState {
+    ConnectionRequest,
+    ConnectInitial,
+    // erecting the MCS domain, joining channels and exchanging the client info
+    Connecting,
+    CapabilityExchange,
+    Finalization,
+    Active,
+}
+
+#[derive(Debug)]
+struct Client {
+    id: u64,
+    addr: SocketAddr,
+    stream: Generic<TcpStream>,
+    registered: bool,
+    state: ClientState,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    closed: bool,
+
+    requested_protocols: u32,
+    user: u16,
+    clipboard_channel: Option<u16>,
+    monitor_layout: bool,
+    requested_size: Option<Size<i32, Physical>>,
+    // desktop layout of the last capability exchange
+    size: Size<i32, Physical>,
+    monitors: Vec<Rectangle<i32, Physical>>,
+    fast_path_output: bool,
+    desktop_resize: bool,
+    suppressed: bool,
+    damage: Vec<Rectangle<i32, Physical>>,
+
+    device_added: bool,
+    buttons: HashSet<u32>,
+    pointer: Option<(u16, u16)>,
+    pressed: HashSet<Keycode>,
+    // whether the num lock following the pause prefix is pending
+    pause: bool,
+
+    clipboard: ChannelData,
+    clipboard_ready: bool,
+    // formats of pending clipboard requests, answered in order
+    clipboard_requests: VecDeque<u32>,
+}
+
+impl Client {
+    fn device(&self) -> RdpVirtualDevice {
+        RdpVirtualDevice { client: self.id }
+    }
+
+    /// Reads the available data of the stream
+    ///
+    /// Returns `true` if reading stopped because the input buffer is full.
+    fn read(&mut self) -> io::Result<bool> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = buf.len().min(MAX_INPUT_LEN - self.input.len());
+            if len == 0 {
+                return Ok(true);
+            }
+            match self.stream.get_ref().read(&mut buf[..len]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.input.extend_from_slice(&buf[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.get_ref().write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.output.drain(..len);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, err: io::Error) {
+        if !self.closed {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                info!(client = self.id, addr = %self.addr, "RDP client disconnected");
+            } else {
+                warn!(client = self.id, addr = %self.addr, ?err, "Closing RDP connection");
+            }
+            // wakes up the event loop to remove the client
+            let _ = self.stream.get_ref().shutdown(Shutdown::Both);
+            self.closed = true;
+        }
+    }
+
+    fn add_damage(&mut self, rect: Rectangle<i32, Physical>) {
+        if rect.is_empty() {
+            return;
+        }
+        self.damage.push(rect);
+        if self.damage.len() > MAX_DAMAGE_RECTS {
+            let bbox = self
+                .damage
+                .iter()
+                .fold(self.damage[0], |bbox, rect| bbox.merge(*rect));
+            self.damage = vec![bbox];
+        }
+    }
+
+    /// Sends the damaged regions if the client is ready for an update
+    fn send_update(&mut self, framebuffer: &[u8], size: Size<i32, Physical>) {
+        if self.closed
+            || self.state != ClientState::Active
+            || self.suppressed
+            || self.damage.is_empty()
+            || !self.output.is_empty()
+        {
+            return;
+        }
+
+        for rect in self.damage.drain(..) {
+            for y in (rect.loc.y..rect.loc.y + rect.size.h).step_by(protocol::TILE_SIZE as usize) {
+                for x in (rect.loc.x..rect.loc.x + rect.size.w).step_by(protocol::TILE_SIZE as usize) {
+                    let tile = Rectangle::from_extemities(
+                        (x, y),
+                        (
+                            (x + protocol::TILE_SIZE).min(rect.loc.x + rect.size.w),
+                            (y + protocol::TILE_SIZE).min(rect.loc.y + rect.size.h),
+                        ),
+                    );
+                    protocol::write_bitmap_update(
+                        framebuffer,
+                        size.w as usize * 4,
+                        tile,
+                        self.fast_path_output,
+                        self.user,
+                        &mut self.output,
+                    );
+                }
+            }
+        }
+
+        if let Err(err) = self.flush() {
+            self.close(err);
+        }
+    }
+
+    /// Starts the capability exchange announcing the given desktop layout
+    fn demand_active(&mut self, size: Size<i32, Physical>, monitors: &[Rectangle<i32, Physical>]) {
+        protocol::write_demand_active(self.user, size, &mut self.output);
+        self.size = size;
+        self.monitors.clear();
+        if self.monitor_layout && !monitors.is_empty() {
+            protocol::write_monitor_layout(self.user, monitors, &mut self.output);
+            self.monitors.extend_from_slice(monitors);
+        }
+        self.state = ClientState::CapabilityExchange;
+    }
+
+    /// Restarts the capability exchange of an active client, if the desktop layout changed
+    fn reactivate(&mut self, size: Size<i32, Physical>, monitors: &[Rectangle<i32, Physical>]) {
+        let monitors = if self.monitor_layout { monitors } else { &[] };
+        if self.state != ClientState::Active || (self.size == size && self.monitors == monitors) {
+            return;
+        }
+        if self.size != size && !self.desktop_resize {
+            self.close(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "client does not support desktop size changes",
+            ));
+            return;
+        }
+
+        protocol::write_deactivate_all(self.user, &mut self.output);
+        self.demand_active(size, monitors);
+        self.damage.clear();
+        if let Err(err) = self.flush() {
+            self.close(err);
+        }
+    }
+
+    fn send_clipboard_pdu(&mut self, pdu: &[u8]) {
+        if let Some(channel) = self.clipboard_channel.filter(|_| self.clipboard_ready) {
+            protocol::write_channel_data(self.user, channel, pdu, &mut self.output);
+        }
+    }
+
+    fn key(&mut self, key: Keycode, pressed: bool, time: u64, emit: &mut dyn FnMut(RdpEvent)) {
+        // clients send repeated key presses, repeat is handled by the compositor
+        let changed = if pressed {
+            self.pressed.insert(key)
+        } else {
+            self.pressed.remove(&key)
+        };
+        if changed {
+            emit(RdpEvent::Input(InputEvent::Keyboard {
+                event: RdpKeyboardInputEvent {
+                    time,
+                    device: self.device(),
+                    key,
+                    count: self.pressed.len() as u32,
+                    state: if pressed {
+                        KeyState::Pressed
+                    } else {
+                        KeyState::Released
+                    },
+                },
+            }));
+        }
+    }
+
+    fn motion(
+        &mut self,
+        x: u16,
+        y: u16,
+        size: Size<i32, Physical>,
+        time: u64,
+        emit: &mut dyn FnMut(RdpEvent),
+    ) {
+        if self.pointer != Some((x, y)) {
+            self.pointer = Some((x, y));
+            emit(RdpEvent::Input(InputEvent::PointerMotionAbsolute {
+                event: RdpMouseMovedEvent {
+                    time,
+                    device: self.device(),
+                    x: x as f64,
+                    y: y as f64,
+                    size,
+                },
+            }));
+        }
+    }
+
+    fn button(&mut self, button: u32, pressed: bool, time: u64, emit: &mut dyn FnMut(RdpEvent)) {
+        let changed = if pressed {
+            self.buttons.insert(button)
+        } else {
+            self.buttons.remove(&button)
+        };
+        if changed {
+            emit(RdpEvent::Input(InputEvent::PointerButton {
+                event: RdpMouseInputEvent {
+                    time,
+                    device: self.device(),
+                    button,
+                    state: if pressed {
+                        ButtonState::Pressed
+                    } else {
+                        ButtonState::Released
+                    },
+                },
+            }));
+        }
+    }
+}
+
+/// Backend serving a framebuffer to RDP clients
+#[derive(Debug)]
+pub struct RdpBackend {
+    listener: Generic<TcpListener>,
+    clients: Vec<Client>,
+    next_client: u64,
+    size: Size<i32, Physical>,
+    monitors: Vec<Rectangle<i32, Physical>>,
+    framebuffer: Vec<u8>,
+    keycodes: HashMap<u32, Keycode>,
+    clipboard_formats: Vec<RdpClipboardFormat>,
+    clock: Clock<Monotonic>,
+    span: tracing::Span,
+}
+
+impl RdpBackend {
+    /// Listen for RDP clients on `addr`, serving a framebuffer of the given size
+    pub fn bind(addr: impl ToSocketAddrs, size: Size<i32, Physical>) -> io::Result<RdpBackend> {
+        let span = info_span!("backend_rdp");
+        let _guard = span.enter();
+
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!(addr = ?listener.local_addr()?, "Listening for RDP clients");
+
+        let mut backend = RdpBackend {
+            listener: Generic::new(listener, Interest::READ, Mode::Level),
+            clients: Vec::new(),
+            next_client: 0,
+            size,
+            monitors: Vec::new(),
+            framebuffer: vec![0; framebuffer_len(size)],
+            keycodes: HashMap::new(),
+            clipboard_formats: Vec::new(),
+            clock: Clock::new(),
+            span: span.clone(),
+        };
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        match xkb::Keymap::new_from_names(&context, "", "", "", "", None, xkb::KEYMAP_COMPILE_NO_FLAGS) {
+            Some(keymap) => backend.set_keymap(&keymap),
+            None => warn!("Failed to compile the default keymap, ignoring unicode key events"),
+        }
+
+        drop(_guard);
+        Ok(backend)
+    }
+
+    /// Address the backend is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.get_ref().local_addr()
+    }
+
+    /// Size of the framebuffer
+    pub fn size(&self) -> Size<i32, Physical> {
+        self.size
+    }
+
+    /// Number of connected clients
+    pub fn clients(&self) -> usize {
+        self.clients.iter().filter(|client| !client.closed).count()
+    }
+
+    /// Set the keymap used to translate the unicode key events of clients into key codes
+    ///
+    /// Clients usually send scancodes, which do not depend on the keymap. Characters without a
+    /// scancode, e.g. from input methods or on-screen keyboards, are sent as unicode and mapped
+    /// to the first key producing them, preferring lower shift levels.
+    pub fn set_keymap(&mut self, keymap: &xkb::Keymap) {
+        let mut syms = Vec::new();
+        for raw in keymap.min_keycode().raw()..=keymap.max_keycode().raw() {
+            let key = Keycode::new(raw);
+            for layout in 0..keymap.num_layouts_for_key(key) {
+                for level in 0..keymap.num_levels_for_key(key, layout) {
+                    for sym in keymap.key_get_syms_by_level(key, layout, level) {
+                        syms.push((level, layout, key, sym.raw()));
+                    }
+                }
+            }
+        }
+        syms.sort_by_key(|(level, layout, _, _)| (*level, *layout));
+
+        self.keycodes.clear();
+        for (_, _, key, sym) in syms {
+            self.keycodes.entry(sym).or_insert(key);
+        }
+    }
+
+    /// Update the framebuffer and send the damaged regions to the clients
+    ///
+    /// `data` contains the `Xrgb8888` pixels of the whole framebuffer with `stride` bytes per row,
+    /// of which only the damaged regions are copied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is too small for the current size of the framebuffer.
+    pub fn update(&mut self, data: &[u8], stride: usize, damage: &[Rectangle<i32, Physical>]) {
+        let row_len = self.size.w as usize * 4;
+        assert!(stride >= row_len && data.len() >= stride * (self.size.h.max(1) as usize - 1) + row_len);
+
+        let bounds = Rectangle::from_loc_and_size((0, 0), self.size);
+        for rect in damage.iter().filter_map(|rect| rect.intersection(bounds)) {
+            let (x, w) = (rect.loc.x as usize * 4, rect.size.w as usize * 4);
+            for y in rect.loc.y as usize..(rect.loc.y + rect.size.h) as usize {
+                self.framebuffer[y * row_len + x..y * row_len + x + w]
+                    .copy_from_slice(&data[y * stride + x..y * stride + x + w]);
+            }
+            for client in &mut self.clients {
+                client.add_damage(rect);
+            }
+        }
+
+        for client in &mut self.clients {
+            client.send_update(&self.framebuffer, self.size);
+        }
+    }
+
+    /// Resize the framebuffer and notify the clients
+    ///
+    /// The framebuffer is cleared and needs to be updated afterwards. Clients not supporting
+    /// size changes are disconnected.
+    pub fn resize(&mut self, size: Size<i32, Physical>) {
+        let _guard = self.span.enter();
+        info!(?size, "Resizing framebuffer");
+
+        self.size = size;
+        self.framebuffer = vec![0; framebuffer_len(size)];
+        for client in &mut self.clients {
+            client.reactivate(size, &self.monitors);
+        }
+    }
+
+    /// Set the monitors presented to the clients
+    ///
+    /// The monitors are regions of the framebuffer, e.g. of the outputs rendered into it,
+    /// with the first one being the primary monitor. Clients not supporting multiple monitors
+    /// show the whole framebuffer as a single monitor, which is also the default.
+    pub fn set_monitors(&mut self, monitors: &[Rectangle<i32, Physical>]) {
+        self.monitors = monitors.to_vec();
+        for client in &mut self.clients {
+            client.reactivate(self.size, monitors);
+        }
+    }
+
+    /// Offer the given formats of the compositor's clipboard to the clients
+    ///
+    /// Clients request the contents when pasting, see [`RdpEvent::ClipboardDataRequested`].
+    pub fn set_clipboard_formats(&mut self, formats: &[RdpClipboardFormat]) {
+        self.clipboard_formats = formats.to_vec();
+        let pdu = protocol::clipboard_format_list(formats.iter().map(|f| (f.id, &*f.name)));
+        for client in &mut self.clients {
+            client.send_clipboard_pdu(&pdu);
+            if let Err(err) = client.flush() {
+                client.close(err);
+            }
+        }
+    }
+
+    /// Request the clipboard contents of a client in the given format
+    ///
+    /// The contents are emitted as [`RdpEvent::ClipboardData`].
+    pub fn request_clipboard_data(&mut self, client: u64, format: u32) {
+        if let Some(client) = self
+            .clients
+            .iter_mut()
+            .find(|c| c.id == client && c.clipboard_ready)
+        {
+            client.clipboard_requests.push_back(format);
+            client.send_clipboard_pdu(&protocol::clipboard_data_request(format));
+            if let Err(err) = client.flush() {
+                client.close(err);
+            }
+        }
+    }
+
+    /// Answer the oldest [`RdpEvent::ClipboardDataRequested`] of a client
+    ///
+    /// `None` tells the client that the contents are unavailable. Text formats have to be
+    /// null-terminated.
+    pub fn send_clipboard_data(&mut self, client: u64, data: Option<&[u8]>) {
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == client) {
+            client.send_clipboard_pdu(&protocol::clipboard_data_response(data));
+            if let Err(err) = client.flush() {
+                client.close(err);
+            }
+        }
+    }
+
+    fn accept(&mut self) -> io::Result<bool> {
+        let mut accepted = false;
+        loop {
+            let (stream, addr) = match self.listener.get_ref().accept() {
+                Ok(conn) => conn,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(accepted),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            stream.set_nonblocking(true)?;
+            let _ = stream.set_nodelay(true);
+
+            let id = self.next_client;
+            self.next_client += 1;
+            info!(client = id, %addr, "New RDP client");
+
+            self.clients.push(Client {
+                id,
+                addr,
+                stream: Generic::new(stream, Interest::BOTH, Mode::Edge),
+                registered: false,
+                state: ClientState::ConnectionRequest,
+                input: Vec::new(),
+                output: Vec::new(),
+                closed: false,
+                requested_protocols: 0,
+                user: MCS_IO_CHANNEL + 1,
+                clipboard_channel: None,
+                monitor_layout: false,
+                requested_size: None,
+                size: self.size,
+                monitors: Vec::new(),
+                fast_path_output: false,
+                desktop_resize: false,
+                suppressed: false,
+                damage: Vec::new(),
+                device_added: false,
+                buttons: HashSet::new(),
+                pointer: None,
+                pressed: HashSet::new(),
+                pause: false,
+                clipboard: ChannelData::default(),
+                clipboard_ready: false,
+                clipboard_requests: VecDeque::new(),
+            });
+            accepted = true;
+        }
+    }
+
+    fn dispatch_client(&mut self, idx: usize, readiness: Readiness, emit: &mut dyn FnMut(RdpEvent)) {
+        let res = (|| {
+            let client = &mut self.clients[idx];
+            if readiness.writable {
+                client.flush()?;
+            }
+            if !(readiness.readable || readiness.error) {
+                return self.handle_input(idx, emit);
+            }
+            // the stream is edge triggered, so keep reading until it is drained
+            while self.clients[idx].read()? {
+                self.handle_input(idx, emit)?;
+                if self.clients[idx].input.len() == MAX_INPUT_LEN {
+                    return Err(invalid_data("PDU exceeds the input buffer"));
+                }
+            }
+            self.handle_input(idx, emit)
+        })();
+        let client = &mut self.clients[idx];
+        match res {
+            Ok(()) => client.send_update(&self.framebuffer, self.size),
+            Err(err) => client.close(err),
+        }
+    }
+
+    fn handle_input(&mut self, idx: usize, emit: &mut dyn FnMut(RdpEvent)) -> io::Result<()> {
+        while !self.clients[idx].closed {
+            let client = &mut self.clients[idx];
+            let Some((pdu, len)) = ClientPdu::parse(&client.input)? else {
+                break;
+            };
+            client.input.drain(..len);
+            self.handle_pdu(idx, pdu, emit)?;
+        }
+        self.clients[idx].flush()
+    }
+
+    fn handle_pdu(&mut self, idx: usize, pdu: ClientPdu, emit: &mut dyn FnMut(RdpEvent)) -> io::Result<()> {
+        let client = &mut self.clients[idx];
+        match (client.state, pdu) {
+            (_, ClientPdu::Disconnect) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            (ClientState::ConnectionRequest, ClientPdu::ConnectionRequest { requested_protocols }) => {
+                client.requested_protocols = requested_protocols.unwrap_or(0);
+                protocol::write_connection_confirm(requested_protocols, &mut client.output);
+                client.state = ClientState::ConnectInitial;
+            }
+            (ClientState::ConnectInitial, ClientPdu::ConnectInitial(data)) => {
+                // the virtual channels follow the I/O channel, the user channel follows them
+                let channels = (1..=data.channels.len() as u16)
+                    .map(|idx| MCS_IO_CHANNEL + idx)
+                    .collect::<Vec<_>>();
+                client.user = MCS_IO_CHANNEL + channels.len() as u16 + 1;
+                client.clipboard_channel = data
+                    .channels
+                    .iter()
+                    .position(|name| name == protocol::CLIPRDR_CHANNEL)
+                    .map(|idx| channels[idx]);
+                client.monitor_layout = data.monitor_layout;
+                client.requested_size = Some(data.desktop_size);
+                protocol::write_connect_response(client.requested_protocols, &channels, &mut client.output);
+                client.state = ClientState::Connecting;
+            }
+            (ClientState::Connecting, ClientPdu::ErectDomain) => {}
+            (ClientState::Connecting, ClientPdu::AttachUser) => {
+                protocol::write_attach_user_confirm(client.user, &mut client.output);
+            }
+            (ClientState::Connecting, ClientPdu::ChannelJoin { channel }) => {
+                protocol::write_channel_join_confirm(client.user, channel, &mut client.output);
+            }
+            (
+                ClientState::Connecting,
+                ClientPdu::Data {
+                    channel: MCS_IO_CHANNEL,
+                    data,
+                },
+            ) => {
+                protocol::parse_client_info(&data)?;
+                protocol::write_license_valid(client.user, &mut client.output);
+                client.demand_active(self.size, &self.monitors);
+            }
+            (ClientState::ConnectionRequest | ClientState::ConnectInitial | ClientState::Connecting, _) => {
+                return Err(invalid_data("unexpected PDU during the connection sequence"));
+            }
+            (
+                _,
+                ClientPdu::Data {
+                    channel: MCS_IO_CHANNEL,
+                    data,
+                },
+            ) => {
+                let pdu = SharePdu::parse(&data)?;
+                self.handle_share_pdu(idx, pdu, emit);
+            }
+            (_, ClientPdu::Data { channel, data }) if Some(channel) == client.clipboard_channel => {
+                if let Some(data) = client.clipboard.push(&data, MAX_CLIPBOARD_LEN)? {
+                    let pdu = ClipboardPdu::parse(&data)?;
+                    self.handle_clipboard_pdu(idx, pdu, emit);
+                }
+            }
+            // other virtual channels are not supported
+            (_, ClientPdu::Data { .. }) => {}
+            (_, ClientPdu::FastPathInput(events)) => {
+                for event in events {
+                    self.handle_input_message(idx, event, emit);
+                }
+            }
+            (_, _) => return Err(invalid_data("unexpected PDU")),
+        }
+        Ok(())
+    }
+
+    fn handle_share_pdu(&mut self, idx: usize, pdu: SharePdu, emit: &mut dyn FnMut(RdpEvent)) {
+        let client = &mut self.clients[idx];
+        let bounds = Rectangle::from_loc_and_size((0, 0), self.size);
+        match pdu {
+            SharePdu::ConfirmActive {
+                fast_path_output,
+                desktop_resize,
+            } if client.state == ClientState::CapabilityExchange => {
+                client.fast_path_output = fast_path_output;
+                client.desktop_resize = desktop_resize;
+                client.state = ClientState::Finalization;
+                if !client.device_added {
+                    client.device_added = true;
+                    emit(RdpEvent::Input(InputEvent::DeviceAdded {
+                        device: client.device(),
+                    }));
+                }
+            }
+            SharePdu::ConfirmActive { .. } => {
+                debug!(client = client.id, "Ignoring unexpected Confirm Active PDU")
+            }
+            SharePdu::Synchronize => protocol::write_synchronize(client.user, &mut client.output),
+            SharePdu::Control { action }
+                if action == protocol::CTRLACTION_COOPERATE
+                    || action == protocol::CTRLACTION_REQUEST_CONTROL =>
+            {
+                protocol::write_control(client.user, action, &mut client.output)
+            }
+            SharePdu::Control { .. } => {}
+            SharePdu::FontList => {
+                protocol::write_font_map(client.user, &mut client.output);
+                if client.state != ClientState::Finalization {
+                    return;
+                }
+                client.state = ClientState::Active;
+                // the layout might have changed during the capability exchange
+                client.reactivate(self.size, &self.monitors);
+                if client.state != ClientState::Active {
+                    return;
+                }
+                client.damage = vec![bounds];
+
+                if client.clipboard_channel.is_some() && !client.clipboard_ready {
+                    client.clipboard_ready = true;
+                    for pdu in protocol::clipboard_init() {
+                        client.send_clipboard_pdu(&pdu);
+                    }
+                    if !self.clipboard_formats.is_empty() {
+                        let pdu = protocol::clipboard_format_list(
+                            self.clipboard_formats.iter().map(|f| (f.id, &*f.name)),
+                        );
+                        client.send_clipboard_pdu(&pdu);
+                    }
+                }
+                if let Some(size) = client.requested_size.take() {
+                    if size != self.size && size.w > 0 && size.h > 0 {
+                        emit(RdpEvent::ResizeRequested {
+                            client: client.id,
+                            size,
+                        });
+                    }
+                }
+            }
+            SharePdu::Input(events) => {
+                for event in events {
+                    self.handle_input_message(idx, event, emit);
+                }
+            }
+            SharePdu::RefreshRect(rects) => {
+                for rect in rects.into_iter().filter_map(|rect| rect.intersection(bounds)) {
+                    client.add_damage(rect);
+                }
+            }
+            SharePdu::SuppressOutput { allow } => {
+                client.suppressed = !allow;
+                if allow {
+                    client.damage = vec![bounds];
+                }
+            }
+            SharePdu::Shutdown => client.close(io::ErrorKind::UnexpectedEof.into()),
+            SharePdu::Ignored => {}
+        }
+    }
+
+    fn handle_input_message(&mut self, idx: usize, msg: InputMessage, emit: &mut dyn FnMut(RdpEvent)) {
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        let client = &mut self.clients[idx];
+        // input is only accepted after the capability exchange
+        if !client.device_added || client.closed {
+            return;
+        }
+        match msg {
+            InputMessage::Scancode {
+                code,
+                extended,
+                extended1,
+                release,
+            } => {
+                let pause = std::mem::take(&mut client.pause);
+                let key = if extended1 && code == SCANCODE_CONTROL {
+                    client.pause = true;
+                    Keycode::new(KEY_PAUSE + 8)
+                } else if pause && !extended && code == SCANCODE_NUMLOCK {
+                    return;
+                } else if let Some(key) = input::scancode_to_keycode(code, extended) {
+                    key
+                } else {
+                    debug!(client = client.id, code, extended, "Ignoring unknown scancode");
+                    return;
+                };
+                client.key(key, !release, time, emit);
+            }
+            InputMessage::Unicode { code, release } => {
+                let keysym = xkb::utf32_to_keysym(code as u32).raw();
+                let Some(&key) = self.keycodes.get(&keysym) else {
+                    debug!(client = client.id, code, "Ignoring unicode key without key code");
+                    return;
+                };
+                client.key(key, !release, time, emit);
+            }
+            InputMessage::Mouse { flags, x, y } => {
+                if flags & (PTRFLAGS_WHEEL | PTRFLAGS_HWHEEL) != 0 {
+                    // 9 bit two's complement, positive rotations scroll up and to the right
+                    let mut rotation = (flags & 0x01ff) as i32;
+                    if flags & PTRFLAGS_WHEEL_NEGATIVE != 0 {
+                        rotation -= 0x0200;
+                    }
+                    let (axis, amount_v120) = if flags & PTRFLAGS_HWHEEL != 0 {
+                        (Axis::Horizontal, rotation)
+                    } else {
+                        (Axis::Vertical, -rotation)
+                    };
+                    emit(RdpEvent::Input(InputEvent::PointerAxis {
+                        event: RdpMouseWheelEvent {
+                            time,
+                            device: client.device(),
+                            axis,
+                            amount_v120: amount_v120 as f64,
+                        },
+                    }));
+                    return;
+                }
+                client.motion(x, y, self.size, time, emit);
+                let button = match flags & (PTRFLAGS_BUTTON1 | PTRFLAGS_BUTTON2 | PTRFLAGS_BUTTON3) {
+                    PTRFLAGS_BUTTON1 => BTN_LEFT,
+                    PTRFLAGS_BUTTON2 => BTN_RIGHT,
+                    PTRFLAGS_BUTTON3 => BTN_MIDDLE,
+                    _ => return,
+                };
+                client.button(button, flags & PTRFLAGS_DOWN != 0, time, emit);
+            }
+            InputMessage::ExtendedMouse { flags, x, y } => {
+                client.motion(x, y, self.size, time, emit);
+                let button = match flags & (PTRXFLAGS_BUTTON1 | PTRXFLAGS_BUTTON2) {
+                    PTRXFLAGS_BUTTON1 => BTN_SIDE,
+                    PTRXFLAGS_BUTTON2 => BTN_EXTRA,
+                    _ => return,
+                };
+                client.button(button, flags & PTRFLAGS_DOWN != 0, time, emit);
+            }
+            InputMessage::Ignored => {}
+        }
+    }
+
+    fn handle_clipboard_pdu(&mut self, idx: usize, pdu: ClipboardPdu, emit: &mut dyn FnMut(RdpEvent)) {
+        let client = &mut self.clients[idx];
+        match pdu {
+            ClipboardPdu::FormatList(formats) => {
+                client.send_clipboard_pdu(&protocol::clipboard_format_list_response());
+                emit(RdpEvent::ClipboardFormats {
+                    client: client.id,
+                    formats: formats
+                        .into_iter()
+                        .map(|(id, name)| RdpClipboardFormat { id, name })
+                        .collect(),
+                });
+            }
+            ClipboardPdu::DataRequest { format } => emit(RdpEvent::ClipboardDataRequested {
+                client: client.id,
+                format,
+            }),
+            ClipboardPdu::DataResponse(data) => match client.clipboard_requests.pop_front() {
+                Some(format) => emit(RdpEvent::ClipboardData {
+                    client: client.id,
+                    format,
+                    data,
+                }),
+                None => debug!(client = client.id, "Ignoring unrequested clipboard data"),
+            },
+            ClipboardPdu::Ignored => {}
+        }
+    }
+
+    fn remove_closed(&mut self, emit: &mut dyn FnMut(RdpEvent)) {
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        for client in self.clients.iter_mut().filter(|client| client.closed) {
+            if !client.device_added {
+                continue;
+            }
+            // release everything still held by the client
+            let device = client.device();
+            let pressed = std::mem::take(&mut client.pressed);
+            for (idx, key) in pressed.iter().enumerate() {
+                emit(RdpEvent::Input(InputEvent::Keyboard {
+                    event: RdpKeyboardInputEvent {
+                        time,
+                        device: device.clone(),
+                        key: *key,
+                        count: (pressed.len() - idx - 1) as u32,
+                        state: KeyState::Released,
+                    },
+                }));
+            }
+            for button in client.buttons.drain() {
+                emit(RdpEvent::Input(InputEvent::PointerButton {
+                    event: RdpMouseInputEvent {
+                        time,
+                        device: device.clone(),
+                        button,
+                        state: ButtonState::Released,
+                    },
+                }));
+            }
+            emit(RdpEvent::Input(InputEvent::DeviceRemoved { device }));
+        }
+        // dropping the sources removes them from the event loop
+        self.clients.retain(|client| !client.closed);
+    }
+}
+
+fn framebuffer_len(size: Size<i32, Physical>) -> usize {
+    size.w.max(0) as usize * size.h.max(0) as usize * 4
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+impl From<protocol::InvalidPdu> for io::Error {
+    fn from(protocol::InvalidPdu(msg): protocol::InvalidPdu) -> Self {
+        invalid_data(msg)
+    }
+}
+
+impl EventSource for RdpBackend {
+    type Event = RdpEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(RdpEvent, &mut ()),
+    {
+        let span = self.span.clone();
+        let _guard = span.enter();
+        let mut emit = |event| callback(event, &mut ());
+
+        let mut accept = false;
+        self.listener.process_events(readiness, token, |_, _| {
+            accept = true;
+            Ok(PostAction::Continue)
+        })?;
+        let accepted = accept && self.accept()?;
+
+        for idx in 0..self.clients.len() {
+            let mut ready = None;
+            self.clients[idx]
+                .stream
+                .process_events(readiness, token, |readiness, _| {
+                    ready = Some(readiness);
+                    Ok(PostAction::Continue)
+                })?;
+            if let Some(readiness) = ready {
+                self.dispatch_client(idx, readiness, &mut emit);
+            }
+        }
+        self.remove_closed(&mut emit);
+
+        Ok(if accepted {
+            // registers the new clients
+            PostAction::Reregister
+        } else {
+            PostAction::Continue
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.register(poll, factory)?;
+        for client in &mut self.clients {
+            client.stream.register(poll, factory)?;
+            client.registered = true;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.reregister(poll, factory)?;
+        for client in &mut self.clients {
+            if client.registered {
+                client.stream.reregister(poll, factory)?;
+            } else {
+                client.stream.register(poll, factory)?;
+                client.registered = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.listener.unregister(poll)?;
+        for client in self.clients.iter_mut().filter(|client| client.registered) {
+            client.stream.unregister(poll)?;
+            client.registered = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1272 @@
+//! Message encoding and parsing of the remote desktop protocol ([MS-RDPBCGR], [MS-RDPECLIP])
+//!
+//! Only the subset needed by the server is implemented: standard RDP security without
+//! encryption, uncompressed bitmap updates, slow-path and fast-path input and the clipboard
+//! virtual channel.
+//!
+//! [MS-RDPBCGR]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpbcgr
+//! [MS-RDPECLIP]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-rdpeclip
+
+use crate::utils::{Physical, Rectangle, Size};
+
+/// Channel of the slow-path PDUs
+pub(super) const MCS_IO_CHANNEL: u16 = 1003;
+// user ids and channels are sent relative to this base
+const MCS_BASE_CHANNEL: u16 = 1001;
+// source of the PDUs sent by the server
+const SERVER_CHANNEL: u16 = 1002;
+const SHARE_ID: u32 = 0x0001_03ea;
+
+/// Name of the static virtual channel of the clipboard
+pub(super) const CLIPRDR_CHANNEL: &str = "cliprdr";
+// maximum size of a virtual channel chunk, which is the default `VCChunkSize`
+const CHANNEL_CHUNK_LEN: usize = 1600;
+const CHANNEL_FLAG_FIRST: u32 = 0x01;
+const CHANNEL_FLAG_LAST: u32 = 0x02;
+
+/// Size of the tiles bitmap updates are split into, which keeps them below the maximum PDU size
+pub(super) const TILE_SIZE: i32 = 64;
+
+// early capability flags of the client core data
+const RNS_UD_CS_SUPPORT_MONITOR_LAYOUT_PDU: u16 = 0x0040;
+
+// share control PDU types
+const PDUTYPE_DEMANDACTIVEPDU: u16 = 0x1;
+const PDUTYPE_CONFIRMACTIVEPDU: u16 = 0x3;
+const PDUTYPE_DEACTIVATEALLPDU: u16 = 0x6;
+const PDUTYPE_DATAPDU: u16 = 0x7;
+
+// share data PDU types
+const PDUTYPE2_UPDATE: u8 = 0x02;
+const PDUTYPE2_CONTROL: u8 = 0x14;
+const PDUTYPE2_INPUT: u8 = 0x1c;
+const PDUTYPE2_SYNCHRONIZE: u8 = 0x1f;
+const PDUTYPE2_REFRESH_RECT: u8 = 0x21;
+const PDUTYPE2_SUPPRESS_OUTPUT: u8 = 0x23;
+const PDUTYPE2_SHUTDOWN_REQUEST: u8 = 0x24;
+const PDUTYPE2_FONTLIST: u8 = 0x27;
+const PDUTYPE2_FONTMAP: u8 = 0x28;
+const PDUTYPE2_MONITOR_LAYOUT_PDU: u8 = 0x37;
+
+/// Control action of a client requesting control
+pub(super) const CTRLACTION_REQUEST_CONTROL: u16 = 0x0001;
+const CTRLACTION_GRANTED_CONTROL: u16 = 0x0002;
+/// Control action of a client cooperating
+pub(super) const CTRLACTION_COOPERATE: u16 = 0x0004;
+
+// clipboard PDU types
+const CB_MONITOR_READY: u16 = 0x0001;
+const CB_FORMAT_LIST: u16 = 0x0002;
+const CB_FORMAT_LIST_RESPONSE: u16 = 0x0003;
+const CB_FORMAT_DATA_REQUEST: u16 = 0x0004;
+const CB_FORMAT_DATA_RESPONSE: u16 = 0x0005;
+const CB_CLIP_CAPS: u16 = 0x0007;
+const CB_RESPONSE_OK: u16 = 0x0001;
+const CB_RESPONSE_FAIL: u16 = 0x0002;
+const CB_ASCII_NAMES: u16 = 0x0004;
+
+/// Error of a malformed or unsupported PDU, which makes the rest of the stream unparsable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct InvalidPdu(pub &'static str);
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], InvalidPdu> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or(InvalidPdu("truncated PDU"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, InvalidPdu> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_le(&mut self) -> Result<u16, InvalidPdu> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u16_be(&mut self) -> Result<u16, InvalidPdu> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32_le(&mut self) -> Result<u32, InvalidPdu> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos.min(self.buf.len())..];
+        self.pos = self.buf.len();
+        rest
+    }
+
+    fn per_length(&mut self) -> Result<usize, InvalidPdu> {
+        let len = self.u8()? as usize;
+        if len & 0x80 != 0 {
+            Ok((len & 0x7f) << 8 | self.u8()? as usize)
+        } else {
+            Ok(len)
+        }
+    }
+
+    fn ber_length(&mut self) -> Result<usize, InvalidPdu> {
+        let len = self.u8()? as usize;
+        if len & 0x80 == 0 {
+            return Ok(len);
+        }
+        let bytes = self.bytes(len & 0x7f)?;
+        if bytes.len() > 4 {
+            return Err(InvalidPdu("invalid BER length"));
+        }
+        Ok(bytes.iter().fold(0, |len, byte| len << 8 | *byte as usize))
+    }
+}
+
+/// Data blocks sent by the client in the MCS Connect Initial PDU
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct ClientData {
+    /// Desktop size requested by the client
+    pub desktop_size: Size<i32, Physical>,
+    /// Whether the client supports the Monitor Layout PDU
+    pub monitor_layout: bool,
+    /// Names of the static virtual channels requested by the client
+    pub channels: Vec<String>,
+}
+
+impl ClientData {
+    fn parse(buf: &[u8]) -> Result<ClientData, InvalidPdu> {
+        // the BER encoded Connect Initial PDU, of which only the user data is used
+        let mut reader = Reader::new(buf);
+        if reader.bytes(2)? != [0x7f, 0x65] {
+            return Err(InvalidPdu("expected MCS Connect Initial"));
+        }
+        let len = reader.ber_length()?;
+        let mut reader = Reader::new(reader.bytes(len)?);
+        let mut user_data = None;
+        while reader.pos < reader.buf.len() {
+            let tag = reader.u8()?;
+            let len = reader.ber_length()?;
+            let value = reader.bytes(len)?;
+            if tag == 0x04 {
+                user_data = Some(value);
+            }
+        }
+        let user_data = user_data.ok_or(InvalidPdu("missing MCS user data"))?;
+
+        // the PER encoded GCC Conference Create Request, the client data blocks follow its H.221 key
+        let key = user_data
+            .windows(4)
+            .position(|key| key == b"Duca")
+            .ok_or(InvalidPdu("missing GCC client data"))?;
+        let mut reader = Reader::new(&user_data[key + 4..]);
+        let len = reader.per_length()?;
+        let mut reader = Reader::new(reader.bytes(len)?);
+
+        let mut data = ClientData::default();
+        while reader.pos < reader.buf.len() {
+            let ty = reader.u16_le()?;
+            let len = reader.u16_le()? as usize;
+            let mut block =
+                Reader::new(reader.bytes(len.checked_sub(4).ok_or(InvalidPdu("invalid data block"))?)?);
+            match ty {
+                // CS_CORE
+                0xc001 => {
+                    block.bytes(4)?;
+                    data.desktop_size = (block.u16_le()? as i32, block.u16_le()? as i32).into();
+                    // the optional fields are present up to the length of the block
+                    if len >= 146 {
+                        block.bytes(132)?;
+                        data.monitor_layout = block.u16_le()? & RNS_UD_CS_SUPPORT_MONITOR_LAYOUT_PDU != 0;
+                    }
+                }
+                // CS_NET
+                0xc003 => {
+                    let count = block.u32_le()?;
+                    for _ in 0..count {
+                        let name = block.bytes(8)?;
+                        block.u32_le()?;
+                        let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                        data.channels
+                            .push(String::from_utf8_lossy(&name[..len]).into_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Input event of a slow-path or fast-path input PDU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum InputMessage {
+    Scancode {
+        code: u8,
+        extended: bool,
+        /// Prefix of the pause key
+        extended1: bool,
+        release: bool,
+    },
+    Unicode {
+        code: u16,
+        release: bool,
+    },
+    Mouse {
+        flags: u16,
+        x: u16,
+        y: u16,
+    },
+    ExtendedMouse {
+        flags: u16,
+        x: u16,
+        y: u16,
+    },
+    /// Lock key state or other events without effect
+    Ignored,
+}
+
+impl InputMessage {
+    fn parse_fast_path(reader: &mut Reader<'_>) -> Result<InputMessage, InvalidPdu> {
+        let header = reader.u8()?;
+        let flags = header & 0x1f;
+        Ok(match header >> 5 {
+            0 => InputMessage::Scancode {
+                code: reader.u8()?,
+                release: flags & 0x01 != 0,
+                extended: flags & 0x02 != 0,
+                extended1: flags & 0x04 != 0,
+            },
+            1 | 2 => {
+                let (flags, x, y) = (reader.u16_le()?, reader.u16_le()?, reader.u16_le()?);
+                if header >> 5 == 1 {
+                    InputMessage::Mouse { flags, x, y }
+                } else {
+                    InputMessage::ExtendedMouse { flags, x, y }
+                }
+            }
+            3 => InputMessage::Ignored,
+            4 => InputMessage::Unicode {
+                code: reader.u16_le()?,
+                release: flags & 0x01 != 0,
+            },
+            // quality of experience timestamp
+            6 => {
+                reader.u32_le()?;
+                InputMessage::Ignored
+            }
+            _ => return Err(InvalidPdu("unsupported fast-path input event")),
+        })
+    }
+
+    fn parse_slow_path(reader: &mut Reader<'_>) -> Result<InputMessage, InvalidPdu> {
+        // event time
+        reader.u32_le()?;
+        let ty = reader.u16_le()?;
+        let (flags, a, b) = (reader.u16_le()?, reader.u16_le()?, reader.u16_le()?);
+        Ok(match ty {
+            0x0004 => InputMessage::Scancode {
+                code: a as u8,
+                extended: flags & 0x0100 != 0,
+                extended1: flags & 0x0200 != 0,
+                release: flags & 0x8000 != 0,
+            },
+            0x0005 => InputMessage::Unicode {
+                code: a,
+                release: flags & 0x8000 != 0,
+            },
+            0x8001 => InputMessage::Mouse { flags, x: a, y: b },
+            0x8002 => InputMessage::ExtendedMouse { flags, x: a, y: b },
+            _ => InputMessage::Ignored,
+        })
+    }
+}
+
+/// PDU sent by the client on the transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum ClientPdu {
+    ConnectionRequest {
+        /// Security protocols of the negotiation request, if any
+        requested_protocols: Option<u32>,
+    },
+    ConnectInitial(ClientData),
+    ErectDomain,
+    AttachUser,
+    ChannelJoin {
+        channel: u16,
+    },
+    /// Data sent on a MCS channel
+    Data {
+        channel: u16,
+        data: Vec<u8>,
+    },
+    FastPathInput(Vec<InputMessage>),
+    Disconnect,
+}
+
+impl ClientPdu {
+    /// Parses the next PDU of `buf`, returning it with its length
+    ///
+    /// Returns `Ok(None)` if the PDU is incomplete.
+    pub fn parse(buf: &[u8]) -> Result<Option<(ClientPdu, usize)>, InvalidPdu> {
+        let Some(&header) = buf.first() else {
+            return Ok(None);
+        };
+        let mut reader = Reader::new(buf);
+        let len = if header == 0x03 {
+            // TPKT
+            let Ok(header) = reader.bytes(4) else {
+                return Ok(None);
+            };
+            u16::from_be_bytes([header[2], header[3]]) as usize
+        } else if header & 0x03 == 0 {
+            if header & 0x80 != 0 {
+                return Err(InvalidPdu("encrypted fast-path input"));
+            }
+            reader.u8().unwrap();
+            match reader.per_length() {
+                Ok(len) => len,
+                Err(_) => return Ok(None),
+            }
+        } else {
+            return Err(InvalidPdu("unknown PDU"));
+        };
+        if len < reader.pos {
+            return Err(InvalidPdu("invalid PDU length"));
+        }
+        let Some(pdu) = buf.get(reader.pos..len) else {
+            return Ok(None);
+        };
+
+        let pdu = if header == 0x03 {
+            Self::parse_x224(pdu)?
+        } else {
+            let mut reader = Reader::new(pdu);
+            let count = match (header >> 2) & 0x0f {
+                0 => reader.u8()?,
+                count => count,
+            };
+            ClientPdu::FastPathInput(
+                (0..count)
+                    .map(|_| InputMessage::parse_fast_path(&mut reader))
+                    .collect::<Result<_, _>>()?,
+            )
+        };
+        Ok(Some((pdu, len)))
+    }
+
+    fn parse_x224(buf: &[u8]) -> Result<ClientPdu, InvalidPdu> {
+        let mut reader = Reader::new(buf);
+        let _header_len = reader.u8()?;
+        let code = reader.u8()?;
+        match code {
+            // Connection Request
+            0xe0 => {
+                // the length of the header includes the variable part
+                reader.bytes(5)?;
+                let mut rest = reader.rest();
+                // skips the cookie or routing token
+                if rest.first() != Some(&0x01) {
+                    if let Some(end) = rest.windows(2).position(|end| end == b"\r\n") {
+                        rest = &rest[end + 2..];
+                    }
+                }
+                let requested_protocols = match rest {
+                    [0x01, _, 8, 0, protocols @ ..] if protocols.len() >= 4 => {
+                        Some(u32::from_le_bytes(protocols[..4].try_into().unwrap()))
+                    }
+                    _ => None,
+                };
+                Ok(ClientPdu::ConnectionRequest { requested_protocols })
+            }
+            // Disconnect Request
+            0x80 => Ok(ClientPdu::Disconnect),
+            // Data
+            0xf0 => {
+                reader.u8()?;
+                Self::parse_mcs(reader.rest())
+            }
+            _ => Err(InvalidPdu("unsupported X.224 PDU")),
+        }
+    }
+
+    fn parse_mcs(buf: &[u8]) -> Result<ClientPdu, InvalidPdu> {
+        if buf.starts_with(&[0x7f, 0x65]) {
+            return ClientData::parse(buf).map(ClientPdu::ConnectInitial);
+        }
+        let mut reader = Reader::new(buf);
+        Ok(match reader.u8()? >> 2 {
+            1 => ClientPdu::ErectDomain,
+            // Disconnect Provider Ultimatum
+            8 => ClientPdu::Disconnect,
+            10 => ClientPdu::AttachUser,
+            14 => {
+                let _initiator = reader.u16_be()?;
+                ClientPdu::ChannelJoin {
+                    channel: reader.u16_be()?,
+                }
+            }
+            // Send Data Request
+            25 => {
+                let _initiator = reader.u16_be()?;
+                let channel = reader.u16_be()?;
+                // data priority and segmentation
+                reader.u8()?;
+                let len = reader.per_length()?;
+                ClientPdu::Data {
+                    channel,
+                    data: reader.bytes(len)?.to_vec(),
+                }
+            }
+            _ => return Err(InvalidPdu("unsupported MCS PDU")),
+        })
+    }
+}
+
+/// Checks the security header of the Client Info PDU
+///
+/// The contents are ignored, as the connection is neither authenticated nor encrypted.
+pub(super) fn parse_client_info(buf: &[u8]) -> Result<(), InvalidPdu> {
+    let mut reader = Reader::new(buf);
+    let flags = reader.u16_le()?;
+    // SEC_INFO_PKT without SEC_ENCRYPT
+    if flags & 0x0040 == 0 || flags & 0x0008 != 0 {
+        return Err(InvalidPdu("expected unencrypted Client Info PDU"));
+    }
+    Ok(())
+}
+
+/// Slow-path PDU sent by the client on the I/O channel after the Client Info PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum SharePdu {
+    ConfirmActive {
+        /// Whether the client accepts fast-path updates
+        fast_path_output: bool,
+        /// Whether the client supports changes of the desktop size
+        desktop_resize: bool,
+    },
+    Synchronize,
+    Control {
+        action: u16,
+    },
+    FontList,
+    Input(Vec<InputMessage>),
+    RefreshRect(Vec<Rectangle<i32, Physical>>),
+    SuppressOutput {
+        allow: bool,
+    },
+    Shutdown,
+    /// PDUs without effect, like flow control or persistent bitmap cache keys
+    Ignored,
+}
+
+impl SharePdu {
+    pub fn parse(buf: &[u8]) -> Result<SharePdu, InvalidPdu> {
+        let mut reader = Reader::new(buf);
+        let len = reader.u16_le()?;
+        // flow control PDUs
+        if len == 0x8000 {
+            return Ok(SharePdu::Ignored);
+        }
+        let ty = reader.u16_le()?;
+        let _source = reader.u16_le()?;
+        match ty & 0x0f {
+            PDUTYPE_CONFIRMACTIVEPDU => Self::parse_confirm_active(&mut reader),
+            PDUTYPE_DATAPDU => Self::parse_data(&mut reader),
+            _ => Ok(SharePdu::Ignored),
+        }
+    }
+
+    fn parse_confirm_active(reader: &mut Reader<'_>) -> Result<SharePdu, InvalidPdu> {
+        let _share_id = reader.u32_le()?;
+        let _originator = reader.u16_le()?;
+        let source_len = reader.u16_le()? as usize;
+        let _caps_len = reader.u16_le()?;
+        reader.bytes(source_len)?;
+        let count = reader.u16_le()?;
+        reader.u16_le()?;
+
+        let mut fast_path_output = false;
+        let mut desktop_resize = false;
+        for _ in 0..count {
+            let ty = reader.u16_le()?;
+            let len = reader.u16_le()? as usize;
+            let mut cap =
+                Reader::new(reader.bytes(len.checked_sub(4).ok_or(InvalidPdu("invalid capability"))?)?);
+            match ty {
+                // general capability set
+                0x0001 => {
+                    cap.bytes(10)?;
+                    // FASTPATH_OUTPUT_SUPPORTED
+                    fast_path_output = cap.u16_le()? & 0x0001 != 0;
+                }
+                // bitmap capability set
+                0x0002 => {
+                    cap.bytes(14)?;
+                    desktop_resize = cap.u16_le()? != 0;
+                }
+                _ => {}
+            }
+        }
+        Ok(SharePdu::ConfirmActive {
+            fast_path_output,
+            desktop_resize,
+        })
+    }
+
+    fn parse_data(reader: &mut Reader<'_>) -> Result<SharePdu, InvalidPdu> {
+        let _share_id = reader.u32_le()?;
+        reader.bytes(4)?;
+        let ty = reader.u8()?;
+        // PACKET_COMPRESSED
+        if reader.u8()? & 0x20 != 0 {
+            return Err(InvalidPdu("compressed PDU"));
+        }
+        reader.u16_le()?;
+        Ok(match ty {
+            PDUTYPE2_SYNCHRONIZE => SharePdu::Synchronize,
+            PDUTYPE2_CONTROL => SharePdu::Control {
+                action: reader.u16_le()?,
+            },
+            PDUTYPE2_FONTLIST => SharePdu::FontList,
+            PDUTYPE2_INPUT => {
+                let count = reader.u16_le()?;
+                reader.u16_le()?;
+                SharePdu::Input(
+                    (0..count)
+                        .map(|_| InputMessage::parse_slow_path(reader))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            PDUTYPE2_REFRESH_RECT => {
+                let count = reader.u8()?;
+                reader.bytes(3)?;
+                SharePdu::RefreshRect(
+                    (0..count)
+                        .map(|_| parse_inclusive_rect(reader))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            PDUTYPE2_SUPPRESS_OUTPUT => SharePdu::SuppressOutput {
+                allow: reader.u8()? != 0,
+            },
+            PDUTYPE2_SHUTDOWN_REQUEST => SharePdu::Shutdown,
+            _ => SharePdu::Ignored,
+        })
+    }
+}
+
+fn parse_inclusive_rect(reader: &mut Reader<'_>) -> Result<Rectangle<i32, Physical>, InvalidPdu> {
+    let (left, top) = (reader.u16_le()? as i32, reader.u16_le()? as i32);
+    let (right, bottom) = (reader.u16_le()? as i32, reader.u16_le()? as i32);
+    Ok(Rectangle::from_extemities((left, top), (right + 1, bottom + 1)))
+}
+
+/// Reassembles the chunks of a static virtual channel
+#[derive(Debug, Default)]
+pub(super) struct ChannelData {
+    data: Vec<u8>,
+}
+
+impl ChannelData {
+    /// Adds a chunk, returning the message once it is complete
+    ///
+    /// Messages longer than `max_len` are rejected.
+    pub fn push(&mut self, chunk: &[u8], max_len: usize) -> Result<Option<Vec<u8>>, InvalidPdu> {
+        let mut reader = Reader::new(chunk);
+        let len = reader.u32_le()? as usize;
+        let flags = reader.u32_le()?;
+        if len > max_len {
+            return Err(InvalidPdu("virtual channel message too long"));
+        }
+        if flags & CHANNEL_FLAG_FIRST != 0 {
+            self.data.clear();
+        }
+        self.data.extend_from_slice(reader.rest());
+        if self.data.len() > len {
+            return Err(InvalidPdu("virtual channel message exceeds its length"));
+        }
+        Ok((flags & CHANNEL_FLAG_LAST != 0).then(|| std::mem::take(&mut self.data)))
+    }
+}
+
+/// PDU of the clipboard virtual channel sent by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum ClipboardPdu {
+    /// The clipboard of the client changed, offering data in the given formats
+    FormatList(Vec<(u32, String)>),
+    DataRequest {
+        format: u32,
+    },
+    DataResponse(Option<Vec<u8>>),
+    /// Capabilities and other PDUs without effect
+    Ignored,
+}
+
+impl ClipboardPdu {
+    pub fn parse(buf: &[u8]) -> Result<ClipboardPdu, InvalidPdu> {
+        let mut reader = Reader::new(buf);
+        let ty = reader.u16_le()?;
+        let flags = reader.u16_le()?;
+        let len = reader.u32_le()? as usize;
+        let mut reader = Reader::new(reader.bytes(len)?);
+        Ok(match ty {
+            // the server does not announce long format names, so the short ones are used
+            CB_FORMAT_LIST => {
+                let mut formats = Vec::new();
+                while reader.pos < reader.buf.len() {
+                    let id = reader.u32_le()?;
+                    let name = reader.bytes(32)?;
+                    let name = if flags & CB_ASCII_NAMES != 0 {
+                        let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                        String::from_utf8_lossy(&name[..len]).into_owned()
+                    } else {
+                        let name = name
+                            .chunks_exact(2)
+                            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                            .take_while(|c| *c != 0)
+                            .collect::<Vec<_>>();
+                        String::from_utf16_lossy(&name)
+                    };
+                    formats.push((id, name));
+                }
+                ClipboardPdu::FormatList(formats)
+            }
+            CB_FORMAT_DATA_REQUEST => ClipboardPdu::DataRequest {
+                format: reader.u32_le()?,
+            },
+            CB_FORMAT_DATA_RESPONSE if flags & CB_RESPONSE_FAIL != 0 => ClipboardPdu::DataResponse(None),
+            CB_FORMAT_DATA_RESPONSE => ClipboardPdu::DataResponse(Some(reader.rest().to_vec())),
+            _ => ClipboardPdu::Ignored,
+        })
+    }
+}
+
+fn write_len_prefixed(
+    out: &mut Vec<u8>,
+    len_size: usize,
+    f: impl FnOnce(&mut Vec<u8>),
+    len: impl FnOnce(usize, &mut [u8]),
+) {
+    let pos = out.len();
+    out.resize(pos + len_size, 0);
+    f(out);
+    let data_len = out.len() - pos - len_size;
+    len(data_len, &mut out[pos..pos + len_size]);
+}
+
+fn write_tpkt(out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    out.extend_from_slice(&[0x03, 0x00]);
+    write_len_prefixed(out, 2, f, |len, buf| {
+        buf.copy_from_slice(&(len as u16 + 4).to_be_bytes())
+    });
+}
+
+fn write_x224_data(out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    write_tpkt(out, |out| {
+        out.extend_from_slice(&[0x02, 0xf0, 0x80]);
+        f(out);
+    });
+}
+
+fn write_ber_length(len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xff {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.push(0x82);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+}
+
+fn write_ber_integer(value: u32, out: &mut Vec<u8>) {
+    let bytes = value.to_be_bytes();
+    // minimal two's complement encoding of the unsigned value
+    let skip = bytes
+        .windows(2)
+        .take_while(|pair| pair[0] == 0 && pair[1] & 0x80 == 0)
+        .count();
+    out.push(0x02);
+    write_ber_length(4 - skip, out);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+fn write_per_length(len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        out.extend_from_slice(&(len as u16 | 0x8000).to_be_bytes());
+    }
+}
+
+/// Writes the X.224 Connection Confirm PDU selecting standard RDP security
+pub(super) fn write_connection_confirm(requested_protocols: Option<u32>, out: &mut Vec<u8>) {
+    write_tpkt(out, |out| {
+        let header_len = if requested_protocols.is_some() { 14 } else { 6 };
+        out.extend_from_slice(&[header_len, 0xd0, 0x00, 0x00, 0x12, 0x34, 0x00]);
+        if requested_protocols.is_some() {
+            // RDP_NEG_RSP selecting PROTOCOL_RDP
+            out.extend_from_slice(&[0x02, 0x00, 0x08, 0x00]);
+            out.extend_from_slice(&0u32.to_le_bytes());
+        }
+    });
+}
+
+/// Writes the MCS Connect Response PDU, assigning `channels` to the requested virtual channels
+pub(super) fn write_connect_response(requested_protocols: u32, channels: &[u16], out: &mut Vec<u8>) {
+    let mut blocks = Vec::new();
+    // SC_CORE
+    blocks.extend_from_slice(&0x0c01u16.to_le_bytes());
+    blocks.extend_from_slice(&12u16.to_le_bytes());
+    blocks.extend_from_slice(&0x0008_0004u32.to_le_bytes());
+    blocks.extend_from_slice(&requested_protocols.to_le_bytes());
+    // SC_SECURITY without encryption
+    blocks.extend_from_slice(&0x0c02u16.to_le_bytes());
+    blocks.extend_from_slice(&12u16.to_le_bytes());
+    blocks.extend_from_slice(&[0; 8]);
+    // SC_NET, padded to an even number of channels
+    let padded = channels.len() + channels.len() % 2;
+    blocks.extend_from_slice(&0x0c03u16.to_le_bytes());
+    blocks.extend_from_slice(&(8 + 2 * padded as u16).to_le_bytes());
+    blocks.extend_from_slice(&MCS_IO_CHANNEL.to_le_bytes());
+    blocks.extend_from_slice(&(channels.len() as u16).to_le_bytes());
+    for channel in channels {
+        blocks.extend_from_slice(&channel.to_le_bytes());
+    }
+    blocks.resize(blocks.len() + 2 * (padded - channels.len()), 0);
+
+    // GCC Conference Create Response
+    let mut response = vec![
+        0x14, // conferenceCreateResponse
+        0x76, 0x0a, // node id
+        0x01, 0x01, // tag
+        0x00, // result
+        0x01, // number of user data sets
+        0xc0, // h221NonStandard key with value
+        0x00, b'M', b'c', b'D', b'n',
+    ];
+    write_per_length(blocks.len(), &mut response);
+    response.extend_from_slice(&blocks);
+    // T.124 object identifier
+    let mut gcc = vec![0x00, 0x05, 0x00, 0x14, 0x7c, 0x00, 0x01];
+    write_per_length(response.len(), &mut gcc);
+    gcc.extend_from_slice(&response);
+
+    let mut content = vec![
+        0x0a, 0x01, 0x00, // result
+        0x02, 0x01, 0x00, // called connect id
+    ];
+    let mut parameters = Vec::new();
+    for value in [22, 3, 0, 1, 0, 1, 0xfff8, 2] {
+        write_ber_integer(value, &mut parameters);
+    }
+    content.push(0x30);
+    write_ber_length(parameters.len(), &mut content);
+    content.extend_from_slice(&parameters);
+    content.push(0x04);
+    write_ber_length(gcc.len(), &mut content);
+    content.extend_from_slice(&gcc);
+
+    write_x224_data(out, |out| {
+        out.extend_from_slice(&[0x7f, 0x66]);
+        write_ber_length(content.len(), out);
+        out.extend_from_slice(&content);
+    });
+}
+
+/// Writes the MCS Attach User Confirm PDU
+pub(super) fn write_attach_user_confirm(user: u16, out: &mut Vec<u8>) {
+    write_x224_data(out, |out| {
+        out.extend_from_slice(&[0x2e, 0x00]);
+        out.extend_from_slice(&(user - MCS_BASE_CHANNEL).to_be_bytes());
+    });
+}
+
+/// Writes the MCS Channel Join Confirm PDU
+pub(super) fn write_channel_join_confirm(user: u16, channel: u16, out: &mut Vec<u8>) {
+    write_x224_data(out, |out| {
+        out.extend_from_slice(&[0x3e, 0x00]);
+        out.extend_from_slice(&(user - MCS_BASE_CHANNEL).to_be_bytes());
+        out.extend_from_slice(&channel.to_be_bytes());
+        out.extend_from_slice(&channel.to_be_bytes());
+    });
+}
+
+fn write_send_data(user: u16, channel: u16, out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    write_x224_data(out, |out| {
+        // Send Data Indication
+        out.push(0x68);
+        out.extend_from_slice(&(user - MCS_BASE_CHANNEL).to_be_bytes());
+        out.extend_from_slice(&channel.to_be_bytes());
+        out.push(0x70);
+        write_len_prefixed(out, 2, f, |len, buf| {
+            buf.copy_from_slice(&(len as u16 | 0x8000).to_be_bytes())
+        });
+    });
+}
+
+fn write_share_control(ty: u16, user: u16, out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    write_send_data(user, MCS_IO_CHANNEL, out, |out| {
+        write_len_prefixed(
+            out,
+            2,
+            |out| {
+                out.extend_from_slice(&(ty | 0x10).to_le_bytes());
+                out.extend_from_slice(&SERVER_CHANNEL.to_le_bytes());
+                f(out);
+            },
+            |len, buf| buf.copy_from_slice(&(len as u16 + 2).to_le_bytes()),
+        );
+    });
+}
+
+fn write_share_data(ty: u8, user: u16, out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    write_share_control(PDUTYPE_DATAPDU, user, out, |out| {
+        out.extend_from_slice(&SHARE_ID.to_le_bytes());
+        // padding and STREAM_LOW
+        out.extend_from_slice(&[0x00, 0x01]);
+        let pos = out.len();
+        out.extend_from_slice(&[0, 0, ty, 0, 0, 0]);
+        f(out);
+        // includes the type, compression type and compressed length
+        let len = out.len() - pos - 2;
+        out[pos..pos + 2].copy_from_slice(&(len as u16).to_le_bytes());
+    });
+}
+
+/// Writes the License Error PDU telling the client that it does not need a license
+pub(super) fn write_license_valid(user: u16, out: &mut Vec<u8>) {
+    write_send_data(user, MCS_IO_CHANNEL, out, |out| {
+        // SEC_LICENSE_PKT
+        out.extend_from_slice(&[0x80, 0x00, 0x00, 0x00]);
+        // ERROR_ALERT, PREAMBLE_VERSION_3_0
+        out.extend_from_slice(&[0xff, 0x03]);
+        out.extend_from_slice(&16u16.to_le_bytes());
+        // STATUS_VALID_CLIENT, ST_NO_TRANSITION
+        out.extend_from_slice(&7u32.to_le_bytes());
+        out.extend_from_slice(&2u32.to_le_bytes());
+        // empty BB_ERROR_BLOB
+        out.extend_from_slice(&[0x04, 0x00, 0x00, 0x00]);
+    });
+}
+
+fn write_capability(ty: u16, out: &mut Vec<u8>, f: impl FnOnce(&mut Vec<u8>)) {
+    out.extend_from_slice(&ty.to_le_bytes());
+    write_len_prefixed(out, 2, f, |len, buf| {
+        buf.copy_from_slice(&(len as u16 + 4).to_le_bytes())
+    });
+}
+
+/// Writes the Demand Active PDU announcing the capabilities of the server
+pub(super) fn write_demand_active(user: u16, size: Size<i32, Physical>, out: &mut Vec<u8>) {
+    let mut caps = Vec::new();
+    // general: fast-path output, long credentials, no bitmap compression header,
+    // refresh rect and suppress output
+    write_capability(0x0001, &mut caps, |out| {
+        for value in [1u16, 3, 0x0200, 0, 0, 0x0405, 0, 0, 0] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&[1, 1]);
+    });
+    // bitmap: 32 bits per pixel, desktop resizing and multiple rectangles
+    write_capability(0x0002, &mut caps, |out| {
+        for value in [32u16, 1, 1, 1, size.w as u16, size.h as u16, 0, 1, 1] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+    });
+    // order: no drawing orders
+    write_capability(0x0003, &mut caps, |out| {
+        out.extend_from_slice(&[0; 20]);
+        for value in [1u16, 20, 0, 1, 0, 0x000a] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend_from_slice(&[0; 52]);
+    });
+    // pointer
+    write_capability(0x0008, &mut caps, |out| {
+        for value in [1u16, 25, 25] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    });
+    // input: scancodes, unicode, extended mouse buttons, horizontal wheel and fast-path input
+    write_capability(0x000d, &mut caps, |out| {
+        out.extend_from_slice(&0x013du16.to_le_bytes());
+        out.extend_from_slice(&[0; 82]);
+    });
+    // share
+    write_capability(0x0009, &mut caps, |out| {
+        out.extend_from_slice(&SERVER_CHANNEL.to_le_bytes());
+        out.extend_from_slice(&[0; 2]);
+    });
+    // font
+    write_capability(0x000e, &mut caps, |out| {
+        out.extend_from_slice(&[1, 0, 0, 0]);
+    });
+    // virtual channel: no compression
+    write_capability(0x0014, &mut caps, |out| {
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(CHANNEL_CHUNK_LEN as u32).to_le_bytes());
+    });
+
+    write_share_control(PDUTYPE_DEMANDACTIVEPDU, user, out, |out| {
+        out.extend_from_slice(&SHARE_ID.to_le_bytes());
+        out.extend_from_slice(&4u16.to_le_bytes());
+        out.extend_from_slice(&(caps.len() as u16 + 4).to_le_bytes());
+        out.extend_from_slice(b"RDP\0");
+        out.extend_from_slice(&8u16.to_le_bytes());
+        out.extend_from_slice(&[0; 2]);
+        out.extend_from_slice(&caps);
+        // session id
+        out.extend_from_slice(&0u32.to_le_bytes());
+    });
+}
+
+/// Writes the Deactivate All PDU, which restarts the capability exchange
+pub(super) fn write_deactivate_all(user: u16, out: &mut Vec<u8>) {
+    write_share_control(PDUTYPE_DEACTIVATEALLPDU, user, out, |out| {
+        out.extend_from_slice(&SHARE_ID.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.push(0);
+    });
+}
+
+/// Writes the Monitor Layout PDU, the first monitor being the primary one
+pub(super) fn write_monitor_layout(user: u16, monitors: &[Rectangle<i32, Physical>], out: &mut Vec<u8>) {
+    write_share_data(PDUTYPE2_MONITOR_LAYOUT_PDU, user, out, |out| {
+        out.extend_from_slice(&(monitors.len() as u32).to_le_bytes());
+        for (idx, monitor) in monitors.iter().enumerate() {
+            let (right, bottom) = (
+                monitor.loc.x + monitor.size.w - 1,
+                monitor.loc.y + monitor.size.h - 1,
+            );
+            for value in [monitor.loc.x, monitor.loc.y, right, bottom] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            // TS_MONITOR_PRIMARY
+            out.extend_from_slice(&((idx == 0) as u32).to_le_bytes());
+        }
+    });
+}
+
+/// Writes the server Synchronize PDU
+pub(super) fn write_synchronize(user: u16, out: &mut Vec<u8>) {
+    write_share_data(PDUTYPE2_SYNCHRONIZE, user, out, |out| {
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&SERVER_CHANNEL.to_le_bytes());
+    });
+}
+
+/// Writes the server Control PDU responding to the given action of the client
+pub(super) fn write_control(user: u16, action: u16, out: &mut Vec<u8>) {
+    let (action, grant_id, control_id) = if action == CTRLACTION_REQUEST_CONTROL {
+        (CTRLACTION_GRANTED_CONTROL, user, SERVER_CHANNEL as u32)
+    } else {
+        (CTRLACTION_COOPERATE, 0, 0)
+    };
+    write_share_data(PDUTYPE2_CONTROL, user, out, |out| {
+        out.extend_from_slice(&action.to_le_bytes());
+        out.extend_from_slice(&grant_id.to_le_bytes());
+        out.extend_from_slice(&control_id.to_le_bytes());
+    });
+}
+
+/// Writes the Font Map PDU, which finishes the connection sequence
+pub(super) fn write_font_map(user: u16, out: &mut Vec<u8>) {
+    write_share_data(PDUTYPE2_FONTMAP, user, out, |out| {
+        for value in [0u16, 0, 0x0003, 0x0004] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    });
+}
+
+/// Writes a bitmap update of `rect` of the `Xrgb8888` framebuffer
+///
+/// The rectangle has to fit into a tile, see [`TILE_SIZE`].
+pub(super) fn write_bitmap_update(
+    framebuffer: &[u8],
+    stride: usize,
+    rect: Rectangle<i32, Physical>,
+    fast_path: bool,
+    user: u16,
+    out: &mut Vec<u8>,
+) {
+    debug_assert!(rect.size.w <= TILE_SIZE && rect.size.h <= TILE_SIZE);
+    let write_data = |out: &mut Vec<u8>| {
+        let (x, y) = (rect.loc.x as usize, rect.loc.y as usize);
+        let (w, h) = (rect.size.w as usize, rect.size.h as usize);
+        // UPDATETYPE_BITMAP with a single rectangle
+        out.extend_from_slice(&[0x01, 0x00, 0x01, 0x00]);
+        for value in [x, y, x + w - 1, y + h - 1, w, h, 32, 0, w * h * 4] {
+            out.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        // uncompressed bitmaps are stored bottom-up, the padding byte is read as alpha by some clients
+        for row in (y..y + h).rev() {
+            let offset = row * stride + x * 4;
+            for pixel in framebuffer[offset..offset + w * 4].chunks_exact(4) {
+                out.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 0xff]);
+            }
+        }
+    };
+
+    if fast_path {
+        // FASTPATH_OUTPUT_ACTION_FASTPATH, FASTPATH_UPDATETYPE_BITMAP
+        out.push(0x00);
+        write_len_prefixed(
+            out,
+            2,
+            |out| {
+                out.push(0x01);
+                write_len_prefixed(out, 2, write_data, |len, buf| {
+                    buf.copy_from_slice(&(len as u16).to_le_bytes())
+                });
+            },
+            |len, buf| buf.copy_from_slice(&((len + 3) as u16 | 0x8000).to_be_bytes()),
+        );
+    } else {
+        write_share_data(PDUTYPE2_UPDATE, user, out, write_data);
+    }
+}
+
+/// Writes a message to a static virtual channel, split into chunks
+pub(super) fn write_channel_data(user: u16, channel: u16, data: &[u8], out: &mut Vec<u8>) {
+    let chunks = data.chunks(CHANNEL_CHUNK_LEN).count();
+    for (idx, chunk) in data.chunks(CHANNEL_CHUNK_LEN).enumerate() {
+        let mut flags = 0;
+        if idx == 0 {
+            flags |= CHANNEL_FLAG_FIRST;
+        }
+        if idx + 1 == chunks {
+            flags |= CHANNEL_FLAG_LAST;
+        }
+        write_send_data(user, channel, out, |out| {
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&flags.to_le_bytes());
+            out.extend_from_slice(chunk);
+        });
+    }
+}
+
+fn clipboard_pdu(ty: u16, flags: u16, f: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&ty.to_le_bytes());
+    out.extend_from_slice(&flags.to_le_bytes());
+    write_len_prefixed(&mut out, 4, f, |len, buf| {
+        buf.copy_from_slice(&(len as u32).to_le_bytes())
+    });
+    out
+}
+
+/// Clipboard Capabilities and Monitor Ready PDUs starting the clipboard channel
+pub(super) fn clipboard_init() -> [Vec<u8>; 2] {
+    let caps = clipboard_pdu(CB_CLIP_CAPS, 0, |out| {
+        // one general capability set of version 2 without long format names
+        out.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0c, 0x00]);
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+    });
+    [caps, clipboard_pdu(CB_MONITOR_READY, 0, |_| {})]
+}
+
+/// Format List PDU with short format names
+pub(super) fn clipboard_format_list<'a>(formats: impl IntoIterator<Item = (u32, &'a str)>) -> Vec<u8> {
+    clipboard_pdu(CB_FORMAT_LIST, 0, |out| {
+        for (id, name) in formats {
+            out.extend_from_slice(&id.to_le_bytes());
+            let mut name = name
+                .encode_utf16()
+                .take(15)
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>();
+            name.resize(32, 0);
+            out.extend_from_slice(&name);
+        }
+    })
+}
+
+/// Format List Response PDU accepting the formats of the client
+pub(super) fn clipboard_format_list_response() -> Vec<u8> {
+    clipboard_pdu(CB_FORMAT_LIST_RESPONSE, CB_RESPONSE_OK, |_| {})
+}
+
+/// Format Data Request PDU
+pub(super) fn clipboard_data_request(format: u32) -> Vec<u8> {
+    clipboard_pdu(CB_FORMAT_DATA_REQUEST, 0, |out| {
+        out.extend_from_slice(&format.to_le_bytes())
+    })
+}
+
+/// Format Data Response PDU, failing without data
+pub(super) fn clipboard_data_response(data: Option<&[u8]>) -> Vec<u8> {
+    let flags = if data.is_some() {
+        CB_RESPONSE_OK
+    } else {
+        CB_RESPONSE_FAIL
+    };
+    clipboard_pdu(CB_FORMAT_DATA_RESPONSE, flags, |out| {
+        out.extend_from_slice(data.unwrap_or_default())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clipboard_data_response, clipboard_format_list, write_bitmap_update, ChannelData, ClientData,
+        ClientPdu, ClipboardPdu, InputMessage, InvalidPdu,
+    };
+    use crate::utils::Rectangle;
+
+    fn tpkt(x224: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0x03, 0x00];
+        buf.extend_from_slice(&(x224.len() as u16 + 4).to_be_bytes());
+        buf.extend_from_slice(x224);
+        buf
+    }
+
+    #[test]
+    fn parse_connection_sequence() {
+        let mut x224 = vec![0x00, 0xe0, 0, 0, 0, 0, 0];
+        x224.extend_from_slice(b"Cookie: mstshash=user\r\n");
+        x224.extend_from_slice(&[0x01, 0x00, 0x08, 0x00, 0x03, 0x00, 0x00, 0x00]);
+        x224[0] = x224.len() as u8 - 1;
+        let buf = tpkt(&x224);
+        assert_eq!(
+            ClientPdu::parse(&buf),
+            Ok(Some((
+                ClientPdu::ConnectionRequest {
+                    requested_protocols: Some(3)
+                },
+                buf.len()
+            )))
+        );
+        assert_eq!(ClientPdu::parse(&buf[..buf.len() - 1]), Ok(None));
+
+        // CS_CORE with the desktop size and CS_NET requesting the clipboard channel
+        let mut blocks = vec![0x01, 0xc0, 12, 0, 0x04, 0x00, 0x08, 0x00, 0x00, 0x05, 0x20, 0x03];
+        blocks.extend_from_slice(&[0x03, 0xc0, 20, 0, 1, 0, 0, 0]);
+        blocks.extend_from_slice(b"cliprdr\0");
+        blocks.extend_from_slice(&[0, 0, 0, 0]);
+        let mut user_data =
+            b"\x00\x05\x00\x14\x7c\x00\x01\x81\x2a\x00\x08\x00\x10\x00\x01\xc0\x00Duca".to_vec();
+        user_data.extend_from_slice(&[0x80, blocks.len() as u8]);
+        user_data.extend_from_slice(&blocks);
+        let mut connect_initial = vec![0x04, 0x01, 0x01, 0x04, 0x01, 0x01, 0x01, 0x01, 0xff];
+        connect_initial.extend_from_slice(&[0x04, 0x81, user_data.len() as u8]);
+        connect_initial.extend_from_slice(&user_data);
+        let mut x224 = vec![0x02, 0xf0, 0x80, 0x7f, 0x65, 0x81, connect_initial.len() as u8];
+        x224.extend_from_slice(&connect_initial);
+        assert_eq!(
+            ClientPdu::parse(&tpkt(&x224)).unwrap().unwrap().0,
+            ClientPdu::ConnectInitial(ClientData {
+                desktop_size: (1280, 800).into(),
+                monitor_layout: false,
+                channels: vec![String::from("cliprdr")],
+            })
+        );
+
+        // Send Data Request of the user 1007 on the I/O channel
+        let buf = tpkt(&[
+            0x02, 0xf0, 0x80, 0x64, 0x00, 0x06, 0x03, 0xeb, 0x70, 0x02, 0xaa, 0xbb,
+        ]);
+        assert_eq!(
+            ClientPdu::parse(&buf).unwrap().unwrap().0,
+            ClientPdu::Data {
+                channel: 1003,
+                data: vec![0xaa, 0xbb],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_fast_path_input() {
+        // an extended key press and a left button press
+        let buf = [
+            0x08, 11, 0x02, 0x48, 0x20, 0x00, 0x90, 10, 0, 20, 0, // followed by an incomplete PDU
+            0x04, 3,
+        ];
+        assert_eq!(
+            ClientPdu::parse(&buf),
+            Ok(Some((
+                ClientPdu::FastPathInput(vec![
+                    InputMessage::Scancode {
+                        code: 0x48,
+                        extended: true,
+                        extended1: false,
+                        release: false,
+                    },
+                    InputMessage::Mouse {
+                        flags: 0x9000,
+                        x: 10,
+                        y: 20,
+                    },
+                ]),
+                11
+            )))
+        );
+        assert_eq!(ClientPdu::parse(&buf[11..]), Ok(None));
+        assert_eq!(
+            ClientPdu::parse(&[0x84, 3, 0]),
+            Err(InvalidPdu("encrypted fast-path input"))
+        );
+    }
+
+    #[test]
+    fn exchange_clipboard_messages() {
+        let mut channel = ChannelData::default();
+        assert_eq!(
+            channel.push(&[6, 0, 0, 0, 1, 0, 0, 0, b'a', b'b', b'c'], 16),
+            Ok(None)
+        );
+        assert_eq!(
+            channel.push(&[6, 0, 0, 0, 2, 0, 0, 0, b'd', b'e', b'f'], 16),
+            Ok(Some(b"abcdef".to_vec()))
+        );
+        assert!(channel.push(&[32, 0, 0, 0, 3, 0, 0, 0], 16).is_err());
+
+        let formats = clipboard_format_list([(13, ""), (0xc004, "HTML Format")]);
+        assert_eq!(
+            ClipboardPdu::parse(&formats),
+            Ok(ClipboardPdu::FormatList(vec![
+                (13, String::new()),
+                (0xc004, String::from("HTML Format"))
+            ]))
+        );
+        assert_eq!(
+            ClipboardPdu::parse(&clipboard_data_response(None)),
+            Ok(ClipboardPdu::DataResponse(None))
+        );
+    }
+
+    #[test]
+    fn encode_bitmap_update() {
+        let framebuffer = [1, 2, 3, 0, 4, 5, 6, 0, 7, 8, 9, 0, 10, 11, 12, 0];
+        let mut out = Vec::new();
+        write_bitmap_update(
+            &framebuffer,
+            8,
+            Rectangle::from_loc_and_size((0, 0), (2, 2)),
+            true,
+            1007,
+            &mut out,
+        );
+        assert_eq!(&out[..6], &[0x00, 0x80, 44, 0x01, 38, 0]);
+        assert_eq!(out.len(), 44);
+        // the rows are stored bottom-up
+        assert_eq!(
+            &out[28..],
+            &[7, 8, 9, 0xff, 10, 11, 12, 0xff, 1, 2, 3, 0xff, 4, 5, 6, 0xff]
+        );
+    }
+}
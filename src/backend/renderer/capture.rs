@@ -0,0 +1,428 @@
+//! Helper for rendering output captures into client provided buffers
+//!
+//! Protocols like `wlr-screencopy` or `ext-image-copy-capture` ask the compositor to copy the content
+//! of an output, or a region of it, into a buffer provided by the client. [`OutputCapture`] implements
+//! the rendering side of these requests independent of the protocol in use: it renders the captured
+//! region into a shm or dmabuf [`WlBuffer`] applying the transform and scale of the output and reports
+//! the damage since the last capture into the same buffer.
+//!
+//! Dmabufs are rendered into directly. Shm buffers are rendered into an offscreen texture first and only
+//! the damaged parts are copied into the shm pool afterwards.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     capture::OutputCapture,
+//! #     element::solid::SolidColorRenderElement,
+//! #     gles::{GlesRenderer, GlesTexture},
+//! #     Color32F,
+//! # };
+//! # use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
+//! # use smithay::utils::{Rectangle, Transform};
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let buffer: WlBuffer = todo!();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! let mut capture = OutputCapture::<GlesTexture>::new(
+//!     Rectangle::from_loc_and_size((0, 0), (1920, 1080)),
+//!     1.0,
+//!     Transform::Normal,
+//! );
+//!
+//! // render the output into the buffer of the capture request
+//! let result = capture
+//!     .capture(&mut renderer, &buffer, &elements, Color32F::BLACK)
+//!     .expect("Failed to capture output");
+//! // `result.damage` can now be sent to the client, once `result.sync` has been reached
+//! ```
+
+use crate::{
+    backend::allocator::{dmabuf::Dmabuf, format::get_bpp, Buffer as _, Fourcc},
+    reexports::wayland_server::{
+        protocol::{wl_buffer::WlBuffer, wl_shm},
+        Resource, Weak,
+    },
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
+    wayland::{
+        dmabuf::get_dmabuf,
+        shm::{self, BufferAccessError},
+    },
+};
+
+use super::{
+    buffer_type,
+    damage::{Error as DamageError, OutputDamageTracker},
+    element::{
+        utils::{Relocate, RelocateRenderElement},
+        RenderElement,
+    },
+    stream::damage_to_buffer,
+    sync::SyncPoint,
+    utils::{CommitCounter, DamageBag},
+    Bind, BufferType, Color32F, ExportMem, Offscreen, Renderer, Texture, TextureMapping,
+};
+
+// Number of captures to keep the damage of for calculating the damage of older buffers
+const MAX_CAPTURE_AGE: usize = 4;
+
+/// Errors returned by [`OutputCapture::capture`]
+#[derive(thiserror::Error)]
+pub enum CaptureError<R: Renderer> {
+    /// The buffer is neither a shm buffer nor a dmabuf
+    #[error("The buffer is not supported for captures")]
+    UnsupportedBuffer,
+    /// The shm buffer uses a format that is not supported
+    #[error("The shm format {0:?} is not supported for captures")]
+    UnsupportedShmFormat(wl_shm::Format),
+    /// The buffer does not match the size of the captured region
+    #[error("The buffer has size {actual:?}, but the capture requires {expected:?}")]
+    InvalidBufferSize {
+        /// Size required for the capture
+        expected: Size<i32, Buffer>,
+        /// Actual size of the buffer
+        actual: Size<i32, Buffer>,
+    },
+    /// Accessing the shm buffer failed
+    #[error(transparent)]
+    BufferAccess(#[from] BufferAccessError),
+    /// Rendering the captured region failed
+    #[error(transparent)]
+    Rendering(DamageError<R>),
+    /// Waiting for the rendering to finish was interrupted
+    #[error("Waiting for the rendering to finish was interrupted")]
+    SyncInterrupted,
+}
+
+impl<R: Renderer> std::fmt::Debug for CaptureError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::UnsupportedBuffer => write!(f, "UnsupportedBuffer"),
+            CaptureError::UnsupportedShmFormat(format) => {
+                f.debug_tuple("UnsupportedShmFormat").field(format).finish()
+            }
+            CaptureError::InvalidBufferSize { expected, actual } => f
+                .debug_struct("InvalidBufferSize")
+                .field("expected", expected)
+                .field("actual", actual)
+                .finish(),
+            CaptureError::BufferAccess(err) => f.debug_tuple("BufferAccess").field(err).finish(),
+            CaptureError::Rendering(err) => f.debug_tuple("Rendering").field(err).finish(),
+            CaptureError::SyncInterrupted => write!(f, "SyncInterrupted"),
+        }
+    }
+}
+
+impl<R: Renderer> From<DamageError<R>> for CaptureError<R> {
+    #[inline]
+    fn from(err: DamageError<R>) -> Self {
+        CaptureError::Rendering(err)
+    }
+}
+
+/// Result of a successful capture
+#[derive(Debug)]
+pub struct CaptureResult {
+    /// Damage of the buffer since it was last used for a capture, in buffer coordinates
+    pub damage: Vec<Rectangle<i32, Buffer>>,
+    /// Sync point that is reached once the content of the buffer is ready
+    pub sync: SyncPoint,
+}
+
+#[derive(Debug)]
+struct CaptureOffscreen<T> {
+    texture: T,
+    format: Fourcc,
+    commit: Option<CommitCounter>,
+}
+
+/// State for capturing a region of an output into client buffers
+///
+/// A single [`OutputCapture`] should be used for all captures of the same region, usually
+/// per capture session, to only redraw and report the parts of the buffers that changed.
+///
+/// `T` is the offscreen texture type of the renderer used for shm buffers.
+#[derive(Debug)]
+pub struct OutputCapture<T> {
+    region: Rectangle<i32, Physical>,
+    scale: Scale<f64>,
+    transform: Transform,
+    damage_tracker: OutputDamageTracker,
+    damage: DamageBag<i32, Buffer>,
+    buffers: Vec<(Weak<WlBuffer>, CommitCounter)>,
+    offscreen: Option<CaptureOffscreen<T>>,
+}
+
+impl<T: Texture + Clone> OutputCapture<T> {
+    /// Create a new capture of `region`
+    ///
+    /// `region` is given in the physical coordinate space of the output, as used by the
+    /// render elements passed to [`OutputCapture::capture`], `scale` and `transform` are
+    /// the ones of the captured output.
+    pub fn new(
+        region: impl Into<Rectangle<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+    ) -> Self {
+        let region = region.into();
+        let scale = scale.into();
+        OutputCapture {
+            region,
+            scale,
+            transform,
+            damage_tracker: OutputDamageTracker::new(transform.transform_size(region.size), scale, transform),
+            damage: DamageBag::new(MAX_CAPTURE_AGE),
+            buffers: Vec::new(),
+            offscreen: None,
+        }
+    }
+
+    /// The captured region
+    pub fn region(&self) -> Rectangle<i32, Physical> {
+        self.region
+    }
+
+    /// Change the captured region, scale or transform
+    ///
+    /// The next capture into any buffer will be fully redrawn, if anything changed.
+    pub fn update(
+        &mut self,
+        region: impl Into<Rectangle<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+    ) {
+        let region = region.into();
+        let scale = scale.into();
+        if self.region != region || self.scale != scale || self.transform != transform {
+            let offscreen = self.offscreen.take();
+            *self = OutputCapture::new(region, scale, transform);
+            // the texture might still be usable if only the scale changed
+            self.offscreen = offscreen.map(|offscreen| CaptureOffscreen {
+                commit: None,
+                ..offscreen
+            });
+        }
+    }
+
+    /// Size of the buffers required for this capture
+    pub fn buffer_size(&self) -> Size<i32, Buffer> {
+        let size = self.transform.transform_size(self.region.size);
+        (size.w, size.h).into()
+    }
+
+    /// Render the captured region into `buffer`
+    ///
+    /// `elements` are positioned in the coordinate space of the output and are moved
+    /// according to the captured region.
+    #[profiling::function]
+    pub fn capture<R, E>(
+        &mut self,
+        renderer: &mut R,
+        buffer: &WlBuffer,
+        elements: &[E],
+        clear_color: impl Into<Color32F>,
+    ) -> Result<CaptureResult, CaptureError<R>>
+    where
+        R: Renderer + Bind<Dmabuf> + Bind<T> + Offscreen<T> + ExportMem,
+        E: RenderElement<R>,
+    {
+        self.buffers.retain(|(weak, _)| weak.is_alive());
+        let last_commit = self
+            .buffers
+            .iter()
+            .find(|(weak, _)| weak == buffer)
+            .map(|(_, commit)| *commit);
+
+        let expected = self.buffer_size();
+        let offset = Point::from((-self.region.loc.x, -self.region.loc.y));
+        let elements = elements
+            .iter()
+            .map(|element| RelocateRenderElement::from_element(element, offset, Relocate::Relative))
+            .collect::<Vec<_>>();
+        let clear_color = clear_color.into();
+
+        let sync = match buffer_type(buffer) {
+            Some(BufferType::Dma) => {
+                let dmabuf = get_dmabuf(buffer)
+                    .map_err(|_| CaptureError::UnsupportedBuffer)?
+                    .clone();
+                check_size(expected, dmabuf.size())?;
+
+                let age = buffer_age(self.damage.current_commit(), last_commit);
+                let result =
+                    self.damage_tracker
+                        .render_output_with(renderer, dmabuf, age, &elements, clear_color)?;
+                if let Some(damage) = result.damage {
+                    self.damage
+                        .add(damage_to_buffer(damage, self.region.size, self.transform));
+                }
+                result.sync
+            }
+            Some(BufferType::Shm) => {
+                let data = shm::with_buffer_contents(buffer, |_, _, data| data)?;
+                let format = shm::shm_format_to_fourcc(data.format)
+                    .filter(|format| get_bpp(*format).is_some())
+                    .ok_or(CaptureError::UnsupportedShmFormat(data.format))?;
+                check_size(expected, (data.width, data.height).into())?;
+
+                let sync = self.render_offscreen(renderer, format, &elements, clear_color)?;
+                self.copy_to_shm(renderer, buffer, last_commit, &sync)?;
+                sync
+            }
+            _ => return Err(CaptureError::UnsupportedBuffer),
+        };
+
+        let commit = self.damage.current_commit();
+        match self.buffers.iter_mut().find(|(weak, _)| weak == buffer) {
+            Some((_, last)) => *last = commit,
+            None => self.buffers.push((buffer.downgrade(), commit)),
+        }
+
+        let damage = self
+            .damage
+            .damage_since(last_commit)
+            .map(|damage| damage.to_vec())
+            .unwrap_or_else(|| vec![Rectangle::from_loc_and_size((0, 0), expected)]);
+        Ok(CaptureResult { damage, sync })
+    }
+
+    fn render_offscreen<R, E>(
+        &mut self,
+        renderer: &mut R,
+        format: Fourcc,
+        elements: &[E],
+        clear_color: Color32F,
+    ) -> Result<SyncPoint, CaptureError<R>>
+    where
+        R: Renderer + Bind<T> + Offscreen<T>,
+        E: RenderElement<R>,
+    {
+        let size = self.buffer_size();
+        let offscreen = match self.offscreen.take() {
+            Some(offscreen) if offscreen.format == format && offscreen.texture.size() == size => offscreen,
+            _ => CaptureOffscreen {
+                texture: renderer
+                    .create_buffer(format, size)
+                    .map_err(DamageError::Rendering)?,
+                format,
+                commit: None,
+            },
+        };
+        let offscreen = self.offscreen.insert(offscreen);
+
+        let age = buffer_age(self.damage.current_commit(), offscreen.commit);
+        let result = self.damage_tracker.render_output_with(
+            renderer,
+            offscreen.texture.clone(),
+            age,
+            elements,
+            clear_color,
+        )?;
+        if let Some(damage) = result.damage {
+            self.damage
+                .add(damage_to_buffer(damage, self.region.size, self.transform));
+        }
+        offscreen.commit = Some(self.damage.current_commit());
+        Ok(result.sync)
+    }
+
+    // Copies everything that changed since the buffer was last captured from the offscreen texture
+    fn copy_to_shm<R>(
+        &mut self,
+        renderer: &mut R,
+        buffer: &WlBuffer,
+        last_commit: Option<CommitCounter>,
+        sync: &SyncPoint,
+    ) -> Result<(), CaptureError<R>>
+    where
+        R: Renderer + Bind<T> + ExportMem,
+    {
+        let size = self.buffer_size();
+        let full = Rectangle::from_loc_and_size((0, 0), size);
+        let region = match self.damage.damage_since(last_commit) {
+            Some(damage) => damage.iter().copied().reduce(|a, b| a.merge(b)),
+            None => Some(full),
+        };
+        let Some(region) = region.and_then(|region| region.intersection(full)) else {
+            return Ok(());
+        };
+
+        let Some(offscreen) = self.offscreen.as_ref() else {
+            return Ok(());
+        };
+        sync.wait().map_err(|_| CaptureError::SyncInterrupted)?;
+        renderer
+            .bind(offscreen.texture.clone())
+            .map_err(DamageError::Rendering)?;
+        let mapping = renderer
+            .copy_framebuffer(region, offscreen.format)
+            .map_err(DamageError::Rendering)?;
+        let flipped = mapping.flipped();
+        let pixels = renderer.map_texture(&mapping).map_err(DamageError::Rendering)?;
+
+        let bpp = get_bpp(offscreen.format).unwrap() / 8;
+        let row_len = region.size.w as usize * bpp;
+        shm::with_buffer_contents_mut(buffer, |ptr, len, data| {
+            let stride = data.stride as usize;
+            for row in 0..region.size.h as usize {
+                let src_row = if flipped {
+                    region.size.h as usize - row - 1
+                } else {
+                    row
+                };
+                let Some(src) = pixels.get(src_row * row_len..(src_row + 1) * row_len) else {
+                    break;
+                };
+                let offset = data.offset as usize
+                    + (region.loc.y as usize + row) * stride
+                    + region.loc.x as usize * bpp;
+                if offset + row_len > len {
+                    break;
+                }
+                // SAFETY: the range was checked against the length of the pool above
+                unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), ptr.add(offset), row_len) };
+            }
+        })?;
+        Ok(())
+    }
+}
+
+fn check_size<R: Renderer>(
+    expected: Size<i32, Buffer>,
+    actual: Size<i32, Buffer>,
+) -> Result<(), CaptureError<R>> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(CaptureError::InvalidBufferSize { expected, actual })
+    }
+}
+
+// Captures only increment the commit if something was damaged, which matches the damage tracker
+// only remembering renders that changed anything.
+fn buffer_age(current: CommitCounter, last: Option<CommitCounter>) -> usize {
+    current.distance(last).map(|distance| distance + 1).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buffer_age, damage_to_buffer};
+    use crate::{
+        backend::renderer::utils::CommitCounter,
+        utils::{Physical, Rectangle, Size, Transform},
+    };
+
+    #[test]
+    fn capture_damage_in_buffer_space() {
+        let region_size = Size::<i32, Physical>::from((100, 50));
+        let damage = [Rectangle::<i32, Physical>::from_loc_and_size((0, 0), (10, 5))];
+
+        let normal = damage_to_buffer(&damage, region_size, Transform::Normal).collect::<Vec<_>>();
+        assert_eq!(normal, vec![Rectangle::from_loc_and_size((0, 0), (10, 5))]);
+
+        let rotated = damage_to_buffer(&damage, region_size, Transform::_90).collect::<Vec<_>>();
+        assert_eq!(rotated[0].size, (5, 10).into());
+
+        let commit = CommitCounter::from(3);
+        assert_eq!(buffer_age(commit, None), 0);
+        assert_eq!(buffer_age(commit, Some(commit)), 1);
+        assert_eq!(buffer_age(commit, Some(CommitCounter::from(1))), 3);
+    }
+}
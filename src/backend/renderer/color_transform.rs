@@ -0,0 +1,714 @@
+//! Color transformations applied to the composited output
+//!
+//! A [`ColorTransform`] describes how the (sRGB encoded) output of the composition is mapped onto
+//! the color space of a display. It consists of a decoding transfer function, a 3x3 matrix operating on
+//! linear light, an encoding transfer function and an optional [`Lut3d`] applied on the result.
+//!
+//! Transforms can be built from colorimetry parameters with [`ColorTransform::from_colorimetry`] or from
+//! matrix/TRC based ICC display profiles with [`ColorTransform::from_icc`]. Renderers usually do not
+//! evaluate the individual stages, but sample a [`Lut3d`] baked with [`ColorTransform::bake`] in a final
+//! composition pass, e.g. using the
+//! [`ColorTransformElement`](crate::backend::renderer::gles::color_transform::ColorTransformElement) of the
+//! [`GlesRenderer`](crate::backend::renderer::gles::GlesRenderer).
+//!
+//! [`ColorFilter`]s like a color temperature adjustment (night light), grayscale or inversion are applied to
+//! the composited content before the transform with [`ColorTransform::with_filters`]. The
+//! [`PixmanRenderer`](crate::backend::renderer::pixman::PixmanRenderer) applies the resulting transform to
+//! every frame set with
+//! [`PixmanRenderer::set_color_transform`](crate::backend::renderer::pixman::PixmanRenderer::set_color_transform),
+//! other software renderers can use [`apply_color_transform`](super::utils::convert::apply_color_transform).
+//! Filters only adjusting every channel on its own can also be applied through the gamma lookup tables of a
+//! display using [`ColorFilter::gamma_ramps`], avoiding the additional composition pass.
+
+use cgmath::{Matrix3, SquareMatrix, Vector3};
+
+/// Default amount of grid points per dimension of a baked [`Lut3d`]
+pub const DEFAULT_LUT_SIZE: u32 = 33;
+
+/// Error returned when parsing an ICC profile
+#[derive(Debug, thiserror::Error)]
+pub enum IccError {
+    /// The data is not a valid ICC profile
+    #[error("The data is not a valid ICC profile")]
+    Invalid,
+    /// The profile is missing a required tag
+    #[error("The profile is missing the required tag {0:?}")]
+    MissingTag(&'static str),
+    /// The profile uses an unsupported tag type
+    #[error("Unsupported tag type {0:?}")]
+    UnsupportedTagType(String),
+}
+
+/// Transfer function mapping encoded values to linear light
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferFunction {
+    /// The values are already linear
+    Linear,
+    /// The piece-wise sRGB transfer function
+    Srgb,
+    /// A pure power function
+    Gamma(f32),
+    /// ICC parametric curve `Y = (a * X + b)^g + e` for `X >= d`, `Y = c * X + f` otherwise,
+    /// with the parameters ordered as `[g, a, b, c, d, e, f]`
+    Parametric([f32; 7]),
+    /// Evenly spaced samples of the function over `[0, 1]`, interpolated linearly
+    Table(Vec<f32>),
+}
+
+impl TransferFunction {
+    /// Convert an encoded value to linear light
+    pub fn eval(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Srgb => {
+                if x <= 0.04045 {
+                    x / 12.92
+                } else {
+                    ((x + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma(gamma) => x.powf(*gamma),
+            TransferFunction::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= *d {
+                    (a * x + b).max(0.0).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            TransferFunction::Table(table) => match table.len() {
+                0 => x,
+                1 => table[0],
+                len => {
+                    let pos = x * (len - 1) as f32;
+                    let idx = (pos.floor() as usize).min(len - 2);
+                    let frac = pos - idx as f32;
+                    table[idx] * (1.0 - frac) + table[idx + 1] * frac
+                }
+            },
+        }
+    }
+
+    /// Convert a linear light value back to its encoded form
+    pub fn eval_inverse(&self, y: f32) -> f32 {
+        let y = y.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Linear => y,
+            TransferFunction::Srgb => {
+                if y <= 0.0031308 {
+                    y * 12.92
+                } else {
+                    1.055 * y.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => y.powf(1.0 / gamma),
+            // the remaining functions are monotonic on [0, 1], so bisect
+            _ => {
+                let increasing = self.eval(1.0) >= self.eval(0.0);
+                let (mut low, mut high) = (0.0f32, 1.0f32);
+                for _ in 0..24 {
+                    let mid = (low + high) / 2.0;
+                    if (self.eval(mid) < y) == increasing {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                (low + high) / 2.0
+            }
+        }
+    }
+}
+
+/// Chromaticity coordinates of the primaries and white point of a color space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Primaries {
+    /// Red primary
+    pub red: (f64, f64),
+    /// Green primary
+    pub green: (f64, f64),
+    /// Blue primary
+    pub blue: (f64, f64),
+    /// White point
+    pub white: (f64, f64),
+}
+
+const D50: (f64, f64) = (0.3457, 0.3585);
+const D65: (f64, f64) = (0.3127, 0.3290);
+
+impl Primaries {
+    /// Primaries of sRGB and BT.709
+    pub const SRGB: Primaries = Primaries {
+        red: (0.640, 0.330),
+        green: (0.300, 0.600),
+        blue: (0.150, 0.060),
+        white: D65,
+    };
+
+    /// Primaries of Display P3
+    pub const DISPLAY_P3: Primaries = Primaries {
+        red: (0.680, 0.320),
+        green: (0.265, 0.690),
+        blue: (0.150, 0.060),
+        white: D65,
+    };
+
+    /// Primaries of BT.2020
+    pub const BT2020: Primaries = Primaries {
+        red: (0.708, 0.292),
+        green: (0.170, 0.797),
+        blue: (0.131, 0.046),
+        white: D65,
+    };
+
+    // Matrix converting linear rgb to XYZ relative to the white point of the primaries
+    fn to_xyz(self) -> Matrix3<f64> {
+        let primaries = Matrix3::from_cols(xy_to_xyz(self.red), xy_to_xyz(self.green), xy_to_xyz(self.blue));
+        let scale = primaries.invert().unwrap_or_else(Matrix3::identity) * xy_to_xyz(self.white);
+        Matrix3::from_cols(
+            primaries.x * scale.x,
+            primaries.y * scale.y,
+            primaries.z * scale.z,
+        )
+    }
+}
+
+fn xy_to_xyz((x, y): (f64, f64)) -> Vector3<f64> {
+    Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+// Bradford chromatic adaptation between two white points
+fn adaptation(from: (f64, f64), to: (f64, f64)) -> Matrix3<f64> {
+    #[rustfmt::skip]
+    let bradford = Matrix3::new(
+        0.8951, -0.7502, 0.0389,
+        0.2664, 1.7135, -0.0685,
+        -0.1614, 0.0367, 1.0296,
+    );
+    let bradford_inv = bradford.invert().unwrap();
+    let from = bradford * xy_to_xyz(from);
+    let to = bradford * xy_to_xyz(to);
+    let scale = Matrix3::from_diagonal(Vector3::new(to.x / from.x, to.y / from.y, to.z / from.z));
+    bradford_inv * scale * bradford
+}
+
+/// A three dimensional lookup table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    size: u32,
+    data: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Create a lookup table from `size`³ rgb values
+    ///
+    /// The entries are ordered with the red index changing fastest, followed by green and blue.
+    /// Returns `None` if the amount of entries does not match `size` or `size` is smaller than 2.
+    pub fn new(size: u32, data: Vec<[f32; 3]>) -> Option<Self> {
+        if size < 2 || data.len() != (size as usize).pow(3) {
+            return None;
+        }
+        Some(Lut3d { size, data })
+    }
+
+    /// Create a lookup table not altering any colors
+    pub fn identity(size: u32) -> Self {
+        Self::from_fn(size.max(2), |rgb| rgb)
+    }
+
+    fn from_fn(size: u32, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size as usize).pow(3));
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push(f([r as f32 / max, g as f32 / max, b as f32 / max]));
+                }
+            }
+        }
+        Lut3d { size, data }
+    }
+
+    /// Amount of grid points per dimension
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Entries of the lookup table, ordered with the red index changing fastest
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    /// Look up a color using trilinear interpolation
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let mut idx = [0usize; 3];
+        let mut frac = [0f32; 3];
+        for i in 0..3 {
+            let pos = rgb[i].clamp(0.0, 1.0) * max;
+            idx[i] = (pos.floor() as usize).min(self.size as usize - 2);
+            frac[i] = pos - idx[i] as f32;
+        }
+
+        let size = self.size as usize;
+        let at = |r: usize, g: usize, b: usize| self.data[(b * size + g) * size + r];
+        let mut result = [0f32; 3];
+        for (db, wb) in [(0, 1.0 - frac[2]), (1, frac[2])] {
+            for (dg, wg) in [(0, 1.0 - frac[1]), (1, frac[1])] {
+                for (dr, wr) in [(0, 1.0 - frac[0]), (1, frac[0])] {
+                    let value = at(idx[0] + dr, idx[1] + dg, idx[2] + db);
+                    let weight = wr * wg * wb;
+                    for c in 0..3 {
+                        result[c] += value[c] * weight;
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Transformation of the composited output into the color space of a display
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorTransform {
+    /// Transfer function decoding the composited content
+    pub input: TransferFunction,
+    /// Matrix applied on linear light, row-major
+    pub matrix: [[f32; 3]; 3],
+    /// Transfer function encoding the result for the display
+    pub output: TransferFunction,
+    /// Lookup table applied on the encoded result
+    pub lut: Option<Lut3d>,
+}
+
+impl Default for ColorTransform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl ColorTransform {
+    /// Transform not altering any colors
+    pub fn identity() -> Self {
+        ColorTransform {
+            input: TransferFunction::Linear,
+            matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            output: TransferFunction::Linear,
+            lut: None,
+        }
+    }
+
+    /// Create a transform from colorimetry parameters
+    ///
+    /// Content encoded with `input` using the `src` primaries is converted to the `dst` primaries
+    /// and encoded with `output`.
+    pub fn from_colorimetry(
+        src: Primaries,
+        input: TransferFunction,
+        dst: Primaries,
+        output: TransferFunction,
+    ) -> Self {
+        let matrix = dst.to_xyz().invert().unwrap_or_else(Matrix3::identity)
+            * adaptation(src.white, dst.white)
+            * src.to_xyz();
+        ColorTransform {
+            input,
+            matrix: to_rows(matrix),
+            output,
+            lut: None,
+        }
+    }
+
+    /// Create a transform for sRGB content from a matrix/TRC based ICC display profile
+    ///
+    /// Profiles only providing lookup table based transforms (`A2B0`/`B2A0`) are not supported.
+    pub fn from_icc(data: &[u8]) -> Result<Self, IccError> {
+        let profile = IccProfile::parse(data)?;
+
+        let column = |tag| profile.xyz(tag).map(|[x, y, z]| Vector3::new(x, y, z));
+        let display = Matrix3::from_cols(column("rXYZ")?, column("gXYZ")?, column("bXYZ")?);
+        let display_inv = display.invert().ok_or(IccError::Invalid)?;
+
+        // the profile connection space is relative to D50
+        let srgb = adaptation(Primaries::SRGB.white, D50) * Primaries::SRGB.to_xyz();
+        let matrix = display_inv * srgb;
+
+        // the lut bakes separate curves per channel, but a single output curve is assumed otherwise
+        let curves = [
+            profile.curve("rTRC")?,
+            profile.curve("gTRC")?,
+            profile.curve("bTRC")?,
+        ];
+        let output = curves[1].clone();
+        let mut transform = ColorTransform {
+            input: TransferFunction::Srgb,
+            matrix: to_rows(matrix),
+            output,
+            lut: None,
+        };
+        if curves[0] != curves[1] || curves[1] != curves[2] {
+            transform.output = TransferFunction::Linear;
+            let lut = Lut3d::from_fn(DEFAULT_LUT_SIZE, |rgb| {
+                let linear = transform.apply(rgb);
+                [
+                    curves[0].eval_inverse(linear[0]),
+                    curves[1].eval_inverse(linear[1]),
+                    curves[2].eval_inverse(linear[2]),
+                ]
+            });
+            transform.lut = Some(lut);
+        }
+        Ok(transform)
+    }
+
+    /// Returns `true` if the transform does not alter any colors
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Apply the transform to a single non-premultiplied color
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let linear = rgb.map(|c| self.input.eval(c));
+        let mut result = [0f32; 3];
+        for (row, value) in self.matrix.iter().zip(result.iter_mut()) {
+            *value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+        }
+        let encoded = result.map(|c| self.output.eval_inverse(c));
+        match self.lut.as_ref() {
+            Some(lut) => lut.sample(encoded),
+            None => encoded,
+        }
+    }
+
+    /// Apply `filters` in order to the content before it is transformed
+    ///
+    /// The filters are baked into the lookup table of the resulting transform.
+    pub fn with_filters(&self, filters: &[ColorFilter]) -> ColorTransform {
+        if filters.is_empty() {
+            return self.clone();
+        }
+
+        let lut = Lut3d::from_fn(DEFAULT_LUT_SIZE, |rgb| self.apply(apply_filters(filters, rgb)));
+        ColorTransform {
+            lut: Some(lut),
+            ..ColorTransform::identity()
+        }
+    }
+
+    /// Bake the whole transform into a lookup table with `size` grid points per dimension
+    pub fn bake(&self, size: u32) -> Lut3d {
+        Lut3d::from_fn(size.max(2), |rgb| self.apply(rgb))
+    }
+}
+
+/// Filter applied to the colors of the whole output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorFilter {
+    /// Tint the output like the light of a black body of the given temperature in Kelvin
+    ///
+    /// A temperature of 6500K does not alter any colors, lower temperatures reduce the amount
+    /// of blue light, e.g. for a night light.
+    Temperature(f32),
+    /// Convert all colors to their luminance
+    Grayscale,
+    /// Invert all colors
+    Invert,
+}
+
+/// Temperature of [`ColorFilter::Temperature`] not altering any colors
+pub const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+
+impl ColorFilter {
+    /// Apply the filter to a single non-premultiplied sRGB encoded color
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        match *self {
+            ColorFilter::Temperature(kelvin) => {
+                let white = temperature_white(kelvin);
+                let neutral = temperature_white(NEUTRAL_TEMPERATURE);
+                [0, 1, 2].map(|c| rgb[c] * (white[c] / neutral[c]).min(1.0))
+            }
+            ColorFilter::Grayscale => {
+                // the luminance is calculated on linear light
+                let [r, g, b] = rgb.map(|c| TransferFunction::Srgb.eval(c));
+                let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                [TransferFunction::Srgb.eval_inverse(luminance); 3]
+            }
+            ColorFilter::Invert => rgb.map(|c| 1.0 - c.clamp(0.0, 1.0)),
+        }
+    }
+
+    /// Returns `true` if every channel is filtered independently of the others
+    ///
+    /// Only those filters can be applied using gamma lookup tables.
+    pub fn is_separable(&self) -> bool {
+        !matches!(self, ColorFilter::Grayscale)
+    }
+
+    /// Create gamma ramps with `size` entries per channel applying all `filters` in order
+    ///
+    /// Returns `None` if any filter is not [separable](ColorFilter::is_separable).
+    pub fn gamma_ramps(filters: &[ColorFilter], size: usize) -> Option<[Vec<u16>; 3]> {
+        if filters.iter().any(|filter| !filter.is_separable()) {
+            return None;
+        }
+
+        let max = size.saturating_sub(1).max(1) as f32;
+        let mut ramps = [
+            Vec::with_capacity(size),
+            Vec::with_capacity(size),
+            Vec::with_capacity(size),
+        ];
+        for i in 0..size {
+            let value = i as f32 / max;
+            let color = apply_filters(filters, [value; 3]);
+            for (ramp, c) in ramps.iter_mut().zip(color) {
+                ramp.push((c.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16);
+            }
+        }
+        Some(ramps)
+    }
+}
+
+fn apply_filters(filters: &[ColorFilter], rgb: [f32; 3]) -> [f32; 3] {
+    filters.iter().fold(rgb, |rgb, filter| filter.apply(rgb))
+}
+
+// Approximation of the sRGB encoded color of a black body by Tanner Helland
+fn temperature_white(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let g = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    [r, g, b].map(|c| (c / 255.0).clamp(0.0, 1.0))
+}
+
+fn to_rows(matrix: Matrix3<f64>) -> [[f32; 3]; 3] {
+    // cgmath matrices are column-major
+    [
+        [matrix.x.x as f32, matrix.y.x as f32, matrix.z.x as f32],
+        [matrix.x.y as f32, matrix.y.y as f32, matrix.z.y as f32],
+        [matrix.x.z as f32, matrix.y.z as f32, matrix.z.z as f32],
+    ]
+}
+
+struct IccProfile<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> IccProfile<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, IccError> {
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err(IccError::Invalid);
+        }
+        Ok(IccProfile { data })
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32, IccError> {
+        self.data
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+            .ok_or(IccError::Invalid)
+    }
+
+    fn s15_fixed16_at(&self, offset: usize) -> Result<f64, IccError> {
+        self.u32_at(offset).map(|value| value as i32 as f64 / 65536.0)
+    }
+
+    fn tag(&self, signature: &'static str) -> Result<&'a [u8], IccError> {
+        let count = self.u32_at(128)? as usize;
+        for i in 0..count {
+            let entry = 132 + i * 12;
+            if self.data.get(entry..entry + 4) == Some(signature.as_bytes()) {
+                let offset = self.u32_at(entry + 4)? as usize;
+                let size = self.u32_at(entry + 8)? as usize;
+                return self
+                    .data
+                    .get(offset..offset.saturating_add(size))
+                    .filter(|tag| tag.len() >= 8)
+                    .ok_or(IccError::Invalid);
+            }
+        }
+        Err(IccError::MissingTag(signature))
+    }
+
+    fn xyz(&self, signature: &'static str) -> Result<[f64; 3], IccError> {
+        let tag = IccProfile {
+            data: self.tag(signature)?,
+        };
+        match &tag.data[0..4] {
+            b"XYZ " => Ok([
+                tag.s15_fixed16_at(8)?,
+                tag.s15_fixed16_at(12)?,
+                tag.s15_fixed16_at(16)?,
+            ]),
+            other => Err(IccError::UnsupportedTagType(
+                String::from_utf8_lossy(other).into_owned(),
+            )),
+        }
+    }
+
+    fn curve(&self, signature: &'static str) -> Result<TransferFunction, IccError> {
+        let tag = IccProfile {
+            data: self.tag(signature)?,
+        };
+        let u16_at = |offset: usize| {
+            tag.data
+                .get(offset..offset + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .ok_or(IccError::Invalid)
+        };
+        match &tag.data[0..4] {
+            b"curv" => match tag.u32_at(8)? {
+                0 => Ok(TransferFunction::Linear),
+                1 => Ok(TransferFunction::Gamma(u16_at(12)? as f32 / 256.0)),
+                count => (0..count as usize)
+                    .map(|i| u16_at(12 + i * 2).map(|value| value as f32 / 65535.0))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(TransferFunction::Table),
+            },
+            b"para" => {
+                let function = u16_at(8)?;
+                let param_count = match function {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return Err(IccError::UnsupportedTagType(format!("para type {}", function))),
+                };
+                let mut params = [0f32; 7];
+                for (i, param) in params.iter_mut().take(param_count).enumerate() {
+                    *param = tag.s15_fixed16_at(12 + i * 4)? as f32;
+                }
+                let [g, a, b, c, d, e, f] = params;
+                let params = match function {
+                    0 => [g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                    1 => [g, a, b, 0.0, -b / a, 0.0, 0.0],
+                    2 => [g, a, b, 0.0, -b / a, c, c],
+                    3 => [g, a, b, c, d, 0.0, 0.0],
+                    _ => [g, a, b, c, d, e, f],
+                };
+                Ok(TransferFunction::Parametric(params))
+            }
+            other => Err(IccError::UnsupportedTagType(
+                String::from_utf8_lossy(other).into_owned(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorFilter, ColorTransform, Lut3d, Primaries, TransferFunction, NEUTRAL_TEMPERATURE};
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 0.002, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn srgb_to_srgb_is_identity() {
+        let transform = ColorTransform::from_colorimetry(
+            Primaries::SRGB,
+            TransferFunction::Srgb,
+            Primaries::SRGB,
+            TransferFunction::Srgb,
+        );
+        let lut = transform.bake(17);
+        for rgb in [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [0.2, 0.5, 0.8], [0.9, 0.1, 0.4]] {
+            assert_close(transform.apply(rgb), rgb);
+            assert_close(lut.sample(rgb), rgb);
+        }
+        assert_close(Lut3d::identity(2).sample([0.3, 0.6, 0.9]), [0.3, 0.6, 0.9]);
+
+        // a wider gamut display shows pure sRGB red with less saturation
+        let p3 = ColorTransform::from_colorimetry(
+            Primaries::SRGB,
+            TransferFunction::Srgb,
+            Primaries::DISPLAY_P3,
+            TransferFunction::Srgb,
+        );
+        let red = p3.apply([1.0, 0.0, 0.0]);
+        assert!(red[0] < 1.0 && red[1] > 0.0);
+    }
+
+    #[test]
+    fn parse_icc_profile() {
+        fn s15(value: f64) -> [u8; 4] {
+            ((value * 65536.0).round() as i32).to_be_bytes()
+        }
+
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+        for (sig, xyz) in [
+            (b"rXYZ", [0.4361, 0.2225, 0.0139]),
+            (b"gXYZ", [0.3851, 0.7169, 0.0971]),
+            (b"bXYZ", [0.1431, 0.0606, 0.7141]),
+        ] {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            xyz.iter().for_each(|value| tag.extend_from_slice(&s15(*value)));
+            tags.push((sig, tag));
+        }
+        for sig in [b"rTRC", b"gTRC", b"bTRC"] {
+            // a single u8Fixed8 gamma value
+            tags.push((sig, b"curv\0\0\0\0\0\0\0\x01\x02\x33".to_vec()));
+        }
+
+        let mut data = vec![0u8; 132 + tags.len() * 12];
+        data[36..40].copy_from_slice(b"acsp");
+        data[128..132].copy_from_slice(&(tags.len() as u32).to_be_bytes());
+        for (i, (sig, tag)) in tags.iter().enumerate() {
+            let offset = data.len() as u32;
+            let entry = 132 + i * 12;
+            data[entry..entry + 4].copy_from_slice(*sig);
+            data[entry + 4..entry + 8].copy_from_slice(&offset.to_be_bytes());
+            data[entry + 8..entry + 12].copy_from_slice(&(tag.len() as u32).to_be_bytes());
+            data.extend_from_slice(tag);
+        }
+
+        // an sRGB-like profile with a gamma 2.2 curve barely changes sRGB content
+        let transform = ColorTransform::from_icc(&data).unwrap();
+        assert_eq!(transform.output, TransferFunction::Gamma(0x233 as f32 / 256.0));
+        assert!(transform.lut.is_none());
+        assert_close(transform.apply([1.0, 1.0, 1.0]), [1.0, 1.0, 1.0]);
+        let red = transform.apply([1.0, 0.0, 0.0]);
+        assert!(red[0] > 0.99 && red[1] < 0.05 && red[2] < 0.05);
+
+        assert!(ColorTransform::from_icc(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn color_filters() {
+        let neutral = ColorFilter::Temperature(NEUTRAL_TEMPERATURE);
+        assert_close(neutral.apply([0.2, 0.5, 0.8]), [0.2, 0.5, 0.8]);
+        let warm = ColorFilter::Temperature(3400.0).apply([1.0, 1.0, 1.0]);
+        assert!(warm[0] > warm[1] && warm[1] > warm[2]);
+
+        let gray = ColorFilter::Grayscale.apply([1.0, 0.0, 0.0]);
+        assert!(gray[0] == gray[1] && gray[1] == gray[2]);
+        assert_close(ColorFilter::Grayscale.apply([1.0, 1.0, 1.0]), [1.0, 1.0, 1.0]);
+
+        let filters = [ColorFilter::Invert, ColorFilter::Grayscale];
+        let transform = ColorTransform::identity().with_filters(&filters);
+        assert_close(transform.apply([1.0, 1.0, 1.0]), [0.0, 0.0, 0.0]);
+        assert!(ColorFilter::gamma_ramps(&filters, 256).is_none());
+
+        let [r, _, b] = ColorFilter::gamma_ramps(&[ColorFilter::Invert], 256).unwrap();
+        assert_eq!((r[0], r[255]), (u16::MAX, 0));
+        assert_eq!(r, b);
+    }
+}
@@ -226,6 +226,27 @@ use shaper::DamageShaper;
 
 const MAX_AGE: usize = 4;
 
+bitflags::bitflags! {
+    /// Debug visualizations drawn by the [`OutputDamageTracker`]
+    ///
+    /// Set through [`OutputDamageTracker::set_debug_flags`], useful for diagnosing elements
+    /// reporting too much or too little damage.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct DamageDebugFlags: u32 {
+        /// Tint the damage of every rendered frame
+        ///
+        /// The tint is removed again on the next frame, unless the region is damaged again.
+        const TINT_DAMAGE = 0b00000001;
+        /// Outline the geometry of all elements in the redrawn regions
+        const OUTLINE_ELEMENTS = 0b00000010;
+        /// Choose the color of the damage tint by the age of the buffer
+        ///
+        /// Red means an age of 0, so the buffer was fully redrawn, green, blue and yellow indicate
+        /// an age of 1, 2 and 3 or more.
+        const SHOW_AGE = 0b00000100;
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct ElementInstanceState {
     last_src: Rectangle<f64, BufferCoords>,
@@ -297,6 +318,8 @@ pub struct OutputDamageTracker {
     opaque_regions_index: Vec<Range<usize>>,
     element_opaque_regions: Vec<Rectangle<i32, Physical>>,
     element_visible_area_workhouse: Vec<Rectangle<i32, Physical>>,
+    debug_flags: DamageDebugFlags,
+    debug_damage: Vec<Rectangle<i32, Physical>>,
     span: tracing::Span,
 }
 
@@ -332,6 +355,40 @@ impl<'a> RenderOutputResult<'a> {
     }
 }
 
+/// Render targets able to report the age of their current content
+///
+/// The age is the number of frames rendered since the content of the buffer was last
+/// rendered into it. An age of `0` or `None` means the content is undefined and the
+/// buffer has to be fully redrawn.
+///
+/// Used by [`OutputDamageTracker::render_output_to`] to only redraw what changed
+/// since the buffer was last used.
+pub trait BufferAge {
+    /// Returns the age of the current content, if known
+    fn buffer_age(&self) -> Option<usize>;
+}
+
+impl<T: BufferAge + ?Sized> BufferAge for &T {
+    #[inline]
+    fn buffer_age(&self) -> Option<usize> {
+        (**self).buffer_age()
+    }
+}
+
+impl<T: BufferAge + ?Sized> BufferAge for std::rc::Rc<T> {
+    #[inline]
+    fn buffer_age(&self) -> Option<usize> {
+        (**self).buffer_age()
+    }
+}
+
+impl<T: BufferAge + ?Sized> BufferAge for std::sync::Arc<T> {
+    #[inline]
+    fn buffer_age(&self) -> Option<usize> {
+        (**self).buffer_age()
+    }
+}
+
 impl<R: Renderer> std::fmt::Debug for Error<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -362,6 +419,8 @@ impl OutputDamageTracker {
             opaque_regions_index: Default::default(),
             element_opaque_regions: Default::default(),
             element_visible_area_workhouse: Default::default(),
+            debug_flags: Default::default(),
+            debug_damage: Default::default(),
             span: info_span!("renderer_damage"),
         }
     }
@@ -382,6 +441,8 @@ impl OutputDamageTracker {
             element_opaque_regions: Default::default(),
             element_visible_area_workhouse: Default::default(),
             last_state: Default::default(),
+            debug_flags: Default::default(),
+            debug_damage: Default::default(),
             span: info_span!("renderer_damage", output = output.name()),
         }
     }
@@ -403,6 +464,8 @@ impl OutputDamageTracker {
             opaque_regions_index: Default::default(),
             element_visible_area_workhouse: Default::default(),
             last_state: Default::default(),
+            debug_flags: Default::default(),
+            debug_damage: Default::default(),
         }
     }
 
@@ -411,6 +474,16 @@ impl OutputDamageTracker {
         &self.mode
     }
 
+    /// Set the [`DamageDebugFlags`] used for subsequent renders
+    pub fn set_debug_flags(&mut self, flags: DamageDebugFlags) {
+        self.debug_flags = flags;
+    }
+
+    /// Returns the currently set [`DamageDebugFlags`]
+    pub fn debug_flags(&self) -> DamageDebugFlags {
+        self.debug_flags
+    }
+
     /// Render this output with the provided [`Renderer`] in the provided buffer
     ///
     /// - `elements` for this output in front-to-back order
@@ -432,6 +505,31 @@ impl OutputDamageTracker {
         self.render_output_internal(renderer, age, elements, clear_color, |r| r.bind(buffer))
     }
 
+    /// Render this output with the provided [`Renderer`] in the provided buffer,
+    /// querying the age of the buffer from the buffer itself
+    ///
+    /// The whole output is redrawn, if the age of the buffer is unknown.
+    ///
+    /// - `elements` for this output in front-to-back order
+    #[instrument(level = "trace", parent = &self.span, skip(renderer, elements, buffer))]
+    #[profiling::function]
+    pub fn render_output_to<E, R, B>(
+        &mut self,
+        renderer: &mut R,
+        buffer: B,
+        elements: &[E],
+        clear_color: Color32F,
+    ) -> Result<RenderOutputResult<'_>, Error<R>>
+    where
+        E: RenderElement<R>,
+        R: Renderer + Bind<B>,
+        B: BufferAge,
+        <R as Renderer>::TextureId: Texture,
+    {
+        let age = buffer.buffer_age().unwrap_or(0);
+        self.render_output_internal(renderer, age, elements, clear_color, |r| r.bind(buffer))
+    }
+
     /// Render this output with the provided [`Renderer`]
     ///
     /// - `elements` for this output in front-to-back order
@@ -681,6 +779,9 @@ impl OutputDamageTracker {
             self.damage.push(output_geo);
         }
 
+        // elements sampling the content below them have to be redrawn if anything changed in their backdrop
+        expand_backdrop_damage(&mut self.damage, render_elements, output_scale, output_geo);
+
         // That is all completely new damage, which we need to store for subsequent renders
         let mut new_damage = self.damage.clone();
         new_damage.shrink_to_fit();
@@ -707,6 +808,9 @@ impl OutputDamageTracker {
             self.damage.push(output_geo);
         };
 
+        // old damage may have touched a backdrop region we did not consider for the new damage
+        expand_backdrop_damage(&mut self.damage, render_elements, output_scale, output_geo);
+
         // Optimize the damage for rendering
 
         // Clamp all rectangles to the bounds removing the ones without intersection.
@@ -821,6 +925,15 @@ impl OutputDamageTracker {
             &mut render_elements,
         );
 
+        // the damage of this frame, if anything changed, is the most recent entry of the damage history
+        let new_damage =
+            if !self.damage.is_empty() && self.debug_flags.contains(DamageDebugFlags::TINT_DAMAGE) {
+                self.last_state.old_damage.front().cloned().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+        self.add_debug_damage(output_geo);
+
         if self.damage.is_empty() {
             trace!("no damage, skipping rendering");
             return Ok(RenderOutputResult::skipped(states));
@@ -907,6 +1020,17 @@ impl OutputDamageTracker {
                 )?;
             }
 
+            if !self.debug_flags.is_empty() {
+                self.draw_debug(
+                    &mut frame,
+                    &render_elements,
+                    &new_damage,
+                    output_scale,
+                    output_geo,
+                    age,
+                )?;
+            }
+
             // return the element damage so that we can re-use the allocation
             std::mem::swap(&mut self.element_damage, &mut element_damage);
             std::mem::swap(&mut self.element_opaque_regions, &mut element_opaque_regions);
@@ -928,3 +1052,234 @@ impl OutputDamageTracker {
         }
     }
 }
+
+impl OutputDamageTracker {
+    // The debug tint of the last frame has to be removed again, so it is added to the damage
+    // of this frame and stored as part of it for buffers rendered later.
+    fn add_debug_damage(&mut self, output_geo: Rectangle<i32, Physical>) {
+        let debug_damage = std::mem::take(&mut self.debug_damage)
+            .into_iter()
+            .filter_map(|rect| rect.intersection(output_geo))
+            .collect::<Vec<_>>();
+        if debug_damage.is_empty() {
+            return;
+        }
+
+        if self.damage.is_empty() {
+            self.last_state.old_damage.push_front(debug_damage.clone());
+            self.last_state.old_damage.truncate(MAX_AGE);
+        } else if let Some(new_damage) = self.last_state.old_damage.front_mut() {
+            new_damage.extend(debug_damage.iter().copied());
+        }
+        self.damage.extend(debug_damage);
+    }
+
+    fn draw_debug<E, F>(
+        &mut self,
+        frame: &mut F,
+        render_elements: &[&E],
+        new_damage: &[Rectangle<i32, Physical>],
+        output_scale: Scale<f64>,
+        output_geo: Rectangle<i32, Physical>,
+        age: usize,
+    ) -> Result<(), F::Error>
+    where
+        E: Element,
+        F: Frame,
+    {
+        if self.debug_flags.contains(DamageDebugFlags::OUTLINE_ELEMENTS) {
+            let width = output_scale.x.max(output_scale.y).round().max(1.0) as i32;
+            let color = Color32F::new(0.0, 0.6, 0.0, 0.6);
+            for element in render_elements {
+                let geometry = element.geometry(output_scale);
+                let (w, h) = (geometry.size.w, geometry.size.h);
+                let edges = [
+                    Rectangle::from_loc_and_size((0, 0), (w, width)),
+                    Rectangle::from_loc_and_size((0, h - width), (w, width)),
+                    Rectangle::from_loc_and_size((0, 0), (width, h)),
+                    Rectangle::from_loc_and_size((w - width, 0), (width, h)),
+                ];
+                for mut edge in edges {
+                    edge.loc += geometry.loc;
+                    // the outline lies inside of the element, so it is repaired by its own damage
+                    for damage in self.damage.iter().filter_map(|d| d.intersection(edge)) {
+                        frame.draw_solid(
+                            damage,
+                            &[Rectangle::from_loc_and_size((0, 0), damage.size)],
+                            color,
+                        )?;
+                    }
+                }
+            }
+        }
+
+        if self.debug_flags.contains(DamageDebugFlags::TINT_DAMAGE) {
+            let color = if self.debug_flags.contains(DamageDebugFlags::SHOW_AGE) {
+                match age {
+                    0 => Color32F::new(0.3, 0.0, 0.0, 0.3),
+                    1 => Color32F::new(0.0, 0.3, 0.0, 0.3),
+                    2 => Color32F::new(0.0, 0.0, 0.3, 0.3),
+                    _ => Color32F::new(0.3, 0.3, 0.0, 0.3),
+                }
+            } else {
+                Color32F::new(0.3, 0.0, 0.0, 0.3)
+            };
+            // avoid blending the tint multiple times for overlapping damage
+            let mut tint = Vec::with_capacity(new_damage.len());
+            for rect in new_damage.iter().filter_map(|rect| rect.intersection(output_geo)) {
+                let remaining = Rectangle::subtract_rects_many([rect], tint.iter().copied());
+                tint.extend(remaining);
+            }
+            for rect in &tint {
+                frame.draw_solid(*rect, &[Rectangle::from_loc_and_size((0, 0), rect.size)], color)?;
+            }
+            self.debug_damage = tint;
+        }
+
+        Ok(())
+    }
+}
+
+/// Damages the whole backdrop region of every element whose backdrop intersects the damage
+///
+/// Elements are processed back to front, so the expanded damage of an element is taken into
+/// account for the elements above.
+fn expand_backdrop_damage<E: Element>(
+    damage: &mut Vec<Rectangle<i32, Physical>>,
+    render_elements: &[&E],
+    output_scale: Scale<f64>,
+    output_geo: Rectangle<i32, Physical>,
+) {
+    for element in render_elements.iter().rev() {
+        let Some(mut region) = element.backdrop_region(output_scale) else {
+            continue;
+        };
+        region.loc += element.geometry(output_scale).loc;
+        let Some(region) = region.intersection(output_geo) else {
+            continue;
+        };
+
+        if damage.iter().any(|rect| rect.overlaps(region)) {
+            damage.push(region);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "renderer_test"))]
+mod tests {
+    use super::{DamageDebugFlags, OutputDamageTracker};
+    use crate::{
+        backend::{
+            allocator::{Allocator, Buffer, Format, Fourcc, Modifier, Swapchain},
+            renderer::{
+                element::{solid::SolidColorRenderElement, Id, Kind},
+                test::DummyRenderer,
+                utils::CommitCounter,
+                Color32F,
+            },
+        },
+        utils::{Buffer as BufferCoords, Physical, Rectangle, Size, Transform},
+    };
+
+    #[derive(Debug)]
+    struct DummyBuffer(Size<i32, BufferCoords>, Format);
+
+    impl Buffer for DummyBuffer {
+        fn size(&self) -> Size<i32, BufferCoords> {
+            self.0
+        }
+
+        fn format(&self) -> Format {
+            self.1
+        }
+    }
+
+    #[derive(Debug)]
+    struct DummyAllocator;
+
+    impl Allocator for DummyAllocator {
+        type Buffer = DummyBuffer;
+        type Error = std::convert::Infallible;
+
+        fn create_buffer(
+            &mut self,
+            width: u32,
+            height: u32,
+            fourcc: Fourcc,
+            modifiers: &[Modifier],
+        ) -> Result<DummyBuffer, Self::Error> {
+            let format = Format {
+                code: fourcc,
+                modifier: modifiers[0],
+            };
+            Ok(DummyBuffer((width as i32, height as i32).into(), format))
+        }
+    }
+
+    #[test]
+    fn debug_tint_is_removed_on_next_frame() {
+        let mut renderer = DummyRenderer::new();
+        let mut tracker = OutputDamageTracker::new((64, 64), 1.0, Transform::Normal);
+        tracker.set_debug_flags(DamageDebugFlags::TINT_DAMAGE | DamageDebugFlags::OUTLINE_ELEMENTS);
+
+        let elements = [SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::<i32, Physical>::from_loc_and_size((8, 8), (16, 16)),
+            CommitCounter::default(),
+            [1.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        )];
+        let result = tracker
+            .render_output(&mut renderer, 1, &elements, Color32F::BLACK)
+            .unwrap();
+        assert!(result.damage.is_some());
+
+        // nothing changed, but the tint of the first frame has to be repaired
+        let result = tracker
+            .render_output(&mut renderer, 1, &elements, Color32F::BLACK)
+            .unwrap();
+        assert!(result.damage.is_some_and(|damage| !damage.is_empty()));
+
+        let result = tracker
+            .render_output(&mut renderer, 1, &elements, Color32F::BLACK)
+            .unwrap();
+        assert!(result.damage.is_none());
+    }
+
+    #[test]
+    fn swapchain_slots_report_their_age() {
+        let mut renderer = DummyRenderer::new();
+        let mut tracker = OutputDamageTracker::new((64, 64), 1.0, Transform::Normal);
+        let mut swapchain = Swapchain::new(DummyAllocator, 64, 64, Fourcc::Argb8888, vec![Modifier::Linear]);
+        let id = Id::new();
+        let element = |commit: usize| {
+            SolidColorRenderElement::new(
+                id.clone(),
+                Rectangle::<i32, Physical>::from_loc_and_size((8, 8), (16, 16)),
+                CommitCounter::from(commit),
+                [1.0, 0.0, 0.0, 1.0],
+                Kind::Unspecified,
+            )
+        };
+        let mut render = |swapchain: &mut Swapchain<DummyAllocator>, element: &SolidColorRenderElement| {
+            let slot = swapchain.acquire().unwrap().unwrap();
+            let damage = tracker
+                .render_output_to(&mut renderer, &slot, &[element], Color32F::BLACK)
+                .unwrap()
+                .damage
+                .cloned();
+            swapchain.submitted(&slot);
+            damage
+        };
+
+        // a new buffer has no content to reuse
+        let full = vec![Rectangle::from_loc_and_size((0, 0), (64, 64))];
+        assert_eq!(render(&mut swapchain, &element(0)), Some(full));
+        // the same buffer is reused with an age of one
+        assert_eq!(render(&mut swapchain, &element(0)), None);
+        assert_eq!(
+            render(&mut swapchain, &element(1)),
+            Some(vec![Rectangle::from_loc_and_size((8, 8), (16, 16))])
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! Element to render borders around a rectangle
+//!
+//! [`BorderRenderElement`] draws a border around a rectangle, e.g. the geometry of a window
+//! or a focus ring. The width of the border can be chosen per edge, the corners can be rounded
+//! and the border can be filled with a single color or a linear gradient.
+//!
+//! The element is drawn with a pixel shader by the
+//! [`GlesRenderer`](crate::backend::renderer::gles::GlesRenderer) and rasterized in software by the
+//! [`PixmanRenderer`](crate::backend::renderer::pixman::PixmanRenderer).
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     element::border::{BorderFill, BorderRenderElement, BorderWidths},
+//! #     Color32F,
+//! # };
+//! # use smithay::utils::Rectangle;
+//! let window_geometry = Rectangle::from_loc_and_size((100, 100), (640, 480));
+//! let mut border = BorderRenderElement::new(
+//!     window_geometry,
+//!     BorderWidths::uniform(4),
+//!     BorderFill::Gradient {
+//!         start: Color32F::new(0.2, 0.4, 0.8, 1.0),
+//!         end: Color32F::new(0.6, 0.2, 0.8, 1.0),
+//!         angle: std::f32::consts::FRAC_PI_4,
+//!     },
+//! );
+//! border.set_corner_radius(8.0);
+//!
+//! // follow the window when it is moved or resized
+//! border.set_geometry(Rectangle::from_loc_and_size((120, 100), (640, 480)));
+//! ```
+
+use crate::{
+    backend::renderer::{utils::CommitCounter, Color32F},
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{Element, Id, Kind};
+
+/// Widths of the edges of a border
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BorderWidths {
+    /// Width of the top edge
+    pub top: i32,
+    /// Width of the right edge
+    pub right: i32,
+    /// Width of the bottom edge
+    pub bottom: i32,
+    /// Width of the left edge
+    pub left: i32,
+}
+
+impl BorderWidths {
+    /// Widths using the same width for all edges
+    pub fn uniform(width: i32) -> Self {
+        BorderWidths {
+            top: width,
+            right: width,
+            bottom: width,
+            left: width,
+        }
+    }
+
+    fn max(&self) -> i32 {
+        self.top.max(self.right).max(self.bottom).max(self.left)
+    }
+
+    fn clamped(self) -> Self {
+        BorderWidths {
+            top: self.top.max(0),
+            right: self.right.max(0),
+            bottom: self.bottom.max(0),
+            left: self.left.max(0),
+        }
+    }
+}
+
+impl From<i32> for BorderWidths {
+    #[inline]
+    fn from(width: i32) -> Self {
+        BorderWidths::uniform(width)
+    }
+}
+
+/// Fill of a border
+///
+/// Colors are expected to be premultiplied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorderFill {
+    /// Fill the border with a single color
+    Solid(Color32F),
+    /// Fill the border with a linear gradient spanning the whole element
+    Gradient {
+        /// Color at the start of the gradient
+        start: Color32F,
+        /// Color at the end of the gradient
+        end: Color32F,
+        /// Direction of the gradient in radians, `0.0` going from left to right
+        /// and increasing clockwise
+        angle: f32,
+    },
+}
+
+impl From<Color32F> for BorderFill {
+    #[inline]
+    fn from(color: Color32F) -> Self {
+        BorderFill::Solid(color)
+    }
+}
+
+/// Element drawing a border around a rectangle
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug, Clone)]
+pub struct BorderRenderElement {
+    id: Id,
+    commit_counter: CommitCounter,
+    geometry: Rectangle<i32, Logical>,
+    widths: BorderWidths,
+    corner_radius: f32,
+    fill: BorderFill,
+    kind: Kind,
+}
+
+impl BorderRenderElement {
+    /// Create a new border drawn around `geometry`
+    ///
+    /// The border is drawn outside of `geometry`, so the element covers `geometry` extended
+    /// by the border `widths`. Geometry and widths are given in logical coordinates.
+    pub fn new(
+        geometry: Rectangle<i32, Logical>,
+        widths: impl Into<BorderWidths>,
+        fill: impl Into<BorderFill>,
+    ) -> Self {
+        BorderRenderElement {
+            id: Id::new(),
+            commit_counter: CommitCounter::default(),
+            geometry,
+            widths: widths.into().clamped(),
+            corner_radius: 0.0,
+            fill: fill.into(),
+            kind: Kind::Unspecified,
+        }
+    }
+
+    /// Set the [`Kind`] of this element
+    pub fn with_kind(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The rectangle the border is drawn around
+    pub fn geometry(&self) -> Rectangle<i32, Logical> {
+        self.geometry
+    }
+
+    /// Update the rectangle the border is drawn around
+    pub fn set_geometry(&mut self, geometry: Rectangle<i32, Logical>) {
+        // Moving the element is picked up by the damage tracker, only a resize changes its content
+        if self.geometry.size != geometry.size {
+            self.commit_counter.increment();
+        }
+        self.geometry = geometry;
+    }
+
+    /// The widths of the edges of the border
+    pub fn widths(&self) -> BorderWidths {
+        self.widths
+    }
+
+    /// Set the widths of the edges of the border
+    pub fn set_widths(&mut self, widths: impl Into<BorderWidths>) {
+        let widths = widths.into().clamped();
+        if self.widths != widths {
+            self.widths = widths;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// The fill of the border
+    pub fn fill(&self) -> BorderFill {
+        self.fill
+    }
+
+    /// Set the fill of the border
+    pub fn set_fill(&mut self, fill: impl Into<BorderFill>) {
+        let fill = fill.into();
+        if self.fill != fill {
+            self.fill = fill;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// The corner radius of the rectangle the border is drawn around
+    pub fn corner_radius(&self) -> f32 {
+        self.corner_radius
+    }
+
+    /// Set the corner radius of the rectangle the border is drawn around in logical coordinates
+    ///
+    /// The outer corners of the border are rounded accordingly.
+    pub fn set_corner_radius(&mut self, corner_radius: f32) {
+        let corner_radius = corner_radius.max(0.0);
+        if self.corner_radius != corner_radius {
+            self.corner_radius = corner_radius;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Returns the area covered by the border
+    pub fn area(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size(
+            self.geometry.loc - Point::from((self.widths.left, self.widths.top)),
+            (
+                self.geometry.size.w + self.widths.left + self.widths.right,
+                self.geometry.size.h + self.widths.top + self.widths.bottom,
+            ),
+        )
+    }
+
+    // Shape of the border when drawn into a rectangle of `size`
+    pub(crate) fn shape(&self, size: Size<i32, Physical>) -> BorderShape {
+        let area = self.area();
+        let scale_x = size.w as f32 / area.size.w.max(1) as f32;
+        let scale_y = size.h as f32 / area.size.h.max(1) as f32;
+        let size = [size.w as f32, size.h as f32];
+
+        let inner = [
+            self.widths.left as f32 * scale_x,
+            self.widths.top as f32 * scale_y,
+            self.geometry.size.w as f32 * scale_x,
+            self.geometry.size.h as f32 * scale_y,
+        ];
+        let inner_radius = self.corner_radius * scale_x;
+        let outer_radius = if self.corner_radius > 0.0 {
+            (self.corner_radius + self.widths.max() as f32) * scale_x
+        } else {
+            0.0
+        };
+
+        let (start, end, angle) = match self.fill {
+            BorderFill::Solid(color) => (color, color, 0.0f32),
+            BorderFill::Gradient { start, end, angle } => (start, end, angle),
+        };
+        // the gradient spans the element along its direction, like css linear gradients
+        let (sin, cos) = angle.sin_cos();
+        let length = (size[0] * cos).abs() + (size[1] * sin).abs();
+        let gradient = [cos / length.max(1.0), sin / length.max(1.0)];
+
+        BorderShape {
+            size,
+            inner,
+            inner_radius,
+            outer_radius,
+            gradient,
+            start,
+            end,
+        }
+    }
+}
+
+// Physical description of a border shared by the renderer implementations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BorderShape {
+    // size of the whole element
+    pub(crate) size: [f32; 2],
+    // rectangle the border is drawn around relative to the element: x, y, width, height
+    pub(crate) inner: [f32; 4],
+    pub(crate) inner_radius: f32,
+    pub(crate) outer_radius: f32,
+    // direction of the gradient scaled by the inverse of its length
+    pub(crate) gradient: [f32; 2],
+    pub(crate) start: Color32F,
+    pub(crate) end: Color32F,
+}
+
+impl BorderShape {
+    /// Premultiplied color of the border at the given point relative to the element
+    #[cfg(any(feature = "renderer_pixman", test))]
+    pub(crate) fn color_at(&self, x: f32, y: f32) -> Color32F {
+        let outer = rounded_rect_distance([0.0, 0.0, self.size[0], self.size[1]], x, y, self.outer_radius);
+        let inner = rounded_rect_distance(self.inner, x, y, self.inner_radius);
+        let coverage = (0.5 - outer).clamp(0.0, 1.0) * (0.5 + inner).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            return Color32F::TRANSPARENT;
+        }
+
+        let t =
+            ((x - self.size[0] * 0.5) * self.gradient[0] + (y - self.size[1] * 0.5) * self.gradient[1] + 0.5)
+                .clamp(0.0, 1.0);
+        let [r0, g0, b0, a0] = self.start.components();
+        let [r1, g1, b1, a1] = self.end.components();
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Color32F::new(mix(r0, r1), mix(g0, g1), mix(b0, b1), mix(a0, a1)) * coverage
+    }
+
+    /// Rectangles covering the border, the rectangle inside of it is never drawn
+    pub(crate) fn bounds(&self) -> [Rectangle<i32, Physical>; 4] {
+        let [x, y, w, h] = self.inner;
+        // the rounded inner corners reach into the rectangle the border is drawn around
+        let inset = self.inner_radius.ceil();
+        let (left, top) = ((x + inset).floor() as i32, (y + inset).floor() as i32);
+        let (right, bottom) = ((x + w - inset).ceil() as i32, (y + h - inset).ceil() as i32);
+        let (width, height) = (self.size[0].ceil() as i32, self.size[1].ceil() as i32);
+        [
+            Rectangle::from_loc_and_size((0, 0), (width, top)),
+            Rectangle::from_loc_and_size((0, bottom), (width, height - bottom)),
+            Rectangle::from_loc_and_size((0, top), (left, bottom - top)),
+            Rectangle::from_loc_and_size((right, top), (width - right, bottom - top)),
+        ]
+    }
+}
+
+// signed distance to the border of a rounded rectangle
+#[cfg(any(feature = "renderer_pixman", test))]
+fn rounded_rect_distance(rect: [f32; 4], x: f32, y: f32, radius: f32) -> f32 {
+    let half = [rect[2] * 0.5, rect[3] * 0.5];
+    let radius = radius.min(half[0]).min(half[1]).max(0.0);
+    let qx = (x - rect[0] - half[0]).abs() - half[0] + radius;
+    let qy = (y - rect[1] - half[1]).abs() - half[1] + radius;
+    qx.max(qy).min(0.0) + (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt() - radius
+}
+
+impl Element for BorderRenderElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        let area = self.area();
+        area.to_f64()
+            .to_buffer(1.0, Transform::Normal, &area.size.to_f64())
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.area().to_physical_precise_round(scale)
+    }
+
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BorderFill, BorderRenderElement, BorderWidths};
+    use crate::{
+        backend::renderer::Color32F,
+        utils::{Rectangle, Size},
+    };
+
+    #[test]
+    fn border_shape_covers_edges_only() {
+        let red = Color32F::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Color32F::new(0.0, 0.0, 1.0, 1.0);
+        let mut border = BorderRenderElement::new(
+            Rectangle::from_loc_and_size((10, 10), (20, 20)),
+            BorderWidths {
+                top: 2,
+                right: 4,
+                bottom: 2,
+                left: 4,
+            },
+            BorderFill::Gradient {
+                start: red,
+                end: blue,
+                angle: 0.0,
+            },
+        );
+        assert_eq!(border.area(), Rectangle::from_loc_and_size((6, 8), (28, 24)));
+
+        let shape = border.shape(Size::from((28, 24)));
+        // inside of the rectangle
+        assert_eq!(shape.color_at(14.5, 12.5), Color32F::TRANSPARENT);
+        // the gradient goes from left to right
+        assert!(shape.color_at(0.5, 12.5).r() > 0.9);
+        assert!(shape.color_at(27.5, 12.5).b() > 0.9);
+        assert_eq!(shape.color_at(14.0, 1.0).a(), 1.0);
+
+        border.set_corner_radius(4.0);
+        let shape = border.shape(Size::from((28, 24)));
+        // the outer corners are cut off
+        assert_eq!(shape.color_at(0.5, 0.5), Color32F::TRANSPARENT);
+        assert_eq!(shape.color_at(14.0, 1.0).a(), 1.0);
+        for bounds in shape.bounds() {
+            assert!(bounds
+                .intersection(Rectangle::from_loc_and_size((8, 6), (12, 12)))
+                .is_none());
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! Render elements for the pointer cursor
+//!
+//! The [`CursorElement`] turns the current [`CursorImageStatus`] of a pointer into render elements of
+//! [`Kind::Cursor`], positioned so that the hotspot of the cursor image is located at the pointer location.
+//! Client provided cursor surfaces are rendered with the hotspot set by the client, named cursor shapes
+//! are rendered from [`MemoryRenderBuffer`]s registered by the compositor, e.g. loaded from a cursor theme.
+//! Named shapes may consist of multiple [`CursorFrame`]s, which are cycled through according to their delays
+//! and the time set by [`CursorElement::set_time`].
+//!
+//! With the `xcursor` feature the shapes can be loaded from an xcursor theme on demand, see
+//! [`CursorElement::set_theme`].
+//!
+//! The resulting elements can be passed to a [`DrmCompositor`](crate::backend::drm::compositor::DrmCompositor),
+//! which scans them out on the cursor plane if possible and otherwise renders them on the primary plane.
+//!
+//! ```no_run
+//! # use drm_fourcc::DrmFourcc;
+//! # use smithay::utils::Transform;
+//! use smithay::backend::renderer::element::{cursor::CursorElement, memory::MemoryRenderBuffer};
+//! use smithay::input::pointer::{CursorIcon, CursorImageStatus};
+//!
+//! # let image: &[u8] = todo!();
+//! let mut cursor = CursorElement::default();
+//! let buffer = MemoryRenderBuffer::from_slice(image, DrmFourcc::Argb8888, (24, 24), 1, Transform::Normal, None);
+//! cursor.set_named_buffer(CursorIcon::Default, buffer, (4, 4));
+//!
+//! // ...whenever the client requests a new cursor image
+//! cursor.set_status(CursorImageStatus::default_named());
+//! ```
+
+use std::{collections::HashMap, time::Duration};
+
+use wayland_server::protocol::wl_surface::WlSurface;
+
+use crate::{
+    backend::renderer::{ImportAll, ImportMem, Renderer},
+    input::pointer::{CursorIcon, CursorImageStatus, CursorImageSurfaceData},
+    utils::{Logical, Physical, Point, Scale},
+    wayland::compositor,
+};
+
+use super::{
+    memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+    surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+    AsRenderElements, Kind,
+};
+
+/// A single image of a named cursor shape
+#[derive(Debug, Clone)]
+pub struct CursorFrame {
+    /// Buffer containing the image
+    pub buffer: MemoryRenderBuffer,
+    /// Hotspot of the image in logical coordinates
+    pub hotspot: Point<f64, Logical>,
+    /// Time the frame is shown before switching to the next frame of the shape
+    pub delay: Duration,
+}
+
+/// Helper to render the cursor image of a pointer
+#[derive(Debug)]
+pub struct CursorElement {
+    status: CursorImageStatus,
+    named: HashMap<CursorIcon, Vec<CursorFrame>>,
+    time: Duration,
+    #[cfg(feature = "xcursor")]
+    theme: Option<CursorTheme>,
+}
+
+#[cfg(feature = "xcursor")]
+#[derive(Debug)]
+struct CursorTheme {
+    theme: xcursor::CursorTheme,
+    size: u32,
+    scale: i32,
+}
+
+impl Default for CursorElement {
+    fn default() -> Self {
+        CursorElement {
+            status: CursorImageStatus::default_named(),
+            named: HashMap::new(),
+            time: Duration::ZERO,
+            #[cfg(feature = "xcursor")]
+            theme: None,
+        }
+    }
+}
+
+impl CursorElement {
+    /// Set the current cursor image status
+    ///
+    /// Named shapes without frames are loaded from the cursor theme, if one is set.
+    pub fn set_status(&mut self, status: CursorImageStatus) {
+        #[cfg(feature = "xcursor")]
+        if let CursorImageStatus::Named(icon) = status {
+            self.load_from_theme(icon);
+        }
+        self.status = status;
+    }
+
+    /// Returns the current cursor image status
+    pub fn status(&self) -> &CursorImageStatus {
+        &self.status
+    }
+
+    /// Set the buffer and hotspot used to render a named cursor shape
+    ///
+    /// Shapes without a buffer are rendered using the buffer of [`CursorIcon::Default`], if any.
+    pub fn set_named_buffer(
+        &mut self,
+        icon: CursorIcon,
+        buffer: MemoryRenderBuffer,
+        hotspot: impl Into<Point<i32, Logical>>,
+    ) {
+        let frame = CursorFrame {
+            buffer,
+            hotspot: hotspot.into().to_f64(),
+            delay: Duration::ZERO,
+        };
+        self.named.insert(icon, vec![frame]);
+    }
+
+    /// Set the frames used to render an animated named cursor shape
+    ///
+    /// Setting no frames removes the shape.
+    pub fn set_named_frames(&mut self, icon: CursorIcon, frames: impl IntoIterator<Item = CursorFrame>) {
+        let frames = frames.into_iter().collect::<Vec<_>>();
+        if frames.is_empty() {
+            self.named.remove(&icon);
+        } else {
+            self.named.insert(icon, frames);
+        }
+    }
+
+    /// Remove all buffers of named cursor shapes, e.g. when the cursor theme changes
+    pub fn clear_named_buffers(&mut self) {
+        self.named.clear();
+    }
+
+    /// Set the current time used to select the frame of animated cursor shapes
+    ///
+    /// The time is expected to increase monotonically, e.g. the time since the compositor started.
+    pub fn set_time(&mut self, time: Duration) {
+        self.time = time;
+    }
+
+    /// Returns the time until the frame of the current cursor shape changes
+    ///
+    /// Returns `None` if the current cursor image is not animated. Client provided cursor surfaces
+    /// are animated by the client and are therefore never reported as animated.
+    pub fn next_frame_in(&self) -> Option<Duration> {
+        let CursorImageStatus::Named(icon) = &self.status else {
+            return None;
+        };
+        let frames = self.named_frames(*icon)?;
+        let (_, remaining) = select_frame(frames, self.time)?;
+        Some(remaining)
+    }
+
+    /// Returns the hotspot of the current cursor image
+    pub fn hotspot(&self) -> Point<i32, Logical> {
+        self.precise_hotspot().to_i32_round()
+    }
+
+    fn precise_hotspot(&self) -> Point<f64, Logical> {
+        match &self.status {
+            CursorImageStatus::Hidden => Point::default(),
+            CursorImageStatus::Named(icon) => self
+                .named_frame(*icon)
+                .map(|frame| frame.hotspot)
+                .unwrap_or_default(),
+            CursorImageStatus::Surface(surface) => surface_hotspot(surface).to_f64(),
+        }
+    }
+
+    fn named_frames(&self, icon: CursorIcon) -> Option<&[CursorFrame]> {
+        self.named
+            .get(&icon)
+            .or_else(|| self.named.get(&CursorIcon::Default))
+            .map(Vec::as_slice)
+    }
+
+    fn named_frame(&self, icon: CursorIcon) -> Option<&CursorFrame> {
+        let frames = self.named_frames(icon)?;
+        match select_frame(frames, self.time) {
+            Some((index, _)) => frames.get(index),
+            None => frames.first(),
+        }
+    }
+}
+
+#[cfg(feature = "xcursor")]
+impl CursorElement {
+    /// Set the xcursor theme named cursor shapes are loaded from
+    ///
+    /// The images closest to `size * scale` pixels are loaded with a buffer scale of `scale`, so the
+    /// cursor has a logical size of about `size`. All previously loaded shapes are removed and the
+    /// shapes are loaded on demand, whenever a named shape without frames is set as the status.
+    pub fn set_theme(&mut self, theme: &str, size: u32, scale: i32) {
+        self.named.clear();
+        self.theme = Some(CursorTheme {
+            theme: xcursor::CursorTheme::load(theme),
+            size,
+            scale: scale.max(1),
+        });
+        if let CursorImageStatus::Named(icon) = self.status {
+            self.load_from_theme(icon);
+        }
+        self.load_from_theme(CursorIcon::Default);
+    }
+
+    fn load_from_theme(&mut self, icon: CursorIcon) {
+        if self.named.contains_key(&icon) {
+            return;
+        }
+        let Some(theme) = self.theme.as_ref() else {
+            return;
+        };
+
+        match load_xcursor_frames(&theme.theme, icon, theme.size, theme.scale) {
+            Ok(frames) => self.set_named_frames(icon, frames),
+            Err(err) => tracing::debug!(?icon, "Failed to load cursor from theme: {}", err),
+        }
+    }
+}
+
+/// Errors returned by [`load_xcursor_frames`]
+#[cfg(feature = "xcursor")]
+#[derive(Debug, thiserror::Error)]
+pub enum XcursorError {
+    /// The theme does not contain the shape
+    #[error("The theme has no cursor named {0}")]
+    NotFound(CursorIcon),
+    /// The cursor file could not be read
+    #[error("Failed to read the cursor file")]
+    Io(#[from] std::io::Error),
+    /// The cursor file is invalid
+    #[error("Failed to parse the cursor file")]
+    Invalid,
+}
+
+/// Load the frames of a named cursor shape from an xcursor theme
+///
+/// The images closest to `size * scale` pixels are used with a buffer scale of `scale`. The alternative
+/// names of the shape are tried, if the theme does not contain the shape under its primary name.
+#[cfg(feature = "xcursor")]
+pub fn load_xcursor_frames(
+    theme: &xcursor::CursorTheme,
+    icon: CursorIcon,
+    size: u32,
+    scale: i32,
+) -> Result<Vec<CursorFrame>, XcursorError> {
+    let path = std::iter::once(icon.name())
+        .chain(icon.alt_names().iter().copied())
+        .find_map(|name| theme.load_icon(name))
+        .ok_or(XcursorError::NotFound(icon))?;
+    let data = std::fs::read(path)?;
+    let images = xcursor::parser::parse_xcursor(&data).ok_or(XcursorError::Invalid)?;
+
+    let scale = scale.max(1);
+    let nominal = size * scale as u32;
+    let nearest = images
+        .iter()
+        .min_by_key(|image| (nominal as i64 - image.size as i64).abs())
+        .ok_or(XcursorError::Invalid)?;
+    Ok(images
+        .iter()
+        .filter(|image| image.width == nearest.width && image.height == nearest.height)
+        .map(|image| CursorFrame {
+            // despite its name `pixels_rgba` holds the little endian ARGB32 pixels of the file
+            buffer: MemoryRenderBuffer::from_slice(
+                &image.pixels_rgba,
+                crate::backend::allocator::Fourcc::Argb8888,
+                (image.width as i32, image.height as i32),
+                scale,
+                crate::utils::Transform::Normal,
+                None,
+            ),
+            hotspot: Point::from((image.xhot as f64, image.yhot as f64)).downscale(scale as f64),
+            delay: Duration::from_millis(image.delay as u64),
+        })
+        .collect())
+}
+
+// Returns the index of the frame shown at `time` and the time until the next frame,
+// or `None` if the frames are not animated
+fn select_frame(frames: &[CursorFrame], time: Duration) -> Option<(usize, Duration)> {
+    if frames.len() < 2 {
+        return None;
+    }
+    let total = frames.iter().map(|frame| frame.delay).sum::<Duration>();
+    if total.is_zero() {
+        return None;
+    }
+
+    let mut elapsed = Duration::from_nanos((time.as_nanos() % total.as_nanos()) as u64);
+    for (index, frame) in frames.iter().enumerate() {
+        if elapsed < frame.delay {
+            return Some((index, frame.delay - elapsed));
+        }
+        elapsed -= frame.delay;
+    }
+    None
+}
+
+fn surface_hotspot(surface: &WlSurface) -> Point<i32, Logical> {
+    compositor::with_states(surface, |states| {
+        states
+            .data_map
+            .get::<CursorImageSurfaceData>()
+            .map(|data| data.lock().unwrap().hotspot)
+            .unwrap_or_default()
+    })
+}
+
+crate::backend::renderer::element::render_elements! {
+    /// Render elements of a [`CursorElement`]
+    pub CursorRenderElement<R> where R: ImportAll + ImportMem;
+    /// A client provided cursor surface
+    Surface=WaylandSurfaceRenderElement<R>,
+    /// A named cursor shape
+    Memory=MemoryRenderBufferRenderElement<R>,
+}
+
+impl<R: Renderer + ImportAll + ImportMem> std::fmt::Debug for CursorRenderElement<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Memory(arg0) => f.debug_tuple("Memory").field(arg0).finish(),
+            Self::_GenericCatcher(_) => unreachable!(),
+        }
+    }
+}
+
+impl<R> AsRenderElements<R> for CursorElement
+where
+    R: Renderer + ImportAll + ImportMem,
+    <R as Renderer>::TextureId: Send + Clone + 'static,
+{
+    type RenderElement = CursorRenderElement<R>;
+
+    /// Returns the render elements of the current cursor image
+    ///
+    /// `location` is the location of the pointer, the elements are offset by the hotspot of the image.
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        match &self.status {
+            CursorImageStatus::Hidden => Vec::new(),
+            CursorImageStatus::Named(icon) => {
+                let Some(frame) = self.named_frame(*icon) else {
+                    return Vec::new();
+                };
+
+                // the hotspot of named shapes may be fractional, e.g. for images with a buffer scale
+                let location = location.to_f64() - frame.hotspot.to_physical(scale);
+                match MemoryRenderBufferRenderElement::from_buffer(
+                    renderer,
+                    location,
+                    &frame.buffer,
+                    Some(alpha),
+                    None,
+                    None,
+                    Kind::Cursor,
+                ) {
+                    Ok(element) => vec![C::from(CursorRenderElement::Memory(element))],
+                    Err(err) => {
+                        tracing::warn!("Failed to import cursor buffer: {}", err);
+                        Vec::new()
+                    }
+                }
+            }
+            CursorImageStatus::Surface(surface) => {
+                let location = location - surface_hotspot(surface).to_physical_precise_round(scale);
+                let elements: Vec<CursorRenderElement<R>> = render_elements_from_surface_tree(
+                    renderer,
+                    surface,
+                    location,
+                    scale,
+                    alpha,
+                    Kind::Cursor,
+                );
+                elements.into_iter().map(C::from).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CursorElement, CursorFrame};
+    use crate::{
+        backend::renderer::element::memory::MemoryRenderBuffer,
+        input::pointer::{CursorIcon, CursorImageStatus},
+        utils::{Point, Transform},
+    };
+
+    #[test]
+    fn named_hotspot_falls_back_to_default() {
+        let mut cursor = CursorElement::default();
+        assert_eq!(cursor.hotspot(), Point::from((0, 0)));
+
+        let buffer = MemoryRenderBuffer::new(
+            drm_fourcc::DrmFourcc::Argb8888,
+            (24, 24),
+            1,
+            Transform::Normal,
+            None,
+        );
+        cursor.set_named_buffer(CursorIcon::Default, buffer.clone(), (4, 4));
+        cursor.set_named_buffer(CursorIcon::Text, buffer, (12, 12));
+
+        cursor.set_status(CursorImageStatus::Named(CursorIcon::Text));
+        assert_eq!(cursor.hotspot(), Point::from((12, 12)));
+        cursor.set_status(CursorImageStatus::Named(CursorIcon::Wait));
+        assert_eq!(cursor.hotspot(), Point::from((4, 4)));
+        cursor.set_status(CursorImageStatus::Hidden);
+        assert_eq!(cursor.hotspot(), Point::from((0, 0)));
+    }
+
+    #[test]
+    fn animated_cursor_cycles_frames() {
+        let mut cursor = CursorElement::default();
+        let frame = |hotspot: i32, delay: u64| CursorFrame {
+            buffer: MemoryRenderBuffer::default(),
+            hotspot: Point::from((hotspot as f64, hotspot as f64)),
+            delay: Duration::from_millis(delay),
+        };
+        cursor.set_named_frames(CursorIcon::Wait, [frame(1, 100), frame(2, 50)]);
+        cursor.set_status(CursorImageStatus::Named(CursorIcon::Wait));
+
+        cursor.set_time(Duration::from_millis(30));
+        assert_eq!(cursor.hotspot(), Point::from((1, 1)));
+        assert_eq!(cursor.next_frame_in(), Some(Duration::from_millis(70)));
+        cursor.set_time(Duration::from_millis(120));
+        assert_eq!(cursor.hotspot(), Point::from((2, 2)));
+        // the animation loops
+        cursor.set_time(Duration::from_millis(160));
+        assert_eq!(cursor.hotspot(), Point::from((1, 1)));
+        assert_eq!(cursor.next_frame_in(), Some(Duration::from_millis(90)));
+
+        cursor.set_status(CursorImageStatus::Hidden);
+        assert_eq!(cursor.next_frame_in(), None);
+    }
+}
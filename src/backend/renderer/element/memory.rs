@@ -220,7 +220,10 @@ use crate::{
     backend::{
         allocator::{format::get_bpp, Fourcc},
         renderer::{
-            utils::{CommitCounter, DamageBag, DamageSet, DamageSnapshot, OpaqueRegions},
+            utils::{
+                snap_to_pixels, snapped_size, CommitCounter, DamageBag, DamageSet, DamageSnapshot,
+                OpaqueRegions, PixelRounding,
+            },
             Frame, ImportMem, Renderer,
         },
     },
@@ -276,6 +279,21 @@ impl MemoryBuffer {
         }
     }
 
+    /// Create a new memory buffer taking ownership of already allocated pixel data
+    ///
+    /// This avoids copying the pixels, e.g. when they were drawn by a software rasterizer.
+    pub fn from_vec(mem: Vec<u8>, format: Fourcc, size: impl Into<Size<i32, Buffer>>) -> Self {
+        let size = size.into();
+        let stride = size.w * (get_bpp(format).expect("Format with unknown bits per pixel") / 8) as i32;
+        assert!(mem.len() >= (stride * size.h) as usize);
+        Self {
+            mem: Arc::new(mem),
+            format,
+            size,
+            stride,
+        }
+    }
+
     /// Get the size of this buffer
     pub fn size(&self) -> Size<i32, Buffer> {
         self.size
@@ -292,17 +310,20 @@ impl MemoryBuffer {
     }
 
     /// Resize this buffer to the size specified
+    ///
+    /// Returns `true` if the size changed, the content of the buffer is undefined afterwards.
     pub fn resize(&mut self, size: impl Into<Size<i32, Buffer>>) -> bool {
         let size = size.into();
+        if self.size == size {
+            return false;
+        }
+
         let stride = size.w * (get_bpp(self.format).expect("Format with unknown bits per pixel") / 8) as i32;
         let mem = Arc::make_mut(&mut self.mem);
-        let mem_size = (stride * size.h) as usize;
-        if mem.len() != mem_size {
-            mem.resize(mem_size, 0);
-            true
-        } else {
-            false
-        }
+        mem.resize((stride * size.h) as usize, 0);
+        self.size = size;
+        self.stride = stride;
+        true
     }
 }
 
@@ -628,9 +649,7 @@ impl<R: Renderer> MemoryRenderBufferRenderElement<R> {
     }
 
     fn physical_size(&self, scale: Scale<f64>) -> Size<i32, Physical> {
-        ((self.size.to_f64().to_physical(scale).to_point() + self.location).to_i32_round()
-            - self.location.to_i32_round())
-        .to_size()
+        snapped_size(self.location, self.size.to_f64().to_physical(scale))
     }
 
     fn scale(&self) -> Scale<f64> {
@@ -666,6 +685,7 @@ impl<R: Renderer> Element for MemoryRenderBufferRenderElement<R> {
 
     fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
         let physical_size = self.physical_size(scale);
+        let bounds = Rectangle::from_loc_and_size(Point::default(), physical_size);
         let logical_scale = self.scale();
 
         self.damage
@@ -686,10 +706,9 @@ impl<R: Renderer> Element for MemoryRenderBufferRenderElement<R> {
                                 rect.upscale(logical_scale)
                             })
                             .map(|rect| {
-                                let surface_scale =
-                                    physical_size.to_f64() / self.size.to_f64().to_physical(scale);
-                                rect.to_physical_precise_up(surface_scale * scale)
+                                snap_to_pixels(self.location, rect.to_physical(scale), PixelRounding::Outward)
                             })
+                            .and_then(|rect| rect.intersection(bounds))
                     })
                     .collect::<DamageSet<_, _>>()
             })
@@ -703,7 +722,6 @@ impl<R: Renderer> Element for MemoryRenderBufferRenderElement<R> {
             return OpaqueRegions::default();
         }
 
-        let physical_size = self.physical_size(scale);
         let logical_scale = self.scale();
 
         self.opaque_regions
@@ -720,10 +738,8 @@ impl<R: Renderer> Element for MemoryRenderBufferRenderElement<R> {
                         rect.loc -= self.src.loc;
                         rect.upscale(logical_scale)
                     })
-                    .map(|rect| {
-                        let surface_scale = physical_size.to_f64() / self.size.to_f64().to_physical(scale);
-                        rect.to_physical_precise_up(surface_scale * scale)
-                    })
+                    .map(|rect| snap_to_pixels(self.location, rect.to_physical(scale), PixelRounding::Inward))
+                    .filter(|rect| !rect.is_empty())
             })
             .collect::<OpaqueRegions<_, _>>()
     }
@@ -768,3 +784,25 @@ where
         Some(UnderlyingStorage::Memory(&self.buffer))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryBuffer;
+    use crate::{backend::allocator::Fourcc, utils::Size};
+
+    #[test]
+    fn resizing_memory_buffer_updates_layout() {
+        let mut buffer = MemoryBuffer::from_vec(vec![0xff; 4 * 4 * 4], Fourcc::Argb8888, (4, 4));
+        assert_eq!(buffer.stride(), 16);
+
+        // the amount of memory stays the same, but the layout changes
+        assert!(buffer.resize((8, 2)));
+        assert_eq!(buffer.size(), Size::from((8, 2)));
+        assert_eq!(buffer.stride(), 32);
+        assert_eq!(buffer.len(), 8 * 2 * 4);
+        assert!(!buffer.resize((8, 2)));
+
+        assert!(buffer.resize((16, 16)));
+        assert_eq!(buffer.len(), 16 * 16 * 4);
+    }
+}
@@ -12,6 +12,7 @@
 //! - [`texture`] - Texture based render element
 //! - [`surface`] - Wayland surface render element
 //! - [`solid`] - Solid color render element
+//! - [`cursor`] - Pointer cursor render elements
 //!
 //! The [`render_elements!`] macro provides an easy way to aggregate multiple different [RenderElement]s
 //! into a single enum.
@@ -36,6 +37,10 @@ use super::{
     Renderer,
 };
 
+#[cfg(any(feature = "renderer_gl", feature = "renderer_pixman"))]
+pub mod border;
+#[cfg(feature = "wayland_frontend")]
+pub mod cursor;
 pub mod memory;
 pub mod solid;
 #[cfg(feature = "wayland_frontend")]
@@ -173,6 +178,7 @@ impl PrimaryScanoutOutput {
     /// provided output and the element is present according to the states
     /// the provided compare function will be run to select the preferred
     /// output. Smithay provides a [`default`](`default_primary_scanout_output_compare`)
+    /// and a [`visible area`](`visible_area_primary_scanout_output_compare`) based
     /// compare function for convenience.
     ///
     /// Returns the updated primary scan-out output if any
@@ -234,14 +240,13 @@ impl PrimaryScanoutOutput {
                 .as_ref()
                 .map(|(output, state)| (output.upgrade().unwrap(), state))
                 .unwrap();
-            let updated = compare(
-                &current_output,
-                current_state,
-                output,
-                element_state.as_ref().unwrap(),
-            )
-            .clone();
-            *primary_scanout_output = Some((updated.downgrade(), element_state.unwrap()));
+            let next_state = element_state.unwrap();
+            let updated = compare(&current_output, current_state, output, &next_state).clone();
+            // Only replace the stored state if the output changed, otherwise
+            // the next comparison would use the state of the wrong output
+            if updated == *output {
+                *primary_scanout_output = Some((updated.downgrade(), next_state));
+            }
             return Some(updated);
         }
 
@@ -298,6 +303,25 @@ pub fn default_primary_scanout_output_compare<'a>(
     }
 }
 
+/// Primary scan-out selection based on the visible area only
+///
+/// This will prefer the output showing the larger portion of the element,
+/// regardless of the refresh rate. Surfaces spanning multiple outputs will
+/// thereby be throttled to the output where most of their area is.
+/// On equal visible area the current output is kept.
+pub fn visible_area_primary_scanout_output_compare<'a>(
+    current_output: &'a Output,
+    current_state: &RenderElementState,
+    next_output: &'a Output,
+    next_state: &RenderElementState,
+) -> &'a Output {
+    if next_state.visible_area > current_state.visible_area {
+        next_output
+    } else {
+        current_output
+    }
+}
+
 /// Holds the states for a set of [`RenderElement`]s
 #[derive(Default, Debug, Clone)]
 pub struct RenderElementStates {
@@ -369,6 +393,13 @@ pub trait Element {
     fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
         OpaqueRegions::default()
     }
+    /// Get the region of the content below this element the element samples from relative to the element
+    ///
+    /// Elements reading back the already rendered content, like a blur, have to be redrawn whenever
+    /// anything changes in this region. The region may extend beyond the geometry of the element.
+    fn backdrop_region(&self, _scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        None
+    }
     /// Returns an alpha value the element should be drawn with regardless of any
     /// already encoded alpha in it's underlying representation.
     fn alpha(&self) -> f32 {
@@ -453,6 +484,10 @@ where
         (*self).opaque_regions(scale)
     }
 
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        (*self).backdrop_region(scale)
+    }
+
     fn alpha(&self) -> f32 {
         (*self).alpha()
     }
@@ -484,6 +519,77 @@ where
     }
 }
 
+impl<E> Element for Box<E>
+where
+    E: Element + ?Sized,
+{
+    fn id(&self) -> &Id {
+        (**self).id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        (**self).current_commit()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        (**self).location(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, BufferCoords> {
+        (**self).src()
+    }
+
+    fn transform(&self) -> Transform {
+        (**self).transform()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        (**self).geometry(scale)
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        (**self).damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        (**self).opaque_regions(scale)
+    }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        (**self).backdrop_region(scale)
+    }
+
+    fn alpha(&self) -> f32 {
+        (**self).alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        (**self).kind()
+    }
+}
+
+impl<R, E> RenderElement<R> for Box<E>
+where
+    R: Renderer,
+    E: RenderElement<R> + ?Sized,
+{
+    #[inline]
+    fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        (**self).underlying_storage(renderer)
+    }
+
+    fn draw(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        (**self).draw(frame, src, dst, damage, opaque_regions)
+    }
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! render_elements_internal {
@@ -729,6 +835,19 @@ macro_rules! render_elements_internal {
             }
         }
 
+        fn backdrop_region(&self, scale: $crate::utils::Scale<f64>) -> Option<$crate::utils::Rectangle<i32, $crate::utils::Physical>> {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call backdrop_region; x, scale)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+
         fn alpha(&self) -> f32 {
             match self {
                 $(
@@ -1459,6 +1578,10 @@ where
         self.0.opaque_regions(scale)
     }
 
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.0.backdrop_region(scale)
+    }
+
     fn alpha(&self) -> f32 {
         self.0.alpha()
     }
@@ -165,6 +165,7 @@ pub struct SolidColorBuffer {
     size: Size<i32, Logical>,
     commit: CommitCounter,
     color: Color32F,
+    corner_radius: f32,
 }
 
 impl Default for SolidColorBuffer {
@@ -174,6 +175,7 @@ impl Default for SolidColorBuffer {
             size: Default::default(),
             commit: Default::default(),
             color: Default::default(),
+            corner_radius: 0.0,
         }
     }
 }
@@ -186,6 +188,7 @@ impl SolidColorBuffer {
             color: color.into(),
             commit: CommitCounter::default(),
             size: size.into(),
+            corner_radius: 0.0,
         }
     }
 
@@ -228,6 +231,22 @@ impl SolidColorBuffer {
     pub fn color(&self) -> Color32F {
         self.color
     }
+
+    /// Set the radius of the corners of this buffer in logical coordinates
+    ///
+    /// Note: If the radius matches the current radius this will do nothing
+    pub fn set_corner_radius(&mut self, radius: f32) {
+        let radius = radius.max(0.0);
+        if radius != self.corner_radius {
+            self.corner_radius = radius;
+            self.commit.increment();
+        }
+    }
+
+    /// Get the current corner radius of this buffer
+    pub fn corner_radius(&self) -> f32 {
+        self.corner_radius
+    }
 }
 
 /// [`Element`] to render a solid color
@@ -239,6 +258,8 @@ pub struct SolidColorRenderElement {
     opaque_regions: Vec<Rectangle<i32, Physical>>,
     commit: CommitCounter,
     color: Color32F,
+    // rects covering the rounded shape relative to the geometry, if the corners are rounded
+    shape: Option<Vec<Rectangle<i32, Physical>>>,
     kind: Kind,
 }
 
@@ -251,9 +272,11 @@ impl SolidColorRenderElement {
         alpha: f32,
         kind: Kind,
     ) -> Self {
+        let scale = scale.into();
         let geo = Rectangle::from_loc_and_size(location, buffer.size.to_physical_precise_round(scale));
         let color = buffer.color * alpha;
         Self::new(buffer.id.clone(), geo, buffer.commit, color, kind)
+            .with_corner_radius(buffer.corner_radius * scale.x as f32)
     }
 
     /// Create a new solid color render element with the specified geometry and color
@@ -281,16 +304,76 @@ impl SolidColorRenderElement {
             opaque_regions,
             commit: commit.into(),
             color,
+            shape: None,
             kind,
         }
     }
 
+    /// Round the corners of this element with the given radius in physical coordinates
+    ///
+    /// The corners are approximated by rectangles, so this works with any renderer
+    /// but the corners are not anti-aliased.
+    pub fn with_corner_radius(mut self, radius: f32) -> Self {
+        let radius = radius
+            .min(self.geometry.size.w as f32 / 2.0)
+            .min(self.geometry.size.h as f32 / 2.0)
+            .floor() as i32;
+        if radius <= 0 {
+            return self;
+        }
+
+        let size = self.geometry.size;
+        if self.color.is_opaque() {
+            let (inner_w, inner_h) = ((size.w - 2 * radius).max(0), (size.h - 2 * radius).max(0));
+            self.opaque_regions = vec![
+                Rectangle::from_loc_and_size((0, radius), (size.w, inner_h)),
+                Rectangle::from_loc_and_size((radius, 0), (inner_w, radius)),
+                Rectangle::from_loc_and_size((radius, size.h - radius), (inner_w, radius)),
+            ];
+            self.opaque_regions.retain(|rect| !rect.is_empty());
+        }
+        self.shape = Some(rounded_shape(size, radius));
+        self
+    }
+
     /// Get the current color of this element
     pub fn color(&self) -> Color32F {
         self.color
     }
 }
 
+// Covers a rectangle of `size` with rounded corners of `radius` with disjoint rects,
+// one rect for every run of rows with the same inset in the corners
+fn rounded_shape(size: Size<i32, Physical>, radius: i32) -> Vec<Rectangle<i32, Physical>> {
+    let mut shape = vec![Rectangle::from_loc_and_size(
+        (0, radius),
+        (size.w, (size.h - 2 * radius).max(0)),
+    )];
+    let r = radius as f64;
+    let mut row = 0;
+    while row < radius {
+        let inset = |row: i32| {
+            let dy = r - row as f64 - 0.5;
+            (r - (r * r - dy * dy).max(0.0).sqrt()).round() as i32
+        };
+        let current = inset(row);
+        let mut rows = 1;
+        while row + rows < radius && inset(row + rows) == current {
+            rows += 1;
+        }
+
+        let width = (size.w - 2 * current).max(0);
+        shape.push(Rectangle::from_loc_and_size((current, row), (width, rows)));
+        shape.push(Rectangle::from_loc_and_size(
+            (current, size.h - row - rows),
+            (width, rows),
+        ));
+        row += rows;
+    }
+    shape.retain(|rect| !rect.is_empty());
+    shape
+}
+
 impl Element for SolidColorRenderElement {
     fn id(&self) -> &Id {
         &self.id
@@ -331,7 +414,16 @@ impl<R: Renderer> RenderElement<R> for SolidColorRenderElement {
         damage: &[Rectangle<i32, Physical>],
         _opaque_regions: &[Rectangle<i32, Physical>],
     ) -> Result<(), <R as Renderer>::Error> {
-        frame.draw_solid(dst, damage, self.color)
+        match self.shape.as_ref() {
+            Some(shape) => {
+                let damage = damage
+                    .iter()
+                    .flat_map(|damage| shape.iter().filter_map(|rect| rect.intersection(*damage)))
+                    .collect::<Vec<_>>();
+                frame.draw_solid(dst, &damage, self.color)
+            }
+            None => frame.draw_solid(dst, damage, self.color),
+        }
     }
 
     #[inline]
@@ -356,3 +448,58 @@ where
         vec![SolidColorRenderElement::from_buffer(self, location, scale, alpha, Kind::Unspecified).into()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{rounded_shape, SolidColorRenderElement};
+    use crate::{
+        backend::renderer::{
+            element::{Element, Id, Kind},
+            utils::CommitCounter,
+        },
+        utils::{Physical, Point, Rectangle, Size},
+    };
+
+    #[test]
+    fn rounded_shape_excludes_corners() {
+        let size = Size::<i32, Physical>::from((40, 30));
+        let shape = rounded_shape(size, 10);
+
+        let contains = |x, y| shape.iter().any(|rect| rect.contains(Point::from((x, y))));
+        assert!(!contains(0, 0));
+        assert!(!contains(39, 29));
+        assert!(contains(20, 0));
+        assert!(contains(0, 15));
+        assert!(contains(5, 5));
+
+        // the rects are disjoint
+        let area = shape.iter().map(|rect| rect.size.w * rect.size.h).sum::<i32>();
+        let covered = (0..size.w)
+            .flat_map(|x| (0..size.h).map(move |y| (x, y)))
+            .filter(|(x, y)| contains(*x, *y))
+            .count() as i32;
+        assert_eq!(area, covered);
+    }
+
+    #[test]
+    fn corner_radius_on_odd_sizes() {
+        let geometry = Rectangle::<i32, Physical>::from_loc_and_size((0, 0), (7, 5));
+        let element = SolidColorRenderElement::new(
+            Id::new(),
+            geometry,
+            CommitCounter::default(),
+            [1.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        )
+        .with_corner_radius(100.0);
+
+        let opaque = element.opaque_regions(1.0.into());
+        assert!(opaque.iter().all(|rect| rect.size.w > 0 && rect.size.h > 0));
+        assert!(opaque.iter().any(|rect| rect.contains(Point::from((3, 2)))));
+        assert!(!opaque.iter().any(|rect| rect.contains(Point::from((0, 0)))));
+
+        let shape = element.shape.as_deref().unwrap();
+        assert!(shape.iter().all(|rect| rect.size.w >= 0 && rect.size.h >= 0));
+        assert!(shape.iter().all(|rect| geometry.contains_rect(*rect)));
+    }
+}
@@ -227,8 +227,8 @@ use wayland_server::protocol::wl_surface;
 use crate::{
     backend::renderer::{
         utils::{
-            Buffer, DamageSet, DamageSnapshot, OpaqueRegions, RendererSurfaceState,
-            RendererSurfaceStateUserData, SurfaceView,
+            snap_to_pixels, snapped_size, Buffer, DamageSet, DamageSnapshot, OpaqueRegions, PixelRounding,
+            RendererSurfaceState, RendererSurfaceStateUserData, SurfaceView,
         },
         Color32F, Frame, ImportAll, Renderer, Texture,
     },
@@ -417,9 +417,7 @@ impl<R: Renderer + ImportAll> WaylandSurfaceRenderElement<R> {
     }
 
     fn size(&self, scale: impl Into<Scale<f64>>) -> Size<i32, Physical> {
-        ((self.view.dst.to_f64().to_physical(scale).to_point() + self.location).to_i32_round()
-            - self.location.to_i32_round())
-        .to_size()
+        snapped_size(self.location, self.view.dst.to_f64().to_physical(scale))
     }
 
     /// Get the buffer dimensions in logical coordinates
@@ -465,7 +463,7 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
     }
 
     fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
-        let dst_size = self.size(scale);
+        let bounds = Rectangle::from_loc_and_size((0, 0), self.size(scale));
         self.damage
             .damage_since(commit)
             .unwrap_or_else(|| {
@@ -486,17 +484,13 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
                     // then crop by the surface view (viewporter for example could define a src rect)
                     .intersection(self.view.src)
                     // move and scale the cropped rect (viewporter could define a dst size)
-                    .map(|rect| self.view.rect_to_global(rect).to_i32_up::<i32>())
-                    // now bring the damage to physical space
+                    .map(|rect| self.view.rect_to_global(rect))
+                    // now bring the damage to physical space, rounding only once at the
+                    // fractional location of the surface to avoid over-damage
                     .map(|rect| {
-                        // We calculate the scale between to rounded
-                        // surface size and the scaled surface size
-                        // and use it to scale the damage to the rounded
-                        // surface size by multiplying the output scale
-                        // with the result.
-                        let surface_scale = dst_size.to_f64() / self.view.dst.to_f64().to_physical(scale);
-                        rect.to_physical_precise_up(surface_scale * scale)
+                        snap_to_pixels(self.location, rect.to_physical(scale), PixelRounding::Outward)
                     })
+                    .and_then(|rect| rect.intersection(bounds))
             })
             .collect::<DamageSet<_, _>>()
     }
@@ -509,12 +503,13 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
         self.opaque_regions
             .iter()
             .map(|r| {
-                let loc = r.loc.to_physical_precise_round(scale);
-                let size = ((r.size.to_f64().to_physical(scale).to_point() + self.location).to_i32_round()
-                    - self.location.to_i32_round())
-                .to_size();
-                Rectangle::from_loc_and_size(loc, size)
+                snap_to_pixels(
+                    self.location,
+                    r.to_f64().to_physical(scale),
+                    PixelRounding::Inward,
+                )
             })
+            .filter(|r| !r.is_empty())
             .collect::<OpaqueRegions<_, _>>()
     }
 
@@ -412,7 +412,10 @@ use crate::{
     backend::{
         allocator::Fourcc,
         renderer::{
-            utils::{DamageBag, DamageSet, DamageSnapshot, OpaqueRegions},
+            utils::{
+                snap_to_pixels, snapped_size, DamageBag, DamageSet, DamageSnapshot, OpaqueRegions,
+                PixelRounding,
+            },
             Frame, ImportMem, Renderer, Texture,
         },
     },
@@ -782,10 +785,7 @@ impl<T: Texture> TextureRenderElement<T> {
     }
 
     fn physical_size(&self, scale: Scale<f64>) -> Size<i32, Physical> {
-        let logical_size = self.logical_size();
-        ((logical_size.to_f64().to_physical(scale).to_point() + self.location).to_i32_round()
-            - self.location.to_i32_round())
-        .to_size()
+        snapped_size(self.location, self.logical_size().to_f64().to_physical(scale))
     }
 
     fn src(&self) -> Rectangle<f64, Logical> {
@@ -839,19 +839,18 @@ where
     fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
         let src = self.src();
         let texture_size = self.texture.size();
-        let physical_size = self.physical_size(scale);
-        let logical_size = self.logical_size();
+        let bounds = Rectangle::from_loc_and_size((0, 0), self.physical_size(scale));
         self.damage_since(commit)
             .into_iter()
             .filter_map(|rect| {
                 rect.to_f64()
                     .to_logical(self.scale as f64, self.transform, &texture_size.to_f64())
                     .intersection(src)
-                    .map(|rect| self.rect_to_global(rect).to_i32_up::<i32>())
                     .map(|rect| {
-                        let surface_scale = physical_size.to_f64() / logical_size.to_f64().to_physical(scale);
-                        rect.to_physical_precise_up(surface_scale * scale)
+                        let rect = self.rect_to_global(rect).to_physical(scale);
+                        snap_to_pixels(self.location, rect, PixelRounding::Outward)
                     })
+                    .and_then(|rect| rect.intersection(bounds))
             })
             .collect::<DamageSet<_, _>>()
     }
@@ -862,8 +861,6 @@ where
         }
 
         let src = self.src();
-        let physical_size = self.physical_size(scale);
-        let logical_size = self.logical_size();
         self.opaque_regions
             .as_ref()
             .map(|r| {
@@ -871,12 +868,11 @@ where
                     .filter_map(|rect| {
                         rect.to_f64()
                             .intersection(src)
-                            .map(|rect| self.rect_to_global(rect).to_i32_up::<i32>())
                             .map(|rect| {
-                                let surface_scale =
-                                    physical_size.to_f64() / logical_size.to_f64().to_physical(scale);
-                                rect.to_physical_precise_up(surface_scale * scale)
+                                let rect = self.rect_to_global(rect).to_physical(scale);
+                                snap_to_pixels(self.location, rect, PixelRounding::Inward)
                             })
+                            .filter(|rect| !rect.is_empty())
                     })
                     .collect::<OpaqueRegions<_, _>>()
             })
@@ -1,16 +1,18 @@
 //! Utilities and helpers around the `Element` trait.
 
+use std::collections::VecDeque;
+
 use crate::{
     backend::renderer::{
         element::{AsRenderElements, Element, Id, Kind, RenderElement, UnderlyingStorage},
-        utils::{DamageSet, OpaqueRegions},
+        utils::{snap_to_pixels, CommitCounter, DamageSet, OpaqueRegions, PixelRounding},
         Renderer,
     },
     utils::{Buffer, Physical, Point, Rectangle, Scale},
 };
 
 /// A element that allows to re-scale another element
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RescaleRenderElement<E> {
     element: E,
     origin: Point<i32, Physical>,
@@ -18,6 +20,13 @@ pub struct RescaleRenderElement<E> {
 }
 
 impl<E: Element> RescaleRenderElement<E> {
+    // the fractional location of the scaled element relative to the origin
+    fn scaled_location(&self, scale: Scale<f64>) -> Point<f64, Physical> {
+        (self.element.geometry(scale).loc - self.origin)
+            .to_f64()
+            .upscale(self.scale)
+    }
+
     /// Create a new re-scale element for an existing element
     ///
     /// The origin can be used to scale the element geometry relative to a [`Point`].
@@ -30,6 +39,21 @@ impl<E: Element> RescaleRenderElement<E> {
             scale: scale.into(),
         }
     }
+
+    /// Returns the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns the origin the element is scaled around
+    pub fn origin(&self) -> Point<i32, Physical> {
+        self.origin
+    }
+
+    /// Returns the scale applied to the element
+    pub fn scale(&self) -> Scale<f64> {
+        self.scale
+    }
 }
 
 impl<E: Element> Element for RescaleRenderElement<E> {
@@ -52,8 +76,8 @@ impl<E: Element> Element for RescaleRenderElement<E> {
         let mut element_geometry = self.element.geometry(scale);
         // First we make the element relative to the origin
         element_geometry.loc -= self.origin;
-        // Then we scale it by our scale
-        element_geometry = element_geometry.to_f64().upscale(self.scale).to_i32_round();
+        // Then we scale it by our scale, rounding the edges to keep adjacent elements adjacent
+        element_geometry = element_geometry.to_f64().upscale(self.scale).to_i32_round_edges();
         // At last we move it back to the origin
         element_geometry.loc += self.origin;
         element_geometry
@@ -68,21 +92,42 @@ impl<E: Element> Element for RescaleRenderElement<E> {
         scale: crate::utils::Scale<f64>,
         commit: Option<crate::backend::renderer::utils::CommitCounter>,
     ) -> DamageSet<i32, Physical> {
+        let location = self.scaled_location(scale);
         self.element
             .damage_since(scale, commit)
             .into_iter()
-            .map(|rect| rect.to_f64().upscale(self.scale).to_i32_up())
+            .map(|rect| {
+                snap_to_pixels(
+                    location,
+                    rect.to_f64().upscale(self.scale),
+                    PixelRounding::Outward,
+                )
+            })
             .collect::<DamageSet<_, _>>()
     }
 
     fn opaque_regions(&self, scale: crate::utils::Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        let location = self.scaled_location(scale);
         self.element
             .opaque_regions(scale)
             .into_iter()
-            .map(|rect| rect.to_f64().upscale(self.scale).to_i32_round())
+            // rounding inwards keeps partially covered pixels at the edges out of the opaque regions
+            .map(|rect| snap_to_pixels(location, rect.to_f64().upscale(self.scale), PixelRounding::Inward))
+            .filter(|rect| !rect.is_empty())
             .collect::<OpaqueRegions<_, _>>()
     }
 
+    fn backdrop_region(&self, scale: crate::utils::Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        let location = self.scaled_location(scale);
+        self.element.backdrop_region(scale).map(|rect| {
+            snap_to_pixels(
+                location,
+                rect.to_f64().upscale(self.scale),
+                PixelRounding::Outward,
+            )
+        })
+    }
+
     fn alpha(&self) -> f32 {
         self.element.alpha()
     }
@@ -111,7 +156,7 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RescaleRenderElement
 }
 
 /// A element that allows to crop another element
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CropRenderElement<E> {
     element: E,
     src: Rectangle<f64, Buffer>,
@@ -181,6 +226,16 @@ impl<E: Element> CropRenderElement<E> {
         }
     }
 
+    /// Returns the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns the rectangle the element is cropped to
+    pub fn crop_rect(&self) -> Rectangle<i32, Physical> {
+        self.crop_rect
+    }
+
     fn element_crop_rect(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
         let element_geometry = self.element.geometry(scale);
 
@@ -267,6 +322,17 @@ impl<E: Element> Element for CropRenderElement<E> {
         }
     }
 
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        let element_crop_rect = self.element_crop_rect(scale)?;
+        self.element
+            .backdrop_region(scale)
+            .and_then(|rect| rect.intersection(element_crop_rect))
+            .map(|mut rect| {
+                rect.loc -= element_crop_rect.loc;
+                rect
+            })
+    }
+
     fn alpha(&self) -> f32 {
         self.element.alpha()
     }
@@ -294,6 +360,325 @@ impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for CropRenderElement<E>
     }
 }
 
+// Number of clip changes kept for calculating the damage of older frames
+const MAX_CLIP_HISTORY: usize = 8;
+
+/// State of a clipped or masked element kept between frames
+///
+/// The state remembers the region an element was last clipped to, so that changing the region
+/// damages the parts of the element which got revealed or hidden, e.g. during reveal animations.
+/// Every wrapped element needs its own state, which has to be passed whenever the element is
+/// created, see [`ClipRenderElement::from_element`].
+#[derive(Debug, Default)]
+pub struct ClipState {
+    commit: CommitCounter,
+    element: Option<(Id, CommitCounter)>,
+    region: Vec<Rectangle<i32, Physical>>,
+    // latest change first
+    history: VecDeque<ClipChange>,
+}
+
+#[derive(Debug, Clone)]
+struct ClipChange {
+    // commit of the wrapped element before the change
+    element_commit: CommitCounter,
+    // changed region relative to the origin of the element, `None` for the whole element
+    damage: Option<Vec<Rectangle<i32, Physical>>>,
+}
+
+/// Changes of a [`ClipState`] at the time an element was created
+#[derive(Debug, Clone)]
+pub(crate) struct ClipSnapshot {
+    commit: CommitCounter,
+    history: Vec<ClipChange>,
+}
+
+impl ClipState {
+    /// Creates a new state for an element that was not clipped before
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the region the element is clipped to for the next frame
+    ///
+    /// If `replaced` is set, the contents of the region changed, so the old and new regions
+    /// are damaged entirely instead of just their difference.
+    pub(crate) fn update(
+        &mut self,
+        element: &impl Element,
+        region: &[Rectangle<i32, Physical>],
+        replaced: bool,
+    ) -> ClipSnapshot {
+        let element_commit = element.current_commit();
+        let change = match &self.element {
+            // the damage tracker has to redraw a new element anyway
+            Some((id, _)) if id != element.id() => {
+                self.history.clear();
+                Some(None)
+            }
+            None => Some(None),
+            Some(_) if replaced => Some(Some(
+                self.region
+                    .iter()
+                    .chain(region.iter())
+                    .copied()
+                    .collect::<Vec<_>>(),
+            )),
+            Some(_) => {
+                let hidden = self
+                    .region
+                    .iter()
+                    .flat_map(|rect| rect.subtract_rects(region.iter().copied()));
+                let revealed = region
+                    .iter()
+                    .flat_map(|rect| rect.subtract_rects(self.region.iter().copied()));
+                let damage = hidden.chain(revealed).collect::<Vec<_>>();
+                let commit_changed = self.element.as_ref().map(|(_, commit)| *commit) != Some(element_commit);
+                (!damage.is_empty() || commit_changed).then_some(Some(damage))
+            }
+        };
+
+        if let Some(damage) = change {
+            self.history.push_front(ClipChange {
+                element_commit: self
+                    .element
+                    .as_ref()
+                    .map(|(_, commit)| *commit)
+                    .unwrap_or_default(),
+                damage,
+            });
+            self.history.truncate(MAX_CLIP_HISTORY);
+            self.commit.increment();
+        }
+        self.element = Some((element.id().clone(), element_commit));
+        self.region = region.to_vec();
+
+        ClipSnapshot {
+            commit: self.commit,
+            history: self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+impl ClipSnapshot {
+    pub(crate) fn current_commit(&self) -> CommitCounter {
+        self.commit
+    }
+
+    /// Damage of the wrapped element restricted to `clip` and the damage of changed regions,
+    /// relative to the element geometry
+    pub(crate) fn damage_since<E: Element>(
+        &self,
+        element: &E,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+        clip: impl Fn(Rectangle<i32, Physical>) -> Vec<Rectangle<i32, Physical>>,
+    ) -> DamageSet<i32, Physical> {
+        let geometry = element.geometry(scale);
+        let full = || DamageSet::from_slice(&[Rectangle::from_loc_and_size((0, 0), geometry.size)]);
+        if commit.is_none() {
+            // nothing was drawn before, so only the visible part needs to be drawn
+            return full().into_iter().flat_map(clip).collect();
+        }
+        let Some(distance) = self.commit.distance(commit) else {
+            return full();
+        };
+        if distance == 0 {
+            return DamageSet::default();
+        }
+        let Some(changes) = self.history.get(..distance) else {
+            return full();
+        };
+
+        let mut damage = Vec::new();
+        for change in changes {
+            let Some(rects) = change.damage.as_ref() else {
+                return full();
+            };
+            damage.extend(rects.iter().filter_map(|rect| {
+                let mut rect = rect.intersection(geometry)?;
+                rect.loc -= geometry.loc;
+                Some(rect)
+            }));
+        }
+        let element_commit = changes[changes.len() - 1].element_commit;
+        damage.extend(
+            element
+                .damage_since(scale, Some(element_commit))
+                .into_iter()
+                .flat_map(clip),
+        );
+        damage.into_iter().collect()
+    }
+}
+
+/// A element that allows to clip another element against an arbitrary region
+///
+/// Unlike [`CropRenderElement`] the region may consist of multiple rectangles,
+/// e.g. to render shaped windows. Everything outside of the region is not drawn,
+/// which works with any renderer by restricting the damage passed to the element.
+///
+/// The geometry of the clipped element is left untouched. Changes of the region
+/// are tracked by a [`ClipState`] and damage the parts of the element that got
+/// revealed or hidden.
+#[derive(Debug, Clone)]
+pub struct ClipRenderElement<E> {
+    element: E,
+    region: Vec<Rectangle<i32, Physical>>,
+    snapshot: ClipSnapshot,
+}
+
+impl<E: Element> ClipRenderElement<E> {
+    /// Create a clipping render element for an existing element
+    ///
+    /// The region is expected to be relative to the same origin the element is relative to.
+    /// `state` has to be kept alongside the element to track changes of the region.
+    pub fn from_element(
+        element: E,
+        region: impl IntoIterator<Item = Rectangle<i32, Physical>>,
+        state: &mut ClipState,
+    ) -> Self {
+        // overlapping rectangles would result in drawing parts of the element twice
+        let region = region.into_iter().fold(Vec::new(), |mut region, rect| {
+            let parts = rect.subtract_rects(region.iter().copied());
+            region.extend(parts);
+            region
+        });
+        let snapshot = state.update(&element, &region, false);
+        ClipRenderElement {
+            element,
+            region,
+            snapshot,
+        }
+    }
+
+    /// Returns the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns the region the element is clipped to
+    pub fn region(&self) -> &[Rectangle<i32, Physical>] {
+        &self.region
+    }
+
+    // the region relative to the element geometry
+    fn element_region(&self, scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
+        let element_geometry = self.element.geometry(scale);
+        self.region
+            .iter()
+            .filter_map(|rect| rect.intersection(element_geometry))
+            .map(|mut rect| {
+                rect.loc -= element_geometry.loc;
+                rect
+            })
+            .collect()
+    }
+}
+
+fn clip_rects<'a, I>(
+    rects: I,
+    region: &'a [Rectangle<i32, Physical>],
+) -> impl Iterator<Item = Rectangle<i32, Physical>> + 'a
+where
+    I: IntoIterator<Item = Rectangle<i32, Physical>>,
+    I::IntoIter: 'a,
+{
+    rects
+        .into_iter()
+        .flat_map(move |rect| region.iter().filter_map(move |clip| clip.intersection(rect)))
+}
+
+impl<E: Element> Element for ClipRenderElement<E> {
+    fn id(&self) -> &Id {
+        self.element.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.snapshot.current_commit()
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.element.src()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.element.location(scale)
+    }
+
+    fn transform(&self) -> crate::utils::Transform {
+        self.element.transform()
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        let region = self.element_region(scale);
+        self.snapshot.damage_since(&self.element, scale, commit, |rect| {
+            clip_rects([rect], &region).collect()
+        })
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        let region = self.element_region(scale);
+        clip_rects(self.element.opaque_regions(scale), &region).collect()
+    }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        let bounds = self
+            .element_region(scale)
+            .into_iter()
+            .reduce(|bounds, rect| bounds.merge(rect))?;
+        self.element
+            .backdrop_region(scale)
+            .and_then(|rect| rect.intersection(bounds))
+    }
+
+    fn alpha(&self) -> f32 {
+        self.element.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.element.kind()
+    }
+}
+
+impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for ClipRenderElement<E> {
+    fn draw(
+        &self,
+        frame: &mut <R as Renderer>::Frame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), <R as Renderer>::Error> {
+        // the damage is relative to the destination of the element
+        let region = self
+            .region
+            .iter()
+            .map(|rect| {
+                let mut rect = *rect;
+                rect.loc -= dst.loc;
+                rect
+            })
+            .collect::<Vec<_>>();
+        let damage = clip_rects(damage.iter().copied(), &region).collect::<Vec<_>>();
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        self.element.draw(frame, src, dst, &damage, opaque_regions)
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        // The clipping can not be applied during direct scan-out
+        None
+    }
+}
+
 /// Defines how the location parameter should apply in [`RelocateRenderElement::from_element`]
 #[derive(Debug, Copy, Clone)]
 pub enum Relocate {
@@ -304,7 +689,7 @@ pub enum Relocate {
 }
 
 /// A element that allows to offset the location of an existing element
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RelocateRenderElement<E> {
     element: E,
     relocate: Relocate,
@@ -322,6 +707,11 @@ impl<E: Element> RelocateRenderElement<E> {
             relocate,
         }
     }
+
+    /// Returns the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
 }
 
 impl<E: Element> Element for RelocateRenderElement<E> {
@@ -369,6 +759,10 @@ impl<E: Element> Element for RelocateRenderElement<E> {
         self.element.opaque_regions(scale)
     }
 
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.element.backdrop_region(scale)
+    }
+
     fn alpha(&self) -> f32 {
         self.element.alpha()
     }
@@ -568,3 +962,205 @@ where
         .map(move |e| RelocateRenderElement::from_element(e, offset, Relocate::Relative))
         .filter_map(move |e| CropRenderElement::from_element(e, scale, constrain))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipRenderElement, ClipState, CropRenderElement, RescaleRenderElement};
+    use crate::{
+        backend::renderer::{
+            element::{Element, Id},
+            utils::{CommitCounter, OpaqueRegions},
+        },
+        utils::{Buffer, Physical, Rectangle, Scale},
+    };
+
+    struct TestElement {
+        id: Id,
+        geometry: Rectangle<i32, Physical>,
+    }
+
+    impl Element for TestElement {
+        fn id(&self) -> &Id {
+            &self.id
+        }
+
+        fn current_commit(&self) -> CommitCounter {
+            CommitCounter::default()
+        }
+
+        fn src(&self) -> Rectangle<f64, Buffer> {
+            Rectangle::from_loc_and_size((0.0, 0.0), (10.0, 10.0))
+        }
+
+        fn geometry(&self, _scale: Scale<f64>) -> Rectangle<i32, Physical> {
+            self.geometry
+        }
+
+        fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+            OpaqueRegions::from_slice(&[Rectangle::from_loc_and_size((1, 1), (3, 3))])
+        }
+
+        fn backdrop_region(&self, _scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+            Some(Rectangle::from_loc_and_size((0, 0), self.geometry.size))
+        }
+    }
+
+    #[test]
+    fn wrapped_regions_stay_within_element() {
+        let element = TestElement {
+            id: Id::new(),
+            geometry: Rectangle::from_loc_and_size((0, 0), (10, 10)),
+        };
+
+        // partially covered pixels must not be reported as opaque
+        let rescaled = RescaleRenderElement::from_element(element, (0, 0).into(), 0.5);
+        let opaque = rescaled
+            .opaque_regions(Scale::from(1.0))
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(opaque, vec![Rectangle::from_loc_and_size((1, 1), (1, 1))]);
+
+        // regions smaller than a pixel vanish
+        let tiny = RescaleRenderElement::from_element(
+            TestElement {
+                id: Id::new(),
+                geometry: Rectangle::from_loc_and_size((0, 0), (10, 10)),
+            },
+            (0, 0).into(),
+            0.2,
+        );
+        assert!(tiny.opaque_regions(Scale::from(1.0)).is_empty());
+
+        let cropped = CropRenderElement::from_element(
+            rescaled.element,
+            1.0,
+            Rectangle::from_loc_and_size((5, 2), (20, 4)),
+        )
+        .unwrap();
+        assert_eq!(
+            cropped.backdrop_region(Scale::from(1.0)),
+            Some(Rectangle::from_loc_and_size((0, 0), (5, 4)))
+        );
+    }
+
+    #[test]
+    fn clipped_element_is_restricted_to_region() {
+        let element = TestElement {
+            id: Id::new(),
+            geometry: Rectangle::from_loc_and_size((10, 10), (10, 10)),
+        };
+
+        // an L-shaped region, partially outside of the element
+        let clipped = ClipRenderElement::from_element(
+            element,
+            [
+                Rectangle::from_loc_and_size((0, 0), (12, 30)),
+                Rectangle::from_loc_and_size((12, 18), (20, 2)),
+            ],
+            &mut ClipState::new(),
+        );
+        let scale = Scale::from(1.0);
+        assert_eq!(
+            clipped.geometry(scale),
+            Rectangle::from_loc_and_size((10, 10), (10, 10))
+        );
+        assert_eq!(
+            clipped.damage_since(scale, None).into_iter().collect::<Vec<_>>(),
+            vec![
+                Rectangle::from_loc_and_size((0, 0), (2, 10)),
+                Rectangle::from_loc_and_size((2, 8), (8, 2)),
+            ]
+        );
+        assert_eq!(
+            clipped.opaque_regions(scale).into_iter().collect::<Vec<_>>(),
+            vec![Rectangle::from_loc_and_size((1, 1), (1, 3))]
+        );
+        assert_eq!(
+            clipped.backdrop_region(scale),
+            Some(Rectangle::from_loc_and_size((0, 0), (10, 10)))
+        );
+    }
+
+    #[test]
+    fn clip_region_changes_are_damaged() {
+        let scale = Scale::from(1.0);
+        let mut state = ClipState::new();
+        let id = Id::new();
+        let same = || TestElement {
+            id: id.clone(),
+            geometry: Rectangle::from_loc_and_size((10, 10), (10, 10)),
+        };
+
+        let first = ClipRenderElement::from_element(
+            same(),
+            [Rectangle::from_loc_and_size((10, 10), (4, 10))],
+            &mut state,
+        );
+        let commit = first.current_commit();
+
+        // the same region does not cause any damage
+        let unchanged = ClipRenderElement::from_element(
+            same(),
+            [Rectangle::from_loc_and_size((10, 10), (4, 10))],
+            &mut state,
+        );
+        assert_eq!(unchanged.current_commit(), commit);
+        assert!(unchanged.damage_since(scale, Some(commit)).is_empty());
+
+        // growing the region damages the revealed part
+        let grown = ClipRenderElement::from_element(
+            same(),
+            [Rectangle::from_loc_and_size((10, 10), (6, 10))],
+            &mut state,
+        );
+        assert_ne!(grown.current_commit(), commit);
+        assert_eq!(
+            grown
+                .damage_since(scale, Some(commit))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![Rectangle::from_loc_and_size((4, 0), (2, 10))]
+        );
+
+        // shrinking it again damages the hidden part, older frames get the union of both changes
+        let shrunk = ClipRenderElement::from_element(
+            same(),
+            [Rectangle::from_loc_and_size((10, 10), (5, 10))],
+            &mut state,
+        );
+        assert_eq!(
+            shrunk
+                .damage_since(scale, Some(grown.current_commit()))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![Rectangle::from_loc_and_size((5, 0), (1, 10))]
+        );
+        assert_eq!(
+            shrunk
+                .damage_since(scale, Some(commit))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![
+                Rectangle::from_loc_and_size((5, 0), (1, 10)),
+                Rectangle::from_loc_and_size((4, 0), (2, 10)),
+            ]
+        );
+
+        // a different element is damaged entirely
+        let other = ClipRenderElement::from_element(
+            TestElement {
+                id: Id::new(),
+                geometry: Rectangle::from_loc_and_size((10, 10), (10, 10)),
+            },
+            [Rectangle::from_loc_and_size((10, 10), (5, 10))],
+            &mut state,
+        );
+        assert_eq!(
+            other
+                .damage_since(scale, Some(shrunk.current_commit()))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![Rectangle::from_loc_and_size((0, 0), (10, 10))]
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! Blur of the content behind render elements
+//!
+//! The [`BlurElement`] blurs everything rendered below it using a dual kawase blur. It is
+//! typically placed directly below a translucent element, like a panel or a window with a
+//! translucent background, with the same geometry as that element.
+//!
+//! The blur samples the content below the element up to [`BlurElement::radius`] pixels outside
+//! of its geometry. The element reports this area as its
+//! [`backdrop_region`](crate::backend::renderer::element::Element::backdrop_region), which makes the
+//! [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker) redraw the blur
+//! whenever anything below it changes.
+//!
+//! The intermediate textures used for blurring are cached per [`EGLContext`](crate::backend::egl::EGLContext).
+//!
+//! Note that the blur can only see content rendered into the same framebuffer. Elements scanned out on
+//! an overlay or underlay plane are not part of the blurred backdrop.
+
+use std::{cell::RefCell, rc::Rc, sync::mpsc::Sender};
+
+use cgmath::Vector3;
+use tracing::trace;
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{Element, Id, Kind, RenderElement, UnderlyingStorage},
+            utils::CommitCounter,
+            Texture,
+        },
+    },
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{
+    ffi,
+    format::fourcc_to_gl_formats,
+    shaders::{link_program, BLUR_DOWN_SHADER, BLUR_SHADER, BLUR_UP_SHADER, BLUR_VERTEX_SHADER},
+    Capability, CleanupResource, GlesError, GlesFrame, GlesRenderer, GlesTexProgram, GlesTexture, Uniform,
+    UniformName, UniformType,
+};
+
+/// Maximum amount of passes of a [`BlurElement`]
+pub const MAX_BLUR_PASSES: u32 = 8;
+// Amount of differently sized sets of intermediate textures kept around
+const MAX_CACHED_BUFFERS: usize = 4;
+
+static QUAD_VERTS: [ffi::types::GLfloat; 8] = [
+    0.0, 0.0, // bottom left
+    1.0, 0.0, // bottom right
+    0.0, 1.0, // top left
+    1.0, 1.0, // top right
+];
+
+/// Render element blurring the content below it
+#[derive(Debug, Clone)]
+pub struct BlurElement {
+    id: Id,
+    commit_counter: CommitCounter,
+    area: Rectangle<i32, Logical>,
+    passes: u32,
+    offset: f32,
+    alpha: f32,
+    kind: Kind,
+}
+
+impl BlurElement {
+    /// Create a new [`BlurElement`] covering `area`
+    ///
+    /// `passes` is the amount of down- and upsampling passes, each doubling the blur radius,
+    /// and is clamped to [`MAX_BLUR_PASSES`]. `offset` is the distance of the sampled pixels in
+    /// every pass. An `alpha` below `1.0` mixes the blurred content with the unblurred content.
+    pub fn new(area: Rectangle<i32, Logical>, passes: u32, offset: f32, alpha: f32, kind: Kind) -> Self {
+        BlurElement {
+            id: Id::new(),
+            commit_counter: CommitCounter::default(),
+            area,
+            passes: passes.clamp(1, MAX_BLUR_PASSES),
+            offset: offset.max(0.0),
+            alpha,
+            kind,
+        }
+    }
+
+    /// Resize the blurred area
+    pub fn resize(&mut self, area: Rectangle<i32, Logical>) {
+        if self.area != area {
+            self.area = area;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Update the strength of the blur
+    pub fn set_blur(&mut self, passes: u32, offset: f32) {
+        let passes = passes.clamp(1, MAX_BLUR_PASSES);
+        let offset = offset.max(0.0);
+        if self.passes != passes || self.offset != offset {
+            self.passes = passes;
+            self.offset = offset;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Update the alpha value of the blurred content
+    pub fn set_alpha(&mut self, alpha: f32) {
+        if self.alpha != alpha {
+            self.alpha = alpha;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Returns the distance in physical pixels up to which content outside of the element
+    /// contributes to the blur
+    pub fn radius(&self) -> i32 {
+        blur_radius(self.passes, self.offset)
+    }
+}
+
+fn blur_radius(passes: u32, offset: f32) -> i32 {
+    (offset * (1u32 << (passes + 1)) as f32).ceil() as i32
+}
+
+impl Element for BlurElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.area
+            .to_f64()
+            .to_buffer(1.0, Transform::Normal, &self.area.size.to_f64())
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.area.to_physical_precise_round(scale)
+    }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        let radius = self.radius();
+        let size = self.geometry(scale).size;
+        Some(Rectangle::from_loc_and_size(
+            (-radius, -radius),
+            (size.w + 2 * radius, size.h + 2 * radius),
+        ))
+    }
+
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+impl RenderElement<GlesRenderer> for BlurElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let cache = blur_cache(frame.renderer);
+        let programs = blur_programs(frame.renderer, &cache)?;
+
+        let radius = self.radius();
+        let backdrop = Rectangle::from_loc_and_size(
+            dst.loc - Point::from((radius, radius)),
+            (dst.size.w + 2 * radius, dst.size.h + 2 * radius),
+        );
+        let Some(window) = window_rect(frame, backdrop) else {
+            return Ok(());
+        };
+
+        let buffers = blur_buffers(frame.renderer, &cache, window.size, self.passes)?;
+        frame.flush_batches()?;
+        unsafe { blur_backdrop(frame, &programs, &buffers, window, self.offset) };
+        // every pair of levels is rendered into once downsampling and once upsampling
+        let area = |buffer: &BlurBuffer| {
+            let size = buffer.texture.size();
+            size.w as u64 * size.h as u64
+        };
+        let pixels = buffers
+            .levels
+            .windows(2)
+            .map(|pass| area(&pass[0]) + area(&pass[1]))
+            .sum();
+        let passes = 2 * buffers.levels.len().saturating_sub(1) as u32;
+        frame.renderer.stats.record_draws(passes, pixels);
+
+        let texture = &buffers.levels[0].texture;
+        let uniforms = [
+            Uniform::new("frag_offset", (window.loc.x as f32, window.loc.y as f32)),
+            Uniform::new("frag_size", (window.size.w as f32, window.size.h as f32)),
+        ];
+        frame.render_texture_from_to(
+            texture,
+            Rectangle::from_loc_and_size((0, 0), texture.size()).to_f64(),
+            dst,
+            damage,
+            &[],
+            Transform::Normal,
+            self.alpha,
+            Some(&programs.finish),
+            &uniforms,
+        )
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+// Returns the rectangle in window coordinates of the currently bound framebuffer covering `rect`
+pub(super) fn window_rect(
+    frame: &GlesFrame<'_>,
+    rect: Rectangle<i32, Physical>,
+) -> Option<Rectangle<i32, Physical>> {
+    let viewport = frame.transform.transform_size(frame.size);
+    let to_window = |point: Point<i32, Physical>| {
+        let ndc = frame.current_projection * Vector3::new(point.x as f32, point.y as f32, 1.0);
+        (
+            (ndc.x + 1.0) / 2.0 * viewport.w as f32,
+            (ndc.y + 1.0) / 2.0 * viewport.h as f32,
+        )
+    };
+    let (x1, y1) = to_window(rect.loc);
+    let (x2, y2) = to_window(rect.loc + rect.size);
+
+    let loc = Point::from((x1.min(x2).floor() as i32, y1.min(y2).floor() as i32));
+    let extremity = Point::from((x1.max(x2).ceil() as i32, y1.max(y2).ceil() as i32));
+    Rectangle::from_extemities(loc, extremity).intersection(Rectangle::from_loc_and_size((0, 0), viewport))
+}
+
+// Copies the content of the currently bound framebuffer inside of `window` into the first level of
+// `buffers`, blurs it and restores the state of the frame afterwards.
+unsafe fn blur_backdrop(
+    frame: &GlesFrame<'_>,
+    programs: &BlurPrograms,
+    buffers: &BlurBuffers,
+    window: Rectangle<i32, Physical>,
+    offset: f32,
+) {
+    let renderer = &*frame.renderer;
+    let gl = &renderer.gl;
+    let instancing = renderer.capabilities.contains(&Capability::Instancing);
+
+    let mut target_fbo = 0;
+    gl.GetIntegerv(ffi::FRAMEBUFFER_BINDING, &mut target_fbo);
+    gl.Disable(ffi::SCISSOR_TEST);
+    gl.Disable(ffi::BLEND);
+
+    copy_framebuffer(renderer, &buffers.levels[0], window);
+
+    for pass in buffers.levels.windows(2) {
+        programs.down.render(gl, &pass[0], &pass[1], offset, instancing);
+    }
+    for pass in buffers.levels.windows(2).rev() {
+        programs.up.render(gl, &pass[1], &pass[0], offset, instancing);
+    }
+
+    gl.BindFramebuffer(ffi::FRAMEBUFFER, target_fbo as ffi::types::GLuint);
+    let viewport = frame.transform.transform_size(frame.size);
+    gl.Viewport(0, 0, viewport.w, viewport.h);
+    gl.Enable(ffi::SCISSOR_TEST);
+    gl.Enable(ffi::BLEND);
+    gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+}
+
+// Copies the content of the currently bound framebuffer inside of `window` into `buffer`.
+//
+// The scissor test has to be disabled and the draw framebuffer might be changed afterwards.
+pub(super) unsafe fn copy_framebuffer(
+    renderer: &GlesRenderer,
+    buffer: &BlurBuffer,
+    window: Rectangle<i32, Physical>,
+) {
+    let gl = &renderer.gl;
+    if renderer.capabilities.contains(&Capability::Blit) {
+        gl.BindFramebuffer(ffi::DRAW_FRAMEBUFFER, buffer.fbo);
+        gl.BlitFramebuffer(
+            window.loc.x,
+            window.loc.y,
+            window.loc.x + window.size.w,
+            window.loc.y + window.size.h,
+            0,
+            0,
+            window.size.w,
+            window.size.h,
+            ffi::COLOR_BUFFER_BIT,
+            ffi::NEAREST,
+        );
+    } else {
+        gl.BindTexture(ffi::TEXTURE_2D, buffer.texture.tex_id());
+        gl.CopyTexSubImage2D(
+            ffi::TEXTURE_2D,
+            0,
+            0,
+            0,
+            window.loc.x,
+            window.loc.y,
+            window.size.w,
+            window.size.h,
+        );
+        gl.BindTexture(ffi::TEXTURE_2D, 0);
+    }
+}
+
+#[derive(Debug)]
+struct BlurProgram {
+    program: ffi::types::GLuint,
+    uniform_tex: ffi::types::GLint,
+    uniform_half_pixel: ffi::types::GLint,
+    uniform_offset: ffi::types::GLint,
+    attrib_vert: ffi::types::GLint,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+impl BlurProgram {
+    unsafe fn compile(renderer: &GlesRenderer, src: &str) -> Result<Self, GlesError> {
+        let gl = &renderer.gl;
+        let program = link_program(gl, BLUR_VERTEX_SHADER, src)?;
+        Ok(BlurProgram {
+            program,
+            uniform_tex: gl.GetUniformLocation(program, b"tex\0".as_ptr() as *const ffi::types::GLchar),
+            uniform_half_pixel: gl
+                .GetUniformLocation(program, b"half_pixel\0".as_ptr() as *const ffi::types::GLchar),
+            uniform_offset: gl.GetUniformLocation(program, b"offset\0".as_ptr() as *const ffi::types::GLchar),
+            attrib_vert: gl.GetAttribLocation(program, b"vert\0".as_ptr() as *const ffi::types::GLchar),
+            destruction_callback_sender: renderer.destruction_callback_sender.clone(),
+        })
+    }
+
+    // Renders `src` into `dst`, sampling with a distance of `offset` pixels of `dst`.
+    unsafe fn render(
+        &self,
+        gl: &ffi::Gles2,
+        src: &BlurBuffer,
+        dst: &BlurBuffer,
+        offset: f32,
+        instancing: bool,
+    ) {
+        let size = dst.texture.size();
+        gl.BindFramebuffer(ffi::FRAMEBUFFER, dst.fbo);
+        gl.Viewport(0, 0, size.w, size.h);
+
+        gl.UseProgram(self.program);
+        gl.ActiveTexture(ffi::TEXTURE0);
+        gl.BindTexture(ffi::TEXTURE_2D, src.texture.tex_id());
+        gl.Uniform1i(self.uniform_tex, 0);
+        gl.Uniform2f(self.uniform_half_pixel, 0.5 / size.w as f32, 0.5 / size.h as f32);
+        gl.Uniform1f(self.uniform_offset, offset);
+
+        gl.EnableVertexAttribArray(self.attrib_vert as u32);
+        gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
+        gl.VertexAttribPointer(
+            self.attrib_vert as u32,
+            2,
+            ffi::FLOAT,
+            ffi::FALSE,
+            0,
+            QUAD_VERTS.as_ptr() as *const _,
+        );
+        if instancing {
+            gl.VertexAttribDivisor(self.attrib_vert as u32, 0);
+        }
+        gl.DrawArrays(ffi::TRIANGLE_STRIP, 0, 4);
+
+        gl.DisableVertexAttribArray(self.attrib_vert as u32);
+        gl.BindTexture(ffi::TEXTURE_2D, 0);
+    }
+}
+
+impl Drop for BlurProgram {
+    fn drop(&mut self) {
+        let _ = self
+            .destruction_callback_sender
+            .send(CleanupResource::Program(self.program));
+    }
+}
+
+#[derive(Debug)]
+struct BlurPrograms {
+    down: BlurProgram,
+    up: BlurProgram,
+    finish: GlesTexProgram,
+}
+
+#[derive(Debug)]
+pub(super) struct BlurBuffer {
+    pub(super) texture: GlesTexture,
+    fbo: ffi::types::GLuint,
+    destruction_callback_sender: Sender<CleanupResource>,
+}
+
+impl BlurBuffer {
+    unsafe fn new(renderer: &GlesRenderer, size: Size<i32, Buffer>) -> Result<Self, GlesError> {
+        let gl = &renderer.gl;
+        let (internal, format, layout) = fourcc_to_gl_formats(Fourcc::Abgr8888).unwrap();
+
+        let mut tex = 0;
+        gl.GenTextures(1, &mut tex);
+        gl.BindTexture(ffi::TEXTURE_2D, tex);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+        gl.TexImage2D(
+            ffi::TEXTURE_2D,
+            0,
+            internal as i32,
+            size.w,
+            size.h,
+            0,
+            format,
+            layout,
+            std::ptr::null(),
+        );
+        gl.BindTexture(ffi::TEXTURE_2D, 0);
+        let texture = GlesTexture::from_raw(renderer, Some(internal), false, tex, size);
+
+        let mut fbo = 0;
+        gl.GenFramebuffers(1, &mut fbo);
+        let buffer = BlurBuffer {
+            texture,
+            fbo,
+            destruction_callback_sender: renderer.destruction_callback_sender.clone(),
+        };
+        gl.BindFramebuffer(ffi::FRAMEBUFFER, fbo);
+        gl.FramebufferTexture2D(ffi::FRAMEBUFFER, ffi::COLOR_ATTACHMENT0, ffi::TEXTURE_2D, tex, 0);
+        let status = gl.CheckFramebufferStatus(ffi::FRAMEBUFFER);
+        if status != ffi::FRAMEBUFFER_COMPLETE {
+            return Err(GlesError::FramebufferBindingError);
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl Drop for BlurBuffer {
+    fn drop(&mut self) {
+        let _ = self
+            .destruction_callback_sender
+            .send(CleanupResource::FramebufferObject(self.fbo));
+    }
+}
+
+// Textures of all passes, each level being half the size of the previous one
+#[derive(Debug)]
+pub(super) struct BlurBuffers {
+    pub(super) levels: Vec<BlurBuffer>,
+}
+
+#[derive(Debug, Default)]
+struct BlurCache {
+    programs: Option<Rc<BlurPrograms>>,
+    // most recently used first
+    buffers: Vec<Rc<BlurBuffers>>,
+}
+
+fn blur_cache(renderer: &GlesRenderer) -> Rc<RefCell<BlurCache>> {
+    let user_data = renderer.egl_context().user_data();
+    user_data.insert_if_missing(|| Rc::new(RefCell::new(BlurCache::default())));
+    user_data.get::<Rc<RefCell<BlurCache>>>().unwrap().clone()
+}
+
+fn blur_programs(
+    renderer: &mut GlesRenderer,
+    cache: &RefCell<BlurCache>,
+) -> Result<Rc<BlurPrograms>, GlesError> {
+    if let Some(programs) = cache.borrow().programs.as_ref() {
+        return Ok(programs.clone());
+    }
+
+    let finish = renderer.compile_custom_texture_shader(
+        BLUR_SHADER,
+        &[
+            UniformName::new("frag_offset", UniformType::_2f),
+            UniformName::new("frag_size", UniformType::_2f),
+        ],
+    )?;
+    let programs = unsafe {
+        Rc::new(BlurPrograms {
+            down: BlurProgram::compile(renderer, BLUR_DOWN_SHADER)?,
+            up: BlurProgram::compile(renderer, BLUR_UP_SHADER)?,
+            finish,
+        })
+    };
+    cache.borrow_mut().programs = Some(programs.clone());
+    Ok(programs)
+}
+
+// Returns a single cached buffer of the given size, e.g. to copy the content of the framebuffer into
+pub(super) fn backdrop_buffer(
+    renderer: &GlesRenderer,
+    size: Size<i32, Physical>,
+) -> Result<Rc<BlurBuffers>, GlesError> {
+    blur_buffers(renderer, &blur_cache(renderer), size, 0)
+}
+
+fn blur_buffers(
+    renderer: &GlesRenderer,
+    cache: &RefCell<BlurCache>,
+    size: Size<i32, Physical>,
+    passes: u32,
+) -> Result<Rc<BlurBuffers>, GlesError> {
+    let mut sizes = Vec::with_capacity(passes as usize + 1);
+    let mut level = Size::<i32, Buffer>::from((size.w, size.h));
+    sizes.push(level);
+    for _ in 0..passes {
+        level = Size::from(((level.w / 2).max(1), (level.h / 2).max(1)));
+        sizes.push(level);
+    }
+
+    let mut cache = cache.borrow_mut();
+    if let Some(idx) = cache.buffers.iter().position(|buffers| {
+        buffers.levels.len() == sizes.len()
+            && buffers
+                .levels
+                .iter()
+                .zip(sizes.iter())
+                .all(|(buffer, size)| buffer.texture.size() == *size)
+    }) {
+        let buffers = cache.buffers.remove(idx);
+        cache.buffers.insert(0, buffers.clone());
+        return Ok(buffers);
+    }
+
+    trace!(?size, passes, "Allocating blur buffers");
+    let levels = sizes
+        .into_iter()
+        .map(|size| unsafe { BlurBuffer::new(renderer, size) })
+        .collect::<Result<Vec<_>, _>>()?;
+    let buffers = Rc::new(BlurBuffers { levels });
+    cache.buffers.insert(0, buffers.clone());
+    cache.buffers.truncate(MAX_CACHED_BUFFERS);
+    Ok(buffers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlurElement;
+    use crate::{
+        backend::renderer::{
+            damage::OutputDamageTracker,
+            element::{solid::SolidColorRenderElement, Element, Id, Kind},
+            utils::CommitCounter,
+        },
+        utils::{Point, Rectangle, Transform},
+    };
+
+    #[test]
+    fn backdrop_changes_damage_blur() {
+        let blur = BlurElement::new(
+            Rectangle::from_loc_and_size((20, 20), (40, 40)),
+            2,
+            2.0,
+            1.0,
+            Kind::Unspecified,
+        );
+        assert_eq!(blur.radius(), 16);
+
+        let background = SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::from_loc_and_size((0, 0), (100, 100)),
+            CommitCounter::default(),
+            [0.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        );
+        let id = Id::new();
+        let mut commit = CommitCounter::default();
+        let damage_at = |tracker: &mut OutputDamageTracker, commit: CommitCounter, loc: (i32, i32)| {
+            let changed = SolidColorRenderElement::new(
+                id.clone(),
+                Rectangle::from_loc_and_size(loc, (10, 10)),
+                commit,
+                [1.0, 0.0, 0.0, 1.0],
+                Kind::Unspecified,
+            );
+            let elements: Vec<Box<dyn Element>> = vec![
+                Box::new(blur.clone()),
+                Box::new(changed),
+                Box::new(background.clone()),
+            ];
+            tracker
+                .damage_output(1, &elements)
+                .unwrap()
+                .0
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        // damage outside of the backdrop does not touch the blur
+        let mut tracker = OutputDamageTracker::new((100, 100), 1.0, Transform::Normal);
+        damage_at(&mut tracker, commit, (90, 90));
+        commit.increment();
+        let damage = damage_at(&mut tracker, commit, (90, 90));
+        assert!(!damage.iter().any(|rect| rect.contains(Point::from((50, 50)))));
+
+        // damage inside of the backdrop redraws the whole backdrop
+        let mut tracker = OutputDamageTracker::new((100, 100), 1.0, Transform::Normal);
+        damage_at(&mut tracker, commit, (0, 0));
+        commit.increment();
+        let damage = damage_at(&mut tracker, commit, (0, 0));
+        assert!(damage.iter().any(|rect| rect.contains(Point::from((70, 70)))));
+    }
+}
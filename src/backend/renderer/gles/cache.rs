@@ -0,0 +1,166 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Limits of the dmabuf texture cache of a [`GlesRenderer`](super::GlesRenderer)
+///
+/// If the cache exceeds any of the limits, the least recently used textures are removed from it.
+/// Removed textures stay valid as long as they are referenced elsewhere, but have to be re-imported
+/// the next time the dmabuf is used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureCacheBudget {
+    /// Maximum number of cached textures
+    pub max_entries: Option<usize>,
+    /// Maximum estimated size of all cached textures in bytes
+    pub max_size: Option<usize>,
+}
+
+/// Statistics of the dmabuf texture cache of a [`GlesRenderer`](super::GlesRenderer)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureCacheStats {
+    /// Number of currently cached textures
+    pub entries: usize,
+    /// Estimated size of all cached textures in bytes
+    pub size: usize,
+    /// Number of imports that re-used a cached texture
+    pub hits: u64,
+    /// Number of imports that had to create a new texture
+    pub misses: u64,
+    /// Number of textures removed to stay within the [`TextureCacheBudget`]
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry<T> {
+    value: T,
+    size: usize,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+pub(super) struct TextureCache<K, T> {
+    entries: HashMap<K, CacheEntry<T>>,
+    budget: TextureCacheBudget,
+    stats: TextureCacheStats,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, T: Clone> TextureCache<K, T> {
+    pub(super) fn new() -> Self {
+        TextureCache {
+            entries: HashMap::new(),
+            budget: TextureCacheBudget::default(),
+            stats: TextureCacheStats::default(),
+            clock: 0,
+        }
+    }
+
+    pub(super) fn get(&mut self, key: &K) -> Option<T> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(entry.value.clone())
+    }
+
+    pub(super) fn record_hit(&mut self) {
+        self.stats.hits += 1;
+    }
+
+    pub(super) fn insert(&mut self, key: K, value: T, size: usize) {
+        self.clock += 1;
+        self.stats.misses += 1;
+        self.stats.size += size;
+        let entry = CacheEntry {
+            value,
+            size,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.stats.size -= old.size;
+        }
+        self.enforce_budget();
+    }
+
+    #[cfg(any(feature = "wayland_frontend", test))]
+    pub(super) fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        let stats = &mut self.stats;
+        self.entries.retain(|key, entry| {
+            let keep = keep(key);
+            if !keep {
+                stats.size -= entry.size;
+            }
+            keep
+        });
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.stats.size = 0;
+    }
+
+    pub(super) fn budget(&self) -> TextureCacheBudget {
+        self.budget
+    }
+
+    pub(super) fn set_budget(&mut self, budget: TextureCacheBudget) {
+        self.budget = budget;
+        self.enforce_budget();
+    }
+
+    pub(super) fn stats(&self) -> TextureCacheStats {
+        TextureCacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    fn enforce_budget(&mut self) {
+        let over_budget = |entries: usize, size: usize, budget: &TextureCacheBudget| {
+            budget.max_entries.is_some_and(|max| entries > max)
+                || budget.max_size.is_some_and(|max| size > max)
+        };
+
+        while over_budget(self.entries.len(), self.stats.size, &self.budget) {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.stats.size -= entry.size;
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TextureCache, TextureCacheBudget};
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = TextureCache::new();
+        cache.set_budget(TextureCacheBudget {
+            max_entries: Some(2),
+            max_size: Some(100),
+        });
+
+        cache.insert(1, "a", 10);
+        cache.insert(2, "b", 10);
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c", 10);
+        assert_eq!(cache.get(&2), None);
+
+        cache.insert(4, "d", 95);
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.size, 95);
+        assert_eq!(stats.evictions, 3);
+        assert_eq!(stats.misses, 4);
+
+        cache.retain(|key| *key != 4);
+        assert_eq!(cache.stats().size, 0);
+    }
+}
@@ -0,0 +1,275 @@
+//! Color transformation of the composited output
+//!
+//! The [`ColorTransformElement`] applies a [`ColorTransform`] to everything rendered below it.
+//! It is meant to be the topmost element of an output covering the whole output, making it the final
+//! pass of the composition. The transform is baked into a 3D lookup table, which is stored in a texture
+//! and sampled by a custom shader.
+//!
+//! Like the [`BlurElement`](super::blur::BlurElement) it can only see content rendered into the same
+//! framebuffer, so elements scanned out on other planes are not transformed.
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            color_transform::{ColorTransform, Lut3d, DEFAULT_LUT_SIZE},
+            element::{Element, Id, Kind, RenderElement, UnderlyingStorage},
+            utils::CommitCounter,
+            ImportMem, Texture,
+        },
+    },
+    utils::{Buffer, Logical, Physical, Rectangle, Scale, Transform},
+};
+
+use super::{
+    blur::{backdrop_buffer, copy_framebuffer, window_rect},
+    ffi,
+    shaders::COLOR_TRANSFORM_SHADER,
+    GlesError, GlesFrame, GlesRenderer, GlesTexProgram, GlesTexture, Uniform, UniformName, UniformType,
+};
+
+struct ColorTransformProgram(GlesTexProgram);
+
+/// Render element applying a [`ColorTransform`] to the content below it
+#[derive(Debug, Clone)]
+pub struct ColorTransformElement {
+    id: Id,
+    commit_counter: CommitCounter,
+    area: Rectangle<i32, Logical>,
+    program: GlesTexProgram,
+    lut: GlesTexture,
+    lut_size: u32,
+}
+
+impl ColorTransformElement {
+    /// Create a new [`ColorTransformElement`] applying `transform` inside of `area`
+    pub fn new(
+        renderer: &mut GlesRenderer,
+        area: Rectangle<i32, Logical>,
+        transform: &ColorTransform,
+    ) -> Result<Self, GlesError> {
+        let program = Self::program(renderer)?;
+        let lut = transform.bake(DEFAULT_LUT_SIZE);
+        Ok(ColorTransformElement {
+            id: Id::new(),
+            commit_counter: CommitCounter::default(),
+            area,
+            program,
+            lut: upload_lut(renderer, &lut)?,
+            lut_size: lut.size(),
+        })
+    }
+
+    /// Returns the shader used for applying color transforms, compiling it if necessary
+    pub fn program(renderer: &mut GlesRenderer) -> Result<GlesTexProgram, GlesError> {
+        if let Some(program) = renderer.egl_context().user_data().get::<ColorTransformProgram>() {
+            return Ok(program.0.clone());
+        }
+
+        let program = renderer.compile_custom_texture_shader(
+            COLOR_TRANSFORM_SHADER,
+            &[
+                UniformName::new("frag_offset", UniformType::_2f),
+                UniformName::new("frag_size", UniformType::_2f),
+                UniformName::new("lut", UniformType::Texture),
+                UniformName::new("lut_size", UniformType::_1f),
+            ],
+        )?;
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| ColorTransformProgram(program.clone()));
+        Ok(program)
+    }
+
+    /// Resize the transformed area, usually to the size of the output
+    pub fn resize(&mut self, area: Rectangle<i32, Logical>) {
+        if self.area != area {
+            self.area = area;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Replace the applied color transform
+    pub fn set_transform(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        transform: &ColorTransform,
+    ) -> Result<(), GlesError> {
+        self.set_lut(renderer, &transform.bake(DEFAULT_LUT_SIZE))
+    }
+
+    /// Replace the applied color transform with a lookup table
+    pub fn set_lut(&mut self, renderer: &mut GlesRenderer, lut: &Lut3d) -> Result<(), GlesError> {
+        self.lut = upload_lut(renderer, lut)?;
+        self.lut_size = lut.size();
+        self.commit_counter.increment();
+        Ok(())
+    }
+}
+
+// Stores the lookup table as `size` slices of increasing blue next to each other
+//
+// Half floats keep the precision needed for 10 bit and floating point framebuffers,
+// only es 2.0 contexts fall back to 8 bits per channel.
+fn upload_lut(renderer: &mut GlesRenderer, lut: &Lut3d) -> Result<GlesTexture, GlesError> {
+    let size = lut.size() as usize;
+    let half_float = renderer
+        .mem_formats()
+        .any(|format| format == Fourcc::Abgr16161616f);
+    let mut data = Vec::with_capacity(size.pow(3) * if half_float { 8 } else { 4 });
+    for g in 0..size {
+        for b in 0..size {
+            for r in 0..size {
+                let color = lut.data()[(b * size + g) * size + r];
+                for c in color.map(|c| c.clamp(0.0, 1.0)).into_iter().chain([1.0]) {
+                    if half_float {
+                        data.extend(f16_bits(c).to_ne_bytes());
+                    } else {
+                        data.push((c * 255.0).round() as u8);
+                    }
+                }
+            }
+        }
+    }
+
+    let texture = renderer.import_memory(
+        &data,
+        if half_float {
+            Fourcc::Abgr16161616f
+        } else {
+            Fourcc::Abgr8888
+        },
+        (size as i32 * size as i32, size as i32).into(),
+        false,
+    )?;
+    renderer.with_context(|gl| unsafe {
+        gl.BindTexture(ffi::TEXTURE_2D, texture.tex_id());
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+        gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+        gl.BindTexture(ffi::TEXTURE_2D, 0);
+    })?;
+    Ok(texture)
+}
+
+// Converts a value in `0.0..=1.0` to a half float, rounding to the nearest representable value
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if value <= 0.0 || exponent < -10 {
+        0
+    } else if exponent <= 0 {
+        // subnormal, the implicit leading one becomes part of the mantissa
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        ((mantissa >> shift) + ((mantissa >> (shift - 1)) & 1)) as u16
+    } else {
+        // a carry of the rounding correctly increments the exponent
+        (((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1)) as u16
+    }
+}
+
+impl Element for ColorTransformElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.area
+            .to_f64()
+            .to_buffer(1.0, Transform::Normal, &self.area.size.to_f64())
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.area.to_physical_precise_round(scale)
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::Unspecified
+    }
+}
+
+impl RenderElement<GlesRenderer> for ColorTransformElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        // every pixel only depends on the pixel below it, so only copy the damaged area
+        let Some(damaged) = damage.iter().copied().reduce(|a, b| a.merge(b)) else {
+            return Ok(());
+        };
+        let Some(window) = window_rect(
+            frame,
+            Rectangle::from_loc_and_size(dst.loc + damaged.loc, damaged.size),
+        ) else {
+            return Ok(());
+        };
+
+        let buffers = backdrop_buffer(frame.renderer, window.size)?;
+        frame.flush_batches()?;
+        let buffer = &buffers.levels[0];
+        unsafe {
+            let gl = &frame.renderer.gl;
+            let mut target_fbo = 0;
+            gl.GetIntegerv(ffi::FRAMEBUFFER_BINDING, &mut target_fbo);
+            gl.Disable(ffi::SCISSOR_TEST);
+            copy_framebuffer(frame.renderer, buffer, window);
+            gl.BindFramebuffer(ffi::FRAMEBUFFER, target_fbo as ffi::types::GLuint);
+            gl.Enable(ffi::SCISSOR_TEST);
+        }
+
+        let texture = &buffer.texture;
+        let uniforms = [
+            Uniform::new("frag_offset", (window.loc.x as f32, window.loc.y as f32)),
+            Uniform::new("frag_size", (window.size.w as f32, window.size.h as f32)),
+            Uniform::new("lut", &self.lut),
+            Uniform::new("lut_size", self.lut_size as f32),
+        ];
+        // the transformed content replaces the content below, so draw it without blending
+        frame.render_texture_from_to(
+            texture,
+            Rectangle::from_loc_and_size((0, 0), texture.size()).to_f64(),
+            dst,
+            damage,
+            &[Rectangle::from_loc_and_size((0, 0), dst.size)],
+            Transform::Normal,
+            1.0,
+            Some(&self.program),
+            &uniforms,
+        )
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::f16_bits;
+
+    #[test]
+    fn lut_values_convert_to_half_floats() {
+        assert_eq!(f16_bits(0.0), 0);
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(0.5), 0x3800);
+        // 1/1023 is off by less than half a step of the 10 bit mantissa
+        assert_eq!(f16_bits(1.0 / 1023.0), 0x1401);
+        // largest subnormal and values rounding up to one
+        assert_eq!(f16_bits(1023.0 / 1024.0 / 16384.0), 0x03ff);
+        assert_eq!(f16_bits(0.99999), 0x3c00);
+    }
+}
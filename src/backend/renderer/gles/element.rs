@@ -1,14 +1,28 @@
 //! RenderElements specific to using a `GlesRenderer`
 
+use std::sync::Arc;
+
+use cgmath::{prelude::*, Matrix3, Vector2};
+
 use crate::{
     backend::renderer::{
-        element::{texture::TextureRenderElement, Element, Id, Kind, RenderElement, UnderlyingStorage},
+        element::{
+            border::BorderRenderElement,
+            texture::TextureRenderElement,
+            utils::{ClipSnapshot, ClipState},
+            Element, Id, Kind, RenderElement, UnderlyingStorage,
+        },
         utils::{CommitCounter, DamageSet, OpaqueRegions},
+        Color32F,
     },
     utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Transform},
 };
 
-use super::{GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, GlesTexProgram, GlesTexture, Uniform};
+use super::{
+    shaders::{ALPHA_MASK_SHADER, BORDER_SHADER, ROUNDED_CORNERS_SHADER, SHADOW_SHADER},
+    GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, GlesTexProgram, GlesTexture, Uniform, UniformName,
+    UniformType, UniformValue,
+};
 
 /// Render element for drawing with a gles2 pixel shader
 #[derive(Debug, Clone)]
@@ -187,6 +201,10 @@ impl Element for TextureShaderElement {
         self.inner.opaque_regions(scale)
     }
 
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.inner.backdrop_region(scale)
+    }
+
     fn alpha(&self) -> f32 {
         self.inner.alpha()
     }
@@ -223,3 +241,722 @@ impl RenderElement<GlesRenderer> for TextureShaderElement {
         )
     }
 }
+
+/// Radii of the corners of a [`RoundedCornerElement`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CornerRadius {
+    /// Radius of the top left corner
+    pub top_left: f32,
+    /// Radius of the top right corner
+    pub top_right: f32,
+    /// Radius of the bottom right corner
+    pub bottom_right: f32,
+    /// Radius of the bottom left corner
+    pub bottom_left: f32,
+}
+
+impl CornerRadius {
+    /// Create a corner radius with the same radius for all corners
+    pub fn uniform(radius: f32) -> Self {
+        CornerRadius {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    fn scaled(self, scale: f64) -> [f32; 4] {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+            .map(|radius| radius * scale as f32)
+    }
+}
+
+impl From<f32> for CornerRadius {
+    #[inline]
+    fn from(radius: f32) -> Self {
+        CornerRadius::uniform(radius)
+    }
+}
+
+struct RoundedCornerProgram(GlesTexProgram);
+
+/// Element clipping another element to a rectangle with rounded, anti-aliased corners
+///
+/// This is typically used to round the corners of windows by wrapping every element of a
+/// window with the geometry of the window.
+///
+/// The clipping is applied by overriding the texture shader while drawing the wrapped element,
+/// it is therefore only applied to textures drawn without a custom shader.
+#[derive(Debug)]
+pub struct RoundedCornerElement<E> {
+    element: E,
+    program: GlesTexProgram,
+    // clip rect relative to the element geometry
+    clip: Rectangle<i32, Physical>,
+    radii: [f32; 4],
+}
+
+impl<E: Element> RoundedCornerElement<E> {
+    /// Create a rounded corner element for an existing element
+    ///
+    /// The geometry is expected to be relative to the same origin the element is relative to
+    /// and the radius is given in logical coordinates.
+    pub fn from_element(
+        renderer: &mut GlesRenderer,
+        element: E,
+        scale: impl Into<Scale<f64>>,
+        geometry: Rectangle<i32, Logical>,
+        radius: impl Into<CornerRadius>,
+    ) -> Result<Self, GlesError> {
+        let scale = scale.into();
+        let mut clip = geometry.to_physical_precise_round(scale);
+        clip.loc -= element.geometry(scale).loc;
+
+        Ok(RoundedCornerElement {
+            element,
+            program: Self::program(renderer)?,
+            clip,
+            radii: radius.into().scaled(scale.x),
+        })
+    }
+
+    /// Returns the texture shader used for rounding corners, compiling it if necessary
+    pub fn program(renderer: &mut GlesRenderer) -> Result<GlesTexProgram, GlesError> {
+        if let Some(program) = renderer.egl_context().user_data().get::<RoundedCornerProgram>() {
+            return Ok(program.0.clone());
+        }
+
+        let program = renderer.compile_custom_texture_shader(
+            ROUNDED_CORNERS_SHADER,
+            &[
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("radii", UniformType::_4f),
+                UniformName::new("frag_to_geo", UniformType::Matrix3x3),
+            ],
+        )?;
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| RoundedCornerProgram(program.clone()));
+        Ok(program)
+    }
+
+    /// Returns a reference to the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
+
+    fn uniforms(&self, frame: &GlesFrame<'_>, dst: Rectangle<i32, Physical>) -> Vec<Uniform<'static>> {
+        let frag_to_geo = frag_to_geo(frame, dst.loc + self.clip.loc);
+
+        let [top_left, top_right, bottom_right, bottom_left] = self.radii;
+        vec![
+            Uniform::new("geo_size", (self.clip.size.w as f32, self.clip.size.h as f32)),
+            Uniform::new("radii", (top_left, top_right, bottom_right, bottom_left)),
+            Uniform::new(
+                "frag_to_geo",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![*frag_to_geo.as_ref()],
+                    transpose: false,
+                },
+            ),
+        ]
+    }
+}
+
+impl<E: Element> Element for RoundedCornerElement<E> {
+    fn id(&self) -> &Id {
+        self.element.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.element.current_commit()
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.element.src()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn transform(&self) -> Transform {
+        self.element.transform()
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        self.element.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        // The rounded corners and everything outside of the clip rect is transparent
+        let clip = self.clip;
+        let corners = rounded_corners(clip, self.radii);
+        self.element
+            .opaque_regions(scale)
+            .into_iter()
+            .filter_map(|region| region.intersection(clip))
+            .flat_map(|region| region.subtract_rects(corners))
+            .collect()
+    }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.element.backdrop_region(scale)
+    }
+
+    fn alpha(&self) -> f32 {
+        self.element.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.element.kind()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.element.location(scale)
+    }
+}
+
+impl<E: RenderElement<GlesRenderer>> RenderElement<GlesRenderer> for RoundedCornerElement<E> {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let uniforms = self.uniforms(frame, dst);
+        let previous_override = frame.tex_program_override.take();
+        frame.override_default_tex_program(self.program.clone(), uniforms);
+        let res = self.element.draw(frame, src, dst, damage, opaque_regions);
+        frame.tex_program_override = previous_override;
+        res
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        // The clipping can not be applied during direct scan-out
+        None
+    }
+}
+
+// Maps window coordinates of fragments to physical coordinates relative to `loc`
+fn frag_to_geo(frame: &GlesFrame<'_>, loc: Point<i32, Physical>) -> Matrix3<f32> {
+    // Maps window coordinates to the normalized device coordinates of the viewport and undoes
+    // the projection of the frame to get back to the physical coordinates of the output.
+    let viewport = frame.transform.transform_size(frame.size).to_f64();
+    let window_to_ndc = Matrix3::new(
+        2.0 / viewport.w as f32,
+        0.0,
+        0.0,
+        0.0,
+        2.0 / viewport.h as f32,
+        0.0,
+        -1.0,
+        -1.0,
+        1.0,
+    );
+    let ndc_to_output = frame
+        .current_projection
+        .invert()
+        .unwrap_or_else(Matrix3::identity);
+    let loc = loc.to_f64();
+    let output_to_geo = Matrix3::from_translation(Vector2::new(-loc.x as f32, -loc.y as f32));
+    output_to_geo * ndc_to_output * window_to_ndc
+}
+
+struct AlphaMaskProgram(GlesTexProgram);
+
+/// Element masking another element with the alpha channel of a texture
+///
+/// The mask texture is stretched over the given geometry and everything outside of it is transparent.
+/// This can be used for shaped windows or reveal animations.
+///
+/// Like [`RoundedCornerElement`] the mask is applied by overriding the texture shader while drawing
+/// the wrapped element and is therefore only applied to textures drawn without a custom shader.
+/// Changes of the mask texture or geometry are tracked by an [`AlphaMaskState`] and damage the
+/// old and new area of the mask.
+#[derive(Debug)]
+pub struct AlphaMaskElement<E> {
+    element: E,
+    program: GlesTexProgram,
+    // mask rect relative to the element geometry
+    clip: Rectangle<i32, Physical>,
+    mask: GlesTexture,
+    snapshot: ClipSnapshot,
+}
+
+/// State of an [`AlphaMaskElement`] kept between frames
+///
+/// Every masked element needs its own state. Replacing the mask texture damages the masked area,
+/// changes to the contents of the same texture are not detected.
+#[derive(Debug, Default)]
+pub struct AlphaMaskState {
+    clip: ClipState,
+    mask: Option<GlesTexture>,
+}
+
+impl AlphaMaskState {
+    /// Creates a new state for an element that was not masked before
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<E: Element> AlphaMaskElement<E> {
+    /// Create an alpha mask element for an existing element
+    ///
+    /// The geometry is expected to be relative to the same origin the element is relative to.
+    /// `state` has to be kept alongside the element to track changes of the mask.
+    pub fn from_element(
+        renderer: &mut GlesRenderer,
+        element: E,
+        scale: impl Into<Scale<f64>>,
+        geometry: Rectangle<i32, Logical>,
+        mask: GlesTexture,
+        state: &mut AlphaMaskState,
+    ) -> Result<Self, GlesError> {
+        let scale = scale.into();
+        let program = Self::program(renderer)?;
+        let area = geometry.to_physical_precise_round(scale);
+        // the mask is stretched over its geometry, so any change affects all of it
+        let replaced = state
+            .mask
+            .as_ref()
+            .map_or(true, |previous| !Arc::ptr_eq(&previous.0, &mask.0));
+        let snapshot = state.clip.update(&element, &[area], replaced);
+        state.mask = Some(mask.clone());
+
+        let mut clip = area;
+        clip.loc -= element.geometry(scale).loc;
+        Ok(AlphaMaskElement {
+            element,
+            program,
+            clip,
+            mask,
+            snapshot,
+        })
+    }
+
+    /// Returns the texture shader used for masking, compiling it if necessary
+    pub fn program(renderer: &mut GlesRenderer) -> Result<GlesTexProgram, GlesError> {
+        if let Some(program) = renderer.egl_context().user_data().get::<AlphaMaskProgram>() {
+            return Ok(program.0.clone());
+        }
+
+        let program = renderer.compile_custom_texture_shader(
+            ALPHA_MASK_SHADER,
+            &[
+                UniformName::new("mask", UniformType::Texture),
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("frag_to_geo", UniformType::Matrix3x3),
+            ],
+        )?;
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| AlphaMaskProgram(program.clone()));
+        Ok(program)
+    }
+
+    /// Returns a reference to the wrapped element
+    pub fn element(&self) -> &E {
+        &self.element
+    }
+
+    /// Returns the texture used as the alpha mask
+    pub fn mask(&self) -> &GlesTexture {
+        &self.mask
+    }
+
+    fn uniforms(&self, frame: &GlesFrame<'_>, dst: Rectangle<i32, Physical>) -> Vec<Uniform<'static>> {
+        let frag_to_geo = frag_to_geo(frame, dst.loc + self.clip.loc);
+        vec![
+            Uniform::new("mask", &self.mask),
+            Uniform::new("geo_size", (self.clip.size.w as f32, self.clip.size.h as f32)),
+            Uniform::new(
+                "frag_to_geo",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![*frag_to_geo.as_ref()],
+                    transpose: false,
+                },
+            ),
+        ]
+    }
+}
+
+impl<E: Element> Element for AlphaMaskElement<E> {
+    fn id(&self) -> &Id {
+        self.element.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.snapshot.current_commit()
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.element.src()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.element.geometry(scale)
+    }
+
+    fn transform(&self) -> Transform {
+        self.element.transform()
+    }
+
+    fn damage_since(&self, scale: Scale<f64>, commit: Option<CommitCounter>) -> DamageSet<i32, Physical> {
+        let clip = self.clip;
+        self.snapshot.damage_since(&self.element, scale, commit, |rect| {
+            rect.intersection(clip).into_iter().collect()
+        })
+    }
+
+    fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        // The mask may make any part of the element translucent
+        OpaqueRegions::default()
+    }
+
+    fn backdrop_region(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.element
+            .backdrop_region(scale)
+            .and_then(|rect| rect.intersection(self.clip))
+    }
+
+    fn alpha(&self) -> f32 {
+        self.element.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.element.kind()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.element.location(scale)
+    }
+}
+
+impl<E: RenderElement<GlesRenderer>> RenderElement<GlesRenderer> for AlphaMaskElement<E> {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let uniforms = self.uniforms(frame, dst);
+        let previous_override = frame.tex_program_override.take();
+        frame.override_default_tex_program(self.program.clone(), uniforms);
+        let res = self.element.draw(frame, src, dst, damage, opaque_regions);
+        frame.tex_program_override = previous_override;
+        res
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        // The mask can not be applied during direct scan-out
+        None
+    }
+}
+
+struct ShadowProgram(GlesPixelProgram);
+
+/// Element drawing the drop shadow of a window
+///
+/// The shadow is cast by a rectangle of the size of the window geometry moved by an offset and
+/// is blurred by approximating a gaussian blur extending up to the shadow radius beyond the
+/// rectangle. The shadow is not drawn below the window geometry itself, so it has to be rendered
+/// below the elements of the window but is not visible through translucent windows.
+#[derive(Debug, Clone)]
+pub struct ShadowElement {
+    program: GlesPixelProgram,
+    id: Id,
+    commit_counter: CommitCounter,
+    geometry: Rectangle<i32, Logical>,
+    radius: i32,
+    offset: Point<i32, Logical>,
+    corner_radius: f32,
+    color: Color32F,
+}
+
+impl ShadowElement {
+    /// Create a new shadow for a window with the given geometry
+    ///
+    /// The radius and offset are given in logical coordinates, the color is expected to be premultiplied.
+    pub fn new(
+        renderer: &mut GlesRenderer,
+        geometry: Rectangle<i32, Logical>,
+        radius: i32,
+        offset: impl Into<Point<i32, Logical>>,
+        color: impl Into<Color32F>,
+    ) -> Result<Self, GlesError> {
+        Ok(ShadowElement {
+            program: Self::program(renderer)?,
+            id: Id::new(),
+            commit_counter: CommitCounter::default(),
+            geometry,
+            radius: radius.max(0),
+            offset: offset.into(),
+            corner_radius: 0.0,
+            color: color.into(),
+        })
+    }
+
+    /// Returns the pixel shader used for drawing shadows, compiling it if necessary
+    pub fn program(renderer: &mut GlesRenderer) -> Result<GlesPixelProgram, GlesError> {
+        if let Some(program) = renderer.egl_context().user_data().get::<ShadowProgram>() {
+            return Ok(program.0.clone());
+        }
+
+        let program = renderer.compile_custom_pixel_shader(
+            SHADOW_SHADER,
+            &[
+                UniformName::new("color", UniformType::_4f),
+                UniformName::new("shadow_rect", UniformType::_4f),
+                UniformName::new("window_rect", UniformType::_4f),
+                UniformName::new("corner_radius", UniformType::_1f),
+                UniformName::new("sigma", UniformType::_1f),
+            ],
+        )?;
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| ShadowProgram(program.clone()));
+        Ok(program)
+    }
+
+    /// Update the geometry of the window casting the shadow
+    pub fn set_geometry(&mut self, geometry: Rectangle<i32, Logical>) {
+        // Moving the element is picked up by the damage tracker, only a resize changes its content
+        if self.geometry.size != geometry.size {
+            self.commit_counter.increment();
+        }
+        self.geometry = geometry;
+    }
+
+    /// Update the radius, offset and color of the shadow
+    pub fn set_shadow(
+        &mut self,
+        radius: i32,
+        offset: impl Into<Point<i32, Logical>>,
+        color: impl Into<Color32F>,
+    ) {
+        let radius = radius.max(0);
+        let offset = offset.into();
+        let color = color.into();
+        if self.radius != radius || self.offset != offset || self.color != color {
+            self.radius = radius;
+            self.offset = offset;
+            self.color = color;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Set the corner radius of the window casting the shadow
+    pub fn set_corner_radius(&mut self, corner_radius: f32) {
+        if self.corner_radius != corner_radius {
+            self.corner_radius = corner_radius;
+            self.commit_counter.increment();
+        }
+    }
+
+    /// Returns the area covered by the shadow
+    pub fn area(&self) -> Rectangle<i32, Logical> {
+        shadow_area(self.geometry, self.radius, self.offset)
+    }
+}
+
+fn shadow_area(
+    geometry: Rectangle<i32, Logical>,
+    radius: i32,
+    offset: Point<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    Rectangle::from_loc_and_size(
+        geometry.loc + offset - Point::from((radius, radius)),
+        (geometry.size.w + 2 * radius, geometry.size.h + 2 * radius),
+    )
+}
+
+impl Element for ShadowElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        let area = self.area();
+        area.to_f64()
+            .to_buffer(1.0, Transform::Normal, &area.size.to_f64())
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.area().to_physical_precise_round(scale)
+    }
+}
+
+impl RenderElement<GlesRenderer> for ShadowElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let area = self.area();
+        let scale_x = dst.size.w as f32 / area.size.w.max(1) as f32;
+        let scale_y = dst.size.h as f32 / area.size.h.max(1) as f32;
+        let radius = self.radius as f32;
+        let (width, height) = (self.geometry.size.w as f32, self.geometry.size.h as f32);
+
+        let uniforms = [
+            Uniform::new("color", self.color.components()),
+            Uniform::new(
+                "shadow_rect",
+                [
+                    radius * scale_x,
+                    radius * scale_y,
+                    width * scale_x,
+                    height * scale_y,
+                ],
+            ),
+            Uniform::new(
+                "window_rect",
+                [
+                    (radius - self.offset.x as f32) * scale_x,
+                    (radius - self.offset.y as f32) * scale_y,
+                    width * scale_x,
+                    height * scale_y,
+                ],
+            ),
+            Uniform::new("corner_radius", self.corner_radius * scale_x),
+            Uniform::new("sigma", radius * scale_x / 3.0),
+        ];
+        frame.render_pixel_shader_to(&self.program, dst, Some(damage), 1.0, &uniforms)
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+struct BorderProgram(GlesPixelProgram);
+
+fn border_program(renderer: &mut GlesRenderer) -> Result<GlesPixelProgram, GlesError> {
+    if let Some(program) = renderer.egl_context().user_data().get::<BorderProgram>() {
+        return Ok(program.0.clone());
+    }
+
+    let program = renderer.compile_custom_pixel_shader(
+        BORDER_SHADER,
+        &[
+            UniformName::new("inner_rect", UniformType::_4f),
+            UniformName::new("inner_radius", UniformType::_1f),
+            UniformName::new("outer_radius", UniformType::_1f),
+            UniformName::new("start_color", UniformType::_4f),
+            UniformName::new("end_color", UniformType::_4f),
+            UniformName::new("gradient", UniformType::_2f),
+        ],
+    )?;
+    renderer
+        .egl_context()
+        .user_data()
+        .insert_if_missing(|| BorderProgram(program.clone()));
+    Ok(program)
+}
+
+impl RenderElement<GlesRenderer> for BorderRenderElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_>,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let program = border_program(frame.renderer)?;
+        let shape = self.shape(dst.size);
+        // the rectangle inside of the border never has to be drawn
+        let bounds = shape.bounds();
+        let damage = damage
+            .iter()
+            .flat_map(|damage| bounds.iter().filter_map(|rect| rect.intersection(*damage)))
+            .collect::<Vec<_>>();
+
+        let uniforms = [
+            Uniform::new("inner_rect", shape.inner),
+            Uniform::new("inner_radius", shape.inner_radius),
+            Uniform::new("outer_radius", shape.outer_radius),
+            Uniform::new("start_color", shape.start.components()),
+            Uniform::new("end_color", shape.end.components()),
+            Uniform::new("gradient", shape.gradient),
+        ];
+        frame.render_pixel_shader_to(&program, dst, Some(&damage), 1.0, &uniforms)
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+// Squares containing the rounded corners of a rectangle
+fn rounded_corners(rect: Rectangle<i32, Physical>, radii: [f32; 4]) -> [Rectangle<i32, Physical>; 4] {
+    let [top_left, top_right, bottom_right, bottom_left] = radii.map(|radius| radius.ceil() as i32);
+    let right = rect.loc.x + rect.size.w;
+    let bottom = rect.loc.y + rect.size.h;
+    [
+        Rectangle::from_loc_and_size(rect.loc, (top_left, top_left)),
+        Rectangle::from_loc_and_size((right - top_right, rect.loc.y), (top_right, top_right)),
+        Rectangle::from_loc_and_size(
+            (right - bottom_right, bottom - bottom_right),
+            (bottom_right, bottom_right),
+        ),
+        Rectangle::from_loc_and_size((rect.loc.x, bottom - bottom_left), (bottom_left, bottom_left)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rounded_corners, shadow_area};
+    use crate::utils::{Point, Rectangle};
+
+    #[test]
+    fn rounded_corners_are_excluded() {
+        let rect = Rectangle::from_loc_and_size((10, 10), (100, 50));
+        let corners = rounded_corners(rect, [4.0, 0.0, 8.5, 2.0]);
+        assert_eq!(corners[0], Rectangle::from_loc_and_size((10, 10), (4, 4)));
+        assert!(corners[1].is_empty());
+        assert_eq!(corners[2], Rectangle::from_loc_and_size((101, 51), (9, 9)));
+        assert_eq!(corners[3], Rectangle::from_loc_and_size((10, 58), (2, 2)));
+
+        let opaque = rect.subtract_rects(corners);
+        assert_eq!(
+            opaque.iter().map(|rect| rect.size.w * rect.size.h).sum::<i32>(),
+            100 * 50 - 16 - 81 - 4
+        );
+    }
+
+    #[test]
+    fn shadow_area_contains_blurred_rect() {
+        let geometry = Rectangle::from_loc_and_size((100, 100), (200, 150));
+        assert_eq!(
+            shadow_area(geometry, 20, Point::from((0, 10))),
+            Rectangle::from_loc_and_size((80, 90), (240, 190))
+        );
+        assert_eq!(shadow_area(geometry, 0, Point::default()), geometry);
+    }
+}
@@ -25,6 +25,22 @@ pub const fn fourcc_to_gl_formats(value: Fourcc) -> Option<(GLenum, GLenum, GLen
     }
 }
 
+/// Returns the format with swapped red and blue channels for formats, that are only
+/// available in one channel order in GL
+pub(super) const fn swap_red_blue(value: Fourcc) -> Option<Fourcc> {
+    match value {
+        Fourcc::Argb2101010 => Some(Fourcc::Abgr2101010),
+        Fourcc::Xrgb2101010 => Some(Fourcc::Xbgr2101010),
+        Fourcc::Abgr2101010 => Some(Fourcc::Argb2101010),
+        Fourcc::Xbgr2101010 => Some(Fourcc::Xrgb2101010),
+        Fourcc::Argb16161616f => Some(Fourcc::Abgr16161616f),
+        Fourcc::Xrgb16161616f => Some(Fourcc::Xbgr16161616f),
+        Fourcc::Abgr16161616f => Some(Fourcc::Argb16161616f),
+        Fourcc::Xbgr16161616f => Some(Fourcc::Xrgb16161616f),
+        _ => None,
+    }
+}
+
 /// Returns the fourcc for a given internal format
 pub const fn gl_internal_format_to_fourcc(format: GLenum) -> Option<Fourcc> {
     match format {
@@ -4,7 +4,7 @@ use cgmath::{prelude::*, Matrix3, Vector2};
 use core::slice;
 use std::{
     collections::HashMap,
-    ffi::{CStr, CString},
+    ffi::CStr,
     fmt, mem,
     os::raw::c_char,
     ptr,
@@ -20,31 +20,39 @@ use tracing::{debug, error, info, info_span, instrument, span, span::EnteredSpan
 #[cfg(feature = "wayland_frontend")]
 use std::sync::Mutex;
 
+pub mod blur;
+mod cache;
+pub mod color_transform;
 pub mod element;
 mod error;
 pub mod format;
 mod shaders;
+mod stats;
 mod texture;
 mod uniform;
 mod version;
+mod yuv;
 
+pub use cache::{TextureCacheBudget, TextureCacheStats};
 pub use error::*;
 use format::*;
 pub use shaders::*;
+pub use stats::FrameStats;
 pub use texture::*;
 pub use uniform::*;
+pub use yuv::{YuvColorSpace, YuvRange};
 
 use self::version::GlVersion;
 
 use super::{
-    sync::SyncPoint, Bind, Blit, Color32F, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen,
-    Renderer, Texture, TextureFilter, TextureMapping, Unbind,
+    sync::SyncPoint, Bind, Blit, Color32F, DebugFlags, ExportDma, ExportMem, Frame, ImportDma, ImportMem,
+    Offscreen, Renderer, RendererCapabilities, RendererId, Texture, TextureFilter, TextureMapping, Unbind,
 };
 use crate::backend::{
     allocator::{
         dmabuf::{Dmabuf, WeakDmabuf},
         format::{get_bpp, get_opaque, has_alpha, FormatSet},
-        Format, Fourcc,
+        Format, Fourcc, Slot,
     },
     egl::fence::EGLFence,
 };
@@ -60,27 +68,19 @@ use crate::{
 #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
 use super::ImportEgl;
 #[cfg(feature = "wayland_frontend")]
-use super::{ImportDmaWl, ImportMemWl};
+use super::{utils::convert, ImportDmaWl, ImportMemWl};
 #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
 use crate::backend::egl::{display::EGLBufferReader, Format as EGLFormat};
 #[cfg(feature = "wayland_frontend")]
-use crate::wayland::shm::shm_format_to_fourcc;
+use crate::wayland::shm::{fourcc_to_shm_format, shm_format_to_fourcc};
 #[cfg(feature = "wayland_frontend")]
-use wayland_server::protocol::wl_buffer;
+use wayland_server::protocol::{wl_buffer, wl_shm};
 
 #[allow(clippy::all, missing_docs, missing_debug_implementations)]
 pub mod ffi {
     include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
 }
 
-crate::utils::ids::id_gen!(renderer_id);
-struct RendererId(usize);
-impl Drop for RendererId {
-    fn drop(&mut self) {
-        renderer_id::remove(self.0);
-    }
-}
-
 enum CleanupResource {
     Texture(ffi::types::GLuint),
     FramebufferObject(ffi::types::GLuint),
@@ -103,6 +103,10 @@ struct GlesBuffer {
 ///
 /// Usually more performant than using a texture as a framebuffer.
 /// Can be read out, but not used like a texture otherwise.
+///
+/// Renderbuffers created with [`GlesRenderer::create_multisample_buffer`] are multisampled.
+/// Their contents can not be read out directly, but have to be resolved into a single-sampled
+/// target first using [`GlesRenderer::resolve_to`].
 #[derive(Debug, Clone)]
 pub struct GlesRenderbuffer(Rc<GlesRenderbufferInternal>);
 
@@ -112,6 +116,7 @@ struct GlesRenderbufferInternal {
     format: ffi::types::GLenum,
     has_alpha: bool,
     size: Size<i32, BufferCoord>,
+    samples: u32,
     destruction_callback_sender: Sender<CleanupResource>,
 }
 
@@ -121,6 +126,11 @@ impl GlesRenderbuffer {
         self.0.size
     }
 
+    /// Number of samples per pixel, `1` if the renderbuffer is not multisampled
+    pub fn samples(&self) -> u32 {
+        self.0.samples
+    }
+
     /// Internal format of the renderbuffer
     pub fn format(&self) -> Option<Fourcc> {
         let fmt = gl_internal_format_to_fourcc(self.0.format);
@@ -238,12 +248,25 @@ pub enum Capability {
     Blit,
     /// GlesRenderer supports 10 bit formats
     _10Bit,
+    /// GlesRenderer supports rendering into half-float formats
+    Fp16,
     /// GlesRenderer supports creating of Renderbuffers with usable formats
     Renderbuffer,
     /// GlesRenderer supports fencing,
     Fencing,
     /// GlesRenderer supports GL debug
     Debug,
+    /// GlesRenderer batches consecutive draws sharing their state into as few draw calls as possible
+    ///
+    /// Textured quads using the same texture, program, uniforms, alpha and blending are drawn together,
+    /// instanced with [`Capability::Instancing`] and from expanded vertices otherwise.
+    /// Batching solid colors from [`Frame::draw_solid`] additionally requires [`Capability::Instancing`].
+    /// Any other draw operation flushes the pending batches first, see [`GlesFrame::flush_batches`].
+    Batching,
+    /// GlesRenderer supports measuring the gpu time of frames, see [`GlesRenderer::set_gpu_timing`]
+    TimerQuery,
+    /// GlesRenderer supports multisampled renderbuffers, see [`GlesRenderer::create_multisample_buffer`]
+    Multisample,
 }
 
 /// A renderer utilizing OpenGL ES
@@ -253,6 +276,9 @@ pub struct GlesRenderer {
     min_filter: TextureFilter,
     max_filter: TextureFilter,
     debug_flags: DebugFlags,
+    yuv_plane_import: bool,
+    yuv_color_space: YuvColorSpace,
+    yuv_range: YuvRange,
 
     // internals
     egl: EGLContext,
@@ -264,18 +290,27 @@ pub struct GlesRenderer {
     gl_version: GlVersion,
     pub(crate) extensions: Vec<String>,
     capabilities: Vec<Capability>,
+    max_texture_size: i32,
+    max_samples: u32,
 
     // shaders
     tex_program: GlesTexProgram,
     solid_program: GlesSolidProgram,
+    solid_batch_program: Option<GlesSolidBatchProgram>,
 
     // caches
     buffers: Vec<GlesBuffer>,
-    dmabuf_cache: std::collections::HashMap<WeakDmabuf, GlesTexture>,
+    dmabuf_cache: cache::TextureCache<WeakDmabuf, GlesTexture>,
     vbos: [ffi::types::GLuint; 2],
     vertices: Vec<f32>,
     non_opaque_damage: Vec<Rectangle<i32, Physical>>,
     opaque_damage: Vec<Rectangle<i32, Physical>>,
+    // pending solid color quads of the current frame, see `GlesFrame::flush_batches`
+    solid_batch: Vec<f32>,
+    // state and quads of pending textured draws of the current frame
+    texture_batch: Option<TextureBatch>,
+    texture_quads: Vec<f32>,
+    stats: stats::StatsRecorder,
 
     // cleanup
     destruction_callback: Receiver<CleanupResource>,
@@ -398,6 +433,8 @@ impl GlesRenderer {
             capabilities.push(Capability::Instancing);
             debug!("Instancing is supported");
         }
+        // textured quads can always be batched, instancing only makes it cheaper
+        capabilities.push(Capability::Batching);
         // required to use 8-bit color formats in renderbuffers, we don't deal with anything lower as a render target
         if gl_version >= version::GLES_3_0 || exts.iter().any(|ext| ext == "GL_OES_rgb8_rgba8") {
             capabilities.push(Capability::Renderbuffer);
@@ -407,8 +444,18 @@ impl GlesRenderer {
         if gl_version >= version::GLES_3_0 {
             capabilities.push(Capability::Blit);
             debug!("Blitting is supported");
+            capabilities.push(Capability::Multisample);
+            debug!("Multisampled renderbuffers are supported");
             capabilities.push(Capability::_10Bit);
             debug!("10-bit formats are supported");
+
+            if exts
+                .iter()
+                .any(|ext| ext == "GL_EXT_color_buffer_half_float" || ext == "GL_EXT_color_buffer_float")
+            {
+                capabilities.push(Capability::Fp16);
+                debug!("Rendering into half-float formats is supported");
+            }
         }
 
         if exts.iter().any(|ext| ext == "GL_OES_EGL_sync") {
@@ -421,6 +468,11 @@ impl GlesRenderer {
             debug!("GL Debug is supported");
         }
 
+        if exts.iter().any(|ext| ext == "GL_EXT_disjoint_timer_query") {
+            capabilities.push(Capability::TimerQuery);
+            debug!("Timer queries are supported");
+        }
+
         Ok(capabilities)
     }
 
@@ -477,13 +529,22 @@ impl GlesRenderer {
 
         if let Some(missing_capability) = unsupported_capabilities.first() {
             let err = match missing_capability {
-                Capability::Instancing => {
+                Capability::Instancing | Capability::Batching => {
                     GlesError::GLExtensionNotSupported(&["GL_EXT_instanced_arrays", "GL_EXT_draw_instanced"])
                 }
-                Capability::Blit | Capability::_10Bit => GlesError::GLVersionNotSupported(version::GLES_3_0),
+                Capability::Blit | Capability::_10Bit | Capability::Multisample => {
+                    GlesError::GLVersionNotSupported(version::GLES_3_0)
+                }
                 Capability::Renderbuffer => GlesError::GLExtensionNotSupported(&["GL_OES_rgb8_rgba8"]),
+                Capability::Fp16 => GlesError::GLExtensionNotSupported(&[
+                    "GL_EXT_color_buffer_half_float",
+                    "GL_EXT_color_buffer_float",
+                ]),
                 Capability::Fencing => GlesError::GLExtensionNotSupported(&["GL_OES_EGL_sync"]),
                 Capability::Debug => GlesError::GLExtensionNotSupported(&["GL_KHR_debug"]),
+                Capability::TimerQuery => {
+                    GlesError::GLExtensionNotSupported(&["GL_EXT_disjoint_timer_query"])
+                }
             };
             return Err(err);
         };
@@ -549,6 +610,13 @@ impl GlesRenderer {
         let (tx, rx) = channel();
         let tex_program = texture_program(&gl, shaders::FRAGMENT_SHADER, &[], tx.clone())?;
         let solid_program = solid_program(&gl)?;
+        let solid_batch_program = if capabilities.contains(&Capability::Batching)
+            && capabilities.contains(&Capability::Instancing)
+        {
+            Some(solid_batch_program(&gl)?)
+        } else {
+            None
+        };
 
         // Initialize vertices based on drawing methodology.
         let vertices: &[ffi::types::GLfloat] = if capabilities.contains(&Capability::Instancing) {
@@ -557,6 +625,13 @@ impl GlesRenderer {
             &TRIANGLE_VERTS
         };
 
+        let mut max_texture_size = 0;
+        gl.GetIntegerv(ffi::MAX_TEXTURE_SIZE, &mut max_texture_size);
+        let mut max_samples = 1;
+        if capabilities.contains(&Capability::Multisample) {
+            gl.GetIntegerv(ffi::MAX_SAMPLES, &mut max_samples);
+        }
+
         let mut vbos = [0; 2];
         gl.GenBuffers(vbos.len() as i32, vbos.as_mut_ptr());
         gl.BindBuffer(ffi::ARRAY_BUFFER, vbos[0]);
@@ -575,9 +650,7 @@ impl GlesRenderer {
         );
         gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
 
-        context
-            .user_data()
-            .insert_if_missing_threadsafe(|| RendererId(renderer_id::next()));
+        context.user_data().insert_if_missing_threadsafe(RendererId::new);
         drop(_guard);
 
         let renderer = GlesRenderer {
@@ -589,24 +662,34 @@ impl GlesRenderer {
             extensions: exts,
             gl_version,
             capabilities,
+            max_texture_size,
+            max_samples: max_samples.max(1) as u32,
 
             tex_program,
             solid_program,
+            solid_batch_program,
             vbos,
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
 
             target: None,
             buffers: Vec::new(),
-            dmabuf_cache: std::collections::HashMap::new(),
+            dmabuf_cache: cache::TextureCache::new(),
             vertices: Vec::with_capacity(6 * 16),
             non_opaque_damage: Vec::with_capacity(16),
             opaque_damage: Vec::with_capacity(16),
+            solid_batch: Vec::new(),
+            texture_batch: None,
+            texture_quads: Vec::new(),
+            stats: stats::StatsRecorder::new(),
 
             destruction_callback: rx,
             destruction_callback_sender: tx,
 
             debug_flags: DebugFlags::empty(),
+            yuv_plane_import: true,
+            yuv_color_space: YuvColorSpace::default(),
+            yuv_range: YuvRange::default(),
             _not_send: std::ptr::null_mut(),
             span,
             gl_debug_span,
@@ -628,7 +711,7 @@ impl GlesRenderer {
     #[profiling::function]
     fn cleanup(&mut self) {
         #[cfg(feature = "wayland_frontend")]
-        self.dmabuf_cache.retain(|entry, _tex| !entry.is_gone());
+        self.dmabuf_cache.retain(|entry| !entry.is_gone());
         // Free outdated buffer resources
         // TODO: Replace with `drain_filter` once it lands
         let mut i = 0;
@@ -681,6 +764,16 @@ impl GlesRenderer {
 
 #[cfg(feature = "wayland_frontend")]
 impl ImportMemWl for GlesRenderer {
+    fn shm_formats(&self) -> Box<dyn Iterator<Item = wl_shm::Format>> {
+        // formats not supported by the gpu are converted on import
+        let native = self.mem_formats().collect::<Vec<_>>();
+        let converted = convert::supported_formats().filter({
+            let native = native.clone();
+            move |format| !native.contains(format)
+        });
+        Box::new(native.into_iter().chain(converted).flat_map(fourcc_to_shm_format))
+    }
+
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     fn import_shm_buffer(
@@ -705,17 +798,32 @@ impl ImportMemWl for GlesRenderer {
             let fourcc =
                 shm_format_to_fourcc(data.format).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
 
-            if self.gl_version.major >= 3 {
-                if !SUPPORTED_MEM_FORMATS_3.contains(&fourcc) {
-                    return Err(GlesError::UnsupportedWlPixelFormat(data.format));
-                }
-            } else if !SUPPORTED_MEM_FORMATS_2.contains(&fourcc) {
+            // Formats we can not sample from are converted on the cpu
+            let converted = if self.mem_formats().any(|format| format == fourcc) {
+                None
+            } else if convert::is_supported(fourcc) {
+                let converted_format = if has_alpha(fourcc) {
+                    Fourcc::Argb8888
+                } else {
+                    Fourcc::Xrgb8888
+                };
+                let src =
+                    unsafe { slice::from_raw_parts(ptr.offset(offset as isize), len - offset as usize) };
+                let converted =
+                    convert::convert_to_vec(src, fourcc, stride as usize, converted_format, (width, height))
+                        .map_err(|_| GlesError::UnsupportedWlPixelFormat(data.format))?;
+                Some((converted_format, converted))
+            } else {
                 return Err(GlesError::UnsupportedWlPixelFormat(data.format));
-            }
+            };
+            let (fourcc, ptr, len, offset, stride) = match converted.as_ref() {
+                Some((format, converted)) => (*format, converted.as_ptr(), converted.len(), 0, width * 4),
+                None => (fourcc, ptr, len, offset, stride),
+            };
 
             let has_alpha = has_alpha(fourcc);
-            let (mut internal_format, read_format, type_) =
-                fourcc_to_gl_formats(fourcc).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
+            let ((mut internal_format, read_format, type_), swapped_rb) =
+                mem_format_to_gl_formats(fourcc).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
             if self.gl_version.major == 2 {
                 // es 2.0 doesn't define sized variants
                 internal_format = match internal_format {
@@ -749,7 +857,12 @@ impl ImportMemWl for GlesRenderer {
                             .get(&id)
                             .cloned()
                     })
-                    .filter(|texture| texture.size == (width, height).into())
+                    .filter(|texture| {
+                        texture.size == (width, height).into()
+                            && texture.format == Some(internal_format)
+                            && texture.has_alpha == has_alpha
+                            && texture.swapped_rb == swapped_rb
+                    })
                     .unwrap_or_else(|| {
                         let mut tex = 0;
                         unsafe { self.gl.GenTextures(1, &mut tex) };
@@ -761,8 +874,10 @@ impl ImportMemWl for GlesRenderer {
                             has_alpha,
                             is_external: false,
                             y_inverted: false,
+                            swapped_rb,
                             size: (width, height).into(),
                             egl_images: None,
+                            yuv: None,
                             destruction_callback_sender: self.destruction_callback_sender.clone(),
                         });
                         if let Some(surface) = surface {
@@ -788,8 +903,13 @@ impl ImportMemWl for GlesRenderer {
                 self.gl
                     .PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize as i32);
 
+                if upload_full && swapped_rb {
+                    swizzle_red_blue(&self.gl);
+                }
+
                 if upload_full || damage.is_empty() {
                     trace!("Uploading shm texture");
+                    self.stats.record_upload((height * stride) as usize);
                     self.gl.TexImage2D(
                         ffi::TEXTURE_2D,
                         0,
@@ -802,8 +922,10 @@ impl ImportMemWl for GlesRenderer {
                         ptr.offset(offset as isize) as *const _,
                     );
                 } else {
-                    for region in damage.iter() {
+                    for region in super::utils::texture_upload_regions(damage, (width, height).into()) {
                         trace!("Uploading partial shm texture");
+                        self.stats
+                            .record_upload((region.size.w * region.size.h * pixelsize as i32) as usize);
                         self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, region.loc.x);
                         self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, region.loc.y);
                         self.gl.TexSubImage2D(
@@ -845,10 +967,32 @@ const SUPPORTED_MEM_FORMATS_3: &[Fourcc] = &[
     Fourcc::Xrgb8888,
     Fourcc::Abgr2101010,
     Fourcc::Xbgr2101010,
+    Fourcc::Argb2101010,
+    Fourcc::Xrgb2101010,
     Fourcc::Abgr16161616f,
     Fourcc::Xbgr16161616f,
+    Fourcc::Argb16161616f,
+    Fourcc::Xrgb16161616f,
 ];
 
+// Returns the gl formats of a memory format and if red and blue have to be swizzled on sampling
+fn mem_format_to_gl_formats(
+    format: Fourcc,
+) -> Option<((ffi::types::GLenum, ffi::types::GLenum, ffi::types::GLenum), bool)> {
+    fourcc_to_gl_formats(format)
+        .map(|formats| (formats, false))
+        .or_else(|| {
+            swap_red_blue(format)
+                .and_then(fourcc_to_gl_formats)
+                .map(|formats| (formats, true))
+        })
+}
+
+unsafe fn swizzle_red_blue(gl: &ffi::Gles2) {
+    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_SWIZZLE_R, ffi::BLUE as i32);
+    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_SWIZZLE_B, ffi::RED as i32);
+}
+
 impl ImportMem for GlesRenderer {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
@@ -877,8 +1021,8 @@ impl ImportMem for GlesRenderer {
         }
 
         let has_alpha = has_alpha(format);
-        let (mut internal, format, layout) =
-            fourcc_to_gl_formats(format).expect("We check the format before");
+        let ((mut internal, format, layout), swapped_rb) =
+            mem_format_to_gl_formats(format).expect("We check the format before");
         if self.gl_version.major == 2 {
             // es 2.0 doesn't define sized variants
             internal = match internal {
@@ -909,8 +1053,12 @@ impl ImportMem for GlesRenderer {
                     layout,
                     data.as_ptr() as *const _,
                 );
+                if swapped_rb {
+                    swizzle_red_blue(&self.gl);
+                }
                 self.gl.BindTexture(ffi::TEXTURE_2D, 0);
             }
+            self.stats.record_upload(data.len());
             // new texture, upload in full
             GlesTextureInternal {
                 texture: tex,
@@ -918,8 +1066,10 @@ impl ImportMem for GlesRenderer {
                 has_alpha,
                 is_external: false,
                 y_inverted: flipped,
+                swapped_rb,
                 size,
                 egl_images: None,
+                yuv: None,
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }
         }));
@@ -946,10 +1096,8 @@ impl ImportMem for GlesRenderer {
         let (read_format, type_) = gl_read_for_internal(texture.0.format.expect("We check that before"))
             .ok_or(GlesError::UnknownPixelFormat)?;
 
-        if data.len()
-            < (region.size.w * region.size.h) as usize
-                * (gl_bpp(read_format, type_).ok_or(GlesError::UnknownPixelFormat)? / 8)
-        {
+        let bpp = gl_bpp(read_format, type_).ok_or(GlesError::UnknownPixelFormat)?;
+        if data.len() < (region.size.w * region.size.h) as usize * (bpp / 8) {
             return Err(GlesError::UnexpectedSize);
         }
 
@@ -978,6 +1126,8 @@ impl ImportMem for GlesRenderer {
             self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
             self.gl.BindTexture(ffi::TEXTURE_2D, 0);
         }
+        self.stats
+            .record_upload((region.size.w * region.size.h) as usize * (bpp / 8));
 
         Ok(())
     }
@@ -1057,8 +1207,10 @@ impl ImportEgl for GlesRenderer {
             has_alpha: !matches!(egl.format, EGLFormat::RGB),
             is_external: egl.format == EGLFormat::External,
             y_inverted: egl.y_inverted,
+            swapped_rb: false,
             size: egl.size,
             egl_images: Some(egl.into_images()),
+            yuv: None,
             destruction_callback_sender: self.destruction_callback_sender.clone(),
         }));
 
@@ -1081,6 +1233,17 @@ impl ImportDma for GlesRenderer {
 
         self.make_current()?;
         self.existing_dmabuf_texture(buffer)?.map(Ok).unwrap_or_else(|| {
+            if let Some(layout) = yuv::yuv_layout(buffer.format().code).filter(|_| self.yuv_plane_import) {
+                match self.import_yuv_dmabuf(buffer, layout) {
+                    Ok(texture) => {
+                        let size = buffer.width() as usize * buffer.height() as usize * 2;
+                        self.dmabuf_cache.insert(buffer.weak(), texture.clone(), size);
+                        return Ok(texture);
+                    }
+                    Err(err) => trace!(?err, "Falling back to importing yuv dmabuf as a single image"),
+                }
+            }
+
             let is_external = !self.egl.dmabuf_render_formats().contains(&buffer.format());
             let image = self
                 .egl
@@ -1089,9 +1252,10 @@ impl ImportDma for GlesRenderer {
                 .map_err(GlesError::BindBufferEGLError)?;
 
             let tex = self.import_egl_image(image, is_external, None)?;
-            let format = fourcc_to_gl_formats(buffer.format().code)
-                .map(|(internal, _, _)| internal)
-                .unwrap_or(ffi::RGBA8);
+            // EGL takes care of the channel order, `swapped_rb` only marks the format of the texture
+            let (format, swapped_rb) = mem_format_to_gl_formats(buffer.format().code)
+                .map(|((internal, _, _), swapped_rb)| (internal, swapped_rb))
+                .unwrap_or((ffi::RGBA8, false));
             let has_alpha = has_alpha(buffer.format().code);
             let texture = GlesTexture(Arc::new(GlesTextureInternal {
                 texture: tex,
@@ -1099,11 +1263,15 @@ impl ImportDma for GlesRenderer {
                 has_alpha,
                 is_external,
                 y_inverted: buffer.y_inverted(),
+                swapped_rb,
                 size: buffer.size(),
                 egl_images: Some(vec![image]),
+                yuv: None,
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }));
-            self.dmabuf_cache.insert(buffer.weak(), texture.clone());
+            let bpp = get_bpp(buffer.format().code).unwrap_or(32);
+            let size = buffer.width() as usize * buffer.height() as usize * bpp / 8;
+            self.dmabuf_cache.insert(buffer.weak(), texture.clone(), size);
             Ok(texture)
         })
     }
@@ -1122,7 +1290,7 @@ impl ImportDmaWl for GlesRenderer {}
 
 impl GlesRenderer {
     #[profiling::function]
-    fn existing_dmabuf_texture(&self, buffer: &Dmabuf) -> Result<Option<GlesTexture>, GlesError> {
+    fn existing_dmabuf_texture(&mut self, buffer: &Dmabuf) -> Result<Option<GlesTexture>, GlesError> {
         let Some(texture) = self.dmabuf_cache.get(&buffer.weak()) else {
             return Ok(None);
         };
@@ -1135,7 +1303,13 @@ impl GlesRenderer {
             let tex = Some(texture.0.texture);
             self.import_egl_image(egl_images[0], texture.0.is_external, tex)?;
         }
-        Ok(Some(texture.clone()))
+        for plane in texture.0.yuv.iter().flat_map(|yuv| yuv.textures()) {
+            if let Some(egl_images) = plane.0.egl_images.as_ref() {
+                self.import_egl_image(egl_images[0], false, Some(plane.0.texture))?;
+            }
+        }
+        self.dmabuf_cache.record_hit();
+        Ok(Some(texture))
     }
 
     #[profiling::function]
@@ -1338,6 +1512,29 @@ impl ExportMem for GlesRenderer {
     }
 }
 
+impl ExportDma for GlesRenderer {
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    fn export_texture(&mut self, texture: &GlesTexture) -> Result<Dmabuf, GlesError> {
+        if texture.0.is_external {
+            return Err(GlesError::UnsupportedPixelLayout);
+        }
+        self.make_current()?;
+
+        let image = unsafe { self.egl.create_image_from_texture(texture.0.texture) }
+            .map_err(GlesError::BindBufferEGLError)?;
+        let res = self
+            .egl
+            .display()
+            .create_dmabuf_from_image(image, texture.size(), texture.0.y_inverted)
+            .map_err(GlesError::BindBufferEGLError);
+        unsafe {
+            ffi_egl::DestroyImageKHR(**self.egl.display().get_display_handle(), image);
+        }
+        res
+    }
+}
+
 impl Bind<Rc<EGLSurface>> for GlesRenderer {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
@@ -1441,6 +1638,17 @@ impl Bind<Dmabuf> for GlesRenderer {
     }
 }
 
+impl<'a> Bind<&'a Slot<Dmabuf>> for GlesRenderer {
+    #[profiling::function]
+    fn bind(&mut self, slot: &'a Slot<Dmabuf>) -> Result<(), <Self as Renderer>::Error> {
+        self.bind((*slot).clone())
+    }
+
+    fn supported_formats(&self) -> Option<FormatSet> {
+        <Self as Bind<Dmabuf>>::supported_formats(self)
+    }
+}
+
 impl Bind<GlesTexture> for GlesRenderer {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
@@ -1506,6 +1714,9 @@ impl Offscreen<GlesTexture> for GlesRenderer {
         {
             return Err(GlesError::UnsupportedPixelLayout);
         }
+        if internal == ffi::RGBA16F && !self.capabilities.contains(&Capability::Fp16) {
+            return Err(GlesError::UnsupportedPixelLayout);
+        }
 
         let tex = unsafe {
             let mut tex = 0;
@@ -1574,6 +1785,68 @@ impl Offscreen<GlesRenderbuffer> for GlesRenderer {
         &mut self,
         format: Fourcc,
         size: Size<i32, BufferCoord>,
+    ) -> Result<GlesRenderbuffer, GlesError> {
+        self.create_renderbuffer(format, size, 1)
+    }
+}
+
+impl GlesRenderer {
+    /// Create a multisampled renderbuffer for offscreen rendering
+    ///
+    /// Rendering into a multisampled buffer reduces aliasing of scaled or rotated elements,
+    /// e.g. for downscaled window previews. After rendering, the contents have to be
+    /// resolved into a single-sampled target with [`GlesRenderer::resolve_to`].
+    ///
+    /// `samples` is clamped to [`GlesRenderer::max_samples`], the actual count is available
+    /// through [`GlesRenderbuffer::samples`]. A count of `1` creates a regular renderbuffer.
+    ///
+    /// Requires [`Capability::Multisample`] for more than one sample.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    pub fn create_multisample_buffer(
+        &mut self,
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+        samples: u32,
+    ) -> Result<GlesRenderbuffer, GlesError> {
+        if samples > 1 && !self.capabilities.contains(&Capability::Multisample) {
+            return Err(GlesError::GLVersionNotSupported(version::GLES_3_0));
+        }
+        self.create_renderbuffer(format, size, samples.clamp(1, self.max_samples))
+    }
+
+    /// Maximum number of samples supported for multisampled renderbuffers
+    ///
+    /// Returns `1` without [`Capability::Multisample`].
+    pub fn max_samples(&self) -> u32 {
+        self.max_samples
+    }
+
+    /// Resolve the currently bound multisampled renderbuffer into `target`
+    ///
+    /// The whole renderbuffer is copied, `target` has to be at least as large as the renderbuffer.
+    /// Fails with [`GlesError::BlitError`], if no renderbuffer is bound.
+    #[instrument(level = "trace", parent = &self.span, skip(self, target))]
+    #[profiling::function]
+    pub fn resolve_to<Target>(&mut self, target: Target) -> Result<(), GlesError>
+    where
+        Self: Bind<Target>,
+    {
+        let size = match self.target.as_ref() {
+            Some(GlesTarget::Renderbuffer { buf, .. }) => buf.size(),
+            _ => return Err(GlesError::BlitError),
+        };
+
+        // resolving requires identical source and destination rectangles
+        let rect = Rectangle::from_loc_and_size((0, 0), (size.w, size.h));
+        self.blit_to(target, rect, rect, TextureFilter::Nearest)
+    }
+
+    fn create_renderbuffer(
+        &mut self,
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+        samples: u32,
     ) -> Result<GlesRenderbuffer, GlesError> {
         if !self.capabilities.contains(&Capability::Renderbuffer) {
             return Err(GlesError::UnsupportedPixelFormat(format));
@@ -1587,13 +1860,26 @@ impl Offscreen<GlesRenderbuffer> for GlesRenderer {
         if internal != ffi::RGBA8 && !self.capabilities.contains(&Capability::_10Bit) {
             return Err(GlesError::UnsupportedPixelLayout);
         }
+        if internal == ffi::RGBA16F && !self.capabilities.contains(&Capability::Fp16) {
+            return Err(GlesError::UnsupportedPixelLayout);
+        }
 
         unsafe {
             let mut rbo = 0;
             self.gl.GenRenderbuffers(1, &mut rbo);
             self.gl.BindRenderbuffer(ffi::RENDERBUFFER, rbo);
-            self.gl
-                .RenderbufferStorage(ffi::RENDERBUFFER, internal, size.w, size.h);
+            if samples > 1 {
+                self.gl.RenderbufferStorageMultisample(
+                    ffi::RENDERBUFFER,
+                    samples as i32,
+                    internal,
+                    size.w,
+                    size.h,
+                );
+            } else {
+                self.gl
+                    .RenderbufferStorage(ffi::RENDERBUFFER, internal, size.w, size.h);
+            }
             self.gl.BindRenderbuffer(ffi::RENDERBUFFER, 0);
 
             Ok(GlesRenderbuffer(Rc::new(GlesRenderbufferInternal {
@@ -1601,6 +1887,7 @@ impl Offscreen<GlesRenderbuffer> for GlesRenderer {
                 format: internal,
                 has_alpha,
                 size,
+                samples,
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             })))
         }
@@ -1767,7 +2054,11 @@ impl Drop for GlesRenderer {
             if self.egl.make_current().is_ok() {
                 self.gl.BindFramebuffer(ffi::FRAMEBUFFER, 0);
                 self.gl.DeleteProgram(self.solid_program.program);
+                if let Some(program) = self.solid_batch_program.as_ref() {
+                    self.gl.DeleteProgram(program.program);
+                }
                 self.gl.DeleteBuffers(self.vbos.len() as i32, self.vbos.as_ptr());
+                self.stats.destroy(&self.gl);
 
                 if self.extensions.iter().any(|ext| ext == "GL_KHR_debug") {
                     self.gl.Disable(ffi::DEBUG_OUTPUT);
@@ -1798,6 +2089,97 @@ impl GlesRenderer {
         &self.egl
     }
 
+    /// Creates a new [`EGLContext`] sharing textures and other resources with the context of this renderer
+    ///
+    /// The new context can be used to run custom GL code, e.g. for drawing user interfaces into
+    /// textures of this renderer or compiling shaders on another thread. Use
+    /// [`EGLContext::make_current_scoped`] to activate it, which restores the context of the renderer
+    /// afterwards.
+    ///
+    /// Rendering commands of the shared context are not synchronized with this renderer. Use a fence
+    /// (e.g. [`EGLFence`]) or `glFinish` before using resources modified by it.
+    pub fn create_shared_context(&self) -> Result<EGLContext, crate::backend::egl::Error> {
+        EGLContext::new_shared(self.egl.display(), &self.egl)
+    }
+
+    /// Returns the statistics of the most recent frame, for which all statistics are available
+    ///
+    /// With [gpu timing](GlesRenderer::set_gpu_timing) enabled the statistics of a frame are
+    /// only available after the gpu finished rendering it, usually a few frames later.
+    pub fn frame_stats(&self) -> Option<FrameStats> {
+        self.stats.last()
+    }
+
+    /// Returns whether the gpu time of frames is measured
+    pub fn gpu_timing(&self) -> bool {
+        self.stats.gpu_timing()
+    }
+
+    /// Enable or disable measuring the gpu time of frames, see [`FrameStats::gpu_time`]
+    ///
+    /// Requires [`Capability::TimerQuery`]. Disabled by default.
+    pub fn set_gpu_timing(&mut self, enabled: bool) -> Result<(), GlesError> {
+        if enabled && !self.capabilities.contains(&Capability::TimerQuery) {
+            return Err(GlesError::GLExtensionNotSupported(&[
+                "GL_EXT_disjoint_timer_query",
+            ]));
+        }
+        self.stats.set_gpu_timing(enabled);
+        Ok(())
+    }
+
+    /// Returns statistics about the cache of imported dmabuf textures
+    pub fn texture_cache_stats(&self) -> TextureCacheStats {
+        self.dmabuf_cache.stats()
+    }
+
+    /// Returns the limits of the cache of imported dmabuf textures
+    pub fn texture_cache_budget(&self) -> TextureCacheBudget {
+        self.dmabuf_cache.budget()
+    }
+
+    /// Limit the cache of imported dmabuf textures
+    ///
+    /// By default the cache is unlimited and entries are only removed once their dmabuf is destroyed.
+    pub fn set_texture_cache_budget(&mut self, budget: TextureCacheBudget) {
+        self.dmabuf_cache.set_budget(budget);
+    }
+
+    /// Set whether multi-planar YUV dmabufs are imported as separate planes
+    ///
+    /// If enabled (the default) each plane is imported on its own and converted to RGB by the `YUV` variant
+    /// of the texture program using the matrix set by [`GlesRenderer::set_yuv_color_space`]. Otherwise or if
+    /// the planes cannot be imported individually, the conversion is left to the EGL implementation.
+    pub fn set_yuv_plane_import(&mut self, enabled: bool) {
+        self.yuv_plane_import = enabled;
+    }
+
+    /// Returns whether multi-planar YUV dmabufs are imported as separate planes
+    pub fn yuv_plane_import(&self) -> bool {
+        self.yuv_plane_import
+    }
+
+    /// Set the color space and range used to convert YUV textures imported as separate planes
+    ///
+    /// Defaults to limited range [`YuvColorSpace::Bt601`], matching most EGL implementations.
+    pub fn set_yuv_color_space(&mut self, color_space: YuvColorSpace, range: YuvRange) {
+        self.yuv_color_space = color_space;
+        self.yuv_range = range;
+    }
+
+    /// Returns the color space and range used to convert YUV textures imported as separate planes
+    pub fn yuv_color_space(&self) -> (YuvColorSpace, YuvRange) {
+        (self.yuv_color_space, self.yuv_range)
+    }
+
+    /// Remove all textures from the cache of imported dmabuf textures
+    ///
+    /// This should be called if the imported textures might have become invalid, e.g. after a gpu reset.
+    /// The dmabufs are re-imported on their next use.
+    pub fn clear_texture_cache(&mut self) {
+        self.dmabuf_cache.clear();
+    }
+
     /// Run custom code in the GL context owned by this renderer.
     ///
     /// The OpenGL state of the renderer is considered an implementation detail
@@ -1831,6 +2213,9 @@ impl GlesRenderer {
     /// Additional uniform values can be defined by passing `UniformName`s to the `additional_uniforms` argument
     /// and can then be set in functions utilizing `GlesPixelProgram` (like [`GlesFrame::render_pixel_shader_to`]).
     ///
+    /// Uniforms of type [`UniformType::Texture`] are declared as `sampler2D` (or `samplerExternalOES` for
+    /// external textures) and are set to a [`GlesTexture`] using [`UniformValue::Texture`].
+    ///
     /// The shader must **not** contain a `#version` directive. It will be interpreted as version 100.
     ///
     /// ## Panics
@@ -1878,22 +2263,7 @@ impl GlesRenderer {
                     attrib_position: self
                         .gl
                         .GetAttribLocation(program, vert_position.as_ptr() as *const ffi::types::GLchar),
-                    additional_uniforms: additional_uniforms
-                        .iter()
-                        .map(|uniform| {
-                            let name = CString::new(uniform.name.as_bytes()).expect("Interior null in name");
-                            let location = self
-                                .gl
-                                .GetUniformLocation(program, name.as_ptr() as *const ffi::types::GLchar);
-                            (
-                                uniform.name.clone().into_owned(),
-                                UniformDesc {
-                                    location,
-                                    type_: uniform.type_,
-                                },
-                            )
-                        })
-                        .collect(),
+                    additional_uniforms: uniform_descs(&self.gl, program, additional_uniforms),
                 },
                 debug: GlesPixelProgramInternal {
                     program: debug_program,
@@ -1916,23 +2286,7 @@ impl GlesRenderer {
                         debug_program,
                         vert_position.as_ptr() as *const ffi::types::GLchar,
                     ),
-                    additional_uniforms: additional_uniforms
-                        .iter()
-                        .map(|uniform| {
-                            let name = CString::new(uniform.name.as_bytes()).expect("Interior null in name");
-                            let location = self.gl.GetUniformLocation(
-                                debug_program,
-                                name.as_ptr() as *const ffi::types::GLchar,
-                            );
-                            (
-                                uniform.name.clone().into_owned(),
-                                UniformDesc {
-                                    location,
-                                    type_: uniform.type_,
-                                },
-                            )
-                        })
-                        .collect(),
+                    additional_uniforms: uniform_descs(&self.gl, debug_program, additional_uniforms),
                 },
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
                 uniform_tint: self
@@ -1947,6 +2301,10 @@ impl GlesRenderer {
     /// They need to handle the following #define variants:
     /// - `EXTERNAL` uses samplerExternalOES instead of sampler2D, requires the GL_OES_EGL_image_external extension
     /// - `NO_ALPHA` needs to ignore the alpha channel of the texture and replace it with 1.0
+    /// - `YUV` `tex` only contains the luma plane of a multi-planar YUV texture, colors need to be sampled
+    ///   with `yuv_texture2D(tex, coords)` instead, which is declared by the renderer. Shaders not
+    ///   mentioning `YUV` get all their `texture2D` calls redirected to `yuv_texture2D`, so they have to
+    ///   handle the define themselves when sampling other textures.
     /// - `DEBUG_FLAGS` see below
     ///
     /// They receive the following variables:
@@ -1958,6 +2316,9 @@ impl GlesRenderer {
     /// Additional uniform values can be defined by passing `UniformName`s to the `additional_uniforms` argument
     /// and can then be set in functions utilizing `GlesTexProgram` (like [`GlesFrame::render_texture`] or [`GlesFrame::render_texture_from_to`]).
     ///
+    /// Uniforms of type [`UniformType::Texture`] are declared as `sampler2D` (or `samplerExternalOES` for
+    /// external textures) and are set to a [`GlesTexture`] using [`UniformValue::Texture`].
+    ///
     /// The shader must contain a line only containing `//_DEFINES`. It will be replaced by the renderer with corresponding `#define` directives.
     ///
     /// ## Panics
@@ -1995,6 +2356,7 @@ impl<'frame> GlesFrame<'frame> {
     where
         F: FnOnce(&ffi::Gles2) -> R,
     {
+        self.flush_batches()?;
         Ok(func(&self.renderer.gl))
     }
 }
@@ -2005,7 +2367,7 @@ impl Renderer for GlesRenderer {
     type Frame<'frame> = GlesFrame<'frame>;
 
     fn id(&self) -> usize {
-        self.egl.user_data().get::<RendererId>().unwrap().0
+        self.egl.user_data().get::<RendererId>().unwrap().get()
     }
 
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
@@ -2033,6 +2395,8 @@ impl Renderer for GlesRenderer {
 
             self.gl.Enable(ffi::BLEND);
             self.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+
+            self.stats.begin_frame(&self.gl);
         }
 
         // Handle the width/height swap when the output is rotated by 90°/270°.
@@ -2123,6 +2487,21 @@ impl Renderer for GlesRenderer {
         self.cleanup();
         Ok(())
     }
+
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        let texture_formats = self.egl.dmabuf_texture_formats().clone();
+        let render_formats = self.egl.dmabuf_render_formats().clone();
+        RendererCapabilities {
+            max_texture_size: (self.max_texture_size > 0)
+                .then(|| (self.max_texture_size, self.max_texture_size).into()),
+            mem_formats: self.mem_formats().collect(),
+            external_only_formats: RendererCapabilities::external_only(&texture_formats, &render_formats),
+            dmabuf_texture_formats: texture_formats,
+            dmabuf_render_formats: render_formats,
+            fencing: self.capabilities.contains(&Capability::Fencing),
+            color_transform: true,
+        }
+    }
 }
 
 /// Vertices for instanced rendering.
@@ -2195,6 +2574,7 @@ impl<'frame> Frame for GlesFrame<'frame> {
             return Ok(());
         }
 
+        self.flush_batches()?;
         unsafe {
             self.renderer.gl.Disable(ffi::BLEND);
         }
@@ -2220,6 +2600,12 @@ impl<'frame> Frame for GlesFrame<'frame> {
             return Ok(());
         }
 
+        if self.renderer.solid_batch_program.is_some() {
+            self.flush_texture_batch()?;
+            push_solid_instances(&mut self.renderer.solid_batch, dst, damage, color);
+            return Ok(());
+        }
+
         let is_opaque = color.is_opaque();
 
         if is_opaque {
@@ -2283,6 +2669,8 @@ impl<'frame> Frame for GlesFrame<'frame> {
 impl<'frame> GlesFrame<'frame> {
     #[profiling::function]
     fn finish_internal(&mut self) -> Result<SyncPoint, GlesError> {
+        // a no-op for finished frames, as every flush empties the batch
+        self.flush_batches()?;
         let _guard = self.span.enter();
 
         if self.finished.swap(true, Ordering::SeqCst) {
@@ -2292,6 +2680,7 @@ impl<'frame> GlesFrame<'frame> {
         unsafe {
             self.renderer.gl.Disable(ffi::SCISSOR_TEST);
             self.renderer.gl.Disable(ffi::BLEND);
+            self.renderer.stats.end_frame(&self.renderer.gl);
         }
 
         // delayed destruction until the next frame rendering.
@@ -2346,6 +2735,7 @@ impl<'frame> GlesFrame<'frame> {
             return Ok(());
         }
 
+        self.flush_batches()?;
         let mut mat = Matrix3::<f32>::identity();
         mat = self.current_projection * mat;
 
@@ -2449,10 +2839,183 @@ impl<'frame> GlesFrame<'frame> {
             gl.DisableVertexAttribArray(self.renderer.solid_program.attrib_vert as u32);
             gl.DisableVertexAttribArray(self.renderer.solid_program.attrib_position as u32);
         }
+        self.renderer
+            .stats
+            .record_draws(1, stats::damage_area(dest.size, damage));
 
         Ok(())
     }
 
+    /// Draws all solid colors and textures batched since the last flush
+    ///
+    /// With [`Capability::Batching`] consecutive draws sharing their state are deferred and submitted
+    /// together. Every drawing operation not fitting into the current batch flushes it first, so this only
+    /// has to be called before accessing the framebuffer by other means.
+    #[profiling::function]
+    pub fn flush_batches(&mut self) -> Result<(), GlesError> {
+        self.flush_solid_batch()?;
+        self.flush_texture_batch()
+    }
+
+    // Draws the solid colors batched by `Frame::draw_solid` in a single instanced draw call
+    fn flush_solid_batch(&mut self) -> Result<(), GlesError> {
+        let Some(program) = self.renderer.solid_batch_program.as_ref() else {
+            return Ok(());
+        };
+        if self.renderer.solid_batch.is_empty() {
+            return Ok(());
+        }
+
+        let batch = &self.renderer.solid_batch;
+        let instances = batch.len() / SOLID_INSTANCE_LEN;
+        // opaque colors are drawn the same with or without blending, disabling it avoids read-back
+        let is_opaque = batch.chunks_exact(SOLID_INSTANCE_LEN).all(|quad| quad[7] == 1.0);
+        let stride = (SOLID_INSTANCE_LEN * mem::size_of::<ffi::types::GLfloat>()) as i32;
+
+        let gl = &self.renderer.gl;
+        unsafe {
+            if is_opaque {
+                gl.Disable(ffi::BLEND);
+            }
+
+            gl.UseProgram(program.program);
+            gl.UniformMatrix3fv(
+                program.uniform_matrix,
+                1,
+                ffi::FALSE,
+                self.current_projection.as_ptr(),
+            );
+
+            gl.EnableVertexAttribArray(program.attrib_vert as u32);
+            gl.BindBuffer(ffi::ARRAY_BUFFER, self.renderer.vbos[0]);
+            gl.VertexAttribPointer(
+                program.attrib_vert as u32,
+                2,
+                ffi::FLOAT,
+                ffi::FALSE,
+                0,
+                ptr::null(),
+            );
+
+            gl.EnableVertexAttribArray(program.attrib_position as u32);
+            gl.EnableVertexAttribArray(program.attrib_color as u32);
+            gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
+            gl.VertexAttribPointer(
+                program.attrib_position as u32,
+                4,
+                ffi::FLOAT,
+                ffi::FALSE,
+                stride,
+                batch.as_ptr() as *const _,
+            );
+            gl.VertexAttribPointer(
+                program.attrib_color as u32,
+                4,
+                ffi::FLOAT,
+                ffi::FALSE,
+                stride,
+                batch[4..].as_ptr() as *const _,
+            );
+
+            gl.VertexAttribDivisor(program.attrib_vert as u32, 0);
+            gl.VertexAttribDivisor(program.attrib_position as u32, 1);
+            gl.VertexAttribDivisor(program.attrib_color as u32, 1);
+            gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, instances as i32);
+
+            // attribute divisors are part of the global state and would leak into other programs
+            gl.VertexAttribDivisor(program.attrib_color as u32, 0);
+            gl.DisableVertexAttribArray(program.attrib_vert as u32);
+            gl.DisableVertexAttribArray(program.attrib_position as u32);
+            gl.DisableVertexAttribArray(program.attrib_color as u32);
+
+            if is_opaque {
+                gl.Enable(ffi::BLEND);
+                gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+            }
+        }
+
+        let pixels = batch
+            .chunks_exact(SOLID_INSTANCE_LEN)
+            .map(|quad| quad[2] as u64 * quad[3] as u64)
+            .sum();
+        self.renderer.stats.record_draws(1, pixels);
+        self.renderer.solid_batch.clear();
+        Ok(())
+    }
+
+    // Draws the textured quads batched by `GlesFrame::render_texture_from_to`
+    fn flush_texture_batch(&mut self) -> Result<(), GlesError> {
+        let Some(batch) = self.renderer.texture_batch.take() else {
+            return Ok(());
+        };
+
+        if !batch.blend {
+            unsafe {
+                self.renderer.gl.Disable(ffi::BLEND);
+            }
+        }
+        let res = self.draw_texture_quads(
+            &batch.texture,
+            None,
+            Matrix3::identity(),
+            batch.alpha,
+            Some(&batch.program),
+            &batch.uniforms,
+        );
+        self.renderer.texture_quads.clear();
+        if !batch.blend {
+            unsafe {
+                self.renderer.gl.Enable(ffi::BLEND);
+                self.renderer.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+            }
+        }
+        res
+    }
+
+    // Adds the damaged parts of `dest` to the texture batch, flushing it first if its state differs
+    #[allow(clippy::too_many_arguments)]
+    fn queue_texture_quads(
+        &mut self,
+        texture: &GlesTexture,
+        tex_matrix: Matrix3<f32>,
+        dest: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        blend: bool,
+        alpha: f32,
+        program: Option<&GlesTexProgram>,
+        additional_uniforms: &[Uniform<'_>],
+    ) -> Result<(), GlesError> {
+        // the override is resolved right away, so changing it does not affect queued quads
+        let (program, additional_uniforms) = program
+            .map(|p| (p, additional_uniforms))
+            .or_else(|| self.tex_program_override.as_ref().map(|(p, a)| (p, &**a)))
+            .unwrap_or((&self.renderer.tex_program, &[]));
+        let matches = self.renderer.texture_batch.as_ref().is_some_and(|batch| {
+            Arc::ptr_eq(&batch.texture.0, &texture.0)
+                && Rc::ptr_eq(&batch.program.0, &program.0)
+                && batch.uniforms[..] == *additional_uniforms
+                && batch.alpha == alpha
+                && batch.blend == blend
+        });
+        if !matches {
+            let batch = TextureBatch {
+                texture: texture.clone(),
+                program: program.clone(),
+                uniforms: additional_uniforms.iter().map(Uniform::to_owned).collect(),
+                alpha,
+                blend,
+            };
+            self.flush_texture_batch()?;
+            self.renderer.texture_batch = Some(batch);
+        }
+
+        // the quads are positioned on the output, so the texture matrix has to undo the offset of `dest`
+        let tex_matrix =
+            tex_matrix * Matrix3::from_translation(Vector2::new(-dest.loc.x as f32, -dest.loc.y as f32));
+        push_texture_instances(&mut self.renderer.texture_quads, dest, damage, tex_matrix);
+        Ok(())
+    }
+
     /// Render part of a texture as given by src to the current target into the rectangle described by dst
     /// as a flat 2d-plane after applying the inverse of the given transformation.
     /// (Meaning `src_transform` should match the orientation of surface being rendered).
@@ -2473,6 +3036,7 @@ impl<'frame> GlesFrame<'frame> {
         program: Option<&GlesTexProgram>,
         additional_uniforms: &[Uniform<'_>],
     ) -> Result<(), GlesError> {
+        self.flush_solid_batch()?;
         let mut mat = Matrix3::<f32>::identity();
 
         // dest position and scale
@@ -2491,7 +3055,24 @@ impl<'frame> GlesFrame<'frame> {
             tex_mat = Matrix3::new(1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0) * tex_mat;
         }
 
-        let render_texture = |renderer: &mut Self, damage: &[Rectangle<i32, Physical>]| {
+        let render_texture = |renderer: &mut Self, damage: &[Rectangle<i32, Physical>], blend: bool| {
+            renderer
+                .renderer
+                .stats
+                .record_draws(0, stats::damage_area(dest.size, damage));
+            if renderer.renderer.capabilities.contains(&Capability::Batching) {
+                return renderer.queue_texture_quads(
+                    texture,
+                    tex_mat,
+                    dest,
+                    damage,
+                    blend,
+                    alpha,
+                    program,
+                    additional_uniforms,
+                );
+            }
+
             let instances = damage.iter().flat_map(|rect| {
                 let dest_size = dest.size;
 
@@ -2511,7 +3092,12 @@ impl<'frame> GlesFrame<'frame> {
                 ]
             });
 
-            renderer.render_texture(
+            if !blend {
+                unsafe {
+                    renderer.renderer.gl.Disable(ffi::BLEND);
+                }
+            }
+            let res = renderer.render_texture(
                 texture,
                 tex_mat,
                 mat,
@@ -2519,7 +3105,14 @@ impl<'frame> GlesFrame<'frame> {
                 alpha,
                 program,
                 additional_uniforms,
-            )
+            );
+            if !blend {
+                unsafe {
+                    renderer.renderer.gl.Enable(ffi::BLEND);
+                    renderer.renderer.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+                }
+            }
+            res
         };
 
         // We split the damage in opaque and non opaque regions, for opaque regions we can
@@ -2554,24 +3147,13 @@ impl<'frame> GlesFrame<'frame> {
         tracing::trace!(non_opaque_damage = ?non_opaque_damage, opaque_damage = ?opaque_damage, "drawing texture");
 
         let non_opaque_render_res = if !non_opaque_damage.is_empty() {
-            render_texture(self, &non_opaque_damage)
+            render_texture(self, &non_opaque_damage, true)
         } else {
             Ok(())
         };
 
         let opaque_render_res = if !opaque_damage.is_empty() {
-            unsafe {
-                self.renderer.gl.Disable(ffi::BLEND);
-            }
-
-            let res = render_texture(self, &opaque_damage);
-
-            unsafe {
-                self.renderer.gl.Enable(ffi::BLEND);
-                self.renderer.gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
-            }
-
-            res
+            render_texture(self, &opaque_damage, false)
         } else {
             Ok(())
         };
@@ -2607,53 +3189,53 @@ impl<'frame> GlesFrame<'frame> {
         &mut self,
         tex: &GlesTexture,
         tex_matrix: Matrix3<f32>,
-        mut matrix: Matrix3<f32>,
+        matrix: Matrix3<f32>,
         instances: Option<impl IntoIterator<Item = ffi::types::GLfloat>>,
         alpha: f32,
         program: Option<&GlesTexProgram>,
         additional_uniforms: &[Uniform<'_>],
     ) -> Result<(), GlesError> {
-        // prepare the vertices
-        self.renderer.vertices.clear();
-        let damage_len = if let Some(instances) = instances {
-            if self.renderer.capabilities.contains(&Capability::Instancing) {
-                self.renderer.vertices.extend(instances);
-                self.renderer.vertices.len() / 4
-            } else {
-                let mut damage = 0;
-                let mut instances = instances.into_iter();
-                while let Some(first) = instances.next() {
-                    damage += 1;
-                    let vertices = [
-                        first,
-                        instances.next().unwrap(),
-                        instances.next().unwrap(),
-                        instances.next().unwrap(),
-                    ];
-                    // Add the 4 f32s per damage rectangle for each of the 6 vertices.
-                    for _ in 0..6 {
-                        self.renderer.vertices.extend_from_slice(&vertices);
-                    }
-                }
-                damage
-            }
-        } else if self.renderer.capabilities.contains(&Capability::Instancing) {
-            self.renderer.vertices.extend_from_slice(&[0.0, 0.0, 1.0, 1.0]);
-            1
+        self.flush_batches()?;
+
+        // the batch is empty after flushing, so its buffer can hold the instances
+        let quads = &mut self.renderer.texture_quads;
+        match instances {
+            Some(instances) => quads.extend(instances),
+            None => quads.extend_from_slice(&[0.0, 0.0, 1.0, 1.0]),
+        }
+
+        let res = self.draw_texture_quads(tex, Some(tex_matrix), matrix, alpha, program, additional_uniforms);
+        self.renderer.texture_quads.clear();
+        res
+    }
+
+    // Draws the quads in `GlesRenderer::texture_quads`.
+    //
+    // With a `tex_matrix` every quad consists of its position and size relative to `matrix`. Without it the
+    // quads are batched and additionally contain the first two rows of their own texture matrix, see
+    // `TEXTURE_INSTANCE_LEN`.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_texture_quads(
+        &mut self,
+        tex: &GlesTexture,
+        tex_matrix: Option<Matrix3<f32>>,
+        matrix: Matrix3<f32>,
+        alpha: f32,
+        program: Option<&GlesTexProgram>,
+        additional_uniforms: &[Uniform<'_>],
+    ) -> Result<(), GlesError> {
+        let quad_len = if tex_matrix.is_some() {
+            4
         } else {
-            // Add the 4 f32s per damage rectangle for each of the 6 vertices.
-            for _ in 0..6 {
-                self.renderer.vertices.extend_from_slice(&[0.0, 0.0, 1.0, 1.0]);
-            }
-            1
+            TEXTURE_INSTANCE_LEN
         };
-
-        if self.renderer.vertices.is_empty() {
+        let quad_count = self.renderer.texture_quads.len() / quad_len;
+        if quad_count == 0 {
             return Ok(());
         }
 
         //apply output transformation
-        matrix = self.current_projection * matrix;
+        let matrix = self.current_projection * matrix;
 
         let target = if tex.0.is_external {
             ffi::TEXTURE_EXTERNAL_OES
@@ -2664,18 +3246,36 @@ impl<'frame> GlesFrame<'frame> {
             .map(|p| (p, additional_uniforms))
             .or_else(|| self.tex_program_override.as_ref().map(|(p, a)| (p, &**a)))
             .unwrap_or((&self.renderer.tex_program, &[]));
-        let program_variant = tex_program.variant_for_format(
-            if !tex.0.is_external { tex.0.format } else { None },
-            tex.0.has_alpha,
-        );
+        // textures imported as separate yuv planes are converted by the yuv variant of every program
+        let yuv_uniforms = tex
+            .0
+            .yuv
+            .as_ref()
+            .map(|planes| planes.uniforms(self.renderer.yuv_color_space, self.renderer.yuv_range));
+        let program_variant = if yuv_uniforms.is_some() {
+            tex_program.yuv_variant()
+        } else {
+            tex_program.variant_for_format(
+                if !tex.0.is_external { tex.0.format } else { None },
+                tex.0.has_alpha,
+            )
+        };
         let program = if self.renderer.debug_flags.is_empty() {
             &program_variant.normal
         } else {
             &program_variant.debug
         };
+        // the texture coordinates are unused by shaders sampling by fragment position
+        let tex_attribs = [program.attrib_tex_x, program.attrib_tex_y]
+            .into_iter()
+            .filter(|attrib| *attrib >= 0)
+            .map(|attrib| attrib as u32);
+        let instancing = self.renderer.capabilities.contains(&Capability::Instancing);
+        let stride = (quad_len * mem::size_of::<ffi::types::GLfloat>()) as i32;
 
         // render
         let gl = &self.renderer.gl;
+        let mut draw_calls = 0;
         unsafe {
             gl.ActiveTexture(ffi::TEXTURE0);
             gl.BindTexture(target, tex.0.texture);
@@ -2699,7 +3299,6 @@ impl<'frame> GlesFrame<'frame> {
 
             gl.Uniform1i(program.uniform_tex, 0);
             gl.UniformMatrix3fv(program.uniform_matrix, 1, ffi::FALSE, matrix.as_ptr());
-            gl.UniformMatrix3fv(program.uniform_tex_matrix, 1, ffi::FALSE, tex_matrix.as_ptr());
             gl.Uniform1f(program.uniform_alpha, alpha);
 
             if !self.renderer.debug_flags.is_empty() {
@@ -2711,7 +3310,7 @@ impl<'frame> GlesFrame<'frame> {
                 gl.Uniform1f(program_variant.uniform_tint, tint);
             }
 
-            for uniform in additional_uniforms {
+            for uniform in additional_uniforms.iter().chain(yuv_uniforms.iter().flatten()) {
                 let desc = program
                     .additional_uniforms
                     .get(&*uniform.name)
@@ -2734,29 +3333,81 @@ impl<'frame> GlesFrame<'frame> {
             gl.EnableVertexAttribArray(program.attrib_vert_position as u32);
             gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
 
-            gl.VertexAttribPointer(
-                program.attrib_vert_position as u32,
-                4,
-                ffi::FLOAT,
-                ffi::FALSE,
-                0,
-                self.renderer.vertices.as_ptr() as *const _,
-            );
+            // the rows of a shared texture matrix are constant attributes, batched quads carry their own
+            if let Some(tex_matrix) = tex_matrix {
+                for (row, attrib) in tex_attribs.clone().enumerate() {
+                    gl.VertexAttrib3f(attrib, tex_matrix.x[row], tex_matrix.y[row], tex_matrix.z[row]);
+                }
+            } else {
+                for attrib in tex_attribs.clone() {
+                    gl.EnableVertexAttribArray(attrib);
+                }
+            }
+            let set_pointers = |data: &[ffi::types::GLfloat]| {
+                gl.VertexAttribPointer(
+                    program.attrib_vert_position as u32,
+                    4,
+                    ffi::FLOAT,
+                    ffi::FALSE,
+                    stride,
+                    data.as_ptr() as *const _,
+                );
+                if tex_matrix.is_none() {
+                    for (row, attrib) in tex_attribs.clone().enumerate() {
+                        gl.VertexAttribPointer(
+                            attrib,
+                            3,
+                            ffi::FLOAT,
+                            ffi::FALSE,
+                            stride,
+                            data[4 + row * 3..].as_ptr() as *const _,
+                        );
+                    }
+                }
+            };
 
-            if self.renderer.capabilities.contains(&Capability::Instancing) {
+            if instancing {
+                set_pointers(&self.renderer.texture_quads);
                 gl.VertexAttribDivisor(program.attrib_vert as u32, 0);
                 gl.VertexAttribDivisor(program.attrib_vert_position as u32, 1);
+                if tex_matrix.is_none() {
+                    for attrib in tex_attribs.clone() {
+                        gl.VertexAttribDivisor(attrib, 1);
+                    }
+                }
+
+                gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, quad_count as i32);
+                draw_calls += 1;
 
-                gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, damage_len as i32);
+                // attribute divisors are part of the global state and would leak into other programs
+                for attrib in tex_attribs.clone() {
+                    gl.VertexAttribDivisor(attrib, 0);
+                }
             } else {
-                let count = damage_len * 6;
-                gl.DrawArrays(ffi::TRIANGLES, 0, count as i32);
+                // Add the quad for each of the 6 vertices, the vertex buffer holds a limited amount of quads.
+                let vertices = &mut self.renderer.vertices;
+                vertices.clear();
+                for quad in self.renderer.texture_quads.chunks_exact(quad_len) {
+                    for _ in 0..6 {
+                        vertices.extend_from_slice(quad);
+                    }
+                }
+                for chunk in vertices.chunks(MAX_RECTS_PER_DRAW * 6 * quad_len) {
+                    set_pointers(chunk);
+                    gl.DrawArrays(ffi::TRIANGLES, 0, (chunk.len() / quad_len) as i32);
+                    draw_calls += 1;
+                }
             }
 
             gl.BindTexture(target, 0);
             gl.DisableVertexAttribArray(program.attrib_vert as u32);
             gl.DisableVertexAttribArray(program.attrib_vert_position as u32);
+            for attrib in tex_attribs {
+                gl.DisableVertexAttribArray(attrib);
+            }
         }
+        // the drawn pixels are recorded by the callers, which know the size of the instances
+        self.renderer.stats.record_draws(draw_calls, 0);
 
         Ok(())
     }
@@ -2773,6 +3424,7 @@ impl<'frame> GlesFrame<'frame> {
     ) -> Result<(), GlesError> {
         let fallback_damage = &[Rectangle::from_loc_and_size(Point::default(), dest.size)];
         let damage = damage.unwrap_or(fallback_damage);
+        self.flush_batches()?;
 
         // prepare the vertices
         self.renderer.vertices.clear();
@@ -2908,6 +3560,9 @@ impl<'frame> GlesFrame<'frame> {
             gl.DisableVertexAttribArray(program.attrib_vert as u32);
             gl.DisableVertexAttribArray(program.attrib_position as u32);
         }
+        self.renderer
+            .stats
+            .record_draws(1, stats::damage_area(dest.size, damage));
 
         Ok(())
     }
@@ -2952,6 +3607,78 @@ impl<'frame> Drop for GlesFrame<'frame> {
     }
 }
 
+// position and size followed by the color of a batched solid color quad
+const SOLID_INSTANCE_LEN: usize = 8;
+
+fn push_solid_instances(
+    batch: &mut Vec<f32>,
+    dest: Rectangle<i32, Physical>,
+    damage: &[Rectangle<i32, Physical>],
+    color: Color32F,
+) {
+    batch.extend(damage.iter().flat_map(|rect| {
+        let rect_constrained_loc = rect
+            .loc
+            .constrain(Rectangle::from_extemities((0, 0), dest.size.to_point()));
+        let rect_clamped_size = rect
+            .size
+            .clamp((0, 0), (dest.size.to_point() - rect_constrained_loc).to_size());
+
+        [
+            (dest.loc.x + rect_constrained_loc.x) as f32,
+            (dest.loc.y + rect_constrained_loc.y) as f32,
+            rect_clamped_size.w as f32,
+            rect_clamped_size.h as f32,
+            color.r(),
+            color.g(),
+            color.b(),
+            color.a(),
+        ]
+    }));
+}
+
+// position and size followed by the first two rows of the texture matrix of a batched textured quad
+const TEXTURE_INSTANCE_LEN: usize = 10;
+
+// State shared by all quads of the texture batch, see `GlesFrame::flush_texture_batch`
+#[derive(Debug)]
+struct TextureBatch {
+    texture: GlesTexture,
+    program: GlesTexProgram,
+    uniforms: Vec<Uniform<'static>>,
+    alpha: f32,
+    blend: bool,
+}
+
+fn push_texture_instances(
+    batch: &mut Vec<f32>,
+    dest: Rectangle<i32, Physical>,
+    damage: &[Rectangle<i32, Physical>],
+    tex_matrix: Matrix3<f32>,
+) {
+    batch.extend(damage.iter().flat_map(|rect| {
+        let rect_constrained_loc = rect
+            .loc
+            .constrain(Rectangle::from_extemities((0, 0), dest.size.to_point()));
+        let rect_clamped_size = rect
+            .size
+            .clamp((0, 0), (dest.size.to_point() - rect_constrained_loc).to_size());
+
+        [
+            (dest.loc.x + rect_constrained_loc.x) as f32,
+            (dest.loc.y + rect_constrained_loc.y) as f32,
+            rect_clamped_size.w as f32,
+            rect_clamped_size.h as f32,
+            tex_matrix.x.x,
+            tex_matrix.y.x,
+            tex_matrix.z.x,
+            tex_matrix.x.y,
+            tex_matrix.y.y,
+            tex_matrix.z.y,
+        ]
+    }));
+}
+
 fn build_texture_mat(
     src: Rectangle<f64, BufferCoord>,
     dest: Rectangle<i32, Physical>,
@@ -2999,10 +3726,49 @@ fn build_texture_mat(
 
 #[cfg(test)]
 mod tests {
-    use super::build_texture_mat;
-    use crate::utils::{Buffer, Physical, Rectangle, Size, Transform};
+    use super::{
+        build_texture_mat, ffi, format::swap_red_blue, mem_format_to_gl_formats, push_solid_instances,
+        SUPPORTED_MEM_FORMATS_3,
+    };
+    use crate::{
+        backend::renderer::Color32F,
+        utils::{Buffer, Physical, Rectangle, Size, Transform},
+    };
     use cgmath::Vector3;
 
+    #[test]
+    fn solid_instances_are_clamped_to_dest() {
+        let mut batch = Vec::new();
+        let dest: Rectangle<i32, Physical> = Rectangle::from_loc_and_size((100, 50), (20, 10));
+        let damage = [
+            Rectangle::from_loc_and_size((0, 0), (20, 10)),
+            Rectangle::from_loc_and_size((15, 5), (10, 10)),
+        ];
+        push_solid_instances(&mut batch, dest, &damage, Color32F::new(0.5, 0.0, 0.0, 0.5));
+
+        assert_eq!(
+            batch,
+            [
+                100.0, 50.0, 20.0, 10.0, 0.5, 0.0, 0.0, 0.5, //
+                115.0, 55.0, 5.0, 5.0, 0.5, 0.0, 0.0, 0.5,
+            ]
+        );
+    }
+
+    #[test]
+    fn mem_formats_have_gl_formats() {
+        for format in SUPPORTED_MEM_FORMATS_3 {
+            let (_, swapped_rb) = mem_format_to_gl_formats(*format).unwrap();
+            if swapped_rb {
+                assert_eq!(swap_red_blue(swap_red_blue(*format).unwrap()), Some(*format));
+            }
+        }
+        let ((internal, _, _), swapped_rb) =
+            mem_format_to_gl_formats(crate::backend::allocator::Fourcc::Xrgb2101010).unwrap();
+        assert_eq!(internal, ffi::RGB10_A2);
+        assert!(swapped_rb);
+    }
+
     #[test]
     fn texture_normal_double_size() {
         let src: Rectangle<f64, Buffer> = Rectangle::from_loc_and_size((0f64, 0f64), (1000f64, 500f64));
@@ -3214,4 +3980,93 @@ mod tests {
         assert_eq!(tex_mat * bottom_right, Vector3::new(0f32, 0f32, 1f32));
         assert_eq!(tex_mat * bottom_left, Vector3::new(0f32, 1f32, 1f32));
     }
+
+    #[test]
+    fn textures_are_batched() {
+        use super::{Capability, GlesRenderer, GlesTexture};
+        use crate::backend::{
+            allocator::Fourcc,
+            egl::{EGLContext, EGLDevice, EGLDisplay},
+            renderer::{Bind, ExportMem, Frame, ImportMem, Offscreen, Renderer},
+        };
+
+        // needs a software renderer to run without a gpu
+        let Some(device) = EGLDevice::enumerate().ok().and_then(|mut devices| {
+            devices.find(|device| {
+                device
+                    .extensions()
+                    .iter()
+                    .any(|ext| ext == "EGL_MESA_device_software")
+            })
+        }) else {
+            return;
+        };
+        let display = unsafe { EGLDisplay::new(device) }.unwrap();
+
+        const COUNT: usize = 20;
+        let (width, height) = (COUNT as i32 * 2, 2);
+        // a red and a blue column, so every quad has to sample with its own texture coordinates
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 255].repeat(2);
+
+        let capabilities =
+            unsafe { GlesRenderer::supported_capabilities(&EGLContext::new(&display).unwrap()) }.unwrap();
+        if !capabilities.contains(&Capability::Instancing) {
+            return;
+        }
+        for (instancing, draw_calls) in [
+            (true, 1),
+            (
+                false,
+                (COUNT + super::MAX_RECTS_PER_DRAW - 1) / super::MAX_RECTS_PER_DRAW,
+            ),
+        ] {
+            let capabilities = capabilities
+                .iter()
+                .copied()
+                .filter(|cap| instancing || *cap != Capability::Instancing);
+            let mut renderer =
+                unsafe { GlesRenderer::with_capabilities(EGLContext::new(&display).unwrap(), capabilities) }
+                    .unwrap();
+            let texture = renderer
+                .import_memory(&pixels, Fourcc::Abgr8888, (2, 2).into(), false)
+                .unwrap();
+            let target: GlesTexture = renderer
+                .create_buffer(Fourcc::Abgr8888, (width, height).into())
+                .unwrap();
+            renderer.bind(target).unwrap();
+
+            let mut frame = renderer
+                .render((width, height).into(), Transform::Normal)
+                .unwrap();
+            for i in 0..COUNT as i32 {
+                let dst = Rectangle::from_loc_and_size((i * 2, 0), (2, 2));
+                frame
+                    .render_texture_from_to(
+                        &texture,
+                        Rectangle::from_loc_and_size((0.0, 0.0), (2.0, 2.0)),
+                        dst,
+                        &[Rectangle::from_loc_and_size((0, 0), dst.size)],
+                        &[],
+                        Transform::Normal,
+                        1.0,
+                        None,
+                        &[],
+                    )
+                    .unwrap();
+            }
+            frame.finish().unwrap().wait().unwrap();
+            assert_eq!(renderer.frame_stats().unwrap().draw_calls, draw_calls as u32);
+
+            let mapping = renderer
+                .copy_framebuffer(
+                    Rectangle::from_loc_and_size((0, 0), (width, height)),
+                    Fourcc::Abgr8888,
+                )
+                .unwrap();
+            let copy = renderer.map_texture(&mapping).unwrap();
+            for (x, pixel) in copy.chunks_exact(4).take(width as usize).enumerate() {
+                assert_eq!(pixel, &pixels[x % 2 * 4..][..4]);
+            }
+        }
+    }
 }
@@ -0,0 +1,56 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// alpha mask stretched over the mask rectangle
+uniform sampler2D mask;
+// size of the mask rectangle
+uniform vec2 geo_size;
+// maps window coordinates to coordinates relative to the mask rectangle
+uniform mat3 frag_to_geo;
+
+void main() {
+#if defined(YUV)
+    vec4 color = yuv_texture2D(tex, v_coords);
+#else
+    vec4 color = texture2D(tex, v_coords);
+#endif
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0) * alpha;
+#else
+    color = color * alpha;
+#endif
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    // everything outside of the mask rectangle is transparent
+    vec2 p = (frag_to_geo * vec3(gl_FragCoord.xy, 1.0)).xy / geo_size;
+    float inside = step(0.0, p.x) * step(p.x, 1.0) * step(0.0, p.y) * step(p.y, 1.0);
+    gl_FragColor = color * texture2D(mask, p).a * inside;
+}
@@ -0,0 +1,23 @@
+#version 100
+
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+uniform sampler2D tex;
+// half the size of a pixel of the target in texture coordinates
+uniform vec2 half_pixel;
+uniform float offset;
+varying vec2 v_coords;
+
+void main() {
+    vec2 o = half_pixel * offset;
+    vec4 sum = texture2D(tex, v_coords) * 4.0;
+    sum += texture2D(tex, v_coords - o);
+    sum += texture2D(tex, v_coords + o);
+    sum += texture2D(tex, v_coords + vec2(o.x, -o.y));
+    sum += texture2D(tex, v_coords - vec2(o.x, -o.y));
+    gl_FragColor = sum / 8.0;
+}
@@ -0,0 +1,48 @@
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+uniform float alpha;
+uniform vec2 size;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// rectangle the border is drawn around relative to the element: x, y, width, height
+uniform vec4 inner_rect;
+uniform float inner_radius;
+uniform float outer_radius;
+// premultiplied colors at the start and end of the gradient
+uniform vec4 start_color;
+uniform vec4 end_color;
+// direction of the gradient scaled by the inverse of its length
+uniform vec2 gradient;
+
+// signed distance to the border of a rounded rectangle
+float rounded_rect_distance(vec4 rect, vec2 point, float radius) {
+    vec2 half_size = rect.zw * 0.5;
+    radius = clamp(radius, 0.0, min(half_size.x, half_size.y));
+    vec2 q = abs(point - rect.xy - half_size) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
+void main() {
+    vec2 point = v_coords * size;
+    float outer = rounded_rect_distance(vec4(0.0, 0.0, size), point, outer_radius);
+    float inner = rounded_rect_distance(inner_rect, point, inner_radius);
+    float coverage = clamp(0.5 - outer, 0.0, 1.0) * clamp(0.5 + inner, 0.0, 1.0);
+
+    float t = clamp(dot(point - size * 0.5, gradient) + 0.5, 0.0, 1.0);
+    vec4 mix_color = mix(start_color, end_color, t) * coverage * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        mix_color = vec4(0.0, 0.3, 0.0, 0.2) + mix_color * 0.8;
+#endif
+
+    gl_FragColor = mix_color;
+}
@@ -0,0 +1,75 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// window coordinates of the lower left corner of the copied framebuffer content
+uniform vec2 frag_offset;
+// size of the copied framebuffer content in window coordinates
+uniform vec2 frag_size;
+
+// 3d lookup table stored as `lut_size` slices of increasing blue next to each other,
+// each slice indexed by red horizontally and green vertically
+uniform sampler2D lut;
+uniform float lut_size;
+
+vec3 sample_lut(vec3 color) {
+    color = clamp(color, 0.0, 1.0);
+    float blue = color.b * (lut_size - 1.0);
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0, lut_size - 1.0);
+
+    // sample at texel centers, so neighbouring slices do not bleed into each other
+    float x = (color.r * (lut_size - 1.0) + 0.5) / (lut_size * lut_size);
+    float y = (color.g * (lut_size - 1.0) + 0.5) / lut_size;
+    vec3 color0 = texture2D(lut, vec2(x + slice0 / lut_size, y)).rgb;
+    vec3 color1 = texture2D(lut, vec2(x + slice1 / lut_size, y)).rgb;
+    return mix(color0, color1, blue - slice0);
+}
+
+void main() {
+    vec2 coords = (gl_FragCoord.xy - frag_offset) / frag_size;
+#if defined(YUV)
+    vec4 color = yuv_texture2D(tex, coords);
+#else
+    vec4 color = texture2D(tex, coords);
+#endif
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    // the lookup table operates on non-premultiplied colors
+    if (color.a > 0.0) {
+        color.rgb = sample_lut(color.rgb / color.a) * color.a;
+    }
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
@@ -2,20 +2,26 @@
 use crate::backend::renderer::gles::*;
 
 pub(in super::super) const VERTEX_SHADER: &str = include_str!("./texture.vert");
+// like `VERTEX_SHADER`, but taking the texture matrix as attributes to allow batching
+pub(in super::super) const VERTEX_SHADER_TEX_PROGRAM: &str = include_str!("./tex_program.vert");
 pub(in super::super) const FRAGMENT_SHADER: &str = include_str!("./texture.frag");
 
 pub(in super::super) const VERTEX_SHADER_SOLID: &str = include_str!("./solid.vert");
 pub(in super::super) const FRAGMENT_SHADER_SOLID: &str = include_str!("./solid.frag");
 
+pub(in super::super) const VERTEX_SHADER_SOLID_BATCH: &str = include_str!("./solid_batch.vert");
+pub(in super::super) const FRAGMENT_SHADER_SOLID_BATCH: &str = include_str!("./solid_batch.frag");
+
 #[derive(Debug)]
 pub(in super::super) struct GlesTexProgramInternal {
     pub(in super::super) program: ffi::types::GLuint,
     pub(in super::super) uniform_tex: ffi::types::GLint,
-    pub(in super::super) uniform_tex_matrix: ffi::types::GLint,
     pub(in super::super) uniform_matrix: ffi::types::GLint,
     pub(in super::super) uniform_alpha: ffi::types::GLint,
     pub(in super::super) attrib_vert: ffi::types::GLint,
     pub(in super::super) attrib_vert_position: ffi::types::GLint,
+    pub(in super::super) attrib_tex_x: ffi::types::GLint,
+    pub(in super::super) attrib_tex_y: ffi::types::GLint,
     pub(in super::super) additional_uniforms: HashMap<String, UniformDesc>,
 }
 
@@ -34,7 +40,7 @@ pub struct GlesTexProgram(pub(in super::super) Rc<GlesTexProgramInner>);
 
 #[derive(Debug)]
 pub(in super::super) struct GlesTexProgramInner {
-    pub(in super::super) variants: [GlesTexProgramVariant; 3],
+    pub(in super::super) variants: [GlesTexProgramVariant; 4],
     pub(super) destruction_callback_sender: Sender<CleanupResource>,
 }
 
@@ -57,6 +63,11 @@ impl GlesTexProgram {
             _ => panic!("Unknown texture type"),
         }
     }
+
+    // Variant converting textures imported as separate yuv planes
+    pub(in super::super) fn yuv_variant(&self) -> &GlesTexProgramVariant {
+        &self.0.variants[3]
+    }
 }
 
 impl Drop for GlesTexProgramInner {
@@ -81,6 +92,15 @@ pub(in super::super) struct GlesSolidProgram {
     pub(in super::super) attrib_position: ffi::types::GLint,
 }
 
+#[derive(Debug, Clone)]
+pub(in super::super) struct GlesSolidBatchProgram {
+    pub(in super::super) program: ffi::types::GLuint,
+    pub(in super::super) uniform_matrix: ffi::types::GLint,
+    pub(in super::super) attrib_vert: ffi::types::GLint,
+    pub(in super::super) attrib_position: ffi::types::GLint,
+    pub(in super::super) attrib_color: ffi::types::GLint,
+}
+
 /// Gles pixel shader
 #[derive(Debug, Clone)]
 pub struct GlesPixelProgram(pub(in super::super) Rc<GlesPixelProgramInner>);
@@ -0,0 +1,26 @@
+#version 100
+
+uniform mat3 matrix;
+
+attribute vec2 vert;
+attribute vec4 vert_position;
+// first two rows of the texture matrix, constant unless quads are batched
+attribute vec3 tex_x;
+attribute vec3 tex_y;
+
+varying vec2 v_coords;
+
+mat2 scale(vec2 scale_vec){
+    return mat2(
+        scale_vec.x, 0.0,
+        0.0, scale_vec.y
+    );
+}
+
+void main() {
+    vec2 vert_transform_translation = vert_position.xy;
+    vec2 vert_transform_scale = vert_position.zw;
+    vec3 position = vec3(vert * scale(vert_transform_scale) + vert_transform_translation, 1.0);
+    v_coords = vec2(dot(tex_x, position), dot(tex_y, position));
+    gl_Position = vec4(matrix * position, 1.0);
+}
\ No newline at end of file
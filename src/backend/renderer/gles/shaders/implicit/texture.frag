@@ -21,7 +21,11 @@ uniform float tint;
 #endif
 
 void main() {
+#if defined(YUV)
+    vec4 color = yuv_texture2D(tex, v_coords);
+#else
     vec4 color = texture2D(tex, v_coords);
+#endif
 
 #if defined(NO_ALPHA)
     color = vec4(color.rgb, 1.0) * alpha;
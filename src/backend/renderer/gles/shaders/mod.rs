@@ -11,6 +11,19 @@ pub const NO_ALPHA: &str = "NO_ALPHA";
 pub const EXTERNAL: &str = "EXTERNAL";
 /// Debug flags shader define
 pub const DEBUG_FLAGS: &str = "DEBUG_FLAGS";
+/// YUV texture shader define
+pub const YUV: &str = "YUV";
+
+pub(super) const ROUNDED_CORNERS_SHADER: &str = include_str!("./rounded_corners.frag");
+pub(super) const ALPHA_MASK_SHADER: &str = include_str!("./alpha_mask.frag");
+pub(super) const SHADOW_SHADER: &str = include_str!("./shadow.frag");
+pub(super) const BORDER_SHADER: &str = include_str!("./border.frag");
+pub(super) const BLUR_SHADER: &str = include_str!("./blur.frag");
+pub(super) const BLUR_VERTEX_SHADER: &str = include_str!("./blur.vert");
+pub(super) const BLUR_DOWN_SHADER: &str = include_str!("./blur_down.frag");
+pub(super) const BLUR_UP_SHADER: &str = include_str!("./blur_up.frag");
+pub(super) const COLOR_TRANSFORM_SHADER: &str = include_str!("./color_transform.frag");
+pub(super) const YUV_PRELUDE: &str = include_str!("./yuv.glsl");
 
 use super::*;
 
@@ -114,39 +127,62 @@ pub unsafe fn link_program(
     Ok(program)
 }
 
+// Replaces the `//_DEFINES_` line of a texture shader with `defines`.
+//
+// The YUV variant additionally declares the chroma planes and `yuv_texture2D`. Shaders not handling
+// `YUV` themselves get every `texture2D` call redirected to it, which is correct as long as they only
+// sample `tex`.
+fn texture_variant_source(src: &str, defines: &[&str]) -> String {
+    let mut header = defines.iter().fold(String::new(), |mut header, define| {
+        let _ = writeln!(header, "#define {}", define);
+        header
+    });
+    if defines.contains(&YUV) {
+        header.push_str(YUV_PRELUDE);
+        if !src.contains(YUV) {
+            header.push_str("#define texture2D(sampler, coords) yuv_texture2D(sampler, coords)\n");
+        }
+    }
+    src.replace("//_DEFINES_", &header)
+}
+
 pub(super) unsafe fn texture_program(
     gl: &ffi::Gles2,
     src: &str,
     additional_uniforms: &[UniformName<'_>],
     destruction_callback_sender: Sender<CleanupResource>,
 ) -> Result<GlesTexProgram, GlesError> {
+    let yuv_uniforms = additional_uniforms
+        .iter()
+        .cloned()
+        .chain(yuv::uniform_names())
+        .collect::<Vec<_>>();
     let create_variant = |defines: &[&str]| -> Result<GlesTexProgramVariant, GlesError> {
-        let shader = src.replace(
-            "//_DEFINES_",
-            &defines.iter().fold(String::new(), |mut shader, define| {
-                let _ = writeln!(&mut shader, "#define {}", define);
-                shader
-            }),
-        );
-        let debug_shader = src.replace(
-            "//_DEFINES_",
+        let shader = texture_variant_source(src, defines);
+        let debug_shader = texture_variant_source(
+            src,
             &defines
                 .iter()
-                .chain(&[shaders::DEBUG_FLAGS])
-                .fold(String::new(), |mut shader, define| {
-                    let _ = writeln!(shader, "#define {}", define);
-                    shader
-                }),
+                .copied()
+                .chain([shaders::DEBUG_FLAGS])
+                .collect::<Vec<_>>(),
         );
+        let additional_uniforms = if defines.contains(&shaders::YUV) {
+            &yuv_uniforms[..]
+        } else {
+            additional_uniforms
+        };
 
-        let program = unsafe { link_program(gl, shaders::VERTEX_SHADER, &shader)? };
-        let debug_program = unsafe { link_program(gl, shaders::VERTEX_SHADER, debug_shader.as_ref())? };
+        let program = unsafe { link_program(gl, shaders::VERTEX_SHADER_TEX_PROGRAM, &shader)? };
+        let debug_program =
+            unsafe { link_program(gl, shaders::VERTEX_SHADER_TEX_PROGRAM, debug_shader.as_ref())? };
 
         let vert = CStr::from_bytes_with_nul(b"vert\0").expect("NULL terminated");
         let vert_position = CStr::from_bytes_with_nul(b"vert_position\0").expect("NULL terminated");
         let tex = CStr::from_bytes_with_nul(b"tex\0").expect("NULL terminated");
         let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
-        let tex_matrix = CStr::from_bytes_with_nul(b"tex_matrix\0").expect("NULL terminated");
+        let tex_x = CStr::from_bytes_with_nul(b"tex_x\0").expect("NULL terminated");
+        let tex_y = CStr::from_bytes_with_nul(b"tex_y\0").expect("NULL terminated");
         let alpha = CStr::from_bytes_with_nul(b"alpha\0").expect("NULL terminated");
         let tint = CStr::from_bytes_with_nul(b"tint\0").expect("NULL terminated");
 
@@ -155,55 +191,29 @@ pub(super) unsafe fn texture_program(
                 program,
                 uniform_tex: gl.GetUniformLocation(program, tex.as_ptr() as *const ffi::types::GLchar),
                 uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
-                uniform_tex_matrix: gl
-                    .GetUniformLocation(program, tex_matrix.as_ptr() as *const ffi::types::GLchar),
                 uniform_alpha: gl.GetUniformLocation(program, alpha.as_ptr() as *const ffi::types::GLchar),
                 attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
                 attrib_vert_position: gl
                     .GetAttribLocation(program, vert_position.as_ptr() as *const ffi::types::GLchar),
-                additional_uniforms: additional_uniforms
-                    .iter()
-                    .map(|uniform| {
-                        let name = CString::new(uniform.name.as_bytes()).expect("Interior null in name");
-                        let location =
-                            gl.GetUniformLocation(program, name.as_ptr() as *const ffi::types::GLchar);
-                        (
-                            uniform.name.clone().into_owned(),
-                            UniformDesc {
-                                location,
-                                type_: uniform.type_,
-                            },
-                        )
-                    })
-                    .collect(),
+                attrib_tex_x: gl.GetAttribLocation(program, tex_x.as_ptr() as *const ffi::types::GLchar),
+                attrib_tex_y: gl.GetAttribLocation(program, tex_y.as_ptr() as *const ffi::types::GLchar),
+                additional_uniforms: uniform_descs(gl, program, additional_uniforms),
             },
             debug: GlesTexProgramInternal {
                 program: debug_program,
                 uniform_tex: gl.GetUniformLocation(debug_program, tex.as_ptr() as *const ffi::types::GLchar),
                 uniform_matrix: gl
                     .GetUniformLocation(debug_program, matrix.as_ptr() as *const ffi::types::GLchar),
-                uniform_tex_matrix: gl
-                    .GetUniformLocation(debug_program, tex_matrix.as_ptr() as *const ffi::types::GLchar),
                 uniform_alpha: gl
                     .GetUniformLocation(debug_program, alpha.as_ptr() as *const ffi::types::GLchar),
                 attrib_vert: gl.GetAttribLocation(debug_program, vert.as_ptr() as *const ffi::types::GLchar),
                 attrib_vert_position: gl
                     .GetAttribLocation(debug_program, vert_position.as_ptr() as *const ffi::types::GLchar),
-                additional_uniforms: additional_uniforms
-                    .iter()
-                    .map(|uniform| {
-                        let name = CString::new(uniform.name.as_bytes()).expect("Interior null in name");
-                        let location =
-                            gl.GetUniformLocation(debug_program, name.as_ptr() as *const ffi::types::GLchar);
-                        (
-                            uniform.name.clone().into_owned(),
-                            UniformDesc {
-                                location,
-                                type_: uniform.type_,
-                            },
-                        )
-                    })
-                    .collect(),
+                attrib_tex_x: gl
+                    .GetAttribLocation(debug_program, tex_x.as_ptr() as *const ffi::types::GLchar),
+                attrib_tex_y: gl
+                    .GetAttribLocation(debug_program, tex_y.as_ptr() as *const ffi::types::GLchar),
+                additional_uniforms: uniform_descs(gl, debug_program, additional_uniforms),
             },
             // debug flags
             uniform_tint: gl.GetUniformLocation(debug_program, tint.as_ptr() as *const ffi::types::GLchar),
@@ -215,6 +225,7 @@ pub(super) unsafe fn texture_program(
             create_variant(&[])?,
             create_variant(&[shaders::NO_ALPHA])?,
             create_variant(&[shaders::EXTERNAL])?,
+            create_variant(&[shaders::YUV])?,
         ],
         destruction_callback_sender,
     })))
@@ -236,3 +247,44 @@ pub(super) unsafe fn solid_program(gl: &ffi::Gles2) -> Result<GlesSolidProgram,
         attrib_position: gl.GetAttribLocation(program, position.as_ptr() as *const ffi::types::GLchar),
     })
 }
+
+pub(super) unsafe fn solid_batch_program(gl: &ffi::Gles2) -> Result<GlesSolidBatchProgram, GlesError> {
+    let program = link_program(
+        gl,
+        shaders::VERTEX_SHADER_SOLID_BATCH,
+        shaders::FRAGMENT_SHADER_SOLID_BATCH,
+    )?;
+
+    let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
+    let vert = CStr::from_bytes_with_nul(b"vert\0").expect("NULL terminated");
+    let position = CStr::from_bytes_with_nul(b"position\0").expect("NULL terminated");
+    let color = CStr::from_bytes_with_nul(b"color\0").expect("NULL terminated");
+
+    Ok(GlesSolidBatchProgram {
+        program,
+        uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
+        attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
+        attrib_position: gl.GetAttribLocation(program, position.as_ptr() as *const ffi::types::GLchar),
+        attrib_color: gl.GetAttribLocation(program, color.as_ptr() as *const ffi::types::GLchar),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{texture_variant_source, FRAGMENT_SHADER, NO_ALPHA, YUV};
+
+    #[test]
+    fn yuv_variant_redirects_sampling_of_unaware_shaders() {
+        let unaware = "//_DEFINES_\nvoid main() { gl_FragColor = texture2D(tex, v_coords); }";
+        let source = texture_variant_source(unaware, &[YUV]);
+        assert!(source.starts_with("#define YUV\n"));
+        assert!(source.contains("yuv_texture2D(sampler2D luma"));
+        assert!(source.contains("#define texture2D(sampler, coords) yuv_texture2D(sampler, coords)"));
+        assert!(!texture_variant_source(unaware, &[NO_ALPHA]).contains("yuv_texture2D"));
+
+        // the builtin shader samples the planes itself
+        let source = texture_variant_source(FRAGMENT_SHADER, &[YUV]);
+        assert!(source.contains("yuv_texture2D(sampler2D luma"));
+        assert!(!source.contains("#define texture2D"));
+    }
+}
@@ -0,0 +1,66 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// size of the clip rectangle
+uniform vec2 geo_size;
+// corner radii: top left, top right, bottom right, bottom left
+uniform vec4 radii;
+// maps window coordinates to coordinates relative to the clip rectangle
+uniform mat3 frag_to_geo;
+
+// signed distance to the border of the rounded clip rectangle
+float rounded_rect_distance(vec2 p) {
+    vec2 half_size = geo_size * 0.5;
+    vec2 center = p - half_size;
+    float radius = center.x < 0.0
+        ? (center.y < 0.0 ? radii.x : radii.w)
+        : (center.y < 0.0 ? radii.y : radii.z);
+    vec2 q = abs(center) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
+void main() {
+#if defined(YUV)
+    vec4 color = yuv_texture2D(tex, v_coords);
+#else
+    vec4 color = texture2D(tex, v_coords);
+#endif
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0) * alpha;
+#else
+    color = color * alpha;
+#endif
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    // anti-aliased edge over one pixel
+    vec2 p = (frag_to_geo * vec3(gl_FragCoord.xy, 1.0)).xy;
+    gl_FragColor = color * clamp(0.5 - rounded_rect_distance(p), 0.0, 1.0);
+}
@@ -0,0 +1,89 @@
+#if defined(GL_FRAGMENT_PRECISION_HIGH)
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+uniform float alpha;
+uniform vec2 size;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// premultiplied color of the shadow
+uniform vec4 color;
+// rectangle casting the shadow relative to the element: x, y, width, height
+uniform vec4 shadow_rect;
+// rectangle of the window the shadow is not drawn below relative to the element
+uniform vec4 window_rect;
+uniform float corner_radius;
+uniform float sigma;
+
+const float PI = 3.141592653589793;
+
+// approximation of the error function
+vec2 erf(vec2 x) {
+    vec2 s = sign(x);
+    vec2 a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+float gaussian(float x, float sigma) {
+    return exp(-(x * x) / (2.0 * sigma * sigma)) / (sqrt(2.0 * PI) * sigma);
+}
+
+// blurred coverage of a single row of the rounded rectangle
+float rounded_box_shadow_x(float x, float y, float sigma, float corner, vec2 half_size) {
+    float delta = min(half_size.y - corner - abs(y), 0.0);
+    float curved = half_size.x - corner + sqrt(max(0.0, corner * corner - delta * delta));
+    vec2 integral = 0.5 + 0.5 * erf((x + vec2(-curved, curved)) * (sqrt(0.5) / sigma));
+    return integral.y - integral.x;
+}
+
+// gaussian blurred coverage of a rounded rectangle, integrated numerically along the y axis
+float rounded_box_shadow(vec4 rect, vec2 point, float sigma, float corner) {
+    vec2 half_size = rect.zw * 0.5;
+    point -= rect.xy + half_size;
+    corner = min(corner, min(half_size.x, half_size.y));
+
+    float low = point.y - half_size.y;
+    float high = point.y + half_size.y;
+    float start = clamp(-3.0 * sigma, low, high);
+    float end = clamp(3.0 * sigma, low, high);
+
+    float step = (end - start) / 4.0;
+    float y = start + step * 0.5;
+    float value = 0.0;
+    for (int i = 0; i < 4; i++) {
+        value += rounded_box_shadow_x(point.x, point.y - y, sigma, corner, half_size) * gaussian(y, sigma) * step;
+        y += step;
+    }
+    return value;
+}
+
+// signed distance to the border of a rounded rectangle
+float rounded_rect_distance(vec4 rect, vec2 point, float radius) {
+    vec2 half_size = rect.zw * 0.5;
+    vec2 q = abs(point - rect.xy - half_size) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
+void main() {
+    vec2 point = v_coords * size;
+    float shadow = rounded_box_shadow(shadow_rect, point, max(sigma, 0.01), corner_radius);
+    // the shadow is not visible through translucent windows
+    float outside = clamp(0.5 + rounded_rect_distance(window_rect, point, corner_radius), 0.0, 1.0);
+
+    vec4 mix_color = color * shadow * outside * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        mix_color = vec4(0.0, 0.3, 0.0, 0.2) + mix_color * 0.8;
+#endif
+
+    gl_FragColor = mix_color;
+}
@@ -0,0 +1,22 @@
+// Inserted in place of the defines of the YUV variant of texture shaders.
+// `tex` only contains the luma plane, colors have to be sampled with `yuv_texture2D`.
+
+// planes containing the chroma samples, both refer to the same texture for semi-planar formats
+uniform sampler2D tex_u;
+uniform sampler2D tex_v;
+// select the channel of the chroma planes containing the respective sample
+uniform mediump vec2 u_select;
+uniform mediump vec2 v_select;
+
+// conversion from the offset corrected samples to RGB
+uniform mediump mat3 yuv_matrix;
+uniform mediump vec3 yuv_offset;
+
+mediump vec4 yuv_texture2D(sampler2D luma, mediump vec2 coords) {
+    mediump vec3 yuv = vec3(
+        texture2D(luma, coords).r,
+        dot(texture2D(tex_u, coords).rg, u_select),
+        dot(texture2D(tex_v, coords).rg, v_select)
+    );
+    return vec4(clamp(yuv_matrix * (yuv - yuv_offset), 0.0, 1.0), 1.0);
+}
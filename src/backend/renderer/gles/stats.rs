@@ -0,0 +1,182 @@
+use std::{collections::VecDeque, time::Duration};
+
+use super::ffi;
+use crate::utils::{Physical, Rectangle, Size};
+
+/// Statistics of a single frame rendered by a [`GlesRenderer`](super::GlesRenderer)
+///
+/// Texture uploads are counted for the next frame finished after them, as textures
+/// are usually imported right before rendering.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    /// Number of issued draw calls
+    pub draw_calls: u32,
+    /// Number of pixels covered by drawing operations, overlapping operations are counted repeatedly
+    pub pixels_drawn: u64,
+    /// Number of texture uploads from memory
+    pub texture_uploads: u32,
+    /// Number of bytes uploaded into textures
+    pub uploaded_bytes: u64,
+    /// Time the gpu spent rendering the frame
+    ///
+    /// Only available with [`GlesRenderer::set_gpu_timing`](super::GlesRenderer::set_gpu_timing)
+    /// and if the measurement was not disturbed, e.g. by a gpu frequency change.
+    pub gpu_time: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub(super) struct StatsRecorder {
+    current: FrameStats,
+    // finished frames waiting for the result of their timer query
+    pending: VecDeque<(FrameStats, Option<ffi::types::GLuint>)>,
+    active_query: Option<ffi::types::GLuint>,
+    free_queries: Vec<ffi::types::GLuint>,
+    last: Option<FrameStats>,
+    gpu_timing: bool,
+}
+
+impl StatsRecorder {
+    pub(super) fn new() -> Self {
+        StatsRecorder {
+            current: FrameStats::default(),
+            pending: VecDeque::new(),
+            active_query: None,
+            free_queries: Vec::new(),
+            last: None,
+            gpu_timing: false,
+        }
+    }
+
+    pub(super) fn gpu_timing(&self) -> bool {
+        self.gpu_timing
+    }
+
+    pub(super) fn set_gpu_timing(&mut self, enabled: bool) {
+        self.gpu_timing = enabled;
+    }
+
+    pub(super) fn last(&self) -> Option<FrameStats> {
+        self.last
+    }
+
+    pub(super) fn record_draws(&mut self, draw_calls: u32, pixels: u64) {
+        self.current.draw_calls += draw_calls;
+        self.current.pixels_drawn += pixels;
+    }
+
+    pub(super) fn record_upload(&mut self, bytes: usize) {
+        self.current.texture_uploads += 1;
+        self.current.uploaded_bytes += bytes as u64;
+    }
+
+    pub(super) unsafe fn begin_frame(&mut self, gl: &ffi::Gles2) {
+        // a leaked frame never ended its query
+        if let Some(query) = self.active_query.take() {
+            gl.EndQueryEXT(ffi::TIME_ELAPSED_EXT);
+            self.free_queries.push(query);
+        }
+
+        if self.gpu_timing {
+            let query = self.free_queries.pop().unwrap_or_else(|| {
+                let mut query = 0;
+                gl.GenQueriesEXT(1, &mut query);
+                query
+            });
+            gl.BeginQueryEXT(ffi::TIME_ELAPSED_EXT, query);
+            self.active_query = Some(query);
+        }
+    }
+
+    pub(super) unsafe fn end_frame(&mut self, gl: &ffi::Gles2) {
+        let query = self.active_query.take();
+        if query.is_some() {
+            gl.EndQueryEXT(ffi::TIME_ELAPSED_EXT);
+        }
+        self.pending.push_back((std::mem::take(&mut self.current), query));
+        self.poll(gl);
+    }
+
+    unsafe fn poll(&mut self, gl: &ffi::Gles2) {
+        let mut disjoint = 0;
+        if self.pending.iter().any(|(_, query)| query.is_some()) {
+            gl.GetIntegerv(ffi::GPU_DISJOINT_EXT, &mut disjoint);
+        }
+
+        while let Some((mut stats, query)) = self.pending.pop_front() {
+            if let Some(query) = query {
+                let mut available = 0;
+                gl.GetQueryObjectuivEXT(query, ffi::QUERY_RESULT_AVAILABLE_EXT, &mut available);
+                if available == 0 {
+                    self.pending.push_front((stats, Some(query)));
+                    break;
+                }
+
+                let mut elapsed = 0;
+                gl.GetQueryObjectui64vEXT(query, ffi::QUERY_RESULT_EXT, &mut elapsed);
+                self.free_queries.push(query);
+                stats.gpu_time = (disjoint == 0).then(|| Duration::from_nanos(elapsed));
+            }
+            self.last = Some(stats);
+        }
+    }
+
+    pub(super) unsafe fn destroy(&mut self, gl: &ffi::Gles2) {
+        let queries = self
+            .free_queries
+            .drain(..)
+            .chain(self.active_query.take())
+            .chain(self.pending.drain(..).filter_map(|(_, query)| query))
+            .collect::<Vec<_>>();
+        if !queries.is_empty() {
+            gl.DeleteQueriesEXT(queries.len() as i32, queries.as_ptr());
+        }
+    }
+}
+
+// Number of pixels of `damage` inside of a destination of `size`
+pub(super) fn damage_area(size: Size<i32, Physical>, damage: &[Rectangle<i32, Physical>]) -> u64 {
+    let bounds = Rectangle::from_loc_and_size((0, 0), size);
+    damage
+        .iter()
+        .filter_map(|rect| rect.intersection(bounds))
+        .map(|rect| rect.size.w as u64 * rect.size.h as u64)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{damage_area, ffi, FrameStats, StatsRecorder};
+    use crate::utils::Rectangle;
+
+    #[test]
+    fn stats_are_collected_per_frame() {
+        // without gpu timing no gl function is called
+        let gl = ffi::Gles2::load_with(|_| std::ptr::null());
+        let mut stats = StatsRecorder::new();
+
+        stats.record_upload(64 * 64 * 4);
+        unsafe { stats.begin_frame(&gl) };
+        let damage = [
+            Rectangle::from_loc_and_size((0, 0), (10, 10)),
+            Rectangle::from_loc_and_size((95, 95), (10, 10)),
+        ];
+        stats.record_draws(1, damage_area((100, 100).into(), &damage));
+        unsafe { stats.end_frame(&gl) };
+        assert_eq!(
+            stats.last(),
+            Some(FrameStats {
+                draw_calls: 1,
+                pixels_drawn: 125,
+                texture_uploads: 1,
+                uploaded_bytes: 64 * 64 * 4,
+                gpu_time: None,
+            })
+        );
+
+        unsafe {
+            stats.begin_frame(&gl);
+            stats.end_frame(&gl);
+        }
+        assert_eq!(stats.last(), Some(FrameStats::default()));
+    }
+}
@@ -28,8 +28,10 @@ impl GlesTexture {
             has_alpha: !opaque,
             is_external: false,
             y_inverted: false,
+            swapped_rb: false,
             size,
             egl_images: None,
+            yuv: None,
             destruction_callback_sender: renderer.destruction_callback_sender.clone(),
         }))
     }
@@ -54,13 +56,23 @@ pub(super) struct GlesTextureInternal {
     pub(super) has_alpha: bool,
     pub(super) is_external: bool,
     pub(super) y_inverted: bool,
+    // red and blue are stored swapped compared to `format`
+    pub(super) swapped_rb: bool,
     pub(super) size: Size<i32, BufferCoord>,
     pub(super) egl_images: Option<Vec<EGLImage>>,
+    // additional planes of textures sampled with a YUV conversion shader
+    pub(super) yuv: Option<yuv::YuvPlanes>,
     pub(super) destruction_callback_sender: Sender<CleanupResource>,
 }
 unsafe impl Send for GlesTextureInternal {}
 unsafe impl Sync for GlesTextureInternal {}
 
+impl PartialEq for GlesTexture {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 impl Drop for GlesTextureInternal {
     fn drop(&mut self) {
         let _ = self
@@ -88,6 +100,11 @@ impl Texture for GlesTexture {
     }
     fn format(&self) -> Option<Fourcc> {
         let fmt = gl_internal_format_to_fourcc(self.0.format?);
+        let fmt = if self.0.swapped_rb {
+            fmt.and_then(swap_red_blue)
+        } else {
+            fmt
+        };
         if self.0.has_alpha {
             fmt
         } else {
@@ -1,6 +1,6 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap, ffi::CString};
 
-use super::GlesError;
+use super::{ffi, GlesError, GlesTexture};
 
 /// Different value types of a shader uniform variable for the [`GlesRenderer`](super::GlesRenderer).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -47,6 +47,8 @@ pub enum UniformType {
     Matrix4x3,
     /// 4x4 matrices
     Matrix4x4,
+    /// A texture sampled by a `sampler2D` or `samplerExternalOES`
+    Texture,
 }
 
 /// GL location and type of a uniform shader variable
@@ -56,6 +58,8 @@ pub struct UniformDesc {
     pub location: super::ffi::types::GLint,
     /// type of the uniform
     pub type_: UniformType,
+    /// texture unit the texture of a [`UniformType::Texture`] uniform is bound to
+    pub texture_unit: Option<u32>,
 }
 
 /// A shader uniform variable consisting out of a name and value
@@ -227,6 +231,8 @@ pub enum UniformValue {
         /// If transpose is `true`, each matrix is assumed to be supplied in row major order.
         transpose: bool,
     },
+    /// A texture
+    Texture(GlesTexture),
 }
 
 impl UniformValue {
@@ -259,6 +265,7 @@ impl UniformValue {
             UniformValue::Matrix4x2 { .. } => UniformType::Matrix4x2,
             UniformValue::Matrix4x3 { .. } => UniformType::Matrix4x3,
             UniformValue::Matrix4x4 { .. } => UniformType::Matrix4x4,
+            UniformValue::Texture(_) => UniformType::Texture,
         }
     }
 
@@ -361,6 +368,21 @@ impl UniformValue {
                     matrices.as_ptr() as *const _,
                 )
             },
+            UniformValue::Texture(texture) => unsafe {
+                let unit = desc.texture_unit.ok_or(GlesError::UniformTypeMismatch {
+                    provided: UniformType::Texture,
+                    declared: desc.type_,
+                })?;
+                let target = if texture.0.is_external {
+                    ffi::TEXTURE_EXTERNAL_OES
+                } else {
+                    ffi::TEXTURE_2D
+                };
+                gl.ActiveTexture(ffi::TEXTURE0 + unit);
+                gl.BindTexture(target, texture.0.texture);
+                gl.Uniform1i(desc.location, unit as i32);
+                gl.ActiveTexture(ffi::TEXTURE0);
+            },
         };
 
         Ok(())
@@ -495,3 +517,48 @@ impl From<[u32; 4]> for UniformValue {
         UniformValue::_4ui(v[0], v[1], v[2], v[3])
     }
 }
+
+impl From<GlesTexture> for UniformValue {
+    #[inline]
+    fn from(texture: GlesTexture) -> Self {
+        UniformValue::Texture(texture)
+    }
+}
+
+impl From<&GlesTexture> for UniformValue {
+    #[inline]
+    fn from(texture: &GlesTexture) -> Self {
+        UniformValue::Texture(texture.clone())
+    }
+}
+
+/// Looks up the locations of the `uniforms` of `program`
+///
+/// Texture uniforms are assigned to consecutive texture units starting at 1,
+/// texture unit 0 is used for the texture rendered by the program.
+pub(super) unsafe fn uniform_descs(
+    gl: &ffi::Gles2,
+    program: ffi::types::GLuint,
+    uniforms: &[UniformName<'_>],
+) -> HashMap<String, UniformDesc> {
+    let mut next_texture_unit = 1;
+    uniforms
+        .iter()
+        .map(|uniform| {
+            let name = CString::new(uniform.name.as_bytes()).expect("Interior null in name");
+            let location = gl.GetUniformLocation(program, name.as_ptr() as *const ffi::types::GLchar);
+            let texture_unit = (uniform.type_ == UniformType::Texture).then(|| {
+                next_texture_unit += 1;
+                next_texture_unit - 1
+            });
+            (
+                uniform.name.clone().into_owned(),
+                UniformDesc {
+                    location,
+                    type_: uniform.type_,
+                    texture_unit,
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,307 @@
+use std::sync::Arc;
+
+use tracing::trace;
+
+use crate::backend::allocator::{dmabuf::Dmabuf, Buffer, Format, Fourcc};
+
+use super::{
+    ffi, GlesError, GlesRenderer, GlesTexture, GlesTextureInternal, Uniform, UniformName, UniformType,
+    UniformValue,
+};
+
+/// Matrix coefficients used to convert YUV buffers to RGB
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvColorSpace {
+    /// ITU-R BT.601, commonly used by standard definition content
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, commonly used by high definition content
+    Bt709,
+    /// ITU-R BT.2020, commonly used by ultra high definition and HDR content
+    Bt2020,
+}
+
+/// Range of the samples of YUV buffers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YuvRange {
+    /// Luma samples range from 16 to 235, chroma samples from 16 to 240 (for 8 bits)
+    #[default]
+    Limited,
+    /// Samples use the full range of values
+    Full,
+}
+
+impl YuvColorSpace {
+    // (Kr, Kb)
+    fn coefficients(self) -> (f32, f32) {
+        match self {
+            YuvColorSpace::Bt601 => (0.299, 0.114),
+            YuvColorSpace::Bt709 => (0.2126, 0.0722),
+            YuvColorSpace::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+// Returns the column-major matrix converting offset corrected YUV samples to RGB and the offset
+fn conversion(color_space: YuvColorSpace, range: YuvRange) -> ([f32; 9], [f32; 3]) {
+    let (kr, kb) = color_space.coefficients();
+    let kg = 1.0 - kr - kb;
+    let (y_scale, c_scale, y_offset) = match range {
+        YuvRange::Limited => (255.0 / 219.0, 255.0 / 224.0, 16.0 / 255.0),
+        YuvRange::Full => (1.0, 1.0, 0.0),
+    };
+    let c_offset = 128.0 / 255.0;
+
+    let r_v = (2.0 - 2.0 * kr) * c_scale;
+    let g_u = -(2.0 * kb * (1.0 - kb) / kg) * c_scale;
+    let g_v = -(2.0 * kr * (1.0 - kr) / kg) * c_scale;
+    let b_u = (2.0 - 2.0 * kb) * c_scale;
+    (
+        [y_scale, y_scale, y_scale, 0.0, g_u, b_u, r_v, g_v, 0.0],
+        [y_offset, c_offset, c_offset],
+    )
+}
+
+/// Layout of a multi-planar YUV format
+#[derive(Debug, Clone, Copy)]
+pub(super) struct YuvLayout {
+    // format each plane is imported as, the first plane contains the luma samples
+    planes: &'static [Fourcc],
+    // horizontal and vertical subsampling of the chroma planes
+    subsampling: (i32, i32),
+    // plane and channel of the chroma samples
+    u: (usize, usize),
+    v: (usize, usize),
+}
+
+pub(super) fn yuv_layout(format: Fourcc) -> Option<YuvLayout> {
+    const SEMI_PLANAR: &[Fourcc] = &[Fourcc::R8, Fourcc::Gr88];
+    const SEMI_PLANAR_16: &[Fourcc] = &[Fourcc::R16, Fourcc::Gr1616];
+    const PLANAR: &[Fourcc] = &[Fourcc::R8, Fourcc::R8, Fourcc::R8];
+
+    let layout = |planes, subsampling, u, v| YuvLayout {
+        planes,
+        subsampling,
+        u,
+        v,
+    };
+    Some(match format {
+        Fourcc::Nv12 => layout(SEMI_PLANAR, (2, 2), (1, 0), (1, 1)),
+        Fourcc::Nv21 => layout(SEMI_PLANAR, (2, 2), (1, 1), (1, 0)),
+        Fourcc::Nv16 => layout(SEMI_PLANAR, (2, 1), (1, 0), (1, 1)),
+        Fourcc::Nv61 => layout(SEMI_PLANAR, (2, 1), (1, 1), (1, 0)),
+        Fourcc::Nv24 => layout(SEMI_PLANAR, (1, 1), (1, 0), (1, 1)),
+        Fourcc::Nv42 => layout(SEMI_PLANAR, (1, 1), (1, 1), (1, 0)),
+        Fourcc::P010 | Fourcc::P012 | Fourcc::P016 => layout(SEMI_PLANAR_16, (2, 2), (1, 0), (1, 1)),
+        Fourcc::Yuv420 => layout(PLANAR, (2, 2), (1, 0), (2, 0)),
+        Fourcc::Yvu420 => layout(PLANAR, (2, 2), (2, 0), (1, 0)),
+        Fourcc::Yuv422 => layout(PLANAR, (2, 1), (1, 0), (2, 0)),
+        Fourcc::Yvu422 => layout(PLANAR, (2, 1), (2, 0), (1, 0)),
+        Fourcc::Yuv444 => layout(PLANAR, (1, 1), (1, 0), (2, 0)),
+        Fourcc::Yvu444 => layout(PLANAR, (1, 1), (2, 0), (1, 0)),
+        _ => return None,
+    })
+}
+
+/// Chroma planes of a texture imported from a multi-planar YUV buffer
+///
+/// The texture itself holds the luma plane.
+#[derive(Debug)]
+pub(super) struct YuvPlanes {
+    u: (GlesTexture, usize),
+    v: (GlesTexture, usize),
+}
+
+impl YuvPlanes {
+    pub(super) fn textures(&self) -> impl Iterator<Item = &GlesTexture> {
+        [&self.u.0, &self.v.0].into_iter()
+    }
+
+    pub(super) fn uniforms(&self, color_space: YuvColorSpace, range: YuvRange) -> [Uniform<'static>; 6] {
+        let select = |channel: usize| if channel == 0 { (1.0, 0.0) } else { (0.0, 1.0) };
+        let (matrix, offset) = conversion(color_space, range);
+        [
+            Uniform::new("tex_u", &self.u.0),
+            Uniform::new("tex_v", &self.v.0),
+            Uniform::new("u_select", select(self.u.1)),
+            Uniform::new("v_select", select(self.v.1)),
+            Uniform::new(
+                "yuv_matrix",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![matrix],
+                    transpose: false,
+                },
+            ),
+            Uniform::new("yuv_offset", (offset[0], offset[1], offset[2])),
+        ]
+    }
+}
+
+// Uniforms declared for the yuv variant of every texture program, matching `YuvPlanes::uniforms`
+pub(super) fn uniform_names() -> [UniformName<'static>; 6] {
+    [
+        UniformName::new("tex_u", UniformType::Texture),
+        UniformName::new("tex_v", UniformType::Texture),
+        UniformName::new("u_select", UniformType::_2f),
+        UniformName::new("v_select", UniformType::_2f),
+        UniformName::new("yuv_matrix", UniformType::Matrix3x3),
+        UniformName::new("yuv_offset", UniformType::_3f),
+    ]
+}
+
+impl GlesRenderer {
+    /// Imports each plane of a multi-planar YUV dmabuf into its own texture
+    pub(super) fn import_yuv_dmabuf(
+        &mut self,
+        buffer: &Dmabuf,
+        layout: YuvLayout,
+    ) -> Result<GlesTexture, GlesError> {
+        let format = buffer.format();
+        // planes like compression metadata can not be imported individually
+        if buffer.num_planes() != layout.planes.len() {
+            return Err(GlesError::UnsupportedPixelLayout);
+        }
+        if let Some(&unsupported) = layout.planes.iter().find(|code| {
+            !self.egl.dmabuf_render_formats().contains(&Format {
+                code: **code,
+                modifier: format.modifier,
+            })
+        }) {
+            return Err(GlesError::UnsupportedPixelFormat(unsupported));
+        }
+
+        let size = buffer.size();
+        let chroma_size = (
+            (size.w + layout.subsampling.0 - 1) / layout.subsampling.0,
+            (size.h + layout.subsampling.1 - 1) / layout.subsampling.1,
+        );
+        let chroma = (1..layout.planes.len())
+            .map(|plane| {
+                let (image, tex) =
+                    self.import_dmabuf_plane(buffer, plane, layout.planes[plane], chroma_size)?;
+                Ok(GlesTexture(Arc::new(GlesTextureInternal {
+                    texture: tex,
+                    format: plane_gl_format(layout.planes[plane]),
+                    has_alpha: false,
+                    is_external: false,
+                    y_inverted: buffer.y_inverted(),
+                    swapped_rb: false,
+                    size: chroma_size.into(),
+                    egl_images: Some(vec![image]),
+                    yuv: None,
+                    destruction_callback_sender: self.destruction_callback_sender.clone(),
+                })))
+            })
+            .collect::<Result<Vec<_>, GlesError>>()?;
+        let (image, tex) = self.import_dmabuf_plane(buffer, 0, layout.planes[0], (size.w, size.h))?;
+        trace!(
+            ?format,
+            planes = layout.planes.len(),
+            "imported yuv dmabuf as separate planes"
+        );
+
+        Ok(GlesTexture(Arc::new(GlesTextureInternal {
+            texture: tex,
+            // the yuv variants of the texture programs always produce opaque rgb
+            format: Some(ffi::RGBA8),
+            has_alpha: false,
+            is_external: false,
+            y_inverted: buffer.y_inverted(),
+            swapped_rb: false,
+            size,
+            egl_images: Some(vec![image]),
+            yuv: Some(YuvPlanes {
+                u: (chroma[layout.u.0 - 1].clone(), layout.u.1),
+                v: (chroma[layout.v.0 - 1].clone(), layout.v.1),
+            }),
+            destruction_callback_sender: self.destruction_callback_sender.clone(),
+        })))
+    }
+
+    fn import_dmabuf_plane(
+        &mut self,
+        buffer: &Dmabuf,
+        plane: usize,
+        format: Fourcc,
+        size: (i32, i32),
+    ) -> Result<(super::EGLImage, ffi::types::GLuint), GlesError> {
+        let image = self
+            .egl
+            .display()
+            .create_image_from_dmabuf_plane(buffer, plane, format, size.into())
+            .map_err(GlesError::BindBufferEGLError)?;
+        let tex = self.import_egl_image(image, false, None)?;
+        unsafe {
+            // chroma planes are sampled without the filters set for the luma plane
+            self.gl.BindTexture(ffi::TEXTURE_2D, tex);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
+            self.gl
+                .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+        }
+        Ok((image, tex))
+    }
+}
+
+fn plane_gl_format(format: Fourcc) -> Option<ffi::types::GLenum> {
+    match format {
+        Fourcc::R8 => Some(ffi::R8),
+        Fourcc::Gr88 => Some(ffi::RG8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{conversion, yuv_layout, YuvColorSpace, YuvRange};
+    use crate::backend::allocator::Fourcc;
+
+    fn convert(yuv: [f32; 3], color_space: YuvColorSpace, range: YuvRange) -> [f32; 3] {
+        let (m, offset) = conversion(color_space, range);
+        let v = [yuv[0] - offset[0], yuv[1] - offset[1], yuv[2] - offset[2]];
+        [0, 1, 2].map(|row| m[row] * v[0] + m[3 + row] * v[1] + m[6 + row] * v[2])
+    }
+
+    #[test]
+    fn conversion_maps_reference_colors() {
+        for color_space in [YuvColorSpace::Bt601, YuvColorSpace::Bt709, YuvColorSpace::Bt2020] {
+            let black = convert([16.0 / 255.0, 0.5, 0.5], color_space, YuvRange::Limited);
+            let white = convert(
+                [235.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0],
+                color_space,
+                YuvRange::Limited,
+            );
+            let full_white = convert([1.0, 128.0 / 255.0, 128.0 / 255.0], color_space, YuvRange::Full);
+            for channel in 0..3 {
+                assert!(black[channel].abs() < 0.01, "{:?}: {:?}", color_space, black);
+                assert!(
+                    (white[channel] - 1.0).abs() < 0.001,
+                    "{:?}: {:?}",
+                    color_space,
+                    white
+                );
+                assert!((full_white[channel] - 1.0).abs() < 0.001);
+            }
+        }
+
+        // pure red in limited range bt.709
+        let red = convert(
+            [63.0 / 255.0, 102.0 / 255.0, 240.0 / 255.0],
+            YuvColorSpace::Bt709,
+            YuvRange::Limited,
+        );
+        assert!(
+            (red[0] - 1.0).abs() < 0.01 && red[1].abs() < 0.01 && red[2].abs() < 0.01,
+            "{:?}",
+            red
+        );
+
+        assert_eq!(yuv_layout(Fourcc::Nv12).unwrap().planes.len(), 2);
+        assert!(yuv_layout(Fourcc::Argb8888).is_none());
+    }
+}
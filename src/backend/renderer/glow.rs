@@ -14,10 +14,10 @@ use crate::{
         allocator::{dmabuf::Dmabuf, format::FormatSet, Format, Fourcc},
         egl::EGLContext,
         renderer::{
-            element::UnderlyingStorage,
+            element::{border::BorderRenderElement, UnderlyingStorage},
             gles::{element::*, *},
-            sync, Bind, Blit, Color32F, DebugFlags, ExportMem, ImportDma, ImportMem, Offscreen, Renderer,
-            TextureFilter, Unbind,
+            sync, Bind, Blit, Color32F, DebugFlags, ExportDma, ExportMem, ImportDma, ImportMem, Offscreen,
+            Renderer, RendererCapabilities, TextureFilter, Unbind,
         },
     },
     utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform},
@@ -243,6 +243,10 @@ impl Renderer for GlowRenderer {
     fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
         self.gl.cleanup_texture_cache()
     }
+
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        self.gl.renderer_capabilities()
+    }
 }
 
 impl<'frame> Frame for GlowFrame<'frame> {
@@ -480,6 +484,13 @@ impl ExportMem for GlowRenderer {
     }
 }
 
+impl ExportDma for GlowRenderer {
+    #[profiling::function]
+    fn export_texture(&mut self, texture: &GlesTexture) -> Result<Dmabuf, GlesError> {
+        self.gl.export_texture(texture)
+    }
+}
+
 impl<T> Bind<T> for GlowRenderer
 where
     GlesRenderer: Bind<T>,
@@ -571,3 +582,21 @@ impl RenderElement<GlowRenderer> for TextureShaderElement {
         RenderElement::<GlesRenderer>::underlying_storage(self, renderer.borrow_mut())
     }
 }
+
+impl RenderElement<GlowRenderer> for BorderRenderElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut GlowFrame<'_>,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        RenderElement::<GlesRenderer>::draw(self, frame.borrow_mut(), src, dst, damage, opaque_regions)
+    }
+
+    fn underlying_storage(&self, renderer: &mut GlowRenderer) -> Option<UnderlyingStorage<'_>> {
+        RenderElement::<GlesRenderer>::underlying_storage(self, renderer.borrow_mut())
+    }
+}
@@ -0,0 +1,314 @@
+//! Helper for mirroring the content of one output on another
+//!
+//! [`OutputMirror`] presents the content of a source output on a target output (clone mode),
+//! e.g. for mirroring a laptop panel on a projector. If the modes of both outputs differ,
+//! the content is scaled to fit the target output and centered, leaving bars cleared with
+//! a color at the sides.
+//!
+//! There are two ways of mirroring an output:
+//!
+//! - [`OutputMirror::blit`] copies the already composited frame of the source output into the
+//!   buffer of the target output. This is the cheapest option, but requires the renderer of the
+//!   target output to be able to read the buffer of the source output, usually because both
+//!   outputs are driven by the same gpu, and both outputs to use the same transform.
+//! - [`OutputMirror::render`] re-renders the elements of the source output for the target output.
+//!   This works with any combination of renderers and transforms. The elements can also be
+//!   obtained with [`OutputMirror::mirror_elements`] to be rendered by other means, e.g. a
+//!   [`DrmCompositor`](crate::backend::drm::compositor::DrmCompositor).
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     element::solid::SolidColorRenderElement,
+//! #     gles::GlesRenderer,
+//! #     mirror::OutputMirror,
+//! #     Color32F,
+//! # };
+//! # use smithay::utils::Transform;
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! // mirror a 2560x1600 panel on a 1920x1080 projector
+//! let mut mirror = OutputMirror::new(
+//!     (2560, 1600),
+//!     1.5,
+//!     Transform::Normal,
+//!     (1920, 1080),
+//!     Transform::Normal,
+//! );
+//!
+//! // render the elements of the panel into the currently bound buffer of the projector
+//! mirror
+//!     .render(&mut renderer, 0, &elements, Color32F::BLACK)
+//!     .expect("Failed to mirror output");
+//! ```
+
+use crate::{
+    backend::allocator::{dmabuf::Dmabuf, Buffer as _},
+    output::{Output, OutputNoMode},
+    utils::{Physical, Rectangle, Scale, Size, Transform},
+};
+
+use super::{
+    damage::{Error as DamageError, OutputDamageTracker, RenderOutputResult},
+    element::{
+        utils::{
+            constrain_render_elements, ConstrainAlign, ConstrainScaleBehavior, CropRenderElement,
+            RelocateRenderElement, RescaleRenderElement,
+        },
+        Element, RenderElement,
+    },
+    Bind, Blit, Color32F, Frame, Renderer, Texture, TextureFilter,
+};
+
+/// Errors returned by [`OutputMirror::blit`]
+#[derive(thiserror::Error)]
+pub enum MirrorError<R: Renderer> {
+    /// The source and target output use different transforms, which can not be applied by blitting
+    #[error("The source transform {from:?} does not match the target transform {to:?}")]
+    TransformMismatch {
+        /// Transform of the source output
+        from: Transform,
+        /// Transform of the target output
+        to: Transform,
+    },
+    /// The renderer returned an error
+    #[error(transparent)]
+    Rendering(R::Error),
+}
+
+impl<R: Renderer> std::fmt::Debug for MirrorError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MirrorError::TransformMismatch { from, to } => f
+                .debug_struct("TransformMismatch")
+                .field("from", from)
+                .field("to", to)
+                .finish(),
+            MirrorError::Rendering(err) => f.debug_tuple("Rendering").field(err).finish(),
+        }
+    }
+}
+
+/// Render element of a source output positioned for the target output of an [`OutputMirror`]
+pub type MirrorRenderElement<E> = CropRenderElement<RelocateRenderElement<RescaleRenderElement<E>>>;
+
+/// State for mirroring the content of one output on another
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct OutputMirror {
+    source_size: Size<i32, Physical>,
+    source_scale: Scale<f64>,
+    source_transform: Transform,
+    target_size: Size<i32, Physical>,
+    target_transform: Transform,
+    filter: TextureFilter,
+    damage_tracker: OutputDamageTracker,
+}
+
+impl OutputMirror {
+    /// Create a new mirror from the mode size, scale and transform of the source output
+    /// and the mode size and transform of the target output
+    pub fn new(
+        source_size: impl Into<Size<i32, Physical>>,
+        source_scale: impl Into<Scale<f64>>,
+        source_transform: Transform,
+        target_size: impl Into<Size<i32, Physical>>,
+        target_transform: Transform,
+    ) -> Self {
+        let source_scale = source_scale.into();
+        let target_size = target_size.into();
+        OutputMirror {
+            source_size: source_size.into(),
+            source_scale,
+            source_transform,
+            target_size,
+            target_transform,
+            filter: TextureFilter::Linear,
+            // the elements are created for the source output, so they have to be queried with its scale
+            damage_tracker: OutputDamageTracker::new(target_size, source_scale, target_transform),
+        }
+    }
+
+    /// Create a new mirror from the current state of the source and target output
+    pub fn from_outputs(source: &Output, target: &Output) -> Result<Self, OutputNoMode> {
+        let source_mode = source.current_mode().ok_or(OutputNoMode)?;
+        let target_mode = target.current_mode().ok_or(OutputNoMode)?;
+        Ok(OutputMirror::new(
+            source_mode.size,
+            source.current_scale().fractional_scale(),
+            source.current_transform(),
+            target_mode.size,
+            target.current_transform(),
+        ))
+    }
+
+    /// Update the mirror to the current state of the source and target output
+    ///
+    /// The next frame is fully redrawn, if anything changed.
+    pub fn update_from_outputs(&mut self, source: &Output, target: &Output) -> Result<(), OutputNoMode> {
+        let mirror = OutputMirror::from_outputs(source, target)?;
+        if self.source_size != mirror.source_size
+            || self.source_scale != mirror.source_scale
+            || self.source_transform != mirror.source_transform
+            || self.target_size != mirror.target_size
+            || self.target_transform != mirror.target_transform
+        {
+            *self = OutputMirror {
+                filter: self.filter,
+                ..mirror
+            };
+        }
+        Ok(())
+    }
+
+    /// Set the filter used for scaling the content of the source output
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = filter;
+    }
+
+    /// Returns the area of the target output the source output is shown in
+    ///
+    /// The area is given in the physical coordinate space of the target output.
+    pub fn letterbox(&self) -> Rectangle<i32, Physical> {
+        letterbox(
+            self.source_transform.transform_size(self.source_size),
+            self.target_transform.transform_size(self.target_size),
+        )
+    }
+
+    /// Returns whether the frames of the source output can be blitted to the target output
+    pub fn can_blit(&self) -> bool {
+        self.source_transform == self.target_transform
+    }
+
+    /// Position the elements of the source output for the target output
+    ///
+    /// `elements` are expected to be positioned in the physical coordinate space of the source output.
+    pub fn mirror_elements<E: Element>(
+        &self,
+        elements: impl IntoIterator<Item = E>,
+    ) -> impl Iterator<Item = MirrorRenderElement<E>> {
+        let source =
+            Rectangle::from_loc_and_size((0, 0), self.source_transform.transform_size(self.source_size));
+        constrain_render_elements(
+            elements,
+            (0, 0),
+            self.letterbox(),
+            source,
+            ConstrainScaleBehavior::Fit,
+            ConstrainAlign::CENTER,
+            self.source_scale,
+        )
+    }
+
+    /// Render the elements of the source output into the currently bound buffer of the target output
+    ///
+    /// - `elements` of the source output in front-to-back order
+    #[profiling::function]
+    pub fn render<'a, R, E>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        elements: &'a [E],
+        clear_color: impl Into<Color32F>,
+    ) -> Result<RenderOutputResult<'_>, DamageError<R>>
+    where
+        R: Renderer,
+        <R as Renderer>::TextureId: Texture,
+        E: RenderElement<R>,
+    {
+        let elements = self.mirror_elements(elements).collect::<Vec<_>>();
+        self.damage_tracker
+            .render_output(renderer, age, &elements, clear_color)
+    }
+
+    /// Copy the composited frame of the source output into the buffer of the target output
+    ///
+    /// `source` has to contain the complete frame of the source output. The parts of `target`
+    /// not covered by the [`letterbox`](OutputMirror::letterbox) are cleared with `clear_color`.
+    #[profiling::function]
+    pub fn blit<R, T>(
+        &self,
+        renderer: &mut R,
+        source: Dmabuf,
+        target: T,
+        clear_color: impl Into<Color32F>,
+    ) -> Result<(), MirrorError<R>>
+    where
+        R: Renderer + Bind<T> + Blit<Dmabuf>,
+    {
+        if !self.can_blit() {
+            return Err(MirrorError::TransformMismatch {
+                from: self.source_transform,
+                to: self.target_transform,
+            });
+        }
+
+        // both outputs use the same transform, so the letterbox can be calculated in buffer space
+        let source_size = source.size();
+        let source_rect = Rectangle::from_loc_and_size((0, 0), (source_size.w, source_size.h));
+        let target_rect = Rectangle::from_loc_and_size((0, 0), self.target_size);
+        let dst = letterbox(source_rect.size, self.target_size);
+
+        renderer.bind(target).map_err(MirrorError::Rendering)?;
+        let bars = target_rect.subtract_rect(dst);
+        if !bars.is_empty() {
+            let mut frame = renderer
+                .render(self.target_size, Transform::Normal)
+                .map_err(MirrorError::Rendering)?;
+            frame
+                .clear(clear_color.into(), &bars)
+                .map_err(MirrorError::Rendering)?;
+            let sync = frame.finish().map_err(MirrorError::Rendering)?;
+            renderer.wait(&sync).map_err(MirrorError::Rendering)?;
+        }
+        renderer
+            .blit_from(source, source_rect, dst, self.filter)
+            .map_err(MirrorError::Rendering)
+    }
+}
+
+// Largest rectangle with the aspect ratio of `source` fitting centered into `target`
+fn letterbox(source: Size<i32, Physical>, target: Size<i32, Physical>) -> Rectangle<i32, Physical> {
+    if source.w <= 0 || source.h <= 0 {
+        return Rectangle::from_loc_and_size((0, 0), target);
+    }
+
+    let scale = f64::min(
+        target.w as f64 / source.w as f64,
+        target.h as f64 / source.h as f64,
+    );
+    let size = Size::<i32, Physical>::from((
+        ((source.w as f64 * scale).round() as i32).min(target.w),
+        ((source.h as f64 * scale).round() as i32).min(target.h),
+    ));
+    Rectangle::from_loc_and_size(((target.w - size.w) / 2, (target.h - size.h) / 2), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{letterbox, OutputMirror};
+    use crate::utils::{Rectangle, Transform};
+
+    #[test]
+    fn mirror_letterboxes_mismatched_modes() {
+        // pillarbox a 4:3 source on a 16:9 target
+        assert_eq!(
+            letterbox((1024, 768).into(), (1920, 1080).into()),
+            Rectangle::from_loc_and_size((240, 0), (1440, 1080))
+        );
+        // letterbox a 16:10 source on a 16:9 target
+        assert_eq!(
+            letterbox((2560, 1600).into(), (1920, 1080).into()),
+            Rectangle::from_loc_and_size((96, 0), (1728, 1080))
+        );
+
+        // the transform of the source output changes its aspect ratio
+        let mirror = OutputMirror::new((1080, 1920), 1.0, Transform::_90, (1920, 1080), Transform::Normal);
+        assert_eq!(
+            mirror.letterbox(),
+            Rectangle::from_loc_and_size((0, 0), (1920, 1080))
+        );
+        assert!(!mirror.can_blit());
+    }
+}
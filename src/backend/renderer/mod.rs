@@ -6,6 +6,26 @@
 //! Supported rendering apis:
 //!
 //! - Raw OpenGL ES 2
+//!
+//! ## Implementing a renderer
+//!
+//! Renderers do not have to be part of smithay, every trait needed to use one with the
+//! [`OutputDamageTracker`](damage::OutputDamageTracker), the render elements and the `desktop` helpers
+//! is public. The minimal surface consists of:
+//!
+//! - [`Renderer`] with matching [`Frame`] and [`Texture`] types. [`Renderer::id`] should be backed
+//!   by a [`RendererId`], as textures of wayland surfaces are cached per renderer id.
+//! - [`ImportMem`] for element types uploading memory, like
+//!   [`MemoryRenderBuffer`](element::memory::MemoryRenderBuffer).
+//!
+//! Rendering wayland surfaces additionally requires:
+//!
+//! - [`ImportMemWl`] and [`ImportDma`]/[`ImportDmaWl`], the latter only has provided methods.
+//! - `ImportEgl` with the `use_system_lib` feature, for which renderers without EGL support only have to
+//!   return an error from `ImportEgl::import_egl_buffer`. [`ImportAll`] is then implemented automatically.
+//!
+//! [`Bind`] and [`Offscreen`] are only needed to render into buffers other than the default target,
+//! [`ExportMem`] to read back the rendered content, e.g. for screencopy.
 
 use std::error::Error;
 use std::fmt;
@@ -27,6 +47,9 @@ pub mod glow;
 #[cfg(feature = "renderer_pixman")]
 pub mod pixman;
 
+#[cfg(feature = "renderer_vulkan")]
+pub mod vulkan;
+
 mod color;
 pub use color::Color32F;
 
@@ -54,6 +77,19 @@ pub mod damage;
 
 pub mod sync;
 
+pub mod offscreen;
+
+pub mod color_transform;
+
+#[cfg(feature = "wayland_frontend")]
+pub mod capture;
+
+pub mod stream;
+
+pub mod mirror;
+
+pub mod screenshot;
+
 #[cfg(feature = "renderer_test")]
 pub mod test;
 
@@ -263,6 +299,81 @@ bitflags::bitflags! {
         const TINT = 0b00000001;
     }
 }
+
+crate::utils::ids::id_gen!(renderer_id);
+
+/// Unique id of a renderer as returned by [`Renderer::id`]
+///
+/// Ids are allocated from a pool shared by all renderers, including ones implemented outside of smithay.
+/// The id is released once this handle is dropped, renderers sharing textures should share the handle.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct RendererId(usize);
+
+impl RendererId {
+    /// Allocate a new id not used by any other renderer
+    pub fn new() -> RendererId {
+        RendererId(renderer_id::next())
+    }
+
+    /// Returns the raw value of this id
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+impl Default for RendererId {
+    fn default() -> Self {
+        RendererId::new()
+    }
+}
+
+impl Drop for RendererId {
+    fn drop(&mut self) {
+        renderer_id::remove(self.0);
+    }
+}
+
+/// Capabilities and limits of a [`Renderer`]
+///
+/// Returned by [`Renderer::renderer_capabilities`] to allow generic code to adapt to a renderer
+/// without knowing its concrete type.
+#[derive(Debug, Default, Clone)]
+pub struct RendererCapabilities {
+    /// Maximum width and height of textures and render targets, `None` if unknown or unlimited
+    pub max_texture_size: Option<Size<i32, BufferCoord>>,
+    /// Formats supported for importing textures from memory
+    pub mem_formats: Vec<Fourcc>,
+    /// Dmabuf formats and modifiers, that can be imported as textures
+    pub dmabuf_texture_formats: FormatSet,
+    /// Dmabuf formats and modifiers, that can be rendered into
+    pub dmabuf_render_formats: FormatSet,
+    /// Dmabuf formats and modifiers, that can only be sampled from, but not rendered into
+    ///
+    /// This is usually the case for yuv formats.
+    pub external_only_formats: FormatSet,
+    /// The renderer can synchronize with other devices using native fences instead of blocking the cpu
+    pub fencing: bool,
+    /// The renderer can apply [color transformations](color_transform) to its output
+    pub color_transform: bool,
+}
+
+impl RendererCapabilities {
+    /// Returns whether a texture or render target of the given size is supported
+    pub fn supports_size(&self, size: Size<i32, BufferCoord>) -> bool {
+        self.max_texture_size
+            .map_or(true, |max| size.w <= max.w && size.h <= max.h)
+    }
+
+    /// Returns the formats of `texture_formats` not contained in `render_formats`
+    pub fn external_only(texture_formats: &FormatSet, render_formats: &FormatSet) -> FormatSet {
+        texture_formats
+            .iter()
+            .filter(|format| !render_formats.contains(format))
+            .copied()
+            .collect()
+    }
+}
+
 /// Abstraction of commonly used rendering operations for compositors.
 pub trait Renderer: fmt::Debug {
     /// Error type returned by the rendering operations of this renderer.
@@ -276,6 +387,8 @@ pub trait Renderer: fmt::Debug {
 
     /// Returns an id, that is unique to all renderers, that can use
     /// `TextureId`s originating from any of these renderers.
+    ///
+    /// Use a [`RendererId`] to allocate an id not colliding with other renderers.
     fn id(&self) -> usize;
 
     /// Set the filter method to be used when rendering a texture into a smaller area than its size
@@ -317,6 +430,13 @@ pub trait Renderer: fmt::Debug {
     fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
         Ok(())
     }
+
+    /// Returns the capabilities and limits of this renderer
+    ///
+    /// The default implementation reports no limits and no supported formats or features.
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        RendererCapabilities::default()
+    }
 }
 
 /// Trait for renderers that support creating offscreen framebuffers to render into.
@@ -426,6 +546,9 @@ pub trait ImportMem: Renderer {
     feature = "use_system_lib"
 ))]
 /// Trait for Renderers supporting importing wl_drm-based buffers.
+///
+/// Renderers not backed by EGL only have to implement [`ImportEgl::import_egl_buffer`],
+/// returning an error, to be usable with [`ImportAll`].
 pub trait ImportEgl: Renderer {
     /// Binds the underlying EGL display to the given Wayland display.
     ///
@@ -440,13 +563,18 @@ pub trait ImportEgl: Renderer {
     /// This might return [`OtherEGLDisplayAlreadyBound`](super::egl::Error::OtherEGLDisplayAlreadyBound)
     /// if called for the same [`Display`](wayland_server::Display) multiple times, as only one egl
     /// display may be bound at any given time.
-    fn bind_wl_display(&mut self, display: &wayland_server::DisplayHandle) -> Result<(), EglError>;
+    ///
+    /// The default implementation returns [`NoEGLDisplayBound`](super::egl::Error::NoEGLDisplayBound).
+    fn bind_wl_display(&mut self, display: &wayland_server::DisplayHandle) -> Result<(), EglError> {
+        let _ = display;
+        Err(EglError::NoEGLDisplayBound)
+    }
 
     /// Unbinds a previously bound egl display, if existing.
     ///
     /// *Note*: As a result any previously created egl-based WlBuffers will not be readable anymore.
     /// Your compositor will have to deal with existing buffers of *unknown* type.
-    fn unbind_wl_display(&mut self);
+    fn unbind_wl_display(&mut self) {}
 
     /// Returns the underlying [`EGLBufferReader`].
     ///
@@ -454,7 +582,9 @@ pub trait ImportEgl: Renderer {
     ///
     /// Returns `None` if no [`Display`](wayland_server::Display) was previously bound to the underlying
     /// [`EGLDisplay`](super::egl::EGLDisplay) (see [`ImportEgl::bind_wl_display`]).
-    fn egl_reader(&self) -> Option<&EGLBufferReader>;
+    fn egl_reader(&self) -> Option<&EGLBufferReader> {
+        None
+    }
 
     /// Import a given wl_drm-based buffer into the renderer (see [`buffer_type`]).
     ///
@@ -665,6 +795,19 @@ pub trait ExportMem: Renderer {
     ) -> Result<&'a [u8], <Self as Renderer>::Error>;
 }
 
+/// Trait for renderers supporting exporting textures as dmabufs.
+pub trait ExportDma: Renderer {
+    /// Exports the passed texture as a [`Dmabuf`], e.g. to share it with another process or device.
+    ///
+    /// The dmabuf refers to the same memory as the texture, later changes to the texture are
+    /// visible in the dmabuf and vice versa.
+    ///
+    /// This function *may* fail, if (but not limited to):
+    /// - The renderer or driver does not support exporting textures
+    /// - The texture was not created by this renderer or is not backed by exportable memory
+    fn export_texture(&mut self, texture: &Self::TextureId) -> Result<Dmabuf, Self::Error>;
+}
+
 /// Trait for renderers supporting blitting contents from one framebuffer to another.
 pub trait Blit<Target>
 where
@@ -39,16 +39,23 @@
 //! and desired target-gpu are up to be implemented by the compositor. The module only
 //! reduces the amount of necessary setup operations.
 //!
+//! To help with the common cases the [`GpuManager`] can track the [role](GpuRole) of every gpu,
+//! given the primary gpu and the gpus driving outputs are registered with it, and pick a render-gpu
+//! per output according to an [`OffloadPolicy`] using [`GpuManager::output_renderer`].
+//! Composition of individual outputs can be forced onto a specific gpu
+//! with [`GpuManager::set_output_render_node`], e.g. for outputs wired to the dedicated gpu
+//! of a hybrid laptop.
+//!
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt,
     sync::{Arc, Mutex},
 };
 
 use super::{
     sync::SyncPoint, Bind, Blit, Color32F, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen,
-    Renderer, Texture, TextureFilter, TextureMapping, Unbind,
+    Renderer, RendererCapabilities, Texture, TextureFilter, TextureMapping, Unbind,
 };
 #[cfg(feature = "wayland_frontend")]
 use super::{ImportDmaWl, ImportMemWl};
@@ -69,6 +76,7 @@ use crate::{
         renderer::sync,
         SwapBuffersError,
     },
+    output::{Output, WeakOutput},
     utils::{Buffer as BufferCoords, Physical, Rectangle, Size, Transform},
 };
 use tracing::{debug, info, info_span, instrument, trace, trace_span, warn};
@@ -84,9 +92,41 @@ pub struct GpuManager<A: GraphicsApi> {
     api: A,
     devices: Vec<A::Device>,
     dmabuf_cache: HashMap<(DrmNode, DrmNode), Option<(bool, Dmabuf)>>,
+    import_cache: ImportCache,
+    primary_node: Option<DrmNode>,
+    display_nodes: HashSet<DrmNode>,
+    offload_policy: OffloadPolicy,
+    output_nodes: HashMap<WeakOutput, DrmNode>,
     span: tracing::Span,
 }
 
+/// Role of a gpu in a multi-gpu setup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuRole {
+    /// The primary gpu, usually driving the internal display and used for composition by default
+    Primary,
+    /// A gpu driving outputs, which is not the primary gpu
+    Display,
+    /// A gpu without any outputs, only used for rendering
+    RenderOnly,
+}
+
+/// Policy selecting the gpu composing an output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OffloadPolicy {
+    /// Compose all outputs on the primary gpu and copy the result to the gpu driving the output
+    ///
+    /// This keeps a dedicated gpu mostly idle, which saves power, but requires a copy for every frame
+    /// of outputs connected to a secondary gpu.
+    #[default]
+    Primary,
+    /// Compose outputs on the gpu driving them, if it is able to render, avoiding copies between gpus
+    Target,
+}
+
+/// Caches, if dmabufs of a given format allocated on the first node can be imported on the second node
+type ImportCache = HashMap<(DrmNode, DrmNode, Format), bool>;
+
 /// Errors generated by [`GpuManager`] and [`MultiRenderer`].
 #[derive(thiserror::Error)]
 pub enum Error<R: GraphicsApi, T: GraphicsApi>
@@ -227,10 +267,129 @@ impl<A: GraphicsApi> GpuManager<A> {
             api,
             devices,
             dmabuf_cache: HashMap::new(),
+            import_cache: HashMap::new(),
+            primary_node: None,
+            display_nodes: HashSet::new(),
+            offload_policy: OffloadPolicy::default(),
+            output_nodes: HashMap::new(),
             span,
         })
     }
 
+    /// Set the primary gpu
+    ///
+    /// Nodes passed to the [`GpuManager`] should be of the same [`NodeType`](crate::backend::drm::NodeType)
+    /// as the ones of the devices of the [`GraphicsApi`], usually render nodes.
+    pub fn set_primary_node(&mut self, node: Option<DrmNode>) {
+        self.primary_node = node;
+    }
+
+    /// Returns the primary gpu, if set
+    pub fn primary_node(&self) -> Option<DrmNode> {
+        self.primary_node
+    }
+
+    /// Register a gpu driving outputs
+    pub fn add_display_node(&mut self, node: DrmNode) {
+        self.display_nodes.insert(node);
+    }
+
+    /// Unregister a gpu driving outputs, e.g. after it was unplugged
+    pub fn remove_display_node(&mut self, node: &DrmNode) {
+        self.display_nodes.remove(node);
+        self.output_nodes.retain(|_, render_node| render_node != node);
+    }
+
+    /// Returns the role of a known gpu
+    ///
+    /// Gpus neither set as primary nor registered as driving outputs are only used for rendering.
+    pub fn gpu_role(&self, node: &DrmNode) -> Option<GpuRole> {
+        if self.primary_node.as_ref() == Some(node) {
+            Some(GpuRole::Primary)
+        } else if self.display_nodes.contains(node) {
+            Some(GpuRole::Display)
+        } else if self.devices.iter().any(|device| device.node() == node) {
+            Some(GpuRole::RenderOnly)
+        } else {
+            None
+        }
+    }
+
+    /// Set the policy selecting the gpu composing an output
+    pub fn set_offload_policy(&mut self, policy: OffloadPolicy) {
+        self.offload_policy = policy;
+    }
+
+    /// Returns the policy selecting the gpu composing an output
+    pub fn offload_policy(&self) -> OffloadPolicy {
+        self.offload_policy
+    }
+
+    /// Force composition of an output onto a specific gpu, overriding the [`OffloadPolicy`]
+    ///
+    /// Passing `None` removes a previously set override. If the gpu is not available at the time
+    /// of rendering, the output is composed as determined by the [`OffloadPolicy`].
+    pub fn set_output_render_node(&mut self, output: &Output, node: Option<DrmNode>) {
+        self.output_nodes.retain(|output, _| output.upgrade().is_some());
+        match node {
+            Some(node) => {
+                self.output_nodes.insert(output.downgrade(), node);
+            }
+            None => {
+                self.output_nodes.remove(&output.downgrade());
+            }
+        }
+    }
+
+    /// Returns the gpu composition of an output was forced onto, if any
+    pub fn output_render_node(&self, output: &Output) -> Option<DrmNode> {
+        self.output_nodes.get(&output.downgrade()).copied()
+    }
+
+    /// Returns the gpu, which should compose an output driven by `target_device`
+    ///
+    /// In order of precedence this is the gpu set via [`GpuManager::set_output_render_node`],
+    /// the gpu preferred by the [`OffloadPolicy`] or the other one of the primary and target gpu,
+    /// whichever is able to render first.
+    pub fn output_render_node_for(
+        &mut self,
+        output: &Output,
+        target_device: &DrmNode,
+    ) -> Result<DrmNode, Error<A, A>> {
+        if self.api.needs_enumeration() || !self.devices.iter().any(|device| device.node() == target_device) {
+            self.api
+                .enumerate(&mut self.devices)
+                .map_err(Error::RenderApiError)?;
+        }
+
+        let devices = &self.devices;
+        select_render_node(
+            self.output_render_node(output),
+            self.offload_policy,
+            self.primary_node,
+            *target_device,
+            |node| devices.iter().any(|device| device.node() == node),
+        )
+        .ok_or(Error::NoDevice(*target_device))
+    }
+
+    /// Create a [`MultiRenderer`] composing an output driven by `target_device`
+    ///
+    /// The render-gpu is selected by [`GpuManager::output_render_node_for`],
+    /// see [`GpuManager::renderer`] for the other arguments.
+    pub fn output_renderer<'api>(
+        &'api mut self,
+        output: &Output,
+        target_device: &DrmNode,
+        copy_format: Fourcc,
+    ) -> Result<MultiRenderer<'api, 'api, A, A>, Error<A, A>>
+    where
+        <A::Device as ApiDevice>::Renderer: Bind<Dmabuf>,
+    {
+        let render_device = self.output_render_node_for(output, target_device)?;
+        self.renderer(&render_device, target_device, copy_format)
+    }
+
     /// Create a [`MultiRenderer`] from a single device.
     ///
     /// This a convenience function to deal with the same types even, if you only need one device.
@@ -259,6 +418,7 @@ impl<A: GraphicsApi> GpuManager<A> {
             render: render.remove(0),
             target: None,
             other_renderers: others,
+            import_cache: &mut self.import_cache,
             span: tracing::Span::current(),
         })
     }
@@ -320,6 +480,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                     format: copy_format,
                 }),
                 other_renderers: others,
+                import_cache: &mut self.import_cache,
                 span: tracing::Span::current(),
             })
         } else {
@@ -327,6 +488,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 render: render.remove(0),
                 target: None,
                 other_renderers: others,
+                import_cache: &mut self.import_cache,
                 span: tracing::Span::current(),
             })
         }
@@ -416,6 +578,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                     format: copy_format,
                 }),
                 other_renderers: others,
+                import_cache: &mut render_api.import_cache,
                 span: tracing::Span::current(),
             })
         } else {
@@ -423,6 +586,7 @@ impl<A: GraphicsApi> GpuManager<A> {
                 render: render.remove(0),
                 target: None,
                 other_renderers: others,
+                import_cache: &mut render_api.import_cache,
                 span: tracing::Span::current(),
             })
         }
@@ -539,6 +703,17 @@ impl<A: GraphicsApi> GpuManager<A> {
                     return Err(Error::DeviceMissing);
                 }
 
+                if let Some(target) = self
+                    .devices
+                    .iter_mut()
+                    .find(|device| target_node == *device.node())
+                {
+                    if import_direct::<A>(dmabuf, Some(damage), &mut texture, target, &mut self.import_cache)
+                    {
+                        return Ok(());
+                    }
+                }
+
                 let mut devices = self.devices.iter_mut();
                 let first = devices.next().unwrap();
                 let src_node = import_on_src_node(dmabuf, Some(damage), &mut texture, first, None, devices)?;
@@ -667,6 +842,23 @@ impl<A: GraphicsApi> GpuManager<A> {
     }
 }
 
+fn select_render_node<N>(
+    forced: Option<N>,
+    policy: OffloadPolicy,
+    primary: Option<N>,
+    target: N,
+    can_render: impl Fn(&N) -> bool,
+) -> Option<N> {
+    let preferred = match policy {
+        OffloadPolicy::Primary => [primary, Some(target)],
+        OffloadPolicy::Target => [Some(target), primary],
+    };
+    forced
+        .into_iter()
+        .chain(preferred.into_iter().flatten())
+        .find(|node| can_render(node))
+}
+
 /// A graphics api, that supports enumerating graphics devices
 pub trait GraphicsApi {
     /// Devices this api produces
@@ -710,6 +902,7 @@ pub struct MultiRenderer<'render, 'target, R: GraphicsApi, T: GraphicsApi> {
     render: &'render mut R::Device,
     target: Option<TargetData<'target, T>>,
     other_renderers: Vec<&'render mut R::Device>,
+    import_cache: &'render mut ImportCache,
     span: tracing::Span,
 }
 
@@ -971,7 +1164,10 @@ where
 {
     type Error = Error<R, T>;
     type TextureId = MultiTexture;
-    type Frame<'frame> = MultiFrame<'render, 'target, 'frame, R, T> where Self: 'frame;
+    type Frame<'frame>
+        = MultiFrame<'render, 'target, 'frame, R, T>
+    where
+        Self: 'frame;
 
     fn id(&self) -> usize {
         self.render.renderer().id()
@@ -997,6 +1193,10 @@ where
         self.render.renderer().debug_flags()
     }
 
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        self.render.renderer().renderer_capabilities()
+    }
+
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     fn render<'frame>(
@@ -1987,6 +2187,60 @@ where
     }
 }
 
+/// Try to import a dmabuf allocated on another node directly on the given device,
+/// skipping any copies if the device supports the format and modifier of the buffer.
+///
+/// The result is cached per source node, device and format, so failing imports are only attempted once.
+fn import_direct<S>(
+    dmabuf: &Dmabuf,
+    damage: Option<&[Rectangle<i32, BufferCoords>]>,
+    texture: &mut MultiTexture,
+    device: &mut S::Device,
+    cache: &mut ImportCache,
+) -> bool
+where
+    S: GraphicsApi + 'static,
+    <S::Device as ApiDevice>::Renderer: Renderer + ImportDma,
+    <<S::Device as ApiDevice>::Renderer as Renderer>::TextureId: 'static,
+{
+    let Some(src_node) = dmabuf.node() else {
+        return false;
+    };
+    let node = *device.node();
+    if src_node == node {
+        return false;
+    }
+
+    let format = dmabuf.format();
+    let key = (src_node, node, format);
+    if cache.get(&key) == Some(&false) {
+        return false;
+    }
+    if !ImportDma::has_dmabuf_format(device.renderer(), format) {
+        cache.insert(key, false);
+        return false;
+    }
+
+    match device.renderer_mut().import_dmabuf(dmabuf, damage) {
+        Ok(imported) => {
+            cache.insert(key, true);
+            texture.insert_texture::<S>(node, imported);
+            true
+        }
+        Err(err) => {
+            debug!(
+                ?err,
+                ?src_node,
+                ?node,
+                ?format,
+                "Direct import failed, falling back to copies"
+            );
+            cache.insert(key, false);
+            false
+        }
+    }
+}
+
 fn dma_shadow_copy<S, T>(
     src_texture: &<<S::Device as ApiDevice>::Renderer as Renderer>::TextureId,
     damage: Option<&[Rectangle<i32, BufferCoords>]>,
@@ -2413,6 +2667,10 @@ where
         mut texture: MultiTexture,
         damage: Option<&[Rectangle<i32, BufferCoords>]>,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        if import_direct::<R>(dmabuf, damage, &mut texture, self.render, self.import_cache) {
+            return Ok(texture);
+        }
+
         let src_node = import_on_src_node::<R, T>(
             dmabuf,
             damage,
@@ -2741,3 +2999,52 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{select_render_node, OffloadPolicy};
+
+    #[test]
+    fn render_node_follows_override_and_policy() {
+        let (primary, dgpu, gone) = (1, 2, 3);
+        let can_render = |node: &u32| *node != gone;
+
+        assert_eq!(
+            select_render_node(None, OffloadPolicy::Primary, Some(primary), dgpu, can_render),
+            Some(primary)
+        );
+        assert_eq!(
+            select_render_node(None, OffloadPolicy::Target, Some(primary), dgpu, can_render),
+            Some(dgpu)
+        );
+        assert_eq!(
+            select_render_node(
+                Some(dgpu),
+                OffloadPolicy::Primary,
+                Some(primary),
+                dgpu,
+                can_render
+            ),
+            Some(dgpu)
+        );
+        // unavailable gpus fall back to the policy
+        assert_eq!(
+            select_render_node(
+                Some(gone),
+                OffloadPolicy::Primary,
+                Some(primary),
+                dgpu,
+                can_render
+            ),
+            Some(primary)
+        );
+        assert_eq!(
+            select_render_node(None, OffloadPolicy::Target, Some(primary), gone, can_render),
+            Some(primary)
+        );
+        assert_eq!(
+            select_render_node(None, OffloadPolicy::Primary, None, gone, can_render),
+            None
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Helper for rendering into offscreen textures
+//!
+//! An [`OffscreenBuffer`] allocates a texture of arbitrary size through [`Offscreen::create_buffer`],
+//! keeps track of the damage of everything rendered into it and can be used as a [`TextureRenderElement`]
+//! afterwards, e.g. to render a window into a texture once and composite the result multiple times.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     element::{solid::SolidColorRenderElement, Kind},
+//! #     gles::GlesRenderer,
+//! #     offscreen::OffscreenBuffer,
+//! #     Color32F,
+//! # };
+//! # use smithay::backend::allocator::Fourcc;
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! let mut buffer = OffscreenBuffer::new(&mut renderer, Fourcc::Abgr8888, (512, 512), 1)
+//!     .expect("Failed to allocate offscreen buffer");
+//!
+//! buffer
+//!     .render(&mut renderer, &elements, Color32F::TRANSPARENT)
+//!     .expect("Failed to render offscreen");
+//!
+//! // the content of the buffer can now be rendered like any other element
+//! let element = buffer.render_element((0.0, 0.0), 1.0, Kind::Unspecified);
+//! ```
+
+use crate::{
+    backend::allocator::{dmabuf::Dmabuf, Fourcc},
+    utils::{Buffer, Physical, Point, Rectangle, Size, Transform},
+};
+
+use super::{
+    damage::{Error as DamageError, OutputDamageTracker, RenderOutputResult},
+    element::{
+        texture::{TextureRenderBuffer, TextureRenderElement},
+        Kind, RenderElement,
+    },
+    Bind, Color32F, ExportDma, Offscreen, Renderer, Texture,
+};
+
+/// A texture that can be rendered into and used as a render element afterwards
+#[derive(Debug)]
+pub struct OffscreenBuffer<T: Texture> {
+    buffer: TextureRenderBuffer<T>,
+    texture: T,
+    damage_tracker: OutputDamageTracker,
+    format: Fourcc,
+    scale: i32,
+}
+
+impl<T: Texture + Clone> OffscreenBuffer<T> {
+    /// Allocate a new offscreen buffer
+    ///
+    /// `size` is the size of the texture in physical coordinates, `scale` the scale the content
+    /// is rendered with and later presented at.
+    pub fn new<R>(
+        renderer: &mut R,
+        format: Fourcc,
+        size: impl Into<Size<i32, Physical>>,
+        scale: i32,
+    ) -> Result<Self, R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let size = size.into();
+        let texture = renderer.create_buffer(format, buffer_size(size))?;
+        let buffer =
+            TextureRenderBuffer::from_texture(renderer, texture.clone(), scale, Transform::Normal, None);
+
+        Ok(OffscreenBuffer {
+            buffer,
+            texture,
+            damage_tracker: OutputDamageTracker::new(size, scale as f64, Transform::Normal),
+            format,
+            scale,
+        })
+    }
+
+    /// Size of the buffer in physical coordinates
+    pub fn size(&self) -> Size<i32, Physical> {
+        let size = self.texture.size();
+        (size.w, size.h).into()
+    }
+
+    /// Scale of the buffer
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// Returns the underlying texture
+    pub fn texture(&self) -> &T {
+        &self.texture
+    }
+
+    /// Returns the [`TextureRenderBuffer`] tracking the content of this buffer
+    pub fn render_buffer(&self) -> &TextureRenderBuffer<T> {
+        &self.buffer
+    }
+
+    /// Reallocate the buffer with a new size
+    ///
+    /// The content of the buffer is lost and fully redrawn on the next [`OffscreenBuffer::render`].
+    pub fn resize<R>(
+        &mut self,
+        renderer: &mut R,
+        size: impl Into<Size<i32, Physical>>,
+    ) -> Result<(), R::Error>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let size = size.into();
+        if size == self.size() {
+            return Ok(());
+        }
+
+        let texture = renderer.create_buffer(self.format, buffer_size(size))?;
+        self.buffer
+            .update_from_texture(renderer, texture.clone(), self.scale, Transform::Normal, None);
+        self.texture = texture;
+        self.damage_tracker = OutputDamageTracker::new(size, self.scale as f64, Transform::Normal);
+        Ok(())
+    }
+
+    /// Render the given elements into the buffer
+    ///
+    /// Only the damaged parts of the buffer are redrawn. The damage is forwarded to render elements
+    /// created from this buffer.
+    pub fn render<R, E>(
+        &mut self,
+        renderer: &mut R,
+        elements: &[E],
+        clear_color: impl Into<Color32F>,
+    ) -> Result<RenderOutputResult<'_>, DamageError<R>>
+    where
+        R: Renderer<TextureId = T> + Bind<T>,
+        E: RenderElement<R>,
+    {
+        let result = self.damage_tracker.render_output_with(
+            renderer,
+            self.texture.clone(),
+            1,
+            elements,
+            clear_color.into(),
+        );
+        renderer.unbind().map_err(DamageError::Rendering)?;
+        let result = result?;
+
+        if let Some(damage) = result.damage {
+            let damage = damage
+                .iter()
+                .map(|rect| {
+                    Rectangle::<i32, Buffer>::from_loc_and_size(
+                        (rect.loc.x, rect.loc.y),
+                        (rect.size.w, rect.size.h),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let _ = self.buffer.render().draw(|_| Ok::<_, ()>(damage));
+        }
+
+        Ok(result)
+    }
+
+    /// Create a render element displaying the content of the buffer
+    pub fn render_element(
+        &self,
+        location: impl Into<Point<f64, Physical>>,
+        alpha: f32,
+        kind: Kind,
+    ) -> TextureRenderElement<T> {
+        TextureRenderElement::from_texture_render_buffer(
+            location,
+            &self.buffer,
+            Some(alpha),
+            None,
+            None,
+            kind,
+        )
+    }
+
+    /// Export the buffer as a dmabuf, if supported by the renderer
+    pub fn export_dmabuf<R>(&self, renderer: &mut R) -> Result<Dmabuf, R::Error>
+    where
+        R: ExportDma<TextureId = T>,
+    {
+        renderer.export_texture(&self.texture)
+    }
+}
+
+fn buffer_size(size: Size<i32, Physical>) -> Size<i32, Buffer> {
+    (size.w, size.h).into()
+}
+
+#[cfg(all(test, feature = "renderer_test"))]
+mod tests {
+    use super::OffscreenBuffer;
+    use crate::{
+        backend::{
+            allocator::Fourcc,
+            renderer::{
+                element::{solid::SolidColorRenderElement, Element, Id, Kind},
+                test::DummyRenderer,
+                utils::CommitCounter,
+                Color32F,
+            },
+        },
+        utils::{Physical, Point, Rectangle, Scale},
+    };
+
+    #[test]
+    fn render_damages_element() {
+        let mut renderer = DummyRenderer::new();
+        let mut buffer = OffscreenBuffer::new(&mut renderer, Fourcc::Abgr8888, (64, 64), 1).unwrap();
+        let element = buffer.render_element((0.0, 0.0), 1.0, Kind::Unspecified);
+        let commit = element.current_commit();
+
+        let solid = SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::<i32, Physical>::from_loc_and_size((8, 8), (16, 16)),
+            CommitCounter::default(),
+            [1.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        );
+        buffer
+            .render(&mut renderer, &[solid], Color32F::TRANSPARENT)
+            .unwrap();
+
+        let element = buffer.render_element((0.0, 0.0), 1.0, Kind::Unspecified);
+        assert_ne!(element.current_commit(), commit);
+        let damage = element.damage_since(Scale::from(1.0), Some(commit));
+        assert!(damage.iter().any(|rect| rect.contains(Point::from((10, 10)))));
+
+        buffer.resize(&mut renderer, (128, 32)).unwrap();
+        assert_eq!(buffer.size(), (128, 32).into());
+    }
+}
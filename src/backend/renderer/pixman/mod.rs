@@ -13,8 +13,8 @@ use tracing::warn;
 use crate::{
     backend::allocator::{
         dmabuf::{Dmabuf, DmabufMapping, DmabufMappingMode, DmabufSyncFailed, DmabufSyncFlags, WeakDmabuf},
-        format::{has_alpha, FormatSet},
-        Buffer,
+        format::{get_bpp, has_alpha, FormatSet},
+        Buffer, Slot,
     },
     utils::{Buffer as BufferCoords, Physical, Rectangle, Scale, Size, Transform},
 };
@@ -34,8 +34,12 @@ use wayland_server::{protocol::wl_buffer, Resource, Weak};
 ))]
 use super::ImportEgl;
 use super::{
-    sync::SyncPoint, Bind, Color32F, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer,
-    Texture, TextureFilter, TextureMapping, Unbind,
+    color_transform::{ColorTransform, Lut3d, DEFAULT_LUT_SIZE},
+    element::{border::BorderRenderElement, RenderElement, UnderlyingStorage},
+    sync::SyncPoint,
+    utils::convert,
+    Bind, Color32F, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer,
+    RendererCapabilities, RendererId, Texture, TextureFilter, TextureMapping, Unbind,
 };
 
 mod error;
@@ -255,6 +259,7 @@ pub struct PixmanFrame<'frame> {
     transform: Transform,
     output_size: Size<i32, Physical>,
     size: Size<i32, Physical>,
+    damage: Vec<pixman::Box32>,
 
     finished: AtomicBool,
 }
@@ -304,6 +309,7 @@ impl<'frame> PixmanFrame<'frame> {
         clip_region = clip_region.intersect(&damage_region);
 
         target_image.set_clip_region32(Some(&clip_region))?;
+        self.damage.extend_from_slice(clip_region.rectangles());
 
         target_image.composite32(
             op,
@@ -339,7 +345,7 @@ impl<'frame> Frame for PixmanFrame<'frame> {
     type TextureId = PixmanTexture;
 
     fn id(&self) -> usize {
-        0
+        self.renderer.id.get()
     }
 
     #[profiling::function]
@@ -557,6 +563,7 @@ impl<'frame> Frame for PixmanFrame<'frame> {
         clip_region = clip_region.intersect(&damage_region);
 
         target_image.set_clip_region32(Some(&clip_region))?;
+        self.damage.extend_from_slice(clip_region.rectangles());
 
         src_image_accessor.with_image(|src_image| {
             if let Some(transform) = transform {
@@ -643,6 +650,18 @@ impl<'frame> PixmanFrame<'frame> {
             return Ok(SyncPoint::signaled());
         }
 
+        if let Some(lut) = self.renderer.color_transform.as_ref() {
+            let binding;
+            let target_image = match self.renderer.target.as_ref().ok_or(PixmanError::NoTargetBound)? {
+                PixmanTarget::Image { image, .. } => {
+                    binding = image.0.image.borrow();
+                    &*binding
+                }
+                PixmanTarget::RenderBuffer(b) => &b.0,
+            };
+            apply_color_transform(target_image, lut, &std::mem::take(&mut self.damage))?;
+        }
+
         if let PixmanTarget::Image { dmabuf, .. } =
             self.renderer.target.as_ref().ok_or(PixmanError::NoTargetBound)?
         {
@@ -671,14 +690,49 @@ impl<'frame> Drop for PixmanFrame<'frame> {
     }
 }
 
+fn apply_color_transform(
+    image: &Image<'_, '_>,
+    lut: &Lut3d,
+    damage: &[pixman::Box32],
+) -> Result<(), PixmanError> {
+    let format = DrmFourcc::try_from(image.format()).map_err(|_| PixmanError::Unsupported)?;
+    let stride = image.stride();
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    // SAFETY: the data is valid for the lifetime of the image and nothing else accesses it while we hold the
+    // image. The region is clipped to the size of the image.
+    let data = unsafe { std::slice::from_raw_parts_mut(image.data() as *mut u8, stride * image.height()) };
+    let bpp = get_bpp(format).ok_or(PixmanError::UnsupportedPixelFormat(format))? / 8;
+
+    let region = pixman::Region32::init_rects(damage).intersect(&pixman::Region32::init_rect(
+        0,
+        0,
+        width as u32,
+        height as u32,
+    ));
+    for rect in region.rectangles() {
+        let offset = rect.y1 as usize * stride + rect.x1 as usize * bpp;
+        convert::apply_lut3d(
+            &mut data[offset..],
+            format,
+            stride,
+            (rect.x2 - rect.x1, rect.y2 - rect.y1),
+            lut,
+        )
+        .map_err(|_| PixmanError::UnsupportedPixelFormat(format))?;
+    }
+    Ok(())
+}
+
 /// A renderer utilizing pixman
 #[derive(Debug)]
 pub struct PixmanRenderer {
+    id: RendererId,
     target: Option<PixmanTarget>,
     downscale_filter: TextureFilter,
     upscale_filter: TextureFilter,
     debug_flags: DebugFlags,
     tint: pixman::Solid<'static>,
+    color_transform: Option<Lut3d>,
 
     // caches
     buffers: Vec<PixmanImage>,
@@ -690,11 +744,13 @@ impl PixmanRenderer {
     pub fn new() -> Result<Self, PixmanError> {
         let tint = pixman::Solid::new([0.0, 0.2, 0.0, 0.2]).map_err(|_| PixmanError::Unsupported)?;
         Ok(Self {
+            id: RendererId::new(),
             target: None,
             downscale_filter: TextureFilter::Linear,
             upscale_filter: TextureFilter::Linear,
             debug_flags: DebugFlags::empty(),
             tint,
+            color_transform: None,
 
             buffers: Default::default(),
             dmabuf_cache: Default::default(),
@@ -703,6 +759,17 @@ impl PixmanRenderer {
 }
 
 impl PixmanRenderer {
+    /// Set a [`ColorTransform`] applied to everything rendered afterwards
+    ///
+    /// The transform is applied to the damaged parts of the target, when a frame is finished.
+    /// Content already present in the target is not modified, so a full redraw is necessary after
+    /// changing the transform to affect the whole output.
+    pub fn set_color_transform(&mut self, transform: Option<&ColorTransform>) {
+        self.color_transform = transform
+            .filter(|transform| !transform.is_identity())
+            .map(|transform| transform.bake(DEFAULT_LUT_SIZE));
+    }
+
     fn existing_dmabuf(&self, dmabuf: &Dmabuf) -> Option<PixmanImage> {
         self.dmabuf_cache
             .iter()
@@ -798,7 +865,7 @@ impl Renderer for PixmanRenderer {
     type Frame<'frame> = PixmanFrame<'frame>;
 
     fn id(&self) -> usize {
-        0
+        self.id.get()
     }
 
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
@@ -842,6 +909,8 @@ impl Renderer for PixmanRenderer {
             output_size,
             size: dst_transform.transform_size(output_size),
 
+            damage: Vec::new(),
+
             finished: AtomicBool::new(false),
         })
     }
@@ -853,6 +922,16 @@ impl Renderer for PixmanRenderer {
         self.cleanup();
         Ok(())
     }
+
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        RendererCapabilities {
+            mem_formats: self.mem_formats().collect(),
+            dmabuf_texture_formats: self.dmabuf_formats(),
+            dmabuf_render_formats: Bind::<Dmabuf>::supported_formats(self).unwrap_or_default(),
+            color_transform: true,
+            ..Default::default()
+        }
+    }
 }
 
 impl ImportMem for PixmanRenderer {
@@ -1064,19 +1143,6 @@ impl ExportMem for PixmanRenderer {
     feature = "use_system_lib"
 ))]
 impl ImportEgl for PixmanRenderer {
-    fn bind_wl_display(
-        &mut self,
-        _display: &wayland_server::DisplayHandle,
-    ) -> Result<(), crate::backend::egl::Error> {
-        Err(crate::backend::egl::Error::NoEGLDisplayBound)
-    }
-
-    fn unbind_wl_display(&mut self) {}
-
-    fn egl_reader(&self) -> Option<&crate::backend::egl::display::EGLBufferReader> {
-        None
-    }
-
     fn import_egl_buffer(
         &mut self,
         _buffer: &wl_buffer::WlBuffer,
@@ -1223,6 +1289,17 @@ impl Bind<Dmabuf> for PixmanRenderer {
     }
 }
 
+impl<'a> Bind<&'a Slot<Dmabuf>> for PixmanRenderer {
+    #[profiling::function]
+    fn bind(&mut self, slot: &'a Slot<Dmabuf>) -> Result<(), <Self as Renderer>::Error> {
+        self.bind((*slot).clone())
+    }
+
+    fn supported_formats(&self) -> Option<FormatSet> {
+        <Self as Bind<Dmabuf>>::supported_formats(self)
+    }
+}
+
 impl Offscreen<PixmanRenderBuffer> for PixmanRenderer {
     #[profiling::function]
     fn create_buffer(
@@ -1259,3 +1336,114 @@ impl Bind<PixmanRenderBuffer> for PixmanRenderer {
         Some(RENDER_BUFFER_FORMATS.clone())
     }
 }
+
+impl RenderElement<PixmanRenderer> for BorderRenderElement {
+    #[profiling::function]
+    fn draw(
+        &self,
+        frame: &mut PixmanFrame<'_>,
+        _src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), PixmanError> {
+        let shape = self.shape(dst.size);
+        // only the damaged parts of the border are rasterized
+        let bounds = shape.bounds();
+        let damage = damage
+            .iter()
+            .flat_map(|damage| bounds.iter().filter_map(|rect| rect.intersection(*damage)))
+            .collect::<Vec<_>>();
+        let Some(area) = damage.iter().copied().reduce(|a, b| a.merge(b)) else {
+            return Ok(());
+        };
+
+        let stride = area.size.w as usize * 4;
+        let mut data = vec![0u8; stride * area.size.h as usize];
+        for rect in &damage {
+            for y in rect.loc.y..rect.loc.y + rect.size.h {
+                for x in rect.loc.x..rect.loc.x + rect.size.w {
+                    let [r, g, b, a] = shape
+                        .color_at(x as f32 + 0.5, y as f32 + 0.5)
+                        .components()
+                        .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u32);
+                    let pixel = (a << 24) | (r << 16) | (g << 8) | b;
+                    let offset = (y - area.loc.y) as usize * stride + (x - area.loc.x) as usize * 4;
+                    data[offset..offset + 4].copy_from_slice(&pixel.to_le_bytes());
+                }
+            }
+        }
+
+        let size = Size::<i32, BufferCoords>::from((area.size.w, area.size.h));
+        let texture = frame
+            .renderer
+            .import_memory(&data, DrmFourcc::Argb8888, size, false)?;
+        let damage = damage
+            .iter()
+            .map(|rect| Rectangle::from_loc_and_size(rect.loc - area.loc, rect.size))
+            .collect::<Vec<_>>();
+        frame.render_texture_from_to(
+            &texture,
+            Rectangle::from_loc_and_size((0, 0), size).to_f64(),
+            Rectangle::from_loc_and_size(dst.loc + area.loc, area.size),
+            &damage,
+            &[],
+            Transform::Normal,
+            1.0,
+        )
+    }
+
+    #[inline]
+    fn underlying_storage(&self, _renderer: &mut PixmanRenderer) -> Option<UnderlyingStorage<'_>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::renderer::color_transform::ColorFilter;
+
+    fn pixel(renderer: &mut PixmanRenderer, x: i32) -> [u8; 4] {
+        let mapping = renderer
+            .copy_framebuffer(Rectangle::from_loc_and_size((x, 0), (1, 1)), DrmFourcc::Argb8888)
+            .unwrap();
+        renderer.map_texture(&mapping).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn color_transform_applies_to_damage_once() {
+        let mut renderer = PixmanRenderer::new().unwrap();
+        let buffer: PixmanRenderBuffer = renderer
+            .create_buffer(DrmFourcc::Argb8888, (2, 1).into())
+            .unwrap();
+        renderer.bind(buffer).unwrap();
+        renderer.set_color_transform(Some(
+            &ColorTransform::identity().with_filters(&[ColorFilter::Invert]),
+        ));
+
+        let mut frame = renderer.render((2, 1).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                Color32F::new(1.0, 0.0, 0.0, 1.0),
+                &[Rectangle::from_loc_and_size((0, 0), (2, 1))],
+            )
+            .unwrap();
+        frame.finish().unwrap().wait().unwrap();
+        // little endian argb: [b, g, r, a]
+        assert_eq!(pixel(&mut renderer, 0), [255, 255, 0, 255]);
+        assert_eq!(pixel(&mut renderer, 1), [255, 255, 0, 255]);
+
+        // undamaged content is not filtered again
+        let mut frame = renderer.render((2, 1).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                Color32F::new(0.0, 0.0, 1.0, 1.0),
+                &[Rectangle::from_loc_and_size((1, 0), (1, 1))],
+            )
+            .unwrap();
+        frame.finish().unwrap().wait().unwrap();
+        assert_eq!(pixel(&mut renderer, 0), [255, 255, 0, 255]);
+        assert_eq!(pixel(&mut renderer, 1), [0, 255, 255, 255]);
+    }
+}
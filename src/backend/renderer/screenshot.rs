@@ -0,0 +1,325 @@
+//! Helper for taking screenshots of outputs
+//!
+//! [`render_screenshot`] renders the elements of an output, or a region of it, into an offscreen texture
+//! and copies the result into cpu memory. This works with any renderer supporting offscreen rendering and
+//! [`ExportMem`] and does not require any of the capture protocols, e.g. for a screenshot keybinding.
+//!
+//! The elements are rendered with the transform of the output, just like they are presented, and the
+//! resulting [`Screenshot`] is rotated back to be upright with rows ordered top to bottom, regardless of
+//! the renderer in use. With the `screenshot_png` feature
+//! it can be encoded as a png image directly.
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{
+//! #     element::solid::SolidColorRenderElement,
+//! #     gles::{GlesRenderer, GlesTexture},
+//! #     screenshot::render_screenshot,
+//! #     Color32F,
+//! # };
+//! # use smithay::utils::Transform;
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! // capture the whole output with a mode of 1920x1080 and a scale of 1.5
+//! let screenshot = render_screenshot::<_, GlesTexture, _>(
+//!     &mut renderer,
+//!     (1920, 1080),
+//!     1.5,
+//!     Transform::Normal,
+//!     None,
+//!     &elements,
+//!     Color32F::BLACK,
+//! )
+//! .expect("Failed to take screenshot");
+//! // rgba pixels of the output
+//! let pixels = screenshot.data();
+//! ```
+
+use crate::{
+    backend::allocator::Fourcc,
+    output::{Output, OutputNoMode},
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::{
+    damage::{Error as DamageError, OutputDamageTracker},
+    element::{
+        utils::{Relocate, RelocateRenderElement},
+        RenderElement,
+    },
+    Bind, Color32F, ExportMem, Offscreen, Renderer, Texture, TextureMapping,
+};
+
+/// Format of the pixels of a [`Screenshot`], the bytes of every pixel are ordered red, green, blue, alpha
+pub const SCREENSHOT_FORMAT: Fourcc = Fourcc::Abgr8888;
+
+/// Errors returned by [`render_screenshot`]
+#[derive(thiserror::Error)]
+pub enum ScreenshotError<R: Renderer> {
+    /// The requested region does not overlap the output
+    #[error("The requested region does not overlap the output")]
+    EmptyRegion,
+    /// The output has no mode set
+    #[error(transparent)]
+    NoMode(#[from] OutputNoMode),
+    /// The renderer returned an error
+    #[error(transparent)]
+    Rendering(R::Error),
+    /// The output could not be rendered
+    #[error(transparent)]
+    Damage(#[from] DamageError<R>),
+    /// Waiting for the rendering to finish was interrupted
+    #[error("Waiting for the rendering to finish was interrupted")]
+    SyncInterrupted,
+}
+
+impl<R: Renderer> std::fmt::Debug for ScreenshotError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScreenshotError::EmptyRegion => f.write_str("EmptyRegion"),
+            ScreenshotError::NoMode(err) => f.debug_tuple("NoMode").field(err).finish(),
+            ScreenshotError::Rendering(err) => f.debug_tuple("Rendering").field(err).finish(),
+            ScreenshotError::Damage(err) => f.debug_tuple("Damage").field(err).finish(),
+            ScreenshotError::SyncInterrupted => f.write_str("SyncInterrupted"),
+        }
+    }
+}
+
+/// Pixels of an output copied into cpu memory
+///
+/// The pixels use the [`SCREENSHOT_FORMAT`], are tightly packed and have premultiplied alpha.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    size: Size<i32, Buffer>,
+    data: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Size of the screenshot in pixels
+    pub fn size(&self) -> Size<i32, Buffer> {
+        self.size
+    }
+
+    /// Stride of the rows of the screenshot in bytes
+    pub fn stride(&self) -> usize {
+        self.size.w as usize * 4
+    }
+
+    /// Pixels of the screenshot
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the pixels of the screenshot
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Encode the screenshot as a png image
+    #[cfg(feature = "screenshot_png")]
+    pub fn encode_png<W: std::io::Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        // png stores straight alpha
+        let mut data = self.data.clone();
+        super::utils::convert::unpremultiply_alpha(&mut data, SCREENSHOT_FORMAT, self.stride(), self.size)
+            .expect("Screenshot format is supported");
+
+        let mut encoder = png::Encoder::new(writer, self.size.w as u32, self.size.h as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()
+    }
+}
+
+/// Render the elements of an output into a [`Screenshot`]
+///
+/// - `size`, `scale` and `transform` describe the current mode of the output
+/// - `region` limits the screenshot to a part of the output, given in the same coordinate space
+///   as the elements, the whole output is captured if it is `None`
+/// - `elements` are positioned in the physical coordinate space of the output in front-to-back order
+#[profiling::function]
+pub fn render_screenshot<R, T, E>(
+    renderer: &mut R,
+    size: impl Into<Size<i32, Physical>>,
+    scale: impl Into<Scale<f64>>,
+    transform: Transform,
+    region: Option<Rectangle<i32, Physical>>,
+    elements: &[E],
+    clear_color: impl Into<Color32F>,
+) -> Result<Screenshot, ScreenshotError<R>>
+where
+    R: Renderer + Offscreen<T> + Bind<T> + ExportMem,
+    <R as Renderer>::TextureId: Texture,
+    E: RenderElement<R>,
+{
+    let output = Rectangle::from_loc_and_size((0, 0), transform.transform_size(size.into()));
+    let region = screenshot_region(output, region).ok_or(ScreenshotError::EmptyRegion)?;
+    let upright_size = Size::<i32, Buffer>::from((region.size.w, region.size.h));
+    // the region is rendered like it is presented on the output and rotated back afterwards
+    let buffer_size = transform.transform_size(upright_size);
+
+    let offset = Point::from((-region.loc.x, -region.loc.y));
+    let elements = elements
+        .iter()
+        .map(|element| RelocateRenderElement::from_element(element, offset, Relocate::Relative))
+        .collect::<Vec<_>>();
+
+    let texture = renderer
+        .create_buffer(SCREENSHOT_FORMAT, buffer_size)
+        .map_err(ScreenshotError::Rendering)?;
+    renderer.bind(texture).map_err(ScreenshotError::Rendering)?;
+    let mut damage_tracker =
+        OutputDamageTracker::new(transform.transform_size(region.size), scale, transform);
+    let result = damage_tracker.render_output(renderer, 0, &elements, clear_color)?;
+    result.sync.wait().map_err(|_| ScreenshotError::SyncInterrupted)?;
+
+    let mapping = renderer
+        .copy_framebuffer(
+            Rectangle::from_loc_and_size((0, 0), buffer_size),
+            SCREENSHOT_FORMAT,
+        )
+        .map_err(ScreenshotError::Rendering)?;
+    let flipped = mapping.flipped();
+    let pixels = renderer
+        .map_texture(&mapping)
+        .map_err(ScreenshotError::Rendering)?;
+    let pixels = unflip_rows(pixels, buffer_size.w as usize * 4, flipped);
+
+    Ok(Screenshot {
+        size: upright_size,
+        data: upright_pixels(&pixels, buffer_size, transform),
+    })
+}
+
+/// Render the elements of an output into a [`Screenshot`] using the current state of the output
+///
+/// See [`render_screenshot`] for details.
+pub fn render_output_screenshot<R, T, E>(
+    renderer: &mut R,
+    output: &Output,
+    region: Option<Rectangle<i32, Physical>>,
+    elements: &[E],
+    clear_color: impl Into<Color32F>,
+) -> Result<Screenshot, ScreenshotError<R>>
+where
+    R: Renderer + Offscreen<T> + Bind<T> + ExportMem,
+    <R as Renderer>::TextureId: Texture,
+    E: RenderElement<R>,
+{
+    let mode = output.current_mode().ok_or(OutputNoMode)?;
+    render_screenshot(
+        renderer,
+        mode.size,
+        output.current_scale().fractional_scale(),
+        output.current_transform(),
+        region,
+        elements,
+        clear_color,
+    )
+}
+
+fn screenshot_region(
+    output: Rectangle<i32, Physical>,
+    region: Option<Rectangle<i32, Physical>>,
+) -> Option<Rectangle<i32, Physical>> {
+    region
+        .map_or(Some(output), |region| region.intersection(output))
+        .filter(|region| !region.is_empty())
+}
+
+// Undoes the output transform applied to the rendered pixels of `size`
+fn upright_pixels(pixels: &[u8], size: Size<i32, Buffer>, transform: Transform) -> Vec<u8> {
+    if transform == Transform::Normal {
+        return pixels.to_vec();
+    }
+    let upright = transform.transform_size(size);
+    let render_transform = transform.invert();
+    let mut data = Vec::with_capacity(pixels.len());
+    for y in 0..upright.h {
+        for x in 0..upright.w {
+            let src = render_transform
+                .transform_rect_in(Rectangle::from_loc_and_size((x, y), (1, 1)), &upright)
+                .loc;
+            let offset = (src.y * size.w + src.x) as usize * 4;
+            data.extend_from_slice(&pixels[offset..offset + 4]);
+        }
+    }
+    data
+}
+
+// Orders the rows of the mapped pixels top to bottom
+fn unflip_rows(pixels: &[u8], stride: usize, flipped: bool) -> Vec<u8> {
+    if !flipped || stride == 0 {
+        return pixels.to_vec();
+    }
+    pixels.chunks_exact(stride).rev().flatten().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{screenshot_region, unflip_rows};
+    use crate::utils::Rectangle;
+
+    #[test]
+    fn screenshot_region_is_clipped_to_output() {
+        let output = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        assert_eq!(screenshot_region(output, None), Some(output));
+        assert_eq!(
+            screenshot_region(
+                output,
+                Some(Rectangle::from_loc_and_size((1800, 1000), (400, 400)))
+            ),
+            Some(Rectangle::from_loc_and_size((1800, 1000), (120, 80)))
+        );
+        assert_eq!(
+            screenshot_region(output, Some(Rectangle::from_loc_and_size((1920, 0), (10, 10)))),
+            None
+        );
+
+        let pixels = [1, 2, 3, 4, 5, 6];
+        assert_eq!(unflip_rows(&pixels, 2, true), vec![5, 6, 3, 4, 1, 2]);
+        assert_eq!(unflip_rows(&pixels, 2, false), pixels.to_vec());
+    }
+
+    #[cfg(feature = "renderer_pixman")]
+    #[test]
+    fn screenshots_are_upright() {
+        use super::render_screenshot;
+        use crate::{
+            backend::renderer::{
+                element::{solid::SolidColorRenderElement, Id, Kind},
+                pixman::{PixmanRenderBuffer, PixmanRenderer},
+                utils::CommitCounter,
+            },
+            utils::Transform,
+        };
+
+        let mut renderer = PixmanRenderer::new().unwrap();
+        // a red bar in the top left corner of the upright output
+        let elements = [SolidColorRenderElement::new(
+            Id::new(),
+            Rectangle::from_loc_and_size((0, 0), (2, 1)),
+            CommitCounter::default(),
+            [1.0, 0.0, 0.0, 1.0],
+            Kind::Unspecified,
+        )];
+        for transform in [Transform::Normal, Transform::_90, Transform::Flipped270] {
+            let size = transform.transform_size((4, 3).into());
+            let screenshot = render_screenshot::<_, PixmanRenderBuffer, _>(
+                &mut renderer,
+                size,
+                1.0,
+                transform,
+                None,
+                &elements,
+                [0.0, 0.0, 0.0, 1.0],
+            )
+            .unwrap();
+            assert_eq!(screenshot.size(), (4, 3).into());
+
+            let red = |x: usize, y: usize| screenshot.data()[(y * 4 + x) * 4] == 0xff;
+            assert!(red(0, 0) && red(1, 0), "{:?}", transform);
+            assert!(!red(2, 0) && !red(0, 1) && !red(3, 2), "{:?}", transform);
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! Helper for streaming the content of an output as dmabufs, e.g. to video encoders
+//!
+//! Screen recorders usually feed the composited output into hardware encoders (for example via
+//! VA-API or PipeWire), which are able to consume dmabufs directly. [`OutputStream`] renders a
+//! region of an output into a small pool of dmabufs and hands them out as [`StreamFrame`]s together
+//! with a [`SyncPoint`] signaling the end of rendering and the damage since the previous frame,
+//! without ever copying the content through cpu memory.
+//!
+//! The buffer of a frame is returned to the pool once the [`StreamFrame`] is dropped. If the
+//! consumer still holds on to all buffers of the pool, or if frames are requested faster than the
+//! configured frame interval, the frame is skipped and its damage is carried over to the next frame.
+//!
+//! ```no_run
+//! # use smithay::backend::{
+//! #     allocator::{dmabuf::AsDmabuf, Allocator, Fourcc, Modifier},
+//! #     renderer::{
+//! #         element::solid::SolidColorRenderElement,
+//! #         gles::GlesRenderer,
+//! #         stream::{OutputStream, StreamResult},
+//! #         Color32F,
+//! #     },
+//! # };
+//! # use smithay::utils::{Clock, Monotonic, Rectangle, Transform};
+//! # use std::time::Duration;
+//! # fn record<A: Allocator>(allocator: A) where A::Buffer: AsDmabuf {
+//! # let mut renderer: GlesRenderer = todo!();
+//! # let elements: Vec<SolidColorRenderElement> = Vec::new();
+//! # let clock = Clock::<Monotonic>::new();
+//! let mut stream = OutputStream::new(
+//!     allocator,
+//!     Fourcc::Xrgb8888,
+//!     vec![Modifier::Linear],
+//!     Rectangle::from_loc_and_size((0, 0), (1920, 1080)),
+//!     1.0,
+//!     Transform::Normal,
+//! );
+//! // stream with at most 30 frames per second
+//! stream.set_frame_interval(Some(Duration::from_secs(1) / 30));
+//!
+//! // call for every frame of the output
+//! match stream
+//!     .render_frame(&mut renderer, &elements, Color32F::BLACK, clock.now())
+//!     .expect("Failed to render frame")
+//! {
+//!     StreamResult::Frame(frame) => {
+//!         // hand `frame.dmabuf()` and `frame.sync` to the encoder and drop
+//!         // the frame once the encoder is done with it
+//!     }
+//!     StreamResult::Skipped(_) => {}
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    backend::allocator::{
+        dmabuf::{AsDmabuf, Dmabuf},
+        Allocator, Fourcc, Modifier, Slot, Swapchain,
+    },
+    utils::{Buffer, Monotonic, Physical, Point, Rectangle, Scale, Size, Time, Transform},
+};
+
+use super::{
+    damage::{Error as DamageError, OutputDamageTracker},
+    element::{
+        utils::{Relocate, RelocateRenderElement},
+        RenderElement,
+    },
+    sync::SyncPoint,
+    Bind, Color32F, Renderer,
+};
+
+/// Errors returned by [`OutputStream::render_frame`]
+#[derive(thiserror::Error)]
+pub enum StreamError<A: std::error::Error, B: std::error::Error, R: Renderer> {
+    /// Allocating a buffer for the pool failed
+    #[error("The underlying allocator encountered an error: {0}")]
+    Allocator(#[source] A),
+    /// Exporting an allocated buffer as dmabuf failed
+    #[error("Failed to export the allocated buffer as dmabuf: {0}")]
+    AsDmabuf(#[source] B),
+    /// Rendering the frame failed
+    #[error(transparent)]
+    Rendering(DamageError<R>),
+}
+
+impl<A: std::error::Error, B: std::error::Error, R: Renderer> std::fmt::Debug for StreamError<A, B, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Allocator(err) => f.debug_tuple("Allocator").field(err).finish(),
+            StreamError::AsDmabuf(err) => f.debug_tuple("AsDmabuf").field(err).finish(),
+            StreamError::Rendering(err) => f.debug_tuple("Rendering").field(err).finish(),
+        }
+    }
+}
+
+impl<A: std::error::Error, B: std::error::Error, R: Renderer> From<DamageError<R>> for StreamError<A, B, R> {
+    #[inline]
+    fn from(err: DamageError<R>) -> Self {
+        StreamError::Rendering(err)
+    }
+}
+
+type StreamErrorType<A, R> =
+    StreamError<<A as Allocator>::Error, <<A as Allocator>::Buffer as AsDmabuf>::Error, R>;
+
+/// Reason for skipping a frame of an [`OutputStream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// The frame was requested before the frame interval elapsed
+    Throttled,
+    /// All buffers of the pool are still in use by the consumer
+    NoFreeBuffer,
+    /// Nothing changed since the previous frame
+    Unchanged,
+}
+
+/// Result of [`OutputStream::render_frame`]
+#[derive(Debug)]
+pub enum StreamResult<B: AsDmabuf + crate::backend::allocator::Buffer> {
+    /// A new frame was rendered
+    Frame(StreamFrame<B>),
+    /// No frame was rendered
+    Skipped(SkipReason),
+}
+
+/// Frame of an [`OutputStream`]
+///
+/// The buffer of the frame is returned to the pool of the stream once this is dropped,
+/// so it should be kept alive until the consumer is done reading from it.
+#[derive(Debug)]
+pub struct StreamFrame<B: AsDmabuf + crate::backend::allocator::Buffer> {
+    slot: Slot<B>,
+    dmabuf: Dmabuf,
+    /// Sequence number of the frame, increasing by one for every frame of the stream
+    pub sequence: u64,
+    /// Presentation time of the frame
+    pub time: Time<Monotonic>,
+    /// Damage since the previous frame of the stream, in buffer coordinates
+    ///
+    /// Contains the whole buffer for the first frame and after the stream was reset.
+    pub damage: Vec<Rectangle<i32, Buffer>>,
+    /// Sync point that is reached once the content of the buffer is ready
+    pub sync: SyncPoint,
+    /// Number of frames skipped since the previous frame of the stream
+    pub skipped: u64,
+}
+
+impl<B: AsDmabuf + crate::backend::allocator::Buffer> StreamFrame<B> {
+    /// The dmabuf containing the content of this frame
+    pub fn dmabuf(&self) -> &Dmabuf {
+        &self.dmabuf
+    }
+
+    /// The underlying buffer of the pool
+    pub fn buffer(&self) -> &B {
+        &self.slot
+    }
+}
+
+/// State for streaming a region of an output as a sequence of dmabufs
+///
+/// See the [module-level documentation](self) for details.
+pub struct OutputStream<A: Allocator> {
+    region: Rectangle<i32, Physical>,
+    scale: Scale<f64>,
+    transform: Transform,
+    damage_tracker: OutputDamageTracker,
+    swapchain: Swapchain<A>,
+    frame_interval: Option<Duration>,
+    last_frame: Option<Time<Monotonic>>,
+    sequence: u64,
+    skipped: u64,
+    total_skipped: u64,
+}
+
+impl<A: Allocator> std::fmt::Debug for OutputStream<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputStream")
+            .field("region", &self.region)
+            .field("scale", &self.scale)
+            .field("transform", &self.transform)
+            .field("damage_tracker", &self.damage_tracker)
+            .field("swapchain", &self.swapchain)
+            .field("frame_interval", &self.frame_interval)
+            .field("last_frame", &self.last_frame)
+            .field("sequence", &self.sequence)
+            .field("skipped", &self.skipped)
+            .field("total_skipped", &self.total_skipped)
+            .finish()
+    }
+}
+
+impl<A> OutputStream<A>
+where
+    A: Allocator,
+    A::Buffer: AsDmabuf,
+{
+    /// Create a new stream of `region`
+    ///
+    /// Buffers of the stream are allocated with `allocator` using `format` and one of `modifiers`,
+    /// which should be chosen from the formats supported by both the renderer and the consumer.
+    /// `region` is given in the physical coordinate space of the output, as used by the render
+    /// elements passed to [`OutputStream::render_frame`], `scale` and `transform` are the ones of
+    /// the streamed output.
+    pub fn new(
+        allocator: A,
+        format: Fourcc,
+        modifiers: Vec<Modifier>,
+        region: impl Into<Rectangle<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+    ) -> Self {
+        let region = region.into();
+        let scale = scale.into();
+        let size = transform.transform_size(region.size);
+        OutputStream {
+            region,
+            scale,
+            transform,
+            damage_tracker: OutputDamageTracker::new(size, scale, transform),
+            swapchain: Swapchain::new(allocator, size.w as u32, size.h as u32, format, modifiers),
+            frame_interval: None,
+            last_frame: None,
+            sequence: 0,
+            skipped: 0,
+            total_skipped: 0,
+        }
+    }
+
+    /// The streamed region
+    pub fn region(&self) -> Rectangle<i32, Physical> {
+        self.region
+    }
+
+    /// Format of the buffers of the stream
+    pub fn format(&self) -> Fourcc {
+        self.swapchain.format()
+    }
+
+    /// Size of the buffers of the stream
+    pub fn buffer_size(&self) -> Size<i32, Buffer> {
+        let size = self.transform.transform_size(self.region.size);
+        (size.w, size.h).into()
+    }
+
+    /// Change the streamed region, scale or transform
+    ///
+    /// If the size of the buffers changes, the pool is re-allocated. Buffers of frames still held
+    /// by the consumer stay valid. The next frame will be fully redrawn, if anything changed.
+    pub fn update(
+        &mut self,
+        region: impl Into<Rectangle<i32, Physical>>,
+        scale: impl Into<Scale<f64>>,
+        transform: Transform,
+    ) {
+        let region = region.into();
+        let scale = scale.into();
+        if self.region == region && self.scale == scale && self.transform == transform {
+            return;
+        }
+
+        self.region = region;
+        self.scale = scale;
+        self.transform = transform;
+        let size = transform.transform_size(region.size);
+        self.damage_tracker = OutputDamageTracker::new(size, scale, transform);
+        self.swapchain.resize(size.w as u32, size.h as u32);
+        self.swapchain.reset_buffer_ages();
+    }
+
+    /// Set the minimal interval between two frames of the stream
+    ///
+    /// Frames requested before the interval elapsed are skipped. `None` streams every frame.
+    pub fn set_frame_interval(&mut self, interval: Option<Duration>) {
+        self.frame_interval = interval;
+    }
+
+    /// The minimal interval between two frames of the stream
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.frame_interval
+    }
+
+    /// Total number of frames skipped by this stream
+    pub fn skipped_frames(&self) -> u64 {
+        self.total_skipped
+    }
+
+    /// Drop all buffers of the pool not currently held by the consumer
+    ///
+    /// The next frame will be fully redrawn.
+    pub fn reset_buffers(&mut self) {
+        self.swapchain.reset_buffers();
+    }
+
+    /// Render the next frame of the stream
+    ///
+    /// `elements` are positioned in the coordinate space of the output and are moved
+    /// according to the streamed region. `time` is the presentation time of the frame,
+    /// used for throttling the stream to its frame interval.
+    #[profiling::function]
+    pub fn render_frame<R, E>(
+        &mut self,
+        renderer: &mut R,
+        elements: &[E],
+        clear_color: impl Into<Color32F>,
+        time: Time<Monotonic>,
+    ) -> Result<StreamResult<A::Buffer>, StreamErrorType<A, R>>
+    where
+        R: Renderer + Bind<Dmabuf>,
+        E: RenderElement<R>,
+    {
+        if is_throttled(self.last_frame, time, self.frame_interval) {
+            return Ok(self.skip(SkipReason::Throttled));
+        }
+
+        let Some(slot) = self.swapchain.acquire().map_err(StreamError::Allocator)? else {
+            return Ok(self.skip(SkipReason::NoFreeBuffer));
+        };
+        let dmabuf = slot.export().map_err(StreamError::AsDmabuf)?;
+
+        let offset = Point::from((-self.region.loc.x, -self.region.loc.y));
+        let elements = elements
+            .iter()
+            .map(|element| RelocateRenderElement::from_element(element, offset, Relocate::Relative))
+            .collect::<Vec<_>>();
+
+        let result = self.damage_tracker.render_output_with(
+            renderer,
+            dmabuf.clone(),
+            slot.age() as usize,
+            &elements,
+            clear_color.into(),
+        )?;
+        let Some(damage) = result.damage else {
+            // dropping the slot returns the buffer to the pool
+            return Ok(self.skip(SkipReason::Unchanged));
+        };
+        let damage = damage_to_buffer(damage, self.region.size, self.transform).collect();
+        let sync = result.sync;
+        self.swapchain.submitted(&slot);
+
+        let frame = StreamFrame {
+            slot,
+            dmabuf,
+            sequence: self.sequence,
+            time,
+            damage,
+            sync,
+            skipped: std::mem::take(&mut self.skipped),
+        };
+        self.sequence += 1;
+        self.last_frame = Some(time);
+        Ok(StreamResult::Frame(frame))
+    }
+
+    fn skip(&mut self, reason: SkipReason) -> StreamResult<A::Buffer> {
+        self.skipped += 1;
+        self.total_skipped += 1;
+        StreamResult::Skipped(reason)
+    }
+}
+
+fn is_throttled(last: Option<Time<Monotonic>>, now: Time<Monotonic>, interval: Option<Duration>) -> bool {
+    match (last, interval) {
+        // a clock jumping backwards should not stall the stream
+        (Some(last), Some(interval)) => now >= last && Time::elapsed(&last, now) < interval,
+        _ => false,
+    }
+}
+
+pub(super) fn damage_to_buffer(
+    damage: &[Rectangle<i32, Physical>],
+    region_size: Size<i32, Physical>,
+    transform: Transform,
+) -> impl Iterator<Item = Rectangle<i32, Buffer>> + '_ {
+    // the damage tracker renders with the inverted output transform
+    let area = region_size.to_logical(1);
+    damage
+        .iter()
+        .map(move |rect| rect.to_logical(1).to_buffer(1, transform.invert(), &area))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::is_throttled;
+    use crate::utils::{Monotonic, Time};
+
+    #[test]
+    fn stream_throttles_to_frame_interval() {
+        let time = |millis: u64| Time::<Monotonic>::from(Duration::from_millis(millis));
+        let interval = Some(Duration::from_millis(33));
+
+        assert!(!is_throttled(None, time(0), interval));
+        assert!(!is_throttled(Some(time(0)), time(10), None));
+        assert!(is_throttled(Some(time(0)), time(10), interval));
+        assert!(!is_throttled(Some(time(0)), time(33), interval));
+        assert!(!is_throttled(Some(time(40)), time(20), interval));
+    }
+}
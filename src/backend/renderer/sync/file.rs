@@ -0,0 +1,80 @@
+use std::{
+    os::unix::io::{AsFd, BorrowedFd, OwnedFd},
+    sync::Arc,
+};
+
+use rustix::event::{poll, PollFd, PollFlags};
+
+use super::{Fence, Interrupted};
+
+/// A native fence represented by a sync file descriptor
+///
+/// Sync files are used by the kernel and by protocols like `linux-drm-syncobj-v1` to communicate
+/// when a buffer is ready to be accessed. They become readable once the fence is signaled.
+/// Renderers supporting native fences can wait for them on the gpu through
+/// [`Renderer::wait`](crate::backend::renderer::Renderer::wait) instead of blocking the cpu.
+#[derive(Debug, Clone)]
+pub struct SyncFile(Arc<OwnedFd>);
+
+impl SyncFile {
+    /// Wrap a sync file descriptor
+    pub fn new(fd: OwnedFd) -> Self {
+        SyncFile(Arc::new(fd))
+    }
+
+    fn poll(&self, timeout: i32) -> Result<bool, Interrupted> {
+        let mut fds = [PollFd::new(&*self.0, PollFlags::IN)];
+        match poll(&mut fds, timeout) {
+            Ok(ready) => Ok(ready > 0),
+            Err(rustix::io::Errno::INTR) | Err(rustix::io::Errno::AGAIN) => Err(Interrupted),
+            Err(err) => {
+                tracing::warn!(?err, "Polling sync file failed");
+                Err(Interrupted)
+            }
+        }
+    }
+}
+
+impl AsFd for SyncFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl Fence for SyncFile {
+    fn is_signaled(&self) -> bool {
+        self.poll(0).unwrap_or(false)
+    }
+
+    fn wait(&self) -> Result<(), Interrupted> {
+        while !self.poll(-1)? {}
+        Ok(())
+    }
+
+    fn is_exportable(&self) -> bool {
+        true
+    }
+
+    fn export(&self) -> Option<OwnedFd> {
+        self.0.try_clone().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncFile;
+    use crate::backend::renderer::sync::{Fence, SyncPoint};
+
+    #[test]
+    fn sync_file_signals_when_readable() {
+        let fd = rustix::event::eventfd(0, rustix::event::EventfdFlags::CLOEXEC).unwrap();
+        let fence = SyncFile::new(fd.try_clone().unwrap());
+        assert!(!fence.is_signaled());
+
+        rustix::io::write(&fd, &1u64.to_ne_bytes()).unwrap();
+        let sync = SyncPoint::from(fence);
+        assert!(sync.is_reached());
+        assert!(sync.wait().is_ok());
+        assert!(sync.export().is_some());
+    }
+}
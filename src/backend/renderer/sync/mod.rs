@@ -5,6 +5,9 @@ use downcast_rs::{impl_downcast, Downcast};
 
 #[cfg(feature = "backend_egl")]
 mod egl;
+mod file;
+
+pub use self::file::SyncFile;
 
 /// Waiting for the fence was interrupted for an unknown reason.
 ///
@@ -12,9 +12,10 @@ use crate::{
 };
 use crate::{
     backend::{
-        allocator::{dmabuf::Dmabuf, Fourcc},
+        allocator::{dmabuf::Dmabuf, Buffer as AllocatorBuffer, Fourcc, Slot},
         renderer::{
-            sync::SyncPoint, DebugFlags, Frame, ImportDma, ImportMem, Renderer, Texture, TextureFilter,
+            sync::SyncPoint, Bind, DebugFlags, Frame, ImportDma, ImportMem, Offscreen, Renderer, RendererId,
+            Texture, TextureFilter, Unbind,
         },
         SwapBuffersError,
     },
@@ -24,11 +25,15 @@ use crate::{
 use super::Color32F;
 
 #[derive(Debug)]
-pub struct DummyRenderer {}
+pub struct DummyRenderer {
+    id: RendererId,
+}
 
 impl DummyRenderer {
     pub fn new() -> DummyRenderer {
-        DummyRenderer {}
+        DummyRenderer {
+            id: RendererId::new(),
+        }
     }
 }
 
@@ -63,7 +68,7 @@ impl Renderer for DummyRenderer {
     type Frame<'a> = DummyFrame;
 
     fn id(&self) -> usize {
-        0
+        self.id.get()
     }
 
     fn render(
@@ -71,7 +76,7 @@ impl Renderer for DummyRenderer {
         _size: Size<i32, Physical>,
         _dst_transform: Transform,
     ) -> Result<DummyFrame, Self::Error> {
-        Ok(DummyFrame {})
+        Ok(DummyFrame { id: self.id.get() })
     }
 
     fn upscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
@@ -98,10 +103,13 @@ impl ImportMem for DummyRenderer {
         &mut self,
         _data: &[u8],
         _format: Fourcc,
-        _size: Size<i32, Buffer>,
+        size: Size<i32, Buffer>,
         _flipped: bool,
     ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
-        unimplemented!()
+        Ok(DummyTexture {
+            width: size.w as u32,
+            height: size.h as u32,
+        })
     }
 
     fn update_memory(
@@ -110,7 +118,7 @@ impl ImportMem for DummyRenderer {
         _data: &[u8],
         _region: Rectangle<i32, Buffer>,
     ) -> Result<(), <Self as Renderer>::Error> {
-        unimplemented!()
+        Ok(())
     }
 
     fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
@@ -198,15 +206,48 @@ impl ImportEgl for DummyRenderer {
 #[cfg(feature = "wayland_frontend")]
 impl ImportDmaWl for DummyRenderer {}
 
+impl Bind<DummyTexture> for DummyRenderer {
+    fn bind(&mut self, _target: DummyTexture) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a, B: AllocatorBuffer> Bind<&'a Slot<B>> for DummyRenderer {
+    fn bind(&mut self, _target: &'a Slot<B>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl Unbind for DummyRenderer {
+    fn unbind(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl Offscreen<DummyTexture> for DummyRenderer {
+    fn create_buffer(
+        &mut self,
+        _format: Fourcc,
+        size: Size<i32, Buffer>,
+    ) -> Result<DummyTexture, Self::Error> {
+        Ok(DummyTexture {
+            width: size.w as u32,
+            height: size.h as u32,
+        })
+    }
+}
+
 #[derive(Debug)]
-pub struct DummyFrame {}
+pub struct DummyFrame {
+    id: usize,
+}
 
 impl Frame for DummyFrame {
     type Error = DummyError;
     type TextureId = DummyTexture;
 
     fn id(&self) -> usize {
-        0
+        self.id
     }
 
     fn clear(&mut self, _color: Color32F, _damage: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
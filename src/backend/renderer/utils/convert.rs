@@ -0,0 +1,542 @@
+//! Conversion of pixel data between [`Fourcc`] formats in cpu memory
+//!
+//! Useful whenever pixel data has to be passed to something not supporting its format,
+//! e.g. when encoding screenshots, loading cursor images or importing shm buffers with
+//! formats a renderer can not sample from.
+//!
+//! Conversions between 8-bit formats with four channels only reorder the bytes of every pixel,
+//! which uses SIMD instructions where available. All other conversions go through an
+//! intermediate representation with 16 bits per channel.
+//!
+//! ```
+//! use smithay::backend::{allocator::Fourcc, renderer::utils::convert};
+//!
+//! // a single opaque red pixel in memory order
+//! let xrgb = [0x00, 0x00, 0xff, 0x00];
+//! let abgr = convert::convert_to_vec(&xrgb, Fourcc::Xrgb8888, 4, Fourcc::Abgr8888, (1, 1)).unwrap();
+//! assert_eq!(abgr, [0xff, 0x00, 0x00, 0xff]);
+//! ```
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::color_transform::{ColorTransform, Lut3d, DEFAULT_LUT_SIZE},
+    },
+    utils::{Buffer, Size},
+};
+
+/// Error returned by the conversion functions
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    /// The format is not supported
+    #[error("Unsupported format: {0:?}")]
+    UnsupportedFormat(Fourcc),
+    /// A buffer is too small for the given size and stride
+    #[error("The buffer is too small for the given size and stride")]
+    BufferTooSmall,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    // byte offsets of the channels of 8-bit formats, `a` is padding for formats without alpha
+    Bytes4 {
+        r: usize,
+        g: usize,
+        b: usize,
+        a: usize,
+        alpha: bool,
+    },
+    Bytes3 {
+        r: usize,
+        g: usize,
+        b: usize,
+    },
+    // bit offsets of the color channels of little endian 2:10:10:10 formats, alpha is always on top
+    Packed10 {
+        r: u32,
+        g: u32,
+        b: u32,
+        alpha: bool,
+    },
+}
+
+impl Layout {
+    fn for_format(format: Fourcc) -> Result<Layout, ConvertError> {
+        let bytes4 = |r, g, b, a, alpha| Layout::Bytes4 { r, g, b, a, alpha };
+        let packed10 = |r, g, b, alpha| Layout::Packed10 { r, g, b, alpha };
+        Ok(match format {
+            Fourcc::Argb8888 => bytes4(2, 1, 0, 3, true),
+            Fourcc::Xrgb8888 => bytes4(2, 1, 0, 3, false),
+            Fourcc::Abgr8888 => bytes4(0, 1, 2, 3, true),
+            Fourcc::Xbgr8888 => bytes4(0, 1, 2, 3, false),
+            Fourcc::Rgba8888 => bytes4(3, 2, 1, 0, true),
+            Fourcc::Rgbx8888 => bytes4(3, 2, 1, 0, false),
+            Fourcc::Bgra8888 => bytes4(1, 2, 3, 0, true),
+            Fourcc::Bgrx8888 => bytes4(1, 2, 3, 0, false),
+            Fourcc::Rgb888 => Layout::Bytes3 { r: 2, g: 1, b: 0 },
+            Fourcc::Bgr888 => Layout::Bytes3 { r: 0, g: 1, b: 2 },
+            Fourcc::Argb2101010 => packed10(20, 10, 0, true),
+            Fourcc::Xrgb2101010 => packed10(20, 10, 0, false),
+            Fourcc::Abgr2101010 => packed10(0, 10, 20, true),
+            Fourcc::Xbgr2101010 => packed10(0, 10, 20, false),
+            format => return Err(ConvertError::UnsupportedFormat(format)),
+        })
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Layout::Bytes3 { .. } => 3,
+            Layout::Bytes4 { .. } | Layout::Packed10 { .. } => 4,
+        }
+    }
+
+    fn has_alpha(&self) -> bool {
+        match *self {
+            Layout::Bytes4 { alpha, .. } | Layout::Packed10 { alpha, .. } => alpha,
+            Layout::Bytes3 { .. } => false,
+        }
+    }
+
+    // Returns the channels of a pixel as 16-bit rgba
+    fn unpack(&self, pixel: &[u8]) -> [u16; 4] {
+        let expand8 = |value: u8| value as u16 * 257;
+        match *self {
+            Layout::Bytes4 { r, g, b, a, alpha } => [
+                expand8(pixel[r]),
+                expand8(pixel[g]),
+                expand8(pixel[b]),
+                if alpha { expand8(pixel[a]) } else { u16::MAX },
+            ],
+            Layout::Bytes3 { r, g, b } => [expand8(pixel[r]), expand8(pixel[g]), expand8(pixel[b]), u16::MAX],
+            Layout::Packed10 { r, g, b, alpha } => {
+                let value = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                let expand10 = |offset: u32| {
+                    let channel = ((value >> offset) & 0x3ff) as u16;
+                    (channel << 6) | (channel >> 4)
+                };
+                [
+                    expand10(r),
+                    expand10(g),
+                    expand10(b),
+                    if alpha {
+                        (value >> 30) as u16 * 0x5555
+                    } else {
+                        u16::MAX
+                    },
+                ]
+            }
+        }
+    }
+
+    fn pack(&self, [red, green, blue, alpha]: [u16; 4], pixel: &mut [u8]) {
+        let reduce8 = |value: u16| ((value as u32 + 128) / 257) as u8;
+        match *self {
+            Layout::Bytes4 { r, g, b, a, .. } => {
+                pixel[r] = reduce8(red);
+                pixel[g] = reduce8(green);
+                pixel[b] = reduce8(blue);
+                pixel[a] = reduce8(alpha);
+            }
+            Layout::Bytes3 { r, g, b } => {
+                pixel[r] = reduce8(red);
+                pixel[g] = reduce8(green);
+                pixel[b] = reduce8(blue);
+            }
+            Layout::Packed10 { r, g, b, .. } => {
+                let reduce = |value: u16, max: u32| (value as u32 * max + 0x7fff) / 0xffff;
+                let value = (reduce(red, 0x3ff) << r)
+                    | (reduce(green, 0x3ff) << g)
+                    | (reduce(blue, 0x3ff) << b)
+                    | (reduce(alpha, 0x3) << 30);
+                pixel.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+const FORMATS: &[Fourcc] = &[
+    Fourcc::Argb8888,
+    Fourcc::Xrgb8888,
+    Fourcc::Abgr8888,
+    Fourcc::Xbgr8888,
+    Fourcc::Rgba8888,
+    Fourcc::Rgbx8888,
+    Fourcc::Bgra8888,
+    Fourcc::Bgrx8888,
+    Fourcc::Rgb888,
+    Fourcc::Bgr888,
+    Fourcc::Argb2101010,
+    Fourcc::Xrgb2101010,
+    Fourcc::Abgr2101010,
+    Fourcc::Xbgr2101010,
+];
+
+/// Returns whether conversions from and to the given format are supported
+pub fn is_supported(format: Fourcc) -> bool {
+    Layout::for_format(format).is_ok()
+}
+
+/// Iterate over all formats supported by the conversion functions
+pub fn supported_formats() -> impl Iterator<Item = Fourcc> {
+    FORMATS.iter().copied()
+}
+
+// Checks that a buffer with the given stride is large enough to hold rows of `row_len` bytes
+fn check_size(
+    len: usize,
+    stride: usize,
+    row_len: usize,
+    size: Size<i32, Buffer>,
+) -> Result<(), ConvertError> {
+    if size.h <= 0 || size.w <= 0 {
+        return Ok(());
+    }
+    if stride < row_len || len < stride * (size.h as usize - 1) + row_len {
+        return Err(ConvertError::BufferTooSmall);
+    }
+    Ok(())
+}
+
+/// Convert the pixels of `src` into `dst`
+///
+/// `src_stride` and `dst_stride` are the number of bytes between the start of two rows.
+#[profiling::function]
+pub fn convert(
+    src: &[u8],
+    src_format: Fourcc,
+    src_stride: usize,
+    dst: &mut [u8],
+    dst_format: Fourcc,
+    dst_stride: usize,
+    size: impl Into<Size<i32, Buffer>>,
+) -> Result<(), ConvertError> {
+    let size = size.into();
+    let src_layout = Layout::for_format(src_format)?;
+    let dst_layout = Layout::for_format(dst_format)?;
+    let width = size.w.max(0) as usize;
+    let src_row_len = width * src_layout.bytes_per_pixel();
+    let dst_row_len = width * dst_layout.bytes_per_pixel();
+    check_size(src.len(), src_stride, src_row_len, size)?;
+    check_size(dst.len(), dst_stride, dst_row_len, size)?;
+
+    let shuffle = byte_shuffle(src_layout, dst_layout);
+    for row in 0..size.h.max(0) as usize {
+        let src_row = &src[row * src_stride..][..src_row_len];
+        let dst_row = &mut dst[row * dst_stride..][..dst_row_len];
+        if src_format == dst_format {
+            dst_row.copy_from_slice(src_row);
+        } else if let Some((shuffle, fill)) = shuffle {
+            shuffle_row(src_row, dst_row, shuffle, fill);
+        } else {
+            for (src_pixel, dst_pixel) in src_row
+                .chunks_exact(src_layout.bytes_per_pixel())
+                .zip(dst_row.chunks_exact_mut(dst_layout.bytes_per_pixel()))
+            {
+                dst_layout.pack(src_layout.unpack(src_pixel), dst_pixel);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert the pixels of `src` into a new tightly packed buffer
+pub fn convert_to_vec(
+    src: &[u8],
+    src_format: Fourcc,
+    src_stride: usize,
+    dst_format: Fourcc,
+    size: impl Into<Size<i32, Buffer>>,
+) -> Result<Vec<u8>, ConvertError> {
+    let size = size.into();
+    let dst_stride = size.w.max(0) as usize * Layout::for_format(dst_format)?.bytes_per_pixel();
+    let mut dst = vec![0; dst_stride * size.h.max(0) as usize];
+    convert(
+        src, src_format, src_stride, &mut dst, dst_format, dst_stride, size,
+    )?;
+    Ok(dst)
+}
+
+/// Multiply the color channels of every pixel with its alpha value
+///
+/// Does nothing for formats without an alpha channel.
+pub fn premultiply_alpha(
+    data: &mut [u8],
+    format: Fourcc,
+    stride: usize,
+    size: impl Into<Size<i32, Buffer>>,
+) -> Result<(), ConvertError> {
+    map_pixels(data, format, stride, size.into(), |[r, g, b, a]| {
+        let multiply = |channel: u16| ((channel as u32 * a as u32 + 0x7fff) / 0xffff) as u16;
+        [multiply(r), multiply(g), multiply(b), a]
+    })
+}
+
+/// Divide the color channels of every pixel by its alpha value
+///
+/// Reverts [`premultiply_alpha`] up to the precision lost by it.
+/// Does nothing for formats without an alpha channel.
+pub fn unpremultiply_alpha(
+    data: &mut [u8],
+    format: Fourcc,
+    stride: usize,
+    size: impl Into<Size<i32, Buffer>>,
+) -> Result<(), ConvertError> {
+    map_pixels(data, format, stride, size.into(), |[r, g, b, a]| {
+        if a == 0 {
+            return [0, 0, 0, 0];
+        }
+        let divide =
+            |channel: u16| ((channel as u32 * 0xffff + a as u32 / 2) / a as u32).min(u16::MAX as u32) as u16;
+        [divide(r), divide(g), divide(b), a]
+    })
+}
+
+/// Apply a [`ColorTransform`] to every pixel, e.g. to filter the output of a software renderer
+///
+/// The pixels are expected to use premultiplied alpha. The transform is baked into a lookup table first,
+/// matching the precision of the gpu based implementations.
+pub fn apply_color_transform(
+    data: &mut [u8],
+    format: Fourcc,
+    stride: usize,
+    size: impl Into<Size<i32, Buffer>>,
+    transform: &ColorTransform,
+) -> Result<(), ConvertError> {
+    Layout::for_format(format)?;
+    if transform.is_identity() {
+        return Ok(());
+    }
+    apply_lut3d(data, format, stride, size, &transform.bake(DEFAULT_LUT_SIZE))
+}
+
+/// Apply a baked [`Lut3d`] to every pixel
+///
+/// Like [`apply_color_transform`], but allows to reuse the lookup table for multiple buffers or frames.
+pub fn apply_lut3d(
+    data: &mut [u8],
+    format: Fourcc,
+    stride: usize,
+    size: impl Into<Size<i32, Buffer>>,
+    lut: &Lut3d,
+) -> Result<(), ConvertError> {
+    let size = size.into();
+    let layout = Layout::for_format(format)?;
+    let to_float = |value: u16| value as f32 / u16::MAX as f32;
+    let to_int = |value: f32| (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+    for_each_pixel(data, layout, stride, size, |[r, g, b, a]| {
+        if a == 0 {
+            return [r, g, b, a];
+        }
+        let alpha = to_float(a);
+        let color = lut.sample([r, g, b].map(|c| to_float(c) / alpha));
+        let [r, g, b] = color.map(|c| to_int(c * alpha));
+        [r, g, b, a]
+    })
+}
+
+fn map_pixels(
+    data: &mut [u8],
+    format: Fourcc,
+    stride: usize,
+    size: Size<i32, Buffer>,
+    func: impl Fn([u16; 4]) -> [u16; 4],
+) -> Result<(), ConvertError> {
+    let layout = Layout::for_format(format)?;
+    if !layout.has_alpha() {
+        let row_len = size.w.max(0) as usize * layout.bytes_per_pixel();
+        return check_size(data.len(), stride, row_len, size);
+    }
+    for_each_pixel(data, layout, stride, size, func)
+}
+
+fn for_each_pixel(
+    data: &mut [u8],
+    layout: Layout,
+    stride: usize,
+    size: Size<i32, Buffer>,
+    func: impl Fn([u16; 4]) -> [u16; 4],
+) -> Result<(), ConvertError> {
+    let row_len = size.w.max(0) as usize * layout.bytes_per_pixel();
+    check_size(data.len(), stride, row_len, size)?;
+
+    for row in 0..size.h.max(0) as usize {
+        for pixel in data[row * stride..][..row_len].chunks_exact_mut(layout.bytes_per_pixel()) {
+            layout.pack(func(layout.unpack(pixel)), pixel);
+        }
+    }
+    Ok(())
+}
+
+// Marks bytes of the destination not copied from the source
+const FILL: u8 = 0x80;
+
+// Returns the source byte for every destination byte and values or'ed into the destination,
+// if the conversion only reorders the bytes of a pixel
+fn byte_shuffle(src: Layout, dst: Layout) -> Option<([u8; 4], [u8; 4])> {
+    let (
+        Layout::Bytes4 {
+            r: src_r,
+            g: src_g,
+            b: src_b,
+            a: src_a,
+            alpha: src_alpha,
+        },
+        Layout::Bytes4 {
+            r, g, b, a, alpha, ..
+        },
+    ) = (src, dst)
+    else {
+        return None;
+    };
+
+    let mut shuffle = [0; 4];
+    let mut fill = [0; 4];
+    shuffle[r] = src_r as u8;
+    shuffle[g] = src_g as u8;
+    shuffle[b] = src_b as u8;
+    shuffle[a] = src_a as u8;
+    if alpha && !src_alpha {
+        shuffle[a] = FILL;
+        fill[a] = 0xff;
+    }
+    Some((shuffle, fill))
+}
+
+fn shuffle_row(src: &[u8], dst: &mut [u8], shuffle: [u8; 4], fill: [u8; 4]) {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("ssse3") {
+        // SAFETY: the cpu supports ssse3
+        return unsafe { shuffle_row_ssse3(src, dst, shuffle, fill) };
+    }
+    shuffle_row_scalar(src, dst, shuffle, fill)
+}
+
+fn shuffle_row_scalar(src: &[u8], dst: &mut [u8], shuffle: [u8; 4], fill: [u8; 4]) {
+    for (src_pixel, dst_pixel) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        for i in 0..4 {
+            let value = if shuffle[i] == FILL {
+                0
+            } else {
+                src_pixel[shuffle[i] as usize]
+            };
+            dst_pixel[i] = value | fill[i];
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn shuffle_row_ssse3(src: &[u8], dst: &mut [u8], shuffle: [u8; 4], fill: [u8; 4]) {
+    use std::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_or_si128, _mm_shuffle_epi8, _mm_storeu_si128};
+
+    // pshufb zeroes bytes with the highest bit of the index set, which matches `FILL`
+    let mut indices = [0u8; 16];
+    let mut fills = [0u8; 16];
+    for pixel in 0..4 {
+        for i in 0..4 {
+            indices[pixel * 4 + i] = if shuffle[i] == FILL {
+                FILL
+            } else {
+                (pixel * 4) as u8 + shuffle[i]
+            };
+            fills[pixel * 4 + i] = fill[i];
+        }
+    }
+    let indices = _mm_loadu_si128(indices.as_ptr() as *const __m128i);
+    let fills = _mm_loadu_si128(fills.as_ptr() as *const __m128i);
+
+    let len = src.len().min(dst.len());
+    let blocks = len / 16;
+    for block in 0..blocks {
+        let pixels = _mm_loadu_si128(src.as_ptr().add(block * 16) as *const __m128i);
+        let pixels = _mm_or_si128(_mm_shuffle_epi8(pixels, indices), fills);
+        _mm_storeu_si128(dst.as_mut_ptr().add(block * 16) as *mut __m128i, pixels);
+    }
+    shuffle_row_scalar(&src[blocks * 16..], &mut dst[blocks * 16..], shuffle, fill);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        convert, convert_to_vec, is_supported, premultiply_alpha, supported_formats, unpremultiply_alpha,
+    };
+    use crate::backend::allocator::Fourcc;
+
+    #[test]
+    fn supported_formats_have_layouts() {
+        assert!(supported_formats().all(is_supported));
+        assert!(!is_supported(Fourcc::Nv12));
+    }
+
+    #[test]
+    fn convert_reorders_bytes_and_fills_alpha() {
+        // enough pixels to cover the simd path and the remainder, with padding at the end of every row
+        let width = 37;
+        let stride = width * 4 + 8;
+        let src = (0..stride * 2).map(|i| i as u8).collect::<Vec<_>>();
+
+        let dst = convert_to_vec(
+            &src,
+            Fourcc::Xrgb8888,
+            stride,
+            Fourcc::Abgr8888,
+            (width as i32, 2),
+        )
+        .unwrap();
+        assert_eq!(dst.len(), width * 4 * 2);
+        for row in 0..2 {
+            for x in 0..width {
+                let src = &src[row * stride + x * 4..][..4];
+                let dst = &dst[(row * width + x) * 4..][..4];
+                assert_eq!(dst, [src[2], src[1], src[0], 0xff]);
+            }
+        }
+
+        let mut too_small = vec![0; 4];
+        assert!(convert(
+            &src,
+            Fourcc::Xrgb8888,
+            stride,
+            &mut too_small,
+            Fourcc::Abgr8888,
+            4,
+            (2, 1)
+        )
+        .is_err());
+        assert!(convert_to_vec(&src, Fourcc::Nv12, stride, Fourcc::Abgr8888, (1, 1)).is_err());
+    }
+
+    #[test]
+    fn convert_roundtrips_through_other_depths() {
+        let src = (0..64u32)
+            .flat_map(|i| [(i * 4) as u8, (255 - i * 4) as u8, (i * 3) as u8, 0xff])
+            .collect::<Vec<_>>();
+
+        let packed = convert_to_vec(&src, Fourcc::Argb8888, 64 * 4, Fourcc::Xbgr2101010, (64, 1)).unwrap();
+        let back = convert_to_vec(&packed, Fourcc::Xbgr2101010, 64 * 4, Fourcc::Argb8888, (64, 1)).unwrap();
+        assert_eq!(back, src);
+
+        let rgb = convert_to_vec(&src, Fourcc::Argb8888, 64 * 4, Fourcc::Bgr888, (64, 1)).unwrap();
+        let back = convert_to_vec(&rgb, Fourcc::Bgr888, 64 * 3, Fourcc::Argb8888, (64, 1)).unwrap();
+        assert_eq!(back, src);
+    }
+
+    #[test]
+    fn premultiply_scales_colors_by_alpha() {
+        let mut pixel = [200, 100, 50, 128];
+        premultiply_alpha(&mut pixel, Fourcc::Argb8888, 4, (1, 1)).unwrap();
+        assert_eq!(pixel, [100, 50, 25, 128]);
+
+        unpremultiply_alpha(&mut pixel, Fourcc::Argb8888, 4, (1, 1)).unwrap();
+        assert!(pixel
+            .iter()
+            .zip([200u8, 100, 50, 128])
+            .all(|(value, expected)| value.abs_diff(expected) <= 1));
+
+        // formats without alpha are left alone
+        let mut pixel = [200, 100, 50, 0];
+        premultiply_alpha(&mut pixel, Fourcc::Xrgb8888, 4, (1, 1)).unwrap();
+        assert_eq!(pixel, [200, 100, 50, 0]);
+    }
+}
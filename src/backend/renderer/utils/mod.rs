@@ -4,6 +4,8 @@
 use crate::utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Size};
 use std::{collections::VecDeque, fmt, sync::Arc};
 
+pub mod convert;
+
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
 #[cfg(feature = "wayland_frontend")]
@@ -544,3 +546,176 @@ pub struct SurfaceView {
     /// The logical offset for a sub-surface
     pub offset: Point<i32, Logical>,
 }
+
+/// Rounding rule for mapping fractional physical rectangles onto whole pixels
+///
+/// See [`snap_to_pixels`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelRounding {
+    /// Round every edge to the nearest pixel, used for the geometry of elements
+    Nearest,
+    /// Include every partially covered pixel, used for damage
+    Outward,
+    /// Exclude every partially covered pixel, used for opaque regions
+    Inward,
+}
+
+/// Maps a rectangle of an element placed at a fractional location onto whole pixels
+///
+/// `rect` is relative to the fractional `location` of the element, the returned rectangle
+/// is relative to the rounded location used for the element geometry. As the edges of the
+/// rectangle are rounded in output space, the geometry, damage and opaque regions of elements
+/// at fractional scales line up exactly, without seams between adjacent elements or damage
+/// missing the partially covered pixels at the edges.
+pub fn snap_to_pixels(
+    location: Point<f64, Physical>,
+    rect: Rectangle<f64, Physical>,
+    rounding: PixelRounding,
+) -> Rectangle<i32, Physical> {
+    let rect = Rectangle::from_loc_and_size(location + rect.loc, rect.size);
+    let mut snapped = match rounding {
+        PixelRounding::Nearest => rect.to_i32_round_edges(),
+        PixelRounding::Outward => rect.to_i32_up(),
+        PixelRounding::Inward => {
+            // rectangles smaller than a pixel may not cover any pixel at all
+            let topleft = rect.loc.to_i32_ceil::<i32>();
+            let bottomright = (rect.loc + rect.size).to_i32_floor::<i32>();
+            Rectangle::from_extemities(
+                topleft,
+                (
+                    std::cmp::max(bottomright.x, topleft.x),
+                    std::cmp::max(bottomright.y, topleft.y),
+                ),
+            )
+        }
+    };
+    snapped.loc -= location.to_i32_round();
+    snapped
+}
+
+/// Returns the physical size of an element of the given fractional size placed at a fractional location
+///
+/// This equals the size of the element rectangle snapped with [`PixelRounding::Nearest`].
+pub fn snapped_size(location: Point<f64, Physical>, size: Size<f64, Physical>) -> Size<i32, Physical> {
+    snap_to_pixels(
+        location,
+        Rectangle::from_loc_and_size((0.0, 0.0), size),
+        PixelRounding::Nearest,
+    )
+    .size
+}
+
+/// Computes the regions of a texture to upload for the given buffer damage
+///
+/// The damage is clipped to the buffer and split into non-overlapping rectangles, so no pixel is
+/// uploaded twice. Rectangles of equal horizontal extent directly below each other, like the lines of
+/// a terminal, are combined into a single rectangle. If most of the buffer is damaged anyway, a single
+/// rectangle covering the whole buffer is returned instead.
+pub fn texture_upload_regions(
+    damage: &[Rectangle<i32, BufferCoord>],
+    size: Size<i32, BufferCoord>,
+) -> Vec<Rectangle<i32, BufferCoord>> {
+    let bounds = Rectangle::from_loc_and_size((0, 0), size);
+    let mut regions: Vec<Rectangle<i32, BufferCoord>> = Vec::with_capacity(damage.len());
+    for rect in damage.iter().filter_map(|rect| rect.intersection(bounds)) {
+        if rect.is_empty() {
+            continue;
+        }
+        let pieces = rect.subtract_rects(regions.iter().copied().filter(|other| other.overlaps(rect)));
+        regions.extend(pieces);
+    }
+
+    let damaged_area: i64 = regions
+        .iter()
+        .map(|rect| rect.size.w as i64 * rect.size.h as i64)
+        .sum();
+    if damaged_area * 4 >= size.w as i64 * size.h as i64 * 3 {
+        return vec![bounds];
+    }
+
+    regions.sort_unstable_by_key(|rect| (rect.loc.x, rect.size.w, rect.loc.y));
+    let mut merged: Vec<Rectangle<i32, BufferCoord>> = Vec::with_capacity(regions.len());
+    for rect in regions {
+        match merged.last_mut() {
+            Some(last)
+                if last.loc.x == rect.loc.x
+                    && last.size.w == rect.size.w
+                    && last.loc.y + last.size.h == rect.loc.y =>
+            {
+                last.size.h += rect.size.h;
+            }
+            _ => merged.push(rect),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{snap_to_pixels, texture_upload_regions, PixelRounding};
+    use crate::utils::{Buffer, Physical, Point, Rectangle, Size};
+
+    #[test]
+    fn upload_regions_merge_lines() {
+        let size = Size::<i32, Buffer>::from((800, 600));
+        let lines = (0..4)
+            .map(|line| Rectangle::from_loc_and_size((0, 100 + line * 20), (800, 20)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texture_upload_regions(&lines, size),
+            vec![Rectangle::from_loc_and_size((0, 100), (800, 80))]
+        );
+
+        // overlapping and out of bounds damage is uploaded only once
+        let damage = [
+            Rectangle::from_loc_and_size((700, 500), (200, 200)),
+            Rectangle::from_loc_and_size((750, 550), (50, 50)),
+        ];
+        assert_eq!(
+            texture_upload_regions(&damage, size),
+            vec![Rectangle::from_loc_and_size((700, 500), (100, 100))]
+        );
+
+        let damage = [Rectangle::from_loc_and_size((0, 0), (800, 500))];
+        assert_eq!(
+            texture_upload_regions(&damage, size),
+            vec![Rectangle::from_loc_and_size((0, 0), size)]
+        );
+    }
+
+    #[test]
+    fn snapped_rects_line_up_at_fractional_scales() {
+        // two elements of 101 logical pixels placed next to each other at scale 1.25
+        let scale = 1.25;
+        let left = Point::<f64, Physical>::from((10.0 * scale, 0.0));
+        let right = Point::<f64, Physical>::from((111.0 * scale, 0.0));
+        let size = Rectangle::from_loc_and_size((0.0, 0.0), (101.0 * scale, 101.0 * scale));
+        let left_geo = snap_to_pixels(left, size, PixelRounding::Nearest);
+        let right_geo = snap_to_pixels(right, size, PixelRounding::Nearest);
+        assert_eq!(
+            left.to_i32_round::<i32>().x + left_geo.size.w,
+            right.to_i32_round::<i32>().x + right_geo.loc.x
+        );
+
+        // damage covers every partially covered pixel, opaque regions only fully covered pixels
+        let origin = Point::default();
+        let rect = Rectangle::from_loc_and_size((1.25, 1.25), (2.5, 2.5));
+        assert_eq!(
+            snap_to_pixels(origin, rect, PixelRounding::Outward),
+            Rectangle::from_loc_and_size((1, 1), (3, 3))
+        );
+        assert_eq!(
+            snap_to_pixels(origin, rect, PixelRounding::Inward),
+            Rectangle::from_loc_and_size((2, 2), (1, 1))
+        );
+        assert_eq!(
+            snap_to_pixels(
+                origin,
+                Rectangle::from_loc_and_size((0.25, 0.25), (0.5, 0.5)),
+                PixelRounding::Inward
+            )
+            .size,
+            Size::from((0, 0))
+        );
+    }
+}
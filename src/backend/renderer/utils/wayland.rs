@@ -6,8 +6,8 @@ use crate::{
     wayland::{
         compositor::{
             self, add_destruction_hook, is_sync_subsurface, with_surface_tree_downward,
-            with_surface_tree_upward, BufferAssignment, Damage, RectangleKind, SubsurfaceCachedState,
-            SurfaceAttributes, SurfaceData, TraversalAction,
+            with_surface_tree_upward, BufferAssignment, Damage, RectangleKind, RegionAttributes,
+            SubsurfaceCachedState, SurfaceAttributes, SurfaceData, TraversalAction,
         },
         viewporter,
     },
@@ -46,6 +46,8 @@ pub struct RendererSurfaceState {
     pub(crate) textures: HashMap<(TypeId, usize), Box<dyn std::any::Any>>,
     pub(crate) surface_view: Option<SurfaceView>,
     pub(crate) opaque_regions: Vec<Rectangle<i32, Logical>>,
+    // the opaque region the opaque regions were last computed from
+    opaque_region_attributes: Option<RegionAttributes>,
 }
 
 // SAFETY: Only thing unsafe here is the `Box<dyn std::any::Any>`, which are the textures.
@@ -209,8 +211,13 @@ impl RendererSurfaceState {
             self.damage.add(buffer_damage);
         }
 
-        // if the buffer or our view changed rebuild our opaque regions
-        if new_buffer || surface_view_changed {
+        // if the buffer, our view or the opaque region changed rebuild our opaque regions,
+        // a changed opaque region may be committed without attaching a new buffer
+        let opaque_region_changed = self.opaque_region_attributes != attrs.opaque_region;
+        if new_buffer || surface_view_changed || opaque_region_changed {
+            if opaque_region_changed {
+                self.opaque_region_attributes = attrs.opaque_region.clone();
+            }
             self.opaque_regions.clear();
             if !self.buffer_has_alpha.unwrap_or(true) {
                 self.opaque_regions
@@ -356,6 +363,7 @@ impl RendererSurfaceState {
         self.surface_view = None;
         self.buffer_has_alpha = None;
         self.opaque_regions.clear();
+        self.opaque_region_attributes = None;
     }
 }
 
@@ -0,0 +1,84 @@
+use std::ffi::CStr;
+
+use ash::vk;
+use drm_fourcc::{DrmFormat, DrmFourcc};
+use thiserror::Error;
+
+use crate::backend::SwapBuffersError;
+
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::protocol::wl_shm;
+
+/// Error returned during rendering using Vulkan
+#[derive(Debug, Error)]
+pub enum VulkanError {
+    /// The physical device does not support Vulkan 1.1
+    #[error("The physical device does not support Vulkan 1.1")]
+    UnsupportedVersion,
+    /// The physical device is missing required extensions
+    #[error("The physical device is missing required extensions: {0:?}")]
+    MissingExtensions(Vec<&'static CStr>),
+    /// The physical device has no queue family supporting graphics operations
+    #[error("No queue family supports graphics operations")]
+    NoGraphicsQueue,
+    /// No memory type matches the requirements of a resource
+    #[error("No suitable memory type found")]
+    NoSuitableMemoryType,
+    /// The given buffer has an unsupported pixel format
+    #[error("Unsupported pixel format: {0:?}")]
+    UnsupportedPixelFormat(DrmFourcc),
+    /// The given dmabuf has an unsupported format and modifier combination
+    #[error("Unsupported format: {0:?}")]
+    UnsupportedFormat(DrmFormat),
+    /// The given wl buffer has an unsupported pixel format
+    #[error("Unsupported wl_shm format: {0:?}")]
+    #[cfg(feature = "wayland_frontend")]
+    UnsupportedWlPixelFormat(wl_shm::Format),
+    /// The planes of the given dmabuf are backed by different memory objects
+    #[error("Dmabufs with disjoint planes are not supported")]
+    DisjointPlanes,
+    /// The given buffer is incomplete
+    #[error("Incomplete buffer {expected} < {actual}")]
+    IncompleteBuffer {
+        /// Expected len of the buffer
+        expected: usize,
+        /// Actual len of the buffer
+        actual: usize,
+    },
+    /// The given region is not inside the texture
+    #[error("The region is out of bounds of the texture")]
+    OutOfBounds,
+    /// The given buffer was not accessible
+    #[error("Error accessing the buffer ({0:?})")]
+    #[cfg(feature = "wayland_frontend")]
+    BufferAccessError(#[from] crate::wayland::shm::BufferAccessError),
+    /// Duplicating the file descriptor of a dmabuf failed
+    #[error("Failed to duplicate the dmabuf file descriptor: {0}")]
+    DuplicateFd(#[source] std::io::Error),
+    /// No target is currently bound
+    #[error("No target is currently bound")]
+    NoTargetBound,
+    /// The requested operation is not supported
+    #[error("The requested operation is not supported")]
+    Unsupported,
+    /// Blocking for a synchronization primitive failed
+    #[error("Blocking for a synchronization primitive got interrupted")]
+    SyncInterrupted,
+    /// Some error from the Vulkan driver
+    #[error(transparent)]
+    Vk(#[from] vk::Result),
+}
+
+impl From<VulkanError> for SwapBuffersError {
+    #[inline]
+    fn from(value: VulkanError) -> Self {
+        match value {
+            x @ VulkanError::SyncInterrupted
+            | x @ VulkanError::Vk(vk::Result::ERROR_OUT_OF_HOST_MEMORY)
+            | x @ VulkanError::Vk(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
+                SwapBuffersError::TemporaryFailure(Box::new(x))
+            }
+            x => SwapBuffersError::ContextLost(Box::new(x)),
+        }
+    }
+}
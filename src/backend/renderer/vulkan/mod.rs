@@ -0,0 +1,2127 @@
+//! Implementation of the rendering traits using Vulkan
+//!
+//! The [`VulkanRenderer`] renders into dmabufs or offscreen [`VulkanTexture`]s and samples textures
+//! imported from memory, shm buffers or dmabufs. All operations of a frame are recorded into a single
+//! render pass, which is submitted once the frame is finished.
+//!
+//! The renderer requires a Vulkan 1.1 device supporting the following device extensions (and their dependencies):
+//! - `VK_EXT_image_drm_format_modifier`
+//! - `VK_EXT_external_memory_dma_buf`
+//! - `VK_KHR_external_memory_fd`
+//! - `VK_EXT_queue_family_foreign`
+//!
+//! To get the required extensions a device must support, use [`VulkanRenderer::required_extensions`].
+//!
+//! Submissions are currently waited upon before returning, so finishing a [`VulkanFrame`] always
+//! returns an already signaled [`SyncPoint`].
+
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    fmt,
+    io::Cursor,
+    os::unix::io::{AsRawFd, BorrowedFd, IntoRawFd},
+    sync::{Arc, Mutex},
+};
+
+use ash::{ext, khr, vk};
+use drm_fourcc::{DrmFormat, DrmFourcc, DrmModifier};
+use tracing::warn;
+
+use crate::{
+    backend::{
+        allocator::{
+            dmabuf::{Dmabuf, WeakDmabuf},
+            format::{get_bpp, has_alpha, FormatSet},
+            vulkan::format::{get_vk_format, known_formats},
+            Buffer, Slot,
+        },
+        vulkan::{version::Version, PhysicalDevice},
+    },
+    utils::{Buffer as BufferCoords, Physical, Point, Rectangle, Size, Transform},
+};
+
+#[cfg(feature = "wayland_frontend")]
+use crate::{
+    backend::renderer::{ImportDmaWl, ImportMemWl},
+    wayland::{compositor::SurfaceData, shm},
+};
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::protocol::wl_buffer;
+
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+use super::ImportEgl;
+use super::{
+    sync::SyncPoint, Bind, Color32F, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer,
+    RendererCapabilities, RendererId, Texture, TextureFilter, TextureMapping, Unbind,
+};
+
+mod error;
+
+pub use error::*;
+
+const ENTRY_POINT: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"main\0") };
+
+const COLOR_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+const COLOR_LAYERS: vk::ImageSubresourceLayers = vk::ImageSubresourceLayers {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    mip_level: 0,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+// Number of descriptor sets per pool, additional pools are created on demand
+const DESCRIPTOR_POOL_SIZE: u32 = 256;
+
+// (upscale, downscale) filters of the samplers
+const FILTERS: [(TextureFilter, TextureFilter); 4] = [
+    (TextureFilter::Linear, TextureFilter::Linear),
+    (TextureFilter::Linear, TextureFilter::Nearest),
+    (TextureFilter::Nearest, TextureFilter::Linear),
+    (TextureFilter::Nearest, TextureFilter::Nearest),
+];
+
+const TINT: [f32; 4] = [0.0, 0.2, 0.0, 0.2];
+
+struct DeviceInner {
+    phd: PhysicalDevice,
+    queue: vk::Queue,
+    queue_family_index: u32,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    external_memory_fd: khr::external_memory_fd::Device,
+    device: ash::Device,
+}
+
+impl fmt::Debug for DeviceInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceInner")
+            .field("phd", &self.phd)
+            .field("queue", &self.queue)
+            .field("queue_family_index", &self.queue_family_index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeviceInner {
+    fn memory_type(&self, type_bits: u32, flags: vk::MemoryPropertyFlags) -> Result<u32, VulkanError> {
+        (0..self.memory_properties.memory_type_count)
+            .find(|&index| {
+                type_bits & (1 << index) != 0
+                    && self.memory_properties.memory_types[index as usize]
+                        .property_flags
+                        .contains(flags)
+            })
+            .ok_or(VulkanError::NoSuitableMemoryType)
+    }
+}
+
+impl Drop for DeviceInner {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.device.device_wait_idle();
+            self.device.destroy_device(None);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TextureInner {
+    device: Arc<DeviceInner>,
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    // view used for sampling, alpha is swizzled to one for formats without alpha
+    view: vk::ImageView,
+    // view used as color attachment, if the image can be rendered to
+    attachment_view: vk::ImageView,
+    format: vk::Format,
+    fourcc: DrmFourcc,
+    size: Size<i32, BufferCoords>,
+    usage: vk::ImageUsageFlags,
+    dmabuf: Option<WeakDmabuf>,
+    flipped: bool,
+    // current layout of images owned by the renderer, images of dmabufs are kept in the general layout
+    layout: Mutex<vk::ImageLayout>,
+}
+
+impl TextureInner {
+    fn new(
+        device: &Arc<DeviceInner>,
+        fourcc: DrmFourcc,
+        size: Size<i32, BufferCoords>,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Self, VulkanError> {
+        let format = vk_format(fourcc).ok_or(VulkanError::UnsupportedPixelFormat(fourcc))?;
+        if size.w <= 0 || size.h <= 0 {
+            return Err(VulkanError::OutOfBounds);
+        }
+
+        // Handles are filled in by the caller, destroying null handles is a no-op
+        Ok(TextureInner {
+            device: device.clone(),
+            image: vk::Image::null(),
+            memory: vk::DeviceMemory::null(),
+            view: vk::ImageView::null(),
+            attachment_view: vk::ImageView::null(),
+            format,
+            fourcc,
+            size,
+            usage,
+            dmabuf: None,
+            flipped: false,
+            layout: Mutex::new(vk::ImageLayout::UNDEFINED),
+        })
+    }
+
+    fn create_views(&mut self) -> Result<(), vk::Result> {
+        let device = &self.device.device;
+        let view_info = |alpha| {
+            vk::ImageViewCreateInfo::default()
+                .image(self.image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(self.format)
+                .components(vk::ComponentMapping {
+                    r: vk::ComponentSwizzle::IDENTITY,
+                    g: vk::ComponentSwizzle::IDENTITY,
+                    b: vk::ComponentSwizzle::IDENTITY,
+                    a: alpha,
+                })
+                .subresource_range(COLOR_RANGE)
+        };
+
+        let alpha = if has_alpha(self.fourcc) {
+            vk::ComponentSwizzle::IDENTITY
+        } else {
+            vk::ComponentSwizzle::ONE
+        };
+        unsafe {
+            self.view = device.create_image_view(&view_info(alpha), None)?;
+            // VUID-VkFramebufferCreateInfo-pAttachments-00884: attachments need an identity swizzle
+            if self.usage.contains(vk::ImageUsageFlags::COLOR_ATTACHMENT) {
+                self.attachment_view =
+                    device.create_image_view(&view_info(vk::ComponentSwizzle::IDENTITY), None)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Barrier transitioning the image into `layout` for use by the renderer,
+    // images of dmabufs are acquired from the foreign queue family.
+    fn acquire(&self, layout: vk::ImageLayout) -> vk::ImageMemoryBarrier<'static> {
+        if self.dmabuf.is_some() {
+            image_barrier(
+                self.image,
+                vk::ImageLayout::GENERAL,
+                layout,
+                vk::QUEUE_FAMILY_FOREIGN_EXT,
+                self.device.queue_family_index,
+            )
+        } else {
+            image_barrier(
+                self.image,
+                *self.layout.lock().unwrap(),
+                layout,
+                vk::QUEUE_FAMILY_IGNORED,
+                vk::QUEUE_FAMILY_IGNORED,
+            )
+        }
+    }
+
+    // Barrier releasing the image of a dmabuf back to the foreign queue family
+    fn release(&self, layout: vk::ImageLayout) -> Option<vk::ImageMemoryBarrier<'static>> {
+        self.dmabuf.is_some().then(|| {
+            image_barrier(
+                self.image,
+                layout,
+                vk::ImageLayout::GENERAL,
+                self.device.queue_family_index,
+                vk::QUEUE_FAMILY_FOREIGN_EXT,
+            )
+        })
+    }
+
+    fn set_layout(&self, layout: vk::ImageLayout) {
+        *self.layout.lock().unwrap() = layout;
+    }
+}
+
+impl Drop for TextureInner {
+    fn drop(&mut self) {
+        // All submissions are waited upon, so the image is not in use anymore
+        let device = &self.device.device;
+        unsafe {
+            device.destroy_image_view(self.attachment_view, None);
+            device.destroy_image_view(self.view, None);
+            device.destroy_image(self.image, None);
+            device.free_memory(self.memory, None);
+        }
+    }
+}
+
+/// A handle to a Vulkan texture
+#[derive(Debug, Clone)]
+pub struct VulkanTexture(Arc<TextureInner>);
+
+impl Texture for VulkanTexture {
+    fn width(&self) -> u32 {
+        self.0.size.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.0.size.h as u32
+    }
+
+    fn size(&self) -> Size<i32, BufferCoords> {
+        self.0.size
+    }
+
+    fn format(&self) -> Option<DrmFourcc> {
+        Some(self.0.fourcc)
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug, Default)]
+struct ShmCache(Mutex<HashMap<usize, VulkanTexture>>);
+
+// Host visible buffer used for uploads and downloads
+#[derive(Debug)]
+struct HostBuffer {
+    device: Arc<DeviceInner>,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl HostBuffer {
+    fn new(device: &Arc<DeviceInner>, len: usize, usage: vk::BufferUsageFlags) -> Result<Self, VulkanError> {
+        let mut buffer = HostBuffer {
+            device: device.clone(),
+            buffer: vk::Buffer::null(),
+            memory: vk::DeviceMemory::null(),
+            ptr: std::ptr::null_mut(),
+            len,
+        };
+
+        let info = vk::BufferCreateInfo::default()
+            .size(len as vk::DeviceSize)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        unsafe {
+            buffer.buffer = device.device.create_buffer(&info, None)?;
+            let requirements = device.device.get_buffer_memory_requirements(buffer.buffer);
+            let memory_type = device.memory_type(
+                requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            let alloc_info = vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type);
+            buffer.memory = device.device.allocate_memory(&alloc_info, None)?;
+            device
+                .device
+                .bind_buffer_memory(buffer.buffer, buffer.memory, 0)?;
+            buffer.ptr =
+                device
+                    .device
+                    .map_memory(buffer.memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())?
+                    as *mut u8;
+        }
+
+        Ok(buffer)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: The memory is mapped for the lifetime of the buffer
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: The memory is mapped for the lifetime of the buffer
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for HostBuffer {
+    fn drop(&mut self) {
+        // Freeing the memory implicitly unmaps it
+        unsafe {
+            self.device.device.destroy_buffer(self.buffer, None);
+            self.device.device.free_memory(self.memory, None);
+        }
+    }
+}
+
+/// Texture mapping of a Vulkan texture
+#[derive(Debug)]
+pub struct VulkanMapping {
+    buffer: HostBuffer,
+    fourcc: DrmFourcc,
+    size: Size<i32, BufferCoords>,
+    flipped: bool,
+}
+
+impl Texture for VulkanMapping {
+    fn width(&self) -> u32 {
+        self.size.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.size.h as u32
+    }
+
+    fn size(&self) -> Size<i32, BufferCoords> {
+        self.size
+    }
+
+    fn format(&self) -> Option<DrmFourcc> {
+        Some(self.fourcc)
+    }
+}
+
+impl TextureMapping for VulkanMapping {
+    fn flipped(&self) -> bool {
+        self.flipped
+    }
+}
+
+// Push constants shared by all shaders
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct PushConstants {
+    // xy: position in normalized device coordinates, zw: texture coordinates
+    vertices: [[f32; 4]; 4],
+    // premultiplied color
+    color: [f32; 4],
+}
+
+impl PushConstants {
+    fn new(positions: [[f32; 2]; 4], tex_coords: [[f32; 2]; 4], color: [f32; 4]) -> Self {
+        let mut vertices = [[0.0; 4]; 4];
+        for (vertex, (position, tex_coord)) in
+            vertices.iter_mut().zip(positions.iter().zip(tex_coords.iter()))
+        {
+            *vertex = [position[0], position[1], tex_coord[0], tex_coord[1]];
+        }
+        PushConstants { vertices, color }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The struct is `repr(C)` and only consists of f32s without any padding
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FormatPipelines {
+    render_pass: vk::RenderPass,
+    texture: vk::Pipeline,
+    solid: vk::Pipeline,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DmabufFormat {
+    format: DrmFormat,
+    features: vk::FormatFeatureFlags,
+    plane_count: u32,
+}
+
+#[derive(Debug)]
+enum FrameOp {
+    Clear {
+        color: [f32; 4],
+        rects: Vec<vk::Rect2D>,
+    },
+    Draw {
+        texture: Option<(VulkanTexture, vk::Sampler)>,
+        constants: PushConstants,
+        scissors: Vec<vk::Rect2D>,
+    },
+}
+
+/// Handle to the currently rendered frame during [`VulkanRenderer::render`](Renderer::render).
+#[derive(Debug)]
+pub struct VulkanFrame<'frame> {
+    renderer: &'frame mut VulkanRenderer,
+    target: VulkanTexture,
+    pipelines: FormatPipelines,
+    framebuffer: vk::Framebuffer,
+
+    transform: Transform,
+    output_size: Size<i32, Physical>,
+    size: Size<i32, Physical>,
+    bounds: Size<i32, Physical>,
+
+    ops: Vec<FrameOp>,
+    finished: bool,
+}
+
+impl<'frame> VulkanFrame<'frame> {
+    fn scissors(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Vec<vk::Rect2D> {
+        damage
+            .iter()
+            .filter_map(|rect| {
+                let rect = Rectangle::from_loc_and_size(rect.loc + dst.loc, rect.size).intersection(dst)?;
+                rect_2d(self.transform.transform_rect_in(rect, &self.size), self.bounds)
+            })
+            .collect()
+    }
+
+    fn push_solid(&mut self, dst: Rectangle<i32, Physical>, scissors: Vec<vk::Rect2D>, color: [f32; 4]) {
+        if scissors.is_empty() {
+            return;
+        }
+
+        let constants = PushConstants::new(
+            quad_positions(dst, self.size, self.transform),
+            [[0.0; 2]; 4],
+            color,
+        );
+        self.ops.push(FrameOp::Draw {
+            texture: None,
+            constants,
+            scissors,
+        });
+    }
+
+    #[profiling::function]
+    fn finish_internal(&mut self) -> Result<SyncPoint, VulkanError> {
+        if self.finished {
+            return Ok(SyncPoint::signaled());
+        }
+        self.finished = true;
+
+        let ops = std::mem::take(&mut self.ops);
+        let result = self.renderer.submit_frame(
+            &self.target,
+            self.pipelines,
+            self.framebuffer,
+            self.output_size,
+            &ops,
+        );
+        unsafe {
+            self.renderer
+                .device
+                .device
+                .destroy_framebuffer(self.framebuffer, None)
+        };
+
+        result.map(|_| SyncPoint::signaled())
+    }
+}
+
+impl<'frame> Frame for VulkanFrame<'frame> {
+    type Error = VulkanError;
+
+    type TextureId = VulkanTexture;
+
+    fn id(&self) -> usize {
+        self.renderer.id.get()
+    }
+
+    #[profiling::function]
+    fn clear(&mut self, color: Color32F, at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        let rects = at
+            .iter()
+            .filter_map(|rect| rect_2d(self.transform.transform_rect_in(*rect, &self.size), self.bounds))
+            .collect::<Vec<_>>();
+        if !rects.is_empty() {
+            self.ops.push(FrameOp::Clear {
+                color: color.components(),
+                rects,
+            });
+        }
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn draw_solid(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        color: Color32F,
+    ) -> Result<(), Self::Error> {
+        let scissors = self.scissors(dst, damage);
+        if self.renderer.debug_flags.contains(DebugFlags::TINT) {
+            self.push_solid(dst, scissors.clone(), color.components());
+            self.push_solid(dst, scissors, TINT);
+        } else {
+            self.push_solid(dst, scissors, color.components());
+        }
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn render_texture_from_to(
+        &mut self,
+        texture: &Self::TextureId,
+        src: Rectangle<f64, BufferCoords>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), Self::Error> {
+        if !Arc::ptr_eq(&texture.0.device, &self.renderer.device) {
+            return Err(VulkanError::Unsupported);
+        }
+        if src.is_empty() || dst.is_empty() {
+            return Ok(());
+        }
+
+        let scissors = self.scissors(dst, damage);
+        if scissors.is_empty() {
+            return Ok(());
+        }
+
+        let constants = PushConstants::new(
+            quad_positions(dst, self.size, self.transform),
+            quad_tex_coords(src, texture.0.size, src_transform, texture.0.flipped),
+            [alpha; 4],
+        );
+        let sampler = self.renderer.sampler();
+        if self.renderer.debug_flags.contains(DebugFlags::TINT) {
+            self.ops.push(FrameOp::Draw {
+                texture: Some((texture.clone(), sampler)),
+                constants,
+                scissors: scissors.clone(),
+            });
+            self.push_solid(dst, scissors, TINT);
+        } else {
+            self.ops.push(FrameOp::Draw {
+                texture: Some((texture.clone(), sampler)),
+                constants,
+                scissors,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    #[profiling::function]
+    fn finish(mut self) -> Result<SyncPoint, Self::Error> {
+        self.finish_internal()
+    }
+
+    fn wait(&mut self, sync: &SyncPoint) -> Result<(), Self::Error> {
+        sync.wait().map_err(|_| VulkanError::SyncInterrupted)
+    }
+}
+
+impl<'frame> Drop for VulkanFrame<'frame> {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish_internal() {
+            warn!("Ignored error finishing VulkanFrame on drop: {}", err);
+        }
+    }
+}
+
+/// A renderer utilizing Vulkan
+#[derive(Debug)]
+pub struct VulkanRenderer {
+    device: Arc<DeviceInner>,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_pools: Vec<vk::DescriptorPool>,
+    current_pool: usize,
+    samplers: [vk::Sampler; 4],
+    vertex_shader: vk::ShaderModule,
+    texture_shader: vk::ShaderModule,
+    solid_shader: vk::ShaderModule,
+    pipelines: HashMap<vk::Format, FormatPipelines>,
+
+    mem_formats: Vec<DrmFourcc>,
+    render_formats: Vec<DrmFourcc>,
+    dmabuf_formats: Vec<DmabufFormat>,
+
+    target: Option<VulkanTexture>,
+    downscale_filter: TextureFilter,
+    upscale_filter: TextureFilter,
+    debug_flags: DebugFlags,
+    id: RendererId,
+
+    // caches
+    buffers: Vec<VulkanTexture>,
+    dmabuf_cache: Vec<VulkanTexture>,
+}
+
+impl VulkanRenderer {
+    /// Returns the list of device extensions required by the Vulkan renderer.
+    ///
+    /// This function may return a different list for each [`PhysicalDevice`], meaning each device should be
+    /// filtered using it's own call to this function.
+    pub fn required_extensions(phd: &PhysicalDevice) -> Vec<&'static CStr> {
+        let mut extensions = vec![
+            ext::image_drm_format_modifier::NAME,
+            ext::external_memory_dma_buf::NAME,
+            khr::external_memory_fd::NAME,
+            ext::queue_family_foreign::NAME,
+        ];
+
+        if phd.api_version() < Version::VERSION_1_2 {
+            // VK_EXT_image_drm_format_modifier requires VK_KHR_image_format_list.
+            // VK_KHR_image_format_list is part of the core API in Vulkan 1.2
+            extensions.push(khr::image_format_list::NAME);
+        }
+
+        extensions
+    }
+
+    /// Creates a new Vulkan renderer on a physical device
+    ///
+    /// Fails if the device does not support Vulkan 1.1, any of the [required extensions](Self::required_extensions)
+    /// or has no queue family supporting graphics operations.
+    pub fn new(phd: &PhysicalDevice) -> Result<VulkanRenderer, VulkanError> {
+        if phd.api_version() < Version::VERSION_1_1 {
+            return Err(VulkanError::UnsupportedVersion);
+        }
+
+        let extensions = Self::required_extensions(phd);
+        let missing = extensions
+            .iter()
+            .copied()
+            .filter(|extension| !phd.has_device_extension(extension))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(VulkanError::MissingExtensions(missing));
+        }
+        let extension_pointers = extensions.iter().copied().map(CStr::as_ptr).collect::<Vec<_>>();
+
+        let instance = phd.instance().handle();
+        let queue_family_index = unsafe { instance.get_physical_device_queue_family_properties(phd.handle()) }
+            .iter()
+            .position(|properties| properties.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .ok_or(VulkanError::NoGraphicsQueue)? as u32;
+
+        let mut mem_formats = Vec::new();
+        let mut render_formats = Vec::new();
+        let mut dmabuf_formats = Vec::new();
+        for &fourcc in known_formats() {
+            let Some(format) = vk_format(fourcc) else {
+                continue;
+            };
+
+            let features = unsafe { instance.get_physical_device_format_properties(phd.handle(), format) }
+                .optimal_tiling_features;
+            let transfer = vk::FormatFeatureFlags::TRANSFER_SRC | vk::FormatFeatureFlags::TRANSFER_DST;
+            if features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE | transfer) {
+                mem_formats.push(fourcc);
+                if features.contains(vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND) {
+                    render_formats.push(fourcc);
+                }
+            }
+
+            // VK_EXT_image_drm_format_modifier is supported, which was checked above
+            for properties in phd.get_format_modifier_properties(format).unwrap_or_default() {
+                dmabuf_formats.push(DmabufFormat {
+                    format: DrmFormat {
+                        code: fourcc,
+                        modifier: DrmModifier::from(properties.drm_format_modifier),
+                    },
+                    features: properties.drm_format_modifier_tiling_features,
+                    plane_count: properties.drm_format_modifier_plane_count,
+                });
+            }
+        }
+
+        let queue_priorities = [1.0];
+        let queue_create_info = [vk::DeviceQueueCreateInfo::default()
+            .queue_family_index(queue_family_index)
+            .queue_priorities(&queue_priorities)];
+        let create_info = vk::DeviceCreateInfo::default()
+            .enabled_extension_names(&extension_pointers)
+            .queue_create_infos(&queue_create_info);
+        let device = unsafe { instance.create_device(phd.handle(), &create_info, None) }?;
+
+        let device = Arc::new(DeviceInner {
+            phd: phd.clone(),
+            queue: unsafe { device.get_device_queue(queue_family_index, 0) },
+            queue_family_index,
+            memory_properties: unsafe { instance.get_physical_device_memory_properties(phd.handle()) },
+            external_memory_fd: khr::external_memory_fd::Device::new(instance, &device),
+            device,
+        });
+
+        // Handles are created by `init`, destroying null handles on failure is a no-op
+        let mut renderer = VulkanRenderer {
+            device,
+            command_pool: vk::CommandPool::null(),
+            command_buffer: vk::CommandBuffer::null(),
+            fence: vk::Fence::null(),
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            descriptor_pools: Vec::new(),
+            current_pool: 0,
+            samplers: [vk::Sampler::null(); 4],
+            vertex_shader: vk::ShaderModule::null(),
+            texture_shader: vk::ShaderModule::null(),
+            solid_shader: vk::ShaderModule::null(),
+            pipelines: HashMap::new(),
+
+            mem_formats,
+            render_formats,
+            dmabuf_formats,
+
+            target: None,
+            downscale_filter: TextureFilter::Linear,
+            upscale_filter: TextureFilter::Linear,
+            debug_flags: DebugFlags::empty(),
+            id: RendererId::new(),
+
+            buffers: Vec::new(),
+            dmabuf_cache: Vec::new(),
+        };
+        renderer.init()?;
+
+        Ok(renderer)
+    }
+
+    /// Returns the [`PhysicalDevice`] this renderer was created with.
+    pub fn physical_device(&self) -> &PhysicalDevice {
+        &self.device.phd
+    }
+
+    fn init(&mut self) -> Result<(), VulkanError> {
+        let device = &self.device.device;
+        unsafe {
+            let pool_info = vk::CommandPoolCreateInfo::default()
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+                .queue_family_index(self.device.queue_family_index);
+            self.command_pool = device.create_command_pool(&pool_info, None)?;
+            let alloc_info = vk::CommandBufferAllocateInfo::default()
+                .command_pool(self.command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            self.command_buffer = device.allocate_command_buffers(&alloc_info)?[0];
+            self.fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+
+            let bindings = [vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT)];
+            let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+            self.descriptor_set_layout = device.create_descriptor_set_layout(&layout_info, None)?;
+
+            let set_layouts = [self.descriptor_set_layout];
+            let push_constant_ranges = [vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: std::mem::size_of::<PushConstants>() as u32,
+            }];
+            let layout_info = vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&set_layouts)
+                .push_constant_ranges(&push_constant_ranges);
+            self.pipeline_layout = device.create_pipeline_layout(&layout_info, None)?;
+
+            for (sampler, (upscale, downscale)) in self.samplers.iter_mut().zip(FILTERS) {
+                let filter = |filter| match filter {
+                    TextureFilter::Linear => vk::Filter::LINEAR,
+                    TextureFilter::Nearest => vk::Filter::NEAREST,
+                };
+                let sampler_info = vk::SamplerCreateInfo::default()
+                    .mag_filter(filter(upscale))
+                    .min_filter(filter(downscale))
+                    .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .max_lod(0.25);
+                *sampler = device.create_sampler(&sampler_info, None)?;
+            }
+
+            self.vertex_shader = create_shader_module(device, include_bytes!("shaders/quad.vert.spv"))?;
+            self.texture_shader = create_shader_module(device, include_bytes!("shaders/texture.frag.spv"))?;
+            self.solid_shader = create_shader_module(device, include_bytes!("shaders/solid.frag.spv"))?;
+        }
+
+        Ok(())
+    }
+
+    fn sampler(&self) -> vk::Sampler {
+        let index = FILTERS
+            .iter()
+            .position(|filters| *filters == (self.upscale_filter, self.downscale_filter))
+            .unwrap();
+        self.samplers[index]
+    }
+
+    fn pipelines(&mut self, format: vk::Format) -> Result<FormatPipelines, VulkanError> {
+        if let Some(pipelines) = self.pipelines.get(&format) {
+            return Ok(*pipelines);
+        }
+
+        let pipelines = self.create_pipelines(format)?;
+        self.pipelines.insert(format, pipelines);
+        Ok(pipelines)
+    }
+
+    fn create_pipelines(&self, format: vk::Format) -> Result<FormatPipelines, VulkanError> {
+        let device = &self.device.device;
+
+        // The contents of the target are loaded, so damaged rendering works
+        let attachments = [vk::AttachmentDescription::default()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let color_attachments = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let subpasses = [vk::SubpassDescription::default()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachments)];
+        let render_pass_info = vk::RenderPassCreateInfo::default()
+            .attachments(&attachments)
+            .subpasses(&subpasses);
+        let render_pass = unsafe { device.create_render_pass(&render_pass_info, None) }?;
+
+        let stages = [self.texture_shader, self.solid_shader].map(|fragment_shader| {
+            [
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(self.vertex_shader)
+                    .name(ENTRY_POINT),
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(fragment_shader)
+                    .name(ENTRY_POINT),
+            ]
+        });
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::default();
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let viewport = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .line_width(1.0);
+        let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        // premultiplied alpha blending
+        let blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .color_write_mask(vk::ColorComponentFlags::RGBA)];
+        let color_blend = vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic = vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let pipeline_infos = stages
+            .iter()
+            .map(|stages| {
+                vk::GraphicsPipelineCreateInfo::default()
+                    .stages(stages)
+                    .vertex_input_state(&vertex_input)
+                    .input_assembly_state(&input_assembly)
+                    .viewport_state(&viewport)
+                    .rasterization_state(&rasterization)
+                    .multisample_state(&multisample)
+                    .color_blend_state(&color_blend)
+                    .dynamic_state(&dynamic)
+                    .layout(self.pipeline_layout)
+                    .render_pass(render_pass)
+                    .subpass(0)
+            })
+            .collect::<Vec<_>>();
+
+        match unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &pipeline_infos, None) } {
+            Ok(pipelines) => Ok(FormatPipelines {
+                render_pass,
+                texture: pipelines[0],
+                solid: pipelines[1],
+            }),
+            Err((pipelines, err)) => {
+                unsafe {
+                    for pipeline in pipelines {
+                        device.destroy_pipeline(pipeline, None);
+                    }
+                    device.destroy_render_pass(render_pass, None);
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    fn descriptor_set(
+        &mut self,
+        texture: &VulkanTexture,
+        sampler: vk::Sampler,
+    ) -> Result<vk::DescriptorSet, VulkanError> {
+        let device = &self.device.device;
+        let set_layouts = [self.descriptor_set_layout];
+        let set = loop {
+            let Some(&pool) = self.descriptor_pools.get(self.current_pool) else {
+                let pool_sizes = [vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: DESCRIPTOR_POOL_SIZE,
+                }];
+                let pool_info = vk::DescriptorPoolCreateInfo::default()
+                    .max_sets(DESCRIPTOR_POOL_SIZE)
+                    .pool_sizes(&pool_sizes);
+                self.descriptor_pools
+                    .push(unsafe { device.create_descriptor_pool(&pool_info, None) }?);
+                continue;
+            };
+
+            let alloc_info = vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(pool)
+                .set_layouts(&set_layouts);
+            match unsafe { device.allocate_descriptor_sets(&alloc_info) } {
+                Ok(sets) => break sets[0],
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY) | Err(vk::Result::ERROR_FRAGMENTED_POOL) => {
+                    self.current_pool += 1;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let image_info = [vk::DescriptorImageInfo {
+            sampler,
+            image_view: texture.0.view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }];
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        unsafe { device.update_descriptor_sets(&[write], &[]) };
+
+        Ok(set)
+    }
+
+    // Records commands using `record` and waits for their execution
+    fn submit(&self, record: impl FnOnce(&ash::Device, vk::CommandBuffer)) -> Result<(), VulkanError> {
+        let device = &self.device.device;
+        let command_buffers = [self.command_buffer];
+        unsafe {
+            device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())?;
+            let begin_info =
+                vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            device.begin_command_buffer(self.command_buffer, &begin_info)?;
+            record(device, self.command_buffer);
+            device.end_command_buffer(self.command_buffer)?;
+
+            let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+            device.queue_submit(self.device.queue, &[submit_info], self.fence)?;
+            let result = device.wait_for_fences(&[self.fence], true, u64::MAX);
+            device.reset_fences(&[self.fence])?;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn submit_frame(
+        &mut self,
+        target: &VulkanTexture,
+        pipelines: FormatPipelines,
+        framebuffer: vk::Framebuffer,
+        viewport_size: Size<i32, Physical>,
+        ops: &[FrameOp],
+    ) -> Result<(), VulkanError> {
+        let result = self.record_frame(target, pipelines, framebuffer, viewport_size, ops);
+        for pool in &self.descriptor_pools {
+            if let Err(err) = unsafe {
+                self.device
+                    .device
+                    .reset_descriptor_pool(*pool, vk::DescriptorPoolResetFlags::empty())
+            } {
+                warn!(?err, "Failed to reset descriptor pool");
+            }
+        }
+        self.current_pool = 0;
+        result
+    }
+
+    fn record_frame(
+        &mut self,
+        target: &VulkanTexture,
+        pipelines: FormatPipelines,
+        framebuffer: vk::Framebuffer,
+        viewport_size: Size<i32, Physical>,
+        ops: &[FrameOp],
+    ) -> Result<(), VulkanError> {
+        let mut textures: Vec<&VulkanTexture> = Vec::new();
+        let mut descriptor_sets = Vec::with_capacity(ops.len());
+        for op in ops {
+            let set = match op {
+                FrameOp::Draw {
+                    texture: Some((texture, sampler)),
+                    ..
+                } => {
+                    if !textures.iter().any(|other| Arc::ptr_eq(&other.0, &texture.0)) {
+                        textures.push(texture);
+                    }
+                    Some(self.descriptor_set(texture, *sampler)?)
+                }
+                _ => None,
+            };
+            descriptor_sets.push(set);
+        }
+
+        let read_only = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        let attachment = vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+        let acquire = textures
+            .iter()
+            .map(|texture| texture.0.acquire(read_only))
+            .chain(std::iter::once(target.0.acquire(attachment)))
+            .collect::<Vec<_>>();
+        let release = textures
+            .iter()
+            .filter_map(|texture| texture.0.release(read_only))
+            .chain(target.0.release(attachment))
+            .collect::<Vec<_>>();
+
+        let pipeline_layout = self.pipeline_layout;
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: extent_2d(target.0.size),
+        };
+        self.submit(|device, cmd| unsafe {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &acquire,
+            );
+
+            let begin_info = vk::RenderPassBeginInfo::default()
+                .render_pass(pipelines.render_pass)
+                .framebuffer(framebuffer)
+                .render_area(render_area);
+            device.cmd_begin_render_pass(cmd, &begin_info, vk::SubpassContents::INLINE);
+            device.cmd_set_viewport(
+                cmd,
+                0,
+                &[vk::Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: viewport_size.w as f32,
+                    height: viewport_size.h as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }],
+            );
+
+            for (op, descriptor_set) in ops.iter().zip(descriptor_sets) {
+                match op {
+                    FrameOp::Clear { color, rects } => {
+                        let attachment = vk::ClearAttachment {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            color_attachment: 0,
+                            clear_value: vk::ClearValue {
+                                color: vk::ClearColorValue { float32: *color },
+                            },
+                        };
+                        let rects = rects
+                            .iter()
+                            .map(|rect| vk::ClearRect {
+                                rect: *rect,
+                                base_array_layer: 0,
+                                layer_count: 1,
+                            })
+                            .collect::<Vec<_>>();
+                        device.cmd_clear_attachments(cmd, &[attachment], &rects);
+                    }
+                    FrameOp::Draw {
+                        constants, scissors, ..
+                    } => {
+                        if let Some(descriptor_set) = descriptor_set {
+                            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipelines.texture);
+                            device.cmd_bind_descriptor_sets(
+                                cmd,
+                                vk::PipelineBindPoint::GRAPHICS,
+                                pipeline_layout,
+                                0,
+                                &[descriptor_set],
+                                &[],
+                            );
+                        } else {
+                            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipelines.solid);
+                        }
+                        device.cmd_push_constants(
+                            cmd,
+                            pipeline_layout,
+                            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                            0,
+                            constants.as_bytes(),
+                        );
+                        for scissor in scissors {
+                            device.cmd_set_scissor(cmd, 0, &[*scissor]);
+                            device.cmd_draw(cmd, 4, 1, 0, 0);
+                        }
+                    }
+                }
+            }
+
+            device.cmd_end_render_pass(cmd);
+            if !release.is_empty() {
+                device.cmd_pipeline_barrier(
+                    cmd,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &release,
+                );
+            }
+        })?;
+
+        for texture in textures {
+            texture.0.set_layout(read_only);
+        }
+        target.0.set_layout(attachment);
+
+        Ok(())
+    }
+
+    fn create_texture(
+        &self,
+        fourcc: DrmFourcc,
+        size: Size<i32, BufferCoords>,
+        usage: vk::ImageUsageFlags,
+        flipped: bool,
+    ) -> Result<VulkanTexture, VulkanError> {
+        let device = &self.device.device;
+        let mut inner = TextureInner::new(&self.device, fourcc, size, usage)?;
+        inner.flipped = flipped;
+
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(inner.format)
+            .extent(extent_3d(size))
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        unsafe {
+            inner.image = device.create_image(&image_info, None)?;
+            let requirements = device.get_image_memory_requirements(inner.image);
+            let memory_type = self.device.memory_type(
+                requirements.memory_type_bits,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            let alloc_info = vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type);
+            inner.memory = device.allocate_memory(&alloc_info, None)?;
+            device.bind_image_memory(inner.image, inner.memory, 0)?;
+        }
+        inner.create_views()?;
+
+        Ok(VulkanTexture(Arc::new(inner)))
+    }
+
+    fn import_dmabuf_texture(
+        &self,
+        dmabuf: &Dmabuf,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<VulkanTexture, VulkanError> {
+        let format = dmabuf.format();
+        let entry = self
+            .dmabuf_formats
+            .iter()
+            .find(|entry| entry.format == format && entry.features.contains(format_features(usage)))
+            .ok_or(VulkanError::UnsupportedFormat(format))?;
+        if dmabuf.num_planes() != entry.plane_count as usize {
+            return Err(VulkanError::UnsupportedFormat(format));
+        }
+
+        // All planes need to be backed by the same memory object
+        let inode = |fd: BorrowedFd<'_>| rustix::fs::fstat(fd).ok().map(|stat| (stat.st_dev, stat.st_ino));
+        let inodes = dmabuf.handles().map(inode).collect::<Vec<_>>();
+        if inodes.iter().any(|inode| inode.is_none() || *inode != inodes[0]) {
+            return Err(VulkanError::DisjointPlanes);
+        }
+
+        let usage = if entry.features.contains(vk::FormatFeatureFlags::TRANSFER_SRC) {
+            usage | vk::ImageUsageFlags::TRANSFER_SRC
+        } else {
+            usage
+        };
+        let mut inner = TextureInner::new(&self.device, format.code, dmabuf.size(), usage)?;
+        inner.dmabuf = Some(dmabuf.weak());
+        inner.flipped = dmabuf.y_inverted();
+
+        let plane_layouts = dmabuf
+            .offsets()
+            .zip(dmabuf.strides())
+            .map(|(offset, stride)| vk::SubresourceLayout {
+                offset: offset as vk::DeviceSize,
+                size: 0,
+                row_pitch: stride as vk::DeviceSize,
+                array_pitch: 0,
+                depth_pitch: 0,
+            })
+            .collect::<Vec<_>>();
+        let mut modifier_info = vk::ImageDrmFormatModifierExplicitCreateInfoEXT::default()
+            .drm_format_modifier(format.modifier.into())
+            .plane_layouts(&plane_layouts);
+        let mut external_memory_info = vk::ExternalMemoryImageCreateInfo::default()
+            .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(inner.format)
+            .extent(extent_3d(inner.size))
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .push_next(&mut modifier_info)
+            .push_next(&mut external_memory_info);
+
+        let device = &self.device.device;
+        let fd = dmabuf.handles().next().unwrap();
+        unsafe {
+            inner.image = device.create_image(&image_info, None)?;
+            let requirements = device.get_image_memory_requirements(inner.image);
+            let mut fd_properties = vk::MemoryFdPropertiesKHR::default();
+            self.device.external_memory_fd.get_memory_fd_properties(
+                vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+                fd.as_raw_fd(),
+                &mut fd_properties,
+            )?;
+            let memory_type = self.device.memory_type(
+                requirements.memory_type_bits & fd_properties.memory_type_bits,
+                vk::MemoryPropertyFlags::empty(),
+            )?;
+
+            let fd = fd.try_clone_to_owned().map_err(VulkanError::DuplicateFd)?;
+            let mut import_info = vk::ImportMemoryFdInfoKHR::default()
+                .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
+                .fd(fd.as_raw_fd());
+            let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default().image(inner.image);
+            let alloc_info = vk::MemoryAllocateInfo::default()
+                .allocation_size(requirements.size)
+                .memory_type_index(memory_type)
+                .push_next(&mut import_info)
+                .push_next(&mut dedicated_info);
+            inner.memory = device.allocate_memory(&alloc_info, None)?;
+            // A successful import transfers the ownership of the file descriptor to the driver
+            let _ = fd.into_raw_fd();
+            device.bind_image_memory(inner.image, inner.memory, 0)?;
+        }
+        inner.create_views()?;
+
+        Ok(VulkanTexture(Arc::new(inner)))
+    }
+
+    // Uploads `regions` of `data` with the given stride into a texture
+    fn upload(
+        &self,
+        texture: &VulkanTexture,
+        data: &[u8],
+        stride: usize,
+        regions: &[Rectangle<i32, BufferCoords>],
+    ) -> Result<(), VulkanError> {
+        if texture.0.dmabuf.is_some() {
+            return Err(VulkanError::Unsupported);
+        }
+
+        let bpp = get_bpp(texture.0.fourcc).ok_or(VulkanError::UnsupportedPixelFormat(texture.0.fourcc))? / 8;
+        let bounds = Rectangle::from_loc_and_size((0, 0), texture.0.size);
+        let regions = regions
+            .iter()
+            .filter_map(|region| region.intersection(bounds))
+            .filter(|region| !region.is_empty())
+            .collect::<Vec<_>>();
+        if regions.is_empty() {
+            return Ok(());
+        }
+
+        let len = regions
+            .iter()
+            .map(|region| region.size.w as usize * region.size.h as usize * bpp)
+            .sum();
+        let mut staging = HostBuffer::new(&self.device, len, vk::BufferUsageFlags::TRANSFER_SRC)?;
+        let mut copies = Vec::with_capacity(regions.len());
+        let mut offset = 0;
+        for region in &regions {
+            let row_len = region.size.w as usize * bpp;
+            for row in 0..region.size.h as usize {
+                let start = (region.loc.y as usize + row) * stride + region.loc.x as usize * bpp;
+                let src = data
+                    .get(start..start + row_len)
+                    .ok_or(VulkanError::IncompleteBuffer {
+                        expected: start + row_len,
+                        actual: data.len(),
+                    })?;
+                let dst = offset + row * row_len;
+                staging.as_mut_slice()[dst..dst + row_len].copy_from_slice(src);
+            }
+
+            copies.push(vk::BufferImageCopy {
+                buffer_offset: offset as vk::DeviceSize,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: COLOR_LAYERS,
+                image_offset: vk::Offset3D {
+                    x: region.loc.x,
+                    y: region.loc.y,
+                    z: 0,
+                },
+                image_extent: extent_3d(region.size),
+            });
+            offset += row_len * region.size.h as usize;
+        }
+
+        let acquire = texture.0.acquire(vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+        let read_only = image_barrier(
+            texture.0.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::QUEUE_FAMILY_IGNORED,
+            vk::QUEUE_FAMILY_IGNORED,
+        );
+        self.submit(|device, cmd| unsafe {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[acquire],
+            );
+            device.cmd_copy_buffer_to_image(
+                cmd,
+                staging.buffer,
+                texture.0.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copies,
+            );
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[read_only],
+            );
+        })?;
+        texture.0.set_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        Ok(())
+    }
+
+    // Copies a region of a texture into host visible memory
+    fn download(
+        &self,
+        texture: &VulkanTexture,
+        region: Rectangle<i32, BufferCoords>,
+        format: DrmFourcc,
+    ) -> Result<VulkanMapping, VulkanError> {
+        if vk_format(format) != Some(texture.0.format) {
+            return Err(VulkanError::UnsupportedPixelFormat(format));
+        }
+        if !texture.0.usage.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+            return Err(VulkanError::Unsupported);
+        }
+        let bounds = Rectangle::from_loc_and_size((0, 0), texture.0.size);
+        if region.is_empty() || !bounds.contains_rect(region) {
+            return Err(VulkanError::OutOfBounds);
+        }
+
+        let bpp = get_bpp(format).ok_or(VulkanError::UnsupportedPixelFormat(format))? / 8;
+        let len = region.size.w as usize * region.size.h as usize * bpp;
+        let buffer = HostBuffer::new(&self.device, len, vk::BufferUsageFlags::TRANSFER_DST)?;
+
+        let layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+        let acquire = texture.0.acquire(layout);
+        let release = texture.0.release(layout).into_iter().collect::<Vec<_>>();
+        let copy = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: COLOR_LAYERS,
+            image_offset: vk::Offset3D {
+                x: region.loc.x,
+                y: region.loc.y,
+                z: 0,
+            },
+            image_extent: extent_3d(region.size),
+        };
+        self.submit(|device, cmd| unsafe {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[acquire],
+            );
+            device.cmd_copy_image_to_buffer(cmd, texture.0.image, layout, buffer.buffer, &[copy]);
+            let host_read = vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ);
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[host_read],
+                &[],
+                &[],
+            );
+            if !release.is_empty() {
+                device.cmd_pipeline_barrier(
+                    cmd,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &release,
+                );
+            }
+        })?;
+        texture.0.set_layout(layout);
+
+        Ok(VulkanMapping {
+            buffer,
+            fourcc: format,
+            size: region.size,
+            flipped: texture.0.flipped,
+        })
+    }
+
+    fn supported_dmabuf_formats(&self, usage: vk::ImageUsageFlags) -> FormatSet {
+        let features = format_features(usage);
+        self.dmabuf_formats
+            .iter()
+            .filter(|entry| entry.features.contains(features))
+            .map(|entry| entry.format)
+            .collect()
+    }
+
+    fn cleanup(&mut self) {
+        let alive = |texture: &VulkanTexture| {
+            texture
+                .0
+                .dmabuf
+                .as_ref()
+                .map(|dmabuf| !dmabuf.is_gone())
+                .unwrap_or(false)
+        };
+        self.dmabuf_cache.retain(alive);
+        self.buffers.retain(alive);
+    }
+}
+
+impl Drop for VulkanRenderer {
+    fn drop(&mut self) {
+        let device = &self.device.device;
+        unsafe {
+            let _ = device.device_wait_idle();
+            for pipelines in self.pipelines.values() {
+                device.destroy_pipeline(pipelines.texture, None);
+                device.destroy_pipeline(pipelines.solid, None);
+                device.destroy_render_pass(pipelines.render_pass, None);
+            }
+            for pool in &self.descriptor_pools {
+                device.destroy_descriptor_pool(*pool, None);
+            }
+            for sampler in self.samplers {
+                device.destroy_sampler(sampler, None);
+            }
+            device.destroy_shader_module(self.vertex_shader, None);
+            device.destroy_shader_module(self.texture_shader, None);
+            device.destroy_shader_module(self.solid_shader, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_fence(self.fence, None);
+            device.destroy_command_pool(self.command_pool, None);
+        }
+    }
+}
+
+impl Renderer for VulkanRenderer {
+    type Error = VulkanError;
+
+    type TextureId = VulkanTexture;
+
+    type Frame<'frame> = VulkanFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.downscale_filter = filter;
+        Ok(())
+    }
+
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.upscale_filter = filter;
+        Ok(())
+    }
+
+    fn set_debug_flags(&mut self, flags: DebugFlags) {
+        self.debug_flags = flags;
+    }
+
+    fn debug_flags(&self) -> DebugFlags {
+        self.debug_flags
+    }
+
+    #[profiling::function]
+    fn render(
+        &mut self,
+        output_size: Size<i32, Physical>,
+        dst_transform: Transform,
+    ) -> Result<VulkanFrame<'_>, Self::Error> {
+        self.cleanup();
+
+        let target = self.target.clone().ok_or(VulkanError::NoTargetBound)?;
+        let pipelines = self.pipelines(target.0.format)?;
+        let extent = extent_2d(target.0.size);
+        let attachments = [target.0.attachment_view];
+        let framebuffer_info = vk::FramebufferCreateInfo::default()
+            .render_pass(pipelines.render_pass)
+            .attachments(&attachments)
+            .width(extent.width)
+            .height(extent.height)
+            .layers(1);
+        let framebuffer = unsafe { self.device.device.create_framebuffer(&framebuffer_info, None) }?;
+
+        Ok(VulkanFrame {
+            renderer: self,
+            bounds: (
+                output_size.w.min(target.0.size.w),
+                output_size.h.min(target.0.size.h),
+            )
+                .into(),
+            target,
+            pipelines,
+            framebuffer,
+
+            transform: dst_transform,
+            output_size,
+            size: dst_transform.transform_size(output_size),
+
+            ops: Vec::new(),
+            finished: false,
+        })
+    }
+
+    fn wait(&mut self, sync: &SyncPoint) -> Result<(), Self::Error> {
+        sync.wait().map_err(|_| VulkanError::SyncInterrupted)
+    }
+
+    fn cleanup_texture_cache(&mut self) -> Result<(), Self::Error> {
+        self.cleanup();
+        Ok(())
+    }
+    fn renderer_capabilities(&self) -> RendererCapabilities {
+        let max = self.device.phd.limits().max_image_dimension2_d as i32;
+        let texture_formats = self.dmabuf_formats();
+        let render_formats = Bind::<Dmabuf>::supported_formats(self).unwrap_or_default();
+        RendererCapabilities {
+            max_texture_size: Some((max, max).into()),
+            mem_formats: self.mem_formats().collect(),
+            external_only_formats: RendererCapabilities::external_only(&texture_formats, &render_formats),
+            dmabuf_texture_formats: texture_formats,
+            dmabuf_render_formats: render_formats,
+            ..Default::default()
+        }
+    }
+}
+
+impl ImportMem for VulkanRenderer {
+    #[profiling::function]
+    fn import_memory(
+        &mut self,
+        data: &[u8],
+        format: DrmFourcc,
+        size: Size<i32, BufferCoords>,
+        flipped: bool,
+    ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        if !self.mem_formats.contains(&format) {
+            return Err(VulkanError::UnsupportedPixelFormat(format));
+        }
+        let bpp = get_bpp(format).ok_or(VulkanError::UnsupportedPixelFormat(format))? / 8;
+
+        let texture = self.create_texture(format, size, mem_usage(), flipped)?;
+        self.upload(
+            &texture,
+            data,
+            size.w as usize * bpp,
+            &[Rectangle::from_loc_and_size((0, 0), size)],
+        )?;
+        Ok(texture)
+    }
+
+    #[profiling::function]
+    fn update_memory(
+        &mut self,
+        texture: &<Self as Renderer>::TextureId,
+        data: &[u8],
+        region: Rectangle<i32, BufferCoords>,
+    ) -> Result<(), <Self as Renderer>::Error> {
+        if !Arc::ptr_eq(&texture.0.device, &self.device) {
+            return Err(VulkanError::Unsupported);
+        }
+        if !Rectangle::from_loc_and_size((0, 0), texture.0.size).contains_rect(region) {
+            return Err(VulkanError::OutOfBounds);
+        }
+        let bpp = get_bpp(texture.0.fourcc).ok_or(VulkanError::UnsupportedPixelFormat(texture.0.fourcc))? / 8;
+
+        self.upload(texture, data, texture.0.size.w as usize * bpp, &[region])
+    }
+
+    fn mem_formats(&self) -> Box<dyn Iterator<Item = DrmFourcc>> {
+        Box::new(self.mem_formats.clone().into_iter())
+    }
+}
+
+impl ExportMem for VulkanRenderer {
+    type TextureMapping = VulkanMapping;
+
+    #[profiling::function]
+    fn copy_framebuffer(
+        &mut self,
+        region: Rectangle<i32, BufferCoords>,
+        format: DrmFourcc,
+    ) -> Result<Self::TextureMapping, <Self as Renderer>::Error> {
+        let target = self.target.as_ref().ok_or(VulkanError::NoTargetBound)?;
+        self.download(target, region, format)
+    }
+
+    #[profiling::function]
+    fn copy_texture(
+        &mut self,
+        texture: &Self::TextureId,
+        region: Rectangle<i32, BufferCoords>,
+        format: DrmFourcc,
+    ) -> Result<Self::TextureMapping, Self::Error> {
+        if !Arc::ptr_eq(&texture.0.device, &self.device) {
+            return Err(VulkanError::Unsupported);
+        }
+        self.download(texture, region, format)
+    }
+
+    fn can_read_texture(&mut self, texture: &Self::TextureId) -> Result<bool, Self::Error> {
+        Ok(Arc::ptr_eq(&texture.0.device, &self.device)
+            && texture.0.usage.contains(vk::ImageUsageFlags::TRANSFER_SRC))
+    }
+
+    fn map_texture<'a>(
+        &mut self,
+        texture_mapping: &'a Self::TextureMapping,
+    ) -> Result<&'a [u8], <Self as Renderer>::Error> {
+        Ok(texture_mapping.buffer.as_slice())
+    }
+}
+
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+impl ImportEgl for VulkanRenderer {
+    fn import_egl_buffer(
+        &mut self,
+        _buffer: &wl_buffer::WlBuffer,
+        _surface: Option<&SurfaceData>,
+        _damage: &[Rectangle<i32, BufferCoords>],
+    ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        Err(VulkanError::Unsupported)
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportMemWl for VulkanRenderer {
+    /// Imports a shm buffer
+    ///
+    /// If a surface is provided, its texture is reused as long as the size and format of the
+    /// buffer do not change and only the damaged regions are uploaded.
+    #[profiling::function]
+    fn import_shm_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        surface: Option<&SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoords>],
+    ) -> Result<VulkanTexture, VulkanError> {
+        let cache = surface.map(|surface| {
+            surface.data_map.insert_if_missing_threadsafe(ShmCache::default);
+            surface.data_map.get::<ShmCache>().unwrap()
+        });
+
+        let texture = shm::with_buffer_contents(buffer, |ptr, len, data| {
+            let fourcc = shm::shm_format_to_fourcc(data.format)
+                .ok_or(VulkanError::UnsupportedWlPixelFormat(data.format))?;
+            if !self.mem_formats.contains(&fourcc) {
+                return Err(VulkanError::UnsupportedWlPixelFormat(data.format));
+            }
+
+            let offset = data.offset as usize;
+            let stride = data.stride as usize;
+            let expected_len = offset + stride * data.height as usize;
+            if len < expected_len {
+                return Err(VulkanError::IncompleteBuffer {
+                    expected: expected_len,
+                    actual: len,
+                });
+            }
+            // SAFETY: The pool was checked to contain the whole buffer
+            let contents = unsafe { std::slice::from_raw_parts(ptr.add(offset), expected_len - offset) };
+
+            let size = Size::from((data.width, data.height));
+            let cached = cache
+                .and_then(|cache| cache.0.lock().unwrap().get(&self.id.get()).cloned())
+                .filter(|texture| texture.0.size == size && texture.0.fourcc == fourcc);
+            let (texture, regions) = match cached {
+                Some(texture) => (texture, super::utils::texture_upload_regions(damage, size)),
+                None => (
+                    self.create_texture(fourcc, size, mem_usage(), false)?,
+                    vec![Rectangle::from_loc_and_size((0, 0), size)],
+                ),
+            };
+            self.upload(&texture, contents, stride, &regions)?;
+
+            Ok(texture)
+        })??;
+
+        if let Some(cache) = cache {
+            cache.0.lock().unwrap().insert(self.id.get(), texture.clone());
+        }
+        Ok(texture)
+    }
+}
+
+impl ImportDma for VulkanRenderer {
+    #[profiling::function]
+    fn import_dmabuf(
+        &mut self,
+        dmabuf: &Dmabuf,
+        _damage: Option<&[Rectangle<i32, BufferCoords>]>,
+    ) -> Result<<Self as Renderer>::TextureId, <Self as Renderer>::Error> {
+        if let Some(texture) = existing_dmabuf(&self.dmabuf_cache, dmabuf) {
+            return Ok(texture);
+        }
+
+        let texture = self.import_dmabuf_texture(dmabuf, vk::ImageUsageFlags::SAMPLED)?;
+        self.dmabuf_cache.push(texture.clone());
+        Ok(texture)
+    }
+
+    fn dmabuf_formats(&self) -> FormatSet {
+        self.supported_dmabuf_formats(vk::ImageUsageFlags::SAMPLED)
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportDmaWl for VulkanRenderer {}
+
+impl Unbind for VulkanRenderer {
+    #[profiling::function]
+    fn unbind(&mut self) -> Result<(), <Self as Renderer>::Error> {
+        self.target = None;
+        Ok(())
+    }
+}
+
+impl Bind<Dmabuf> for VulkanRenderer {
+    #[profiling::function]
+    fn bind(&mut self, target: Dmabuf) -> Result<(), <Self as Renderer>::Error> {
+        let texture = match existing_dmabuf(&self.buffers, &target) {
+            Some(texture) => texture,
+            None => {
+                let texture = self.import_dmabuf_texture(&target, vk::ImageUsageFlags::COLOR_ATTACHMENT)?;
+                self.buffers.push(texture.clone());
+                texture
+            }
+        };
+
+        self.target = Some(texture);
+        Ok(())
+    }
+
+    fn supported_formats(&self) -> Option<FormatSet> {
+        Some(self.supported_dmabuf_formats(vk::ImageUsageFlags::COLOR_ATTACHMENT))
+    }
+}
+
+impl<'a> Bind<&'a Slot<Dmabuf>> for VulkanRenderer {
+    #[profiling::function]
+    fn bind(&mut self, slot: &'a Slot<Dmabuf>) -> Result<(), <Self as Renderer>::Error> {
+        self.bind((*slot).clone())
+    }
+
+    fn supported_formats(&self) -> Option<FormatSet> {
+        <Self as Bind<Dmabuf>>::supported_formats(self)
+    }
+}
+
+impl Offscreen<VulkanTexture> for VulkanRenderer {
+    #[profiling::function]
+    fn create_buffer(
+        &mut self,
+        format: DrmFourcc,
+        size: Size<i32, BufferCoords>,
+    ) -> Result<VulkanTexture, <Self as Renderer>::Error> {
+        if !self.render_formats.contains(&format) {
+            return Err(VulkanError::UnsupportedPixelFormat(format));
+        }
+
+        self.create_texture(
+            format,
+            size,
+            mem_usage() | vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            false,
+        )
+    }
+}
+
+impl Bind<VulkanTexture> for VulkanRenderer {
+    #[profiling::function]
+    fn bind(&mut self, target: VulkanTexture) -> Result<(), <Self as Renderer>::Error> {
+        if !Arc::ptr_eq(&target.0.device, &self.device)
+            || !target.0.usage.contains(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        {
+            return Err(VulkanError::Unsupported);
+        }
+
+        self.target = Some(target);
+        Ok(())
+    }
+}
+
+fn existing_dmabuf(cache: &[VulkanTexture], dmabuf: &Dmabuf) -> Option<VulkanTexture> {
+    cache
+        .iter()
+        .find(|texture| {
+            texture
+                .0
+                .dmabuf
+                .as_ref()
+                .and_then(WeakDmabuf::upgrade)
+                .is_some_and(|buf| &buf == dmabuf)
+        })
+        .cloned()
+}
+
+// The renderer blends in the encoding of the buffers like the other renderers,
+// so the sRGB formats used by the allocator are replaced by their UNORM counterparts.
+fn vk_format(fourcc: DrmFourcc) -> Option<vk::Format> {
+    get_vk_format(fourcc).map(|format| match format {
+        vk::Format::B8G8R8A8_SRGB => vk::Format::B8G8R8A8_UNORM,
+        vk::Format::R8G8B8A8_SRGB => vk::Format::R8G8B8A8_UNORM,
+        vk::Format::A8B8G8R8_SRGB_PACK32 => vk::Format::A8B8G8R8_UNORM_PACK32,
+        format => format,
+    })
+}
+
+// Usage of textures created from memory
+fn mem_usage() -> vk::ImageUsageFlags {
+    vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST
+}
+
+// Format features a dmabuf modifier needs to support to be imported with `usage`
+fn format_features(usage: vk::ImageUsageFlags) -> vk::FormatFeatureFlags {
+    let mut features = vk::FormatFeatureFlags::empty();
+    if usage.contains(vk::ImageUsageFlags::SAMPLED) {
+        features |= vk::FormatFeatureFlags::SAMPLED_IMAGE;
+    }
+    if usage.contains(vk::ImageUsageFlags::COLOR_ATTACHMENT) {
+        features |= vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND;
+    }
+    features
+}
+
+fn create_shader_module(device: &ash::Device, spirv: &[u8]) -> Result<vk::ShaderModule, vk::Result> {
+    let code = ash::util::read_spv(&mut Cursor::new(spirv)).expect("Invalid SPIR-V");
+    let info = vk::ShaderModuleCreateInfo::default().code(&code);
+    unsafe { device.create_shader_module(&info, None) }
+}
+
+fn image_barrier(
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    src_queue_family_index: u32,
+    dst_queue_family_index: u32,
+) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(src_queue_family_index)
+        .dst_queue_family_index(dst_queue_family_index)
+        .image(image)
+        .subresource_range(COLOR_RANGE)
+}
+
+fn extent_2d<Kind>(size: Size<i32, Kind>) -> vk::Extent2D {
+    vk::Extent2D {
+        width: size.w as u32,
+        height: size.h as u32,
+    }
+}
+
+fn extent_3d<Kind>(size: Size<i32, Kind>) -> vk::Extent3D {
+    vk::Extent3D {
+        width: size.w as u32,
+        height: size.h as u32,
+        depth: 1,
+    }
+}
+
+// Converts a rectangle in framebuffer coordinates into a scissor clamped to `bounds`
+fn rect_2d(rect: Rectangle<i32, Physical>, bounds: Size<i32, Physical>) -> Option<vk::Rect2D> {
+    let rect = rect.intersection(Rectangle::from_loc_and_size((0, 0), bounds))?;
+    if rect.is_empty() {
+        return None;
+    }
+
+    Some(vk::Rect2D {
+        offset: vk::Offset2D {
+            x: rect.loc.x,
+            y: rect.loc.y,
+        },
+        extent: extent_2d(rect.size),
+    })
+}
+
+// Corners of a quad in the order of the triangle strip drawn by the vertex shader
+const CORNERS: [(f64, f64); 4] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+
+// Positions of the corners of `dst` in normalized device coordinates. `size` is the size
+// of the frame after applying the output transform.
+fn quad_positions(
+    dst: Rectangle<i32, Physical>,
+    size: Size<i32, Physical>,
+    transform: Transform,
+) -> [[f32; 2]; 4] {
+    let dst = dst.to_f64();
+    let size = size.to_f64();
+    let framebuffer_size = transform.transform_size(size);
+    CORNERS.map(|(x, y)| {
+        let point = dst.loc + Point::from((dst.size.w * x, dst.size.h * y));
+        let point = transform.transform_point_in(point, &size);
+        [
+            (point.x / framebuffer_size.w * 2.0 - 1.0) as f32,
+            (point.y / framebuffer_size.h * 2.0 - 1.0) as f32,
+        ]
+    })
+}
+
+// Texture coordinates of the corners of the destination quad sampling `src`
+fn quad_tex_coords(
+    src: Rectangle<f64, BufferCoords>,
+    texture_size: Size<i32, BufferCoords>,
+    src_transform: Transform,
+    flipped: bool,
+) -> [[f32; 2]; 4] {
+    let src_size = src_transform.transform_size(src.size);
+    // rotations are undone, flips are their own inverse in this orientation
+    let transform = if src_transform.flipped() {
+        src_transform
+    } else {
+        src_transform.invert()
+    };
+    let texture_size = texture_size.to_f64();
+    CORNERS.map(|(x, y)| {
+        let point = Point::<f64, BufferCoords>::from((src_size.w * x, src_size.h * y));
+        let mut point = src.loc + transform.transform_point_in(point, &src_size);
+        if flipped {
+            point.y = texture_size.h - point.y;
+        }
+        [
+            (point.x / texture_size.w) as f32,
+            (point.y / texture_size.h) as f32,
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quad_positions, quad_tex_coords};
+    use crate::utils::{Rectangle, Transform};
+
+    #[test]
+    fn quad_maps_to_framebuffer() {
+        let positions = quad_positions(
+            Rectangle::from_loc_and_size((0, 0), (800, 600)),
+            (800, 600).into(),
+            Transform::Normal,
+        );
+        assert_eq!(positions, [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]]);
+
+        // the top left corner of a rotated frame is the top right corner of the framebuffer
+        let positions = quad_positions(
+            Rectangle::from_loc_and_size((0, 0), (600, 800)),
+            (600, 800).into(),
+            Transform::_90,
+        );
+        assert_eq!(positions[0], [1.0, -1.0]);
+        assert_eq!(positions[3], [-1.0, 1.0]);
+
+        let tex_coords = quad_tex_coords(
+            Rectangle::from_loc_and_size((50.0, 0.0), (50.0, 50.0)),
+            (100, 50).into(),
+            Transform::Normal,
+            false,
+        );
+        assert_eq!(tex_coords, [[0.5, 0.0], [1.0, 0.0], [0.5, 1.0], [1.0, 1.0]]);
+
+        let tex_coords = quad_tex_coords(
+            Rectangle::from_loc_and_size((0.0, 0.0), (100.0, 50.0)),
+            (100, 50).into(),
+            Transform::Normal,
+            true,
+        );
+        assert_eq!(tex_coords, [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]]);
+    }
+}
@@ -0,0 +1,17 @@
+#version 450
+
+// Compile with `glslc --target-env=vulkan1.1 -O quad.vert -o quad.vert.spv`
+
+layout(push_constant) uniform PushConstants {
+    // xy: position in normalized device coordinates, zw: texture coordinates
+    vec4 vertices[4];
+    vec4 color;
+} pc;
+
+layout(location = 0) out vec2 uv;
+
+void main() {
+    vec4 vertex = pc.vertices[gl_VertexIndex];
+    uv = vertex.zw;
+    gl_Position = vec4(vertex.xy, 0.0, 1.0);
+}
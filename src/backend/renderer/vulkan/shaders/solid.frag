@@ -0,0 +1,15 @@
+#version 450
+
+// Compile with `glslc --target-env=vulkan1.1 -O solid.frag -o solid.frag.spv`
+
+layout(push_constant) uniform PushConstants {
+    vec4 vertices[4];
+    // premultiplied color
+    vec4 color;
+} pc;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = pc.color;
+}
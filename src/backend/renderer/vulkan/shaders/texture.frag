@@ -0,0 +1,18 @@
+#version 450
+
+// Compile with `glslc --target-env=vulkan1.1 -O texture.frag -o texture.frag.spv`
+
+layout(push_constant) uniform PushConstants {
+    vec4 vertices[4];
+    // premultiplied alpha for all channels
+    vec4 color;
+} pc;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = texture(tex, uv) * pc.color;
+}
@@ -0,0 +1,780 @@
+//!
+//! Notifications about system suspend and resume by systemd-logind.
+//!
+//! Sessions are not paused when the system goes to sleep, yet many drivers lose the state
+//! of the display pipeline across a suspend while still reporting it unchanged. Without
+//! restoring the state outputs stay black after resuming.
+//!
+//! A [`LogindSleepNotifier`] is a [`calloop`] event source delivering a [`SleepEvent`] before
+//! the system suspends and after it resumed. It holds a delay inhibitor lock, so suspending
+//! waits for the callback handling [`SleepEvent::PrepareForSleep`] to return, up to the
+//! `InhibitDelayMaxSec` configured for logind. This allows to e.g. stop rendering or lock the
+//! screen beforehand. After [`SleepEvent::Resumed`] the full output state should be re-committed,
+//! e.g. with [`DrmDevice::restore_state`](crate::backend::drm::DrmDevice::restore_state).
+//!
+//! The notifier talks to logind over the D-Bus system bus and does not depend on the session
+//! provider used to open devices.
+//!
+//! ```no_run
+//! # use smithay::backend::drm::DrmDevice;
+//! use smithay::backend::session::logind::{LogindSleepNotifier, SleepEvent};
+//!
+//! # let mut device: DrmDevice = todo!();
+//! let event_loop = calloop::EventLoop::<DrmDevice>::try_new().unwrap();
+//! let notifier = LogindSleepNotifier::new("my-compositor").expect("Failed to connect to logind");
+//!
+//! event_loop
+//!     .handle()
+//!     .insert_source(notifier, |event, _, device| match event {
+//!         SleepEvent::PrepareForSleep => {
+//!             // ...stop rendering, the system suspends once this callback returns
+//!         }
+//!         SleepEvent::Resumed => {
+//!             device.restore_state().expect("Failed to restore drm state");
+//!             // ...and render a new frame on every surface
+//!         }
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{
+    collections::VecDeque,
+    env,
+    io::{self, IoSliceMut, Read, Write},
+    os::unix::{io::OwnedFd, net::UnixStream},
+    path::PathBuf,
+};
+
+use calloop::{
+    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use rustix::net::{RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags};
+
+use tracing::{debug, info, info_span, warn};
+
+const DEFAULT_SYSTEM_BUS: &str = "/run/dbus/system_bus_socket";
+const MAX_MESSAGE_SIZE: usize = 128 * 1024 * 1024;
+const MAX_FDS: usize = 16;
+
+const DBUS_NAME: &str = "org.freedesktop.DBus";
+const DBUS_PATH: &str = "/org/freedesktop/DBus";
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+const SLEEP_MATCH_RULE: &str = "type='signal',sender='org.freedesktop.login1',\
+    interface='org.freedesktop.login1.Manager',member='PrepareForSleep',path='/org/freedesktop/login1'";
+
+// message types
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+// header fields
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+const FIELD_UNIX_FDS: u8 = 9;
+
+/// Events generated by a [`LogindSleepNotifier`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepEvent {
+    /// The system is about to suspend
+    ///
+    /// Suspending is delayed until the callback returns.
+    PrepareForSleep,
+    /// The system resumed from suspend
+    ///
+    /// The state of all outputs should be restored.
+    Resumed,
+}
+
+/// Event source for suspend and resume notifications of systemd-logind
+#[derive(Debug)]
+pub struct LogindSleepNotifier {
+    source: Generic<UnixStream, Error>,
+    bus: Bus,
+    who: String,
+    inhibitor: Option<OwnedFd>,
+    pending_inhibit: Option<u32>,
+    span: tracing::Span,
+}
+
+impl LogindSleepNotifier {
+    /// Connect to logind on the system bus and take a delay inhibitor lock for sleep
+    ///
+    /// `who` is a human-readable name of the application shown as the owner of the lock.
+    pub fn new(who: impl Into<String>) -> Result<LogindSleepNotifier, Error> {
+        let who = who.into();
+        let span = info_span!("backend_session_logind");
+        let _guard = span.enter();
+
+        let mut stream = UnixStream::connect(system_bus_path()).map_err(Error::Connect)?;
+        authenticate(&mut stream)?;
+
+        let mut bus = Bus::default();
+        bus.call(&stream, DBUS_NAME, DBUS_PATH, DBUS_NAME, "Hello", "", &[])?;
+        let mut rule = Writer::default();
+        rule.str(SLEEP_MATCH_RULE);
+        bus.call(&stream, DBUS_NAME, DBUS_PATH, DBUS_NAME, "AddMatch", "s", &rule.0)?;
+
+        let serial = bus.inhibit(&stream, &who)?;
+        let inhibitor = 'reply: loop {
+            bus.receive(&stream)?;
+            while let Some(message) = bus.next_message()? {
+                if message.reply_serial == Some(serial) {
+                    break 'reply message.into_inhibitor()?;
+                }
+            }
+        };
+        stream.set_nonblocking(true)?;
+        info!("Took delay inhibitor lock for sleep");
+
+        drop(_guard);
+        Ok(LogindSleepNotifier {
+            source: Generic::new_with_error(stream, Interest::READ, Mode::Level),
+            bus,
+            who,
+            inhibitor: Some(inhibitor),
+            pending_inhibit: None,
+            span,
+        })
+    }
+
+    /// Returns whether the notifier currently delays suspending the system
+    pub fn is_inhibiting(&self) -> bool {
+        self.inhibitor.is_some()
+    }
+}
+
+impl EventSource for LogindSleepNotifier {
+    type Event = SleepEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = Error;
+
+    #[profiling::function]
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Error>
+    where
+        F: FnMut(SleepEvent, &mut ()),
+    {
+        let LogindSleepNotifier {
+            source,
+            bus,
+            who,
+            inhibitor,
+            pending_inhibit,
+            span,
+        } = self;
+        let _guard = span.enter();
+
+        source.process_events(readiness, token, |_, stream| {
+            bus.receive(stream)?;
+            while let Some(message) = bus.next_message()? {
+                if message.reply_serial.is_some() && message.reply_serial == *pending_inhibit {
+                    *pending_inhibit = None;
+                    match message.into_inhibitor() {
+                        Ok(fd) => *inhibitor = Some(fd),
+                        Err(err) => warn!("Failed to take delay inhibitor lock: {}", err),
+                    }
+                    continue;
+                }
+
+                if message.kind != SIGNAL
+                    || message.interface.as_deref() != Some(LOGIND_MANAGER)
+                    || message.member.as_deref() != Some("PrepareForSleep")
+                {
+                    continue;
+                }
+                if message.signature != "b" {
+                    return Err(Error::InvalidMessage);
+                }
+                let sleeping = message.reader().u32()? != 0;
+
+                if sleeping {
+                    debug!("Preparing for sleep");
+                    callback(SleepEvent::PrepareForSleep, &mut ());
+                    // releasing the lock lets the system suspend
+                    inhibitor.take();
+                } else {
+                    debug!("Resumed from sleep");
+                    if inhibitor.is_none() && pending_inhibit.is_none() {
+                        *pending_inhibit = Some(bus.inhibit(stream, who)?);
+                    }
+                    callback(SleepEvent::Resumed, &mut ());
+                }
+            }
+            Ok(PostAction::Continue)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+/// Errors of the logind sleep notifier
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to connect to the system bus
+    #[error("Failed to connect to the system bus: {0}")]
+    Connect(#[source] io::Error),
+    /// The system bus rejected the authentication
+    #[error("Failed to authenticate with the system bus")]
+    Authentication,
+    /// Failed to communicate with the system bus
+    #[error("Failed to communicate with the system bus: {0}")]
+    Io(#[from] io::Error),
+    /// The system bus closed the connection
+    #[error("The system bus closed the connection")]
+    Disconnected,
+    /// Received a malformed message
+    #[error("Received a malformed message from the system bus")]
+    InvalidMessage,
+    /// logind refused to hand out an inhibitor lock
+    #[error("Failed to take an inhibitor lock: {0}")]
+    InhibitFailed(String),
+}
+
+fn system_bus_path() -> PathBuf {
+    env::var("DBUS_SYSTEM_BUS_ADDRESS")
+        .ok()
+        .and_then(|address| {
+            address
+                .split(';')
+                .find_map(|address| address.strip_prefix("unix:path="))
+                .map(|path| PathBuf::from(path.split(',').next().unwrap_or(path)))
+        })
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSTEM_BUS))
+}
+
+// SASL handshake with the EXTERNAL mechanism, which authenticates with the credentials of the socket
+fn authenticate(stream: &mut UnixStream) -> Result<(), Error> {
+    let uid = unsafe { libc::getuid() }.to_string();
+    let uid = uid.bytes().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    stream.write_all(b"\0")?;
+    stream.write_all(format!("AUTH EXTERNAL {}\r\n", uid).as_bytes())?;
+    if !read_line(stream)?.starts_with("OK ") {
+        return Err(Error::Authentication);
+    }
+    stream.write_all(b"NEGOTIATE_UNIX_FD\r\n")?;
+    if read_line(stream)? != "AGREE_UNIX_FD" {
+        return Err(Error::Authentication);
+    }
+    stream.write_all(b"BEGIN\r\n")?;
+    Ok(())
+}
+
+// Reads byte by byte, so no message data following the handshake is consumed
+fn read_line(stream: &mut UnixStream) -> Result<String, Error> {
+    let mut line = Vec::new();
+    while !line.ends_with(b"\r\n") {
+        let mut byte = [0];
+        if stream.read(&mut byte)? == 0 || line.len() > 512 {
+            return Err(Error::Authentication);
+        }
+        line.push(byte[0]);
+    }
+    line.truncate(line.len() - 2);
+    String::from_utf8(line).map_err(|_| Error::Authentication)
+}
+
+#[derive(Debug, Default)]
+struct Bus {
+    serial: u32,
+    buffer: Vec<u8>,
+    fds: VecDeque<OwnedFd>,
+}
+
+impl Bus {
+    #[allow(clippy::too_many_arguments)]
+    fn call(
+        &mut self,
+        stream: &UnixStream,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        signature: &str,
+        body: &[u8],
+    ) -> io::Result<u32> {
+        self.serial += 1;
+        let mut fields = vec![
+            (FIELD_PATH, Value::ObjectPath(path)),
+            (FIELD_DESTINATION, Value::Str(destination)),
+            (FIELD_INTERFACE, Value::Str(interface)),
+            (FIELD_MEMBER, Value::Str(member)),
+        ];
+        if !signature.is_empty() {
+            fields.push((FIELD_SIGNATURE, Value::Signature(signature)));
+        }
+        (&*stream).write_all(&encode(METHOD_CALL, self.serial, &fields, body))?;
+        Ok(self.serial)
+    }
+
+    fn inhibit(&mut self, stream: &UnixStream, who: &str) -> io::Result<u32> {
+        let mut body = Writer::default();
+        body.str("sleep");
+        body.str(who);
+        body.str("Prepare outputs for suspend");
+        body.str("delay");
+        self.call(
+            stream,
+            LOGIND_NAME,
+            LOGIND_PATH,
+            LOGIND_MANAGER,
+            "Inhibit",
+            "ssss",
+            &body.0,
+        )
+    }
+
+    fn receive(&mut self, stream: &UnixStream) -> Result<(), Error> {
+        let mut data = [0u8; 4096];
+        let mut space = [0u8; rustix::cmsg_space!(ScmRights(MAX_FDS))];
+        let mut control = RecvAncillaryBuffer::new(&mut space);
+        let result = rustix::net::recvmsg(
+            stream,
+            &mut [IoSliceMut::new(&mut data)],
+            &mut control,
+            RecvFlags::CMSG_CLOEXEC,
+        );
+        let bytes = match result {
+            Ok(msg) => msg.bytes,
+            Err(rustix::io::Errno::AGAIN) | Err(rustix::io::Errno::INTR) => return Ok(()),
+            Err(err) => return Err(Error::Io(err.into())),
+        };
+
+        for msg in control.drain() {
+            if let RecvAncillaryMessage::ScmRights(fds) = msg {
+                self.fds.extend(fds);
+            }
+        }
+        if bytes == 0 {
+            return Err(Error::Disconnected);
+        }
+        self.buffer.extend_from_slice(&data[..bytes]);
+        Ok(())
+    }
+
+    fn next_message(&mut self) -> Result<Option<Message>, Error> {
+        let Some((mut message, len)) = decode(&self.buffer)? else {
+            return Ok(None);
+        };
+        self.buffer.drain(..len);
+        for _ in 0..message.unix_fds {
+            message
+                .fds
+                .push(self.fds.pop_front().ok_or(Error::InvalidMessage)?);
+        }
+        Ok(Some(message))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Message {
+    kind: u8,
+    big_endian: bool,
+    reply_serial: Option<u32>,
+    interface: Option<String>,
+    member: Option<String>,
+    error_name: Option<String>,
+    signature: String,
+    unix_fds: u32,
+    body: Vec<u8>,
+    fds: Vec<OwnedFd>,
+}
+
+impl Message {
+    fn reader(&self) -> Reader<'_> {
+        Reader {
+            data: &self.body,
+            pos: 0,
+            big_endian: self.big_endian,
+        }
+    }
+
+    // The reply to an `Inhibit` call carries the lock as its only fd
+    fn into_inhibitor(mut self) -> Result<OwnedFd, Error> {
+        if self.kind == ERROR {
+            return Err(Error::InhibitFailed(self.error_name.unwrap_or_default()));
+        }
+        if self.kind != METHOD_RETURN || self.signature != "h" {
+            return Err(Error::InvalidMessage);
+        }
+        let index = self.reader().u32()? as usize;
+        if index >= self.fds.len() {
+            return Err(Error::InvalidMessage);
+        }
+        Ok(self.fds.swap_remove(index))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    Str(&'a str),
+    ObjectPath(&'a str),
+    Signature(&'a str),
+    U32(u32),
+}
+
+#[derive(Debug, Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn pad(&mut self, align: usize) {
+        let len = (self.0.len() + align - 1) / align * align;
+        self.0.resize(len, 0);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.pad(4);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.0.push(value.len() as u8);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn value(&mut self, value: Value<'_>) {
+        match value {
+            Value::Str(s) => {
+                self.signature("s");
+                self.str(s);
+            }
+            Value::ObjectPath(s) => {
+                self.signature("o");
+                self.str(s);
+            }
+            Value::Signature(s) => {
+                self.signature("g");
+                self.signature(s);
+            }
+            Value::U32(v) => {
+                self.signature("u");
+                self.u32(v);
+            }
+        }
+    }
+}
+
+fn encode(kind: u8, serial: u32, fields: &[(u8, Value<'_>)], body: &[u8]) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.0.extend_from_slice(&[b'l', kind, 0, 1]);
+    writer.u32(body.len() as u32);
+    writer.u32(serial);
+    // length of the header field array, filled in below
+    writer.u32(0);
+    for (code, value) in fields {
+        writer.pad(8);
+        writer.0.push(*code);
+        writer.value(*value);
+    }
+    let fields_len = (writer.0.len() - 16) as u32;
+    writer.0[12..16].copy_from_slice(&fields_len.to_le_bytes());
+    writer.pad(8);
+    writer.0.extend_from_slice(body);
+    writer.0
+}
+
+// Returns the message and its length, or `None` if the buffer does not contain a full message yet
+fn decode(buffer: &[u8]) -> Result<Option<(Message, usize)>, Error> {
+    if buffer.len() < 16 {
+        return Ok(None);
+    }
+    let big_endian = match buffer[0] {
+        b'l' => false,
+        b'B' => true,
+        _ => return Err(Error::InvalidMessage),
+    };
+    let mut header = Reader {
+        data: &buffer[..16],
+        pos: 4,
+        big_endian,
+    };
+    let body_len = header.u32()? as usize;
+    let _serial = header.u32()?;
+    let fields_len = header.u32()? as usize;
+
+    let body_start = (16 + fields_len + 7) / 8 * 8;
+    let len = body_start + body_len;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(Error::InvalidMessage);
+    }
+    if buffer.len() < len {
+        return Ok(None);
+    }
+
+    let mut message = Message {
+        kind: buffer[1],
+        big_endian,
+        body: buffer[body_start..len].to_vec(),
+        ..Default::default()
+    };
+    let mut fields = Reader {
+        data: &buffer[..16 + fields_len],
+        pos: 16,
+        big_endian,
+    };
+    while fields.pos < fields.data.len() {
+        fields.align(8)?;
+        let code = fields.u8()?;
+        let value = match fields.signature()? {
+            "s" => Value::Str(fields.str()?),
+            "o" => Value::ObjectPath(fields.str()?),
+            "g" => Value::Signature(fields.signature()?),
+            "u" => Value::U32(fields.u32()?),
+            // fields we do not care about may be of any type and are ignored
+            signature => {
+                fields.skip_variant(signature, 0)?;
+                continue;
+            }
+        };
+        match (code, value) {
+            (FIELD_INTERFACE, Value::Str(s)) => message.interface = Some(s.to_owned()),
+            (FIELD_MEMBER, Value::Str(s)) => message.member = Some(s.to_owned()),
+            (FIELD_ERROR_NAME, Value::Str(s)) => message.error_name = Some(s.to_owned()),
+            (FIELD_REPLY_SERIAL, Value::U32(v)) => message.reply_serial = Some(v),
+            (FIELD_SIGNATURE, Value::Signature(s)) => message.signature = s.to_owned(),
+            (FIELD_UNIX_FDS, Value::U32(v)) => message.unix_fds = v,
+            _ => {}
+        }
+    }
+
+    Ok(Some((message, len)))
+}
+
+#[derive(Debug)]
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn align(&mut self, align: usize) -> Result<(), Error> {
+        self.pos = (self.pos + align - 1) / align * align;
+        if self.pos > self.data.len() {
+            return Err(Error::InvalidMessage);
+        }
+        Ok(())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(Error::InvalidMessage)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.align(4)?;
+        let bytes = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    // strings and signatures are followed by a nul byte not included in their length
+    fn terminated(&mut self, len: usize) -> Result<&'a str, Error> {
+        let bytes = self.bytes(len + 1)?;
+        std::str::from_utf8(&bytes[..len]).map_err(|_| Error::InvalidMessage)
+    }
+
+    fn str(&mut self) -> Result<&'a str, Error> {
+        let len = self.u32()? as usize;
+        self.terminated(len)
+    }
+
+    fn signature(&mut self) -> Result<&'a str, Error> {
+        let len = self.u8()? as usize;
+        self.terminated(len)
+    }
+
+    // Skips the value of a variant with the given signature, which has to be a single complete type
+    fn skip_variant(&mut self, signature: &str, depth: usize) -> Result<(), Error> {
+        match split_type(signature.as_bytes())? {
+            (ty, []) => self.skip(ty, depth),
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    fn skip(&mut self, ty: &[u8], depth: usize) -> Result<(), Error> {
+        if depth > MAX_NESTING {
+            return Err(Error::InvalidMessage);
+        }
+        match ty[0] {
+            b'y' => self.bytes(1).map(|_| ()),
+            b'n' | b'q' => {
+                self.align(2)?;
+                self.bytes(2).map(|_| ())
+            }
+            b'b' | b'i' | b'u' | b'h' => self.u32().map(|_| ()),
+            b'x' | b't' | b'd' => {
+                self.align(8)?;
+                self.bytes(8).map(|_| ())
+            }
+            b's' | b'o' => self.str().map(|_| ()),
+            b'g' => self.signature().map(|_| ()),
+            b'v' => {
+                let signature = self.signature()?;
+                self.skip_variant(signature, depth + 1)
+            }
+            b'a' => {
+                let len = self.u32()? as usize;
+                // the padding to the first element is not part of the length
+                self.align(alignment(ty[1]))?;
+                self.bytes(len).map(|_| ())
+            }
+            b'(' | b'{' => {
+                self.align(8)?;
+                let mut members = &ty[1..ty.len() - 1];
+                while !members.is_empty() {
+                    let (member, rest) = split_type(members)?;
+                    self.skip(member, depth + 1)?;
+                    members = rest;
+                }
+                Ok(())
+            }
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+}
+
+// Maximum nesting of containers allowed by the specification
+const MAX_NESTING: usize = 64;
+
+// Splits the first single complete type off a signature
+fn split_type(signature: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let len = match signature.first() {
+        None => return Err(Error::InvalidMessage),
+        Some(b'a') => 1 + split_type(&signature[1..])?.0.len(),
+        Some(open @ (b'(' | b'{')) => {
+            let close = if *open == b'(' { b')' } else { b'}' };
+            let mut depth = 0usize;
+            let end = signature
+                .iter()
+                .position(|c| {
+                    if c == open {
+                        depth += 1;
+                    } else if *c == close {
+                        depth -= 1;
+                    }
+                    depth == 0
+                })
+                .ok_or(Error::InvalidMessage)?;
+            // empty structs are not allowed
+            if end < 2 {
+                return Err(Error::InvalidMessage);
+            }
+            end + 1
+        }
+        Some(_) => 1,
+    };
+    Ok(signature.split_at(len))
+}
+
+fn alignment(code: u8) -> usize {
+    match code {
+        b'n' | b'q' => 2,
+        b'b' | b'i' | b'u' | b'h' | b's' | b'o' | b'a' => 4,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Value, Writer, FIELD_INTERFACE, FIELD_MEMBER, FIELD_PATH, FIELD_SIGNATURE};
+    use super::{LOGIND_MANAGER, LOGIND_PATH, SIGNAL};
+
+    #[test]
+    fn messages_roundtrip() {
+        let mut body = Writer::default();
+        body.u32(1);
+        let fields = [
+            (FIELD_PATH, Value::ObjectPath(LOGIND_PATH)),
+            (FIELD_INTERFACE, Value::Str(LOGIND_MANAGER)),
+            (FIELD_MEMBER, Value::Str("PrepareForSleep")),
+            (FIELD_SIGNATURE, Value::Signature("b")),
+        ];
+        let data = encode(SIGNAL, 42, &fields, &body.0);
+
+        assert!(decode(&data[..data.len() - 1]).unwrap().is_none());
+        let (message, len) = decode(&data).unwrap().unwrap();
+        assert_eq!(len, data.len());
+        assert_eq!(message.kind, SIGNAL);
+        assert_eq!(message.interface.as_deref(), Some(LOGIND_MANAGER));
+        assert_eq!(message.member.as_deref(), Some("PrepareForSleep"));
+        assert_eq!(message.signature, "b");
+        assert_eq!(message.reader().u32().unwrap(), 1);
+    }
+
+    #[test]
+    fn unknown_header_fields_are_skipped() {
+        let mut writer = Writer::default();
+        writer.0.extend_from_slice(&[b'l', SIGNAL, 0, 1]);
+        writer.u32(0);
+        writer.u32(1);
+        writer.u32(0);
+        // a field of a type we never expect, an array of string to variant pairs
+        writer.pad(8);
+        writer.0.push(200);
+        writer.signature("a{sv}");
+        writer.u32(16);
+        writer.pad(8);
+        let start = writer.0.len();
+        writer.str("ab");
+        writer.signature("u");
+        writer.u32(7);
+        assert_eq!(writer.0.len() - start, 16);
+        writer.pad(8);
+        writer.0.push(FIELD_MEMBER);
+        writer.value(Value::Str("PrepareForSleep"));
+        let fields_len = (writer.0.len() - 16) as u32;
+        writer.0[12..16].copy_from_slice(&fields_len.to_le_bytes());
+        writer.pad(8);
+
+        let (message, len) = decode(&writer.0).unwrap().unwrap();
+        assert_eq!(len, writer.0.len());
+        assert_eq!(message.member.as_deref(), Some("PrepareForSleep"));
+
+        // a truncated array is still rejected
+        writer.0[start - 8..start - 4].copy_from_slice(&64u32.to_le_bytes());
+        assert!(decode(&writer.0).is_err());
+    }
+}
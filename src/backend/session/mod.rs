@@ -25,6 +25,9 @@
 //! gated by the `backend_session_libseat` cargo feature.
 //!
 //! Other implementations can be provided out-of-tree.
+//!
+//! Independent of the session provider, the `logind` module notifies about system suspend and resume,
+//! gated by the `backend_session_logind` cargo feature.
 
 use rustix::fs::OFlags;
 use std::{
@@ -153,3 +156,5 @@ impl AsErrno for () {
 
 #[cfg(feature = "backend_session_libseat")]
 pub mod libseat;
+#[cfg(feature = "backend_session_logind")]
+pub mod logind;
@@ -287,6 +287,28 @@ pub fn all_gpus<S: AsRef<str>>(seat: S) -> io::Result<Vec<PathBuf>> {
     Ok(gpus)
 }
 
+/// Returns the names of all seats with input devices, sorted by name
+///
+/// Devices without an `ID_SEAT` property belong to `seat0`. Might be used for creating
+/// a [`LibinputInputBackend`](crate::backend::libinput::LibinputInputBackend) for multiple seats.
+pub fn input_seats() -> io::Result<Vec<String>> {
+    let mut enumerator = Enumerator::new()?;
+    enumerator.match_subsystem("input")?;
+    enumerator.match_property("ID_INPUT", "1")?;
+    let mut seats = enumerator
+        .scan_devices()?
+        .map(|device| {
+            device
+                .property_value("ID_SEAT")
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("seat0"))
+        })
+        .collect::<Vec<_>>();
+    seats.sort();
+    seats.dedup();
+    Ok(seats)
+}
+
 /// Returns the loaded driver for a device named by it's [`dev_t`].
 pub fn driver(dev: dev_t) -> io::Result<Option<OsString>> {
     let mut enumerator = Enumerator::new()?;
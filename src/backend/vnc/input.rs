@@ -0,0 +1,234 @@
+//! Input backend implementation for the VNC backend.
+
+use crate::{
+    backend::input::{
+        self, AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, InputBackend, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent,
+        PointerButtonEvent, PointerMotionAbsoluteEvent, UnusedEvent,
+    },
+    utils::{Physical, Size},
+};
+
+/// Marker used to define the `InputBackend` types for the VNC backend.
+#[derive(Debug)]
+pub struct VncInput;
+
+/// Virtual input device of a single VNC client
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VncVirtualDevice {
+    pub(crate) client: u64,
+}
+
+impl VncVirtualDevice {
+    /// Id of the client, as used by [`VncEvent`](super::VncEvent)
+    pub fn client(&self) -> u64 {
+        self.client
+    }
+}
+
+impl Device for VncVirtualDevice {
+    fn id(&self) -> String {
+        format!("vnc-{}", self.client)
+    }
+
+    fn name(&self) -> String {
+        format!("vnc virtual input {}", self.client)
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(capability, DeviceCapability::Keyboard | DeviceCapability::Pointer)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// VNC-Backend internal event wrapping a key event of a client into a [`KeyboardKeyEvent`]
+#[derive(Debug, Clone)]
+pub struct VncKeyboardInputEvent {
+    pub(crate) time: u64,
+    pub(crate) device: VncVirtualDevice,
+    pub(crate) key: Keycode,
+    pub(crate) keysym: u32,
+    pub(crate) count: u32,
+    pub(crate) state: KeyState,
+}
+
+impl VncKeyboardInputEvent {
+    /// Keysym sent by the client, which was translated into the key code of this event
+    pub fn keysym(&self) -> u32 {
+        self.keysym
+    }
+}
+
+impl input::Event<VncInput> for VncKeyboardInputEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<VncInput> for VncKeyboardInputEvent {
+    fn key_code(&self) -> Keycode {
+        self.key
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// VNC-Backend internal event wrapping a scroll button of a client into a [`PointerAxisEvent`]
+#[derive(Debug, Clone)]
+pub struct VncMouseWheelEvent {
+    pub(crate) time: u64,
+    pub(crate) device: VncVirtualDevice,
+    pub(crate) axis: Axis,
+    pub(crate) amount: f64,
+}
+
+impl input::Event<VncInput> for VncMouseWheelEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerAxisEvent<VncInput> for VncMouseWheelEvent {
+    fn amount(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            Some(self.amount * 120.)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+/// VNC-Backend internal event wrapping a button of a client into a [`PointerButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct VncMouseInputEvent {
+    pub(crate) time: u64,
+    pub(crate) device: VncVirtualDevice,
+    pub(crate) button: u32,
+    pub(crate) state: ButtonState,
+}
+
+impl input::Event<VncInput> for VncMouseInputEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerButtonEvent<VncInput> for VncMouseInputEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// VNC-Backend internal event wrapping a pointer position of a client into a [`PointerMotionAbsoluteEvent`]
+#[derive(Debug, Clone)]
+pub struct VncMouseMovedEvent {
+    pub(crate) time: u64,
+    pub(crate) device: VncVirtualDevice,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<i32, Physical>,
+}
+
+impl input::Event<VncInput> for VncMouseMovedEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        self.device.clone()
+    }
+}
+
+impl PointerMotionAbsoluteEvent<VncInput> for VncMouseMovedEvent {}
+impl AbsolutePositionEvent<VncInput> for VncMouseMovedEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl InputBackend for VncInput {
+    type Device = VncVirtualDevice;
+    type KeyboardKeyEvent = VncKeyboardInputEvent;
+    type PointerAxisEvent = VncMouseWheelEvent;
+    type PointerButtonEvent = VncMouseInputEvent;
+
+    type PointerMotionEvent = UnusedEvent;
+
+    type PointerMotionAbsoluteEvent = VncMouseMovedEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
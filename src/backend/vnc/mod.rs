@@ -0,0 +1,811 @@
+//! VNC backend exposing an output over the remote framebuffer protocol
+//!
+//! Headless servers often need remote access to a compositor without relying on screencasting
+//! through PipeWire and the desktop portals. A [`VncBackend`] listens for RFB/VNC clients and
+//! sends them the contents of a framebuffer, e.g. the output of the
+//! [headless backend](crate::backend::headless), read back after rendering.
+//!
+//! The backend is a [`calloop`] event source:
+//! - Input of the clients is translated into [`InputEvent`]s of the [`VncInput`] backend. Every client
+//!   is represented by its own [`VncVirtualDevice`]. Keysyms are mapped to key codes using a keymap,
+//!   which defaults to the default xkb keymap and can be changed with [`VncBackend::set_keymap`].
+//! - Clients supporting the `ExtendedDesktopSize` extension may request a new size, emitted as
+//!   [`VncEvent::ResizeRequested`]. It is up to the compositor to accept the request, e.g. by changing
+//!   the mode of the output and calling [`VncBackend::resize`].
+//!
+//! After rendering, the damaged regions of the framebuffer are updated with [`VncBackend::update`].
+//! Clients only receive the regions damaged since their last update, encoded in their pixel format.
+//!
+//! Connections are neither authenticated nor encrypted, so the backend should only listen on
+//! trusted networks or behind a tunnel.
+//!
+//! ```no_run
+//! use smithay::backend::vnc::{VncBackend, VncEvent};
+//!
+//! # struct State;
+//! let mut event_loop = calloop::EventLoop::<State>::try_new().unwrap();
+//! let vnc = VncBackend::bind("127.0.0.1:5900", (1280, 800).into(), "smithay").unwrap();
+//!
+//! event_loop
+//!     .handle()
+//!     .insert_source(vnc, |event, _, _state| match event {
+//!         VncEvent::Input(event) => {
+//!             // ...process the input event
+//!         }
+//!         VncEvent::ResizeRequested { size, .. } => {
+//!             // ...change the size of the output and call `VncBackend::resize`
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! // after rendering a frame
+//! # use smithay::utils::{Physical, Rectangle};
+//! # let vnc: &mut VncBackend = todo!();
+//! # let (pixels, stride, damage): (Vec<u8>, usize, Vec<Rectangle<i32, Physical>>) = todo!();
+//! vnc.update(&pixels, stride, &damage);
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug, info, info_span, warn};
+use xkbcommon::xkb;
+
+use crate::{
+    backend::input::{xorg_mouse_to_libinput, Axis, ButtonState, InputEvent, KeyState, Keycode},
+    utils::{Clock, Monotonic, Physical, Rectangle, Size},
+};
+
+mod input;
+mod protocol;
+
+pub use self::input::*;
+use self::protocol::{ClientMessage, PixelFormat};
+
+// Number of damaged rectangles per client before they are merged into their bounding box
+const MAX_DAMAGE_RECTS: usize = 32;
+// Maximum number of unprocessed bytes buffered per client, large enough for any message we parse
+const MAX_INPUT_LEN: usize = 1 << 20;
+
+/// Events generated by the [`VncBackend`]
+#[derive(Debug)]
+pub enum VncEvent {
+    /// An input event of a client occurred.
+    Input(InputEvent<VncInput>),
+
+    /// A client requested a new framebuffer size.
+    ///
+    /// Accept the request with [`VncBackend::resize`] or decline it with [`VncBackend::reject_resize`].
+    ResizeRequested {
+        /// Id of the requesting client, see [`VncVirtualDevice::client`]
+        client: u64,
+        /// The requested size
+        size: Size<i32, Physical>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientState {
+    Version,
+    Security,
+    Init,
+    Ready,
+}
+
+#[derive(Debug)]
+struct Client {
+    id: u64,
+    addr: SocketAddr,
+    stream: Generic<TcpStream>,
+    registered: bool,
+    state: ClientState,
+    minor_version: u8,
+    input: Vec<u8>,
+    // remaining bytes of a message, which are dropped without buffering them
+    skip: usize,
+    output: Vec<u8>,
+    closed: bool,
+
+    format: PixelFormat,
+    desktop_size: bool,
+    extended_desktop_size: bool,
+    update_requested: bool,
+    damage: Vec<Rectangle<i32, Physical>>,
+    // reason and status of a pending desktop size change
+    size_changed: Option<(u16, u16)>,
+
+    buttons: u8,
+    pointer: Option<(u16, u16)>,
+    pressed: HashSet<Keycode>,
+}
+
+impl Client {
+    fn device(&self) -> VncVirtualDevice {
+        VncVirtualDevice { client: self.id }
+    }
+
+    /// Reads the available data of the stream
+    ///
+    /// Returns `true` if reading stopped because the input buffer is full.
+    fn read(&mut self) -> io::Result<bool> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = buf.len().min(MAX_INPUT_LEN - self.input.len());
+            if len == 0 {
+                return Ok(true);
+            }
+            match self.stream.get_ref().read(&mut buf[..len]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.input.extend_from_slice(&buf[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.get_ref().write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.output.drain(..len);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, err: io::Error) {
+        if !self.closed {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                info!(client = self.id, addr = %self.addr, "VNC client disconnected");
+            } else {
+                warn!(client = self.id, addr = %self.addr, ?err, "Closing VNC connection");
+            }
+            // wakes up the event loop to remove the client
+            let _ = self.stream.get_ref().shutdown(Shutdown::Both);
+            self.closed = true;
+        }
+    }
+
+    fn add_damage(&mut self, rect: Rectangle<i32, Physical>) {
+        if rect.is_empty() {
+            return;
+        }
+        self.damage.push(rect);
+        if self.damage.len() > MAX_DAMAGE_RECTS {
+            let bbox = self
+                .damage
+                .iter()
+                .fold(self.damage[0], |bbox, rect| bbox.merge(*rect));
+            self.damage = vec![bbox];
+        }
+    }
+
+    /// Sends a framebuffer update if the client requested one and anything changed
+    fn send_update(&mut self, framebuffer: &[u8], size: Size<i32, Physical>) {
+        if self.closed
+            || self.state != ClientState::Ready
+            || !self.update_requested
+            || !self.output.is_empty()
+        {
+            return;
+        }
+        let size_changed = self.size_changed.take();
+        if self.damage.is_empty() && size_changed.is_none() {
+            return;
+        }
+
+        let rects = self.damage.len() + size_changed.is_some() as usize;
+        protocol::write_update_header(rects as u16, &mut self.output);
+        if let Some((reason, status)) = size_changed {
+            if self.extended_desktop_size {
+                protocol::write_extended_desktop_size(size, reason, status, &mut self.output);
+            } else {
+                protocol::write_desktop_size(size, &mut self.output);
+            }
+        }
+        for rect in self.damage.drain(..) {
+            protocol::write_raw_rect(
+                framebuffer,
+                size.w as usize * 4,
+                rect,
+                &self.format,
+                &mut self.output,
+            );
+        }
+        self.update_requested = false;
+
+        if let Err(err) = self.flush() {
+            self.close(err);
+        }
+    }
+}
+
+/// Backend serving a framebuffer to VNC clients
+#[derive(Debug)]
+pub struct VncBackend {
+    listener: Generic<TcpListener>,
+    clients: Vec<Client>,
+    next_client: u64,
+    name: String,
+    size: Size<i32, Physical>,
+    framebuffer: Vec<u8>,
+    keycodes: HashMap<u32, Keycode>,
+    resize_request: Option<u64>,
+    clock: Clock<Monotonic>,
+    span: tracing::Span,
+}
+
+impl VncBackend {
+    /// Listen for VNC clients on `addr`, serving a framebuffer of the given size and name
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        size: Size<i32, Physical>,
+        name: impl Into<String>,
+    ) -> io::Result<VncBackend> {
+        let span = info_span!("backend_vnc");
+        let _guard = span.enter();
+
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!(addr = ?listener.local_addr()?, "Listening for VNC clients");
+
+        let mut backend = VncBackend {
+            listener: Generic::new(listener, Interest::READ, Mode::Level),
+            clients: Vec::new(),
+            next_client: 0,
+            name: name.into(),
+            size,
+            framebuffer: vec![0; framebuffer_len(size)],
+            keycodes: HashMap::new(),
+            resize_request: None,
+            clock: Clock::new(),
+            span: span.clone(),
+        };
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        match xkb::Keymap::new_from_names(&context, "", "", "", "", None, xkb::KEYMAP_COMPILE_NO_FLAGS) {
+            Some(keymap) => backend.set_keymap(&keymap),
+            None => warn!("Failed to compile the default keymap, ignoring key events"),
+        }
+
+        drop(_guard);
+        Ok(backend)
+    }
+
+    /// Address the backend is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.get_ref().local_addr()
+    }
+
+    /// Size of the framebuffer
+    pub fn size(&self) -> Size<i32, Physical> {
+        self.size
+    }
+
+    /// Number of connected clients
+    pub fn clients(&self) -> usize {
+        self.clients.iter().filter(|client| !client.closed).count()
+    }
+
+    /// Set the keymap used to translate the keysyms sent by clients into key codes
+    ///
+    /// This should usually match the keymap of the keyboard the events are processed by.
+    /// Keysyms are mapped to the first key producing them, preferring lower shift levels.
+    pub fn set_keymap(&mut self, keymap: &xkb::Keymap) {
+        let mut syms = Vec::new();
+        for raw in keymap.min_keycode().raw()..=keymap.max_keycode().raw() {
+            let key = Keycode::new(raw);
+            for layout in 0..keymap.num_layouts_for_key(key) {
+                for level in 0..keymap.num_levels_for_key(key, layout) {
+                    for sym in keymap.key_get_syms_by_level(key, layout, level) {
+                        syms.push((level, layout, key, sym.raw()));
+                    }
+                }
+            }
+        }
+        syms.sort_by_key(|(level, layout, _, _)| (*level, *layout));
+
+        self.keycodes.clear();
+        for (_, _, key, sym) in syms {
+            self.keycodes.entry(sym).or_insert(key);
+        }
+    }
+
+    /// Update the framebuffer and send the damaged regions to the clients
+    ///
+    /// `data` contains the `Xrgb8888` pixels of the whole framebuffer with `stride` bytes per row,
+    /// of which only the damaged regions are copied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is too small for the current size of the framebuffer.
+    pub fn update(&mut self, data: &[u8], stride: usize, damage: &[Rectangle<i32, Physical>]) {
+        let row_len = self.size.w as usize * 4;
+        assert!(stride >= row_len && data.len() >= stride * (self.size.h.max(1) as usize - 1) + row_len);
+
+        let bounds = Rectangle::from_loc_and_size((0, 0), self.size);
+        for rect in damage.iter().filter_map(|rect| rect.intersection(bounds)) {
+            let (x, w) = (rect.loc.x as usize * 4, rect.size.w as usize * 4);
+            for y in rect.loc.y as usize..(rect.loc.y + rect.size.h) as usize {
+                self.framebuffer[y * row_len + x..y * row_len + x + w]
+                    .copy_from_slice(&data[y * stride + x..y * stride + x + w]);
+            }
+            for client in &mut self.clients {
+                client.add_damage(rect);
+            }
+        }
+
+        for client in &mut self.clients {
+            client.send_update(&self.framebuffer, self.size);
+        }
+    }
+
+    /// Resize the framebuffer and notify the clients
+    ///
+    /// The framebuffer is cleared and needs to be updated afterwards. Clients not supporting
+    /// size changes are disconnected.
+    pub fn resize(&mut self, size: Size<i32, Physical>) {
+        let _guard = self.span.enter();
+        info!(?size, "Resizing framebuffer");
+
+        self.size = size;
+        self.framebuffer = vec![0; framebuffer_len(size)];
+        let requester = self.resize_request.take();
+        for client in self
+            .clients
+            .iter_mut()
+            .filter(|client| client.state == ClientState::Ready)
+        {
+            if client.extended_desktop_size {
+                let reason = if requester == Some(client.id) { 1 } else { 2 };
+                client.size_changed = Some((reason, 0));
+            } else if client.desktop_size {
+                client.size_changed = Some((0, 0));
+            } else {
+                client.close(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "client does not support desktop size changes",
+                ));
+                continue;
+            }
+            client.damage = vec![Rectangle::from_loc_and_size((0, 0), size)];
+            client.send_update(&self.framebuffer, size);
+        }
+    }
+
+    /// Decline the pending [`VncEvent::ResizeRequested`]
+    pub fn reject_resize(&mut self) {
+        if let Some(requester) = self.resize_request.take() {
+            if let Some(client) = self.clients.iter_mut().find(|client| client.id == requester) {
+                // prohibited by the server
+                client.size_changed = Some((1, 1));
+                client.send_update(&self.framebuffer, self.size);
+            }
+        }
+    }
+
+    fn accept(&mut self) -> io::Result<bool> {
+        let mut accepted = false;
+        loop {
+            let (stream, addr) = match self.listener.get_ref().accept() {
+                Ok(conn) => conn,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(accepted),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            stream.set_nonblocking(true)?;
+            let _ = stream.set_nodelay(true);
+
+            let id = self.next_client;
+            self.next_client += 1;
+            info!(client = id, %addr, "New VNC client");
+
+            let mut client = Client {
+                id,
+                addr,
+                stream: Generic::new(stream, Interest::BOTH, Mode::Edge),
+                registered: false,
+                state: ClientState::Version,
+                minor_version: 8,
+                input: Vec::new(),
+                skip: 0,
+                output: protocol::VERSION.to_vec(),
+                closed: false,
+                format: PixelFormat::default(),
+                desktop_size: false,
+                extended_desktop_size: false,
+                update_requested: false,
+                damage: Vec::new(),
+                size_changed: None,
+                buttons: 0,
+                pointer: None,
+                pressed: HashSet::new(),
+            };
+            if let Err(err) = client.flush() {
+                client.close(err);
+                continue;
+            }
+            self.clients.push(client);
+            accepted = true;
+        }
+    }
+
+    fn dispatch_client(&mut self, idx: usize, readiness: Readiness, emit: &mut dyn FnMut(VncEvent)) {
+        let res = (|| {
+            let client = &mut self.clients[idx];
+            if readiness.writable {
+                client.flush()?;
+            }
+            if !(readiness.readable || readiness.error) {
+                return self.handle_input(idx, emit);
+            }
+            // the stream is edge triggered, so keep reading until it is drained
+            while self.clients[idx].read()? {
+                self.handle_input(idx, emit)?;
+                if self.clients[idx].input.len() == MAX_INPUT_LEN {
+                    return Err(invalid_data("message exceeds the input buffer"));
+                }
+            }
+            self.handle_input(idx, emit)
+        })();
+        let client = &mut self.clients[idx];
+        match res {
+            Ok(()) => client.send_update(&self.framebuffer, self.size),
+            Err(err) => client.close(err),
+        }
+    }
+
+    fn handle_input(&mut self, idx: usize, emit: &mut dyn FnMut(VncEvent)) -> io::Result<()> {
+        loop {
+            let client = &mut self.clients[idx];
+            match client.state {
+                ClientState::Version => {
+                    if client.input.len() < protocol::VERSION.len() {
+                        break;
+                    }
+                    let version = client.input.drain(..protocol::VERSION.len()).collect::<Vec<_>>();
+                    let minor = std::str::from_utf8(&version[8..11])
+                        .ok()
+                        .and_then(|minor| minor.parse::<u8>().ok())
+                        .filter(|_| version.starts_with(b"RFB 003.") && version[11] == b'\n')
+                        .ok_or_else(|| invalid_data("invalid protocol version"))?;
+                    client.minor_version = minor;
+                    if minor >= 7 {
+                        client.output.extend_from_slice(&[1, protocol::SECURITY_NONE]);
+                        client.state = ClientState::Security;
+                    } else {
+                        client
+                            .output
+                            .extend_from_slice(&(protocol::SECURITY_NONE as u32).to_be_bytes());
+                        client.state = ClientState::Init;
+                    }
+                }
+                ClientState::Security => {
+                    let Some(&security) = client.input.first() else {
+                        break;
+                    };
+                    client.input.drain(..1);
+                    if security != protocol::SECURITY_NONE {
+                        return Err(invalid_data("unsupported security type"));
+                    }
+                    if client.minor_version >= 8 {
+                        client.output.extend_from_slice(&0u32.to_be_bytes());
+                    }
+                    client.state = ClientState::Init;
+                }
+                ClientState::Init => {
+                    if client.input.is_empty() {
+                        break;
+                    }
+                    // clients are always shared
+                    client.input.drain(..1);
+                    protocol::write_server_init(self.size, &self.name, &mut client.output);
+                    client.state = ClientState::Ready;
+                    emit(VncEvent::Input(InputEvent::DeviceAdded {
+                        device: client.device(),
+                    }));
+                }
+                ClientState::Ready if client.skip > 0 => {
+                    if client.input.is_empty() {
+                        break;
+                    }
+                    let len = client.skip.min(client.input.len());
+                    client.input.drain(..len);
+                    client.skip -= len;
+                }
+                ClientState::Ready => {
+                    let (msg, len) = match ClientMessage::parse(&client.input) {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(protocol::UnknownMessage(ty)) => {
+                            return Err(invalid_data(format!("unknown message type {}", ty)))
+                        }
+                    };
+                    client.input.drain(..len);
+                    self.handle_message(idx, msg, emit)?;
+                }
+            }
+        }
+        self.clients[idx].flush()
+    }
+
+    fn handle_message(
+        &mut self,
+        idx: usize,
+        msg: ClientMessage,
+        emit: &mut dyn FnMut(VncEvent),
+    ) -> io::Result<()> {
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        let client = &mut self.clients[idx];
+        match msg {
+            ClientMessage::SetPixelFormat(format) => {
+                if !format.is_supported() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "unsupported pixel format",
+                    ));
+                }
+                client.format = format;
+            }
+            ClientMessage::SetEncodings(encodings) => {
+                client.desktop_size = encodings.contains(&protocol::ENCODING_DESKTOP_SIZE);
+                let extended = encodings.contains(&protocol::ENCODING_EXTENDED_DESKTOP_SIZE);
+                if extended && !client.extended_desktop_size {
+                    // announces support for size changes to the client
+                    client.size_changed = Some((0, 0));
+                }
+                client.extended_desktop_size = extended;
+            }
+            ClientMessage::FramebufferUpdateRequest { incremental, rect } => {
+                client.update_requested = true;
+                if !incremental {
+                    if let Some(rect) = rect.intersection(Rectangle::from_loc_and_size((0, 0), self.size)) {
+                        client.add_damage(rect);
+                    }
+                }
+            }
+            ClientMessage::KeyEvent { down, keysym } => {
+                let Some(&key) = self.keycodes.get(&keysym) else {
+                    debug!(client = client.id, keysym, "Ignoring key without key code");
+                    return Ok(());
+                };
+                // clients send repeated key presses, repeat is handled by the compositor
+                let changed = if down {
+                    client.pressed.insert(key)
+                } else {
+                    client.pressed.remove(&key)
+                };
+                if changed {
+                    emit(VncEvent::Input(InputEvent::Keyboard {
+                        event: VncKeyboardInputEvent {
+                            time,
+                            device: client.device(),
+                            key,
+                            keysym,
+                            count: client.pressed.len() as u32,
+                            state: if down {
+                                KeyState::Pressed
+                            } else {
+                                KeyState::Released
+                            },
+                        },
+                    }));
+                }
+            }
+            ClientMessage::PointerEvent { buttons, x, y } => {
+                if client.pointer != Some((x, y)) {
+                    client.pointer = Some((x, y));
+                    emit(VncEvent::Input(InputEvent::PointerMotionAbsolute {
+                        event: VncMouseMovedEvent {
+                            time,
+                            device: client.device(),
+                            x: x as f64,
+                            y: y as f64,
+                            size: self.size,
+                        },
+                    }));
+                }
+                let changed = client.buttons ^ buttons;
+                client.buttons = buttons;
+                for bit in (0..8).filter(|bit| changed & (1 << bit) != 0) {
+                    let pressed = buttons & (1 << bit) != 0;
+                    // buttons 4 to 7 are scroll wheel steps
+                    let axis = match bit {
+                        3 => Some((Axis::Vertical, -1.)),
+                        4 => Some((Axis::Vertical, 1.)),
+                        5 => Some((Axis::Horizontal, -1.)),
+                        6 => Some((Axis::Horizontal, 1.)),
+                        _ => None,
+                    };
+                    let event = match axis {
+                        Some((axis, amount)) if pressed => InputEvent::PointerAxis {
+                            event: VncMouseWheelEvent {
+                                time,
+                                device: client.device(),
+                                axis,
+                                amount,
+                            },
+                        },
+                        Some(_) => continue,
+                        None => InputEvent::PointerButton {
+                            event: VncMouseInputEvent {
+                                time,
+                                device: client.device(),
+                                button: xorg_mouse_to_libinput(bit + 1),
+                                state: if pressed {
+                                    ButtonState::Pressed
+                                } else {
+                                    ButtonState::Released
+                                },
+                            },
+                        },
+                    };
+                    emit(VncEvent::Input(event));
+                }
+            }
+            // clipboard contents are not supported, the text itself is skipped
+            ClientMessage::ClientCutText { len } => client.skip = len as usize,
+            ClientMessage::SetDesktopSize { size } => {
+                if size.w <= 0 || size.h <= 0 {
+                    // invalid screen layout
+                    client.size_changed = Some((1, 3));
+                } else {
+                    self.resize_request = Some(client.id);
+                    emit(VncEvent::ResizeRequested {
+                        client: client.id,
+                        size,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_closed(&mut self, emit: &mut dyn FnMut(VncEvent)) {
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        for client in self.clients.iter_mut().filter(|client| client.closed) {
+            if client.state != ClientState::Ready {
+                continue;
+            }
+            // release everything still held by the client
+            let device = client.device();
+            let pressed = std::mem::take(&mut client.pressed);
+            for (idx, key) in pressed.iter().enumerate() {
+                emit(VncEvent::Input(InputEvent::Keyboard {
+                    event: VncKeyboardInputEvent {
+                        time,
+                        device: device.clone(),
+                        key: *key,
+                        keysym: 0,
+                        count: (pressed.len() - idx - 1) as u32,
+                        state: KeyState::Released,
+                    },
+                }));
+            }
+            for bit in [0, 1, 2, 7]
+                .into_iter()
+                .filter(|bit| client.buttons & (1 << bit) != 0)
+            {
+                emit(VncEvent::Input(InputEvent::PointerButton {
+                    event: VncMouseInputEvent {
+                        time,
+                        device: device.clone(),
+                        button: xorg_mouse_to_libinput(bit + 1),
+                        state: ButtonState::Released,
+                    },
+                }));
+            }
+            emit(VncEvent::Input(InputEvent::DeviceRemoved { device }));
+        }
+        if self.resize_request.is_some_and(|requester| {
+            self.clients
+                .iter()
+                .any(|client| client.id == requester && client.closed)
+        }) {
+            self.resize_request = None;
+        }
+        // dropping the sources removes them from the event loop
+        self.clients.retain(|client| !client.closed);
+    }
+}
+
+fn framebuffer_len(size: Size<i32, Physical>) -> usize {
+    size.w.max(0) as usize * size.h.max(0) as usize * 4
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+impl EventSource for VncBackend {
+    type Event = VncEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(VncEvent, &mut ()),
+    {
+        let span = self.span.clone();
+        let _guard = span.enter();
+        let mut emit = |event| callback(event, &mut ());
+
+        let mut accept = false;
+        self.listener.process_events(readiness, token, |_, _| {
+            accept = true;
+            Ok(PostAction::Continue)
+        })?;
+        let accepted = accept && self.accept()?;
+
+        for idx in 0..self.clients.len() {
+            let mut ready = None;
+            self.clients[idx]
+                .stream
+                .process_events(readiness, token, |readiness, _| {
+                    ready = Some(readiness);
+                    Ok(PostAction::Continue)
+                })?;
+            if let Some(readiness) = ready {
+                self.dispatch_client(idx, readiness, &mut emit);
+            }
+        }
+        self.remove_closed(&mut emit);
+
+        Ok(if accepted {
+            // registers the new clients
+            PostAction::Reregister
+        } else {
+            PostAction::Continue
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.register(poll, factory)?;
+        for client in &mut self.clients {
+            client.stream.register(poll, factory)?;
+            client.registered = true;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.reregister(poll, factory)?;
+        for client in &mut self.clients {
+            if client.registered {
+                client.stream.reregister(poll, factory)?;
+            } else {
+                client.stream.register(poll, factory)?;
+                client.registered = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.listener.unregister(poll)?;
+        for client in self.clients.iter_mut().filter(|client| client.registered) {
+            client.stream.unregister(poll)?;
+            client.registered = false;
+        }
+        Ok(())
+    }
+}
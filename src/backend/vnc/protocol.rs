@@ -0,0 +1,357 @@
+//! Message encoding and parsing of the remote framebuffer protocol (RFC 6143)
+
+use crate::utils::{Physical, Rectangle, Size};
+
+pub(super) const VERSION: &[u8; 12] = b"RFB 003.008\n";
+pub(super) const SECURITY_NONE: u8 = 1;
+
+pub(super) const ENCODING_RAW: i32 = 0;
+pub(super) const ENCODING_DESKTOP_SIZE: i32 = -223;
+pub(super) const ENCODING_EXTENDED_DESKTOP_SIZE: i32 = -308;
+
+/// Pixel format of the framebuffer updates sent to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PixelFormat {
+    pub bits_per_pixel: u8,
+    pub depth: u8,
+    pub big_endian: bool,
+    pub true_color: bool,
+    pub red_max: u16,
+    pub green_max: u16,
+    pub blue_max: u16,
+    pub red_shift: u8,
+    pub green_shift: u8,
+    pub blue_shift: u8,
+}
+
+impl Default for PixelFormat {
+    /// The native format of the server, matching `Xrgb8888`
+    fn default() -> Self {
+        PixelFormat {
+            bits_per_pixel: 32,
+            depth: 24,
+            big_endian: false,
+            true_color: true,
+            red_max: 255,
+            green_max: 255,
+            blue_max: 255,
+            red_shift: 16,
+            green_shift: 8,
+            blue_shift: 0,
+        }
+    }
+}
+
+impl PixelFormat {
+    fn parse(buf: &[u8]) -> PixelFormat {
+        PixelFormat {
+            bits_per_pixel: buf[0],
+            depth: buf[1],
+            big_endian: buf[2] != 0,
+            true_color: buf[3] != 0,
+            red_max: u16::from_be_bytes([buf[4], buf[5]]),
+            green_max: u16::from_be_bytes([buf[6], buf[7]]),
+            blue_max: u16::from_be_bytes([buf[8], buf[9]]),
+            red_shift: buf[10],
+            green_shift: buf[11],
+            blue_shift: buf[12],
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[
+            self.bits_per_pixel,
+            self.depth,
+            self.big_endian as u8,
+            self.true_color as u8,
+        ]);
+        out.extend_from_slice(&self.red_max.to_be_bytes());
+        out.extend_from_slice(&self.green_max.to_be_bytes());
+        out.extend_from_slice(&self.blue_max.to_be_bytes());
+        out.extend_from_slice(&[self.red_shift, self.green_shift, self.blue_shift, 0, 0, 0]);
+    }
+
+    /// Whether framebuffer updates can be encoded in this format
+    ///
+    /// Color maps are not supported, every channel has to fit into a pixel.
+    pub fn is_supported(&self) -> bool {
+        let fits = |max: u16, shift: u8| {
+            let bits = u16::BITS - max.leading_zeros();
+            shift < self.bits_per_pixel && shift as u32 + bits <= self.bits_per_pixel as u32
+        };
+        self.true_color
+            && matches!(self.bits_per_pixel, 8 | 16 | 32)
+            && fits(self.red_max, self.red_shift)
+            && fits(self.green_max, self.green_shift)
+            && fits(self.blue_max, self.blue_shift)
+    }
+
+    fn encode(&self, xrgb: u32, out: &mut Vec<u8>) {
+        let channel = |value: u32, max: u16, shift: u8| ((value & 0xff) * max as u32 / 255) << shift;
+        let pixel = channel(xrgb >> 16, self.red_max, self.red_shift)
+            | channel(xrgb >> 8, self.green_max, self.green_shift)
+            | channel(xrgb, self.blue_max, self.blue_shift);
+        match (self.bits_per_pixel, self.big_endian) {
+            (8, _) => out.push(pixel as u8),
+            (16, false) => out.extend_from_slice(&(pixel as u16).to_le_bytes()),
+            (16, true) => out.extend_from_slice(&(pixel as u16).to_be_bytes()),
+            (_, false) => out.extend_from_slice(&pixel.to_le_bytes()),
+            (_, true) => out.extend_from_slice(&pixel.to_be_bytes()),
+        }
+    }
+}
+
+/// Messages sent by a client after the handshake
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ClientMessage {
+    SetPixelFormat(PixelFormat),
+    SetEncodings(Vec<i32>),
+    FramebufferUpdateRequest {
+        incremental: bool,
+        rect: Rectangle<i32, Physical>,
+    },
+    KeyEvent {
+        down: bool,
+        keysym: u32,
+    },
+    PointerEvent {
+        buttons: u8,
+        x: u16,
+        y: u16,
+    },
+    ClientCutText {
+        /// Length of the text following the message
+        len: u32,
+    },
+    SetDesktopSize {
+        size: Size<i32, Physical>,
+    },
+}
+
+/// Error of an unknown message type, which makes the rest of the stream unparsable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct UnknownMessage(pub u8);
+
+fn be_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn be_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+impl ClientMessage {
+    /// Parses the next message of `buf`, returning it with its length
+    ///
+    /// Returns `Ok(None)` if the message is incomplete.
+    pub fn parse(buf: &[u8]) -> Result<Option<(ClientMessage, usize)>, UnknownMessage> {
+        let Some(&ty) = buf.first() else {
+            return Ok(None);
+        };
+        // length of the fixed part of the message
+        let header = match ty {
+            0 => 20,
+            2 => 4,
+            3 => 10,
+            4 => 8,
+            5 => 6,
+            6 => 8,
+            251 => 8,
+            ty => return Err(UnknownMessage(ty)),
+        };
+        if buf.len() < header {
+            return Ok(None);
+        }
+        // the text of `ClientCutText` is not part of the message, as it might be arbitrarily long
+        let len = match ty {
+            2 => header + 4 * be_u16(buf, 2) as usize,
+            251 => header + 16 * buf[6] as usize,
+            _ => header,
+        };
+        if buf.len() < len {
+            return Ok(None);
+        }
+
+        let msg = match ty {
+            0 => ClientMessage::SetPixelFormat(PixelFormat::parse(&buf[4..])),
+            2 => ClientMessage::SetEncodings(
+                buf[4..len]
+                    .chunks_exact(4)
+                    .map(|encoding| i32::from_be_bytes(encoding.try_into().unwrap()))
+                    .collect(),
+            ),
+            3 => ClientMessage::FramebufferUpdateRequest {
+                incremental: buf[1] != 0,
+                rect: Rectangle::from_loc_and_size(
+                    (be_u16(buf, 2) as i32, be_u16(buf, 4) as i32),
+                    (be_u16(buf, 6) as i32, be_u16(buf, 8) as i32),
+                ),
+            },
+            4 => ClientMessage::KeyEvent {
+                down: buf[1] != 0,
+                keysym: be_u32(buf, 4),
+            },
+            5 => ClientMessage::PointerEvent {
+                buttons: buf[1],
+                x: be_u16(buf, 2),
+                y: be_u16(buf, 4),
+            },
+            6 => ClientMessage::ClientCutText { len: be_u32(buf, 4) },
+            _ => ClientMessage::SetDesktopSize {
+                size: (be_u16(buf, 2) as i32, be_u16(buf, 4) as i32).into(),
+            },
+        };
+        Ok(Some((msg, len)))
+    }
+}
+
+/// Writes the `ServerInit` message
+pub(super) fn write_server_init(size: Size<i32, Physical>, name: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(size.w as u16).to_be_bytes());
+    out.extend_from_slice(&(size.h as u16).to_be_bytes());
+    PixelFormat::default().write(out);
+    out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Writes the header of a `FramebufferUpdate` message with `rects` rectangles
+pub(super) fn write_update_header(rects: u16, out: &mut Vec<u8>) {
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&rects.to_be_bytes());
+}
+
+fn write_rect_header(rect: Rectangle<i32, Physical>, encoding: i32, out: &mut Vec<u8>) {
+    for value in [rect.loc.x, rect.loc.y, rect.size.w, rect.size.h] {
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    }
+    out.extend_from_slice(&encoding.to_be_bytes());
+}
+
+/// Writes a raw encoded rectangle of an `Xrgb8888` framebuffer
+pub(super) fn write_raw_rect(
+    framebuffer: &[u8],
+    stride: usize,
+    rect: Rectangle<i32, Physical>,
+    format: &PixelFormat,
+    out: &mut Vec<u8>,
+) {
+    write_rect_header(rect, ENCODING_RAW, out);
+    out.reserve(rect.size.w as usize * rect.size.h as usize * format.bits_per_pixel as usize / 8);
+    for y in rect.loc.y..rect.loc.y + rect.size.h {
+        let start = y as usize * stride + rect.loc.x as usize * 4;
+        let row = &framebuffer[start..start + rect.size.w as usize * 4];
+        for pixel in row.chunks_exact(4) {
+            format.encode(u32::from_le_bytes(pixel.try_into().unwrap()), out);
+        }
+    }
+}
+
+/// Writes a `DesktopSize` pseudo-rectangle
+pub(super) fn write_desktop_size(size: Size<i32, Physical>, out: &mut Vec<u8>) {
+    write_rect_header(
+        Rectangle::from_loc_and_size((0, 0), size),
+        ENCODING_DESKTOP_SIZE,
+        out,
+    );
+}
+
+/// Writes an `ExtendedDesktopSize` pseudo-rectangle describing a single screen
+pub(super) fn write_extended_desktop_size(
+    size: Size<i32, Physical>,
+    reason: u16,
+    status: u16,
+    out: &mut Vec<u8>,
+) {
+    write_rect_header(
+        Rectangle::from_loc_and_size((reason as i32, status as i32), size),
+        ENCODING_EXTENDED_DESKTOP_SIZE,
+        out,
+    );
+    out.extend_from_slice(&[1, 0, 0, 0]);
+    // screen id, position, size and flags
+    out.extend_from_slice(&0u32.to_be_bytes());
+    for value in [0, 0, size.w as u16, size.h as u16] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend_from_slice(&0u32.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_raw_rect, ClientMessage, PixelFormat, UnknownMessage};
+    use crate::utils::Rectangle;
+
+    #[test]
+    fn parse_messages_and_encode_pixels() {
+        let mut buf = vec![3, 1, 0, 0, 0, 0, 0, 4, 0, 2];
+        buf.extend_from_slice(&[4, 1, 0, 0, 0, 0, 0xff, 0x0d]);
+        buf.extend_from_slice(&[2, 0, 0, 2, 0, 0, 0, 0]);
+
+        let (msg, len) = ClientMessage::parse(&buf).unwrap().unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::FramebufferUpdateRequest {
+                incremental: true,
+                rect: Rectangle::from_loc_and_size((0, 0), (4, 2)),
+            }
+        );
+        let (msg, len2) = ClientMessage::parse(&buf[len..]).unwrap().unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::KeyEvent {
+                down: true,
+                keysym: 0xff0d
+            }
+        );
+        // the encoding list is incomplete
+        assert_eq!(ClientMessage::parse(&buf[len + len2..]), Ok(None));
+        assert_eq!(ClientMessage::parse(&[42]), Err(UnknownMessage(42)));
+
+        let rgb565 = PixelFormat {
+            bits_per_pixel: 16,
+            depth: 16,
+            big_endian: true,
+            red_max: 31,
+            green_max: 63,
+            blue_max: 31,
+            red_shift: 11,
+            green_shift: 5,
+            blue_shift: 0,
+            ..PixelFormat::default()
+        };
+        let framebuffer = [0x00, 0x00, 0xff, 0x00, 0xff, 0xff, 0xff, 0x00];
+        let mut out = Vec::new();
+        write_raw_rect(
+            &framebuffer,
+            8,
+            Rectangle::from_loc_and_size((0, 0), (2, 1)),
+            &rgb565,
+            &mut out,
+        );
+        assert_eq!(&out[12..], &[0xf8, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn reject_invalid_pixel_formats() {
+        assert!(PixelFormat::default().is_supported());
+        let shifted = PixelFormat {
+            red_shift: 40,
+            ..PixelFormat::default()
+        };
+        assert!(!shifted.is_supported());
+        let overflowing = PixelFormat {
+            bits_per_pixel: 16,
+            red_max: 255,
+            red_shift: 11,
+            ..PixelFormat::default()
+        };
+        assert!(!overflowing.is_supported());
+
+        // cut text is skipped instead of waiting for all of it
+        let buf = [6, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, b'a'];
+        assert_eq!(
+            ClientMessage::parse(&buf),
+            Ok(Some((ClientMessage::ClientCutText { len: u32::MAX }, 8)))
+        );
+    }
+}
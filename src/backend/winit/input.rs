@@ -2,14 +2,14 @@ use std::path::PathBuf;
 
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, MouseButton as WinitMouseButton, MouseScrollDelta},
+    event::{ElementState, Ime, MouseButton as WinitMouseButton, MouseScrollDelta},
 };
 
 use crate::backend::input::{
     self, AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
     DeviceCapability, Event, InputBackend, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent,
     PointerButtonEvent, PointerMotionAbsoluteEvent, TouchCancelEvent, TouchDownEvent, TouchEvent,
-    TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+    TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
 };
 
 /// Marker used to define the `InputBackend` types for the winit backend.
@@ -345,6 +345,54 @@ impl TouchEvent<WinitInput> for WinitTouchCancelledEvent {
     }
 }
 
+/// Winit-Backend internal event wrapping `winit`'s types into a [`TouchFrameEvent`]
+///
+/// `winit` delivers every touch point separately, so a frame follows every touch down, motion and up event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WinitTouchFrameEvent {
+    pub(crate) time: u64,
+}
+
+impl Event<WinitInput> for WinitTouchFrameEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> WinitVirtualDevice {
+        WinitVirtualDevice
+    }
+}
+
+impl TouchFrameEvent<WinitInput> for WinitTouchFrameEvent {}
+
+/// Winit-Backend internal event wrapping an input method event of `winit`
+///
+/// These are delivered as [`InputEvent::Special`](crate::backend::input::InputEvent::Special) events,
+/// once input methods were enabled for the window using
+/// [`set_ime_allowed`](winit::window::Window::set_ime_allowed).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WinitImeEvent {
+    pub(crate) time: u64,
+    pub(crate) ime: Ime,
+}
+
+impl WinitImeEvent {
+    /// The input method event, e.g. the pre-edit or commit string of a composition
+    pub fn ime(&self) -> &Ime {
+        &self.ime
+    }
+}
+
+impl Event<WinitInput> for WinitImeEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> WinitVirtualDevice {
+        WinitVirtualDevice
+    }
+}
+
 impl From<ElementState> for KeyState {
     #[inline]
     fn from(state: ElementState) -> Self {
@@ -402,7 +450,7 @@ impl InputBackend for WinitInput {
     type TouchUpEvent = WinitTouchEndedEvent;
     type TouchMotionEvent = WinitTouchMovedEvent;
     type TouchCancelEvent = WinitTouchCancelledEvent;
-    type TouchFrameEvent = UnusedEvent;
+    type TouchFrameEvent = WinitTouchFrameEvent;
     type TabletToolAxisEvent = UnusedEvent;
     type TabletToolProximityEvent = UnusedEvent;
     type TabletToolTipEvent = UnusedEvent;
@@ -410,5 +458,5 @@ impl InputBackend for WinitInput {
 
     type SwitchToggleEvent = UnusedEvent;
 
-    type SpecialEvent = UnusedEvent;
+    type SpecialEvent = WinitImeEvent;
 }
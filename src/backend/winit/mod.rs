@@ -17,10 +17,25 @@
 //!
 //! The other types in this module are the instances of the associated types of these
 //! two traits for the winit backend.
+//!
+//! ## Input
+//!
+//! Keyboard, pointer and touch input of all windows is reported through a single
+//! [`WinitVirtualDevice`]. Input method events are forwarded as
+//! [`InputEvent::Special`] events carrying a [`WinitImeEvent`], once enabled using
+//! [`WinitWindow::set_ime_allowed`]. Tablet tools are not reported, as `winit` provides no tablet events.
+//!
+//! ## Multiple windows
+//!
+//! Additional windows can be opened at runtime using [`WinitGraphicsBackend::create_window`], e.g. to
+//! emulate a multi-monitor setup by mapping every window to its own output. Each window has its own
+//! [`WinitWindowSurface`] and scale factor, while the renderer of the backend is shared by all of them.
+//! Use [`WinitEventLoop::dispatch_new_window_events`] to find out which window an event belongs to.
 
+use std::collections::HashMap;
 use std::io::Error as IoError;
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use calloop::generic::Generic;
@@ -43,8 +58,8 @@ use crate::{
     backend::{
         egl::{
             context::{GlAttributes, PixelFormatRequirements},
-            display::EGLDisplay,
-            native, EGLContext, EGLSurface, Error as EGLError,
+            display::{EGLDisplay, PixelFormat},
+            ffi, native, EGLContext, EGLSurface, Error as EGLError,
         },
         input::InputEvent,
         renderer::{
@@ -135,53 +150,19 @@ where
                     EGLContext::new_with_config(&display, gl_attributes, PixelFormatRequirements::_8_bit())
                 })?;
 
-        let (surface, is_x11) = match window.window_handle().map(|handle| handle.as_raw()) {
-            Ok(RawWindowHandle::Wayland(handle)) => {
-                debug!("Winit backend: Wayland");
-                let size = window.inner_size();
-                let surface = unsafe {
-                    wegl::WlEglSurface::new_from_raw(
-                        handle.surface.as_ptr() as *mut _,
-                        size.width as i32,
-                        size.height as i32,
-                    )
-                }
-                .map_err(|err| Error::Surface(err.into()))?;
-                unsafe {
-                    (
-                        EGLSurface::new(
-                            &display,
-                            context.pixel_format().unwrap(),
-                            context.config_id(),
-                            surface,
-                        )
-                        .map_err(EGLError::CreationFailed)?,
-                        false,
-                    )
-                }
-            }
-            Ok(RawWindowHandle::Xlib(handle)) => {
-                debug!("Winit backend: X11");
-                unsafe {
-                    (
-                        EGLSurface::new(
-                            &display,
-                            context.pixel_format().unwrap(),
-                            context.config_id(),
-                            native::XlibWindow(handle.window),
-                        )
-                        .map_err(EGLError::CreationFailed)?,
-                        true,
-                    )
-                }
-            }
-            _ => panic!("only running on Wayland or with Xlib is supported"),
-        };
+        let (surface, is_x11) = create_surface(
+            &display,
+            context.pixel_format().unwrap(),
+            context.config_id(),
+            &window,
+        )?;
 
         let _ = context.unbind();
         (display, context, surface, is_x11)
     };
 
+    let pixel_format = context.pixel_format().unwrap();
+    let config_id = context.config_id();
     let egl = Rc::new(surface);
     let renderer = unsafe { GlesRenderer::new(context)?.into() };
     let damage_tracking = display.supports_damage();
@@ -193,21 +174,31 @@ where
 
     Ok((
         WinitGraphicsBackend {
-            window: window.clone(),
-            span: span.clone(),
-            _display: display,
-            egl_surface: egl,
-            damage_tracking,
-            bind_size: None,
             renderer,
+            display,
+            pixel_format,
+            config_id,
+            surface: WinitWindowSurface {
+                window: window.clone(),
+                egl_surface: egl,
+                damage_tracking,
+                bind_size: None,
+                span: span.clone(),
+            },
         },
         WinitEventLoop {
             inner: WinitEventLoopInner {
-                scale_factor: window.scale_factor(),
+                windows: HashMap::from([(
+                    window.id(),
+                    WindowState {
+                        window: Arc::downgrade(&window),
+                        scale_factor: window.scale_factor(),
+                        is_x11,
+                    },
+                )]),
+                primary_window: window.id(),
                 clock: Clock::<Monotonic>::new(),
                 key_counter: 0,
-                window,
-                is_x11,
             },
             fake_token: None,
             event_loop,
@@ -217,6 +208,52 @@ where
     ))
 }
 
+/// Creates the `EGLSurface` of a window, returning whether the window is an X11 window
+fn create_surface(
+    display: &EGLDisplay,
+    pixel_format: PixelFormat,
+    config_id: ffi::egl::types::EGLConfig,
+    window: &WinitWindow,
+) -> Result<(EGLSurface, bool), Error> {
+    match window.window_handle().map(|handle| handle.as_raw()) {
+        Ok(RawWindowHandle::Wayland(handle)) => {
+            debug!("Winit backend: Wayland");
+            let size = window.inner_size();
+            let surface = unsafe {
+                wegl::WlEglSurface::new_from_raw(
+                    handle.surface.as_ptr() as *mut _,
+                    size.width as i32,
+                    size.height as i32,
+                )
+            }
+            .map_err(|err| Error::Surface(err.into()))?;
+            unsafe {
+                Ok((
+                    EGLSurface::new(display, pixel_format, config_id, surface)
+                        .map_err(EGLError::CreationFailed)?,
+                    false,
+                ))
+            }
+        }
+        Ok(RawWindowHandle::Xlib(handle)) => {
+            debug!("Winit backend: X11");
+            unsafe {
+                Ok((
+                    EGLSurface::new(
+                        display,
+                        pixel_format,
+                        config_id,
+                        native::XlibWindow(handle.window),
+                    )
+                    .map_err(EGLError::CreationFailed)?,
+                    true,
+                ))
+            }
+        }
+        _ => panic!("only running on Wayland or with Xlib is supported"),
+    }
+}
+
 /// Errors thrown by the `winit` backends
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -244,13 +281,10 @@ pub enum Error {
 #[derive(Debug)]
 pub struct WinitGraphicsBackend<R> {
     renderer: R,
-    // The display isn't used past this point but must be kept alive.
-    _display: EGLDisplay,
-    egl_surface: Rc<EGLSurface>,
-    window: Arc<WinitWindow>,
-    damage_tracking: bool,
-    bind_size: Option<Size<i32, Physical>>,
-    span: tracing::Span,
+    display: EGLDisplay,
+    pixel_format: PixelFormat,
+    config_id: ffi::egl::types::EGLConfig,
+    surface: WinitWindowSurface,
 }
 
 impl<R> WinitGraphicsBackend<R>
@@ -260,18 +294,17 @@ where
 {
     /// Window size of the underlying window
     pub fn window_size(&self) -> Size<i32, Physical> {
-        let (w, h): (i32, i32) = self.window.inner_size().into();
-        (w, h).into()
+        self.surface.window_size()
     }
 
     /// Scale factor of the underlying window.
     pub fn scale_factor(&self) -> f64 {
-        self.window.scale_factor()
+        self.surface.scale_factor()
     }
 
     /// Reference to the underlying window
     pub fn window(&self) -> &WinitWindow {
-        &self.window
+        self.surface.window()
     }
 
     /// Access the underlying renderer
@@ -280,9 +313,119 @@ where
     }
 
     /// Bind the underlying window to the underlying renderer.
-    #[instrument(level = "trace", parent = &self.span, skip(self))]
-    #[profiling::function]
     pub fn bind(&mut self) -> Result<(), crate::backend::SwapBuffersError> {
+        self.surface.bind(&mut self.renderer)
+    }
+
+    /// Retrieve the underlying `EGLSurface` for advanced operations
+    ///
+    /// **Note:** Don't carelessly use this to manually bind the renderer to the surface,
+    /// `WinitGraphicsBackend::bind` transparently handles window resizes for you.
+    pub fn egl_surface(&self) -> Rc<EGLSurface> {
+        self.surface.egl_surface()
+    }
+
+    /// Retrieve the buffer age of the current backbuffer of the window.
+    ///
+    /// This will only return a meaningful value, if this `WinitGraphicsBackend`
+    /// is currently bound (by previously calling [`WinitGraphicsBackend::bind`]).
+    ///
+    /// Otherwise and on error this function returns `None`.
+    /// If you are using this value actively e.g. for damage-tracking you should
+    /// likely interpret an error just as if "0" was returned.
+    pub fn buffer_age(&self) -> Option<usize> {
+        self.surface.buffer_age()
+    }
+
+    /// Submits the back buffer to the window by swapping, requires the window to be previously
+    /// bound (see [`WinitGraphicsBackend::bind`]).
+    pub fn submit(
+        &mut self,
+        damage: Option<&[Rectangle<i32, Physical>]>,
+    ) -> Result<(), crate::backend::SwapBuffersError> {
+        self.surface.submit(damage)
+    }
+
+    /// Open an additional window rendered to by the renderer of this backend
+    ///
+    /// Events of the new window are dispatched by the given [`WinitEventLoop`], which has to be the
+    /// event loop created together with this backend. The window is closed once the returned
+    /// [`WinitWindowSurface`] is dropped.
+    pub fn create_window(
+        &self,
+        event_loop: &mut WinitEventLoop,
+        attributes: WindowAttributes,
+    ) -> Result<WinitWindowSurface, Error> {
+        let span = info_span!(parent: &self.surface.span, "winit_window", window = tracing::field::Empty);
+        let _guard = span.enter();
+
+        // SAFETY: we don't drop event loop ourselves.
+        #[allow(deprecated)]
+        let window = Arc::new(unsafe { event_loop.event_loop.get_mut() }.create_window(attributes)?);
+        span.record("window", Into::<u64>::into(window.id()));
+        debug!("Window created");
+
+        let (surface, is_x11) = create_surface(&self.display, self.pixel_format, self.config_id, &window)?;
+        event_loop.inner.windows.insert(
+            window.id(),
+            WindowState {
+                window: Arc::downgrade(&window),
+                scale_factor: window.scale_factor(),
+                is_x11,
+            },
+        );
+
+        drop(_guard);
+        Ok(WinitWindowSurface {
+            window,
+            egl_surface: Rc::new(surface),
+            damage_tracking: self.display.supports_damage(),
+            bind_size: None,
+            span,
+        })
+    }
+}
+
+/// Window created by `winit` with an `EGLSurface` for rendering
+///
+/// Every [`WinitGraphicsBackend`] renders to its own window, additional windows are created by
+/// [`WinitGraphicsBackend::create_window`] and rendered to by binding them to the renderer of the backend.
+#[derive(Debug)]
+pub struct WinitWindowSurface {
+    window: Arc<WinitWindow>,
+    egl_surface: Rc<EGLSurface>,
+    damage_tracking: bool,
+    bind_size: Option<Size<i32, Physical>>,
+    span: tracing::Span,
+}
+
+impl WinitWindowSurface {
+    /// Window size of the underlying window
+    pub fn window_size(&self) -> Size<i32, Physical> {
+        let (w, h): (i32, i32) = self.window.inner_size().into();
+        (w, h).into()
+    }
+
+    /// Scale factor of the underlying window.
+    pub fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    /// Reference to the underlying window
+    pub fn window(&self) -> &WinitWindow {
+        &self.window
+    }
+
+    /// Bind the underlying window to the given renderer.
+    ///
+    /// The renderer has to be the renderer of the [`WinitGraphicsBackend`] this window was created with.
+    #[instrument(level = "trace", parent = &self.span, skip(self, renderer))]
+    #[profiling::function]
+    pub fn bind<R>(&mut self, renderer: &mut R) -> Result<(), crate::backend::SwapBuffersError>
+    where
+        R: Bind<Rc<EGLSurface>>,
+        crate::backend::SwapBuffersError: From<<R as Renderer>::Error>,
+    {
         // NOTE: we must resize before making the current context current, otherwise the back
         // buffer will be latched. Some nvidia drivers may not like it, but a lot of wayland
         // software does the order that way due to mesa latching back buffer on each
@@ -293,7 +436,7 @@ where
         }
         self.bind_size = Some(window_size);
 
-        self.renderer.bind(self.egl_surface.clone())?;
+        renderer.bind(self.egl_surface.clone())?;
 
         Ok(())
     }
@@ -301,19 +444,17 @@ where
     /// Retrieve the underlying `EGLSurface` for advanced operations
     ///
     /// **Note:** Don't carelessly use this to manually bind the renderer to the surface,
-    /// `WinitGraphicsBackend::bind` transparently handles window resizes for you.
+    /// `WinitWindowSurface::bind` transparently handles window resizes for you.
     pub fn egl_surface(&self) -> Rc<EGLSurface> {
         self.egl_surface.clone()
     }
 
     /// Retrieve the buffer age of the current backbuffer of the window.
     ///
-    /// This will only return a meaningful value, if this `WinitGraphicsBackend`
-    /// is currently bound (by previously calling [`WinitGraphicsBackend::bind`]).
+    /// This will only return a meaningful value, if this window is currently bound
+    /// (by previously calling [`WinitWindowSurface::bind`]).
     ///
     /// Otherwise and on error this function returns `None`.
-    /// If you are using this value actively e.g. for damage-tracking you should
-    /// likely interpret an error just as if "0" was returned.
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     pub fn buffer_age(&self) -> Option<usize> {
         if self.damage_tracking {
@@ -324,7 +465,7 @@ where
     }
 
     /// Submits the back buffer to the window by swapping, requires the window to be previously
-    /// bound (see [`WinitGraphicsBackend::bind`]).
+    /// bound (see [`WinitWindowSurface::bind`]).
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     pub fn submit(
@@ -359,23 +500,30 @@ where
 
 #[derive(Debug)]
 struct WinitEventLoopInner {
-    window: Arc<WinitWindow>,
+    windows: HashMap<WindowId, WindowState>,
+    primary_window: WindowId,
     clock: Clock<Monotonic>,
     key_counter: u32,
-    is_x11: bool,
+}
+
+#[derive(Debug)]
+struct WindowState {
+    window: Weak<WinitWindow>,
     scale_factor: f64,
+    is_x11: bool,
 }
 
 /// Abstracted event loop of a [`WinitWindow`].
 ///
 /// You can register it into `calloop` or call
 /// [`dispatch_new_events`](WinitEventLoop::dispatch_new_events) periodically to receive any
-/// events.
+/// events. When registered into `calloop`, the id of the window an event belongs to is passed
+/// as the metadata of the event.
 #[derive(Debug)]
 pub struct WinitEventLoop {
     inner: WinitEventLoopInner,
     fake_token: Option<Token>,
-    pending_events: Vec<WinitEvent>,
+    pending_events: Vec<(WindowId, WinitEvent)>,
     event_loop: Generic<EventLoop<()>>,
     span: tracing::Span,
 }
@@ -392,11 +540,23 @@ impl WinitEventLoop {
     ///
     /// The linked [`WinitGraphicsBackend`] will error with a lost context and should
     /// not be used anymore as well.
+    pub fn dispatch_new_events<F>(&mut self, mut callback: F) -> PumpStatus
+    where
+        F: FnMut(WinitEvent),
+    {
+        self.dispatch_new_window_events(|_, event| callback(event))
+    }
+
+    /// Processes new events like [`dispatch_new_events`](WinitEventLoop::dispatch_new_events),
+    /// additionally providing the id of the window each event belongs to.
+    ///
+    /// This is needed to tell apart the events of windows created by
+    /// [`WinitGraphicsBackend::create_window`].
     #[instrument(level = "trace", parent = &self.span, skip_all)]
     #[profiling::function]
-    pub fn dispatch_new_events<F>(&mut self, callback: F) -> PumpStatus
+    pub fn dispatch_new_window_events<F>(&mut self, callback: F) -> PumpStatus
     where
-        F: FnMut(WinitEvent),
+        F: FnMut(WindowId, WinitEvent),
     {
         // SAFETY: we don't drop event loop ourselves.
         let event_loop = unsafe { self.event_loop.get_mut() };
@@ -411,55 +571,86 @@ impl WinitEventLoop {
     }
 }
 
-struct WinitEventLoopApp<'a, F: FnMut(WinitEvent)> {
+struct WinitEventLoopApp<'a, F: FnMut(WindowId, WinitEvent)> {
     inner: &'a mut WinitEventLoopInner,
     callback: F,
 }
 
-impl<'a, F: FnMut(WinitEvent)> WinitEventLoopApp<'a, F> {
+impl<'a, F: FnMut(WindowId, WinitEvent)> WinitEventLoopApp<'a, F> {
     fn timestamp(&self) -> u64 {
         self.inner.clock.now().as_micros()
     }
+
+    fn touch_frame(&mut self, window_id: WindowId) {
+        let event = InputEvent::TouchFrame {
+            event: WinitTouchFrameEvent {
+                time: self.timestamp(),
+            },
+        };
+        (self.callback)(window_id, WinitEvent::Input(event));
+    }
 }
 
-impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
+impl<'a, F: FnMut(WindowId, WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {
-        (self.callback)(WinitEvent::Input(InputEvent::DeviceAdded {
-            device: WinitVirtualDevice,
-        }));
+        (self.callback)(
+            self.inner.primary_window,
+            WinitEvent::Input(InputEvent::DeviceAdded {
+                device: WinitVirtualDevice,
+            }),
+        );
     }
 
-    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        let Some(state) = self.inner.windows.get(&window_id) else {
+            return;
+        };
+        let Some(window) = state.window.upgrade() else {
+            // the window was dropped
+            self.inner.windows.remove(&window_id);
+            return;
+        };
+        let is_x11 = state.is_x11;
+        let scale_factor = state.scale_factor;
+
         match event {
             WindowEvent::Resized(size) => {
                 trace!("Resizing window to {size:?}");
                 let (w, h): (i32, i32) = size.into();
 
-                (self.callback)(WinitEvent::Resized {
-                    size: (w, h).into(),
-                    scale_factor: self.inner.scale_factor,
-                });
+                (self.callback)(
+                    window_id,
+                    WinitEvent::Resized {
+                        size: (w, h).into(),
+                        scale_factor,
+                    },
+                );
             }
             WindowEvent::ScaleFactorChanged {
                 scale_factor: new_scale_factor,
                 ..
             } => {
                 trace!("Scale factor changed to {new_scale_factor}");
-                self.inner.scale_factor = new_scale_factor;
-                let (w, h): (i32, i32) = self.inner.window.inner_size().into();
-                (self.callback)(WinitEvent::Resized {
-                    size: (w, h).into(),
-                    scale_factor: self.inner.scale_factor,
-                });
+                if let Some(state) = self.inner.windows.get_mut(&window_id) {
+                    state.scale_factor = new_scale_factor;
+                }
+                let (w, h): (i32, i32) = window.inner_size().into();
+                (self.callback)(
+                    window_id,
+                    WinitEvent::Resized {
+                        size: (w, h).into(),
+                        scale_factor: new_scale_factor,
+                    },
+                );
             }
             WindowEvent::RedrawRequested => {
-                (self.callback)(WinitEvent::Redraw);
+                (self.callback)(window_id, WinitEvent::Redraw);
             }
             WindowEvent::CloseRequested => {
-                (self.callback)(WinitEvent::CloseRequested);
+                (self.callback)(window_id, WinitEvent::CloseRequested);
             }
             WindowEvent::Focused(focused) => {
-                (self.callback)(WinitEvent::Focus(focused));
+                (self.callback)(window_id, WinitEvent::Focus(focused));
             }
             WindowEvent::KeyboardInput {
                 event, is_synthetic, ..
@@ -480,10 +671,10 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         state: event.state,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
             }
             WindowEvent::CursorMoved { position, .. } => {
-                let size = self.inner.window.inner_size();
+                let size = window.inner_size();
                 let x = position.x / size.width as f64;
                 let y = position.y / size.height as f64;
                 let event = InputEvent::PointerMotionAbsolute {
@@ -493,7 +684,7 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         global_position: position,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
             }
             WindowEvent::MouseWheel { delta, .. } => {
                 let event = InputEvent::PointerAxis {
@@ -502,7 +693,7 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         delta,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
             }
             WindowEvent::MouseInput { state, button, .. } => {
                 let event = InputEvent::PointerButton {
@@ -510,10 +701,10 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         time: self.timestamp(),
                         button,
                         state,
-                        is_x11: self.inner.is_x11,
+                        is_x11,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
             }
             WindowEvent::Touch(Touch {
                 phase: TouchPhase::Started,
@@ -521,9 +712,9 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                 id,
                 ..
             }) => {
-                let size = self.inner.window.inner_size();
+                let size = window.inner_size();
                 let x = location.x / size.width as f64;
-                let y = location.y / size.width as f64;
+                let y = location.y / size.height as f64;
                 let event = InputEvent::TouchDown {
                     event: WinitTouchStartedEvent {
                         time: self.timestamp(),
@@ -533,7 +724,8 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                     },
                 };
 
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
+                self.touch_frame(window_id);
             }
             WindowEvent::Touch(Touch {
                 phase: TouchPhase::Moved,
@@ -541,9 +733,9 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                 id,
                 ..
             }) => {
-                let size = self.inner.window.inner_size();
+                let size = window.inner_size();
                 let x = location.x / size.width as f64;
-                let y = location.y / size.width as f64;
+                let y = location.y / size.height as f64;
                 let event = InputEvent::TouchMotion {
                     event: WinitTouchMovedEvent {
                         time: self.timestamp(),
@@ -553,7 +745,8 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                     },
                 };
 
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
+                self.touch_frame(window_id);
             }
 
             WindowEvent::Touch(Touch {
@@ -562,9 +755,9 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                 id,
                 ..
             }) => {
-                let size = self.inner.window.inner_size();
+                let size = window.inner_size();
                 let x = location.x / size.width as f64;
-                let y = location.y / size.width as f64;
+                let y = location.y / size.height as f64;
                 let event = InputEvent::TouchMotion {
                     event: WinitTouchMovedEvent {
                         time: self.timestamp(),
@@ -573,7 +766,7 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         id,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
 
                 let event = InputEvent::TouchUp {
                     event: WinitTouchEndedEvent {
@@ -582,7 +775,8 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                     },
                 };
 
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
+                self.touch_frame(window_id);
             }
 
             WindowEvent::Touch(Touch {
@@ -596,7 +790,14 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
                         id,
                     },
                 };
-                (self.callback)(WinitEvent::Input(event));
+                (self.callback)(window_id, WinitEvent::Input(event));
+            }
+            WindowEvent::Ime(ime) => {
+                let event = InputEvent::Special(WinitImeEvent {
+                    time: self.timestamp(),
+                    ime,
+                });
+                (self.callback)(window_id, WinitEvent::Input(event));
             }
             WindowEvent::DroppedFile(_)
             | WindowEvent::Destroyed
@@ -607,7 +808,6 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
             | WindowEvent::KeyboardInput { .. }
             | WindowEvent::HoveredFile(_)
             | WindowEvent::HoveredFileCancelled
-            | WindowEvent::Ime(_)
             | WindowEvent::Moved(_)
             | WindowEvent::Occluded(_)
             | WindowEvent::DoubleTapGesture { .. }
@@ -623,7 +823,7 @@ impl<'a, F: FnMut(WinitEvent)> ApplicationHandler for WinitEventLoopApp<'a, F> {
 
 impl EventSource for WinitEventLoop {
     type Event = WinitEvent;
-    type Metadata = ();
+    type Metadata = WindowId;
     type Ret = ();
     type Error = IoError;
 
@@ -631,13 +831,13 @@ impl EventSource for WinitEventLoop {
 
     fn before_sleep(&mut self) -> calloop::Result<Option<(calloop::Readiness, calloop::Token)>> {
         let mut pending_events = std::mem::take(&mut self.pending_events);
-        let callback = |event| {
-            pending_events.push(event);
+        let callback = |window_id, event| {
+            pending_events.push((window_id, event));
         };
         // NOTE: drain winit's event loop before going to sleep, so we can
         // wake up if other thread has woken up underlying winit loop, like other
         // event queue got dispatched during that.
-        self.dispatch_new_events(callback);
+        self.dispatch_new_window_events(callback);
         self.pending_events = pending_events;
         if self.pending_events.is_empty() {
             Ok(None)
@@ -655,11 +855,11 @@ impl EventSource for WinitEventLoop {
     where
         F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
     {
-        let mut callback = |event| callback(event, &mut ());
-        for event in self.pending_events.drain(..) {
-            callback(event);
+        let mut callback = |mut window_id, event| callback(event, &mut window_id);
+        for (window_id, event) in self.pending_events.drain(..) {
+            callback(window_id, event);
         }
-        Ok(match self.dispatch_new_events(callback) {
+        Ok(match self.dispatch_new_window_events(callback) {
             PumpStatus::Continue => PostAction::Continue,
             PumpStatus::Exit(_) => PostAction::Remove,
         })
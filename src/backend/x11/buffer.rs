@@ -23,6 +23,10 @@
 //! the X server will notify when the frame has been presented to the window. The notification
 //! of presentation usually occurs on a V-blank.
 //!
+//! Presentation may be limited to the damaged regions of the buffer, passed to the X server as the
+//! update region of the present request. Only those parts of the window are updated, which avoids
+//! copying the whole buffer for small changes.
+//!
 //! If you do need to modify any of the logic pertaining to the using the present extension, do
 //! ensure you read the `presentproto.txt` file (link in the non-public comments of the
 //! x11 mod.rs).
@@ -36,11 +40,15 @@ use x11rb::{
     protocol::{
         dri3::ConnectionExt as _,
         present::{self, ConnectionExt},
-        xproto::PixmapWrapper,
+        xfixes::ConnectionExt as _,
+        xproto::{self, PixmapWrapper},
     },
 };
 
-use crate::backend::allocator::{dmabuf::Dmabuf, Buffer};
+use crate::{
+    backend::allocator::{dmabuf::Dmabuf, Buffer},
+    utils::{Physical, Rectangle},
+};
 
 // Shm can be easily supported in the future using, xcb_shm_create_pixmap.
 
@@ -55,12 +63,19 @@ where
 
     /// Presents the pixmap to the window.
     ///
+    /// If `damage` is not [`None`], only the damaged regions of the window are updated.
+    ///
     /// The wrapper is consumed when this function is called. The return value will contain the
     /// id of the pixmap.
     ///
     /// The pixmap will be automatically dropped when it bubbles up in the X11 event loop after the
     /// X server has finished presentation with the buffer behind the pixmap.
-    fn present(self, connection: C, window: &Window) -> Result<u32, X11Error>;
+    fn present(
+        self,
+        connection: C,
+        window: &Window,
+        damage: Option<&[Rectangle<i32, Physical>]>,
+    ) -> Result<u32, X11Error>;
 }
 
 impl<C> PixmapWrapperExt<C> for PixmapWrapper<C>
@@ -155,7 +170,30 @@ where
     }
 
     #[profiling::function]
-    fn present(self, connection: C, window: &Window) -> Result<u32, X11Error> {
+    fn present(
+        self,
+        connection: C,
+        window: &Window,
+        damage: Option<&[Rectangle<i32, Physical>]>,
+    ) -> Result<u32, X11Error> {
+        let update = match damage {
+            Some(damage) => {
+                let rects = damage
+                    .iter()
+                    .map(|rect| xproto::Rectangle {
+                        x: rect.loc.x.clamp(0, i16::MAX as i32) as i16,
+                        y: rect.loc.y.clamp(0, i16::MAX as i32) as i16,
+                        width: rect.size.w.clamp(0, u16::MAX as i32) as u16,
+                        height: rect.size.h.clamp(0, u16::MAX as i32) as u16,
+                    })
+                    .collect::<Vec<_>>();
+                let region = connection.generate_id()?;
+                connection.xfixes_create_region(region, &rects)?;
+                region
+            }
+            None => x11rb::NONE, // Update the entire window
+        };
+
         let next_serial = window.0.next_serial.fetch_add(1, Ordering::SeqCst);
         // We want to present as soon as possible, so wait 1ms so the X server will present when next convenient.
         let msc = window.0.last_msc.load(Ordering::SeqCst) + 1;
@@ -167,9 +205,9 @@ where
             window.id(),
             self.pixmap(),
             next_serial,
-            x11rb::NONE, // Update the entire window
-            x11rb::NONE, // Update the entire window
-            0,           // No offsets
+            x11rb::NONE, // The entire pixmap is valid
+            update,
+            0, // No offsets
             0,
             x11rb::NONE,    // Let the X server pick the most suitable crtc
             x11rb::NONE,    // Do not wait to present
@@ -181,6 +219,11 @@ where
             &[], // We don't need to notify any other windows.
         )?;
 
+        // The region is only used while processing the request.
+        if update != x11rb::NONE {
+            connection.xfixes_destroy_region(update)?;
+        }
+
         // Pixmaps are reference counted on the X server. Because of reference counting we may
         // drop the wrapper and the X server will free the pixmap when presentation has completed.
         Ok(self.pixmap())
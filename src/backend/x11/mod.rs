@@ -55,6 +55,18 @@
 //! }
 //! ```
 //!
+//! ## Multiple windows
+//!
+//! Any number of windows may be created from the same [`X11Handle`], each with its own [`X11Surface`].
+//! This allows testing setups with multiple outputs by treating every window as an independent output:
+//! - Every window is resized independently, reported by [`X11Event::Resized`].
+//! - Presentation is synchronized per window, the next frame of a window should be rendered after its
+//!   [`X11Event::PresentCompleted`].
+//! - Input events carry the XID of the window they occurred in, so pointer positions can be mapped to the
+//!   matching output. All windows share a single [`X11VirtualDevice`].
+//!
+//! Use [`X11Event::window_id`] to route events to the matching output.
+//!
 //! ## EGL
 //!
 //! When using [`EGL`](crate::backend::egl), an [`X11Surface`] may be used to create an [`EGLDisplay`].
@@ -84,7 +96,7 @@ use crate::{
         egl::{native::X11DefaultDisplay, EGLDevice, EGLDisplay, Error as EGLError},
         input::{Axis, ButtonState, InputEvent, KeyState, Keycode},
     },
-    utils::{x11rb::X11Source, Logical, Size},
+    utils::{x11rb::X11Source, Logical, Monotonic, Size, Time},
 };
 use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
 use drm::node::path_to_type;
@@ -104,6 +116,7 @@ use std::{
         atomic::{AtomicU32, Ordering},
         mpsc, Arc, Mutex, Weak,
     },
+    time::Duration,
 };
 use tracing::{debug_span, error, info, instrument, warn};
 use x11rb::{
@@ -169,6 +182,12 @@ pub enum X11Event {
     PresentCompleted {
         /// XID of the window
         window_id: u32,
+        /// Time the buffer was displayed, as reported by the X server
+        ///
+        /// This may be used for presentation feedback.
+        time: Time<Monotonic>,
+        /// Media stream counter of the presentation, the number of vblanks of the crtc
+        sequence: u64,
     },
 
     /// The window has received a request to be closed.
@@ -178,6 +197,22 @@ pub enum X11Event {
     },
 }
 
+impl X11Event {
+    /// Returns the XID of the window this event belongs to.
+    ///
+    /// Returns [`None`] for input events not targeting a specific window, which apply to all windows.
+    pub fn window_id(&self) -> Option<u32> {
+        match self {
+            X11Event::Refresh { window_id }
+            | X11Event::Focus { window_id, .. }
+            | X11Event::Resized { window_id, .. }
+            | X11Event::PresentCompleted { window_id, .. }
+            | X11Event::CloseRequested { window_id } => Some(*window_id),
+            X11Event::Input { window_id, .. } => *window_id,
+        }
+    }
+}
+
 /// Represents an active connection to the X to manage events on the Window provided by the backend.
 #[derive(Debug)]
 pub struct X11Backend {
@@ -966,6 +1001,9 @@ impl X11Inner {
                     (callback)(
                         X11Event::PresentCompleted {
                             window_id: complete_notify.window,
+                            // The unadjusted system time of the X server uses the monotonic clock
+                            time: Time::from(Duration::from_micros(complete_notify.ust)),
+                            sequence: complete_notify.msc,
                         },
                         &mut (),
                     );
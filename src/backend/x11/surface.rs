@@ -15,7 +15,7 @@ use crate::{
         },
         x11::{buffer::PixmapWrapperExt, window_inner::WindowInner, AllocateBuffersError, Window},
     },
-    utils::{Logical, Size},
+    utils::{Logical, Physical, Rectangle, Size},
 };
 
 use super::{WindowTemporary, X11Error};
@@ -93,6 +93,21 @@ impl X11Surface {
     #[instrument(level = "trace", parent = &self.span, skip(self))]
     #[profiling::function]
     pub fn submit(&mut self) -> Result<(), X11Error> {
+        self.present(None)
+    }
+
+    /// Consume and submit the buffer to the window, only updating the damaged regions.
+    ///
+    /// The damage is in the physical coordinates of the buffer. The rest of the window keeps its
+    /// previous contents, so an empty damage presents the buffer without changing the window.
+    /// This is considerably faster than [`submit`](Self::submit) for large windows.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[profiling::function]
+    pub fn submit_with_damage(&mut self, damage: &[Rectangle<i32, Physical>]) -> Result<(), X11Error> {
+        self.present(Some(damage))
+    }
+
+    fn present(&mut self, damage: Option<&[Rectangle<i32, Physical>]>) -> Result<(), X11Error> {
         if let Some(connection) = self.connection.upgrade() {
             // Get a new buffer
             let mut next = self
@@ -111,7 +126,7 @@ impl X11Surface {
                 let pixmap = PixmapWrapper::with_dmabuf(&*connection, window.as_ref(), &next)?;
 
                 // Now present the current buffer
-                let _ = pixmap.present(&*connection, window.as_ref())?;
+                let _ = pixmap.present(&*connection, window.as_ref(), damage)?;
             }
             self.swapchain.submitted(&next);
 
@@ -0,0 +1,667 @@
+//! Clock-driven animations
+//!
+//! An [`Animation`] interpolates between two values of any type implementing [`Interpolate`]
+//! (e.g. numbers, points, sizes and rectangles) following a [`Curve`], which is either a classic
+//! [`Easing`] function over a fixed duration or a physically modelled [`Spring`].
+//! Animations are driven by [`Time`]s of the [`Monotonic`] clock, usually the presentation
+//! time of the frame being rendered, and can be retargeted while running without jumps.
+//!
+//! [`ElementAnimations`] keeps track of geometry, opacity and crop animations of elements and reports
+//! the outputs of a [`Space`] that need another frame while animations are running:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use smithay::desktop::{animation::{Curve, Easing, ElementAnimations}, Space, Window};
+//! # use smithay::utils::{Clock, Logical, Monotonic, Rectangle};
+//! # let space: Space<Window> = unimplemented!();
+//! # let window: Window = unimplemented!();
+//! # let (from, to): (Rectangle<f64, Logical>, Rectangle<f64, Logical>) = unimplemented!();
+//! let clock = Clock::<Monotonic>::new();
+//! let mut animations = ElementAnimations::new();
+//!
+//! // animate a window moving to a new location
+//! let curve = Curve::Easing {
+//!     duration: Duration::from_millis(250),
+//!     easing: Easing::EaseOutCubic,
+//! };
+//! animations.animate_geometry(&window, from, to, curve, clock.now());
+//!
+//! // while rendering
+//! let now = clock.now();
+//! if let Some(geometry) = animations.geometry(&window, now) {
+//!     // render the window at `geometry` instead of its mapped location
+//! }
+//! for output in animations.outputs_needing_redraw(&space, now) {
+//!     // schedule a redraw of `output`
+//! }
+//! animations.refresh(now);
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    output::Output,
+    utils::{Logical, Monotonic, Point, Rectangle, Size, Time},
+};
+
+use super::space::{Space, SpaceElement};
+
+/// Easing functions mapping a linear progress to an eased one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    /// No easing
+    Linear,
+    /// Quadratic acceleration from zero velocity
+    EaseInQuad,
+    /// Quadratic deceleration to zero velocity
+    EaseOutQuad,
+    /// Quadratic acceleration until halfway, then deceleration
+    EaseInOutQuad,
+    /// Cubic acceleration from zero velocity
+    EaseInCubic,
+    /// Cubic deceleration to zero velocity
+    EaseOutCubic,
+    /// Cubic acceleration until halfway, then deceleration
+    EaseInOutCubic,
+    /// Exponential deceleration to zero velocity
+    EaseOutExpo,
+    /// A cubic bézier curve from `(0, 0)` to `(1, 1)` with the control points `(x1, y1)` and `(x2, y2)`,
+    /// as used by CSS `cubic-bezier()`
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    /// Applies the easing function to a progress between `0.0` and `1.0`
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::EaseOutExpo => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2f64.powf(-10.0 * t)
+                }
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    let bezier = |p1: f64, p2: f64, t: f64| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    let derivative = |p1: f64, p2: f64, t: f64| {
+        let u = 1.0 - t;
+        3.0 * u * u * p1 + 6.0 * u * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+    };
+
+    // find the curve parameter for x, newton's method first, bisection as fallback
+    let mut t = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, t) - x;
+        if error.abs() < 1e-7 {
+            return bezier(y1, y2, t);
+        }
+        let slope = derivative(x1, x2, t);
+        if slope.abs() < 1e-7 {
+            break;
+        }
+        t -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    while high - low > 1e-7 {
+        if bezier(x1, x2, t) < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+    bezier(y1, y2, t)
+}
+
+/// A damped spring moving from the start to the end value
+///
+/// Springs have no fixed duration, they settle once the remaining displacement is below
+/// [`Spring::epsilon`]. Underdamped springs (`damping_ratio < 1.0`) overshoot the end value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    /// Ratio of the damping to critical damping, `1.0` settles as fast as possible without overshooting
+    pub damping_ratio: f64,
+    /// Stiffness of the spring, higher values result in faster animations
+    pub stiffness: f64,
+    /// Remaining relative displacement at which the spring is considered settled
+    pub epsilon: f64,
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Spring {
+            damping_ratio: 1.0,
+            stiffness: 800.0,
+            epsilon: 0.0001,
+        }
+    }
+}
+
+// upper bound for the duration of springs with unreasonable parameters
+const MAX_SPRING_DURATION: Duration = Duration::from_secs(10);
+
+impl Spring {
+    // displacement from the end value at `t` seconds, starting at 1 with zero velocity
+    fn displacement(&self, t: f64) -> f64 {
+        let omega = self.stiffness.max(f64::EPSILON).sqrt();
+        let zeta = self.damping_ratio.max(0.0);
+
+        if zeta < 1.0 {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            (-zeta * omega * t).exp() * ((omega_d * t).cos() + (zeta * omega / omega_d) * (omega_d * t).sin())
+        } else if zeta == 1.0 {
+            (-omega * t).exp() * (1.0 + omega * t)
+        } else {
+            let root = (zeta * zeta - 1.0).sqrt();
+            let r1 = -omega * (zeta - root);
+            let r2 = -omega * (zeta + root);
+            (r2 * (r1 * t).exp() - r1 * (r2 * t).exp()) / (r2 - r1)
+        }
+    }
+
+    /// Returns the progress of the spring after `elapsed` time
+    ///
+    /// The returned value starts at `0.0` and approaches `1.0`.
+    pub fn progress(&self, elapsed: Duration) -> f64 {
+        1.0 - self.displacement(elapsed.as_secs_f64())
+    }
+
+    /// Returns the time it takes the spring to settle
+    pub fn duration(&self) -> Duration {
+        let epsilon = self.epsilon.abs().max(f64::EPSILON);
+        let omega = self.stiffness.max(f64::EPSILON).sqrt();
+        let zeta = self.damping_ratio.max(0.0);
+        // the displacement of underdamped springs oscillates inside a decaying envelope
+        let envelope = |t: f64| {
+            if zeta < 1.0 {
+                (-zeta * omega * t).exp() / (1.0 - zeta * zeta).sqrt()
+            } else {
+                self.displacement(t).abs()
+            }
+        };
+
+        let step = Duration::from_millis(1);
+        let mut settled = Duration::ZERO;
+        let mut elapsed = Duration::ZERO;
+        while elapsed < MAX_SPRING_DURATION && envelope(elapsed.as_secs_f64()) >= epsilon {
+            elapsed += step;
+            if self.displacement(elapsed.as_secs_f64()).abs() >= epsilon {
+                settled = elapsed + step;
+            }
+        }
+        settled.min(MAX_SPRING_DURATION)
+    }
+}
+
+/// Timing of an [`Animation`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    /// An easing function over a fixed duration
+    Easing {
+        /// Duration of the animation
+        duration: Duration,
+        /// Easing function
+        easing: Easing,
+    },
+    /// A damped spring
+    Spring(Spring),
+}
+
+impl Curve {
+    /// Returns the total duration of the curve
+    pub fn duration(&self) -> Duration {
+        match self {
+            Curve::Easing { duration, .. } => *duration,
+            Curve::Spring(spring) => spring.duration(),
+        }
+    }
+
+    /// Returns the progress of the curve after `elapsed` time
+    ///
+    /// The value starts at `0.0` and ends at `1.0`, but may exceed this range in between for
+    /// overshooting curves.
+    pub fn progress(&self, elapsed: Duration) -> f64 {
+        match self {
+            Curve::Easing { duration, easing } => {
+                if duration.is_zero() {
+                    return 1.0;
+                }
+                easing.apply(elapsed.as_secs_f64() / duration.as_secs_f64())
+            }
+            Curve::Spring(spring) => spring.progress(elapsed),
+        }
+    }
+}
+
+/// Values that can be interpolated by an [`Animation`]
+pub trait Interpolate: Copy {
+    /// Interpolates between `self` at a progress of `0.0` and `other` at a progress of `1.0`
+    ///
+    /// Progress values outside of this range extrapolate.
+    fn interpolate(&self, other: &Self, progress: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, progress: f64) -> Self {
+        self + (other - self) * progress
+    }
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, progress: f64) -> Self {
+        self + (other - self) * progress as f32
+    }
+}
+
+macro_rules! interpolate_geometry {
+    ($ty:ty, $round:expr) => {
+        impl<Kind> Interpolate for Point<$ty, Kind> {
+            fn interpolate(&self, other: &Self, progress: f64) -> Self {
+                let x = (self.x as f64).interpolate(&(other.x as f64), progress);
+                let y = (self.y as f64).interpolate(&(other.y as f64), progress);
+                ($round(x) as $ty, $round(y) as $ty).into()
+            }
+        }
+
+        impl<Kind> Interpolate for Size<$ty, Kind> {
+            fn interpolate(&self, other: &Self, progress: f64) -> Self {
+                // sizes may not become negative when overshooting
+                let w = (self.w as f64)
+                    .interpolate(&(other.w as f64), progress)
+                    .max(0.0);
+                let h = (self.h as f64)
+                    .interpolate(&(other.h as f64), progress)
+                    .max(0.0);
+                ($round(w) as $ty, $round(h) as $ty).into()
+            }
+        }
+
+        impl<Kind> Interpolate for Rectangle<$ty, Kind> {
+            fn interpolate(&self, other: &Self, progress: f64) -> Self {
+                Rectangle::from_loc_and_size(
+                    self.loc.interpolate(&other.loc, progress),
+                    self.size.interpolate(&other.size, progress),
+                )
+            }
+        }
+    };
+}
+
+interpolate_geometry!(f64, std::convert::identity);
+interpolate_geometry!(i32, f64::round);
+
+/// An animation of a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation<T> {
+    from: T,
+    to: T,
+    curve: Curve,
+    start: Time<Monotonic>,
+    duration: Duration,
+}
+
+impl<T: Interpolate> Animation<T> {
+    /// Creates a new animation starting at `start`
+    pub fn new(from: T, to: T, curve: Curve, start: Time<Monotonic>) -> Self {
+        Animation {
+            from,
+            to,
+            curve,
+            start,
+            duration: curve.duration(),
+        }
+    }
+
+    /// Returns the start value
+    pub fn from(&self) -> T {
+        self.from
+    }
+
+    /// Returns the end value
+    pub fn to(&self) -> T {
+        self.to
+    }
+
+    /// Returns the curve of the animation
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// Returns the time the animation started
+    pub fn start(&self) -> Time<Monotonic> {
+        self.start
+    }
+
+    /// Returns the total duration of the animation
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the progress of the animation at a point in time
+    pub fn progress(&self, now: Time<Monotonic>) -> f64 {
+        let elapsed = Time::elapsed(&self.start, now);
+        if elapsed >= self.duration {
+            return 1.0;
+        }
+        self.curve.progress(elapsed)
+    }
+
+    /// Returns the value of the animation at a point in time
+    pub fn value(&self, now: Time<Monotonic>) -> T {
+        self.from.interpolate(&self.to, self.progress(now))
+    }
+
+    /// Returns whether the animation finished at a point in time
+    pub fn is_done(&self, now: Time<Monotonic>) -> bool {
+        Time::elapsed(&self.start, now) >= self.duration
+    }
+
+    /// Restarts the animation towards a new end value
+    ///
+    /// The animation continues from its current value at `now`.
+    pub fn retarget(&mut self, to: T, now: Time<Monotonic>) {
+        self.from = self.value(now);
+        self.to = to;
+        self.start = now;
+    }
+}
+
+/// Animated values of an element at a point in time
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AnimatedValues {
+    /// Geometry of the element in space coordinates, if animated
+    pub geometry: Option<Rectangle<f64, Logical>>,
+    /// Opacity of the element, if animated
+    pub opacity: Option<f32>,
+    /// Visible part of the element relative to its geometry, if animated
+    pub crop: Option<Rectangle<f64, Logical>>,
+}
+
+#[derive(Debug, Default)]
+struct ElementState {
+    geometry: Option<Animation<Rectangle<f64, Logical>>>,
+    opacity: Option<Animation<f32>>,
+    crop: Option<Animation<Rectangle<f64, Logical>>>,
+}
+
+impl ElementState {
+    fn is_done(&self, now: Time<Monotonic>) -> bool {
+        self.geometry.as_ref().map_or(true, |a| a.is_done(now))
+            && self.opacity.as_ref().map_or(true, |a| a.is_done(now))
+            && self.crop.as_ref().map_or(true, |a| a.is_done(now))
+    }
+}
+
+fn start_or_retarget<T: Interpolate>(
+    animation: &mut Option<Animation<T>>,
+    from: T,
+    to: T,
+    curve: Curve,
+    now: Time<Monotonic>,
+) {
+    match animation {
+        Some(animation) if !animation.is_done(now) => {
+            animation.curve = curve;
+            animation.duration = curve.duration();
+            animation.retarget(to, now);
+        }
+        _ => *animation = Some(Animation::new(from, to, curve, now)),
+    }
+}
+
+/// Tracks running geometry, opacity and crop animations of elements
+///
+/// Finished animations keep reporting their end value until [`ElementAnimations::refresh`] is called.
+/// Compositors are expected to apply the end state to the element themselves, e.g. by mapping it at
+/// its final location when starting the animation and rendering it at the animated geometry meanwhile.
+#[derive(Debug)]
+pub struct ElementAnimations<E> {
+    elements: Vec<(E, ElementState)>,
+}
+
+impl<E> Default for ElementAnimations<E> {
+    fn default() -> Self {
+        ElementAnimations { elements: Vec::new() }
+    }
+}
+
+impl<E: PartialEq + Clone> ElementAnimations<E> {
+    /// Creates a new empty set of animations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state_mut(&mut self, element: &E) -> &mut ElementState {
+        let pos = match self.elements.iter().position(|(e, _)| e == element) {
+            Some(pos) => pos,
+            None => {
+                self.elements.push((element.clone(), ElementState::default()));
+                self.elements.len() - 1
+            }
+        };
+        &mut self.elements[pos].1
+    }
+
+    fn state(&self, element: &E) -> Option<&ElementState> {
+        self.elements
+            .iter()
+            .find(|(e, _)| e == element)
+            .map(|(_, state)| state)
+    }
+
+    /// Animates the geometry of an element in space coordinates
+    ///
+    /// If the geometry of the element is already animating, the animation continues from its current
+    /// value and `from` is ignored.
+    pub fn animate_geometry(
+        &mut self,
+        element: &E,
+        from: Rectangle<f64, Logical>,
+        to: Rectangle<f64, Logical>,
+        curve: Curve,
+        now: Time<Monotonic>,
+    ) {
+        start_or_retarget(&mut self.state_mut(element).geometry, from, to, curve, now);
+    }
+
+    /// Animates the opacity of an element
+    ///
+    /// If the opacity of the element is already animating, the animation continues from its current
+    /// value and `from` is ignored.
+    pub fn animate_opacity(&mut self, element: &E, from: f32, to: f32, curve: Curve, now: Time<Monotonic>) {
+        start_or_retarget(&mut self.state_mut(element).opacity, from, to, curve, now);
+    }
+
+    /// Animates the visible part of an element relative to its geometry
+    ///
+    /// If the crop of the element is already animating, the animation continues from its current
+    /// value and `from` is ignored.
+    pub fn animate_crop(
+        &mut self,
+        element: &E,
+        from: Rectangle<f64, Logical>,
+        to: Rectangle<f64, Logical>,
+        curve: Curve,
+        now: Time<Monotonic>,
+    ) {
+        start_or_retarget(&mut self.state_mut(element).crop, from, to, curve, now);
+    }
+
+    /// Returns the animated values of an element at a point in time
+    pub fn values(&self, element: &E, now: Time<Monotonic>) -> AnimatedValues {
+        let Some(state) = self.state(element) else {
+            return AnimatedValues::default();
+        };
+        AnimatedValues {
+            geometry: state.geometry.as_ref().map(|a| a.value(now)),
+            opacity: state.opacity.as_ref().map(|a| a.value(now)),
+            crop: state.crop.as_ref().map(|a| a.value(now)),
+        }
+    }
+
+    /// Returns the animated geometry of an element at a point in time
+    pub fn geometry(&self, element: &E, now: Time<Monotonic>) -> Option<Rectangle<f64, Logical>> {
+        self.values(element, now).geometry
+    }
+
+    /// Returns the animated opacity of an element at a point in time
+    pub fn opacity(&self, element: &E, now: Time<Monotonic>) -> Option<f32> {
+        self.values(element, now).opacity
+    }
+
+    /// Returns the animated crop of an element at a point in time
+    pub fn crop(&self, element: &E, now: Time<Monotonic>) -> Option<Rectangle<f64, Logical>> {
+        self.values(element, now).crop
+    }
+
+    /// Returns whether any animation of an element is still running at a point in time
+    pub fn is_animating(&self, element: &E, now: Time<Monotonic>) -> bool {
+        self.state(element).is_some_and(|state| !state.is_done(now))
+    }
+
+    /// Returns whether any animation is still running at a point in time and another frame is needed
+    pub fn needs_redraw(&self, now: Time<Monotonic>) -> bool {
+        self.elements.iter().any(|(_, state)| !state.is_done(now))
+    }
+
+    /// Returns all elements with running animations at a point in time
+    pub fn animating_elements(&self, now: Time<Monotonic>) -> impl Iterator<Item = &E> {
+        self.elements
+            .iter()
+            .filter(move |(_, state)| !state.is_done(now))
+            .map(|(element, _)| element)
+    }
+
+    /// Stops all animations of an element
+    pub fn remove(&mut self, element: &E) {
+        self.elements.retain(|(e, _)| e != element);
+    }
+
+    /// Removes finished animations
+    ///
+    /// Should be called after rendering a frame, so the end values of finished animations are rendered once.
+    pub fn refresh(&mut self, now: Time<Monotonic>) {
+        for (_, state) in &mut self.elements {
+            if state.geometry.as_ref().is_some_and(|a| a.is_done(now)) {
+                state.geometry = None;
+            }
+            if state.opacity.as_ref().is_some_and(|a| a.is_done(now)) {
+                state.opacity = None;
+            }
+            if state.crop.as_ref().is_some_and(|a| a.is_done(now)) {
+                state.crop = None;
+            }
+        }
+        self.elements
+            .retain(|(_, state)| state.geometry.is_some() || state.opacity.is_some() || state.crop.is_some());
+    }
+}
+
+impl<E: SpaceElement + PartialEq + Clone> ElementAnimations<E> {
+    /// Returns the outputs of a space that need to be redrawn for the animations at a point in time
+    ///
+    /// These are the outputs overlapping an animated element, either at its mapped location or at
+    /// its animated geometry.
+    pub fn outputs_needing_redraw(&self, space: &Space<E>, now: Time<Monotonic>) -> Vec<Output> {
+        let mut outputs = Vec::new();
+        for (element, state) in &self.elements {
+            if state.is_done(now) {
+                continue;
+            }
+
+            let mut candidates = space.outputs_for_element(element);
+            if let Some(geometry) = state.geometry.as_ref().map(|a| a.value(now)) {
+                let geometry = geometry.to_i32_up();
+                candidates.extend(
+                    space
+                        .outputs()
+                        .filter(|output| {
+                            space
+                                .output_geometry(output)
+                                .is_some_and(|output_geometry| output_geometry.overlaps(geometry))
+                        })
+                        .cloned(),
+                );
+            }
+            for output in candidates {
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+        }
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Animation, Curve, Easing, Spring};
+    use crate::utils::{Logical, Monotonic, Point, Time};
+
+    #[test]
+    fn animation_reaches_target() {
+        let at =
+            |millis: u64| Time::<Monotonic>::from(Duration::from_secs(1) + Duration::from_millis(millis));
+        let start = at(0);
+
+        let curves = [
+            Curve::Easing {
+                duration: Duration::from_millis(200),
+                easing: Easing::EaseInOutCubic,
+            },
+            Curve::Easing {
+                duration: Duration::from_millis(200),
+                easing: Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+            },
+            Curve::Spring(Spring::default()),
+            Curve::Spring(Spring {
+                damping_ratio: 0.5,
+                ..Spring::default()
+            }),
+        ];
+        for curve in curves {
+            let from = Point::<i32, Logical>::from((0, 0));
+            let to = Point::<i32, Logical>::from((100, -50));
+            let animation = Animation::new(from, to, curve, start);
+
+            assert_eq!(animation.value(start), from);
+            assert!(!animation.is_done(at(10)));
+            let done = animation.duration().as_millis() as u64;
+            assert!(done > 0 && done < 10_000);
+            assert!(animation.is_done(at(done)));
+            assert_eq!(animation.value(at(done)), to);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Most-recently-used keyboard focus tracking
+//!
+//! A [`FocusStack`] keeps elements in most-recently-used order. It is meant to back
+//! focus restoration, e.g. after the focused window is closed, and window switchers
+//! cycling through windows in the order they were last used.
+//!
+//! [`FocusHistory`] manages one [`FocusStack`] per key, usually a
+//! [`WorkspaceId`](crate::desktop::WorkspaceId) or an [`Output`](crate::output::Output),
+//! and makes sure an element is only ever part of one of them.
+//!
+//! Elements are kept until they are explicitly removed or are no longer alive, so
+//! temporarily unmapped (e.g. minimized) windows keep their position in the history.
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::utils::IsAlive;
+
+/// Elements in most-recently-used order
+#[derive(Debug, Clone)]
+pub struct FocusStack<E> {
+    // most recently used first
+    elements: Vec<E>,
+    // position of the element currently selected by cycling
+    cycle: Option<usize>,
+}
+
+impl<E> Default for FocusStack<E> {
+    fn default() -> Self {
+        FocusStack {
+            elements: Vec::new(),
+            cycle: None,
+        }
+    }
+}
+
+impl<E: PartialEq> FocusStack<E> {
+    /// Creates an empty stack
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks an element as focused, moving it to the top of the stack
+    ///
+    /// The element is added if it is not yet part of the stack. This ends any ongoing cycle.
+    pub fn focus(&mut self, element: E) {
+        self.cycle = None;
+        if let Some(idx) = self.elements.iter().position(|e| *e == element) {
+            self.elements.remove(idx);
+        }
+        self.elements.insert(0, element);
+    }
+
+    /// Returns the most recently focused element
+    pub fn current(&self) -> Option<&E> {
+        self.elements.first()
+    }
+
+    /// Returns the most recently focused element matching the given predicate
+    ///
+    /// Useful to skip e.g. unmapped windows.
+    pub fn most_recent<F: FnMut(&E) -> bool>(&self, mut filter: F) -> Option<&E> {
+        self.elements.iter().find(|e| filter(e))
+    }
+
+    /// Removes an element from the stack
+    ///
+    /// Returns the element that should receive focus instead, if the removed element was
+    /// the most recently focused one. The returned element is already moved to the top of the stack.
+    pub fn remove(&mut self, element: &E) -> Option<&E> {
+        let idx = self.elements.iter().position(|e| e == element)?;
+        self.elements.remove(idx);
+        self.cycle = match self.cycle {
+            Some(cycle) if cycle > idx => Some(cycle - 1),
+            Some(cycle) if cycle == idx => None,
+            cycle => cycle,
+        };
+
+        if idx == 0 {
+            self.elements.first()
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the element is part of the stack
+    pub fn contains(&self, element: &E) -> bool {
+        self.elements.contains(element)
+    }
+
+    /// Returns the elements in most-recently-used order
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.elements.iter()
+    }
+
+    /// Returns the number of elements in the stack
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns whether the stack is empty
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Selects the next less recently used element, wrapping around
+    ///
+    /// Cycling does not change the order of the stack until [`FocusStack::end_cycle`] is called,
+    /// so repeatedly calling this walks through all elements like a typical alt-tab switcher.
+    pub fn focus_next(&mut self) -> Option<&E> {
+        if self.elements.is_empty() {
+            return None;
+        }
+        let cycle = self
+            .cycle
+            .map(|idx| (idx + 1) % self.elements.len())
+            .unwrap_or(1 % self.elements.len());
+        self.cycle = Some(cycle);
+        self.elements.get(cycle)
+    }
+
+    /// Selects the next more recently used element, wrapping around
+    ///
+    /// See [`FocusStack::focus_next`].
+    pub fn focus_prev(&mut self) -> Option<&E> {
+        if self.elements.is_empty() {
+            return None;
+        }
+        let len = self.elements.len();
+        let cycle = self.cycle.map(|idx| (idx + len - 1) % len).unwrap_or(len - 1);
+        self.cycle = Some(cycle);
+        self.elements.get(cycle)
+    }
+
+    /// Returns the element currently selected by cycling, if a cycle is ongoing
+    pub fn cycle_selection(&self) -> Option<&E> {
+        self.cycle.and_then(|idx| self.elements.get(idx))
+    }
+
+    /// Ends the current cycle, moving the selected element to the top of the stack
+    ///
+    /// Returns the selected element, which should receive focus.
+    pub fn end_cycle(&mut self) -> Option<&E> {
+        let idx = self.cycle.take()?;
+        let element = self.elements.remove(idx);
+        self.elements.insert(0, element);
+        self.elements.first()
+    }
+
+    /// Aborts the current cycle without changing the order of the stack
+    pub fn cancel_cycle(&mut self) {
+        self.cycle = None;
+    }
+}
+
+impl<E: PartialEq + IsAlive> FocusStack<E> {
+    /// Removes elements that are no longer alive
+    ///
+    /// Returns the element that should receive focus instead, if the most recently
+    /// focused element was removed.
+    pub fn refresh(&mut self) -> Option<&E> {
+        let top_died = self.elements.first().map(|e| !e.alive()).unwrap_or(false);
+        let selection_died = self.cycle_selection().map(|e| !e.alive()).unwrap_or(false);
+        if selection_died {
+            self.cycle = None;
+        }
+        if let Some(cycle) = self.cycle {
+            let dead_before = self.elements[..cycle].iter().filter(|e| !e.alive()).count();
+            self.cycle = Some(cycle - dead_before);
+        }
+        self.elements.retain(|e| e.alive());
+
+        if top_died {
+            self.elements.first()
+        } else {
+            None
+        }
+    }
+}
+
+/// Per-key collection of [`FocusStack`]s
+///
+/// `K` is usually a [`WorkspaceId`](crate::desktop::WorkspaceId) or an [`Output`](crate::output::Output).
+#[derive(Debug, Clone)]
+pub struct FocusHistory<K, E> {
+    stacks: HashMap<K, FocusStack<E>>,
+}
+
+impl<K, E> Default for FocusHistory<K, E> {
+    fn default() -> Self {
+        FocusHistory {
+            stacks: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, E: PartialEq> FocusHistory<K, E> {
+    /// Creates an empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks an element as focused on the given key
+    ///
+    /// The element is removed from the stacks of all other keys, so moving a window to
+    /// another workspace only requires focusing it there.
+    pub fn focus(&mut self, key: &K, element: E) {
+        for (k, stack) in self.stacks.iter_mut() {
+            if k != key {
+                stack.remove(&element);
+            }
+        }
+        self.stacks.entry(key.clone()).or_default().focus(element);
+    }
+
+    /// Returns the stack of the given key, if any
+    pub fn stack(&self, key: &K) -> Option<&FocusStack<E>> {
+        self.stacks.get(key)
+    }
+
+    /// Returns the stack of the given key mutably, creating it if necessary
+    pub fn stack_mut(&mut self, key: &K) -> &mut FocusStack<E> {
+        self.stacks.entry(key.clone()).or_default()
+    }
+
+    /// Returns the most recently focused element of the given key
+    pub fn current(&self, key: &K) -> Option<&E> {
+        self.stacks.get(key).and_then(FocusStack::current)
+    }
+
+    /// Returns the key whose stack contains the element
+    pub fn key_of(&self, element: &E) -> Option<&K> {
+        self.stacks
+            .iter()
+            .find(|(_, stack)| stack.contains(element))
+            .map(|(key, _)| key)
+    }
+
+    /// Removes an element from the history
+    ///
+    /// Returns the key of the stack the element was part of together with the element
+    /// that should receive focus instead, if the removed element was the most recently
+    /// focused element of that key.
+    pub fn remove(&mut self, element: &E) -> Option<(K, Option<&E>)> {
+        let (key, stack) = self
+            .stacks
+            .iter_mut()
+            .find(|(_, stack)| stack.contains(element))?;
+        Some((key.clone(), stack.remove(element)))
+    }
+
+    /// Removes the stack of a key, e.g. when a workspace is destroyed
+    pub fn remove_key(&mut self, key: &K) -> Option<FocusStack<E>> {
+        self.stacks.remove(key)
+    }
+}
+
+impl<K: Eq + Hash + Clone, E: PartialEq + IsAlive> FocusHistory<K, E> {
+    /// Removes elements that are no longer alive from all stacks
+    pub fn refresh(&mut self) {
+        for stack in self.stacks.values_mut() {
+            stack.refresh();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_and_restore() {
+        let mut stack = FocusStack::new();
+        stack.focus(1);
+        stack.focus(2);
+        stack.focus(3);
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        assert_eq!(stack.focus_next(), Some(&2));
+        assert_eq!(stack.focus_next(), Some(&1));
+        assert_eq!(stack.end_cycle(), Some(&1));
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![1, 3, 2]);
+
+        assert_eq!(stack.focus_prev(), Some(&2));
+        stack.cancel_cycle();
+        assert_eq!(stack.current(), Some(&1));
+
+        assert_eq!(stack.remove(&3), None);
+        assert_eq!(stack.remove(&1), Some(&2));
+        assert_eq!(stack.remove(&2), None);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn history_moves_elements_between_keys() {
+        let mut history = FocusHistory::new();
+        history.focus(&"a", 1);
+        history.focus(&"a", 2);
+        history.focus(&"b", 1);
+
+        assert_eq!(history.current(&"a"), Some(&2));
+        assert_eq!(history.key_of(&1), Some(&"b"));
+        assert_eq!(history.remove(&1), Some(("b", None)));
+        assert_eq!(history.remove(&2), Some(("a", None)));
+    }
+}
@@ -0,0 +1,676 @@
+//! Tiling layout helpers
+//!
+//! This module provides a [`TilingLayout`] keeping track of an ordered list of elements,
+//! that are arranged by a [`Layout`] algorithm into a given area (e.g. the non-exclusive zone of an output).
+//!
+//! Smithay ships with a few common algorithms:
+//! - [`MasterStack`] places one or more master elements on one side and stacks the rest on the other,
+//! - [`Spiral`] recursively splits the remaining area, producing a fibonacci-like spiral,
+//! - [`Grid`] arranges elements into evenly sized rows and columns.
+//!
+//! Custom algorithms can be provided by implementing [`Layout`].
+//!
+//! Interactive resizes can be forwarded to the layout using [`TilingLayout::resize_element`], which
+//! updates the parameters of the algorithm (e.g. split ratios), so the manual adjustment is kept
+//! on the next arrangement.
+
+use std::fmt;
+
+use crate::utils::{Logical, Point, Rectangle, Size};
+
+use super::space::{Space, SpaceElement};
+
+bitflags::bitflags! {
+    /// Edges of an element being interactively resized
+    ///
+    /// The values match the ones of `xdg_toplevel::ResizeEdge`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ResizeEdge: u32 {
+        /// Top edge
+        const TOP = 1;
+        /// Bottom edge
+        const BOTTOM = 2;
+        /// Left edge
+        const LEFT = 4;
+        /// Right edge
+        const RIGHT = 8;
+
+        /// Top-left corner
+        const TOP_LEFT = Self::TOP.bits() | Self::LEFT.bits();
+        /// Bottom-left corner
+        const BOTTOM_LEFT = Self::BOTTOM.bits() | Self::LEFT.bits();
+        /// Top-right corner
+        const TOP_RIGHT = Self::TOP.bits() | Self::RIGHT.bits();
+        /// Bottom-right corner
+        const BOTTOM_RIGHT = Self::BOTTOM.bits() | Self::RIGHT.bits();
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl From<wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge> for ResizeEdge {
+    #[inline]
+    fn from(edge: wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge) -> Self {
+        Self::from_bits_truncate(edge as u32)
+    }
+}
+
+#[cfg(feature = "xwayland")]
+impl From<crate::xwayland::xwm::ResizeEdge> for ResizeEdge {
+    #[inline]
+    fn from(edge: crate::xwayland::xwm::ResizeEdge) -> Self {
+        use crate::xwayland::xwm::ResizeEdge as X11ResizeEdge;
+        match edge {
+            X11ResizeEdge::Top => ResizeEdge::TOP,
+            X11ResizeEdge::Bottom => ResizeEdge::BOTTOM,
+            X11ResizeEdge::Left => ResizeEdge::LEFT,
+            X11ResizeEdge::Right => ResizeEdge::RIGHT,
+            X11ResizeEdge::TopLeft => ResizeEdge::TOP_LEFT,
+            X11ResizeEdge::BottomLeft => ResizeEdge::BOTTOM_LEFT,
+            X11ResizeEdge::TopRight => ResizeEdge::TOP_RIGHT,
+            X11ResizeEdge::BottomRight => ResizeEdge::BOTTOM_RIGHT,
+        }
+    }
+}
+
+/// Gaps between and around tiled elements
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Gaps {
+    /// Gap between two neighboring elements
+    pub inner: i32,
+    /// Gap between the elements and the border of the arranged area
+    ///
+    /// The gap is never smaller than half of the inner gap.
+    pub outer: i32,
+}
+
+/// A tiling algorithm
+pub trait Layout: fmt::Debug {
+    /// Arranges `count` elements into the given area.
+    ///
+    /// Must return exactly `count` rectangles in element order.
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>>;
+
+    /// Adjusts the layout parameters, after the element at `index` was interactively
+    /// resized by `delta` on the given `edges`.
+    ///
+    /// `area` and `count` match the values last passed to [`Layout::arrange`].
+    /// Returns whenever the layout changed. The default implementation ignores resizes.
+    fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+        index: usize,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> bool {
+        let _ = (area, count, index, edges, delta);
+        false
+    }
+}
+
+impl<L: Layout + ?Sized> Layout for Box<L> {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        (**self).arrange(area, count)
+    }
+
+    fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+        index: usize,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> bool {
+        (**self).resize(area, count, index, edges, delta)
+    }
+}
+
+const MIN_RATIO: f64 = 0.05;
+const MAX_RATIO: f64 = 0.95;
+
+fn split_horizontal(
+    area: Rectangle<i32, Logical>,
+    ratio: f64,
+) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    let w = (area.size.w as f64 * ratio).round() as i32;
+    (
+        Rectangle::from_loc_and_size(area.loc, (w, area.size.h)),
+        Rectangle::from_loc_and_size((area.loc.x + w, area.loc.y), (area.size.w - w, area.size.h)),
+    )
+}
+
+fn split_vertical(
+    area: Rectangle<i32, Logical>,
+    ratio: f64,
+) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    let h = (area.size.h as f64 * ratio).round() as i32;
+    (
+        Rectangle::from_loc_and_size(area.loc, (area.size.w, h)),
+        Rectangle::from_loc_and_size((area.loc.x, area.loc.y + h), (area.size.w, area.size.h - h)),
+    )
+}
+
+fn stack(area: Rectangle<i32, Logical>, count: usize, horizontal: bool) -> Vec<Rectangle<i32, Logical>> {
+    let count = count as i32;
+    (0..count)
+        .map(|i| {
+            if horizontal {
+                let x = area.loc.x + area.size.w * i / count;
+                let next = area.loc.x + area.size.w * (i + 1) / count;
+                Rectangle::from_loc_and_size((x, area.loc.y), (next - x, area.size.h))
+            } else {
+                let y = area.loc.y + area.size.h * i / count;
+                let next = area.loc.y + area.size.h * (i + 1) / count;
+                Rectangle::from_loc_and_size((area.loc.x, y), (area.size.w, next - y))
+            }
+        })
+        .collect()
+}
+
+/// Side of the master area in a [`MasterStack`] layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterSide {
+    /// Master area on the left, stack on the right
+    Left,
+    /// Master area on the right, stack on the left
+    Right,
+    /// Master area on the top, stack on the bottom
+    Top,
+    /// Master area on the bottom, stack on the top
+    Bottom,
+}
+
+/// Master/stack layout
+///
+/// The first `master_count` elements share the master area, the remaining elements are stacked
+/// in the other part of the area. If all elements fit into the master area, it covers the whole area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasterStack {
+    /// Fraction of the area covered by the master area
+    pub ratio: f64,
+    /// Number of elements in the master area
+    pub master_count: usize,
+    /// Side of the master area
+    pub side: MasterSide,
+}
+
+impl Default for MasterStack {
+    fn default() -> Self {
+        MasterStack {
+            ratio: 0.5,
+            master_count: 1,
+            side: MasterSide::Left,
+        }
+    }
+}
+
+impl MasterStack {
+    fn split(&self, area: Rectangle<i32, Logical>) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+        match self.side {
+            MasterSide::Left => split_horizontal(area, self.ratio),
+            MasterSide::Top => split_vertical(area, self.ratio),
+            MasterSide::Right => {
+                let (stack, master) = split_horizontal(area, 1.0 - self.ratio);
+                (master, stack)
+            }
+            MasterSide::Bottom => {
+                let (stack, master) = split_vertical(area, 1.0 - self.ratio);
+                (master, stack)
+            }
+        }
+    }
+}
+
+impl Layout for MasterStack {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        let horizontal = matches!(self.side, MasterSide::Top | MasterSide::Bottom);
+        let master_count = self.master_count.min(count);
+        if master_count == 0 || master_count == count {
+            return stack(area, count, horizontal);
+        }
+
+        let (master, rest) = self.split(area);
+        let mut geometries = stack(master, master_count, horizontal);
+        geometries.extend(stack(rest, count - master_count, horizontal));
+        geometries
+    }
+
+    fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+        index: usize,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> bool {
+        let master_count = self.master_count.min(count);
+        if master_count == 0 || master_count == count || index >= count {
+            return false;
+        }
+        let is_master = index < master_count;
+
+        // the edge of the element facing the split and the growth direction of the master area
+        let (edge, amount, size) = match self.side {
+            MasterSide::Left if is_master => (ResizeEdge::RIGHT, delta.x, area.size.w),
+            MasterSide::Left => (ResizeEdge::LEFT, delta.x, area.size.w),
+            MasterSide::Right if is_master => (ResizeEdge::LEFT, -delta.x, area.size.w),
+            MasterSide::Right => (ResizeEdge::RIGHT, -delta.x, area.size.w),
+            MasterSide::Top if is_master => (ResizeEdge::BOTTOM, delta.y, area.size.h),
+            MasterSide::Top => (ResizeEdge::TOP, delta.y, area.size.h),
+            MasterSide::Bottom if is_master => (ResizeEdge::TOP, -delta.y, area.size.h),
+            MasterSide::Bottom => (ResizeEdge::BOTTOM, -delta.y, area.size.h),
+        };
+        if !edges.contains(edge) || amount == 0 || size <= 0 {
+            return false;
+        }
+
+        self.ratio = (self.ratio + amount as f64 / size as f64).clamp(MIN_RATIO, MAX_RATIO);
+        true
+    }
+}
+
+/// Spiral layout
+///
+/// Every element splits the remaining area in half (adjustable via the per-split ratios),
+/// rotating the side it occupies clockwise: left, top, right, bottom.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spiral {
+    ratios: Vec<f64>,
+}
+
+impl Spiral {
+    /// Returns the ratio of the split created by the element at `index`
+    pub fn ratio(&self, index: usize) -> f64 {
+        self.ratios.get(index).copied().unwrap_or(0.5)
+    }
+
+    /// Sets the ratio of the split created by the element at `index`
+    pub fn set_ratio(&mut self, index: usize, ratio: f64) {
+        if self.ratios.len() <= index {
+            self.ratios.resize(index + 1, 0.5);
+        }
+        self.ratios[index] = ratio.clamp(MIN_RATIO, MAX_RATIO);
+    }
+
+    // returns the tile of each element and the area that was split by it
+    fn splits(
+        &self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+    ) -> Vec<(Rectangle<i32, Logical>, Rectangle<i32, Logical>)> {
+        let mut rest = area;
+        (0..count)
+            .map(|i| {
+                let split_area = rest;
+                if i + 1 == count {
+                    return (rest, split_area);
+                }
+                let ratio = self.ratio(i);
+                let tile = match i % 4 {
+                    0 => {
+                        let (tile, remaining) = split_horizontal(rest, ratio);
+                        rest = remaining;
+                        tile
+                    }
+                    1 => {
+                        let (tile, remaining) = split_vertical(rest, ratio);
+                        rest = remaining;
+                        tile
+                    }
+                    2 => {
+                        let (remaining, tile) = split_horizontal(rest, 1.0 - ratio);
+                        rest = remaining;
+                        tile
+                    }
+                    _ => {
+                        let (remaining, tile) = split_vertical(rest, 1.0 - ratio);
+                        rest = remaining;
+                        tile
+                    }
+                };
+                (tile, split_area)
+            })
+            .collect()
+    }
+}
+
+impl Layout for Spiral {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        self.splits(area, count)
+            .into_iter()
+            .map(|(tile, _)| tile)
+            .collect()
+    }
+
+    fn resize(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+        index: usize,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> bool {
+        if index >= count {
+            return false;
+        }
+        let splits = self.splits(area, count);
+
+        // edge facing the remaining area and the growth of the tile for the split at `idx`
+        let facing = |idx: usize| match idx % 4 {
+            0 => (ResizeEdge::RIGHT, delta.x, splits[idx].1.size.w),
+            1 => (ResizeEdge::BOTTOM, delta.y, splits[idx].1.size.h),
+            2 => (ResizeEdge::LEFT, -delta.x, splits[idx].1.size.w),
+            _ => (ResizeEdge::TOP, -delta.y, splits[idx].1.size.h),
+        };
+
+        let mut changed = false;
+        // the split created by this element
+        if index + 1 < count {
+            let (edge, amount, size) = facing(index);
+            if edges.contains(edge) && amount != 0 && size > 0 {
+                self.set_ratio(index, self.ratio(index) + amount as f64 / size as f64);
+                changed = true;
+            }
+        }
+        // the split of the previous element, which created the area this element resides in
+        if index > 0 {
+            let (edge, amount, size) = facing(index - 1);
+            let opposite = match edge {
+                ResizeEdge::RIGHT => ResizeEdge::LEFT,
+                ResizeEdge::LEFT => ResizeEdge::RIGHT,
+                ResizeEdge::BOTTOM => ResizeEdge::TOP,
+                _ => ResizeEdge::BOTTOM,
+            };
+            if edges.contains(opposite) && amount != 0 && size > 0 {
+                self.set_ratio(index - 1, self.ratio(index - 1) + amount as f64 / size as f64);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Grid layout
+///
+/// Arranges the elements into rows of evenly sized cells, the last row might contain less elements
+/// and stretches them over the full width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Grid {
+    /// Fixed number of columns, if `None` the number is derived from the element count
+    pub columns: Option<usize>,
+}
+
+impl Layout for Grid {
+    fn arrange(&self, area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (count as f64).sqrt().ceil() as usize)
+            .clamp(1, count);
+        let rows = (count + columns - 1) / columns;
+
+        stack(area, rows, false)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(row, row_area)| {
+                let in_row = (count - row * columns).min(columns);
+                stack(row_area, in_row, true)
+            })
+            .collect()
+    }
+}
+
+/// Keeps track of an ordered list of elements arranged by a [`Layout`]
+#[derive(Debug)]
+pub struct TilingLayout<E, L = Box<dyn Layout + Send>> {
+    elements: Vec<E>,
+    layout: L,
+    gaps: Gaps,
+}
+
+impl<E: PartialEq, L: Layout> TilingLayout<E, L> {
+    /// Creates a new empty [`TilingLayout`] with the given algorithm
+    pub fn new(layout: L) -> Self {
+        TilingLayout {
+            elements: Vec::new(),
+            layout,
+            gaps: Gaps::default(),
+        }
+    }
+
+    /// Returns the layout algorithm
+    pub fn layout(&self) -> &L {
+        &self.layout
+    }
+
+    /// Returns the layout algorithm mutably
+    pub fn layout_mut(&mut self) -> &mut L {
+        &mut self.layout
+    }
+
+    /// Replaces the layout algorithm
+    pub fn set_layout(&mut self, layout: L) {
+        self.layout = layout;
+    }
+
+    /// Returns the configured gaps
+    pub fn gaps(&self) -> Gaps {
+        self.gaps
+    }
+
+    /// Sets the gaps used for arranging elements
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+    }
+
+    /// Appends an element to the layout, if it is not already part of it
+    pub fn add_element(&mut self, element: E) {
+        if !self.elements.contains(&element) {
+            self.elements.push(element);
+        }
+    }
+
+    /// Inserts an element at a given position, moving it if it is already part of the layout
+    pub fn insert_element(&mut self, index: usize, element: E) {
+        self.elements.retain(|e| e != &element);
+        let index = index.min(self.elements.len());
+        self.elements.insert(index, element);
+    }
+
+    /// Removes an element from the layout
+    pub fn remove_element(&mut self, element: &E) -> Option<E> {
+        let pos = self.elements.iter().position(|e| e == element)?;
+        Some(self.elements.remove(pos))
+    }
+
+    /// Swaps the positions of two elements in the layout
+    pub fn swap_elements(&mut self, a: &E, b: &E) -> bool {
+        let a = self.elements.iter().position(|e| e == a);
+        let b = self.elements.iter().position(|e| e == b);
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                self.elements.swap(a, b);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Iterate over the elements in layout order
+    pub fn elements(&self) -> impl DoubleEndedIterator<Item = &E> + ExactSizeIterator {
+        self.elements.iter()
+    }
+
+    /// Computes the geometries of all elements for a given area in layout order
+    pub fn arrange(&self, area: Rectangle<i32, Logical>) -> Vec<(&E, Rectangle<i32, Logical>)> {
+        let half = self.gaps.inner / 2;
+        let mut area = area;
+        // elements are always inset by half the inner gap, which might already exceed the outer gap
+        let border = (self.gaps.outer - half).max(0);
+        area.loc += Point::from((border, border));
+        area.size -= Size::from(((2 * border).min(area.size.w), (2 * border).min(area.size.h)));
+
+        self.elements
+            .iter()
+            .zip(self.layout.arrange(area, self.elements.len()))
+            .map(|(element, mut geometry)| {
+                geometry.loc += Point::from((half, half));
+                geometry.size.w = (geometry.size.w - self.gaps.inner).max(1);
+                geometry.size.h = (geometry.size.h - self.gaps.inner).max(1);
+                (element, geometry)
+            })
+            .collect()
+    }
+
+    /// Returns the geometry of a single element for a given area
+    pub fn element_geometry(
+        &self,
+        element: &E,
+        area: Rectangle<i32, Logical>,
+    ) -> Option<Rectangle<i32, Logical>> {
+        self.arrange(area)
+            .into_iter()
+            .find(|(e, _)| *e == element)
+            .map(|(_, geo)| geo)
+    }
+
+    /// Forwards an interactive resize of an element to the layout algorithm.
+    ///
+    /// `delta` is the amount the dragged `edges` were moved, `area` needs to match the value
+    /// used to arrange the elements. Returns whenever the layout changed and should be re-applied.
+    pub fn resize_element(
+        &mut self,
+        element: &E,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+        area: Rectangle<i32, Logical>,
+    ) -> bool {
+        let Some(index) = self.elements.iter().position(|e| e == element) else {
+            return false;
+        };
+        self.layout.resize(area, self.elements.len(), index, edges, delta)
+    }
+
+    /// Forwards the new size requested during an interactive resize of an element.
+    ///
+    /// The size is compared to the current tile of the element to compute the edge movement.
+    /// See [`TilingLayout::resize_element`] for more information.
+    pub fn resize_element_to(
+        &mut self,
+        element: &E,
+        edges: ResizeEdge,
+        size: Size<i32, Logical>,
+        area: Rectangle<i32, Logical>,
+    ) -> bool {
+        let Some(current) = self.element_geometry(element, area) else {
+            return false;
+        };
+        let dw = size.w - current.size.w;
+        let dh = size.h - current.size.h;
+        let dx = if edges.contains(ResizeEdge::LEFT) { -dw } else { dw };
+        let dy = if edges.contains(ResizeEdge::TOP) { -dh } else { dh };
+        self.resize_element(element, edges, (dx, dy).into(), area)
+    }
+
+    /// Arranges all elements into the given area and maps them into a [`Space`].
+    ///
+    /// `configure` is called for every element with its new geometry to let the compositor
+    /// send the new size to the client (e.g. via [`ToplevelSurface::with_pending_state`](crate::wayland::shell::xdg::ToplevelSurface::with_pending_state)).
+    /// Elements are mapped at the location of their tile, without changing their stacking order.
+    pub fn apply<F>(&self, space: &mut Space<E>, area: Rectangle<i32, Logical>, mut configure: F)
+    where
+        E: SpaceElement + Clone,
+        F: FnMut(&E, Rectangle<i32, Logical>),
+    {
+        let geometries = self.arrange(area);
+        // keep the existing stacking order, by remapping in back-to-front order
+        let mut ordered = space
+            .elements()
+            .filter_map(|e| geometries.iter().position(|(g, _)| *g == e))
+            .collect::<Vec<_>>();
+        let unmapped = (0..geometries.len())
+            .filter(|i| !ordered.contains(i))
+            .collect::<Vec<_>>();
+        ordered.extend(unmapped);
+
+        for idx in ordered {
+            let (element, geometry) = geometries[idx];
+            configure(element, geometry);
+            space.map_element(element.clone(), geometry.loc, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area() -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((0, 0), (1000, 800))
+    }
+
+    #[test]
+    fn master_stack() {
+        let layout = MasterStack::default();
+        let geos = layout.arrange(area(), 3);
+        assert_eq!(geos[0], Rectangle::from_loc_and_size((0, 0), (500, 800)));
+        assert_eq!(geos[1], Rectangle::from_loc_and_size((500, 0), (500, 400)));
+        assert_eq!(geos[2], Rectangle::from_loc_and_size((500, 400), (500, 400)));
+
+        // a single element covers everything
+        assert_eq!(layout.arrange(area(), 1), vec![area()]);
+    }
+
+    #[test]
+    fn master_stack_resize() {
+        let mut layout = TilingLayout::new(MasterStack::default());
+        layout.add_element(1);
+        layout.add_element(2);
+
+        // dragging the wrong edge does nothing
+        assert!(!layout.resize_element(&1, ResizeEdge::LEFT, (100, 0).into(), area()));
+        assert!(layout.resize_element_to(&1, ResizeEdge::RIGHT, (600, 800).into(), area()));
+        assert_eq!(
+            layout.element_geometry(&2, area()),
+            Some(Rectangle::from_loc_and_size((600, 0), (400, 800)))
+        );
+    }
+
+    #[test]
+    fn spiral() {
+        let mut layout = Spiral::default();
+        let geos = layout.arrange(area(), 4);
+        assert_eq!(geos[0], Rectangle::from_loc_and_size((0, 0), (500, 800)));
+        assert_eq!(geos[1], Rectangle::from_loc_and_size((500, 0), (500, 400)));
+        assert_eq!(geos[2], Rectangle::from_loc_and_size((750, 400), (250, 400)));
+        assert_eq!(geos[3], Rectangle::from_loc_and_size((500, 400), (250, 400)));
+
+        // growing the third element to the left shrinks the fourth
+        assert!(layout.resize(area(), 4, 2, ResizeEdge::LEFT, (-50, 0).into()));
+        let geos = layout.arrange(area(), 4);
+        assert_eq!(geos[2], Rectangle::from_loc_and_size((700, 400), (300, 400)));
+        assert_eq!(geos[3], Rectangle::from_loc_and_size((500, 400), (200, 400)));
+    }
+
+    #[test]
+    fn grid_with_gaps() {
+        let mut layout = TilingLayout::new(Grid::default());
+        for i in 0..3 {
+            layout.add_element(i);
+        }
+        layout.set_gaps(Gaps { inner: 10, outer: 20 });
+        let geos = layout.arrange(area());
+        assert_eq!(geos[0].1, Rectangle::from_loc_and_size((20, 20), (475, 375)));
+        assert_eq!(geos[1].1, Rectangle::from_loc_and_size((505, 20), (475, 375)));
+        assert_eq!(geos[2].1, Rectangle::from_loc_and_size((20, 405), (960, 375)));
+
+        // inner gaps wider than twice the outer gap
+        layout.set_gaps(Gaps { inner: 40, outer: 0 });
+        let geos = layout.arrange(area());
+        assert_eq!(geos[0].1, Rectangle::from_loc_and_size((20, 20), (460, 360)));
+        assert_eq!(geos[1].1, Rectangle::from_loc_and_size((520, 20), (460, 360)));
+        assert_eq!(geos[2].1, Rectangle::from_loc_and_size((20, 420), (960, 360)));
+    }
+}
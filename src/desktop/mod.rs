@@ -20,13 +20,65 @@
 //!
 //! Note that a [`Window`] on it's own has no position. For that it needs to be placed inside a [`Space`].
 //!
+//! ### [`WindowGroup`]
+//!
+//! A window group holds multiple [`Window`]s in a single slot as a tabbed or stacked container,
+//! showing only its active window and providing the data necessary to render a tab bar.
+//!
 //! ### [`Space`]
 //!
 //! A space represents a two-dimensional plane of undefined dimensions.
 //! [`Window`]s (and other types implementing [`SpaceElement`](space::SpaceElement)) and [`Output`](crate::output::Output)s can be mapped onto it.
 //!
 //! Elements get a position and stacking order through mapping. Outputs become views of a part of the [`Space`]
-//! and can be rendered via [`render_output`](crate::desktop::space::render_output).
+//! and can be rendered via [`render_output`](crate::desktop::space::render_output). Custom renderers can query
+//! the accumulated damage of an output without rendering via [`damage_output`](crate::desktop::space::damage_output).
+//!
+//! ### [`Workspaces`]
+//!
+//! Manages multiple [`Space`]s per [`Output`](crate::output::Output), called [`Workspace`]s.
+//! Every output owns an independent list of workspaces, of which exactly one is active and thus visible at a time.
+//! Elements can be assigned to and moved between workspaces, input and rendering helpers only consider active workspaces.
+//!
+//! ### Focus history
+//!
+//! The [`focus`] module provides [`FocusHistory`](focus::FocusHistory), which tracks the most-recently-used
+//! order of elements per workspace or output to restore focus after windows close and to implement window switchers.
+//!
+//! ### Tiling layouts
+//!
+//! The [`layout`] module provides a [`TilingLayout`](layout::TilingLayout) helper, which arranges elements
+//! using composable tiling algorithms like master/stack, spiral or grid layouts and maps them into a [`Space`].
+//!
+//! ### Animations
+//!
+//! The [`animation`] module provides clock-driven [`Animation`](animation::Animation)s with easing curves
+//! and springs, and [`ElementAnimations`](animation::ElementAnimations), which interpolates the geometry,
+//! opacity and crop of elements and reports the outputs needing another frame while animations are running.
+//!
+//! ### Overview
+//!
+//! The [`overview`] module provides an [`Overview`](overview::Overview) helper, which arranges all elements
+//! of a [`Space`] in a grid of scaled down, non-overlapping slots with interpolatable animation targets
+//! and produces scaled render elements for them.
+//!
+//! ### Window rules
+//!
+//! The [`rules`] module provides [`WindowRules`](rules::WindowRules), which match windows by properties
+//! like their app_id or title and yield declarative actions to apply to them.
+//!
+//! ### Session restore
+//!
+//! The [`session`] module provides serializable layout snapshots of a [`Space`] or [`Workspaces`]
+//! (with the `serde` feature) and helpers to map restarted windows back into their saved slots.
+//!
+//! ### Interactive move and resize
+//!
+//! [`PointerMoveGrab`], [`PointerResizeGrab`] and their touch counterparts implement interactive move and
+//! resize operations of elements mapped in a [`Space`], as requested by clients through `xdg_toplevel.move`
+//! and `xdg_toplevel.resize` or their X11 equivalents. Constraint policy is provided by the compositor
+//! through [`InteractiveGrabHandler`]. The [`snap`] module provides [`EdgeSnapping`](snap::EdgeSnapping),
+//! which snaps moved elements to the edges of outputs, other elements and layer-shell exclusive zones.
 //!
 //! ### Layer Shell
 //!
@@ -43,26 +95,61 @@
 //! relations to one-another. Popups are then automatically rendered with their matching toplevel surfaces,
 //! when either [`crate::backend::renderer::element::AsRenderElements::render_elements`] or [`render_output`](crate::desktop::space::render_output) is called.
 //!
+//! ### Session lock
+//!
+//! A [`LockScreen`] manages the surfaces of an `ext-session-lock` client. It keeps one lock surface per output
+//! configured to the size of the output, renders a solid color for outputs without a lock surface and
+//! restricts hit-testing to lock surfaces while the session is locked.
+//!
 //! ## Remarks
 //!
 //! Note that the desktop abstractions are concerned with easing rendering different clients and therefore need to be able
 //! to manage client buffers to do so. If you plan to use the provided drawing functions, you need to use
 //! [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler).
 
+pub mod animation;
+pub mod focus;
+pub mod layout;
+pub mod overview;
+#[cfg(feature = "wayland_frontend")]
+pub mod rules;
+pub mod session;
+pub mod snap;
 pub mod space;
 pub use self::space::Space;
+pub mod workspace;
+pub use self::workspace::{Workspace, WorkspaceId, Workspaces};
 
 #[cfg(feature = "wayland_frontend")]
 pub use self::wayland::{
-    layer::{layer_map_for_output, LayerMap, LayerSurface},
+    decoration::{DecorationHit, DecorationStyle, WindowDecoration},
+    foreign_toplevel::ForeignToplevelSync,
+    grabs::{
+        handle_resize_commit, InteractiveGrabHandler, PointerMoveGrab, PointerResizeGrab, ResizeData,
+        ResizeState, TouchMoveGrab, TouchResizeGrab,
+    },
+    group::{GroupLayout, TabInfo, WindowGroup, DEFAULT_TAB_BAR_HEIGHT},
+    layer::{layer_map_for_output, ExclusiveZonePolicy, LayerMap, LayerSurface},
     popup::*,
+    restore::RestoreGeometry,
+    session_lock::LockScreen,
+    snapshot::WindowSnapshot,
+    thumbnail::WindowThumbnail,
     utils,
     window::*,
 };
 #[cfg(feature = "wayland_frontend")]
 mod wayland {
+    pub mod decoration;
+    pub mod foreign_toplevel;
+    pub mod grabs;
+    pub mod group;
     pub(crate) mod layer;
     pub mod popup;
+    pub mod restore;
+    pub mod session_lock;
+    pub mod snapshot;
+    pub mod thumbnail;
     pub mod utils;
     pub mod window;
 }
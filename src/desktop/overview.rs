@@ -0,0 +1,306 @@
+//! Overview (exposé) layouts
+//!
+//! An [`Overview`] arranges all elements of a [`Space`], e.g. the one of a
+//! [`Workspace`](super::Workspace), in a grid of non-overlapping, scaled down slots.
+//! Every [`OverviewSlot`] stores the current geometry of its element and its target geometry in the grid,
+//! so compositors can animate the transition by interpolating between both with
+//! [`OverviewSlot::geometry`] and an animation progress between `0.0` and `1.0`.
+//!
+//! [`Overview::render_elements`] produces scaled render elements for all slots at a given progress,
+//! which can be rendered instead of the space contents while the overview is shown.
+//!
+//! ```no_run
+//! # use smithay::desktop::{overview::{Overview, OverviewConfig}, Space, Window};
+//! # use smithay::utils::{Logical, Point, Rectangle};
+//! # let space: Space<Window> = unimplemented!();
+//! # let output_geometry: Rectangle<i32, Logical> = unimplemented!();
+//! # let pointer: Point<f64, Logical> = unimplemented!();
+//! let overview = Overview::new(&space, output_geometry, OverviewConfig::default());
+//!
+//! // once the animation finished, pick the window under the pointer
+//! if let Some(window) = overview.element_under(pointer, 1.0) {
+//!     // ...
+//! }
+//! ```
+
+use crate::{
+    backend::renderer::{
+        element::{utils::RescaleRenderElement, AsRenderElements},
+        Renderer, Texture,
+    },
+    utils::{Logical, Point, Rectangle, Scale, Size},
+};
+
+use super::space::{Space, SpaceElement};
+
+/// Configuration of an [`Overview`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverviewConfig {
+    /// Gap in logical pixels between slots and around the grid
+    pub gap: i32,
+    /// Maximum scale of elements in the grid
+    ///
+    /// Elements are only ever scaled down by default, set this to a value above `1.0` to enlarge
+    /// small elements as well.
+    pub max_scale: f64,
+}
+
+impl Default for OverviewConfig {
+    fn default() -> Self {
+        OverviewConfig {
+            gap: 32,
+            max_scale: 1.0,
+        }
+    }
+}
+
+/// Placement of a single element in an [`Overview`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverviewSlot<E> {
+    /// The element placed in the slot
+    pub element: E,
+    /// Geometry of the element in the space when the overview was created
+    pub from: Rectangle<f64, Logical>,
+    /// Geometry of the element in the overview grid
+    pub to: Rectangle<f64, Logical>,
+}
+
+impl<E> OverviewSlot<E> {
+    /// Returns the geometry of the element at an animation progress
+    ///
+    /// A progress of `0.0` returns [`OverviewSlot::from`], `1.0` returns [`OverviewSlot::to`].
+    /// Values outside of this range are extrapolated, which allows for overshooting easing curves.
+    pub fn geometry(&self, progress: f64) -> Rectangle<f64, Logical> {
+        let lerp = |from: f64, to: f64| from + (to - from) * progress;
+        Rectangle::from_loc_and_size(
+            (
+                lerp(self.from.loc.x, self.to.loc.x),
+                lerp(self.from.loc.y, self.to.loc.y),
+            ),
+            (
+                lerp(self.from.size.w, self.to.size.w),
+                lerp(self.from.size.h, self.to.size.h),
+            ),
+        )
+    }
+
+    /// Returns the scale of the element at an animation progress
+    pub fn scale(&self, progress: f64) -> f64 {
+        if self.from.size.w <= 0.0 {
+            return 1.0;
+        }
+        self.geometry(progress).size.w / self.from.size.w
+    }
+}
+
+/// Grid of scaled down elements of a [`Space`]
+#[derive(Debug, Clone)]
+pub struct Overview<E> {
+    area: Rectangle<i32, Logical>,
+    slots: Vec<OverviewSlot<E>>,
+}
+
+impl<E: SpaceElement + PartialEq + Clone> Overview<E> {
+    /// Arranges all mapped elements of a space inside `area`
+    ///
+    /// `area` is usually the geometry of an output or its non-exclusive zone in space coordinates.
+    /// Elements are ordered by their current location, top to bottom and left to right,
+    /// and the number of columns is chosen to make the scaled elements as large as possible.
+    /// Minimized elements are not included.
+    pub fn new(space: &Space<E>, area: Rectangle<i32, Logical>, config: OverviewConfig) -> Self {
+        let mut elements = space
+            .elements()
+            .filter_map(|element| {
+                let geometry = space.element_geometry(element)?;
+                Some((element.clone(), geometry))
+            })
+            .collect::<Vec<_>>();
+        elements.sort_by_key(|(_, geometry)| {
+            let center = geometry.loc + geometry.size.downscale(2).to_point();
+            (center.y, center.x)
+        });
+
+        let sizes = elements
+            .iter()
+            .map(|(_, geometry)| geometry.size)
+            .collect::<Vec<_>>();
+        let targets = arrange(&sizes, area, config);
+        let slots = elements
+            .into_iter()
+            .zip(targets)
+            .map(|((element, from), to)| OverviewSlot {
+                element,
+                from: from.to_f64(),
+                to,
+            })
+            .collect();
+
+        Overview { area, slots }
+    }
+
+    /// Returns the area the elements were arranged in
+    pub fn area(&self) -> Rectangle<i32, Logical> {
+        self.area
+    }
+
+    /// Returns all slots of the overview
+    pub fn slots(&self) -> &[OverviewSlot<E>] {
+        &self.slots
+    }
+
+    /// Returns the slot of an element
+    pub fn slot(&self, element: &E) -> Option<&OverviewSlot<E>> {
+        self.slots.iter().find(|slot| &slot.element == element)
+    }
+
+    /// Returns the element at a point in space coordinates at an animation progress
+    pub fn element_under(&self, point: Point<f64, Logical>, progress: f64) -> Option<&E> {
+        self.slots
+            .iter()
+            .find(|slot| slot.geometry(progress).contains(point))
+            .map(|slot| &slot.element)
+    }
+
+    /// Retrieve scaled render elements for all slots at an animation progress
+    ///
+    /// `region` is the part of the space being rendered, usually the geometry of the output,
+    /// and `scale` the scale of the output. Elements are returned front to back.
+    pub fn render_elements<R>(
+        &self,
+        renderer: &mut R,
+        region: Rectangle<i32, Logical>,
+        scale: impl Into<Scale<f64>>,
+        progress: f64,
+        alpha: f32,
+    ) -> Vec<RescaleRenderElement<<E as AsRenderElements<R>>::RenderElement>>
+    where
+        R: Renderer,
+        <R as Renderer>::TextureId: Texture + 'static,
+        E: AsRenderElements<R>,
+    {
+        let scale = scale.into();
+        self.slots
+            .iter()
+            .rev()
+            .filter(|slot| slot.geometry(progress).overlaps(region.to_f64()))
+            .flat_map(|slot| {
+                let geometry = slot.geometry(progress);
+                let origin = (geometry.loc - region.loc.to_f64()).to_physical_precise_round(scale);
+                // render at the origin of the geometry and scale everything around it
+                let location = origin - slot.element.geometry().loc.to_physical_precise_round(scale);
+                slot.element
+                    .render_elements::<<E as AsRenderElements<R>>::RenderElement>(
+                        renderer, location, scale, alpha,
+                    )
+                    .into_iter()
+                    .map(move |element| {
+                        RescaleRenderElement::from_element(element, origin, slot.scale(progress))
+                    })
+            })
+            .collect()
+    }
+}
+
+// Computes the target geometries of elements with the given sizes
+fn arrange(
+    sizes: &[Size<i32, Logical>],
+    area: Rectangle<i32, Logical>,
+    config: OverviewConfig,
+) -> Vec<Rectangle<f64, Logical>> {
+    let count = sizes.len();
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let gap = config.gap as f64;
+    let cell_size = |columns: usize| {
+        let rows = (count + columns - 1) / columns;
+        Size::<f64, Logical>::from((
+            ((area.size.w as f64 - gap * (columns + 1) as f64) / columns as f64).max(1.0),
+            ((area.size.h as f64 - gap * (rows + 1) as f64) / rows as f64).max(1.0),
+        ))
+    };
+    let fit = |size: Size<i32, Logical>, cell: Size<f64, Logical>| {
+        if size.w <= 0 || size.h <= 0 {
+            return 1.0;
+        }
+        f64::min(cell.w / size.w as f64, cell.h / size.h as f64).min(config.max_scale)
+    };
+
+    // pick the number of columns covering the largest area with scaled elements
+    let columns = (1..=count)
+        .max_by(|a, b| {
+            let covered = |columns: usize| {
+                let cell = cell_size(columns);
+                sizes
+                    .iter()
+                    .map(|size| {
+                        let scale = fit(*size, cell);
+                        size.w as f64 * size.h as f64 * scale * scale
+                    })
+                    .sum::<f64>()
+            };
+            // prefer fewer columns on ties
+            covered(*a).total_cmp(&covered(*b)).then(b.cmp(a))
+        })
+        .unwrap();
+    let rows = (count + columns - 1) / columns;
+    let cell = cell_size(columns);
+
+    sizes
+        .iter()
+        .enumerate()
+        .map(|(index, size)| {
+            let row = index / columns;
+            let column = index % columns;
+            // center incomplete rows
+            let in_row = if row == rows - 1 {
+                count - row * columns
+            } else {
+                columns
+            };
+            let row_offset = (columns - in_row) as f64 * (cell.w + gap) / 2.0;
+
+            let scale = fit(*size, cell);
+            let scaled = Size::<f64, Logical>::from((size.w as f64 * scale, size.h as f64 * scale));
+            let cell_loc = Point::<f64, Logical>::from((
+                area.loc.x as f64 + gap + row_offset + column as f64 * (cell.w + gap),
+                area.loc.y as f64 + gap + row as f64 * (cell.h + gap),
+            ));
+            Rectangle::from_loc_and_size(
+                (
+                    cell_loc.x + (cell.w - scaled.w) / 2.0,
+                    cell_loc.y + (cell.h - scaled.h) / 2.0,
+                ),
+                scaled,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arrange, OverviewConfig};
+    use crate::utils::{Logical, Rectangle, Size};
+
+    #[test]
+    fn overview_slots_do_not_overlap() {
+        let area = Rectangle::<i32, Logical>::from_loc_and_size((100, 0), (1920, 1080));
+        let sizes = [(1920, 1080), (800, 600), (400, 1000), (1280, 720), (640, 480)]
+            .into_iter()
+            .map(Size::from)
+            .collect::<Vec<_>>();
+        let slots = arrange(&sizes, area, OverviewConfig::default());
+
+        assert_eq!(slots.len(), sizes.len());
+        for (i, slot) in slots.iter().enumerate() {
+            assert!(area.to_f64().contains_rect(*slot));
+            // the aspect ratio is preserved
+            let size = sizes[i].to_f64();
+            assert!((slot.size.w / slot.size.h - size.w / size.h).abs() < 0.001);
+            for other in &slots[i + 1..] {
+                assert!(!slot.overlaps(*other));
+            }
+        }
+    }
+}
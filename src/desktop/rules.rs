@@ -0,0 +1,405 @@
+//! Declarative window rules
+//!
+//! [`WindowRules`] hold an ordered list of [`WindowRule`]s, each consisting of a [`WindowMatch`]
+//! describing the windows it applies to and a list of [`RuleAction`]s to apply to them.
+//!
+//! Rules are evaluated when a window is created and need to be re-evaluated when its
+//! properties change. Clients usually set the title and app_id of a toplevel only after creating it
+//! and change them (as well as the matched maximized and fullscreen states) over its lifetime,
+//! all of which takes effect with the next commit of the toplevel surface. A typical integration thus
+//! calls [`WindowRules::on_commit`] for the window of every committed root surface in
+//! [`CompositorHandler::commit`](crate::wayland::compositor::CompositorHandler::commit)
+//! (and [`WindowRules::apply`] on the corresponding X11 property notifications) and applies the
+//! returned actions:
+//!
+//! ```no_run
+//! use smithay::desktop::{rules::{RuleAction, Pattern, WindowMatch, WindowRule, WindowRules}, Window};
+//!
+//! let mut rules = WindowRules::<()>::new();
+//! rules.add_rule(WindowRule {
+//!     matcher: WindowMatch {
+//!         app_id: Some(Pattern::Exact("org.gnome.Calculator".into())),
+//!         ..Default::default()
+//!     },
+//!     actions: vec![RuleAction::Floating(true), RuleAction::Opacity(0.9)],
+//! });
+//!
+//! // in `CompositorHandler::commit`
+//! # let window: Window = todo!();
+//! if let Some(actions) = rules.on_commit(&window) {
+//!     for action in actions {
+//!         // apply the action
+//!     }
+//! }
+//! ```
+//!
+//! Compositors can extend the set of actions with their own type using [`RuleAction::Custom`].
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use crate::{
+    desktop::{Window, WindowSurface},
+    utils::{Logical, Size},
+};
+
+// Generations are never reused, so matches recorded for removed rules never alias new ones
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Pattern used to match string properties of a window
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches if the property is equal to the given string
+    Exact(String),
+    /// Matches if the property contains the given string
+    Contains(String),
+    /// Matches if the given regular expression matches the property
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    /// Returns whether the pattern matches the given string
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Exact(s) => value == s,
+            Pattern::Contains(s) => value.contains(s.as_str()),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Describes the windows a [`WindowRule`] applies to
+///
+/// All set fields have to match for a window to be matched, unset fields match every window.
+/// Properties not provided by a window (e.g. the app_id of an X11 window) never match a pattern.
+#[derive(Debug, Default, Clone)]
+pub struct WindowMatch {
+    /// Pattern for the app_id of a wayland window
+    pub app_id: Option<Pattern>,
+    /// Pattern for the title of a window
+    pub title: Option<Pattern>,
+    /// Pattern for the `WM_CLASS` of an X11 window
+    pub x11_class: Option<Pattern>,
+    /// Whether the window is an X11 window
+    pub is_x11: Option<bool>,
+    /// Whether the window is transient for another window, e.g. a dialog
+    pub has_parent: Option<bool>,
+    /// Whether the window is maximized
+    ///
+    /// For wayland windows this is the state last acknowledged and committed by the client.
+    pub maximized: Option<bool>,
+    /// Whether the window is fullscreen
+    ///
+    /// For wayland windows this is the state last acknowledged and committed by the client.
+    pub fullscreen: Option<bool>,
+}
+
+impl WindowMatch {
+    /// Returns whether the given window is matched
+    pub fn matches(&self, window: &Window) -> bool {
+        self.matches_properties(&WindowProperties::from_window(window))
+    }
+
+    fn matches_properties(&self, properties: &WindowProperties) -> bool {
+        fn matches_pattern(pattern: &Option<Pattern>, value: Option<&str>) -> bool {
+            match pattern {
+                Some(pattern) => value.map(|value| pattern.matches(value)).unwrap_or(false),
+                None => true,
+            }
+        }
+        fn matches_flag(flag: Option<bool>, value: bool) -> bool {
+            flag.map(|flag| flag == value).unwrap_or(true)
+        }
+
+        matches_pattern(&self.app_id, properties.app_id.as_deref())
+            && matches_pattern(&self.title, properties.title.as_deref())
+            && matches_pattern(&self.x11_class, properties.x11_class.as_deref())
+            && matches_flag(self.is_x11, properties.is_x11)
+            && matches_flag(self.has_parent, properties.has_parent)
+            && matches_flag(self.maximized, properties.maximized)
+            && matches_flag(self.fullscreen, properties.fullscreen)
+    }
+}
+
+/// Action applied to windows matched by a [`WindowRule`]
+///
+/// `A` allows compositors to add their own actions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction<A = ()> {
+    /// Place the window on the workspace with the given name
+    Workspace(String),
+    /// Make the window floating or tiled
+    Floating(bool),
+    /// Set the size of the window
+    Size(Size<i32, Logical>),
+    /// Set the opacity of the window
+    Opacity(f32),
+    /// Compositor defined action
+    Custom(A),
+}
+
+/// A single window rule
+#[derive(Debug, Clone)]
+pub struct WindowRule<A = ()> {
+    /// Windows this rule applies to
+    pub matcher: WindowMatch,
+    /// Actions applied to matched windows
+    pub actions: Vec<RuleAction<A>>,
+}
+
+/// Ordered set of [`WindowRule`]s
+#[derive(Debug)]
+pub struct WindowRules<A = ()> {
+    generation: u64,
+    rules: Vec<WindowRule<A>>,
+    // generation of every rule in `rules`
+    rule_generations: Vec<u64>,
+}
+
+impl<A> Default for WindowRules<A> {
+    fn default() -> Self {
+        WindowRules {
+            generation: next_generation(),
+            rules: Vec::new(),
+            rule_generations: Vec::new(),
+        }
+    }
+}
+
+// Generations of the rules last matched by a window, per set of rules
+#[derive(Debug, Default)]
+struct MatchedRules(Mutex<Vec<(u64, Vec<u64>)>>);
+
+impl MatchedRules {
+    // Records the matched rules, returns whether they changed
+    fn update(&self, rules: u64, matching: Vec<u64>) -> bool {
+        let mut matched = self.0.lock().unwrap();
+        match matched.iter_mut().find(|(generation, _)| *generation == rules) {
+            Some((_, previous)) if *previous == matching => false,
+            Some((_, previous)) => {
+                *previous = matching;
+                true
+            }
+            None => {
+                matched.push((rules, matching));
+                true
+            }
+        }
+    }
+}
+
+impl<A> WindowRules<A> {
+    /// Creates an empty set of rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a rule
+    ///
+    /// Actions of later rules take precedence over actions of earlier rules.
+    pub fn add_rule(&mut self, rule: WindowRule<A>) {
+        self.rules.push(rule);
+        self.rule_generations.push(next_generation());
+    }
+
+    /// Removes all rules
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.rule_generations.clear();
+    }
+
+    /// Returns the rules in order of evaluation
+    pub fn rules(&self) -> &[WindowRule<A>] {
+        &self.rules
+    }
+
+    /// Returns the actions of all rules matching the given window, in order of evaluation
+    pub fn evaluate<'a>(&'a self, window: &Window) -> impl Iterator<Item = &'a RuleAction<A>> {
+        self.matching(&WindowProperties::from_window(window))
+            .into_iter()
+            .flat_map(move |idx| self.rules[idx].actions.iter())
+    }
+
+    /// Evaluates the rules for a window and returns its actions, if the set of matching rules changed
+    ///
+    /// The rules matched by a window are remembered, so this returns `Some` on the first call
+    /// for a window and afterwards only, if a change of the window properties (like its title)
+    /// caused a different set of rules to match. If rules were added or removed in between,
+    /// this also returns `Some` in case the matched rules differ, rules added again after
+    /// [`WindowRules::clear`] count as different rules.
+    pub fn apply(&self, window: &Window) -> Option<Vec<&RuleAction<A>>> {
+        window
+            .user_data()
+            .insert_if_missing_threadsafe(MatchedRules::default);
+        let matched = window.user_data().get::<MatchedRules>().unwrap();
+        self.apply_properties(matched, &WindowProperties::from_window(window))
+    }
+
+    /// Re-evaluates the rules for a window after its surface was committed
+    ///
+    /// Should be called for the window of every committed root surface, which includes the initial
+    /// commit of a toplevel before its initial configure is sent, so the returned actions can still
+    /// influence it. Returns the actions of all matching rules on the first call for a window and
+    /// afterwards only, if a change of its title, app_id or committed state caused a different set
+    /// of rules to match, see [`WindowRules::apply`].
+    pub fn on_commit(&self, window: &Window) -> Option<Vec<&RuleAction<A>>> {
+        self.apply(window)
+    }
+
+    fn apply_properties(
+        &self,
+        matched: &MatchedRules,
+        properties: &WindowProperties,
+    ) -> Option<Vec<&RuleAction<A>>> {
+        let matching = self.matching(properties);
+        let generations = matching.iter().map(|idx| self.rule_generations[*idx]).collect();
+        if !matched.update(self.generation, generations) {
+            return None;
+        }
+
+        Some(
+            matching
+                .into_iter()
+                .flat_map(|idx| self.rules[idx].actions.iter())
+                .collect(),
+        )
+    }
+
+    fn matching(&self, properties: &WindowProperties) -> Vec<usize> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.matcher.matches_properties(properties))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct WindowProperties {
+    app_id: Option<String>,
+    title: Option<String>,
+    x11_class: Option<String>,
+    is_x11: bool,
+    has_parent: bool,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+impl WindowProperties {
+    fn from_window(window: &Window) -> Self {
+        match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                let state = toplevel.current_state();
+                let maximized = state.states.contains(xdg_toplevel::State::Maximized);
+                let fullscreen = state.states.contains(xdg_toplevel::State::Fullscreen);
+                WindowProperties {
+                    app_id: window.app_id(),
+                    title: window.title(),
+                    x11_class: None,
+                    is_x11: false,
+                    has_parent: toplevel.parent().is_some(),
+                    maximized,
+                    fullscreen,
+                }
+            }
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => WindowProperties {
+                app_id: None,
+                title: window.title(),
+                x11_class: Some(surface.class()),
+                is_x11: true,
+                has_parent: surface.is_transient_for().is_some(),
+                maximized: surface.is_maximized(),
+                fullscreen: surface.is_fullscreen(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MatchedRules, Pattern, RuleAction, WindowMatch, WindowProperties, WindowRule, WindowRules};
+
+    fn properties(app_id: &str, maximized: bool) -> WindowProperties {
+        WindowProperties {
+            app_id: Some(app_id.into()),
+            title: Some("title".into()),
+            maximized,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn match_properties() {
+        let matcher = WindowMatch {
+            app_id: Some(Pattern::Contains("term".into())),
+            maximized: Some(true),
+            ..Default::default()
+        };
+        assert!(matcher.matches_properties(&properties("foot-terminal", true)));
+        assert!(!matcher.matches_properties(&properties("foot-terminal", false)));
+        assert!(!matcher.matches_properties(&properties("firefox", true)));
+
+        // x11 windows have no app_id
+        let x11 = WindowProperties {
+            is_x11: true,
+            ..Default::default()
+        };
+        assert!(!matcher.matches_properties(&x11));
+        assert!(WindowMatch::default().matches_properties(&x11));
+    }
+
+    #[test]
+    fn apply_only_reports_changes() {
+        let floating = || WindowRule {
+            matcher: WindowMatch {
+                app_id: Some(Pattern::Exact("calc".into())),
+                ..Default::default()
+            },
+            actions: vec![RuleAction::<()>::Floating(true)],
+        };
+        let mut rules = WindowRules::new();
+        rules.add_rule(floating());
+        let matched = MatchedRules::default();
+        let window = properties("calc", false);
+
+        assert_eq!(
+            rules.apply_properties(&matched, &window),
+            Some(vec![&RuleAction::Floating(true)])
+        );
+        assert_eq!(rules.apply_properties(&matched, &window), None);
+        assert_eq!(
+            rules.apply_properties(&matched, &properties("other", false)),
+            Some(vec![])
+        );
+        assert_eq!(
+            rules.apply_properties(&matched, &window),
+            Some(vec![&RuleAction::Floating(true)])
+        );
+
+        // replacing the rules is picked up, even though the new rule has the same index
+        rules.clear();
+        rules.add_rule(floating());
+        assert_eq!(
+            rules.apply_properties(&matched, &window),
+            Some(vec![&RuleAction::Floating(true)])
+        );
+
+        // other sets of rules are tracked separately
+        let mut other = WindowRules::new();
+        other.add_rule(floating());
+        assert!(other.apply_properties(&matched, &window).is_some());
+        assert_eq!(rules.apply_properties(&matched, &window), None);
+    }
+}
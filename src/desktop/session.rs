@@ -0,0 +1,325 @@
+//! Layout snapshots for session restore
+//!
+//! A [`SpaceLayout`] records the geometry, stacking order and output of every element of a
+//! [`Space`](super::Space), a [`WorkspacesLayout`] does the same for all workspaces managed
+//! by [`Workspaces`]. Elements are identified by stable identifiers supplied by the compositor,
+//! e.g. derived from the app_id and a session management token, as smithay has no way of
+//! recognizing a window across compositor restarts.
+//!
+//! With the `serde` feature enabled all layout types implement `Serialize` and `Deserialize`,
+//! so they can be stored on disk and read back at startup. New windows are then matched up with
+//! their saved slots using [`Space::restore_element`](super::Space::restore_element)
+//! or [`Workspaces::restore_element`].
+//!
+//! ```no_run
+//! # use smithay::desktop::{Space, Window, session::SpaceLayout};
+//! # fn stable_id(window: &Window) -> Option<String> { unimplemented!() }
+//! # let mut space: Space<Window> = unimplemented!();
+//! # let window: Window = unimplemented!();
+//! // before shutting down
+//! let layout = space.layout(stable_id);
+//!
+//! // after restarting, for every newly mapped window
+//! # let layout: SpaceLayout<String> = layout;
+//! if let Err(window) = space.restore_element(&layout, window, stable_id, true) {
+//!     // no saved slot, place it as usual
+//!     space.map_element(window, (0, 0), true);
+//! }
+//! ```
+
+use crate::{
+    output::Output,
+    utils::{Logical, Point, Rectangle},
+};
+
+use super::{
+    space::{SpaceElement, StackingLayer},
+    WorkspaceId, Workspaces,
+};
+
+/// Output an element was placed on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputPlacement {
+    /// Name of the output, see [`Output::name`]
+    pub name: String,
+    /// Location of the element relative to the top-left corner of the output
+    pub offset: Point<i32, Logical>,
+}
+
+/// Saved state of a single element
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElementLayout<K> {
+    /// Identifier of the element supplied by the compositor
+    pub id: K,
+    /// Geometry of the element in space coordinates
+    ///
+    /// The size is informational, it is up to the compositor to configure restored windows accordingly.
+    pub geometry: Rectangle<i32, Logical>,
+    /// Output overlapping most with the element, if any
+    ///
+    /// If an output with the same name is mapped on restore, the element is placed relative to it,
+    /// so the element follows the output if its location changed.
+    pub output: Option<OutputPlacement>,
+    /// Stacking layer of the element
+    pub layer: StackingLayer,
+    /// Opacity of the element
+    pub opacity: f32,
+    /// Whether the element was minimized
+    pub minimized: bool,
+}
+
+/// Saved layout of a [`Space`](super::Space)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpaceLayout<K> {
+    /// Saved elements in z-order back to front, followed by minimized elements
+    pub elements: Vec<ElementLayout<K>>,
+}
+
+impl<K> Default for SpaceLayout<K> {
+    fn default() -> Self {
+        SpaceLayout { elements: Vec::new() }
+    }
+}
+
+impl<K: PartialEq> SpaceLayout<K> {
+    /// Returns the saved slot of an element identifier
+    pub fn slot(&self, id: &K) -> Option<&ElementLayout<K>> {
+        self.elements.iter().find(|e| &e.id == id)
+    }
+
+    pub(crate) fn position(&self, id: &K) -> Option<usize> {
+        self.elements.iter().position(|e| &e.id == id)
+    }
+}
+
+/// Saved state of a single workspace
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkspaceLayout<K> {
+    /// Name of the workspace
+    pub name: Option<String>,
+    /// Name of the output owning the workspace
+    pub output: String,
+    /// Whether the workspace was the active one of its output
+    pub active: bool,
+    /// Layout of the workspace contents
+    pub space: SpaceLayout<K>,
+}
+
+/// Saved layout of all workspaces of [`Workspaces`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkspacesLayout<K> {
+    /// Saved workspaces ordered by output
+    pub workspaces: Vec<WorkspaceLayout<K>>,
+}
+
+impl<K> Default for WorkspacesLayout<K> {
+    fn default() -> Self {
+        WorkspacesLayout {
+            workspaces: Vec::new(),
+        }
+    }
+}
+
+impl<E: SpaceElement + PartialEq> Workspaces<E> {
+    /// Captures the layout of all workspaces
+    ///
+    /// `id` returns the stable identifier of an element, elements without one are skipped.
+    pub fn layout<K, F>(&self, mut id: F) -> WorkspacesLayout<K>
+    where
+        F: FnMut(&E) -> Option<K>,
+    {
+        let mut workspaces = Vec::new();
+        for output in self.outputs() {
+            for workspace in self.workspaces_for_output(output) {
+                workspaces.push(WorkspaceLayout {
+                    name: workspace.name().map(String::from),
+                    output: output.name(),
+                    active: self.is_active(workspace.id()),
+                    space: workspace.space().layout(&mut id),
+                });
+            }
+        }
+        WorkspacesLayout { workspaces }
+    }
+
+    /// Recreates the saved workspaces of an output
+    ///
+    /// Creates workspaces until the output has at least as many as were saved for it,
+    /// restores their names and activates the previously active one.
+    /// This should be called once an output is added, before restoring its elements.
+    pub fn restore_output_layout<K>(&mut self, layout: &WorkspacesLayout<K>, output: &Output) {
+        let name = output.name();
+        let saved = layout
+            .workspaces
+            .iter()
+            .filter(|w| w.output == name)
+            .collect::<Vec<_>>();
+        while self.workspaces_for_output(output).count() < saved.len() {
+            if self.create_workspace(output).is_none() {
+                return;
+            }
+        }
+
+        let ids = self
+            .workspaces_for_output(output)
+            .map(|w| w.id())
+            .collect::<Vec<_>>();
+        for (saved, id) in saved.into_iter().zip(ids) {
+            if let Some(workspace) = self.workspace_mut(id) {
+                workspace.set_name(saved.name.clone());
+            }
+            if saved.active {
+                self.activate(id);
+            }
+        }
+    }
+
+    /// Maps an element into its saved workspace and slot
+    ///
+    /// The workspace is looked up by the name of its output and its position among the workspaces of
+    /// that output, see [`Workspaces::restore_output_layout`]. See [`Space::restore_element`](super::Space::restore_element)
+    /// for how the element is placed inside the workspace.
+    ///
+    /// Returns the workspace and the saved slot, or the element if there is no slot for it
+    /// or the output it was on is not present.
+    pub fn restore_element<'l, K, F>(
+        &mut self,
+        layout: &'l WorkspacesLayout<K>,
+        element: E,
+        mut id: F,
+        activate: bool,
+    ) -> Result<(WorkspaceId, &'l ElementLayout<K>), E>
+    where
+        K: PartialEq,
+        E: Clone,
+        F: FnMut(&E) -> Option<K>,
+    {
+        let Some(key) = id(&element) else {
+            return Err(element);
+        };
+        let Some(saved) = layout.workspaces.iter().find(|w| w.space.slot(&key).is_some()) else {
+            return Err(element);
+        };
+        let index = layout
+            .workspaces
+            .iter()
+            .take_while(|w| !std::ptr::eq(*w, saved))
+            .filter(|w| w.output == saved.output)
+            .count();
+        let target = self
+            .outputs()
+            .find(|o| o.name() == saved.output)
+            .and_then(|output| self.workspaces_for_output(output).nth(index))
+            .map(|w| w.id());
+        let Some(target) = target else {
+            return Err(element);
+        };
+
+        if let Some(old) = self.workspace_for_element(&element).filter(|old| *old != target) {
+            self.workspace_mut(old).unwrap().space_mut().unmap_elem(&element);
+        }
+        self.workspace_mut(target)
+            .unwrap()
+            .space_mut()
+            .restore_element(&saved.space, element, id, activate)
+            .map(|slot| (target, slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        desktop::{space::SpaceElement, Workspaces},
+        output::{Mode, Output, PhysicalProperties, Subpixel},
+        utils::{IsAlive, Logical, Point, Rectangle},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestElement(u32);
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (100, 100))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    fn output(name: &str) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: (1000, 1000).into(),
+                refresh: 60_000,
+            }),
+            None,
+            None,
+            None,
+        );
+        output
+    }
+
+    #[test]
+    fn workspaces_restore_follows_outputs() {
+        let left = output("left");
+        let right = output("right");
+        let id = |e: &TestElement| Some(e.0);
+
+        let mut workspaces = Workspaces::default();
+        workspaces.add_output(&left, (0, 0));
+        workspaces.add_output(&right, (1000, 0));
+        let second = workspaces.create_workspace(&right).unwrap();
+        workspaces
+            .workspace_mut(second)
+            .unwrap()
+            .set_name("second".to_string());
+        workspaces.activate(second);
+        workspaces.map_element(second, TestElement(1), (1010, 20), false);
+        let layout = workspaces.layout(id);
+        assert_eq!(layout.workspaces.len(), 3);
+
+        // the right output moved to the left side
+        let mut restored = Workspaces::default();
+        restored.add_output(&right, (0, 0));
+        restored.restore_output_layout(&layout, &right);
+        let (workspace, slot) = restored
+            .restore_element(&layout, TestElement(1), id, false)
+            .unwrap();
+        assert_eq!(slot.geometry.loc, (1010, 20).into());
+
+        let workspace = restored.workspace(workspace).unwrap();
+        assert_eq!(workspace.name(), Some("second"));
+        assert!(restored.is_active(workspace.id()));
+        assert_eq!(
+            workspace.space().element_location(&TestElement(1)),
+            Some((10, 20).into())
+        );
+
+        // elements without a saved slot are handed back
+        assert!(restored
+            .restore_element(&layout, TestElement(2), id, false)
+            .is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Edge snapping for interactive moves
+//!
+//! [`EdgeSnapping`] adjusts the location of an element being moved, so that its edges snap to the
+//! edges of outputs, of other elements mapped in the same [`Space`] and of the area left by layer-shell
+//! exclusive zones, once they come close enough. A snapped edge resists further movement until
+//! the element is dragged away by more than [`SnapConfig::release_distance`].
+//!
+//! The helper is meant to be layered on top of the interactive move grabs by calling
+//! [`EdgeSnapping::constrain`] from
+//! [`InteractiveGrabHandler::constrain_move`](crate::desktop::InteractiveGrabHandler::constrain_move)
+//! and [`EdgeSnapping::reset`] once the move finished.
+//!
+//! ```no_run
+//! # use smithay::desktop::{snap::EdgeSnapping, Space, Window};
+//! # use smithay::utils::{Logical, Point};
+//! # let space: Space<Window> = unimplemented!();
+//! # let window: Window = unimplemented!();
+//! # let location: Point<i32, Logical> = unimplemented!();
+//! let mut snapping = EdgeSnapping::default();
+//!
+//! // for every motion of the move grab
+//! let location = snapping.constrain(&space, &window, location);
+//!
+//! // once the grab ended
+//! snapping.reset();
+//! ```
+
+use crate::utils::{Logical, Point, Rectangle, Size};
+
+use super::space::{Space, SpaceElement};
+
+/// Configuration of [`EdgeSnapping`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapConfig {
+    /// Distance in logical pixels at which an edge snaps to a nearby edge
+    pub snap_distance: i32,
+    /// Distance in logical pixels an element has to be moved away from a snapped position to be released
+    ///
+    /// Values smaller than [`SnapConfig::snap_distance`] are treated as equal to it.
+    pub release_distance: i32,
+    /// Snap to the edges of outputs
+    pub output_edges: bool,
+    /// Snap to the edges of other elements
+    pub element_edges: bool,
+    /// Snap to the edges of the area of outputs not covered by layer-shell exclusive zones
+    ///
+    /// Only has an effect with the `wayland_frontend` feature.
+    pub exclusive_zones: bool,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        SnapConfig {
+            snap_distance: 16,
+            release_distance: 32,
+            output_edges: true,
+            element_edges: true,
+            exclusive_zones: true,
+        }
+    }
+}
+
+/// Edge an element can snap to along one axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    // the start (left or top) edge of the element snaps to this coordinate
+    Start(i32),
+    // the end (right or bottom) edge of the element snaps to this coordinate
+    End(i32),
+}
+
+impl Edge {
+    // location of the element along the axis if it snaps to this edge
+    fn location(self, size: i32) -> i32 {
+        match self {
+            Edge::Start(pos) => pos,
+            Edge::End(pos) => pos - size,
+        }
+    }
+}
+
+/// Snapping state of an interactive move
+#[derive(Debug, Clone, Default)]
+pub struct EdgeSnapping {
+    config: SnapConfig,
+    snapped: Point<Option<i32>, Logical>,
+}
+
+impl EdgeSnapping {
+    /// Creates a new snapping helper
+    pub fn new(config: SnapConfig) -> Self {
+        EdgeSnapping {
+            config,
+            snapped: (None, None).into(),
+        }
+    }
+
+    /// Returns the configuration
+    pub fn config(&self) -> &SnapConfig {
+        &self.config
+    }
+
+    /// Changes the configuration
+    pub fn set_config(&mut self, config: SnapConfig) {
+        self.config = config;
+    }
+
+    /// Returns whether the element is currently snapped horizontally or vertically
+    pub fn is_snapped(&self) -> bool {
+        self.snapped.x.is_some() || self.snapped.y.is_some()
+    }
+
+    /// Forgets the snapped positions, should be called once the interactive move ended
+    pub fn reset(&mut self) {
+        self.snapped = (None, None).into();
+    }
+
+    /// Snaps the proposed location of an element being moved inside a space
+    ///
+    /// `location` is the location of the element as it would be passed to [`Space::map_element`].
+    /// Returns the location the element should be mapped at instead.
+    pub fn constrain<E: SpaceElement + PartialEq>(
+        &mut self,
+        space: &Space<E>,
+        element: &E,
+        location: Point<i32, Logical>,
+    ) -> Point<i32, Logical> {
+        let size = element.geometry().size;
+        let geometry = Rectangle::from_loc_and_size(location, size);
+        let (horizontal, vertical) = self.edges(space, element, geometry);
+
+        let release_distance = self.config.release_distance.max(self.config.snap_distance);
+        let x = snap_axis(
+            &mut self.snapped.x,
+            &horizontal,
+            location.x,
+            size.w,
+            self.config.snap_distance,
+            release_distance,
+        );
+        let y = snap_axis(
+            &mut self.snapped.y,
+            &vertical,
+            location.y,
+            size.h,
+            self.config.snap_distance,
+            release_distance,
+        );
+        (x, y).into()
+    }
+
+    fn edges<E: SpaceElement + PartialEq>(
+        &self,
+        space: &Space<E>,
+        element: &E,
+        geometry: Rectangle<i32, Logical>,
+    ) -> (Vec<Edge>, Vec<Edge>) {
+        let mut horizontal = Vec::new();
+        let mut vertical = Vec::new();
+        // consider targets close to the element, not just overlapping ones
+        let distance = self.config.snap_distance;
+        let area = Rectangle::from_loc_and_size(
+            geometry.loc - Point::from((distance, distance)),
+            geometry.size + Size::from((distance * 2, distance * 2)),
+        );
+
+        for output in space.outputs() {
+            let Some(output_geometry) = space.output_geometry(output) else {
+                continue;
+            };
+            if !output_geometry.overlaps(area) {
+                continue;
+            }
+
+            if self.config.output_edges {
+                push_inner_edges(&mut horizontal, &mut vertical, output_geometry);
+            }
+            #[cfg(feature = "wayland_frontend")]
+            if self.config.exclusive_zones {
+                let mut zone = super::layer_map_for_output(output).non_exclusive_zone();
+                zone.loc += output_geometry.loc;
+                if zone != output_geometry {
+                    push_inner_edges(&mut horizontal, &mut vertical, zone);
+                }
+            }
+        }
+
+        if self.config.element_edges {
+            for other in space.elements().filter(|e| *e != element) {
+                let Some(other) = space.element_geometry(other) else {
+                    continue;
+                };
+                if !other.overlaps(area) {
+                    continue;
+                }
+
+                // snap next to the other element, if they are side by side
+                if ranges_overlap(area.loc.y, area.size.h, other.loc.y, other.size.h) {
+                    horizontal.push(Edge::Start(other.loc.x + other.size.w));
+                    horizontal.push(Edge::End(other.loc.x));
+                }
+                if ranges_overlap(area.loc.x, area.size.w, other.loc.x, other.size.w) {
+                    vertical.push(Edge::Start(other.loc.y + other.size.h));
+                    vertical.push(Edge::End(other.loc.y));
+                }
+            }
+        }
+
+        (horizontal, vertical)
+    }
+}
+
+fn push_inner_edges(horizontal: &mut Vec<Edge>, vertical: &mut Vec<Edge>, rect: Rectangle<i32, Logical>) {
+    horizontal.push(Edge::Start(rect.loc.x));
+    horizontal.push(Edge::End(rect.loc.x + rect.size.w));
+    vertical.push(Edge::Start(rect.loc.y));
+    vertical.push(Edge::End(rect.loc.y + rect.size.h));
+}
+
+fn ranges_overlap(a: i32, a_len: i32, b: i32, b_len: i32) -> bool {
+    a < b + b_len && b < a + a_len
+}
+
+fn snap_axis(
+    snapped: &mut Option<i32>,
+    edges: &[Edge],
+    location: i32,
+    size: i32,
+    snap_distance: i32,
+    release_distance: i32,
+) -> i32 {
+    if let Some(pos) = *snapped {
+        if (location - pos).abs() <= release_distance {
+            return pos;
+        }
+        *snapped = None;
+    }
+
+    *snapped = edges
+        .iter()
+        .map(|edge| edge.location(size))
+        .filter(|pos| (location - pos).abs() <= snap_distance)
+        .min_by_key(|pos| (location - pos).abs());
+    snapped.unwrap_or(location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{snap_axis, Edge};
+
+    #[test]
+    fn snap_release_hysteresis() {
+        let edges = [Edge::Start(0), Edge::End(1000)];
+        let mut snapped = None;
+
+        // far away from any edge
+        assert_eq!(snap_axis(&mut snapped, &edges, 500, 100, 10, 30), 500);
+        assert_eq!(snapped, None);
+
+        // the end edge of the element snaps to 1000
+        assert_eq!(snap_axis(&mut snapped, &edges, 895, 100, 10, 30), 900);
+        assert_eq!(snapped, Some(900));
+
+        // moving past the snap distance keeps it snapped
+        assert_eq!(snap_axis(&mut snapped, &edges, 880, 100, 10, 30), 900);
+
+        // until the release distance is exceeded
+        assert_eq!(snap_axis(&mut snapped, &edges, 860, 100, 10, 30), 860);
+        assert_eq!(snapped, None);
+
+        // the start edge snaps to 0
+        assert_eq!(snap_axis(&mut snapped, &edges, -4, 100, 10, 30), 0);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{any::Any, fmt, sync::Arc};
+
+use crate::{
+    backend::renderer::{
+        element::{AsRenderElements, RenderElement},
+        Renderer,
+    },
+    output::Output,
+    utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale},
+};
+
+use super::SpaceElement;
+
+/// Object-safe version of a [`SpaceElement`] that can be rendered with `R`
+///
+/// This trait is implemented for all types implementing [`SpaceElement`] and
+/// [`AsRenderElements<R>`], so it usually does not need to be implemented manually.
+/// It is used by [`DynamicSpaceElement`] to map elements of arbitrary types onto a
+/// [`Space`](crate::desktop::Space).
+pub trait DynSpaceElement<R: Renderer>: SpaceElement + fmt::Debug {
+    /// Returns the render elements of this element as trait objects
+    ///
+    /// See [`AsRenderElements::render_elements`]
+    fn dyn_render_elements(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<Box<dyn RenderElement<R>>>;
+
+    /// Returns this element as [`Any`] to allow downcasting it to its concrete type
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<R, T> DynSpaceElement<R> for T
+where
+    R: Renderer,
+    T: SpaceElement + AsRenderElements<R> + fmt::Debug + 'static,
+    <T as AsRenderElements<R>>::RenderElement: 'static,
+{
+    fn dyn_render_elements(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<Box<dyn RenderElement<R>>> {
+        self.render_elements::<<T as AsRenderElements<R>>::RenderElement>(renderer, location, scale, alpha)
+            .into_iter()
+            .map(|element| Box::new(element) as Box<dyn RenderElement<R>>)
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A type-erased [`SpaceElement`]
+///
+/// Allows mapping elements of types not known at compile time onto the same
+/// [`Space`](crate::desktop::Space), e.g. elements provided by plugins, as an
+/// alternative to a closed enum generated by the [`space_elements!`](crate::space_elements) macro.
+///
+/// Clones of a [`DynamicSpaceElement`] refer to the same element and compare equal,
+/// while two separately created [`DynamicSpaceElement`]s never do.
+pub struct DynamicSpaceElement<R: Renderer>(Arc<dyn DynSpaceElement<R>>);
+
+impl<R: Renderer> DynamicSpaceElement<R> {
+    /// Wraps an element into a [`DynamicSpaceElement`]
+    pub fn new<T: DynSpaceElement<R> + 'static>(element: T) -> Self {
+        DynamicSpaceElement(Arc::new(element))
+    }
+
+    /// Returns a reference to the underlying element, if it is of type `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref::<T>()
+    }
+}
+
+impl<R: Renderer> fmt::Debug for DynamicSpaceElement<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynamicSpaceElement").field(&self.0).finish()
+    }
+}
+
+impl<R: Renderer> Clone for DynamicSpaceElement<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        DynamicSpaceElement(self.0.clone())
+    }
+}
+
+impl<R: Renderer> PartialEq for DynamicSpaceElement<R> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<R: Renderer> Eq for DynamicSpaceElement<R> {}
+
+impl<R: Renderer> IsAlive for DynamicSpaceElement<R> {
+    #[inline]
+    fn alive(&self) -> bool {
+        self.0.alive()
+    }
+}
+
+impl<R: Renderer> SpaceElement for DynamicSpaceElement<R> {
+    fn geometry(&self) -> Rectangle<i32, Logical> {
+        self.0.geometry()
+    }
+    fn bbox(&self) -> Rectangle<i32, Logical> {
+        self.0.bbox()
+    }
+    fn is_in_input_region(&self, point: &Point<f64, Logical>) -> bool {
+        self.0.is_in_input_region(point)
+    }
+    fn z_index(&self) -> u8 {
+        self.0.z_index()
+    }
+
+    fn set_activate(&self, activated: bool) {
+        self.0.set_activate(activated)
+    }
+    fn output_enter(&self, output: &Output, overlap: Rectangle<i32, Logical>) {
+        self.0.output_enter(output, overlap)
+    }
+    fn output_leave(&self, output: &Output) {
+        self.0.output_leave(output)
+    }
+    fn refresh(&self) {
+        self.0.refresh()
+    }
+}
+
+impl<R: Renderer> AsRenderElements<R> for DynamicSpaceElement<R> {
+    type RenderElement = Box<dyn RenderElement<R>>;
+
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        self.0
+            .dyn_render_elements(renderer, location, scale, alpha)
+            .into_iter()
+            .map(C::from)
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "renderer_test"))]
+mod tests {
+    use super::DynamicSpaceElement;
+    use crate::{
+        backend::renderer::{
+            damage::OutputDamageTracker,
+            element::{solid::SolidColorRenderElement, AsRenderElements, Element, Id, Kind},
+            test::DummyRenderer,
+            utils::CommitCounter,
+            Color32F,
+        },
+        desktop::{space::SpaceElement, Space},
+        output::Output,
+        utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+    };
+
+    #[derive(Debug)]
+    struct Square {
+        id: Id,
+        size: Size<i32, Logical>,
+    }
+
+    #[derive(Debug)]
+    struct Bar {
+        id: Id,
+        size: Size<i32, Logical>,
+    }
+
+    macro_rules! test_element {
+        ($ty:ty) => {
+            impl IsAlive for $ty {
+                fn alive(&self) -> bool {
+                    true
+                }
+            }
+
+            impl SpaceElement for $ty {
+                fn bbox(&self) -> Rectangle<i32, Logical> {
+                    Rectangle::from_loc_and_size((0, 0), self.size)
+                }
+                fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+                    true
+                }
+                fn set_activate(&self, _activated: bool) {}
+                fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+                fn output_leave(&self, _output: &Output) {}
+            }
+
+            impl AsRenderElements<DummyRenderer> for $ty {
+                type RenderElement = SolidColorRenderElement;
+
+                fn render_elements<C: From<Self::RenderElement>>(
+                    &self,
+                    _renderer: &mut DummyRenderer,
+                    location: Point<i32, Physical>,
+                    scale: Scale<f64>,
+                    _alpha: f32,
+                ) -> Vec<C> {
+                    let size = self.bbox().size.to_physical_precise_round(scale);
+                    vec![SolidColorRenderElement::new(
+                        self.id.clone(),
+                        Rectangle::from_loc_and_size(location, size),
+                        CommitCounter::default(),
+                        [1.0, 1.0, 1.0, 1.0],
+                        Kind::Unspecified,
+                    )
+                    .into()]
+                }
+            }
+        };
+    }
+
+    test_element!(Square);
+    test_element!(Bar);
+
+    #[test]
+    fn clones_compare_equal() {
+        let element = DynamicSpaceElement::<DummyRenderer>::new(Square {
+            id: Id::new(),
+            size: (10, 10).into(),
+        });
+        let other = DynamicSpaceElement::<DummyRenderer>::new(Square {
+            id: Id::new(),
+            size: (10, 10).into(),
+        });
+        assert_eq!(element, element.clone());
+        assert_ne!(element, other);
+    }
+
+    #[test]
+    fn different_types_share_a_space() {
+        let square_id = Id::new();
+        let square = DynamicSpaceElement::<DummyRenderer>::new(Square {
+            id: square_id.clone(),
+            size: (50, 50).into(),
+        });
+        let bar = DynamicSpaceElement::new(Bar {
+            id: Id::new(),
+            size: (100, 10).into(),
+        });
+
+        let mut space = Space::default();
+        space.map_element(square.clone(), (0, 0), false);
+        space.map_element(bar.clone(), (0, 100), false);
+        assert_eq!(space.elements().count(), 2);
+        assert_eq!(
+            space.element_geometry(&bar),
+            Some(Rectangle::from_loc_and_size((0, 100), (100, 10)))
+        );
+
+        let (under, _) = space.element_under((10.0, 105.0)).unwrap();
+        assert_eq!(under, &bar);
+        assert!(under.downcast_ref::<Bar>().is_some());
+        assert!(under.downcast_ref::<Square>().is_none());
+
+        // render elements are forwarded through the box
+        let mut renderer = DummyRenderer::new();
+        let elements = space.render_elements_for_region(
+            &mut renderer,
+            &Rectangle::from_loc_and_size((0, 0), (200, 200)),
+            2.0,
+            1.0,
+        );
+        assert_eq!(elements.len(), 2);
+        let rendered = elements.iter().find(|e| e.id() == &square_id).unwrap();
+        assert_eq!(
+            rendered.geometry(Scale::from(2.0)),
+            Rectangle::from_loc_and_size((0, 0), (100, 100))
+        );
+        assert_eq!(
+            rendered.opaque_regions(Scale::from(2.0)).to_vec(),
+            vec![Rectangle::from_loc_and_size((0, 0), (100, 100))]
+        );
+
+        let mut damage_tracker = OutputDamageTracker::new((400, 400), 2.0, Transform::Normal);
+        let result = damage_tracker
+            .render_output(&mut renderer, 0, &elements, Color32F::BLACK)
+            .unwrap();
+        assert!(result.states.element_was_presented(square_id));
+    }
+}
@@ -15,8 +15,10 @@ use crate::{
     utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale},
 };
 
+mod dynamic;
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
+pub use self::dynamic::{DynSpaceElement, DynamicSpaceElement};
 #[cfg(feature = "wayland_frontend")]
 pub use self::wayland::SurfaceTree;
 
@@ -50,6 +52,28 @@ impl From<RenderZindex> for Option<u8> {
     }
 }
 
+/// Stacking layer of an element inside a [`Space`](super::Space)
+///
+/// Elements with the same [`SpaceElement::z_index`] are stacked by their layer first
+/// and by their individual stacking order second. Rendering and input hit-testing
+/// both follow the resulting order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StackingLayer {
+    /// Below all normal elements, e.g. desktop widgets
+    Below,
+    /// Default layer for elements
+    #[default]
+    Normal,
+    /// Above all normal elements, e.g. always-on-top windows or docks
+    Above,
+    /// Above all other layers, e.g. sticky picture-in-picture windows
+    ///
+    /// When managed by [`Workspaces`](crate::desktop::Workspaces), elements in this layer
+    /// are shown on every workspace of their output and follow workspace switches.
+    Sticky,
+}
+
 /// Element mappable onto a [`Space`](super::Space)
 pub trait SpaceElement: IsAlive {
     /// Returns the geometry of this element.
@@ -153,6 +177,14 @@ where
         }
     }
 
+    pub(super) fn alpha(&self) -> f32 {
+        match self {
+            #[cfg(feature = "wayland_frontend")]
+            SpaceElements::Layer { .. } => 1.0,
+            SpaceElements::Element(inner) => inner.alpha,
+        }
+    }
+
     pub(super) fn render_location(&self) -> Point<i32, Logical> {
         match self {
             #[cfg(feature = "wayland_frontend")]
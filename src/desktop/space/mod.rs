@@ -4,11 +4,11 @@
 use crate::{
     backend::renderer::{
         damage::{Error as OutputDamageTrackerError, OutputDamageTracker, RenderOutputResult},
-        element::{AsRenderElements, RenderElement, Wrap},
+        element::{utils::CropRenderElement, AsRenderElements, RenderElement, RenderElementStates, Wrap},
         Color32F, Renderer, Texture,
     },
     output::{Output, OutputModeSource, OutputNoMode},
-    utils::{IsAlive, Logical, Point, Rectangle, Scale, Transform},
+    utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Transform},
 };
 #[cfg(feature = "wayland_frontend")]
 use crate::{
@@ -16,13 +16,18 @@ use crate::{
     desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
     wayland::shell::wlr_layer::Layer,
 };
-use std::{collections::HashMap, fmt};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+};
 use tracing::{debug, debug_span, instrument};
 #[cfg(feature = "wayland_frontend")]
 use wayland_server::protocol::wl_surface::WlSurface;
 
 mod element;
 mod output;
+mod session;
 mod utils;
 
 #[cfg(feature = "wayland_frontend")]
@@ -38,9 +43,36 @@ crate::utils::ids::id_gen!(space_id);
 struct InnerElement<E> {
     element: E,
     location: Point<i32, Logical>,
+    layer: StackingLayer,
+    transient_for: Option<E>,
+    alpha: f32,
+    visibility: OutputVisibility,
     outputs: HashMap<Output, Rectangle<i32, Logical>>,
 }
 
+/// Defines on which [`Output`]s a [`SpaceElement`] is visible
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum OutputVisibility {
+    /// Visible on all outputs it overlaps with
+    #[default]
+    All,
+    /// Only visible on the given output
+    Only(Output),
+    /// Hidden on the given outputs
+    Hidden(Vec<Output>),
+}
+
+impl OutputVisibility {
+    /// Returns whether an element with this visibility is visible on the given output
+    pub fn is_visible_on(&self, output: &Output) -> bool {
+        match self {
+            OutputVisibility::All => true,
+            OutputVisibility::Only(o) => o == output,
+            OutputVisibility::Hidden(outputs) => !outputs.contains(output),
+        }
+    }
+}
+
 /// Represents two dimensional plane to map windows and outputs upon.
 ///
 /// Space is generic over the types of elements mapped onto it.
@@ -52,6 +84,8 @@ pub struct Space<E: SpaceElement> {
     pub(super) id: usize,
     // in z-order, back to front
     elements: Vec<InnerElement<E>>,
+    // in order of minimization
+    minimized: Vec<InnerElement<E>>,
     outputs: Vec<Output>,
     span: tracing::Span,
 }
@@ -79,6 +113,7 @@ impl<E: SpaceElement> Default for Space<E> {
         Self {
             id,
             elements: Default::default(),
+            minimized: Default::default(),
             outputs: Default::default(),
             span,
         }
@@ -94,7 +129,9 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     /// Map a [`SpaceElement`] and move it to top of the stack
     ///
     /// This can safely be called on an already mapped window
-    /// to update its location inside the space.
+    /// to update its location inside the space. New elements are
+    /// placed into [`StackingLayer::Normal`], already mapped ones
+    /// keep their layer. Minimized elements are restored.
     ///
     /// If activate is true it will set the new windows state
     /// to be activate and removes that state from every
@@ -104,15 +141,30 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         P: Into<Point<i32, Logical>>,
     {
         #[allow(clippy::mutable_key_type)]
-        let outputs = if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
-            self.elements.remove(pos).outputs
-        } else {
-            HashMap::new()
+        let (layer, transient_for, alpha, visibility, outputs) = match self.take_elem(&element) {
+            Some(inner) => (
+                inner.layer,
+                inner.transient_for,
+                inner.alpha,
+                inner.visibility,
+                inner.outputs,
+            ),
+            None => (
+                StackingLayer::default(),
+                None,
+                1.0,
+                OutputVisibility::default(),
+                HashMap::new(),
+            ),
         };
 
         let inner = InnerElement {
             element,
             location: location.into(),
+            layer,
+            transient_for,
+            alpha,
+            visibility,
             outputs,
         };
         self.insert_elem(inner, activate);
@@ -141,20 +193,290 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         }
 
         self.elements.push(elem);
+        self.sort_elements();
+    }
+
+    /// Moves an already mapped [`SpaceElement`] to the bottom of the stack
+    ///
+    /// The element stays inside its [`StackingLayer`] and transient children
+    /// stay above it. This function does nothing for unmapped windows.
+    pub fn lower_element(&mut self, element: &E) {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let inner = self.elements.remove(pos);
+            self.elements.insert(0, inner);
+            self.sort_elements();
+        }
+    }
+
+    /// Moves an already mapped [`SpaceElement`] directly above `sibling`
+    ///
+    /// Elements can only be restacked inside their own [`StackingLayer`] and
+    /// [`SpaceElement::z_index`], so the element might end up elsewhere, if these differ
+    /// from the sibling. This function does nothing, if either element is unmapped.
+    pub fn restack_above(&mut self, element: &E, sibling: &E) {
+        self.restack(element, sibling, 1);
+    }
+
+    /// Moves an already mapped [`SpaceElement`] directly below `sibling`
+    ///
+    /// See [`Space::restack_above`] for the restrictions that apply.
+    pub fn restack_below(&mut self, element: &E, sibling: &E) {
+        self.restack(element, sibling, 0);
+    }
+
+    fn restack(&mut self, element: &E, sibling: &E, offset: usize) {
+        if element == sibling || !self.elements.iter().any(|inner| &inner.element == sibling) {
+            return;
+        }
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let inner = self.elements.remove(pos);
+            let sibling_pos = self
+                .elements
+                .iter()
+                .position(|inner| &inner.element == sibling)
+                .unwrap();
+            self.elements.insert(sibling_pos + offset, inner);
+            self.sort_elements();
+        }
+    }
+
+    /// Marks a mapped [`SpaceElement`] as transient for another element, e.g. a dialog for its parent window
+    ///
+    /// Transient elements are automatically kept above their parent, as long as
+    /// both share the same [`StackingLayer`] and [`SpaceElement::z_index`].
+    /// Passing `None` removes the relation, which also happens once the parent is unmapped or dies.
+    /// This function does nothing for unmapped elements.
+    pub fn set_transient_for(&mut self, element: &E, parent: Option<E>) {
+        if let Some(inner) = self.elements.iter_mut().find(|inner| &inner.element == element) {
+            inner.transient_for = parent;
+            self.sort_elements();
+        }
+    }
+
+    /// Returns the element a mapped [`SpaceElement`] is transient for, if any
+    pub fn transient_for(&self, element: &E) -> Option<&E> {
         self.elements
-            .sort_by(|e1, e2| e1.element.z_index().cmp(&e2.element.z_index()));
+            .iter()
+            .find(|inner| &inner.element == element)
+            .and_then(|inner| inner.transient_for.as_ref())
+    }
+
+    /// Sets the opacity of a mapped [`SpaceElement`]
+    ///
+    /// The opacity is multiplied with the alpha passed to the rendering functions of
+    /// this space, which can be used to dim inactive windows or to fade elements in and out.
+    /// Values are clamped to the range `0.0..=1.0`. This function does nothing for unmapped elements.
+    pub fn set_element_opacity(&mut self, element: &E, alpha: f32) {
+        if let Some(inner) = self.elements.iter_mut().find(|inner| &inner.element == element) {
+            inner.alpha = alpha.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Returns the opacity of a mapped [`SpaceElement`]
+    pub fn element_opacity(&self, element: &E) -> Option<f32> {
+        self.elements
+            .iter()
+            .find(|inner| &inner.element == element)
+            .map(|inner| inner.alpha)
+    }
+
+    /// Sets the [`OutputVisibility`] of a mapped [`SpaceElement`]
+    ///
+    /// Elements are neither rendered nor receive input on outputs they are hidden on.
+    /// The corresponding output enter and leave events are sent on the next [`Space::refresh`].
+    /// This function does nothing for unmapped elements.
+    pub fn set_element_visibility(&mut self, element: &E, visibility: OutputVisibility) {
+        if let Some(inner) = self.elements.iter_mut().find(|inner| &inner.element == element) {
+            inner.visibility = visibility;
+        }
+    }
+
+    /// Returns the [`OutputVisibility`] of a mapped [`SpaceElement`]
+    pub fn element_visibility(&self, element: &E) -> Option<&OutputVisibility> {
+        self.elements
+            .iter()
+            .find(|inner| &inner.element == element)
+            .map(|inner| &inner.visibility)
+    }
+
+    fn sort_elements(&mut self) {
+        self.elements.sort_by_key(|e| (e.element.z_index(), e.layer));
+
+        // Place transient elements above their parent (and its other transient elements), if they
+        // ended up below it, while otherwise keeping the current order. Relations only apply inside
+        // the same layer and z-index, so every band of those is sorted on its own.
+        let len = self.elements.len();
+        let mut order = Vec::with_capacity(len);
+        let mut start = 0;
+        while start < len {
+            let band = (self.elements[start].element.z_index(), self.elements[start].layer);
+            let end = self.elements[start..]
+                .iter()
+                .position(|e| (e.element.z_index(), e.layer) != band)
+                .map_or(len, |pos| start + pos);
+            let elements = &self.elements[start..end];
+
+            let mut children = vec![Vec::new(); elements.len()];
+            let mut has_parent = vec![false; elements.len()];
+            for (idx, inner) in elements.iter().enumerate() {
+                let parent = inner
+                    .transient_for
+                    .as_ref()
+                    .and_then(|parent| elements.iter().position(|e| &e.element == parent));
+                if let Some(parent) = parent.filter(|parent| *parent != idx) {
+                    children[parent].push(idx);
+                    has_parent[idx] = true;
+                }
+            }
+
+            let mut placed = vec![false; elements.len()];
+            let mut ready = (0..elements.len())
+                .filter(|idx| !has_parent[*idx])
+                .map(Reverse)
+                .collect::<BinaryHeap<_>>();
+            for _ in 0..elements.len() {
+                let idx = match ready.pop() {
+                    Some(Reverse(idx)) => idx,
+                    // only cyclic relations are left, break them up in stacking order
+                    None => (0..elements.len()).find(|idx| !placed[*idx]).unwrap(),
+                };
+                placed[idx] = true;
+                order.push(start + idx);
+                ready.extend(children[idx].iter().copied().filter(|c| !placed[*c]).map(Reverse));
+            }
+
+            start = end;
+        }
+
+        let mut elements = std::mem::take(&mut self.elements)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.elements = order
+            .into_iter()
+            .map(|idx| elements[idx].take().unwrap())
+            .collect();
+    }
+
+    /// Moves a mapped [`SpaceElement`] into another [`StackingLayer`]
+    ///
+    /// The element is placed on top of the elements already in that layer.
+    /// This function does nothing for unmapped elements.
+    pub fn set_element_layer(&mut self, element: &E, layer: StackingLayer) {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let mut inner = self.elements.remove(pos);
+            inner.layer = layer;
+            self.elements.push(inner);
+            self.sort_elements();
+        }
+    }
+
+    /// Returns the [`StackingLayer`] of a mapped [`SpaceElement`]
+    pub fn element_layer(&self, element: &E) -> Option<StackingLayer> {
+        self.elements
+            .iter()
+            .find(|inner| &inner.element == element)
+            .map(|inner| inner.layer)
+    }
+
+    /// Moves all elements in [`StackingLayer::Sticky`] into another space
+    ///
+    /// The elements keep their location, state and stacking order and are placed
+    /// on top of their layer. Used to carry sticky elements along on workspace switches.
+    pub(crate) fn move_sticky_elements(&mut self, to: &mut Space<E>) {
+        let (sticky, elements): (Vec<_>, Vec<_>) = std::mem::take(&mut self.elements)
+            .into_iter()
+            .partition(|inner| inner.layer == StackingLayer::Sticky);
+        self.elements = elements;
+        to.elements.extend(sticky);
+        to.sort_elements();
+
+        let (sticky, minimized): (Vec<_>, Vec<_>) = std::mem::take(&mut self.minimized)
+            .into_iter()
+            .partition(|inner| inner.layer == StackingLayer::Sticky);
+        self.minimized = minimized;
+        to.minimized.extend(sticky);
     }
 
     /// Unmap a [`SpaceElement`] from this space.
     ///
-    /// This function does nothing for already unmapped windows
+    /// This function does nothing for already unmapped windows.
+    /// Minimized elements are forgotten and elements transient for the
+    /// unmapped element lose that relation.
     pub fn unmap_elem(&mut self, element: &E) {
-        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
-            let elem = self.elements.remove(pos);
+        if let Some(elem) = self.take_elem(element) {
             for output in elem.outputs.keys() {
                 elem.element.output_leave(output);
             }
+            for inner in self.elements.iter_mut().chain(self.minimized.iter_mut()) {
+                if inner.transient_for.as_ref() == Some(element) {
+                    inner.transient_for = None;
+                }
+            }
+        }
+    }
+
+    fn take_elem(&mut self, element: &E) -> Option<InnerElement<E>> {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            Some(self.elements.remove(pos))
+        } else if let Some(pos) = self.minimized.iter().position(|inner| &inner.element == element) {
+            Some(self.minimized.remove(pos))
+        } else {
+            None
+        }
+    }
+
+    /// Minimizes a mapped [`SpaceElement`]
+    ///
+    /// The element is removed from the stack, receives output leave events and is deactivated,
+    /// but its location, [`StackingLayer`], opacity and visibility are remembered for
+    /// [`Space::unminimize_element`]. Minimized elements are neither rendered nor receive input.
+    ///
+    /// The element itself stays alive, so it can still be rendered on demand, e.g. by a
+    /// [`WindowThumbnail`](crate::desktop::WindowThumbnail) for a taskbar preview. Compositors should
+    /// keep sending throttled frame callbacks to minimized windows (see the `throttle` argument
+    /// of [`Window::send_frame`](crate::desktop::Window::send_frame)) and may mark them as
+    /// suspended with [`Window::set_suspended`](crate::desktop::Window::set_suspended).
+    ///
+    /// This is usually called from the minimize requests of xdg-shell and the X11 window manager.
+    /// Returns `false` if the element was not mapped.
+    pub fn minimize_element(&mut self, element: &E) -> bool {
+        let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) else {
+            return false;
+        };
+
+        let mut inner = self.elements.remove(pos);
+        for output in inner.outputs.keys() {
+            inner.element.output_leave(output);
         }
+        inner.outputs.clear();
+        inner.element.set_activate(false);
+        self.minimized.push(inner);
+        true
+    }
+
+    /// Restores a minimized [`SpaceElement`] on top of the stack at its previous location
+    ///
+    /// If activate is true the element is activated and all other elements are deactivated.
+    /// Returns `false` if the element was not minimized.
+    pub fn unminimize_element(&mut self, element: &E, activate: bool) -> bool {
+        let Some(pos) = self.minimized.iter().position(|inner| &inner.element == element) else {
+            return false;
+        };
+
+        let inner = self.minimized.remove(pos);
+        self.insert_elem(inner, activate);
+        true
+    }
+
+    /// Returns whether the [`SpaceElement`] is minimized
+    pub fn is_minimized(&self, element: &E) -> bool {
+        self.minimized.iter().any(|inner| &inner.element == element)
+    }
+
+    /// Iterate minimized elements in the order they were minimized
+    pub fn minimized_elements(&self) -> impl DoubleEndedIterator<Item = &E> + ExactSizeIterator {
+        self.minimized.iter().map(|e| &e.element)
     }
 
     /// Iterate elements in z-order back to front
@@ -188,6 +510,10 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             .iter()
             .rev()
             .filter(|e| e.bbox().to_f64().contains(point))
+            .filter(|e| {
+                e.visibility == OutputVisibility::All
+                    || self.output_under(point).any(|o| e.visibility.is_visible_on(o))
+            })
             .find_map(|e| {
                 // we need to offset the point to the location where the surface is actually drawn
                 let render_location = e.render_location();
@@ -320,6 +646,25 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             .collect()
     }
 
+    /// Returns the output with the highest scale a [`SpaceElement`] overlaps with.
+    ///
+    /// Elements spanning multiple outputs with different (fractional) scales should be rendered
+    /// at the scale of this output by their clients, so they stay sharp on all outputs they are
+    /// displayed on. The rendering of outputs with a lower scale then downscales their buffers.
+    pub fn max_scale_output_for_element(&self, elem: &E) -> Option<Output> {
+        self.elements
+            .iter()
+            .find(|e| &e.element == elem)?
+            .outputs
+            .keys()
+            .max_by(|a, b| {
+                a.current_scale()
+                    .fractional_scale()
+                    .total_cmp(&b.current_scale().fractional_scale())
+            })
+            .cloned()
+    }
+
     /// Refresh some internal values and update client state,
     /// meaning this will handle output enter and leave events
     /// for mapped outputs and windows based on their position.
@@ -329,6 +674,12 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     #[profiling::function]
     pub fn refresh(&mut self) {
         self.elements.retain(|e| e.alive());
+        self.minimized.retain(|e| e.alive());
+        for inner in self.elements.iter_mut().chain(self.minimized.iter_mut()) {
+            if inner.transient_for.as_ref().is_some_and(|parent| !parent.alive()) {
+                inner.transient_for = None;
+            }
+        }
 
         let outputs = self
             .outputs
@@ -346,7 +697,12 @@ impl<E: SpaceElement + PartialEq> Space<E> {
 
             for (output, output_geometry) in &outputs {
                 // Check if the bounding box of the toplevel intersects with the output
-                if let Some(mut overlap) = output_geometry.intersection(bbox) {
+                let overlap = e
+                    .visibility
+                    .is_visible_on(output)
+                    .then(|| output_geometry.intersection(bbox))
+                    .flatten();
+                if let Some(mut overlap) = overlap {
                     // output_enter expects the overlap to be relative to the element
                     overlap.loc -= bbox.loc;
                     let old = e.outputs.insert(output.clone(), overlap);
@@ -378,6 +734,7 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     /// *Note:* Because this is not rendering a specific output,
     /// this will not contain layer surfaces.
     /// Use [`Space::render_elements_for_output`], if you care about this.
+    /// For the same reason the [`OutputVisibility`] of the elements is not considered.
     #[instrument(level = "trace", skip(self, renderer, scale), parent = &self.span)]
     #[profiling::function]
     pub fn render_elements_for_region<'a, R: Renderer, S: Into<Scale<f64>>>(
@@ -392,29 +749,54 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         E: AsRenderElements<R>,
         <E as AsRenderElements<R>>::RenderElement: 'a,
     {
-        let scale = scale.into();
+        self.render_elements_for_region_on(renderer, region, scale.into(), alpha, None)
+            .into_iter()
+            .map(|(elem, _)| elem)
+            .collect()
+    }
 
+    // Also returns whether the element producing a render element extends outside the region
+    fn render_elements_for_region_on<'a, R: Renderer>(
+        &'a self,
+        renderer: &mut R,
+        region: &Rectangle<i32, Logical>,
+        scale: Scale<f64>,
+        alpha: f32,
+        output: Option<&Output>,
+    ) -> Vec<(<E as AsRenderElements<R>>::RenderElement, bool)>
+    where
+        <R as Renderer>::TextureId: Texture + 'static,
+        E: AsRenderElements<R>,
+        <E as AsRenderElements<R>>::RenderElement: 'a,
+    {
         self.elements
             .iter()
             .rev()
+            .filter(|e| output.map(|o| e.visibility.is_visible_on(o)).unwrap_or(true))
             .filter(|e| {
                 let geometry = e.bbox();
                 region.overlaps(geometry)
             })
             .flat_map(|e| {
                 let location = e.render_location() - region.loc;
+                let spanning = !region.contains_rect(e.bbox());
                 e.element
                     .render_elements::<<E as AsRenderElements<R>>::RenderElement>(
                         renderer,
                         location.to_physical_precise_round(scale),
                         scale,
-                        alpha,
+                        alpha * e.alpha,
                     )
+                    .into_iter()
+                    .map(move |elem| (elem, spanning))
             })
             .collect::<Vec<_>>()
     }
 
     /// Retrieve the render elements for an output
+    ///
+    /// Elements spanning multiple outputs are cropped to the output, so their damage
+    /// and opaque regions stay within the output's coordinate space.
     #[instrument(level = "trace", skip(self, renderer), parent = &self.span)]
     #[profiling::function]
     pub fn render_elements_for_output<
@@ -442,8 +824,13 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         // The unwrap is safe or we would have returned OutputError::Unmapped already
         let output_geo = self.output_geometry(output).unwrap();
 
-        let mut space_elements: Vec<SpaceElements<'a, E>> =
-            self.elements.iter().rev().map(SpaceElements::Element).collect();
+        let mut space_elements: Vec<SpaceElements<'a, E>> = self
+            .elements
+            .iter()
+            .rev()
+            .filter(|e| e.visibility.is_visible_on(output))
+            .map(SpaceElements::Element)
+            .collect();
 
         #[cfg(feature = "wayland_frontend")]
         {
@@ -456,6 +843,8 @@ impl<E: SpaceElement + PartialEq> Space<E> {
 
         space_elements.sort_by_key(|e| std::cmp::Reverse(e.z_index()));
 
+        let output_rect =
+            Rectangle::from_loc_and_size((0, 0), output_geo.size.to_physical_precise_round(output_scale));
         Ok(space_elements
             .into_iter()
             .filter(|e| {
@@ -464,12 +853,21 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             })
             .flat_map(|e| {
                 let location = e.render_location() - output_geo.loc;
+                let spanning = !output_geo.contains_rect(e.bbox());
                 e.render_elements::<SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>>(
                     renderer,
                     location.to_physical_precise_round(output_scale),
                     Scale::from(output_scale),
-                    alpha,
+                    alpha * e.alpha(),
                 )
+                .into_iter()
+                .filter_map(move |elem| match elem {
+                    SpaceRenderElements::Element(elem) if spanning => {
+                        CropRenderElement::from_element(elem, output_scale, output_rect)
+                            .map(SpaceRenderElements::CroppedElement)
+                    }
+                    elem => Some(elem),
+                })
             })
             .collect::<Vec<_>>())
     }
@@ -525,6 +923,8 @@ crate::backend::renderer::element::render_elements! {
     Surface=WaylandSurfaceRenderElement<R>,
     /// A single texture
     Element=Wrap<E>,
+    /// A single texture cropped to the output it is rendered on
+    CroppedElement=CropRenderElement<Wrap<E>>,
 }
 #[cfg(not(feature = "wayland_frontend"))]
 crate::backend::renderer::element::render_elements! {
@@ -535,6 +935,8 @@ crate::backend::renderer::element::render_elements! {
     pub SpaceRenderElements<R, E>;
     /// A single texture
     Element=Wrap<E>,
+    /// A single texture cropped to the output it is rendered on
+    CroppedElement=CropRenderElement<Wrap<E>>,
 }
 
 impl<
@@ -548,6 +950,7 @@ impl<
             #[cfg(feature = "wayland_frontend")]
             Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
             Self::Element(arg0) => f.debug_tuple("Element").field(arg0).finish(),
+            Self::CroppedElement(arg0) => f.debug_tuple("CroppedElement").field(arg0).finish(),
             Self::_GenericCatcher(_) => unreachable!(),
         }
     }
@@ -572,6 +975,8 @@ crate::backend::renderer::element::render_elements! {
 /// If multiple spaces are given their elements will be stacked
 /// the same way.
 ///
+/// Elements spanning multiple outputs are cropped to the given output.
+///
 /// *Note*: If the `wayland_frontend`-feature is enabled
 /// this will include layer-shell surfaces added to this
 /// outputs [`LayerMap`](crate::desktop::LayerMap).
@@ -630,11 +1035,27 @@ where
     for space in spaces {
         let _guard = space.span.enter();
         if let Some(output_geo) = space.output_geometry(output) {
+            // elements spanning multiple outputs are cropped to this output
+            let output_rect =
+                Rectangle::from_loc_and_size((0, 0), output_geo.size.to_physical_precise_round(output_scale));
             render_elements.extend(
                 space
-                    .render_elements_for_region(renderer, &output_geo, output_scale, alpha)
+                    .render_elements_for_region_on(
+                        renderer,
+                        &output_geo,
+                        Scale::from(output_scale),
+                        alpha,
+                        Some(output),
+                    )
                     .into_iter()
-                    .map(|e| SpaceRenderElements::Element(Wrap::from(e))),
+                    .filter_map(|(e, spanning)| {
+                        if spanning {
+                            CropRenderElement::from_element(Wrap::from(e), output_scale, output_rect)
+                                .map(SpaceRenderElements::CroppedElement)
+                        } else {
+                            Some(SpaceRenderElements::Element(Wrap::from(e)))
+                        }
+                    }),
             );
         }
     }
@@ -704,3 +1125,331 @@ where
 
     damage_tracker.render_output(renderer, age, &render_elements, clear_color)
 }
+
+/// Damage a output without rendering it
+///
+/// Accumulates the damage of the output since the frame `age` frames ago, in output coordinates,
+/// as [`render_output`] would have rendered it. This allows compositors using custom renderers
+/// or forwarding frames to remote displays to reuse the damage tracking of smithay.
+///
+/// An `age` of `0` always returns the full output as damaged.
+///
+/// *Note*: The state of the `damage_tracker` advances with every call, it should not be shared with
+/// a damage tracker used for rendering the same output.
+#[allow(clippy::too_many_arguments)]
+#[profiling::function]
+pub fn damage_output<
+    'a,
+    'd,
+    #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+    #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+    C: RenderElement<R>,
+    E: SpaceElement + PartialEq + AsRenderElements<R> + 'a,
+    S: IntoIterator<Item = &'a Space<E>>,
+>(
+    output: &Output,
+    renderer: &mut R,
+    alpha: f32,
+    age: usize,
+    spaces: S,
+    custom_elements: &'a [C],
+    damage_tracker: &'d mut OutputDamageTracker,
+) -> Result<(Option<&'d Vec<Rectangle<i32, Physical>>>, RenderElementStates), OutputNoMode>
+where
+    <R as Renderer>::TextureId: Clone + Texture + 'static,
+    <E as AsRenderElements<R>>::RenderElement: 'a,
+    SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>:
+        From<Wrap<<E as AsRenderElements<R>>::RenderElement>>,
+{
+    if let OutputModeSource::Auto(renderer_output) = damage_tracker.mode() {
+        assert!(renderer_output == output);
+    }
+
+    let space_render_elements = space_render_elements(renderer, spaces, output, alpha)?;
+
+    let mut render_elements: Vec<OutputRenderElements<'a, R, <E as AsRenderElements<R>>::RenderElement, C>> =
+        Vec::with_capacity(custom_elements.len() + space_render_elements.len());
+
+    render_elements.extend(custom_elements.iter().map(OutputRenderElements::Custom));
+    render_elements.extend(space_render_elements.into_iter().map(OutputRenderElements::Space));
+
+    damage_tracker.damage_output(age, &render_elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Space, SpaceElement, StackingLayer};
+    #[cfg(feature = "renderer_test")]
+    use crate::{
+        backend::renderer::{
+            element::{solid::SolidColorRenderElement, AsRenderElements, Element, Id, Kind},
+            test::DummyRenderer,
+            utils::CommitCounter,
+        },
+        utils::{Physical, Scale},
+    };
+    use crate::{
+        output::{Mode, Output, PhysicalProperties, Scale as OutputScale, Subpixel},
+        utils::{IsAlive, Logical, Point, Rectangle},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestElement(u32);
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (100, 100))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    #[cfg(feature = "renderer_test")]
+    impl AsRenderElements<DummyRenderer> for TestElement {
+        type RenderElement = SolidColorRenderElement;
+
+        fn render_elements<C: From<Self::RenderElement>>(
+            &self,
+            _renderer: &mut DummyRenderer,
+            location: Point<i32, Physical>,
+            scale: Scale<f64>,
+            _alpha: f32,
+        ) -> Vec<C> {
+            let size = self.bbox().size.to_physical_precise_round(scale);
+            vec![SolidColorRenderElement::new(
+                Id::new(),
+                Rectangle::from_loc_and_size(location, size),
+                CommitCounter::default(),
+                [1.0, 1.0, 1.0, 1.0],
+                Kind::Unspecified,
+            )
+            .into()]
+        }
+    }
+
+    fn output(name: &str, scale: f64) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: (1500, 1500).into(),
+                refresh: 60_000,
+            }),
+            None,
+            Some(OutputScale::Fractional(scale)),
+            None,
+        );
+        output
+    }
+
+    fn stack(space: &Space<TestElement>) -> Vec<u32> {
+        space.elements().map(|e| e.0).collect()
+    }
+
+    #[test]
+    fn layers_and_restacking() {
+        let mut space = Space::default();
+        for i in 0..4 {
+            space.map_element(TestElement(i), (0, 0), false);
+        }
+        assert_eq!(stack(&space), [0, 1, 2, 3]);
+
+        space.set_element_layer(&TestElement(0), StackingLayer::Above);
+        space.set_element_layer(&TestElement(3), StackingLayer::Below);
+        assert_eq!(stack(&space), [3, 1, 2, 0]);
+
+        // raising does not leave the layer
+        space.raise_element(&TestElement(1), false);
+        assert_eq!(stack(&space), [3, 2, 1, 0]);
+        assert_eq!(space.element_under((50.0, 50.0)).map(|(e, _)| e.0), Some(0));
+
+        space.restack_below(&TestElement(1), &TestElement(2));
+        assert_eq!(stack(&space), [3, 1, 2, 0]);
+        space.lower_element(&TestElement(2));
+        assert_eq!(stack(&space), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn transients_stay_above_parent() {
+        let mut space = Space::default();
+        for i in 0..4 {
+            space.map_element(TestElement(i), (0, 0), false);
+        }
+        space.set_transient_for(&TestElement(1), Some(TestElement(0)));
+        space.set_transient_for(&TestElement(2), Some(TestElement(0)));
+        assert_eq!(stack(&space), [0, 1, 2, 3]);
+
+        space.raise_element(&TestElement(0), false);
+        assert_eq!(stack(&space), [3, 0, 1, 2]);
+
+        space.restack_above(&TestElement(3), &TestElement(2));
+        space.lower_element(&TestElement(0));
+        assert_eq!(stack(&space), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn nested_transients_stay_together() {
+        let mut space = Space::default();
+        for i in 0..5 {
+            space.map_element(TestElement(i), (0, 0), false);
+        }
+        space.set_transient_for(&TestElement(1), Some(TestElement(0)));
+        space.set_transient_for(&TestElement(2), Some(TestElement(0)));
+        space.set_transient_for(&TestElement(3), Some(TestElement(1)));
+        assert_eq!(stack(&space), [0, 1, 2, 3, 4]);
+
+        space.raise_element(&TestElement(0), false);
+        assert_eq!(stack(&space), [4, 0, 1, 2, 3]);
+        space.raise_element(&TestElement(1), false);
+        assert_eq!(stack(&space), [4, 0, 2, 1, 3]);
+        space.lower_element(&TestElement(1));
+        assert_eq!(stack(&space), [4, 0, 1, 2, 3]);
+
+        // cyclic relations do not lose elements
+        space.set_transient_for(&TestElement(0), Some(TestElement(3)));
+        assert_eq!(stack(&space).len(), 5);
+    }
+
+    #[test]
+    fn unmapping_parent_clears_transients() {
+        let mut space = Space::default();
+        for i in 0..3 {
+            space.map_element(TestElement(i), (0, 0), false);
+        }
+        space.set_transient_for(&TestElement(1), Some(TestElement(0)));
+        space.minimize_element(&TestElement(1));
+        space.set_transient_for(&TestElement(2), Some(TestElement(0)));
+
+        space.unmap_elem(&TestElement(0));
+        assert_eq!(space.transient_for(&TestElement(2)), None);
+        space.unminimize_element(&TestElement(1), false);
+        assert_eq!(space.transient_for(&TestElement(1)), None);
+
+        // a remapped parent does not pick up its old transients
+        space.map_element(TestElement(0), (0, 0), false);
+        assert_eq!(stack(&space), [2, 1, 0]);
+    }
+
+    #[test]
+    fn minimize_restores_state() {
+        let mut space = Space::default();
+        for i in 0..3 {
+            space.map_element(TestElement(i), (i as i32 * 10, 0), false);
+        }
+        space.set_element_layer(&TestElement(0), StackingLayer::Above);
+        assert!(space.minimize_element(&TestElement(0)));
+        assert!(!space.minimize_element(&TestElement(0)));
+        assert_eq!(stack(&space), [1, 2]);
+        assert!(space.is_minimized(&TestElement(0)));
+        assert_eq!(space.element_under((5.0, 5.0)).map(|(e, _)| e.0), None);
+
+        assert!(space.unminimize_element(&TestElement(0), false));
+        assert_eq!(stack(&space), [1, 2, 0]);
+        assert_eq!(space.element_location(&TestElement(0)), Some((0, 0).into()));
+        assert_eq!(space.element_layer(&TestElement(0)), Some(StackingLayer::Above));
+
+        space.minimize_element(&TestElement(1));
+        space.unmap_elem(&TestElement(1));
+        assert_eq!(space.minimized_elements().count(), 0);
+    }
+
+    #[test]
+    fn layout_restore_keeps_stacking() {
+        let mut space = Space::default();
+        for i in 0..3 {
+            space.map_element(TestElement(i), (i as i32 * 10, 0), false);
+        }
+        space.set_element_opacity(&TestElement(0), 0.5);
+        space.minimize_element(&TestElement(2));
+        let layout = space.layout(|e| Some(e.0));
+        assert_eq!(layout.elements.len(), 3);
+
+        let mut restored = Space::default();
+        let id = |e: &TestElement| Some(e.0);
+        assert!(restored
+            .restore_element(&layout, TestElement(1), id, false)
+            .is_ok());
+        assert!(restored
+            .restore_element(&layout, TestElement(0), id, false)
+            .is_ok());
+        assert!(restored
+            .restore_element(&layout, TestElement(2), id, false)
+            .is_ok());
+        assert!(restored
+            .restore_element(&layout, TestElement(7), id, false)
+            .is_err());
+
+        assert_eq!(stack(&restored), [0, 1]);
+        assert!(restored.is_minimized(&TestElement(2)));
+        assert_eq!(restored.element_location(&TestElement(1)), Some((10, 0).into()));
+        assert_eq!(restored.element_opacity(&TestElement(0)), Some(0.5));
+    }
+
+    #[test]
+    fn spanning_element_prefers_highest_scale() {
+        let low = output("low", 1.0);
+        let high = output("high", 1.5);
+
+        let mut space = Space::default();
+        space.map_output(&low, (0, 0));
+        space.map_output(&high, (1500, 0));
+        space.map_element(TestElement(0), (1450, 0), false);
+        space.refresh();
+        assert_eq!(space.max_scale_output_for_element(&TestElement(0)), Some(high));
+
+        space.map_element(TestElement(0), (0, 0), false);
+        space.refresh();
+        assert_eq!(space.max_scale_output_for_element(&TestElement(0)), Some(low));
+    }
+
+    #[cfg(feature = "renderer_test")]
+    #[test]
+    fn spanning_elements_are_cropped_per_output() {
+        let left = output("left", 1.0);
+        let right = output("right", 1.5);
+
+        let mut space = Space::default();
+        space.map_output(&left, (0, 0));
+        space.map_output(&right, (1500, 0));
+        space.map_element(TestElement(0), (1450, 0), false);
+        space.map_element(TestElement(1), (100, 100), false);
+        space.refresh();
+
+        let mut renderer = DummyRenderer::default();
+        let mut geometries = |output: &Output| {
+            super::space_render_elements::<_, TestElement, _>(&mut renderer, [&space], output, 1.0)
+                .unwrap()
+                .iter()
+                .map(|e| e.geometry(output.current_scale().fractional_scale().into()))
+                .collect::<Vec<_>>()
+        };
+        // only the spanning element is cropped, to the part visible on each output
+        assert_eq!(
+            geometries(&left),
+            [
+                Rectangle::from_loc_and_size((100, 100), (100, 100)),
+                Rectangle::from_loc_and_size((1450, 0), (50, 100)),
+            ]
+        );
+        assert_eq!(
+            geometries(&right),
+            [Rectangle::from_loc_and_size((0, 0), (75, 150))]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    desktop::session::{ElementLayout, OutputPlacement, SpaceLayout},
+    utils::{Logical, Rectangle},
+};
+
+use super::{InnerElement, Space, SpaceElement};
+
+impl<E: SpaceElement + PartialEq> Space<E> {
+    /// Captures the layout of this space
+    ///
+    /// `id` returns the stable identifier of an element, elements without one are skipped.
+    /// See [`session`](crate::desktop::session) for more information.
+    pub fn layout<K, F>(&self, mut id: F) -> SpaceLayout<K>
+    where
+        F: FnMut(&E) -> Option<K>,
+    {
+        let elements = self
+            .elements
+            .iter()
+            .map(|inner| (inner, false))
+            .chain(self.minimized.iter().map(|inner| (inner, true)))
+            .filter_map(|(inner, minimized)| {
+                let id = id(&inner.element)?;
+                let geometry = inner.geometry();
+                Some(ElementLayout {
+                    id,
+                    geometry,
+                    output: self.output_placement(inner),
+                    layer: inner.layer,
+                    opacity: inner.alpha,
+                    minimized,
+                })
+            })
+            .collect();
+        SpaceLayout { elements }
+    }
+
+    /// Maps an element into its saved slot
+    ///
+    /// The slot is found by the identifier returned by `id` for the element. The element is placed at its
+    /// saved location, relative to its saved output if an output with the same name is mapped, and restacked
+    /// below any element already restored from a slot above it. Its stacking layer, opacity and minimized
+    /// state are restored as well.
+    ///
+    /// Returns the saved slot, e.g. to configure the saved size, or the element if there is no slot for it.
+    pub fn restore_element<'l, K, F>(
+        &mut self,
+        layout: &'l SpaceLayout<K>,
+        element: E,
+        mut id: F,
+        activate: bool,
+    ) -> Result<&'l ElementLayout<K>, E>
+    where
+        K: PartialEq,
+        E: Clone,
+        F: FnMut(&E) -> Option<K>,
+    {
+        let Some(index) = id(&element).and_then(|key| layout.position(&key)) else {
+            return Err(element);
+        };
+        let slot = &layout.elements[index];
+
+        let location = slot
+            .output
+            .as_ref()
+            .and_then(|placement| {
+                self.outputs
+                    .iter()
+                    .find(|o| o.name() == placement.name)
+                    .and_then(|o| self.output_geometry(o))
+                    .map(|geo| geo.loc + placement.offset)
+            })
+            .unwrap_or(slot.geometry.loc);
+        self.map_element(element.clone(), location, activate && !slot.minimized);
+        self.set_element_layer(&element, slot.layer);
+        self.set_element_opacity(&element, slot.opacity);
+
+        // the element was mapped on top, move it below the lowest element saved above it
+        let sibling = self
+            .elements
+            .iter()
+            .map(|inner| &inner.element)
+            .filter(|e| **e != element)
+            .find(|e| {
+                id(e)
+                    .and_then(|key| layout.position(&key))
+                    .map(|pos| pos > index)
+                    .unwrap_or(false)
+            })
+            .cloned();
+        if let Some(sibling) = sibling {
+            self.restack_below(&element, &sibling);
+        }
+
+        if slot.minimized {
+            self.minimize_element(&element);
+        }
+
+        Ok(slot)
+    }
+
+    fn output_placement(&self, inner: &InnerElement<E>) -> Option<OutputPlacement> {
+        let geometry = inner.geometry();
+        self.outputs
+            .iter()
+            .filter_map(|output| {
+                let output_geometry = self.output_geometry(output)?;
+                let overlap = output_geometry.intersection(geometry)?;
+                Some((output, output_geometry, area(overlap)))
+            })
+            .max_by_key(|(_, _, area)| *area)
+            .map(|(output, output_geometry, _)| OutputPlacement {
+                name: output.name(),
+                offset: geometry.loc - output_geometry.loc,
+            })
+    }
+}
+
+fn area(rect: Rectangle<i32, Logical>) -> i64 {
+    rect.size.w as i64 * rect.size.h as i64
+}
@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+
+use wayland_server::protocol::wl_surface::WlSurface;
+
+use crate::{
+    backend::renderer::{
+        element::{surface::WaylandSurfaceRenderElement, AsRenderElements},
+        ImportAll, Renderer,
+    },
+    desktop::{space::SpaceElement, WindowGroup},
+    output::Output,
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+    wayland::seat::WaylandFocus,
+};
+
+impl SpaceElement for WindowGroup {
+    fn geometry(&self) -> Rectangle<i32, Logical> {
+        let Some(active) = self.active() else {
+            return Rectangle::default();
+        };
+        let bar_height = self.bar_height();
+        let mut geometry = SpaceElement::geometry(&active);
+        geometry.loc.y -= bar_height;
+        geometry.size.h += bar_height;
+        geometry
+    }
+
+    fn bbox(&self) -> Rectangle<i32, Logical> {
+        match self.active() {
+            Some(active) => SpaceElement::bbox(&active).merge(SpaceElement::geometry(self)),
+            None => Rectangle::default(),
+        }
+    }
+
+    fn is_in_input_region(&self, point: &Point<f64, Logical>) -> bool {
+        let Some(active) = self.active() else {
+            return false;
+        };
+        let mut bar = SpaceElement::geometry(self);
+        bar.size.h = self.bar_height();
+        bar.to_f64().contains(*point) || active.is_in_input_region(point)
+    }
+
+    fn z_index(&self) -> u8 {
+        self.active()
+            .map(|active| SpaceElement::z_index(&active))
+            .unwrap_or_default()
+    }
+
+    fn set_activate(&self, activated: bool) {
+        self.set_activated(activated);
+    }
+
+    fn output_enter(&self, output: &Output, overlap: Rectangle<i32, Logical>) {
+        WindowGroup::output_enter(self, output, overlap);
+    }
+
+    fn output_leave(&self, output: &Output) {
+        WindowGroup::output_leave(self, output);
+    }
+
+    fn refresh(&self) {
+        self.cleanup();
+        if let Some(active) = self.active() {
+            SpaceElement::refresh(&active);
+        }
+    }
+}
+
+impl<R> AsRenderElements<R> for WindowGroup
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: Clone + 'static,
+{
+    type RenderElement = WaylandSurfaceRenderElement<R>;
+
+    fn render_elements<C: From<WaylandSurfaceRenderElement<R>>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        // the group geometry is offset by the tab bar, so the active window
+        // ends up below it when rendered at the same location
+        match self.active() {
+            Some(active) => active.render_elements(renderer, location, scale, alpha),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl WaylandFocus for WindowGroup {
+    fn wl_surface(&self) -> Option<Cow<'_, WlSurface>> {
+        self.active().and_then(|active| {
+            active
+                .wl_surface()
+                .map(|surface| Cow::Owned(surface.into_owned()))
+        })
+    }
+}
@@ -4,13 +4,29 @@ use tracing::instrument;
 use wayland_server::protocol::wl_surface::WlSurface;
 
 use crate::{
-    backend::renderer::utils::RendererSurfaceStateUserData,
+    backend::renderer::{element::RenderElementStates, utils::RendererSurfaceStateUserData},
+    desktop::{
+        layer_map_for_output,
+        utils::{
+            surface_presentation_feedback_flags_from_states, surface_primary_scanout_output,
+            take_presentation_feedback_surface_tree, OutputPresentationFeedback,
+        },
+        PopupManager, Space,
+    },
     output::{Output, WeakOutput},
     utils::{Logical, Point, Rectangle},
-    wayland::compositor::{with_surface_tree_downward, TraversalAction},
+    wayland::{
+        compositor::{send_surface_state, with_surface_tree_downward, TraversalAction},
+        fractional_scale::with_fractional_scale,
+        seat::WaylandFocus,
+    },
 };
 
+use super::SpaceElement;
+
+mod group;
 mod layer;
+mod popup;
 mod window;
 #[cfg(feature = "xwayland")]
 mod x11;
@@ -80,6 +96,107 @@ pub fn output_update(output: &Output, output_overlap: Option<Rectangle<i32, Logi
     );
 }
 
+impl<E: SpaceElement + PartialEq + WaylandFocus> Space<E> {
+    /// Collects the presentation feedback of all surfaces presented on an output
+    ///
+    /// This walks all elements mapped on the output including their popups, as well as
+    /// the layer surfaces of the output, and takes the pending feedback of every surface
+    /// whose [primary scan-out output](surface_primary_scanout_output) is the given output.
+    /// Surfaces presented using zero-copy according to `render_element_states` are flagged accordingly.
+    ///
+    /// The returned [`OutputPresentationFeedback`] should be marked as
+    /// [presented](OutputPresentationFeedback::presented) or [discarded](OutputPresentationFeedback::discarded)
+    /// once the backend reports the outcome of the frame.
+    ///
+    /// Note: This requires the primary scan-out output of the surfaces to be kept up-to-date with
+    /// [`update_surface_primary_scanout_output`](crate::desktop::utils::update_surface_primary_scanout_output).
+    #[profiling::function]
+    pub fn take_presentation_feedback(
+        &self,
+        output: &Output,
+        render_element_states: &RenderElementStates,
+    ) -> OutputPresentationFeedback {
+        let mut output_feedback = OutputPresentationFeedback::new(output);
+        let flags = |surface: &WlSurface, _: &_| {
+            surface_presentation_feedback_flags_from_states(surface, render_element_states)
+        };
+
+        for element in self.elements_for_output(output) {
+            let Some(surface) = element.wl_surface() else {
+                continue;
+            };
+            take_presentation_feedback_surface_tree(
+                &surface,
+                &mut output_feedback,
+                surface_primary_scanout_output,
+                flags,
+            );
+            for (popup, _) in PopupManager::popups_for_surface(&surface) {
+                take_presentation_feedback_surface_tree(
+                    popup.wl_surface(),
+                    &mut output_feedback,
+                    surface_primary_scanout_output,
+                    flags,
+                );
+            }
+        }
+
+        let map = layer_map_for_output(output);
+        for layer_surface in map.layers() {
+            layer_surface.take_presentation_feedback(
+                &mut output_feedback,
+                surface_primary_scanout_output,
+                flags,
+            );
+        }
+
+        output_feedback
+    }
+
+    /// Sends the preferred scale to the surfaces of all elements
+    ///
+    /// Every surface of an element, including its popups, gets the scale of the output with the
+    /// highest scale the element overlaps with (see [`Space::max_scale_output_for_element`]),
+    /// so elements spanning outputs with different scales stay sharp on all of them.
+    /// The scale is sent using `wp_fractional_scale_v1.preferred_scale`, if the client bound it,
+    /// and as `wl_surface.preferred_buffer_scale` together with the transform of that output.
+    ///
+    /// Elements not overlapping any output are left untouched. This should be called after
+    /// [`Space::refresh`], which updates the outputs elements overlap with.
+    #[profiling::function]
+    pub fn send_preferred_scales(&self) {
+        for element in self.elements() {
+            let Some(surface) = element.wl_surface() else {
+                continue;
+            };
+            let Some(output) = self.max_scale_output_for_element(element) else {
+                continue;
+            };
+            let scale = output.current_scale();
+            let transform = output.current_transform();
+
+            let send = |surface: &WlSurface| {
+                with_surface_tree_downward(
+                    surface,
+                    (),
+                    |_, _, _| TraversalAction::DoChildren(()),
+                    |surface, states, _| {
+                        with_fractional_scale(states, |fractional_scale| {
+                            fractional_scale.set_preferred_scale(scale.fractional_scale());
+                        });
+                        send_surface_state(surface, states, scale.integer_scale(), transform);
+                    },
+                    |_, _, _| true,
+                );
+            };
+            send(&surface);
+            for (popup, _) in PopupManager::popups_for_surface(&surface) {
+                send(popup.wl_surface());
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct WindowOutputState {
     output_overlap: HashMap<WeakOutput, Rectangle<i32, Logical>>,
@@ -0,0 +1,65 @@
+use wayland_server::protocol::wl_surface::WlSurface;
+
+use crate::{
+    backend::renderer::{
+        element::{
+            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            Kind,
+        },
+        ImportAll, Renderer,
+    },
+    desktop::{utils::bbox_from_surface_tree, PopupManager},
+    utils::{Logical, Point, Rectangle, Scale},
+};
+
+impl PopupManager {
+    /// Returns the render elements of all popups visible on an output
+    ///
+    /// This is useful for compositors not using [`Space`](crate::desktop::Space) for rendering,
+    /// or drawing popups on a separate layer above their windows.
+    ///
+    /// - `roots` are the toplevel surfaces owning the popups, together with the location of their
+    ///   window geometry in global compositor space, in front-to-back order.
+    /// - `output_geometry` is the geometry of the output in global compositor space.
+    ///
+    /// The elements are returned in front-to-back order. Their damage is tracked per surface commit,
+    /// so passing them to an [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker)
+    /// only redraws what changed since the last frame.
+    #[profiling::function]
+    pub fn render_elements_for_output<'a, R, C>(
+        renderer: &mut R,
+        roots: impl IntoIterator<Item = (&'a WlSurface, Point<i32, Logical>)>,
+        output_geometry: Rectangle<i32, Logical>,
+        scale: impl Into<Scale<f64>>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll,
+        <R as Renderer>::TextureId: Clone + 'static,
+        C: From<WaylandSurfaceRenderElement<R>>,
+    {
+        let scale = scale.into();
+        let mut render_elements = Vec::new();
+
+        for (root, location) in roots {
+            for (popup, popup_offset) in PopupManager::popups_for_surface(root) {
+                let popup_location = location + popup_offset - popup.geometry().loc;
+                if !bbox_from_surface_tree(popup.wl_surface(), popup_location).overlaps(output_geometry) {
+                    continue;
+                }
+
+                let render_location = (popup_location - output_geometry.loc).to_physical_precise_round(scale);
+                render_elements.extend(render_elements_from_surface_tree(
+                    renderer,
+                    popup.wl_surface(),
+                    render_location,
+                    scale,
+                    alpha,
+                    Kind::Unspecified,
+                ));
+            }
+        }
+
+        render_elements
+    }
+}
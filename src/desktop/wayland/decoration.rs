@@ -0,0 +1,450 @@
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use crate::{
+    backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id, Kind},
+        utils::CommitCounter,
+        Color32F,
+    },
+    desktop::{layout::ResizeEdge, Window, WindowSurface},
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+/// Appearance of server-side decorations drawn by [`WindowDecoration`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecorationStyle {
+    /// Width of the border around the window and its titlebar
+    pub border_width: i32,
+    /// Height of the titlebar
+    pub titlebar_height: i32,
+    /// Radius of the outer corners
+    ///
+    /// The contents of the window itself are not clipped, so the radius is limited to the space
+    /// available above the window and usually should not exceed the border width at the bottom.
+    pub corner_radius: i32,
+    /// Size of the titlebar buttons
+    pub button_size: i32,
+    /// Color of the border of activated windows
+    pub border_color: Color32F,
+    /// Color of the border of inactive windows
+    pub border_color_inactive: Color32F,
+    /// Color of the titlebar of activated windows
+    pub titlebar_color: Color32F,
+    /// Color of the titlebar of inactive windows
+    pub titlebar_color_inactive: Color32F,
+    /// Color of the close button
+    pub close_button_color: Color32F,
+    /// Color of the maximize button
+    pub maximize_button_color: Color32F,
+}
+
+impl Default for DecorationStyle {
+    fn default() -> Self {
+        DecorationStyle {
+            border_width: 2,
+            titlebar_height: 24,
+            corner_radius: 0,
+            button_size: 16,
+            border_color: Color32F::new(0.2, 0.4, 0.8, 1.0),
+            border_color_inactive: Color32F::new(0.3, 0.3, 0.3, 1.0),
+            titlebar_color: Color32F::new(0.15, 0.15, 0.15, 1.0),
+            titlebar_color_inactive: Color32F::new(0.25, 0.25, 0.25, 1.0),
+            close_button_color: Color32F::new(0.8, 0.2, 0.2, 1.0),
+            maximize_button_color: Color32F::new(0.5, 0.5, 0.5, 1.0),
+        }
+    }
+}
+
+/// Part of the decorations found by [`WindowDecoration::hit_test`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecorationHit {
+    /// The close button
+    Close,
+    /// The maximize button
+    Maximize,
+    /// The titlebar, usually starting an interactive move
+    TitleBar,
+    /// The border, usually starting an interactive resize of the given edges
+    Resize(ResizeEdge),
+}
+
+#[derive(Debug, Clone)]
+struct Part {
+    id: Id,
+    commit: CommitCounter,
+    rect: Rectangle<i32, Logical>,
+    color: Color32F,
+}
+
+impl Part {
+    fn new() -> Self {
+        Part {
+            id: Id::new(),
+            commit: CommitCounter::default(),
+            rect: Rectangle::default(),
+            color: Color32F::TRANSPARENT,
+        }
+    }
+
+    fn update(&mut self, rect: Rectangle<i32, Logical>, color: Color32F) {
+        if self.rect != rect || self.color != color {
+            self.rect = rect;
+            self.color = color;
+            self.commit.increment();
+        }
+    }
+}
+
+/// Server-side decorations for a [`Window`]
+///
+/// Draws a border and a titlebar with close and maximize buttons around a window using
+/// solid color elements. Text like the window title is not drawn, but can be placed into
+/// [`WindowDecoration::title_geometry`] by the compositor.
+///
+/// All geometry is relative to the top-left corner of the window geometry. Decorations grow
+/// outwards, so the effective geometry of a decorated window is [`WindowDecoration::geometry`],
+/// while [`WindowDecoration::content_size`] converts a size available for the whole decorated
+/// window into the size to configure the window with.
+///
+/// Render elements keep their identity as long as the decorations do not change,
+/// so a `WindowDecoration` should be kept per window and updated with [`WindowDecoration::update`]
+/// before rendering.
+#[derive(Debug, Clone)]
+pub struct WindowDecoration {
+    style: DecorationStyle,
+    size: Size<i32, Logical>,
+    activated: bool,
+    border: Vec<Part>,
+    titlebar: Vec<Part>,
+    close: Part,
+    maximize: Part,
+}
+
+impl WindowDecoration {
+    /// Creates new decorations with the given style
+    pub fn new(style: DecorationStyle) -> Self {
+        WindowDecoration {
+            style,
+            size: Size::default(),
+            activated: false,
+            border: Vec::new(),
+            titlebar: Vec::new(),
+            close: Part::new(),
+            maximize: Part::new(),
+        }
+    }
+
+    /// Returns the style of the decorations
+    pub fn style(&self) -> &DecorationStyle {
+        &self.style
+    }
+
+    /// Sets the style of the decorations
+    pub fn set_style(&mut self, style: DecorationStyle) {
+        self.style = style;
+        self.rebuild();
+    }
+
+    /// Updates the decorations to match the size and activated state of the window
+    pub fn update(&mut self, window: &Window) {
+        let activated = match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                toplevel.with_pending_state(|state| state.states.contains(xdg_toplevel::State::Activated))
+            }
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => surface.is_activated(),
+        };
+        self.update_with(window.geometry().size, activated);
+    }
+
+    /// Updates the decorations for a window of the given size and activated state
+    pub fn update_with(&mut self, size: Size<i32, Logical>, activated: bool) {
+        if self.size != size || self.activated != activated {
+            self.size = size;
+            self.activated = activated;
+            self.rebuild();
+        }
+    }
+
+    fn radius(&self) -> i32 {
+        let style = &self.style;
+        let outer = self.geometry();
+        style
+            .corner_radius
+            .min(style.border_width + style.titlebar_height)
+            .min(outer.size.w / 2)
+            .min(outer.size.h / 2)
+            .max(0)
+    }
+
+    /// Returns the geometry of the decorated window, relative to the window geometry
+    pub fn geometry(&self) -> Rectangle<i32, Logical> {
+        let bw = self.style.border_width;
+        let th = self.style.titlebar_height;
+        Rectangle::from_loc_and_size((-bw, -bw - th), (self.size.w + 2 * bw, self.size.h + th + 2 * bw))
+    }
+
+    /// Returns the window size fitting into the given size of the decorated window
+    pub fn content_size(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let bw = self.style.border_width;
+        let th = self.style.titlebar_height;
+        Size::from(((size.w - 2 * bw).max(1), (size.h - th - 2 * bw).max(1)))
+    }
+
+    /// Returns the geometry of the titlebar, relative to the window geometry
+    pub fn titlebar_geometry(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size(
+            (0, -self.style.titlebar_height),
+            (self.size.w, self.style.titlebar_height),
+        )
+    }
+
+    /// Returns the geometry of the close button, relative to the window geometry
+    pub fn close_button_geometry(&self) -> Rectangle<i32, Logical> {
+        self.button_geometry(0)
+    }
+
+    /// Returns the geometry of the maximize button, relative to the window geometry
+    pub fn maximize_button_geometry(&self) -> Rectangle<i32, Logical> {
+        self.button_geometry(1)
+    }
+
+    /// Returns the free area of the titlebar left of the buttons, e.g. to draw the window title
+    pub fn title_geometry(&self) -> Rectangle<i32, Logical> {
+        let mut geometry = self.titlebar_geometry();
+        geometry.size.w = (self.maximize_button_geometry().loc.x - geometry.loc.x).max(0);
+        geometry
+    }
+
+    fn button_geometry(&self, idx: i32) -> Rectangle<i32, Logical> {
+        let titlebar = self.titlebar_geometry();
+        let size = self.style.button_size.min(titlebar.size.h).max(0);
+        let padding = (titlebar.size.h - size) / 2;
+        Rectangle::from_loc_and_size(
+            (
+                titlebar.loc.x + titlebar.size.w - (size + padding) * (idx + 1),
+                titlebar.loc.y + padding,
+            ),
+            (size, size),
+        )
+    }
+
+    /// Returns the part of the decorations under the given point, relative to the window geometry
+    pub fn hit_test(&self, point: Point<f64, Logical>) -> Option<DecorationHit> {
+        let outer = self.geometry().to_f64();
+        if !outer.contains(point)
+            || Rectangle::from_loc_and_size((0, 0), self.size)
+                .to_f64()
+                .contains(point)
+        {
+            return None;
+        }
+        if self.close_button_geometry().to_f64().contains(point) {
+            return Some(DecorationHit::Close);
+        }
+        if self.maximize_button_geometry().to_f64().contains(point) {
+            return Some(DecorationHit::Maximize);
+        }
+        if self.titlebar_geometry().to_f64().contains(point) {
+            return Some(DecorationHit::TitleBar);
+        }
+
+        // corners extend along the edges to be easier to grab
+        let corner = self.style.border_width.max(self.radius()) as f64;
+        let mut edges = ResizeEdge::empty();
+        if point.x < outer.loc.x + corner {
+            edges |= ResizeEdge::LEFT;
+        } else if point.x >= outer.loc.x + outer.size.w - corner {
+            edges |= ResizeEdge::RIGHT;
+        }
+        if point.y < outer.loc.y + corner {
+            edges |= ResizeEdge::TOP;
+        } else if point.y >= outer.loc.y + outer.size.h - corner {
+            edges |= ResizeEdge::BOTTOM;
+        }
+        Some(DecorationHit::Resize(edges))
+    }
+
+    fn rebuild(&mut self) {
+        let style = self.style;
+        let (border_color, titlebar_color) = if self.activated {
+            (style.border_color, style.titlebar_color)
+        } else {
+            (style.border_color_inactive, style.titlebar_color_inactive)
+        };
+
+        let radius = self.radius();
+        let outer = self.geometry();
+        let content = Rectangle::from_loc_and_size((0, 0), self.size);
+        let border = rounded_rects(outer, radius, true, |y| {
+            (y >= content.loc.y && y < content.loc.y + content.size.h)
+                .then_some((content.loc.x, content.loc.x + content.size.w))
+        });
+        update_parts(&mut self.border, &border, border_color);
+
+        let inner_radius = (radius - style.border_width).max(0);
+        let titlebar = rounded_rects(self.titlebar_geometry(), inner_radius, false, |_| None);
+        update_parts(&mut self.titlebar, &titlebar, titlebar_color);
+
+        let close = self.close_button_geometry();
+        self.close.update(close, style.close_button_color);
+        let maximize = self.maximize_button_geometry();
+        self.maximize.update(maximize, style.maximize_button_color);
+    }
+
+    /// Returns the render elements of the decorations
+    ///
+    /// `location` is the location of the window geometry, the elements are returned
+    /// in front-to-back order and should be rendered behind the window.
+    pub fn render_elements<C: From<SolidColorRenderElement>>(
+        &self,
+        location: Point<i32, Physical>,
+        scale: impl Into<Scale<f64>>,
+        alpha: f32,
+    ) -> Vec<C> {
+        let scale = scale.into();
+        [&self.close, &self.maximize]
+            .into_iter()
+            .chain(self.titlebar.iter())
+            .chain(self.border.iter())
+            .filter(|part| !part.rect.is_empty())
+            .map(|part| {
+                let geometry = to_physical_edges(part.rect, scale);
+                SolidColorRenderElement::new(
+                    part.id.clone(),
+                    Rectangle::from_loc_and_size(location + geometry.loc, geometry.size),
+                    part.commit,
+                    part.color * alpha,
+                    Kind::Unspecified,
+                )
+                .into()
+            })
+            .collect()
+    }
+}
+
+// rounds the edges instead of location and size to keep adjacent rectangles gapless
+fn to_physical_edges(rect: Rectangle<i32, Logical>, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+    let rect = rect.to_f64().to_physical(scale);
+    let x0 = rect.loc.x.round() as i32;
+    let y0 = rect.loc.y.round() as i32;
+    let x1 = (rect.loc.x + rect.size.w).round() as i32;
+    let y1 = (rect.loc.y + rect.size.h).round() as i32;
+    Rectangle::from_loc_and_size((x0, y0), (x1 - x0, y1 - y0))
+}
+
+fn update_parts(parts: &mut Vec<Part>, rects: &[Rectangle<i32, Logical>], color: Color32F) {
+    parts.truncate(rects.len());
+    while parts.len() < rects.len() {
+        parts.push(Part::new());
+    }
+    for (part, rect) in parts.iter_mut().zip(rects) {
+        part.update(*rect, color);
+    }
+}
+
+// Decomposes a rectangle with rounded corners into rectangles by merging rows with equal spans,
+// `hole` optionally returns a horizontal span of a row to leave out.
+fn rounded_rects(
+    rect: Rectangle<i32, Logical>,
+    radius: i32,
+    round_bottom: bool,
+    hole: impl Fn(i32) -> Option<(i32, i32)>,
+) -> Vec<Rectangle<i32, Logical>> {
+    let inset = |row: i32| -> i32 {
+        let from_edge = if row < radius {
+            row
+        } else if round_bottom && row >= rect.size.h - radius {
+            rect.size.h - 1 - row
+        } else {
+            return 0;
+        };
+        let dy = radius as f64 - from_edge as f64 - 0.5;
+        (radius as f64 - (radius as f64 * radius as f64 - dy * dy).max(0.0).sqrt()).round() as i32
+    };
+    let spans = |row: i32| -> [Option<(i32, i32)>; 2] {
+        let y = rect.loc.y + row;
+        let x0 = rect.loc.x + inset(row);
+        let x1 = rect.loc.x + rect.size.w - inset(row);
+        match hole(y) {
+            Some((h0, h1)) => [
+                (x0 < h0.min(x1)).then_some((x0, h0.min(x1))),
+                (h1.max(x0) < x1).then_some((h1.max(x0), x1)),
+            ],
+            None => [(x0 < x1).then_some((x0, x1)), None],
+        }
+    };
+
+    let mut rects = Vec::new();
+    let mut start = 0;
+    while start < rect.size.h {
+        let current = spans(start);
+        let mut end = start + 1;
+        while end < rect.size.h && spans(end) == current {
+            end += 1;
+        }
+        for (x0, x1) in current.into_iter().flatten() {
+            rects.push(Rectangle::from_loc_and_size(
+                (x0, rect.loc.y + start),
+                (x1 - x0, end - start),
+            ));
+        }
+        start = end;
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_test() {
+        let mut decoration = WindowDecoration::new(DecorationStyle {
+            border_width: 4,
+            titlebar_height: 20,
+            button_size: 16,
+            ..Default::default()
+        });
+        decoration.update_with((100, 100).into(), true);
+
+        assert_eq!(
+            decoration.geometry(),
+            Rectangle::from_loc_and_size((-4, -24), (108, 128))
+        );
+        assert_eq!(decoration.hit_test((50.0, 50.0).into()), None);
+        assert_eq!(
+            decoration.hit_test((10.0, -10.0).into()),
+            Some(DecorationHit::TitleBar)
+        );
+        assert_eq!(
+            decoration.hit_test((90.0, -10.0).into()),
+            Some(DecorationHit::Close)
+        );
+        assert_eq!(
+            decoration.hit_test((70.0, -10.0).into()),
+            Some(DecorationHit::Maximize)
+        );
+        assert_eq!(
+            decoration.hit_test((-2.0, 50.0).into()),
+            Some(DecorationHit::Resize(ResizeEdge::LEFT))
+        );
+        assert_eq!(
+            decoration.hit_test((101.0, 102.0).into()),
+            Some(DecorationHit::Resize(ResizeEdge::BOTTOM_RIGHT))
+        );
+    }
+
+    #[test]
+    fn rounded_border_covers_ring() {
+        let rects = rounded_rects(Rectangle::from_loc_and_size((0, 0), (20, 20)), 4, true, |y| {
+            (2..18).contains(&y).then_some((2, 18))
+        });
+        let area: i32 = rects.iter().map(|r| r.size.w * r.size.h).sum();
+        // the ring without the cut off corners
+        assert!(area < 20 * 20 - 16 * 16);
+        assert!(rects
+            .iter()
+            .all(|r| !r.overlaps(Rectangle::from_loc_and_size((2, 2), (16, 16)))));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    desktop::Window,
+    utils::IsAlive,
+    wayland::foreign_toplevel_list::{
+        ForeignToplevelHandle, ForeignToplevelListHandler, ForeignToplevelListState,
+    },
+};
+
+/// Keeps [foreign toplevel handles](ForeignToplevelHandle) in sync with a set of [`Window`]s
+///
+/// Every window passed to [`ForeignToplevelSync::refresh`] is announced through the
+/// [`ForeignToplevelListState`] and its title and app_id are updated whenever they change.
+/// Windows that are no longer alive or are no longer part of the refreshed set are closed.
+///
+/// The ext-foreign-toplevel-list protocol only carries the identity, title and app_id of toplevels,
+/// so outputs and states like activated, maximized or fullscreen are not synchronized.
+///
+/// ```no_run
+/// # use smithay::desktop::{ForeignToplevelSync, Space, Window};
+/// # use smithay::wayland::foreign_toplevel_list::{ForeignToplevelListHandler, ForeignToplevelListState};
+/// # fn refresh<D: ForeignToplevelListHandler>(state: &mut ForeignToplevelListState, space: &Space<Window>) {
+/// let mut sync = ForeignToplevelSync::new();
+///
+/// // e.g. after each dispatch of the event loop
+/// sync.refresh::<D, _>(state, space.elements());
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ForeignToplevelSync {
+    toplevels: Vec<(Window, ForeignToplevelHandle)>,
+}
+
+impl ForeignToplevelSync {
+    /// Creates a new empty synchronization helper
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Announces new windows, updates changed ones and closes the handles of removed windows
+    ///
+    /// `windows` should contain all currently mapped windows, unmapped windows are closed
+    /// and announced again as a new toplevel once they are mapped again.
+    pub fn refresh<'a, D, I>(&mut self, state: &mut ForeignToplevelListState, windows: I)
+    where
+        D: ForeignToplevelListHandler,
+        I: IntoIterator<Item = &'a Window>,
+    {
+        let windows = windows
+            .into_iter()
+            .filter(|window| window.alive())
+            .collect::<Vec<_>>();
+
+        self.toplevels.retain(|(window, handle)| {
+            let keep = windows.contains(&window);
+            if !keep {
+                state.remove_toplevel(handle);
+            }
+            keep
+        });
+
+        for window in windows {
+            let title = window.title().unwrap_or_default();
+            let app_id = window.app_id().unwrap_or_default();
+
+            match self.toplevels.iter().find(|(w, _)| w == window) {
+                Some((_, handle)) => {
+                    let changed = handle.title() != title || handle.app_id() != app_id;
+                    if changed {
+                        handle.send_title(&title);
+                        handle.send_app_id(&app_id);
+                        handle.send_done();
+                    }
+                }
+                None => {
+                    let handle = state.new_toplevel::<D>(title, app_id);
+                    self.toplevels.push((window.clone(), handle));
+                }
+            }
+        }
+    }
+
+    /// Closes the handle of a single window, e.g. when it gets unmapped
+    pub fn remove(&mut self, state: &mut ForeignToplevelListState, window: &Window) {
+        if let Some(pos) = self.toplevels.iter().position(|(w, _)| w == window) {
+            let (_, handle) = self.toplevels.remove(pos);
+            state.remove_toplevel(&handle);
+        }
+    }
+
+    /// Returns the handle announced for a window
+    pub fn handle_for(&self, window: &Window) -> Option<&ForeignToplevelHandle> {
+        self.toplevels
+            .iter()
+            .find(|(w, _)| w == window)
+            .map(|(_, handle)| handle)
+    }
+
+    /// Returns the window of a handle, e.g. to act on requests referring to the handle
+    pub fn window_for(&self, handle: &ForeignToplevelHandle) -> Option<&Window> {
+        let identifier = handle.identifier();
+        self.toplevels
+            .iter()
+            .find(|(_, h)| h.identifier() == identifier)
+            .map(|(window, _)| window)
+    }
+}
@@ -0,0 +1,987 @@
+use std::{fmt, sync::Mutex};
+
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use crate::{
+    desktop::{
+        layout::ResizeEdge,
+        space::{Space, SpaceElement},
+        Window, WindowSurface,
+    },
+    input::{
+        pointer::{
+            AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+            GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+            GestureSwipeUpdateEvent, GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab,
+            PointerInnerHandle, RelativeMotionEvent,
+        },
+        touch::{
+            DownEvent, GrabStartData as TouchGrabStartData, MotionEvent as TouchMotionEvent,
+            OrientationEvent, ShapeEvent, TouchGrab, TouchInnerHandle, UpEvent,
+        },
+        SeatHandler,
+    },
+    utils::{Logical, Point, Serial, Size},
+    wayland::{compositor::with_states, shell::xdg::SurfaceCachedState},
+};
+
+/// Compositor policy for the interactive move and resize grabs
+///
+/// The grabs provided by this module update the location of the grabbed element inside the
+/// [`Space`] returned by [`InteractiveGrabHandler::grab_space`] and send the necessary configures.
+/// The remaining methods allow to constrain the operation, e.g. to snap windows to output edges,
+/// and to get notified once it is finished.
+pub trait InteractiveGrabHandler: SeatHandler + Sized + 'static {
+    /// Element type mapped into the space
+    type Element: SpaceElement + PartialEq + Clone + AsRef<Window> + fmt::Debug + Send + 'static;
+
+    /// Returns the space the element is mapped in
+    ///
+    /// The grab ends, if this returns `None`.
+    fn grab_space(&mut self, element: &Self::Element) -> Option<&mut Space<Self::Element>>;
+
+    /// Constrains the location of an element being moved
+    ///
+    /// `location` is the proposed location of the element inside its space. The default implementation
+    /// does not apply any constraints.
+    fn constrain_move(
+        &mut self,
+        element: &Self::Element,
+        location: Point<i32, Logical>,
+    ) -> Point<i32, Logical> {
+        let _ = element;
+        location
+    }
+
+    /// Constrains the size of an element being resized
+    ///
+    /// `size` is the proposed size of the window geometry, already clamped to the min and max size
+    /// hints of the client. The default implementation does not apply any further constraints.
+    fn constrain_resize(
+        &mut self,
+        element: &Self::Element,
+        edges: ResizeEdge,
+        size: Size<i32, Logical>,
+    ) -> Size<i32, Logical> {
+        let _ = (element, edges);
+        size
+    }
+
+    /// Called once a move grab ended
+    fn move_finished(&mut self, element: &Self::Element) {
+        let _ = element;
+    }
+
+    /// Called once a resize grab ended
+    ///
+    /// Wayland clients might not have committed their final size yet.
+    fn resize_finished(&mut self, element: &Self::Element) {
+        let _ = element;
+    }
+}
+
+/// Information about an interactive resize operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeData {
+    /// The edges the window is being resized with
+    pub edges: ResizeEdge,
+    /// The location of the window at the start of the operation
+    pub initial_window_location: Point<i32, Logical>,
+    /// The size of the window geometry at the start of the operation
+    pub initial_window_size: Size<i32, Logical>,
+}
+
+/// State of an interactive resize operation of a [`Window`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeState {
+    /// The window is not being resized
+    #[default]
+    NotResizing,
+    /// The window is currently being resized
+    Resizing(ResizeData),
+    /// The resize has finished and the client needs to ack the final configure
+    WaitingForFinalAck(ResizeData, Serial),
+    /// The resize has finished and the client needs to commit its final state
+    WaitingForCommit(ResizeData),
+}
+
+impl Window {
+    /// Returns the state of an interactive resize started by a [`PointerResizeGrab`] or [`TouchResizeGrab`]
+    pub fn resize_state(&self) -> ResizeState {
+        self.user_data()
+            .get::<Mutex<ResizeState>>()
+            .map(|state| *state.lock().unwrap())
+            .unwrap_or_default()
+    }
+
+    fn set_resize_state(&self, state: ResizeState) {
+        self.user_data()
+            .insert_if_missing_threadsafe(|| Mutex::new(ResizeState::default()));
+        *self
+            .user_data()
+            .get::<Mutex<ResizeState>>()
+            .unwrap()
+            .lock()
+            .unwrap() = state;
+    }
+
+    /// Notifies the window about an acknowledged configure
+    ///
+    /// This should be called from [`XdgShellHandler::ack_configure`](crate::wayland::shell::xdg::XdgShellHandler::ack_configure)
+    /// to track the end of an interactive resize, see [`handle_resize_commit`].
+    pub fn ack_resize_configure(&self, serial: Serial) {
+        if let ResizeState::WaitingForFinalAck(data, final_serial) = self.resize_state() {
+            if serial.is_no_older_than(&final_serial) {
+                self.set_resize_state(ResizeState::WaitingForCommit(data));
+            }
+        }
+    }
+
+    fn size_constraints(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        match self.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => with_states(toplevel.wl_surface(), |states| {
+                let mut guard = states.cached_state.get::<SurfaceCachedState>();
+                let data = guard.current();
+                (data.min_size, data.max_size)
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => (
+                surface.min_size().unwrap_or_default(),
+                surface.max_size().unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+/// Moves a window being resized by its top or left edges as its size changes
+///
+/// Wayland clients may pick a different size than the one requested during an interactive resize,
+/// so the location of the window can only be adjusted once the new size was committed.
+/// This should be called in the commit handler of the compositor for toplevel surfaces
+/// after [`Window::on_commit`].
+pub fn handle_resize_commit<E>(space: &mut Space<E>, element: &E)
+where
+    E: SpaceElement + PartialEq + Clone + AsRef<Window>,
+{
+    let window = element.as_ref();
+    if window.toplevel().is_none() {
+        // X11 windows are positioned directly by the grab
+        return;
+    }
+
+    let data = match window.resize_state() {
+        ResizeState::Resizing(data) => data,
+        ResizeState::WaitingForCommit(data) => {
+            window.set_resize_state(ResizeState::NotResizing);
+            data
+        }
+        _ => return,
+    };
+    if !data.edges.intersects(ResizeEdge::TOP_LEFT) {
+        return;
+    }
+
+    let Some(mut location) = space.element_location(element) else {
+        return;
+    };
+    let size = window.geometry().size;
+    let adjusted = resized_location(data, size);
+    if data.edges.intersects(ResizeEdge::LEFT) {
+        location.x = adjusted.x;
+    }
+    if data.edges.intersects(ResizeEdge::TOP) {
+        location.y = adjusted.y;
+    }
+    space.map_element(element.clone(), location, false);
+}
+
+fn resized_location(data: ResizeData, size: Size<i32, Logical>) -> Point<i32, Logical> {
+    let mut location = data.initial_window_location;
+    if data.edges.intersects(ResizeEdge::LEFT) {
+        location.x += data.initial_window_size.w - size.w;
+    }
+    if data.edges.intersects(ResizeEdge::TOP) {
+        location.y += data.initial_window_size.h - size.h;
+    }
+    location
+}
+
+// a max size of 0 means unlimited, sizes are always at least 1x1
+fn resized_size(
+    data: ResizeData,
+    delta: Point<f64, Logical>,
+    min_size: Size<i32, Logical>,
+    max_size: Size<i32, Logical>,
+) -> Size<i32, Logical> {
+    let edges = data.edges;
+    let mut size = data.initial_window_size;
+    if edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
+        let dx = if edges.intersects(ResizeEdge::LEFT) {
+            -delta.x
+        } else {
+            delta.x
+        };
+        size.w = (size.w as f64 + dx) as i32;
+    }
+    if edges.intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM) {
+        let dy = if edges.intersects(ResizeEdge::TOP) {
+            -delta.y
+        } else {
+            delta.y
+        };
+        size.h = (size.h as f64 + dy) as i32;
+    }
+
+    let max_w = if max_size.w == 0 { i32::MAX } else { max_size.w };
+    let max_h = if max_size.h == 0 { i32::MAX } else { max_size.h };
+    size.w = size.w.max(min_size.w.max(1)).min(max_w);
+    size.h = size.h.max(min_size.h.max(1)).min(max_h);
+    size
+}
+
+struct MoveOperation<E> {
+    element: E,
+    initial_location: Point<i32, Logical>,
+}
+
+impl<E: fmt::Debug> fmt::Debug for MoveOperation<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MoveOperation")
+            .field("element", &self.element)
+            .field("initial_location", &self.initial_location)
+            .finish()
+    }
+}
+
+impl<E> MoveOperation<E>
+where
+    E: SpaceElement + PartialEq + Clone + AsRef<Window>,
+{
+    fn new<D>(data: &mut D, element: E) -> Option<Self>
+    where
+        D: InteractiveGrabHandler<Element = E>,
+    {
+        let initial_location = data.grab_space(&element)?.element_location(&element)?;
+        Some(MoveOperation {
+            element,
+            initial_location,
+        })
+    }
+
+    // returns false if the operation should end
+    fn update<D>(&self, data: &mut D, delta: Point<f64, Logical>) -> bool
+    where
+        D: InteractiveGrabHandler<Element = E>,
+    {
+        if !self.element.alive() {
+            return false;
+        }
+        let location = (self.initial_location.to_f64() + delta).to_i32_round();
+        let location = data.constrain_move(&self.element, location);
+        let Some(space) = data.grab_space(&self.element) else {
+            return false;
+        };
+        space.map_element(self.element.clone(), location, true);
+        true
+    }
+}
+
+struct ResizeOperation<E> {
+    element: E,
+    data: ResizeData,
+    last_size: Size<i32, Logical>,
+}
+
+impl<E: fmt::Debug> fmt::Debug for ResizeOperation<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResizeOperation")
+            .field("element", &self.element)
+            .field("data", &self.data)
+            .field("last_size", &self.last_size)
+            .finish()
+    }
+}
+
+impl<E> ResizeOperation<E>
+where
+    E: SpaceElement + PartialEq + Clone + AsRef<Window>,
+{
+    fn new<D>(data: &mut D, element: E, edges: ResizeEdge) -> Option<Self>
+    where
+        D: InteractiveGrabHandler<Element = E>,
+    {
+        let initial_window_location = data.grab_space(&element)?.element_location(&element)?;
+        let initial_window_size = element.as_ref().geometry().size;
+        let resize_data = ResizeData {
+            edges,
+            initial_window_location,
+            initial_window_size,
+        };
+        element
+            .as_ref()
+            .set_resize_state(ResizeState::Resizing(resize_data));
+        Some(ResizeOperation {
+            element,
+            data: resize_data,
+            last_size: initial_window_size,
+        })
+    }
+
+    // returns false if the operation should end
+    fn update<D>(&mut self, data: &mut D, delta: Point<f64, Logical>) -> bool
+    where
+        D: InteractiveGrabHandler<Element = E>,
+    {
+        // It is impossible to get the size hints of a dead toplevel
+        if !self.element.alive() {
+            return false;
+        }
+
+        let window = self.element.as_ref();
+        let (min_size, max_size) = window.size_constraints();
+        let size = resized_size(self.data, delta, min_size, max_size);
+        self.last_size = data.constrain_resize(&self.element, self.data.edges, size);
+
+        match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                toplevel.with_pending_state(|state| {
+                    state.states.set(xdg_toplevel::State::Resizing);
+                    state.size = Some(self.last_size);
+                });
+                toplevel.send_pending_configure();
+            }
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => {
+                let location = resized_location(self.data, self.last_size);
+                let _ = surface.configure(crate::utils::Rectangle::from_loc_and_size(
+                    location,
+                    self.last_size,
+                ));
+                if let Some(space) = data.grab_space(&self.element) {
+                    space.map_element(self.element.clone(), location, true);
+                }
+            }
+        }
+        true
+    }
+
+    fn finish<D>(&mut self, data: &mut D)
+    where
+        D: InteractiveGrabHandler<Element = E>,
+    {
+        if !self.element.alive() {
+            return;
+        }
+
+        let window = self.element.as_ref();
+        match window.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                toplevel.with_pending_state(|state| {
+                    state.states.unset(xdg_toplevel::State::Resizing);
+                    state.size = Some(self.last_size);
+                });
+                let state = match toplevel.send_pending_configure() {
+                    Some(serial) => ResizeState::WaitingForFinalAck(self.data, serial),
+                    None => ResizeState::WaitingForCommit(self.data),
+                };
+                window.set_resize_state(state);
+            }
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => {
+                let location = resized_location(self.data, self.last_size);
+                let _ = surface.configure(crate::utils::Rectangle::from_loc_and_size(
+                    location,
+                    self.last_size,
+                ));
+                window.set_resize_state(ResizeState::NotResizing);
+            }
+        }
+        data.resize_finished(&self.element);
+    }
+}
+
+/// Pointer grab to interactively move an element
+///
+/// The grab ends once all buttons are released.
+pub struct PointerMoveGrab<D: InteractiveGrabHandler> {
+    start_data: PointerGrabStartData<D>,
+    operation: MoveOperation<D::Element>,
+}
+
+impl<D: InteractiveGrabHandler> fmt::Debug for PointerMoveGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerMoveGrab")
+            .field("start_data", &self.start_data)
+            .field("element", &self.operation.element)
+            .finish()
+    }
+}
+
+impl<D: InteractiveGrabHandler> PointerMoveGrab<D> {
+    /// Creates a new move grab for an element
+    ///
+    /// Returns `None` if the element is not mapped in the space returned by [`InteractiveGrabHandler::grab_space`].
+    pub fn new(data: &mut D, start_data: PointerGrabStartData<D>, element: D::Element) -> Option<Self> {
+        Some(PointerMoveGrab {
+            start_data,
+            operation: MoveOperation::new(data, element)?,
+        })
+    }
+
+    /// Returns the element being moved
+    pub fn element(&self) -> &D::Element {
+        &self.operation.element
+    }
+}
+
+impl<D: InteractiveGrabHandler> PointerGrab<D> for PointerMoveGrab<D> {
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, None, event);
+
+        let delta = event.location - self.start_data.location;
+        if !self.operation.update(data, delta) {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event);
+    }
+
+    fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // No more buttons are pressed, release the grab.
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, details: AxisFrame) {
+        handle.axis(data, details)
+    }
+
+    fn frame(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData<D> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut D) {
+        data.move_finished(&self.operation.element);
+    }
+}
+
+/// Touch grab to interactively move an element
+///
+/// The grab ends once the touch point that started it is lifted.
+pub struct TouchMoveGrab<D: InteractiveGrabHandler> {
+    start_data: TouchGrabStartData<D>,
+    operation: MoveOperation<D::Element>,
+}
+
+impl<D: InteractiveGrabHandler> fmt::Debug for TouchMoveGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchMoveGrab")
+            .field("start_data", &self.start_data)
+            .field("element", &self.operation.element)
+            .finish()
+    }
+}
+
+impl<D: InteractiveGrabHandler> TouchMoveGrab<D> {
+    /// Creates a new move grab for an element
+    ///
+    /// Returns `None` if the element is not mapped in the space returned by [`InteractiveGrabHandler::grab_space`].
+    pub fn new(data: &mut D, start_data: TouchGrabStartData<D>, element: D::Element) -> Option<Self> {
+        Some(TouchMoveGrab {
+            start_data,
+            operation: MoveOperation::new(data, element)?,
+        })
+    }
+
+    /// Returns the element being moved
+    pub fn element(&self) -> &D::Element {
+        &self.operation.element
+    }
+}
+
+impl<D: InteractiveGrabHandler> TouchGrab<D> for TouchMoveGrab<D> {
+    fn down(
+        &mut self,
+        _data: &mut D,
+        _handle: &mut TouchInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::TouchFocus, Point<f64, Logical>)>,
+        _event: &DownEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn up(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &UpEvent, seq: Serial) {
+        if event.slot != self.start_data.slot {
+            return;
+        }
+
+        handle.up(data, event, seq);
+        handle.unset_grab(self, data);
+    }
+
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::TouchFocus, Point<f64, Logical>)>,
+        event: &TouchMotionEvent,
+        _seq: Serial,
+    ) {
+        if event.slot != self.start_data.slot {
+            return;
+        }
+
+        let delta = event.location - self.start_data.location;
+        if !self.operation.update(data, delta) {
+            handle.unset_grab(self, data);
+        }
+    }
+
+    fn frame(&mut self, _data: &mut D, _handle: &mut TouchInnerHandle<'_, D>, _seq: Serial) {}
+
+    fn cancel(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, seq: Serial) {
+        handle.cancel(data, seq);
+        handle.unset_grab(self, data);
+    }
+
+    fn shape(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &ShapeEvent, seq: Serial) {
+        handle.shape(data, event, seq);
+    }
+
+    fn orientation(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        event: &OrientationEvent,
+        seq: Serial,
+    ) {
+        handle.orientation(data, event, seq);
+    }
+
+    fn start_data(&self) -> &TouchGrabStartData<D> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut D) {
+        data.move_finished(&self.operation.element);
+    }
+}
+
+/// Pointer grab to interactively resize an element
+///
+/// The grab sends configures with the `Resizing` state and the new size to the window, clamped to the
+/// min and max size hints of the client. Once all buttons are released, the final configure is sent.
+///
+/// Compositors need to call [`Window::ack_resize_configure`] and [`handle_resize_commit`] to keep the
+/// opposite edges of windows resized by their top or left edges in place.
+pub struct PointerResizeGrab<D: InteractiveGrabHandler> {
+    start_data: PointerGrabStartData<D>,
+    operation: ResizeOperation<D::Element>,
+}
+
+impl<D: InteractiveGrabHandler> fmt::Debug for PointerResizeGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerResizeGrab")
+            .field("start_data", &self.start_data)
+            .field("operation", &self.operation)
+            .finish()
+    }
+}
+
+impl<D: InteractiveGrabHandler> PointerResizeGrab<D> {
+    /// Creates a new resize grab for an element
+    ///
+    /// Returns `None` if the element is not mapped in the space returned by [`InteractiveGrabHandler::grab_space`].
+    pub fn new(
+        data: &mut D,
+        start_data: PointerGrabStartData<D>,
+        element: D::Element,
+        edges: ResizeEdge,
+    ) -> Option<Self> {
+        Some(PointerResizeGrab {
+            start_data,
+            operation: ResizeOperation::new(data, element, edges)?,
+        })
+    }
+
+    /// Returns the element being resized
+    pub fn element(&self) -> &D::Element {
+        &self.operation.element
+    }
+}
+
+impl<D: InteractiveGrabHandler> PointerGrab<D> for PointerResizeGrab<D> {
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, None, event);
+
+        let delta = event.location - self.start_data.location;
+        if !self.operation.update(data, delta) {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event);
+    }
+
+    fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // No more buttons are pressed, release the grab.
+            handle.unset_grab(self, data, event.serial, event.time, true);
+        }
+    }
+
+    fn axis(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, details: AxisFrame) {
+        handle.axis(data, details)
+    }
+
+    fn frame(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>) {
+        handle.frame(data);
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        event: &GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+
+    fn start_data(&self) -> &PointerGrabStartData<D> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut D) {
+        self.operation.finish(data);
+    }
+}
+
+/// Touch grab to interactively resize an element
+///
+/// See [`PointerResizeGrab`] for more information.
+/// The grab ends once the touch point that started it is lifted.
+pub struct TouchResizeGrab<D: InteractiveGrabHandler> {
+    start_data: TouchGrabStartData<D>,
+    operation: ResizeOperation<D::Element>,
+}
+
+impl<D: InteractiveGrabHandler> fmt::Debug for TouchResizeGrab<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchResizeGrab")
+            .field("start_data", &self.start_data)
+            .field("operation", &self.operation)
+            .finish()
+    }
+}
+
+impl<D: InteractiveGrabHandler> TouchResizeGrab<D> {
+    /// Creates a new resize grab for an element
+    ///
+    /// Returns `None` if the element is not mapped in the space returned by [`InteractiveGrabHandler::grab_space`].
+    pub fn new(
+        data: &mut D,
+        start_data: TouchGrabStartData<D>,
+        element: D::Element,
+        edges: ResizeEdge,
+    ) -> Option<Self> {
+        Some(TouchResizeGrab {
+            start_data,
+            operation: ResizeOperation::new(data, element, edges)?,
+        })
+    }
+
+    /// Returns the element being resized
+    pub fn element(&self) -> &D::Element {
+        &self.operation.element
+    }
+}
+
+impl<D: InteractiveGrabHandler> TouchGrab<D> for TouchResizeGrab<D> {
+    fn down(
+        &mut self,
+        _data: &mut D,
+        _handle: &mut TouchInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::TouchFocus, Point<f64, Logical>)>,
+        _event: &DownEvent,
+        _seq: Serial,
+    ) {
+    }
+
+    fn up(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &UpEvent, seq: Serial) {
+        if event.slot != self.start_data.slot {
+            return;
+        }
+
+        handle.up(data, event, seq);
+        handle.unset_grab(self, data);
+    }
+
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::TouchFocus, Point<f64, Logical>)>,
+        event: &TouchMotionEvent,
+        _seq: Serial,
+    ) {
+        if event.slot != self.start_data.slot {
+            return;
+        }
+
+        let delta = event.location - self.start_data.location;
+        if !self.operation.update(data, delta) {
+            handle.unset_grab(self, data);
+        }
+    }
+
+    fn frame(&mut self, _data: &mut D, _handle: &mut TouchInnerHandle<'_, D>, _seq: Serial) {}
+
+    fn cancel(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, seq: Serial) {
+        handle.cancel(data, seq);
+        handle.unset_grab(self, data);
+    }
+
+    fn shape(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &ShapeEvent, seq: Serial) {
+        handle.shape(data, event, seq);
+    }
+
+    fn orientation(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        event: &OrientationEvent,
+        seq: Serial,
+    ) {
+        handle.orientation(data, event, seq);
+    }
+
+    fn start_data(&self) -> &TouchGrabStartData<D> {
+        &self.start_data
+    }
+
+    fn unset(&mut self, data: &mut D) {
+        self.operation.finish(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resized_location, resized_size, ResizeData};
+    use crate::{desktop::layout::ResizeEdge, utils::Size};
+
+    fn resize_data(edges: ResizeEdge) -> ResizeData {
+        ResizeData {
+            edges,
+            initial_window_location: (100, 100).into(),
+            initial_window_size: (400, 300).into(),
+        }
+    }
+
+    #[test]
+    fn resize_follows_edges() {
+        let unconstrained = Size::default();
+
+        let data = resize_data(ResizeEdge::BOTTOM_RIGHT);
+        let size = resized_size(data, (50.0, 20.0).into(), unconstrained, unconstrained);
+        assert_eq!(size, (450, 320).into());
+        // the location only changes when resizing by the top or left edges
+        assert_eq!(resized_location(data, size), (100, 100).into());
+
+        let data = resize_data(ResizeEdge::TOP_LEFT);
+        let size = resized_size(data, (50.0, 20.0).into(), unconstrained, unconstrained);
+        assert_eq!(size, (350, 280).into());
+        assert_eq!(resized_location(data, size), (150, 120).into());
+
+        // edges not part of the operation keep their size
+        let data = resize_data(ResizeEdge::LEFT);
+        let size = resized_size(data, (-50.0, 20.0).into(), unconstrained, unconstrained);
+        assert_eq!(size, (450, 300).into());
+        assert_eq!(resized_location(data, size), (50, 100).into());
+    }
+
+    #[test]
+    fn resize_respects_size_hints() {
+        let data = resize_data(ResizeEdge::TOP_LEFT);
+        let min = Size::from((200, 0));
+        let max = Size::from((500, 0));
+
+        let size = resized_size(data, (300.0, 1000.0).into(), min, max);
+        assert_eq!(size, (200, 1).into());
+        // the opposite edges stay in place
+        assert_eq!(resized_location(data, size), (300, 399).into());
+
+        let size = resized_size(data, (-300.0, -1000.0).into(), min, max);
+        assert_eq!(size, (500, 1300).into());
+    }
+}
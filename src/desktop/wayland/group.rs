@@ -0,0 +1,483 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    desktop::{space::SpaceElement, Window, WindowSurface},
+    output::{Output, WeakOutput},
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
+};
+
+crate::utils::ids::id_gen!(group_id);
+
+/// Default height of a single row of the tab bar of a [`WindowGroup`]
+pub const DEFAULT_TAB_BAR_HEIGHT: i32 = 24;
+
+/// Arrangement of the tabs of a [`WindowGroup`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupLayout {
+    /// Tabs are placed side by side in a single row
+    #[default]
+    Tabbed,
+    /// Tabs are placed in rows above each other
+    Stacked,
+}
+
+/// Information about a single tab of a [`WindowGroup`]
+///
+/// Returned by [`WindowGroup::tabs`] to render tab bars.
+#[derive(Debug, Clone)]
+pub struct TabInfo {
+    /// Window represented by this tab
+    pub window: Window,
+    /// Title of the window
+    pub title: Option<String>,
+    /// App-id of the window, X11 windows have none
+    pub app_id: Option<String>,
+    /// Whether this is the active window of the group
+    pub active: bool,
+    /// Geometry of the tab relative to the geometry of the group
+    pub geometry: Rectangle<i32, Logical>,
+}
+
+#[derive(Debug)]
+struct GroupState {
+    windows: Vec<Window>,
+    active: usize,
+    layout: GroupLayout,
+    tab_bar_height: i32,
+    activated: bool,
+    outputs: Vec<(WeakOutput, Rectangle<i32, Logical>)>,
+}
+
+impl GroupState {
+    fn active(&self) -> Option<&Window> {
+        self.windows.get(self.active)
+    }
+
+    fn bar_height(&self) -> i32 {
+        match self.layout {
+            GroupLayout::Tabbed => self.tab_bar_height,
+            GroupLayout::Stacked => self.tab_bar_height * self.windows.len() as i32,
+        }
+    }
+
+    fn switch_to(&mut self, idx: usize) -> bool {
+        if idx == self.active || idx >= self.windows.len() {
+            return false;
+        }
+
+        if let Some(previous) = self.windows.get(self.active) {
+            if self.activated {
+                previous.set_activated(false);
+            }
+            for (output, _) in &self.outputs {
+                if let Some(output) = output.upgrade() {
+                    previous.output_leave(&output);
+                }
+            }
+        }
+        self.active = idx;
+        self.enter_active();
+        true
+    }
+
+    fn enter_active(&self) {
+        if let Some(active) = self.active() {
+            if self.activated {
+                active.set_activated(true);
+            }
+            for (output, overlap) in &self.outputs {
+                if let Some(output) = output.upgrade() {
+                    active.output_enter(&output, *overlap);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GroupInner {
+    id: usize,
+    state: Mutex<GroupState>,
+}
+
+impl Drop for GroupInner {
+    fn drop(&mut self) {
+        group_id::remove(self.id);
+    }
+}
+
+/// A tabbed or stacked container of [`Window`]s
+///
+/// A window group occupies a single slot, e.g. in a [`Space`](crate::desktop::Space) or a
+/// [`TilingLayout`](crate::desktop::layout::TilingLayout), and shows exactly one of its windows, the
+/// active window, below a tab bar. All windows of a group share the same size.
+///
+/// The group does not draw the tab bar itself, instead [`WindowGroup::tabs`] provides everything
+/// necessary to render it, while [`WindowGroup::tab_under`] can be used to handle clicks on it.
+///
+/// When the group is activated only the active window is activated. Switching the active window
+/// transfers the activated state and output enter/leave events to the new active window.
+/// Keyboard focus should follow [`WindowGroup::active`].
+#[derive(Debug, Clone)]
+pub struct WindowGroup(Arc<GroupInner>);
+
+impl PartialEq for WindowGroup {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id == other.0.id
+    }
+}
+
+impl Eq for WindowGroup {}
+
+impl Hash for WindowGroup {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
+
+impl IsAlive for WindowGroup {
+    #[inline]
+    fn alive(&self) -> bool {
+        self.state().windows.iter().any(IsAlive::alive)
+    }
+}
+
+impl WindowGroup {
+    /// Creates a new group containing a single window
+    pub fn new(window: Window, layout: GroupLayout) -> WindowGroup {
+        WindowGroup(Arc::new(GroupInner {
+            id: group_id::next(),
+            state: Mutex::new(GroupState {
+                windows: vec![window],
+                active: 0,
+                layout,
+                tab_bar_height: DEFAULT_TAB_BAR_HEIGHT,
+                activated: false,
+                outputs: Vec::new(),
+            }),
+        }))
+    }
+
+    fn state(&self) -> MutexGuard<'_, GroupState> {
+        self.0.state.lock().unwrap()
+    }
+
+    /// Adds a window after the active window and makes it the active window
+    ///
+    /// Does nothing if the window is already part of the group.
+    pub fn add_window(&self, window: Window) {
+        let mut state = self.state();
+        if state.windows.contains(&window) {
+            return;
+        }
+
+        let idx = if state.windows.is_empty() {
+            0
+        } else {
+            state.active + 1
+        };
+        state.windows.insert(idx, window);
+        if state.windows.len() == 1 {
+            state.enter_active();
+        } else {
+            // the previously active window still sits at `state.active`
+            state.switch_to(idx);
+        }
+    }
+
+    /// Removes a window from the group
+    ///
+    /// If the window was active, the next window (or the previous one, if it was the last window)
+    /// becomes active. Returns `false` if the window was not part of the group.
+    pub fn remove_window(&self, window: &Window) -> bool {
+        let mut state = self.state();
+        let Some(idx) = state.windows.iter().position(|w| w == window) else {
+            return false;
+        };
+
+        let was_active = idx == state.active;
+        if was_active {
+            if state.activated {
+                window.set_activated(false);
+            }
+            for (output, _) in &state.outputs {
+                if let Some(output) = output.upgrade() {
+                    window.output_leave(&output);
+                }
+            }
+        }
+
+        state.windows.remove(idx);
+        state.active = active_after_removal(state.active, idx, state.windows.len());
+        if was_active {
+            state.enter_active();
+        }
+        true
+    }
+
+    /// Returns the windows of this group in tab order
+    pub fn windows(&self) -> Vec<Window> {
+        self.state().windows.clone()
+    }
+
+    /// Returns whether the window is part of this group
+    pub fn contains(&self, window: &Window) -> bool {
+        self.state().windows.contains(window)
+    }
+
+    /// Returns the number of windows in this group
+    pub fn len(&self) -> usize {
+        self.state().windows.len()
+    }
+
+    /// Returns whether the group contains no windows
+    pub fn is_empty(&self) -> bool {
+        self.state().windows.is_empty()
+    }
+
+    /// Returns the active window, if the group is not empty
+    pub fn active(&self) -> Option<Window> {
+        self.state().active().cloned()
+    }
+
+    /// Makes the given window the active window
+    ///
+    /// Returns `false` if the window is not part of the group or already active.
+    pub fn set_active(&self, window: &Window) -> bool {
+        let mut state = self.state();
+        match state.windows.iter().position(|w| w == window) {
+            Some(idx) => state.switch_to(idx),
+            None => false,
+        }
+    }
+
+    /// Makes the next window the active window, wrapping around, and returns it
+    pub fn activate_next(&self) -> Option<Window> {
+        let mut state = self.state();
+        if state.windows.is_empty() {
+            return None;
+        }
+        let idx = (state.active + 1) % state.windows.len();
+        state.switch_to(idx);
+        state.active().cloned()
+    }
+
+    /// Makes the previous window the active window, wrapping around, and returns it
+    pub fn activate_previous(&self) -> Option<Window> {
+        let mut state = self.state();
+        if state.windows.is_empty() {
+            return None;
+        }
+        let idx = (state.active + state.windows.len() - 1) % state.windows.len();
+        state.switch_to(idx);
+        state.active().cloned()
+    }
+
+    /// Returns the layout of the tabs
+    pub fn layout(&self) -> GroupLayout {
+        self.state().layout
+    }
+
+    /// Sets the layout of the tabs
+    ///
+    /// Changing the layout might change the space available to the windows,
+    /// so [`WindowGroup::configure`] should be called afterwards.
+    pub fn set_layout(&self, layout: GroupLayout) {
+        self.state().layout = layout;
+    }
+
+    /// Returns the height of a single row of the tab bar
+    pub fn tab_bar_height(&self) -> i32 {
+        self.state().tab_bar_height
+    }
+
+    /// Sets the height of a single row of the tab bar
+    ///
+    /// Defaults to [`DEFAULT_TAB_BAR_HEIGHT`]. Setting it to 0 hides the tab bar.
+    pub fn set_tab_bar_height(&self, height: i32) {
+        self.state().tab_bar_height = height.max(0);
+    }
+
+    /// Returns the total height reserved for the tab bar
+    ///
+    /// For [`GroupLayout::Stacked`] this grows with the number of windows.
+    pub fn bar_height(&self) -> i32 {
+        self.state().bar_height()
+    }
+
+    /// Returns the information necessary to render the tab bar
+    ///
+    /// Tabs are returned in tab order, their geometry is relative to the geometry of the group.
+    pub fn tabs(&self) -> Vec<TabInfo> {
+        let state = self.state();
+        let width = state.active().map(|w| w.geometry().size.w).unwrap_or(0);
+        let count = state.windows.len() as i32;
+
+        state
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(idx, window)| TabInfo {
+                window: window.clone(),
+                title: window.title(),
+                app_id: window.app_id(),
+                active: idx == state.active,
+                geometry: tab_geometry(state.layout, width, state.tab_bar_height, count, idx as i32),
+            })
+            .collect()
+    }
+
+    /// Returns the window whose tab is under the given point
+    ///
+    /// - `point` should be relative to the geometry of the group.
+    pub fn tab_under<P: Into<Point<f64, Logical>>>(&self, point: P) -> Option<Window> {
+        let point = point.into();
+        self.tabs()
+            .into_iter()
+            .find(|tab| tab.geometry.to_f64().contains(point))
+            .map(|tab| tab.window)
+    }
+
+    /// Requests a new size for all windows of the group
+    ///
+    /// `size` is the size of the whole group including the tab bar.
+    /// Configures are only sent to wayland windows, which already received their initial configure.
+    pub fn configure(&self, size: Size<i32, Logical>) {
+        let state = self.state();
+        let size = Size::from((size.w, (size.h - state.bar_height()).max(1)));
+
+        for window in &state.windows {
+            match window.underlying_surface() {
+                WindowSurface::Wayland(toplevel) => {
+                    toplevel.with_pending_state(|state| state.size = Some(size));
+                    if toplevel.is_initial_configure_sent() {
+                        toplevel.send_pending_configure();
+                    }
+                }
+                #[cfg(feature = "xwayland")]
+                WindowSurface::X11(surface) => {
+                    let geometry = Rectangle::from_loc_and_size(surface.geometry().loc, size);
+                    let _ = surface.configure(geometry);
+                }
+            }
+        }
+    }
+
+    /// Removes windows that are no longer alive
+    ///
+    /// Returns `true` if any windows were removed. This is automatically called by
+    /// [`SpaceElement::refresh`].
+    pub fn cleanup(&self) -> bool {
+        let dead = self
+            .state()
+            .windows
+            .iter()
+            .filter(|w| !w.alive())
+            .cloned()
+            .collect::<Vec<_>>();
+        for window in &dead {
+            self.remove_window(window);
+        }
+        !dead.is_empty()
+    }
+
+    pub(crate) fn set_activated(&self, activated: bool) {
+        let mut state = self.state();
+        state.activated = activated;
+        if let Some(active) = state.active() {
+            active.set_activated(activated);
+        }
+    }
+
+    pub(crate) fn output_enter(&self, output: &Output, overlap: Rectangle<i32, Logical>) {
+        let mut state = self.state();
+        state
+            .outputs
+            .retain(|(weak, _)| weak.is_alive() && weak != output);
+        state.outputs.push((output.downgrade(), overlap));
+        if let Some(active) = state.active() {
+            active.output_enter(output, overlap);
+        }
+    }
+
+    pub(crate) fn output_leave(&self, output: &Output) {
+        let mut state = self.state();
+        state
+            .outputs
+            .retain(|(weak, _)| weak.is_alive() && weak != output);
+        if let Some(active) = state.active() {
+            active.output_leave(output);
+        }
+    }
+}
+
+// the tabs of a tabbed group share the width without gaps, even if it isn't divisible by their count
+fn tab_geometry(
+    layout: GroupLayout,
+    width: i32,
+    height: i32,
+    count: i32,
+    idx: i32,
+) -> Rectangle<i32, Logical> {
+    match layout {
+        GroupLayout::Tabbed => {
+            let x = width * idx / count;
+            let next_x = width * (idx + 1) / count;
+            Rectangle::from_loc_and_size((x, 0), (next_x - x, height))
+        }
+        GroupLayout::Stacked => Rectangle::from_loc_and_size((0, height * idx), (width, height)),
+    }
+}
+
+// keeps the active window active, or selects its successor if the active window was removed
+fn active_after_removal(active: usize, removed: usize, len: usize) -> usize {
+    if removed < active || active >= len {
+        active.saturating_sub(1)
+    } else {
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{active_after_removal, tab_geometry, GroupLayout};
+    use crate::utils::Rectangle;
+
+    #[test]
+    fn tabs_share_the_group_width() {
+        let tabs = (0..3)
+            .map(|idx| tab_geometry(GroupLayout::Tabbed, 100, 24, 3, idx))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tabs,
+            [
+                Rectangle::from_loc_and_size((0, 0), (33, 24)),
+                Rectangle::from_loc_and_size((33, 0), (33, 24)),
+                Rectangle::from_loc_and_size((66, 0), (34, 24)),
+            ]
+        );
+
+        assert_eq!(
+            tab_geometry(GroupLayout::Stacked, 100, 24, 3, 2),
+            Rectangle::from_loc_and_size((0, 48), (100, 24))
+        );
+    }
+
+    #[test]
+    fn removal_keeps_active_window() {
+        // removing a window before the active one
+        assert_eq!(active_after_removal(2, 0, 2), 1);
+        // removing a window after the active one
+        assert_eq!(active_after_removal(1, 2, 2), 1);
+        // removing the active window selects the next one
+        assert_eq!(active_after_removal(1, 1, 2), 1);
+        // or the previous one, if it was the last window
+        assert_eq!(active_after_removal(2, 2, 2), 1);
+        assert_eq!(active_after_removal(0, 0, 0), 0);
+    }
+}
@@ -1,14 +1,14 @@
 use crate::{
     desktop::{utils::*, PopupManager},
     output::{Output, WeakOutput},
-    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle},
+    utils::{user_data::UserDataMap, IsAlive, Logical, Physical, Point, Rectangle},
     wayland::{
         compositor::{with_states, with_surface_tree_downward, SurfaceData, TraversalAction},
         dmabuf::DmabufFeedback,
         seat::WaylandFocus,
         shell::wlr_layer::{
             Anchor, ExclusiveZone, KeyboardInteractivity, Layer as WlrLayer, LayerSurface as WlrLayerSurface,
-            LayerSurfaceCachedState, LayerSurfaceData,
+            LayerSurfaceCachedState, LayerSurfaceData, Margins,
         },
     },
 };
@@ -34,6 +34,19 @@ pub struct LayerMap {
     layers: IndexSet<LayerSurface>,
     output: WeakOutput,
     zone: Rectangle<i32, Logical>,
+    zone_changed: bool,
+    policy: ExclusiveZonePolicy,
+}
+
+/// Policy for arranging multiple exclusive [`LayerSurface`]s anchored to the same edge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusiveZonePolicy {
+    /// Exclusive zones on the same edge add up, every surface is placed next to the previous ones
+    #[default]
+    Stack,
+    /// Exclusive zones on the same edge overlap, every surface is placed at the edge itself
+    /// and the edge reserves the largest of the zones
+    Overlap,
 }
 
 /// Retrieve a [`LayerMap`] for a given [`Output`].
@@ -64,6 +77,8 @@ pub fn layer_map_for_output(o: &Output) -> MutexGuard<'_, LayerMap> {
                     })
                     .unwrap_or_else(|| (0, 0).into()),
             ),
+            zone_changed: false,
+            policy: ExclusiveZonePolicy::default(),
         })
     });
     userdata.get::<Mutex<LayerMap>>().unwrap().lock().unwrap()
@@ -138,6 +153,44 @@ impl LayerMap {
         self.zone
     }
 
+    /// Return the area of this output, that is not exclusive to any [`LayerSurface`]s, in physical pixels.
+    ///
+    /// With fractional scales the logical zone might not end on whole pixels. The returned area
+    /// is shrunk to the pixels fully contained in the zone, so that content placed into it never
+    /// overlaps an exclusive surface.
+    pub fn non_exclusive_zone_physical(&self) -> Rectangle<i32, Physical> {
+        let scale = self
+            .output()
+            .map(|o| o.current_scale().fractional_scale())
+            .unwrap_or(1.0);
+        let zone = self.zone.to_f64();
+        Rectangle::from_extemities(
+            zone.loc.to_physical(scale).to_i32_ceil(),
+            (zone.loc + zone.size).to_physical(scale).to_i32_floor(),
+        )
+    }
+
+    /// Returns the new non-exclusive zone, if it changed since the last call.
+    ///
+    /// Compositors can use this after [`LayerMap::arrange`] or mapping and unmapping layer surfaces
+    /// to find out, if the usable area of the output changed and windows need to be re-arranged.
+    pub fn take_zone_change(&mut self) -> Option<Rectangle<i32, Logical>> {
+        std::mem::take(&mut self.zone_changed).then_some(self.zone)
+    }
+
+    /// Returns the policy used for overlapping exclusive zones.
+    pub fn exclusive_zone_policy(&self) -> ExclusiveZonePolicy {
+        self.policy
+    }
+
+    /// Sets the policy used for overlapping exclusive zones and re-arranges the layer surfaces.
+    pub fn set_exclusive_zone_policy(&mut self, policy: ExclusiveZonePolicy) {
+        if self.policy != policy {
+            self.policy = policy;
+            self.arrange();
+        }
+    }
+
     /// Returns the geometry of a given mapped [`LayerSurface`].
     ///
     /// If the surface was not previously mapped onto this layer map,
@@ -266,7 +319,7 @@ impl LayerMap {
                     })
                     .unwrap_or_else(|| (0, 0).into()),
             );
-            let mut zone = output_rect;
+            let mut reserved = EdgeReservation::default();
             trace!("Arranging layers into {:?}", output_rect.size);
 
             for layer in self.layers.iter() {
@@ -298,9 +351,21 @@ impl LayerMap {
                     *states.cached_state.get::<LayerSurfaceCachedState>().current()
                 });
 
-                let mut source = match data.exclusive_zone {
-                    ExclusiveZone::Exclusive(_) | ExclusiveZone::Neutral => zone,
-                    ExclusiveZone::DontCare => output_rect,
+                let exclusive = match data.exclusive_zone {
+                    ExclusiveZone::Exclusive(amount) => Some(EdgeReservation::for_layer(
+                        data.anchor,
+                        amount as i32,
+                        &data.margin,
+                    )),
+                    _ => None,
+                };
+
+                let mut source = match (data.exclusive_zone, exclusive) {
+                    (ExclusiveZone::DontCare, _) => output_rect,
+                    (_, Some(own)) if self.policy == ExclusiveZonePolicy::Overlap => {
+                        reserved.without_edges_of(&own).shrink(output_rect)
+                    }
+                    _ => reserved.shrink(output_rect),
                 };
 
                 // adjust the copy rect to account for the margins
@@ -351,47 +416,10 @@ impl LayerMap {
 
                 let location: Point<i32, Logical> = (x, y).into();
 
-                if let ExclusiveZone::Exclusive(amount) = data.exclusive_zone {
-                    match data.anchor {
-                        x if x.contains(Anchor::TOP) && x.contains(Anchor::BOTTOM) => {
-                            zone.size.w -= amount as i32;
-                            if x.contains(Anchor::LEFT) {
-                                zone.loc.x += amount as i32 + data.margin.left;
-                                zone.size.w -= data.margin.left;
-                            }
-                            if x.contains(Anchor::RIGHT) {
-                                zone.size.w -= data.margin.right
-                            }
-                        }
-                        x if x.contains(Anchor::LEFT) && x.contains(Anchor::RIGHT) => {
-                            zone.size.h -= amount as i32;
-                            if x.contains(Anchor::TOP) {
-                                zone.loc.y += amount as i32 + data.margin.top;
-                                zone.size.h -= data.margin.top
-                            }
-                            if x.contains(Anchor::BOTTOM) {
-                                zone.size.h -= data.margin.bottom
-                            }
-                        }
-                        x if x == Anchor::all() => {
-                            zone.size.w = 0;
-                            zone.size.h = 0;
-                        }
-                        x if x.contains(Anchor::LEFT) && !x.contains(Anchor::RIGHT) => {
-                            zone.loc.x += amount as i32 + data.margin.left;
-                            zone.size.w -= amount as i32 + data.margin.left;
-                        }
-                        x if x.contains(Anchor::TOP) && !x.contains(Anchor::BOTTOM) => {
-                            zone.loc.y += amount as i32 + data.margin.top;
-                            zone.size.h -= amount as i32 + data.margin.top;
-                        }
-                        x if x.contains(Anchor::RIGHT) && !x.contains(Anchor::LEFT) => {
-                            zone.size.w -= amount as i32 + data.margin.right;
-                        }
-                        x if x.contains(Anchor::BOTTOM) && !x.contains(Anchor::TOP) => {
-                            zone.size.h -= amount as i32 + data.margin.bottom;
-                        }
-                        _ => {}
+                if let Some(own) = exclusive {
+                    match self.policy {
+                        ExclusiveZonePolicy::Stack => reserved.stack(&own),
+                        ExclusiveZonePolicy::Overlap => reserved.overlap(&own),
                     }
                 }
 
@@ -429,8 +457,12 @@ impl LayerMap {
                 }
             }
 
+            let zone = reserved.shrink(output_rect);
             trace!("Remaining zone {:?}", zone);
-            self.zone = zone;
+            if self.zone != zone {
+                self.zone = zone;
+                self.zone_changed = true;
+            }
         }
 
         changed
@@ -458,6 +490,92 @@ impl LayerMap {
     }
 }
 
+/// Space reserved by exclusive layer surfaces on each edge of an output
+#[derive(Debug, Default, Clone, Copy)]
+struct EdgeReservation {
+    top: i32,
+    bottom: i32,
+    left: i32,
+    right: i32,
+}
+
+impl EdgeReservation {
+    fn for_layer(anchor: Anchor, amount: i32, margin: &Margins) -> Self {
+        let mut reservation = EdgeReservation::default();
+        match anchor {
+            x if x.contains(Anchor::TOP) && x.contains(Anchor::BOTTOM) => {
+                if x.contains(Anchor::LEFT) {
+                    reservation.left = amount + margin.left;
+                    if x.contains(Anchor::RIGHT) {
+                        reservation.right = margin.right;
+                    }
+                } else if x.contains(Anchor::RIGHT) {
+                    reservation.right = amount + margin.right;
+                } else {
+                    reservation.right = amount;
+                }
+            }
+            x if x.contains(Anchor::LEFT) && x.contains(Anchor::RIGHT) => {
+                if x.contains(Anchor::TOP) {
+                    reservation.top = amount + margin.top;
+                    if x.contains(Anchor::BOTTOM) {
+                        reservation.bottom = margin.bottom;
+                    }
+                } else if x.contains(Anchor::BOTTOM) {
+                    reservation.bottom = amount + margin.bottom;
+                } else {
+                    reservation.bottom = amount;
+                }
+            }
+            x if x.contains(Anchor::LEFT) && !x.contains(Anchor::RIGHT) => {
+                reservation.left = amount + margin.left;
+            }
+            x if x.contains(Anchor::TOP) && !x.contains(Anchor::BOTTOM) => {
+                reservation.top = amount + margin.top;
+            }
+            x if x.contains(Anchor::RIGHT) && !x.contains(Anchor::LEFT) => {
+                reservation.right = amount + margin.right;
+            }
+            x if x.contains(Anchor::BOTTOM) && !x.contains(Anchor::TOP) => {
+                reservation.bottom = amount + margin.bottom;
+            }
+            _ => {}
+        }
+        reservation
+    }
+
+    fn shrink(&self, mut rect: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        rect.loc.x += self.left;
+        rect.loc.y += self.top;
+        rect.size.w -= self.left + self.right;
+        rect.size.h -= self.top + self.bottom;
+        rect
+    }
+
+    fn stack(&mut self, other: &Self) {
+        self.top += other.top;
+        self.bottom += other.bottom;
+        self.left += other.left;
+        self.right += other.right;
+    }
+
+    fn overlap(&mut self, other: &Self) {
+        self.top = self.top.max(other.top);
+        self.bottom = self.bottom.max(other.bottom);
+        self.left = self.left.max(other.left);
+        self.right = self.right.max(other.right);
+    }
+
+    fn without_edges_of(&self, other: &Self) -> Self {
+        EdgeReservation {
+            top: if other.top != 0 { 0 } else { self.top },
+            bottom: if other.bottom != 0 { 0 } else { self.bottom },
+            left: if other.left != 0 { 0 } else { self.left },
+            right: if other.right != 0 { 0 } else { self.right },
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct LayerState {
     pub location: Option<Point<i32, Logical>>,
@@ -724,3 +842,93 @@ impl WaylandFocus for LayerSurface {
         Some(Cow::Borrowed(self.0.surface.wl_surface()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{layer_map_for_output, EdgeReservation};
+    use crate::{
+        output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+        utils::Rectangle,
+        wayland::shell::wlr_layer::{Anchor, Margins},
+    };
+
+    #[test]
+    fn exclusive_zone_policies() {
+        let top_bar = EdgeReservation::for_layer(
+            Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
+            30,
+            &Margins {
+                top: 5,
+                ..Default::default()
+            },
+        );
+        let second_bar = EdgeReservation::for_layer(Anchor::TOP, 20, &Margins::default());
+        let dock = EdgeReservation::for_layer(Anchor::LEFT, 50, &Margins::default());
+        let output = Rectangle::from_loc_and_size((0, 0), (1000, 1000));
+
+        let mut stacked = EdgeReservation::default();
+        let mut overlapping = EdgeReservation::default();
+        for layer in [&top_bar, &second_bar, &dock] {
+            stacked.stack(layer);
+            overlapping.overlap(layer);
+        }
+        assert_eq!(
+            stacked.shrink(output),
+            Rectangle::from_loc_and_size((50, 55), (950, 945))
+        );
+        assert_eq!(
+            overlapping.shrink(output),
+            Rectangle::from_loc_and_size((50, 35), (950, 965))
+        );
+
+        // overlapping surfaces are placed at their edge, ignoring the zones of the same edge
+        assert_eq!(
+            overlapping.without_edges_of(&second_bar).shrink(output),
+            Rectangle::from_loc_and_size((50, 0), (950, 1000))
+        );
+    }
+
+    #[test]
+    fn fractional_zone_changes() {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: (1500, 1000).into(),
+                refresh: 60_000,
+            }),
+            None,
+            Some(Scale::Fractional(1.5)),
+            None,
+        );
+
+        let mut map = layer_map_for_output(&output);
+        map.arrange();
+        assert!(map.take_zone_change().is_none());
+        // the logical zone is rounded up, the physical zone only contains whole pixels
+        assert_eq!(
+            map.non_exclusive_zone(),
+            Rectangle::from_loc_and_size((0, 0), (1000, 667))
+        );
+        assert_eq!(
+            map.non_exclusive_zone_physical(),
+            Rectangle::from_loc_and_size((0, 0), (1500, 1000))
+        );
+
+        output.change_current_state(None, None, Some(Scale::Fractional(1.25)), None);
+        map.arrange();
+        assert_eq!(
+            map.take_zone_change(),
+            Some(Rectangle::from_loc_and_size((0, 0), (1200, 800)))
+        );
+        assert!(map.take_zone_change().is_none());
+    }
+}
@@ -21,12 +21,16 @@ use crate::{
         SeatHandler,
     },
     utils::{DeadResource, IsAlive, Logical, Point, Serial, SERIAL_COUNTER},
-    wayland::{compositor::get_role, seat::WaylandFocus, shell::xdg::XDG_POPUP_ROLE},
+    wayland::{
+        compositor::{get_parent, get_role, with_states},
+        seat::WaylandFocus,
+        shell::xdg::{XdgPopupSurfaceData, XDG_POPUP_ROLE},
+    },
 };
 
 use thiserror::Error;
 
-use super::{PopupKind, PopupManager};
+use super::{find_popup_root_surface, PopupKind, PopupManager};
 
 /// Defines the possible errors that
 /// can be returned from [`PopupManager::grab_popup`]
@@ -152,6 +156,62 @@ impl PopupGrabInner {
         Ok(guard.serial.replace(serial))
     }
 
+    pub(super) fn contains_surface(&self, surface: &WlSurface) -> bool {
+        let grabs = {
+            let guard = self.internal.lock().unwrap();
+            guard
+                .active_grabs
+                .iter()
+                .filter(|p| p.alive())
+                .map(|p| p.wl_surface().clone())
+                .collect::<Vec<_>>()
+        };
+        if grabs.is_empty() {
+            return false;
+        }
+
+        // Walk up the subsurface and popup parents until we either
+        // hit one of the grabbed popups or a non-popup surface
+        let mut surface = surface.clone();
+        loop {
+            while let Some(parent) = get_parent(&surface) {
+                surface = parent;
+            }
+            if grabs.contains(&surface) {
+                return true;
+            }
+            if get_role(&surface) != Some(XDG_POPUP_ROLE) {
+                return false;
+            }
+            let parent = with_states(&surface, |states| {
+                states
+                    .data_map
+                    .get::<XdgPopupSurfaceData>()
+                    .and_then(|data| data.lock().unwrap().parent.clone())
+            });
+            match parent {
+                Some(parent) => surface = parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub(super) fn dismiss_outside(&self, root: &WlSurface, focus: Option<&WlSurface>) -> bool {
+        if !self.active() || focus.map(|s| self.contains_surface(s)).unwrap_or(false) {
+            return false;
+        }
+        self.ungrab(root, PopupUngrabStrategy::All);
+        true
+    }
+
+    pub(super) fn root_surface(&self) -> Option<WlSurface> {
+        let guard = self.internal.lock().unwrap();
+        guard
+            .active_grabs
+            .first()
+            .and_then(|p| find_popup_root_surface(p).ok())
+    }
+
     fn ungrab(&self, root: &WlSurface, strategy: PopupUngrabStrategy) -> Option<WlSurface> {
         let mut guard = self.internal.lock().unwrap();
         let dismissed = match strategy {
@@ -358,6 +418,32 @@ where
             .or(Some(root_surface.into_owned()))
     }
 
+    /// Returns whether a surface is part of the popups of this grab
+    ///
+    /// This is the case for the grabbed popups, any popups created on top of them
+    /// and the subsurfaces of those.
+    pub fn contains_surface(&self, surface: &WlSurface) -> bool {
+        self.toplevel_grab.contains_surface(surface)
+    }
+
+    /// Dismisses all popups of this grab, if an input event happened outside of them
+    ///
+    /// `focus` is the surface under a pointer button press or touch down event, if any.
+    /// If it is not part of the grabbed popups (see [`PopupGrab::contains_surface`]), the
+    /// whole popup chain is dismissed, sending `popup_done` to the topmost popup first.
+    ///
+    /// Returns `true` if the popups were dismissed. The pointer and keyboard grabs
+    /// end once they notice the popup grab [has ended](PopupGrab::has_ended).
+    ///
+    /// Unlike [`PopupPointerGrab`], which only dismisses popups when a surface of a different client is
+    /// clicked, this also dismisses popups when clicking other surfaces of the same client.
+    pub fn dismiss_if_outside(&mut self, focus: Option<&WlSurface>) -> bool {
+        let Some(root) = self.root.wl_surface() else {
+            return false;
+        };
+        self.toplevel_grab.dismiss_outside(&root, focus)
+    }
+
     /// Convenience method for getting a [`KeyboardGrabStartData`] for this grab.
     ///
     /// The focus of the [`KeyboardGrabStartData`] will always be the root
@@ -185,6 +185,33 @@ impl PopupManager {
         })
     }
 
+    /// Recomputes the geometry of the reactive popups of a given toplevel surface.
+    ///
+    /// This should be called whenever the conditions used for constraining the popups changed,
+    /// e.g. when the toplevel was moved or resized. `unconstrain` is called for every configured
+    /// popup that requested reactive positioning and is expected to update the pending geometry of the
+    /// popup, for example using [`PopupKind::unconstrain`]. Popups whose geometry changed receive a new
+    /// `xdg_popup.configure` followed by a `xdg_surface.configure` event.
+    pub fn reconstrain_reactive_popups<F>(surface: &WlSurface, mut unconstrain: F)
+    where
+        F: FnMut(&PopupKind),
+    {
+        for (popup, _) in PopupManager::popups_for_surface(surface) {
+            let PopupKind::Xdg(ref xdg) = popup else {
+                continue;
+            };
+            let reactive = xdg.with_pending_state(|state| state.positioner.reactive);
+            if !reactive || !xdg.is_initial_configure_sent() {
+                continue;
+            }
+
+            unconstrain(&popup);
+            if let Err(err) = xdg.send_pending_configure() {
+                trace!("Failed to reconstrain popup {:?}: {}", popup, err);
+            }
+        }
+    }
+
     /// Dismiss the `popup` associated with the `surface.
     pub fn dismiss_popup(surface: &WlSurface, popup: &PopupKind) -> Result<(), DeadResource> {
         if !surface.alive() {
@@ -200,6 +227,24 @@ impl PopupManager {
         Ok(())
     }
 
+    /// Dismisses all popup grabs an input event happened outside of
+    ///
+    /// `focus` is the surface under a pointer button press or touch down event, if any.
+    /// Every active grab, whose popups do not contain `focus`, is ended and its popup chain is
+    /// dismissed with `popup_done` being sent to the topmost popup first.
+    /// See [`PopupGrab::dismiss_if_outside`] for dismissing a single grab.
+    ///
+    /// Returns `true` if any popups were dismissed.
+    pub fn dismiss_grabs_outside(&mut self, focus: Option<&WlSurface>) -> bool {
+        let mut dismissed = false;
+        for grab in &self.popup_grabs {
+            if let Some(root) = grab.root_surface() {
+                dismissed |= grab.dismiss_outside(&root, focus);
+            }
+        }
+        dismissed
+    }
+
     /// Needs to be called periodically (but not necessarily frequently)
     /// to cleanup internal resources.
     pub fn cleanup(&mut self) {
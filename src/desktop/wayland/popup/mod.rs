@@ -6,11 +6,12 @@ pub use manager::*;
 use wayland_server::protocol::wl_surface::WlSurface;
 
 use crate::{
-    utils::{IsAlive, Logical, Point, Rectangle},
+    desktop::utils::bbox_from_surface_tree,
+    utils::{IsAlive, Logical, Point, Rectangle, Serial},
     wayland::{
         compositor::with_states,
         input_method,
-        shell::xdg::{self, SurfaceCachedState, XdgPopupSurfaceData},
+        shell::xdg::{self, PopupConfigureError, SurfaceCachedState, XdgPopupSurfaceData},
     },
 };
 
@@ -73,6 +74,95 @@ impl PopupKind {
         }
     }
 
+    /// Fits this popup into the `target` rectangle according to its positioner rules
+    ///
+    /// `target` is usually the work area of the output(s) the popup is displayed on, relative to
+    /// the window geometry of the popup's root surface (see [`find_popup_root_surface`]). The adjusted
+    /// geometry is computed by [`PositionerState::get_unconstrained_geometry`](xdg::PositionerState::get_unconstrained_geometry)
+    /// and stored in the pending state of the popup.
+    ///
+    /// Input method popups are placed below the text input cursor rectangle, or above it if there is
+    /// not enough space below, and slid horizontally to stay inside `target`.
+    pub fn unconstrain(&self, target: Rectangle<i32, Logical>) {
+        match *self {
+            PopupKind::Xdg(ref popup) => {
+                let mut target = target;
+                target.loc -= get_popup_toplevel_coords(self);
+                popup.with_pending_state(|state| {
+                    state.geometry = state.positioner.get_unconstrained_geometry(target);
+                });
+            }
+            PopupKind::InputMethod(ref popup) => {
+                let Some(parent) = popup.get_parent() else {
+                    return;
+                };
+
+                // The location of input method popups is relative to the parent surface,
+                // while target is relative to its window geometry.
+                let mut target = target;
+                target.loc += parent.location.loc;
+
+                let cursor = popup.text_input_rectangle();
+                let size = bbox_from_surface_tree(popup.wl_surface(), (0, 0)).size;
+
+                let mut location = Point::from((cursor.loc.x, cursor.loc.y + cursor.size.h));
+                let above = cursor.loc.y - size.h;
+                if location.y + size.h > target.loc.y + target.size.h && above >= target.loc.y {
+                    location.y = above;
+                }
+                location.x = location
+                    .x
+                    .min(target.loc.x + target.size.w - size.w)
+                    .max(target.loc.x);
+
+                popup.set_location(location);
+            }
+        }
+    }
+
+    /// Fits this popup into the `target` rectangle and sends the corrected configure
+    ///
+    /// See [`PopupKind::unconstrain`] for the meaning of `target`.
+    ///
+    /// A configure is only sent if the initial configure was already sent and the geometry changed,
+    /// otherwise the adjusted geometry will be part of the initial configure.
+    pub fn send_unconstrained_configure(
+        &self,
+        target: Rectangle<i32, Logical>,
+    ) -> Result<Option<Serial>, PopupConfigureError> {
+        self.unconstrain(target);
+        match *self {
+            PopupKind::Xdg(ref popup) if popup.is_initial_configure_sent() => popup.send_pending_configure(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Handles a `xdg_popup.reposition` request
+    ///
+    /// Replaces the positioner of this popup, fits the new geometry into `target` (see
+    /// [`PopupKind::unconstrain`]) and sends the `xdg_popup.repositioned` event carrying `token`
+    /// followed by the new configure.
+    ///
+    /// Returns `None` for input method popups, which cannot be repositioned.
+    pub fn reposition(
+        &self,
+        positioner: xdg::PositionerState,
+        token: u32,
+        target: Rectangle<i32, Logical>,
+    ) -> Option<Serial> {
+        match *self {
+            PopupKind::Xdg(ref popup) => {
+                popup.with_pending_state(|state| {
+                    state.geometry = positioner.get_geometry();
+                    state.positioner = positioner;
+                });
+                self.unconstrain(target);
+                Some(popup.send_repositioned(token))
+            }
+            PopupKind::InputMethod(_) => None,
+        }
+    }
+
     fn send_done(&self) {
         match *self {
             PopupKind::Xdg(ref t) => t.send_popup_done(),
@@ -0,0 +1,234 @@
+use std::sync::Mutex;
+
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+use wayland_server::Resource;
+
+use crate::{
+    desktop::{Window, WindowSurface},
+    output::Output,
+    utils::{Logical, Point, Rectangle, Serial},
+    wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceData},
+};
+
+/// Geometry of a [`Window`] before it was maximized or made fullscreen
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreGeometry {
+    /// Geometry of the window in global compositor space
+    pub geometry: Rectangle<i32, Logical>,
+    /// Output the window was on, if any
+    pub output: Option<Output>,
+}
+
+#[derive(Debug, Default)]
+struct RestoreState {
+    maximize: Option<RestoreGeometry>,
+    fullscreen: Option<RestoreGeometry>,
+    pending_location: Option<(Option<Serial>, Point<i32, Logical>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Maximize,
+    Fullscreen,
+}
+
+impl Window {
+    fn with_restore_state<T>(&self, f: impl FnOnce(&mut RestoreState) -> T) -> T {
+        self.user_data()
+            .insert_if_missing_threadsafe(|| Mutex::new(RestoreState::default()));
+        let mut state = self
+            .user_data()
+            .get::<Mutex<RestoreState>>()
+            .unwrap()
+            .lock()
+            .unwrap();
+        f(&mut state)
+    }
+
+    /// Maximizes this window and remembers its previous geometry
+    ///
+    /// - `current` is the current geometry of the window in global compositor space
+    ///   and `output` the output it is currently displayed on.
+    /// - `target` is the maximized geometry, usually the non-exclusive zone of the output.
+    ///
+    /// The previous geometry is only saved, if the window is not already maximized.
+    /// Wayland windows are sent a configure with the maximized state and size, the new location
+    /// should be applied once the client committed a buffer for it, see [`Window::take_pending_location`].
+    /// X11 windows are configured immediately. Returns the serial of the sent configure, if any.
+    pub fn maximize(
+        &self,
+        current: Rectangle<i32, Logical>,
+        output: Option<&Output>,
+        target: Rectangle<i32, Logical>,
+    ) -> Option<Serial> {
+        self.with_restore_state(|state| {
+            state.maximize.get_or_insert_with(|| RestoreGeometry {
+                geometry: current,
+                output: output.cloned(),
+            });
+        });
+        self.apply_mode(Mode::Maximize, true, Some(target), None)
+    }
+
+    /// Unmaximizes this window and restores the geometry it had before [`Window::maximize`]
+    ///
+    /// Returns the restored geometry. Without a saved geometry the size is left to the client.
+    /// Like for [`Window::maximize`] the location should be applied using [`Window::take_pending_location`].
+    pub fn unmaximize_restore(&self) -> Option<RestoreGeometry> {
+        let restore = self.with_restore_state(|state| state.maximize.take());
+        self.apply_mode(
+            Mode::Maximize,
+            false,
+            restore.as_ref().map(|restore| restore.geometry),
+            None,
+        );
+        restore
+    }
+
+    /// Makes this window fullscreen on the given output and remembers its previous geometry
+    ///
+    /// - `current` is the current geometry of the window in global compositor space
+    ///   and `previous_output` the output it is currently displayed on.
+    /// - `target` is the geometry of `output` in global compositor space.
+    ///
+    /// The previous geometry is only saved, if the window is not already fullscreen.
+    /// A maximized window stays maximized and returns to its maximized geometry once it leaves fullscreen.
+    /// See [`Window::maximize`] for how the configure sequence is handled.
+    pub fn fullscreen(
+        &self,
+        current: Rectangle<i32, Logical>,
+        previous_output: Option<&Output>,
+        output: &Output,
+        target: Rectangle<i32, Logical>,
+    ) -> Option<Serial> {
+        self.with_restore_state(|state| {
+            state.fullscreen.get_or_insert_with(|| RestoreGeometry {
+                geometry: current,
+                output: previous_output.cloned(),
+            });
+        });
+        self.apply_mode(Mode::Fullscreen, true, Some(target), Some(output))
+    }
+
+    /// Leaves fullscreen and restores the geometry this window had before [`Window::fullscreen`]
+    ///
+    /// Returns the restored geometry. Without a saved geometry the size is left to the client.
+    pub fn unfullscreen_restore(&self) -> Option<RestoreGeometry> {
+        let restore = self.with_restore_state(|state| state.fullscreen.take());
+        self.apply_mode(
+            Mode::Fullscreen,
+            false,
+            restore.as_ref().map(|restore| restore.geometry),
+            None,
+        );
+        restore
+    }
+
+    /// Returns the geometry saved by [`Window::maximize`], if the window is maximized
+    pub fn maximize_restore_geometry(&self) -> Option<RestoreGeometry> {
+        self.with_restore_state(|state| state.maximize.clone())
+    }
+
+    /// Returns the geometry saved by [`Window::fullscreen`], if the window is fullscreen
+    pub fn fullscreen_restore_geometry(&self) -> Option<RestoreGeometry> {
+        self.with_restore_state(|state| state.fullscreen.clone())
+    }
+
+    /// Returns the location the window should be moved to, once it is ready
+    ///
+    /// Changing the location together with the size before the client has drawn a buffer of the
+    /// new size causes visible glitches. This returns the location set by the last maximize, fullscreen
+    /// or restore request, once the client acknowledged the corresponding configure.
+    /// It should be called after [`Window::on_commit`] in the commit handler of the compositor.
+    pub fn take_pending_location(&self) -> Option<Point<i32, Logical>> {
+        let acked = |serial: Serial| match self.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => with_states(toplevel.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .configure_serial
+                    .map(|acked| acked.is_no_older_than(&serial))
+                    .unwrap_or(false)
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(_) => true,
+        };
+
+        self.with_restore_state(|state| match state.pending_location {
+            Some((serial, location)) if serial.map(acked).unwrap_or(true) => {
+                state.pending_location = None;
+                Some(location)
+            }
+            _ => None,
+        })
+    }
+
+    fn apply_mode(
+        &self,
+        mode: Mode,
+        enable: bool,
+        geometry: Option<Rectangle<i32, Logical>>,
+        output: Option<&Output>,
+    ) -> Option<Serial> {
+        let serial = match self.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                let xdg_state = match mode {
+                    Mode::Maximize => xdg_toplevel::State::Maximized,
+                    Mode::Fullscreen => xdg_toplevel::State::Fullscreen,
+                };
+                let client = toplevel.wl_surface().client();
+                toplevel.with_pending_state(|state| {
+                    if enable {
+                        state.states.set(xdg_state);
+                    } else {
+                        state.states.unset(xdg_state);
+                    }
+                    if mode == Mode::Fullscreen {
+                        state.fullscreen_output = output
+                            .zip(client.as_ref())
+                            .and_then(|(output, client)| output.client_outputs(client).into_iter().next());
+                    }
+                    state.size = geometry.map(|geometry| geometry.size);
+                });
+                if toplevel.is_initial_configure_sent() {
+                    toplevel.send_pending_configure()
+                } else {
+                    None
+                }
+            }
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(surface) => {
+                let _ = match mode {
+                    Mode::Maximize => surface.set_maximized(enable),
+                    Mode::Fullscreen => surface.set_fullscreen(enable),
+                };
+                if let Some(geometry) = geometry {
+                    let _ = surface.configure(geometry);
+                }
+                None
+            }
+        };
+
+        if let Some(geometry) = geometry {
+            let serial = serial.or_else(|| {
+                // the configure might have been sent earlier or is still pending the initial commit
+                self.toplevel().and_then(|toplevel| {
+                    with_states(toplevel.wl_surface(), |states| {
+                        states
+                            .data_map
+                            .get::<XdgToplevelSurfaceData>()
+                            .unwrap()
+                            .lock()
+                            .unwrap()
+                            .configure_serial
+                    })
+                })
+            });
+            self.with_restore_state(|state| state.pending_location = Some((serial, geometry.loc)));
+        }
+        serial
+    }
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, time::Duration};
+
+use wayland_server::protocol::wl_surface::WlSurface;
+
+use crate::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            Kind,
+        },
+        Color32F, ImportAll, Renderer,
+    },
+    desktop::{
+        utils::{send_frames_surface_tree, under_from_surface_tree},
+        WindowSurfaceType,
+    },
+    output::Output,
+    utils::{Logical, Point, Scale, Size},
+    wayland::session_lock::LockSurface,
+};
+
+/// Manages the surfaces of an `ext-session-lock` client
+///
+/// While locked, the lock screen replaces the contents of every output:
+///
+/// - Lock surfaces added with [`LockScreen::add_surface`] are configured to the logical size of their output
+///   and are reconfigured by [`LockScreen::refresh`] whenever the mode, scale or transform of the output changes.
+/// - [`LockScreen::render_elements`] returns the lock surface of an output, or a solid color element
+///   covering the whole output if the client did not provide a surface for it (yet).
+/// - [`LockScreen::surface_under`] only ever returns lock surfaces, so nothing else can receive input
+///   while the session is locked.
+///
+/// ```no_run
+/// # use smithay::desktop::LockScreen;
+/// # use smithay::output::Output;
+/// # use smithay::wayland::session_lock::{LockSurface, SessionLocker};
+/// # let mut lock_screen = LockScreen::new([0.0, 0.0, 0.0, 1.0]);
+/// # let (confirmation, surface, output): (SessionLocker, LockSurface, Output) = unimplemented!();
+/// // in `SessionLockHandler::lock`
+/// lock_screen.lock();
+/// // confirm after a frame was rendered with the lock screen on every output
+/// confirmation.lock();
+///
+/// // in `SessionLockHandler::new_surface`
+/// lock_screen.add_surface(surface, &output);
+///
+/// // in `SessionLockHandler::unlock`
+/// lock_screen.unlock();
+/// ```
+#[derive(Debug)]
+pub struct LockScreen {
+    locked: bool,
+    color: Color32F,
+    surfaces: Vec<(Output, LockSurface)>,
+    backgrounds: HashMap<Output, SolidColorBuffer>,
+}
+
+impl LockScreen {
+    /// Creates a new unlocked lock screen
+    ///
+    /// `color` is used for outputs without a lock surface.
+    pub fn new(color: impl Into<Color32F>) -> Self {
+        LockScreen {
+            locked: false,
+            color: color.into(),
+            surfaces: Vec::new(),
+            backgrounds: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the session is currently locked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Locks the session
+    ///
+    /// Should be called from [`SessionLockHandler::lock`](crate::wayland::session_lock::SessionLockHandler::lock).
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    /// Unlocks the session and forgets all lock surfaces
+    ///
+    /// Should be called from [`SessionLockHandler::unlock`](crate::wayland::session_lock::SessionLockHandler::unlock).
+    pub fn unlock(&mut self) {
+        self.locked = false;
+        for (output, surface) in self.surfaces.drain(..) {
+            output.leave(surface.wl_surface());
+        }
+        self.backgrounds.clear();
+    }
+
+    /// Sets the color used for outputs without a lock surface
+    pub fn set_color(&mut self, color: impl Into<Color32F>) {
+        self.color = color.into();
+        for buffer in self.backgrounds.values_mut() {
+            buffer.set_color(self.color);
+        }
+    }
+
+    /// Adds the lock surface of an output and configures it to the size of the output
+    ///
+    /// Should be called from [`SessionLockHandler::new_surface`](crate::wayland::session_lock::SessionLockHandler::new_surface).
+    /// A previous lock surface of the output is replaced.
+    pub fn add_surface(&mut self, surface: LockSurface, output: &Output) {
+        if let Some(pos) = self.surfaces.iter().position(|(o, _)| o == output) {
+            let (_, old) = self.surfaces.remove(pos);
+            output.leave(old.wl_surface());
+        }
+
+        configure(&surface, output);
+        output.enter(surface.wl_surface());
+        self.surfaces.push((output.clone(), surface));
+    }
+
+    /// Returns the lock surface of an output
+    pub fn surface_for_output(&self, output: &Output) -> Option<&LockSurface> {
+        self.surfaces
+            .iter()
+            .find(|(o, _)| o == output)
+            .map(|(_, surface)| surface)
+    }
+
+    /// Removes dead surfaces and reconfigures surfaces whose output changed
+    ///
+    /// Needs to be called periodically, at best before every wayland socket flush.
+    pub fn refresh(&mut self) {
+        self.surfaces.retain(|(_, surface)| surface.alive());
+        for (output, surface) in &self.surfaces {
+            configure(surface, output);
+        }
+    }
+
+    /// Forgets the lock surface and background of an output, should be called once it is removed
+    pub fn remove_output(&mut self, output: &Output) {
+        self.surfaces.retain(|(o, _)| o != output);
+        self.backgrounds.remove(output);
+    }
+
+    /// Returns the lock surface under a point relative to an output
+    ///
+    /// Returns `None` if the session is not locked or there is no lock surface under the point. While locked
+    /// this should be used instead of any other hit-testing, so no other surface can receive input.
+    pub fn surface_under(
+        &self,
+        output: &Output,
+        point: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        if !self.locked {
+            return None;
+        }
+        let surface = self.surface_for_output(output)?;
+        under_from_surface_tree(surface.wl_surface(), point, (0, 0), WindowSurfaceType::ALL)
+    }
+
+    /// Retrieve the render elements for an output
+    ///
+    /// Returns the elements of the lock surface of the output, or a solid color element covering the
+    /// whole output if there is none. Returns nothing if the session is not locked.
+    /// The elements should be rendered instead of any other content of the output.
+    pub fn render_elements<R, C>(&mut self, renderer: &mut R, output: &Output, alpha: f32) -> Vec<C>
+    where
+        R: Renderer + ImportAll,
+        <R as Renderer>::TextureId: Clone + 'static,
+        C: From<WaylandSurfaceRenderElement<R>> + From<SolidColorRenderElement>,
+    {
+        if !self.locked {
+            return Vec::new();
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        if let Some(surface) = self.surface_for_output(output) {
+            return render_elements_from_surface_tree(
+                renderer,
+                surface.wl_surface(),
+                (0, 0),
+                scale,
+                alpha,
+                Kind::Unspecified,
+            );
+        }
+
+        let size = output_size(output);
+        let color = self.color;
+        let buffer = self
+            .backgrounds
+            .entry(output.clone())
+            .or_insert_with(|| SolidColorBuffer::new(size, color));
+        buffer.resize(size);
+        vec![SolidColorRenderElement::from_buffer(buffer, (0, 0), scale, alpha, Kind::Unspecified).into()]
+    }
+
+    /// Sends frame callbacks to the lock surface of an output
+    pub fn send_frames(&self, output: &Output, time: impl Into<Duration>, throttle: Option<Duration>) {
+        if let Some(surface) = self.surface_for_output(output) {
+            send_frames_surface_tree(surface.wl_surface(), output, time, throttle, |_, _| {
+                Some(output.clone())
+            });
+        }
+    }
+}
+
+fn output_size(output: &Output) -> Size<i32, Logical> {
+    output
+        .current_mode()
+        .map(|mode| {
+            let logical_size = mode
+                .size
+                .to_f64()
+                .to_logical(output.current_scale().fractional_scale())
+                .to_i32_round();
+            output.current_transform().transform_size(logical_size)
+        })
+        .unwrap_or_default()
+}
+
+fn configure(surface: &LockSurface, output: &Output) {
+    let size = output_size(output);
+    surface.with_pending_state(|state| {
+        state.size = Some((size.w.max(0) as u32, size.h.max(0) as u32).into());
+    });
+    surface.send_configure();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::output_size;
+    use crate::{
+        output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+        utils::Transform,
+    };
+
+    #[test]
+    fn lock_surface_size_follows_output() {
+        let output = Output::new(
+            "lock".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        assert_eq!(output_size(&output), (0, 0).into());
+
+        output.change_current_state(
+            Some(Mode {
+                size: (3000, 2000).into(),
+                refresh: 60_000,
+            }),
+            Some(Transform::_90),
+            Some(Scale::Fractional(1.5)),
+            None,
+        );
+        assert_eq!(output_size(&output), (1333, 2000).into());
+    }
+}
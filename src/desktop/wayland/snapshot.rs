@@ -0,0 +1,125 @@
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error as DamageError, OutputDamageTracker},
+            element::{
+                surface::WaylandSurfaceRenderElement,
+                texture::{TextureBuffer, TextureRenderElement},
+                AsRenderElements, Kind,
+            },
+            ImportAll, Offscreen, Renderer, Texture,
+        },
+    },
+    desktop::Window,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+/// A snapshot of the contents of a [`Window`]
+///
+/// A snapshot holds a copy of everything a window would have rendered at the time it was taken,
+/// including subsurfaces and popups tracked by the [`PopupManager`](crate::desktop::PopupManager).
+/// It stays renderable after the client surfaces are destroyed and is therefore useful for
+/// close and open animations.
+///
+/// Use [`Window::snapshot`] to create one.
+#[derive(Debug, Clone)]
+pub struct WindowSnapshot<T> {
+    buffer: TextureBuffer<T>,
+    bbox: Rectangle<i32, Logical>,
+    geometry: Rectangle<i32, Logical>,
+}
+
+impl<T> WindowSnapshot<T> {
+    /// Returns the bounding box of the captured contents relative to the window location
+    ///
+    /// See [`Window::bbox_with_popups`].
+    pub fn bbox(&self) -> Rectangle<i32, Logical> {
+        self.bbox
+    }
+
+    /// Returns the geometry of the window at the time the snapshot was taken
+    pub fn geometry(&self) -> Rectangle<i32, Logical> {
+        self.geometry
+    }
+
+    /// Returns the underlying texture buffer of this snapshot
+    pub fn texture_buffer(&self) -> &TextureBuffer<T> {
+        &self.buffer
+    }
+}
+
+impl<T: Texture + Clone> WindowSnapshot<T> {
+    /// Creates a render element for this snapshot
+    ///
+    /// `location` is the location the window would be rendered at, like in
+    /// [`AsRenderElements::render_elements`] for [`Window`].
+    pub fn render_element(
+        &self,
+        location: Point<i32, Physical>,
+        scale: impl Into<Scale<f64>>,
+        alpha: f32,
+    ) -> TextureRenderElement<T> {
+        let location = location + self.bbox.loc.to_physical_precise_round(scale);
+        TextureRenderElement::from_texture_buffer(
+            location.to_f64(),
+            &self.buffer,
+            Some(alpha),
+            None,
+            None,
+            Kind::Unspecified,
+        )
+    }
+}
+
+impl Window {
+    /// Renders the current contents of this window into an offscreen texture
+    ///
+    /// The contents are rendered at the integer buffer `scale`, usually the ceiled scale of the
+    /// output the window is displayed on. The resulting [`WindowSnapshot`] keeps a reference to the
+    /// texture and can be rendered even after the window is gone.
+    ///
+    /// Note: This binds the renderer to a new buffer and unbinds it afterwards,
+    /// so it should not be called while another target is bound.
+    #[profiling::function]
+    pub fn snapshot<R>(
+        &self,
+        renderer: &mut R,
+        scale: i32,
+    ) -> Result<WindowSnapshot<R::TextureId>, DamageError<R>>
+    where
+        R: Renderer + ImportAll + Offscreen<<R as Renderer>::TextureId>,
+        <R as Renderer>::TextureId: Texture + Clone + 'static,
+    {
+        let bbox = self.bbox_with_popups();
+        let geometry = self.geometry();
+
+        let size = Size::from((bbox.size.w.max(1), bbox.size.h.max(1)));
+        let buffer_size = size.to_buffer(scale, Transform::Normal);
+        let texture = renderer
+            .create_buffer(Fourcc::Abgr8888, buffer_size)
+            .map_err(DamageError::Rendering)?;
+
+        let location = bbox.loc.upscale(-1).to_physical(scale);
+        let elements: Vec<WaylandSurfaceRenderElement<R>> =
+            AsRenderElements::<R>::render_elements(self, renderer, location, Scale::from(scale as f64), 1.0);
+
+        let mut damage_tracker =
+            OutputDamageTracker::new(size.to_physical(scale), scale as f64, Transform::Normal);
+        let res = damage_tracker.render_output_with(
+            renderer,
+            texture.clone(),
+            0,
+            &elements,
+            [0.0, 0.0, 0.0, 0.0].into(),
+        );
+        renderer.unbind().map_err(DamageError::Rendering)?;
+        res?;
+
+        Ok(WindowSnapshot {
+            buffer: TextureBuffer::from_texture(renderer, texture, scale, Transform::Normal, None),
+            bbox,
+            geometry,
+        })
+    }
+}
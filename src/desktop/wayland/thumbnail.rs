@@ -0,0 +1,304 @@
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error as DamageError, OutputDamageTracker},
+            element::{
+                surface::WaylandSurfaceRenderElement,
+                texture::{TextureBuffer, TextureRenderElement},
+                AsRenderElements, Kind, RenderElement,
+            },
+            ImportAll, Offscreen, Renderer, Texture,
+        },
+    },
+    desktop::Window,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+#[derive(Debug)]
+struct ThumbnailState<T> {
+    texture: T,
+    buffer: TextureBuffer<T>,
+    damage_tracker: OutputDamageTracker,
+    geometry: Rectangle<i32, Logical>,
+    size: Size<i32, Logical>,
+    scale: i32,
+}
+
+/// A scaled down rendering of a [`Window`]
+///
+/// The thumbnail fits the window geometry into a requested size while preserving its aspect ratio.
+/// Its contents are kept in an offscreen texture, which is only redrawn by [`WindowThumbnail::update`]
+/// if the window was damaged since the last update, making it cheap to display many thumbnails at once,
+/// e.g. in alt-tab switchers or overview grids.
+#[derive(Debug)]
+pub struct WindowThumbnail<T> {
+    window: Window,
+    max_size: Size<i32, Logical>,
+    scale: i32,
+    state: Option<ThumbnailState<T>>,
+}
+
+impl<T> WindowThumbnail<T> {
+    /// Creates a new thumbnail for a window
+    ///
+    /// - `max_size` is the size the window is fitted into
+    /// - `scale` is the integer buffer scale of the texture, usually the ceiled scale of the output
+    ///   the thumbnail is displayed on
+    ///
+    /// The thumbnail is empty until [`WindowThumbnail::update`] is called.
+    pub fn new(window: Window, max_size: impl Into<Size<i32, Logical>>, scale: i32) -> Self {
+        WindowThumbnail {
+            window,
+            max_size: max_size.into(),
+            scale: scale.max(1),
+            state: None,
+        }
+    }
+
+    /// Returns the window of this thumbnail
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /// Returns the size the window is fitted into
+    pub fn max_size(&self) -> Size<i32, Logical> {
+        self.max_size
+    }
+
+    /// Sets the size the window is fitted into
+    ///
+    /// This invalidates the cached contents.
+    pub fn set_max_size(&mut self, max_size: impl Into<Size<i32, Logical>>) {
+        let max_size = max_size.into();
+        if self.max_size != max_size {
+            self.max_size = max_size;
+            self.state = None;
+        }
+    }
+
+    /// Sets the buffer scale of the thumbnail
+    ///
+    /// This invalidates the cached contents.
+    pub fn set_scale(&mut self, scale: i32) {
+        let scale = scale.max(1);
+        if self.scale != scale {
+            self.scale = scale;
+            self.state = None;
+        }
+    }
+
+    /// Drops the cached contents, forcing a full redraw on the next update
+    pub fn invalidate(&mut self) {
+        self.state = None;
+    }
+
+    /// Returns the size of the thumbnail, if it was rendered
+    ///
+    /// The size is at most [`WindowThumbnail::max_size`] and has the aspect ratio of the window geometry.
+    pub fn size(&self) -> Option<Size<i32, Logical>> {
+        self.state.as_ref().map(|state| state.size)
+    }
+}
+
+impl<T: Texture + Clone + 'static> WindowThumbnail<T> {
+    /// Creates a render element for this thumbnail, if it was rendered
+    ///
+    /// `location` is the location of the top-left corner of the thumbnail.
+    /// The element keeps its identity between updates without damage.
+    pub fn render_element(
+        &self,
+        location: Point<i32, Physical>,
+        alpha: f32,
+    ) -> Option<TextureRenderElement<T>> {
+        self.state.as_ref().map(|state| {
+            TextureRenderElement::from_texture_buffer(
+                location.to_f64(),
+                &state.buffer,
+                Some(alpha),
+                None,
+                None,
+                Kind::Unspecified,
+            )
+        })
+    }
+
+    /// Renders the window into the thumbnail texture, if necessary
+    ///
+    /// The texture is recreated if the window geometry changed, otherwise only damaged regions are
+    /// redrawn. Returns `true` if the contents changed.
+    ///
+    /// Note: This binds the renderer to the thumbnail texture and unbinds it afterwards,
+    /// so it should not be called while another target is bound.
+    #[profiling::function]
+    pub fn update<R>(&mut self, renderer: &mut R) -> Result<bool, DamageError<R>>
+    where
+        R: Renderer<TextureId = T> + ImportAll + Offscreen<T>,
+    {
+        let geometry = self.window.geometry();
+        if geometry.is_empty() || self.max_size.is_empty() {
+            let changed = self.state.is_some();
+            self.state = None;
+            return Ok(changed);
+        }
+
+        let (size, render_scale) = fit_into(geometry.size, self.max_size, self.scale);
+        if self
+            .state
+            .as_ref()
+            .map(|state| state.geometry.size != geometry.size || state.size != size)
+            .unwrap_or(true)
+        {
+            self.state = Some(ThumbnailState::new(
+                renderer,
+                geometry,
+                size,
+                self.scale,
+                render_scale,
+            )?);
+        }
+        let state = self.state.as_mut().unwrap();
+        state.geometry = geometry;
+
+        let location = geometry
+            .loc
+            .upscale(-1)
+            .to_f64()
+            .to_physical(render_scale)
+            .to_i32_round();
+        let elements: Vec<WaylandSurfaceRenderElement<R>> =
+            AsRenderElements::<R>::render_elements(&self.window, renderer, location, render_scale, 1.0);
+        state.render(renderer, &elements)
+    }
+}
+
+// Returns the logical size of the thumbnail and the scale to render the window at
+fn fit_into(
+    geometry: Size<i32, Logical>,
+    max_size: Size<i32, Logical>,
+    scale: i32,
+) -> (Size<i32, Logical>, Scale<f64>) {
+    let factor = f64::min(
+        max_size.w as f64 / geometry.w as f64,
+        max_size.h as f64 / geometry.h as f64,
+    );
+    let size = Size::<i32, Logical>::from((
+        ((geometry.w as f64 * factor).round() as i32).max(1),
+        ((geometry.h as f64 * factor).round() as i32).max(1),
+    ));
+    (size, Scale::from(factor * scale as f64))
+}
+
+impl<T: Texture + Clone + 'static> ThumbnailState<T> {
+    fn new<R>(
+        renderer: &mut R,
+        geometry: Rectangle<i32, Logical>,
+        size: Size<i32, Logical>,
+        scale: i32,
+        render_scale: Scale<f64>,
+    ) -> Result<Self, DamageError<R>>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+    {
+        let texture = renderer
+            .create_buffer(Fourcc::Abgr8888, size.to_buffer(scale, Transform::Normal))
+            .map_err(DamageError::Rendering)?;
+        Ok(ThumbnailState {
+            texture: texture.clone(),
+            buffer: TextureBuffer::from_texture(renderer, texture, scale, Transform::Normal, None),
+            // the elements are scaled down while rendering, so the damage tracker has to use
+            // the same scale to calculate their geometry
+            damage_tracker: OutputDamageTracker::new(
+                size.to_physical(scale),
+                render_scale,
+                Transform::Normal,
+            ),
+            geometry,
+            size,
+            scale,
+        })
+    }
+
+    fn render<R, E>(&mut self, renderer: &mut R, elements: &[E]) -> Result<bool, DamageError<R>>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+        E: RenderElement<R>,
+    {
+        // the texture keeps its contents between updates, so it always has an age of 1
+        let res = self
+            .damage_tracker
+            .render_output_with(
+                renderer,
+                self.texture.clone(),
+                1,
+                elements,
+                [0.0, 0.0, 0.0, 0.0].into(),
+            )
+            .map(|res| res.damage.is_some());
+        renderer.unbind().map_err(DamageError::Rendering)?;
+        let damaged = res?;
+
+        if damaged {
+            // a new buffer gets a new id, so users of the render element pick up the change
+            self.buffer = TextureBuffer::from_texture(
+                renderer,
+                self.texture.clone(),
+                self.scale,
+                Transform::Normal,
+                None,
+            );
+        }
+
+        Ok(damaged)
+    }
+}
+
+#[cfg(all(test, feature = "renderer_test"))]
+mod tests {
+    use super::{fit_into, ThumbnailState};
+    use crate::{
+        backend::{
+            allocator::Fourcc,
+            renderer::{
+                element::{
+                    memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                    Element, Kind,
+                },
+                test::DummyRenderer,
+            },
+        },
+        utils::{Physical, Rectangle, Scale, Size, Transform},
+    };
+
+    #[test]
+    fn window_is_scaled_down() {
+        let mut renderer = DummyRenderer::new();
+        let geometry = Rectangle::from_loc_and_size((0, 0), (400, 300));
+        let (size, render_scale) = fit_into(geometry.size, (100, 100).into(), 2);
+        assert_eq!(size, (100, 75).into());
+
+        let mut state = ThumbnailState::new(&mut renderer, geometry, size, 2, render_scale).unwrap();
+        // stands in for a window surface, which is sized by the scale it is rendered at
+        let buffer = MemoryRenderBuffer::new(Fourcc::Argb8888, (400, 300), 1, Transform::Normal, None);
+        let element = MemoryRenderBufferRenderElement::from_buffer(
+            &mut renderer,
+            (0.0, 0.0),
+            &buffer,
+            None,
+            None,
+            None,
+            Kind::Unspecified,
+        )
+        .unwrap();
+
+        // the element covers exactly the texture of the thumbnail
+        let (output_size, scale, _) =
+            <(Size<i32, Physical>, Scale<f64>, Transform)>::try_from(state.damage_tracker.mode()).unwrap();
+        assert_eq!(output_size, (200, 150).into());
+        assert_eq!(
+            element.geometry(scale),
+            Rectangle::from_loc_and_size((0, 0), (200, 150))
+        );
+        assert!(state.render(&mut renderer, &[element]).unwrap());
+    }
+}
@@ -3,7 +3,8 @@
 use crate::{
     backend::renderer::{
         element::{
-            PrimaryScanoutOutput, RenderElementPresentationState, RenderElementState, RenderElementStates,
+            visible_area_primary_scanout_output_compare, Id, PrimaryScanoutOutput,
+            RenderElementPresentationState, RenderElementState, RenderElementStates,
         },
         utils::{RendererSurfaceState, RendererSurfaceStateUserData},
     },
@@ -209,6 +210,28 @@ where
         .update_from_render_element_states(surface, output, states, compare)
 }
 
+/// Selects the primary scan-out output of every surface by its visible area
+///
+/// The returned closure updates the primary scan-out output of a surface from the `states`
+/// of rendering the given output using [`visible_area_primary_scanout_output_compare`] and returns
+/// the selected output. Passing it to [`send_frames_surface_tree`] after rendering every output
+/// sends the frame callbacks of surfaces spanning multiple outputs only for the output
+/// showing most of them.
+pub fn visible_area_primary_scanout_output<'a>(
+    output: &'a Output,
+    states: &'a RenderElementStates,
+) -> impl Fn(&wl_surface::WlSurface, &SurfaceData) -> Option<Output> + 'a {
+    move |surface, surface_data| {
+        update_surface_primary_scanout_output(
+            surface,
+            output,
+            surface_data,
+            states,
+            visible_area_primary_scanout_output_compare,
+        )
+    }
+}
+
 /// Sends frame callbacks for a surface and its subsurfaces with the given `time`.
 ///
 /// The frame callbacks for a [`WlSurface`](wl_surface::WlSurface) will only be sent if the
@@ -218,6 +241,13 @@ where
 /// throttle threshold. If the threshold is `None` this will never send frame callbacks
 /// for a surface that is not visible. Specifying [`Duration::ZERO`] as the throttle threshold
 /// will always send frame callbacks for non visible surfaces.
+///
+/// Surfaces spanning multiple outputs only receive frame callbacks from their primary
+/// scan-out output, so they are throttled to the refresh rate of a single output.
+/// Pass [`visible_area_primary_scanout_output`] to select the output showing most of the surface.
+///
+/// To skip frame callbacks for surfaces completely occluded by opaque content above them
+/// use [`visible_primary_scanout_output`].
 pub fn send_frames_surface_tree<T, F>(
     surface: &wl_surface::WlSurface,
     output: &Output,
@@ -270,6 +300,37 @@ pub fn send_frames_surface_tree<T, F>(
     );
 }
 
+/// Returns the primary scan-out output of a surface, unless it was occluded on the given output
+///
+/// Surfaces completely covered by opaque elements above them are skipped by the
+/// [`OutputDamageTracker`](crate::backend::renderer::damage::OutputDamageTracker) and reported as
+/// not presented in the returned `states`. Passing the returned closure to [`send_frames_surface_tree`]
+/// skips frame callbacks for those surfaces, they only receive throttled frame callbacks until they
+/// become visible again.
+///
+/// Surfaces without a primary scan-out output are considered to be on the given output.
+pub fn visible_primary_scanout_output<'a>(
+    output: &'a Output,
+    states: &'a RenderElementStates,
+) -> impl Fn(&wl_surface::WlSurface, &SurfaceData) -> Option<Output> + 'a {
+    move |surface, surface_data| {
+        let primary = surface_primary_scanout_output(surface, surface_data);
+        visible_output(surface, output, states, primary)
+    }
+}
+
+fn visible_output(
+    id: impl Into<Id>,
+    output: &Output,
+    states: &RenderElementStates,
+    primary: Option<Output>,
+) -> Option<Output> {
+    match primary {
+        Some(primary) if primary != *output => Some(primary),
+        _ => states.element_was_presented(id).then(|| output.clone()),
+    }
+}
+
 /// Sends dmabuf feedback for a surface and its subsurfaces with the given select function.
 ///
 /// The dmabuf feedback for a [`WlSurface`](wl_surface::WlSurface) will only be sent if the
@@ -506,3 +567,123 @@ impl SurfaceFrameThrottlingState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::visible_output;
+    use crate::{
+        backend::renderer::{
+            damage::OutputDamageTracker,
+            element::{
+                solid::SolidColorRenderElement, visible_area_primary_scanout_output_compare, Element, Id,
+                Kind, PrimaryScanoutOutput, RenderElementState, RenderElementStates,
+            },
+            utils::CommitCounter,
+        },
+        output::{Output, PhysicalProperties, Subpixel},
+        utils::{Rectangle, Transform},
+    };
+
+    #[test]
+    fn occluded_elements_are_not_visible() {
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        let other = Output::new(
+            "other".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        let element = |geometry| {
+            SolidColorRenderElement::new(
+                Id::new(),
+                geometry,
+                CommitCounter::default(),
+                [0.0, 0.0, 0.0, 1.0],
+                Kind::Unspecified,
+            )
+        };
+        // an opaque fullscreen element above a smaller one
+        let fullscreen = element(Rectangle::from_loc_and_size((0, 0), (800, 600)));
+        let below = element(Rectangle::from_loc_and_size((100, 100), (100, 100)));
+
+        let mut damage_tracker = OutputDamageTracker::new((800, 600), 1.0, Transform::Normal);
+        let (_, states) = damage_tracker
+            .damage_output(1, &[fullscreen.clone(), below.clone()])
+            .unwrap();
+        assert_eq!(
+            visible_output(fullscreen.id().clone(), &output, &states, None),
+            Some(output.clone())
+        );
+        assert_eq!(visible_output(below.id().clone(), &output, &states, None), None);
+        assert_eq!(
+            visible_output(below.id().clone(), &output, &states, Some(output.clone())),
+            None
+        );
+        // the primary output of elements on multiple outputs is kept
+        assert_eq!(
+            visible_output(below.id().clone(), &output, &states, Some(other.clone())),
+            Some(other)
+        );
+
+        let (_, states) = damage_tracker
+            .damage_output(1, std::slice::from_ref(&below))
+            .unwrap();
+        assert_eq!(
+            visible_output(below.id().clone(), &output, &states, None),
+            Some(output)
+        );
+    }
+
+    #[test]
+    fn primary_output_follows_visible_area() {
+        let output = |name: &str| {
+            Output::new(
+                name.into(),
+                PhysicalProperties {
+                    size: (0, 0).into(),
+                    subpixel: Subpixel::Unknown,
+                    make: String::new(),
+                    model: String::new(),
+                    serial_number: String::new(),
+                },
+            )
+        };
+        let left = output("left");
+        let right = output("right");
+        let id = Id::new();
+        let states = |visible_area| RenderElementStates {
+            states: [(id.clone(), RenderElementState::rendered(visible_area))].into(),
+        };
+
+        let mut primary = PrimaryScanoutOutput::default();
+        let mut update = |output: &Output, visible_area| {
+            primary.update_from_render_element_states(
+                id.clone(),
+                output,
+                &states(visible_area),
+                visible_area_primary_scanout_output_compare,
+            )
+        };
+        assert_eq!(update(&left, 100), Some(left.clone()));
+        assert_eq!(update(&right, 50), Some(left.clone()));
+        // keeping the current output also keeps its state, so a growing but still
+        // smaller area on the other output does not take over
+        assert_eq!(update(&right, 80), Some(left.clone()));
+        assert_eq!(update(&left, 70), Some(left.clone()));
+        assert_eq!(update(&right, 80), Some(right.clone()));
+        assert_eq!(update(&left, 80), Some(right));
+    }
+}
@@ -3,12 +3,16 @@ use crate::{desktop::space::SpaceElement, xwayland::X11Surface};
 use crate::{
     desktop::{space::RenderZindex, utils::*, PopupManager},
     output::Output,
-    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle},
+    utils::{
+        hook::{Hook, HookId},
+        user_data::UserDataMap,
+        IsAlive, Logical, Point, Rectangle, Serial,
+    },
     wayland::{
         compositor::{with_states, SurfaceData},
         dmabuf::DmabufFeedback,
         seat::WaylandFocus,
-        shell::xdg::{SurfaceCachedState, ToplevelSurface},
+        shell::xdg::{SurfaceCachedState, ToplevelState, ToplevelSurface, XdgToplevelSurfaceData},
     },
 };
 use std::{
@@ -43,9 +47,60 @@ pub(crate) struct WindowInner {
     surface: WindowSurface,
     bbox: Mutex<Rectangle<i32, Logical>>,
     pub(crate) z_index: AtomicU8,
+    configure: Mutex<ConfigureTracker>,
     user_data: UserDataMap,
 }
 
+type ConfigureCommitHook = dyn Fn(&Window, &AckedConfigure) + Send + Sync;
+
+#[derive(Debug, Default)]
+struct ConfigureTracker {
+    serial: Option<Serial>,
+    state: ToplevelState,
+    hooks: Vec<Hook<ConfigureCommitHook>>,
+}
+
+/// A configure acknowledged by the client and applied by a commit
+///
+/// See [`Window::add_configure_commit_hook`].
+#[derive(Debug, Clone)]
+pub struct AckedConfigure {
+    /// Serial of the acked configure
+    pub serial: Serial,
+    /// State of the previously applied configure
+    pub previous: ToplevelState,
+    /// State of the applied configure
+    pub current: ToplevelState,
+}
+
+impl AckedConfigure {
+    /// Returns whether the configured size changed
+    pub fn size_changed(&self) -> bool {
+        self.previous.size != self.current.size
+    }
+
+    /// Returns whether any toplevel states were added or removed
+    pub fn states_changed(&self) -> bool {
+        self.previous.states != self.current.states
+    }
+
+    /// Returns the toplevel states added by this configure
+    pub fn added_states(&self) -> impl Iterator<Item = xdg_toplevel::State> + '_ {
+        self.current
+            .states
+            .iter()
+            .filter(|state| !self.previous.states.contains(*state))
+    }
+
+    /// Returns the toplevel states removed by this configure
+    pub fn removed_states(&self) -> impl Iterator<Item = xdg_toplevel::State> + '_ {
+        self.previous
+            .states
+            .iter()
+            .filter(|state| !self.current.states.contains(*state))
+    }
+}
+
 impl Drop for WindowInner {
     fn drop(&mut self) {
         window_id::remove(self.id);
@@ -119,6 +174,7 @@ impl Window {
             surface: WindowSurface::Wayland(toplevel),
             bbox: Mutex::new(Rectangle::from_loc_and_size((0, 0), (0, 0))),
             z_index: AtomicU8::new(RenderZindex::Shell as u8),
+            configure: Mutex::new(ConfigureTracker::default()),
             user_data: UserDataMap::new(),
         }))
     }
@@ -133,6 +189,7 @@ impl Window {
             surface: WindowSurface::X11(surface),
             bbox: Mutex::new(Rectangle::from_loc_and_size((0, 0), (0, 0))),
             z_index: AtomicU8::new(RenderZindex::Shell as u8),
+            configure: Mutex::new(ConfigureTracker::default()),
             user_data: UserDataMap::new(),
         }))
     }
@@ -218,6 +275,25 @@ impl Window {
         }
     }
 
+    /// Marks this window as suspended, e.g. while it is minimized or fully occluded
+    ///
+    /// Wayland clients are informed via the xdg-toplevel `suspended` state and may stop rendering,
+    /// the state needs to be sent with a configure. X11 windows have no equivalent, so this only
+    /// returns `true` for wayland windows whose state changed.
+    pub fn set_suspended(&self, suspended: bool) -> bool {
+        match &self.0.surface {
+            WindowSurface::Wayland(s) => s.with_pending_state(|state| {
+                if suspended {
+                    state.states.set(xdg_toplevel::State::Suspended)
+                } else {
+                    state.states.unset(xdg_toplevel::State::Suspended)
+                }
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(_) => false,
+        }
+    }
+
     /// Sends the frame callback to all the subsurfaces in this window that requested it
     ///
     /// See [`send_frames_surface_tree`] for more information
@@ -320,11 +396,77 @@ impl Window {
     /// Updates internal values
     ///
     /// Needs to be called whenever the toplevel surface or any unsynchronized subsurfaces of this window are updated
-    /// to correctly update the bounding box of this window.
+    /// to correctly update the bounding box of this window and to invoke the
+    /// [configure commit hooks](Window::add_configure_commit_hook).
     pub fn on_commit(&self) {
         if let Some(surface) = self.wl_surface() {
             *self.0.bbox.lock().unwrap() = bbox_from_surface_tree(&surface, (0, 0));
         }
+        if let Some(acked) = self.take_acked_configure() {
+            let hooks = self
+                .0
+                .configure
+                .lock()
+                .unwrap()
+                .hooks
+                .iter()
+                .map(|hook| hook.cb.clone())
+                .collect::<Vec<_>>();
+            for hook in hooks {
+                hook(self, &acked);
+            }
+        }
+    }
+
+    fn take_acked_configure(&self) -> Option<AckedConfigure> {
+        let toplevel = self.toplevel()?;
+        let (serial, current) = with_states(toplevel.wl_surface(), |states| {
+            let attributes = states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap();
+            (attributes.configure_serial, attributes.current.clone())
+        });
+
+        let mut tracker = self.0.configure.lock().unwrap();
+        let serial = serial?;
+        if tracker.serial == Some(serial) {
+            return None;
+        }
+        tracker.serial = Some(serial);
+        let previous = std::mem::replace(&mut tracker.state, current.clone());
+        Some(AckedConfigure {
+            serial,
+            previous,
+            current,
+        })
+    }
+
+    /// Registers a hook invoked by [`Window::on_commit`] for commits applying a newly acked configure
+    ///
+    /// The hook receives the serial of the acked configure and the configured state before and after it,
+    /// which makes it easy to e.g. finish interactive resizes or start animations once a client
+    /// actually applied a new size or state. Only xdg toplevel windows invoke configure commit hooks.
+    pub fn add_configure_commit_hook<F>(&self, hook: F) -> HookId
+    where
+        F: Fn(&Window, &AckedConfigure) + Send + Sync + 'static,
+    {
+        let hook = Hook::new(Arc::new(hook) as Arc<ConfigureCommitHook>);
+        let id = hook.id;
+        self.0.configure.lock().unwrap().hooks.push(hook);
+        id
+    }
+
+    /// Removes a hook registered with [`Window::add_configure_commit_hook`]
+    pub fn remove_configure_commit_hook(&self, hook_id: HookId) {
+        self.0
+            .configure
+            .lock()
+            .unwrap()
+            .hooks
+            .retain(|hook| hook.id != hook_id);
     }
 
     /// Finds the topmost surface under this point matching the input regions of the surface and returns
@@ -378,6 +520,36 @@ impl Window {
         }
     }
 
+    /// Returns the title of this window, if any
+    pub(crate) fn title(&self) -> Option<String> {
+        match &self.0.surface {
+            WindowSurface::Wayland(s) => with_states(s.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .and_then(|data| data.lock().unwrap().title.clone())
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(s) => Some(s.title()),
+        }
+    }
+
+    /// Returns the app_id of this window, if any
+    ///
+    /// X11 windows have no app_id.
+    pub(crate) fn app_id(&self) -> Option<String> {
+        match &self.0.surface {
+            WindowSurface::Wayland(s) => with_states(s.wl_surface(), |states| {
+                states
+                    .data_map
+                    .get::<XdgToplevelSurfaceData>()
+                    .and_then(|data| data.lock().unwrap().app_id.clone())
+            }),
+            #[cfg(feature = "xwayland")]
+            WindowSurface::X11(_) => None,
+        }
+    }
+
     /// Returns the underlying surface
     pub fn underlying_surface(&self) -> &WindowSurface {
         &self.0.surface
@@ -395,6 +567,13 @@ impl Window {
     }
 }
 
+impl AsRef<Window> for Window {
+    #[inline]
+    fn as_ref(&self) -> &Window {
+        self
+    }
+}
+
 impl WaylandFocus for Window {
     #[inline]
     fn wl_surface(&self) -> Option<Cow<'_, wl_surface::WlSurface>> {
@@ -0,0 +1,851 @@
+//! Helper to manage multiple [`Space`]s per [`Output`].
+//!
+//! A [`Workspaces`] instance tracks an independent list of [`Workspace`]s for every
+//! output added to it. Each workspace is backed by its own [`Space`], only the
+//! currently active workspace of an output has the output mapped and is thus
+//! visible and receives output enter/leave events.
+//!
+//! Element coordinates of all workspaces share the same global coordinate system,
+//! the output is mapped at the same location into every workspace it owns.
+//!
+//! Keyboard focus is tracked per workspace: [`Workspaces::focus_element`] activates an element
+//! and remembers it as the focus of its workspace, switching to that workspace if necessary.
+//! After activating another workspace, [`Workspaces::focus_target`] returns the element
+//! that was focused last on it, which is usually passed to
+//! [`KeyboardHandle::set_focus`](crate::input::keyboard::KeyboardHandle::set_focus).
+//!
+//! Elements in [`StackingLayer::Sticky`](super::space::StackingLayer::Sticky) are shown on every
+//! workspace of their output: activating another workspace moves them over from the previously
+//! active one.
+
+#[cfg(feature = "wayland_frontend")]
+use crate::backend::renderer::ImportAll;
+use crate::{
+    backend::renderer::{
+        element::{AsRenderElements, Wrap},
+        Renderer, Texture,
+    },
+    output::Output,
+    utils::{Logical, Point, Rectangle},
+};
+use tracing::{debug, debug_span};
+
+use super::space::{OutputError, Space, SpaceElement, SpaceRenderElements};
+
+crate::utils::ids::id_gen!(workspace_id);
+
+/// Unique identifier of a [`Workspace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorkspaceId(usize);
+
+/// A single workspace owned by an [`Output`]
+#[derive(Debug)]
+pub struct Workspace<E: SpaceElement> {
+    id: WorkspaceId,
+    name: Option<String>,
+    space: Space<E>,
+    focused: Option<E>,
+}
+
+impl<E: SpaceElement> Drop for Workspace<E> {
+    #[inline]
+    fn drop(&mut self) {
+        workspace_id::remove(self.id.0);
+    }
+}
+
+impl<E: SpaceElement> Workspace<E> {
+    fn new() -> Self {
+        Workspace {
+            id: WorkspaceId(workspace_id::next()),
+            name: None,
+            space: Space::default(),
+            focused: None,
+        }
+    }
+
+    /// Returns the id of this workspace
+    pub fn id(&self) -> WorkspaceId {
+        self.id
+    }
+
+    /// Returns the name of this workspace, if any was set
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Sets the name of this workspace
+    pub fn set_name(&mut self, name: impl Into<Option<String>>) {
+        self.name = name.into();
+    }
+
+    /// Returns the [`Space`] backing this workspace
+    pub fn space(&self) -> &Space<E> {
+        &self.space
+    }
+
+    /// Returns the [`Space`] backing this workspace mutably
+    pub fn space_mut(&mut self) -> &mut Space<E> {
+        &mut self.space
+    }
+}
+
+impl<E: SpaceElement + PartialEq> Workspace<E> {
+    /// Returns the element focused last on this workspace, if it is still mapped
+    pub fn focused(&self) -> Option<&E> {
+        self.focused
+            .as_ref()
+            .filter(|focused| self.space.elements().any(|e| e == *focused))
+    }
+}
+
+#[derive(Debug)]
+struct OutputWorkspaces<E: SpaceElement> {
+    output: Output,
+    location: Point<i32, Logical>,
+    workspaces: Vec<Workspace<E>>,
+    active: usize,
+}
+
+impl<E: SpaceElement> OutputWorkspaces<E> {
+    fn active(&self) -> &Workspace<E> {
+        &self.workspaces[self.active]
+    }
+
+    fn active_mut(&mut self) -> &mut Workspace<E> {
+        &mut self.workspaces[self.active]
+    }
+}
+
+impl<E: SpaceElement + PartialEq> OutputWorkspaces<E> {
+    fn move_sticky_elements(&mut self, from: usize, to: usize, focused: &mut Option<WorkspaceId>) {
+        let workspaces = &mut self.workspaces;
+        let (from, to) = if from < to {
+            let (head, tail) = workspaces.split_at_mut(to);
+            (&mut head[from], &mut tail[0])
+        } else {
+            let (head, tail) = workspaces.split_at_mut(from);
+            (&mut tail[0], &mut head[to])
+        };
+        from.space.move_sticky_elements(&mut to.space);
+
+        // focus follows the element
+        let focus_moved = from
+            .focused
+            .as_ref()
+            .is_some_and(|focused| to.space.element_layer(focused).is_some());
+        if focus_moved {
+            to.focused = from.focused.take();
+            if *focused == Some(from.id) {
+                *focused = Some(to.id);
+            }
+        }
+    }
+}
+
+/// Manages independent sets of [`Workspace`]s for multiple [`Output`]s
+///
+/// Every added output owns at least one workspace and exactly one of them is active at a time.
+#[derive(Debug)]
+pub struct Workspaces<E: SpaceElement> {
+    outputs: Vec<OutputWorkspaces<E>>,
+    // workspace of the currently focused element
+    focused: Option<WorkspaceId>,
+    span: tracing::Span,
+}
+
+impl<E: SpaceElement> Default for Workspaces<E> {
+    #[inline]
+    fn default() -> Self {
+        Workspaces {
+            outputs: Vec::new(),
+            focused: None,
+            span: debug_span!("desktop_workspaces"),
+        }
+    }
+}
+
+impl<E: SpaceElement + PartialEq> Workspaces<E> {
+    /// Adds a new [`Output`] at a given location with a single empty workspace.
+    ///
+    /// Returns the id of the initial workspace. Calling this function on an already
+    /// added output will update its location and return the id of its active workspace.
+    pub fn add_output<P: Into<Point<i32, Logical>>>(&mut self, output: &Output, location: P) -> WorkspaceId {
+        let location = location.into();
+        if let Some(entry) = self.outputs.iter_mut().find(|o| &o.output == output) {
+            entry.location = location;
+            for workspace in entry.workspaces.iter_mut() {
+                if workspace.space.outputs().any(|o| o == output) {
+                    workspace.space.map_output(output, location);
+                }
+            }
+            return entry.active().id;
+        }
+
+        debug!(parent: &self.span, output = output.name(), "Adding output at {:?}", location);
+        let mut workspace = Workspace::new();
+        workspace.space.map_output(output, location);
+        let id = workspace.id;
+        self.outputs.push(OutputWorkspaces {
+            output: output.clone(),
+            location,
+            workspaces: vec![workspace],
+            active: 0,
+        });
+        id
+    }
+
+    /// Removes an [`Output`] and returns all workspaces it owned.
+    ///
+    /// The output will be unmapped from the returned workspaces, elements stay mapped.
+    pub fn remove_output(&mut self, output: &Output) -> Vec<Workspace<E>> {
+        let Some(pos) = self.outputs.iter().position(|o| &o.output == output) else {
+            return Vec::new();
+        };
+        debug!(parent: &self.span, output = output.name(), "Removing output");
+        let mut entry = self.outputs.remove(pos);
+        for workspace in entry.workspaces.iter_mut() {
+            workspace.space.unmap_output(output);
+        }
+        if entry.workspaces.iter().any(|w| Some(w.id) == self.focused) {
+            self.focused = None;
+        }
+        entry.workspaces
+    }
+
+    /// Iterate over all added [`Output`]s
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().map(|o| &o.output)
+    }
+
+    /// Creates a new empty workspace on the given [`Output`].
+    ///
+    /// The workspace is appended after all existing workspaces of the output and is not activated.
+    /// Returns `None` if the output was not added.
+    pub fn create_workspace(&mut self, output: &Output) -> Option<WorkspaceId> {
+        let entry = self.outputs.iter_mut().find(|o| &o.output == output)?;
+        let workspace = Workspace::new();
+        let id = workspace.id;
+        entry.workspaces.push(workspace);
+        Some(id)
+    }
+
+    /// Removes a workspace.
+    ///
+    /// The last remaining workspace of an output can not be removed.
+    /// If the removed workspace was active, the previous workspace of the output will be activated
+    /// and receives the sticky elements of the removed workspace.
+    pub fn remove_workspace(&mut self, id: WorkspaceId) -> Option<Workspace<E>> {
+        let (idx, pos) = self.position(id)?;
+        let entry = &mut self.outputs[idx];
+        if entry.workspaces.len() == 1 {
+            return None;
+        }
+        if self.focused == Some(id) {
+            self.focused = None;
+        }
+
+        if pos == entry.active {
+            let new_active = pos.saturating_sub(1).min(entry.workspaces.len() - 2);
+            let mut workspace = entry.workspaces.remove(pos);
+            workspace.space.unmap_output(&entry.output);
+            workspace
+                .space
+                .move_sticky_elements(&mut entry.workspaces[new_active].space);
+            entry.active = new_active;
+            let (output, location) = (entry.output.clone(), entry.location);
+            entry.active_mut().space.map_output(&output, location);
+            Some(workspace)
+        } else {
+            if pos < entry.active {
+                entry.active -= 1;
+            }
+            Some(entry.workspaces.remove(pos))
+        }
+    }
+
+    /// Moves a workspace to another [`Output`], keeping all of its elements.
+    ///
+    /// The element locations are translated by the offset between both output locations.
+    /// Returns `false` if the workspace is the last of its output or the target output was not added.
+    pub fn move_workspace_to_output(&mut self, id: WorkspaceId, output: &Output) -> bool
+    where
+        E: Clone,
+    {
+        let Some(target) = self.outputs.iter().position(|o| &o.output == output) else {
+            return false;
+        };
+        let Some((idx, _)) = self.position(id) else {
+            return false;
+        };
+        if idx == target {
+            return true;
+        }
+
+        let source_location = self.outputs[idx].location;
+        let Some(mut workspace) = self.remove_workspace(id) else {
+            return false;
+        };
+        let entry = &mut self.outputs[target];
+        let offset = entry.location - source_location;
+        if offset != Point::from((0, 0)) {
+            // remap back to front to keep the stacking order
+            let mapped = workspace
+                .space
+                .elements()
+                .map(|e| (e.clone(), workspace.space.element_location(e).unwrap_or_default()))
+                .collect::<Vec<_>>();
+            for (element, location) in mapped {
+                workspace.space.map_element(element, location + offset, false);
+            }
+        }
+        entry.workspaces.push(workspace);
+        true
+    }
+
+    /// Activates a workspace on its [`Output`].
+    ///
+    /// Returns `false` if no workspace with the given id exists.
+    pub fn activate(&mut self, id: WorkspaceId) -> bool {
+        let Some((idx, pos)) = self.position(id) else {
+            return false;
+        };
+        let _guard = self.span.enter();
+        let entry = &mut self.outputs[idx];
+        if entry.active == pos {
+            return true;
+        }
+
+        debug!(output = entry.output.name(), "Activating workspace {:?}", id);
+        let (output, location) = (entry.output.clone(), entry.location);
+        entry.active_mut().space.unmap_output(&output);
+        let previous = entry.active;
+        entry.active = pos;
+        entry.active_mut().space.map_output(&output, location);
+        entry.move_sticky_elements(previous, pos, &mut self.focused);
+        true
+    }
+
+    /// Activates the next workspace of an [`Output`], wrapping around.
+    pub fn activate_next(&mut self, output: &Output) -> Option<WorkspaceId> {
+        let entry = self.outputs.iter().find(|o| &o.output == output)?;
+        let id = entry.workspaces[(entry.active + 1) % entry.workspaces.len()].id;
+        self.activate(id);
+        Some(id)
+    }
+
+    /// Activates the previous workspace of an [`Output`], wrapping around.
+    pub fn activate_prev(&mut self, output: &Output) -> Option<WorkspaceId> {
+        let entry = self.outputs.iter().find(|o| &o.output == output)?;
+        let len = entry.workspaces.len();
+        let id = entry.workspaces[(entry.active + len - 1) % len].id;
+        self.activate(id);
+        Some(id)
+    }
+
+    /// Returns the active workspace of an [`Output`]
+    pub fn active_workspace(&self, output: &Output) -> Option<&Workspace<E>> {
+        self.outputs
+            .iter()
+            .find(|o| &o.output == output)
+            .map(|entry| entry.active())
+    }
+
+    /// Returns the active workspace of an [`Output`] mutably
+    pub fn active_workspace_mut(&mut self, output: &Output) -> Option<&mut Workspace<E>> {
+        self.outputs
+            .iter_mut()
+            .find(|o| &o.output == output)
+            .map(|entry| entry.active_mut())
+    }
+
+    /// Returns the [`Space`] of the active workspace of an [`Output`]
+    ///
+    /// This can be passed to [`render_output`](super::space::render_output).
+    pub fn active_space(&self, output: &Output) -> Option<&Space<E>> {
+        self.active_workspace(output).map(|w| &w.space)
+    }
+
+    /// Iterate over the active workspaces of all [`Output`]s
+    pub fn active_workspaces(&self) -> impl Iterator<Item = &Workspace<E>> {
+        self.outputs.iter().map(|entry| entry.active())
+    }
+
+    /// Returns whenever the given workspace is currently active
+    pub fn is_active(&self, id: WorkspaceId) -> bool {
+        self.position(id)
+            .map(|(idx, pos)| self.outputs[idx].active == pos)
+            .unwrap_or(false)
+    }
+
+    /// Iterate over all workspaces of an [`Output`] in order
+    pub fn workspaces_for_output<'a>(
+        &'a self,
+        output: &'a Output,
+    ) -> impl DoubleEndedIterator<Item = &'a Workspace<E>> {
+        self.outputs
+            .iter()
+            .filter(move |o| &o.output == output)
+            .flat_map(|entry| entry.workspaces.iter())
+    }
+
+    /// Iterate over all workspaces of all [`Output`]s
+    pub fn workspaces(&self) -> impl Iterator<Item = &Workspace<E>> {
+        self.outputs.iter().flat_map(|entry| entry.workspaces.iter())
+    }
+
+    /// Returns the workspace with the given id
+    pub fn workspace(&self, id: WorkspaceId) -> Option<&Workspace<E>> {
+        self.position(id)
+            .map(|(idx, pos)| &self.outputs[idx].workspaces[pos])
+    }
+
+    /// Returns the workspace with the given id mutably
+    pub fn workspace_mut(&mut self, id: WorkspaceId) -> Option<&mut Workspace<E>> {
+        self.position(id)
+            .map(|(idx, pos)| &mut self.outputs[idx].workspaces[pos])
+    }
+
+    /// Returns the [`Output`] owning the given workspace
+    pub fn output_for_workspace(&self, id: WorkspaceId) -> Option<&Output> {
+        self.position(id).map(|(idx, _)| &self.outputs[idx].output)
+    }
+
+    /// Returns the workspace a given element is mapped in, if any
+    pub fn workspace_for_element(&self, element: &E) -> Option<WorkspaceId> {
+        self.workspaces()
+            .find(|w| w.space.elements().any(|e| e == element))
+            .map(|w| w.id)
+    }
+
+    /// Map an element onto a workspace and move it to the top of its stack.
+    ///
+    /// If the element was previously mapped onto a different workspace, it is removed from there.
+    ///
+    /// See [`Space::map_element`] for more information.
+    pub fn map_element<P>(&mut self, id: WorkspaceId, element: E, location: P, activate: bool) -> bool
+    where
+        P: Into<Point<i32, Logical>>,
+    {
+        if self.position(id).is_none() {
+            return false;
+        }
+        if let Some(old) = self.workspace_for_element(&element).filter(|old| *old != id) {
+            self.workspace_mut(old).unwrap().space.unmap_elem(&element);
+        }
+        self.workspace_mut(id)
+            .unwrap()
+            .space
+            .map_element(element, location, activate);
+        true
+    }
+
+    /// Focuses an element, activating its workspace if necessary.
+    ///
+    /// The element is raised and activated, the previously focused element is deactivated.
+    /// Returns the workspace of the element or `None` if it is not mapped.
+    pub fn focus_element(&mut self, element: &E) -> Option<WorkspaceId>
+    where
+        E: Clone,
+    {
+        let id = self.workspace_for_element(element)?;
+        if let Some(previous) = self
+            .focused
+            .filter(|previous| *previous != id)
+            .and_then(|previous| self.workspace(previous))
+            .and_then(|previous| previous.focused())
+        {
+            previous.set_activate(false);
+        }
+
+        self.activate(id);
+        let workspace = self.workspace_mut(id).unwrap();
+        workspace.space.raise_element(element, true);
+        workspace.focused = Some(element.clone());
+        self.focused = Some(id);
+        Some(id)
+    }
+
+    /// Deactivates the focused element, e.g. when keyboard focus moves to a layer surface.
+    ///
+    /// The element stays the [focus target](Workspaces::focus_target) of its workspace.
+    pub fn clear_focus(&mut self) {
+        if let Some(focused) = self.focused.take().and_then(|id| self.workspace(id)?.focused()) {
+            focused.set_activate(false);
+        }
+    }
+
+    /// Returns the currently focused element
+    pub fn focused_element(&self) -> Option<&E> {
+        self.workspace(self.focused?)?.focused()
+    }
+
+    /// Returns the element that should be focused on the active workspace of an [`Output`]
+    ///
+    /// This is the element focused last on the workspace, if it is still mapped.
+    pub fn focus_target(&self, output: &Output) -> Option<&E> {
+        self.active_workspace(output)?.focused()
+    }
+
+    /// Moves an already mapped element to another workspace.
+    ///
+    /// The location of the element is translated by the offset between the outputs
+    /// owning both workspaces, keeping its position relative to the output.
+    pub fn move_element(&mut self, element: &E, to: WorkspaceId) -> bool
+    where
+        E: Clone,
+    {
+        let Some(from) = self.workspace_for_element(element) else {
+            return false;
+        };
+        if from == to {
+            return true;
+        }
+        let Some((to_idx, _)) = self.position(to) else {
+            return false;
+        };
+        let (from_idx, _) = self.position(from).unwrap();
+        let offset = self.outputs[to_idx].location - self.outputs[from_idx].location;
+
+        let workspace = self.workspace_mut(from).unwrap();
+        let location = workspace.space.element_location(element).unwrap_or_default();
+        workspace.space.unmap_elem(element);
+        let was_focused = workspace.focused.as_ref() == Some(element);
+        if was_focused {
+            workspace.focused = None;
+        }
+
+        let workspace = self.workspace_mut(to).unwrap();
+        workspace
+            .space
+            .map_element(element.clone(), location + offset, false);
+        // focus follows the element
+        if was_focused {
+            workspace.focused = Some(element.clone());
+            if self.focused == Some(from) {
+                self.focused = Some(to);
+            }
+        }
+        true
+    }
+
+    /// Unmaps an element from whatever workspace it is mapped in.
+    pub fn unmap_elem(&mut self, element: &E) {
+        if let Some(id) = self.workspace_for_element(element) {
+            let workspace = self.workspace_mut(id).unwrap();
+            workspace.space.unmap_elem(element);
+            if workspace.focused.as_ref() == Some(element) {
+                workspace.focused = None;
+                if self.focused == Some(id) {
+                    self.focused = None;
+                }
+            }
+        }
+    }
+
+    /// Returns the [`Output`] under a given point, if any
+    pub fn output_under<P: Into<Point<f64, Logical>>>(&self, point: P) -> Option<&Output> {
+        let point = point.into();
+        self.outputs
+            .iter()
+            .rev()
+            .find(|entry| {
+                entry
+                    .active()
+                    .space
+                    .output_geometry(&entry.output)
+                    .map(|geo| geo.to_f64().contains(point))
+                    .unwrap_or(false)
+            })
+            .map(|entry| &entry.output)
+    }
+
+    /// Returns the geometry of an [`Output`] in global coordinates
+    pub fn output_geometry(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        self.active_space(output)
+            .and_then(|space| space.output_geometry(output))
+    }
+
+    /// Finds the topmost element on the active workspaces under a given point.
+    ///
+    /// Only the active workspace of the [`Output`] under the point is considered.
+    /// See [`Space::element_under`] for more information.
+    pub fn element_under<P: Into<Point<f64, Logical>>>(&self, point: P) -> Option<(&E, Point<i32, Logical>)> {
+        let point = point.into();
+        let output = self.output_under(point)?;
+        self.active_space(output)?.element_under(point)
+    }
+
+    /// Refresh all workspaces.
+    ///
+    /// Focused elements which are no longer mapped are forgotten.
+    /// See [`Space::refresh`] for more information.
+    pub fn refresh(&mut self) {
+        for workspace in self.outputs.iter_mut().flat_map(|o| o.workspaces.iter_mut()) {
+            workspace.space.refresh();
+            if workspace.focused().is_none() {
+                workspace.focused = None;
+            }
+        }
+    }
+
+    /// Retrieve the render elements of the active workspace of an output
+    ///
+    /// See [`Space::render_elements_for_output`] for more information.
+    pub fn render_elements_for_output<
+        'a,
+        #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+        #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+    >(
+        &'a self,
+        renderer: &mut R,
+        output: &Output,
+        alpha: f32,
+    ) -> Result<Vec<SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>>, OutputError>
+    where
+        <R as Renderer>::TextureId: Clone + Texture + 'static,
+        E: AsRenderElements<R>,
+        <E as AsRenderElements<R>>::RenderElement: 'a,
+        SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>:
+            From<Wrap<<E as AsRenderElements<R>>::RenderElement>>,
+    {
+        self.active_space(output)
+            .ok_or(OutputError::Unmapped)?
+            .render_elements_for_output(renderer, output, alpha)
+    }
+
+    fn position(&self, id: WorkspaceId) -> Option<(usize, usize)> {
+        self.outputs.iter().enumerate().find_map(|(idx, entry)| {
+            entry
+                .workspaces
+                .iter()
+                .position(|w| w.id == id)
+                .map(|pos| (idx, pos))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::Workspaces;
+    use crate::{
+        desktop::space::{SpaceElement, StackingLayer},
+        output::{Mode, Output, PhysicalProperties, Subpixel},
+        utils::{IsAlive, Logical, Point, Rectangle},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestElement(u32);
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (100, 100))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ActivatableElement(u32, Rc<Cell<bool>>);
+    impl ActivatableElement {
+        fn new(id: u32) -> Self {
+            ActivatableElement(id, Rc::new(Cell::new(false)))
+        }
+        fn activated(&self) -> bool {
+            self.1.get()
+        }
+    }
+    impl PartialEq for ActivatableElement {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+    impl SpaceElement for ActivatableElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (100, 100))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, activated: bool) {
+            self.1.set(activated);
+        }
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+    impl IsAlive for ActivatableElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    fn output(name: &str) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+            },
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: (1000, 1000).into(),
+                refresh: 60_000,
+            }),
+            None,
+            None,
+            None,
+        );
+        output
+    }
+
+    #[test]
+    fn activation_limits_input() {
+        let mut workspaces = Workspaces::default();
+        let output = output("test-1");
+        let first = workspaces.add_output(&output, (0, 0));
+        let second = workspaces.create_workspace(&output).unwrap();
+
+        assert!(workspaces.map_element(second, TestElement(1), (10, 10), false));
+        assert_eq!(workspaces.workspace_for_element(&TestElement(1)), Some(second));
+        assert!(workspaces.element_under((20.0, 20.0)).is_none());
+
+        assert!(workspaces.activate(second));
+        assert!(!workspaces.is_active(first));
+        assert_eq!(
+            workspaces.element_under((20.0, 20.0)).map(|(e, _)| e.clone()),
+            Some(TestElement(1))
+        );
+
+        // remapping onto a different workspace removes the element from the previous one
+        assert!(workspaces.map_element(first, TestElement(1), (10, 10), false));
+        assert!(workspaces.element_under((20.0, 20.0)).is_none());
+        assert_eq!(workspaces.workspace_for_element(&TestElement(1)), Some(first));
+    }
+
+    #[test]
+    fn move_between_outputs() {
+        let mut workspaces = Workspaces::default();
+        let left = output("test-left");
+        let right = output("test-right");
+        let first = workspaces.add_output(&left, (0, 0));
+        let second = workspaces.add_output(&right, (1000, 0));
+
+        workspaces.map_element(first, TestElement(1), (10, 10), false);
+        assert!(workspaces.move_element(&TestElement(1), second));
+        assert_eq!(
+            workspaces
+                .workspace(second)
+                .unwrap()
+                .space()
+                .element_location(&TestElement(1)),
+            Some((1010, 10).into())
+        );
+
+        // the last workspace of an output can not be removed
+        assert!(!workspaces.move_workspace_to_output(second, &left));
+        let third = workspaces.create_workspace(&right).unwrap();
+        workspaces.activate(third);
+        assert!(workspaces.move_workspace_to_output(second, &left));
+        assert_eq!(workspaces.output_for_workspace(second), Some(&left));
+        assert_eq!(
+            workspaces
+                .workspace(second)
+                .unwrap()
+                .space()
+                .element_location(&TestElement(1)),
+            Some((10, 10).into())
+        );
+    }
+
+    #[test]
+    fn focus_follows_workspaces() {
+        let mut workspaces = Workspaces::default();
+        let output = output("test-1");
+        let first = workspaces.add_output(&output, (0, 0));
+        let second = workspaces.create_workspace(&output).unwrap();
+
+        let a = ActivatableElement::new(1);
+        let b = ActivatableElement::new(2);
+        workspaces.map_element(first, a.clone(), (10, 10), false);
+        workspaces.map_element(second, b.clone(), (10, 10), false);
+
+        // focusing an element on an inactive workspace switches to it
+        assert_eq!(workspaces.focus_element(&a), Some(first));
+        assert!(a.activated());
+        assert_eq!(workspaces.focus_element(&b), Some(second));
+        assert!(workspaces.is_active(second));
+        assert!(!a.activated());
+        assert!(b.activated());
+        assert_eq!(workspaces.focused_element(), Some(&b));
+
+        // each workspace remembers its last focused element
+        workspaces.activate(first);
+        assert_eq!(workspaces.focus_target(&output), Some(&a));
+        workspaces.activate(second);
+        assert_eq!(workspaces.focus_target(&output), Some(&b));
+
+        workspaces.clear_focus();
+        assert!(!b.activated());
+        assert!(workspaces.focused_element().is_none());
+        assert_eq!(workspaces.focus_target(&output), Some(&b));
+
+        // focus moves along with the element and is dropped on unmap
+        workspaces.focus_element(&b);
+        assert!(workspaces.move_element(&b, first));
+        assert_eq!(workspaces.focused_element(), Some(&b));
+        assert!(workspaces.workspace(second).unwrap().focused().is_none());
+        workspaces.unmap_elem(&b);
+        assert!(workspaces.focused_element().is_none());
+        assert!(workspaces.workspace(first).unwrap().focused().is_none());
+    }
+
+    #[test]
+    fn sticky_elements_follow_workspace_switches() {
+        let mut workspaces = Workspaces::default();
+        let output = output("test-1");
+        let first = workspaces.add_output(&output, (0, 0));
+        let second = workspaces.create_workspace(&output).unwrap();
+
+        let sticky = ActivatableElement::new(1);
+        let normal = ActivatableElement::new(2);
+        workspaces.map_element(first, sticky.clone(), (10, 10), false);
+        workspaces.map_element(first, normal.clone(), (10, 10), false);
+        workspaces
+            .workspace_mut(first)
+            .unwrap()
+            .space_mut()
+            .set_element_layer(&sticky, StackingLayer::Sticky);
+        workspaces.focus_element(&sticky);
+
+        workspaces.activate(second);
+        assert_eq!(workspaces.workspace_for_element(&sticky), Some(second));
+        assert_eq!(workspaces.workspace_for_element(&normal), Some(first));
+        assert_eq!(
+            workspaces.element_under((20.0, 20.0)).map(|(e, _)| e.0),
+            Some(sticky.0)
+        );
+        let space = workspaces.workspace(second).unwrap().space();
+        assert_eq!(space.element_location(&sticky), Some((10, 10).into()));
+        assert_eq!(space.element_layer(&sticky), Some(StackingLayer::Sticky));
+        assert_eq!(workspaces.focused_element(), Some(&sticky));
+
+        // removing the active workspace hands them to the next active one
+        assert!(workspaces.remove_workspace(second).is_some());
+        assert!(workspaces.is_active(first));
+        assert_eq!(workspaces.workspace_for_element(&sticky), Some(first));
+    }
+}
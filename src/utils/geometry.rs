@@ -325,11 +325,17 @@ where
 ///
 /// Operations on points are saturating.
 #[repr(C)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(serialize = "N: serde::Serialize", deserialize = "N: serde::Deserialize<'de>"))
+)]
 pub struct Point<N, Kind> {
     /// horizontal coordinate
     pub x: N,
     /// vertical coordinate
     pub y: N,
+    #[cfg_attr(feature = "serde", serde(skip))]
     _kind: std::marker::PhantomData<Kind>,
 }
 
@@ -676,11 +682,17 @@ impl<N: Default, Kind> Default for Point<N, Kind> {
 ///
 /// Operations on sizes are saturating.
 #[repr(C)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(serialize = "N: serde::Serialize", deserialize = "N: serde::Deserialize<'de>"))
+)]
 pub struct Size<N, Kind> {
     /// horizontal coordinate
     pub w: N,
     /// vertical coordinate
     pub h: N,
+    #[cfg_attr(feature = "serde", serde(skip))]
     _kind: std::marker::PhantomData<Kind>,
 }
 
@@ -1031,6 +1043,11 @@ impl<N: Coordinate, Kind> Sub<Size<N, Kind>> for Point<N, Kind> {
 ///
 /// Operations on rectangles are saturating.
 #[repr(C)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(serialize = "N: serde::Serialize", deserialize = "N: serde::Deserialize<'de>"))
+)]
 pub struct Rectangle<N, Kind> {
     /// Location of the top-left corner of the rectangle
     pub loc: Point<N, Kind>,
@@ -1097,6 +1114,15 @@ impl<Kind> Rectangle<f64, Kind> {
     pub fn to_i32_up<N: Coordinate>(self) -> Rectangle<N, Kind> {
         Rectangle::from_extemities(self.loc.to_i32_floor(), (self.loc + self.size).to_i32_ceil())
     }
+
+    /// Convert to i32 by rounding the edges of the float-based rectangle
+    ///
+    /// Unlike [`Rectangle::to_i32_round`] this keeps rectangles sharing an edge adjacent,
+    /// as the size is derived from the rounded edges instead of being rounded separately.
+    #[inline]
+    pub fn to_i32_round_edges<N: Coordinate>(self) -> Rectangle<N, Kind> {
+        Rectangle::from_extemities(self.loc.to_i32_round(), (self.loc + self.size).to_i32_round())
+    }
 }
 
 impl<N: Coordinate, Kind> Rectangle<N, Kind> {
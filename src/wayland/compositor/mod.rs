@@ -266,7 +266,7 @@ impl Default for SurfaceAttributes {
 }
 
 /// Kind of a rectangle part of a region
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RectangleKind {
     /// This rectangle should be added to the region
     Add,
@@ -282,7 +282,7 @@ pub enum RectangleKind {
 /// This struct contains an ordered `Vec` containing the rectangles defining
 /// a region. They should be added or subtracted in this order to compute the
 /// actual contents of the region.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct RegionAttributes {
     /// List of rectangle part of this region
     pub rects: Vec<(RectangleKind, Rectangle<i32, Logical>)>,
@@ -214,7 +214,7 @@ use wayland_server::{
 };
 
 #[cfg(feature = "backend_drm")]
-use crate::backend::drm::DrmNode;
+use crate::backend::drm::{DrmNode, DrmSurface};
 use crate::{
     backend::allocator::{
         dmabuf::{Dmabuf, DmabufFlags, Plane},
@@ -384,6 +384,51 @@ impl DmabufFeedbackBuilder {
         self
     }
 
+    /// Adds a scan-out preference tranche to the builder
+    ///
+    /// Only formats supported by `scanout_formats` and `render_formats` are added to the tranche,
+    /// so there is always a fallback render path available in case a buffer can not be scanned out
+    /// directly.
+    pub fn add_scanout_tranche(
+        self,
+        target_device: libc::dev_t,
+        scanout_formats: impl IntoIterator<Item = Format>,
+        render_formats: impl IntoIterator<Item = Format>,
+    ) -> Self {
+        let render_formats = render_formats.into_iter().collect::<IndexSet<_>>();
+        let formats = scanout_formats
+            .into_iter()
+            .filter(|format| render_formats.contains(format))
+            .collect::<Vec<_>>();
+        self.add_preference_tranche(
+            target_device,
+            Some(zwp_linux_dmabuf_feedback_v1::TrancheFlags::Scanout),
+            formats,
+        )
+    }
+
+    /// Adds a scan-out preference tranche for the planes of a [`DrmSurface`]
+    ///
+    /// See [`DmabufFeedbackBuilder::add_scanout_tranche`] and [`DrmSurface::scanout_formats`].
+    /// The tranche is skipped if the device of the surface can not be queried.
+    #[cfg(feature = "backend_drm")]
+    pub fn add_surface_scanout_tranche(
+        self,
+        surface: &DrmSurface,
+        render_formats: impl IntoIterator<Item = Format>,
+    ) -> Self {
+        match surface.device_fd().dev_id() {
+            Ok(dev_id) => self.add_scanout_tranche(dev_id, surface.scanout_formats(), render_formats),
+            Err(err) => {
+                tracing::warn!(
+                    ?err,
+                    "Failed to query device of drm surface, skipping scan-out tranche"
+                );
+                self
+            }
+        }
+    }
+
     /// Build the [`DmabufFeedback`]
     ///
     /// Returns an error if the format table shared memory file could
@@ -1235,3 +1280,37 @@ impl DmabufParamsData {
 }
 
 id_gen!(global_id);
+
+#[cfg(test)]
+mod tests {
+    use super::DmabufFeedbackBuilder;
+    use crate::backend::allocator::{Format, Fourcc, Modifier};
+    use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::TrancheFlags;
+
+    #[test]
+    fn scanout_tranche_limited_to_render_formats() {
+        let format = |code| Format {
+            code,
+            modifier: Modifier::Linear,
+        };
+        let render_formats = [format(Fourcc::Argb8888), format(Fourcc::Xrgb8888)];
+        let scanout_formats = [format(Fourcc::Xrgb8888), format(Fourcc::Nv12)];
+
+        let feedback = DmabufFeedbackBuilder::new(1, render_formats)
+            .add_scanout_tranche(2, scanout_formats, render_formats)
+            .build()
+            .unwrap();
+
+        let tranches = &feedback.0.tranches;
+        assert_eq!(tranches.len(), 2);
+        assert_eq!(tranches[0].flags, TrancheFlags::Scanout);
+        assert_eq!(tranches[0].target_device, 2);
+        let formats = &feedback.0.format_table.formats;
+        let scanout = tranches[0]
+            .indices
+            .iter()
+            .map(|i| formats[*i])
+            .collect::<Vec<_>>();
+        assert_eq!(scanout, vec![format(Fourcc::Xrgb8888)]);
+    }
+}
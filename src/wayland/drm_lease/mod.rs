@@ -86,13 +86,15 @@ use std::{
     },
 };
 
-use drm::control::{connector, crtc, plane, Device, RawResourceHandle};
+use drm::control::{connector, crtc, plane, RawResourceHandle};
 use rustix::fs::OFlags;
 use wayland_protocols::wp::drm_lease::v1::server::*;
 use wayland_server::backend::GlobalId;
 use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
 
-use crate::backend::drm::{DrmDevice, DrmDeviceFd, DrmNode, NodeType, PlaneClaim};
+use crate::backend::drm::{
+    device::LeaseStorage, DrmDevice, DrmDeviceFd, DrmError, DrmNode, NodeType, PlaneClaim,
+};
 
 /// Delegate type for a drm_lease global
 #[derive(Debug)]
@@ -133,6 +135,7 @@ pub struct DrmLeaseRequest {
 #[derive(Debug)]
 pub struct DrmLeaseBuilder {
     drm: DrmDeviceFd,
+    leases: LeaseStorage,
     planes: HashMap<plane::Handle, PlaneClaim>,
     connectors: HashSet<connector::Handle>,
     crtcs: HashSet<crtc::Handle>,
@@ -143,6 +146,7 @@ impl DrmLeaseBuilder {
     pub fn new(drm: &DrmDevice) -> DrmLeaseBuilder {
         DrmLeaseBuilder {
             drm: drm.device_fd().clone(),
+            leases: drm.lease_storage().clone(),
             planes: HashMap::new(),
             connectors: HashSet::new(),
             crtcs: HashSet::new(),
@@ -164,7 +168,7 @@ impl DrmLeaseBuilder {
         self.planes.insert(plane, claim);
     }
 
-    fn build(self) -> io::Result<DrmLease> {
+    fn build(self) -> Result<DrmLease, DrmError> {
         let objects: Vec<RawResourceHandle> = self
             .planes
             .keys()
@@ -173,10 +177,11 @@ impl DrmLeaseBuilder {
             .chain(self.connectors.iter().cloned().map(Into::into))
             .chain(self.crtcs.iter().cloned().map(Into::into))
             .collect();
-        let (id, fd) = self.drm.create_lease(&objects, OFlags::CLOEXEC.bits())?;
+        let (id, fd) = self.leases.create(&self.drm, &objects)?;
 
         Ok(DrmLease {
             drm: self.drm.clone(),
+            leases: self.leases,
             planes: self.planes,
             connectors: self.connectors,
             crtcs: self.crtcs,
@@ -194,6 +199,7 @@ impl DrmLeaseBuilder {
 #[derive(Debug, Clone)]
 pub struct DrmLease {
     drm: DrmDeviceFd,
+    leases: LeaseStorage,
     planes: HashMap<plane::Handle, PlaneClaim>,
     connectors: HashSet<connector::Handle>,
     crtcs: HashSet<crtc::Handle>,
@@ -232,6 +238,7 @@ impl DrmLease {
 #[derive(Debug)]
 struct DrmLeaseRef {
     drm: DrmDeviceFd,
+    leases: LeaseStorage,
     obj: Weak<Mutex<Option<wp_drm_lease_v1::WpDrmLeaseV1>>>,
     lease_id: NonZeroU32,
     revoked: Arc<AtomicBool>,
@@ -245,7 +252,7 @@ impl Drop for DrmLease {
         }
         if !self.revoked.swap(true, Ordering::SeqCst) {
             tracing::info!(?self.lease_id, "Revoking lease");
-            if let Err(err) = self.drm.revoke_lease(self.lease_id) {
+            if let Err(err) = self.leases.revoke(&self.drm, self.lease_id) {
                 tracing::warn!(?err, "Error revoking lease");
             };
         }
@@ -259,7 +266,7 @@ impl DrmLeaseRef {
         }
         if !self.revoked.swap(true, Ordering::SeqCst) {
             tracing::info!(?self.lease_id, "Revoking lease");
-            if let Err(err) = self.drm.revoke_lease(self.lease_id) {
+            if let Err(err) = self.leases.revoke(&self.drm, self.lease_id) {
                 tracing::warn!(?err, "Error revoking lease");
             };
         }
@@ -893,6 +900,7 @@ where
 
                             let lease_ref = DrmLeaseRef {
                                 drm: lease.drm.clone(),
+                                leases: lease.leases.clone(),
                                 obj: Arc::downgrade(&lease.obj),
                                 lease_id: lease.lease_id,
                                 connectors: lease.connectors.clone(),
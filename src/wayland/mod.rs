@@ -75,9 +75,11 @@ pub mod shm;
 pub mod single_pixel_buffer;
 pub mod socket;
 pub mod tablet_manager;
+pub mod tearing_control;
 pub mod text_input;
 pub mod viewporter;
 pub mod virtual_keyboard;
+pub mod wl_drm;
 pub mod xdg_activation;
 pub mod xdg_foreign;
 pub mod xdg_system_bell;
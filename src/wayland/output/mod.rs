@@ -40,6 +40,7 @@
 //!         subpixel: Subpixel::HorizontalRgb,  // subpixel information
 //!         make: "Screens Inc".into(),     // make of the monitor
 //!         model: "Monitor Ultra".into(),  // model of the monitor
+//!         serial_number: "1234".into(),   // serial number of the monitor
 //!     },
 //! );
 //! // create a global, if you want to advertise it to clients
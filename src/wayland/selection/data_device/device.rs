@@ -25,7 +25,7 @@ use crate::{
     },
 };
 
-use super::{dnd_grab, DataDeviceHandler, DataDeviceState};
+use super::{dnd_grab, icon, DataDeviceHandler, DataDeviceState};
 
 /// WlSurface role of drag and drop icon
 pub const DND_ICON_ROLE: &str = "dnd_icon";
@@ -80,6 +80,7 @@ where
                             }
                         }
                         // The StartDrag is in response to a pointer implicit grab, all is good
+                        icon::set_dnd_icon(&seat, icon.clone());
                         handler.started(source.clone(), icon.clone(), seat.clone());
                         let start_data = pointer.grab_start_data().unwrap();
                         pointer.set_grab(
@@ -103,6 +104,7 @@ where
                             }
                         }
                         // The StartDrag is in response to a touch implicit grab, all is good
+                        icon::set_dnd_icon(&seat, icon.clone());
                         handler.started(source.clone(), icon.clone(), seat.clone());
                         let start_data = touch.grab_start_data().unwrap();
                         touch.set_grab(
@@ -31,7 +31,7 @@ use crate::{
     wayland::{seat::WaylandFocus, selection::seat_data::SeatData},
 };
 
-use super::{with_source_metadata, ClientDndGrabHandler, DataDeviceHandler};
+use super::{icon, with_source_metadata, ClientDndGrabHandler, DataDeviceHandler};
 
 /// Grab during a client-initiated DnD operation.
 pub struct DnDGrab<D: SeatHandler> {
@@ -265,6 +265,7 @@ where
 
         ClientDndGrabHandler::dropped(data, self.seat.clone());
         self.icon = None;
+        icon::set_dnd_icon(&self.seat, None);
         // in all cases abandon the drop
         // no more buttons are pressed, release the grab
         if let Some(ref surface) = self.current_focus {
@@ -0,0 +1,134 @@
+use std::{cell::RefCell, sync::Mutex};
+
+use wayland_server::{protocol::wl_surface::WlSurface, Resource};
+
+use crate::{
+    backend::renderer::{
+        element::{
+            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            Kind,
+        },
+        ImportAll, Renderer,
+    },
+    input::{Seat, SeatHandler},
+    utils::{Logical, Point, Scale},
+    wayland::compositor::{add_post_commit_hook, with_states, SurfaceAttributes},
+};
+
+/// The icon of an active client drag'n'drop operation
+///
+/// Retrieved via [`dnd_icon`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DndIcon {
+    /// The icon surface
+    pub surface: WlSurface,
+    /// Offset of the icon relative to the pointer or touch point
+    ///
+    /// The offset starts at `(0, 0)` and is updated on every commit of the icon
+    /// according to the offset attached by the client.
+    pub offset: Point<i32, Logical>,
+}
+
+impl DndIcon {
+    /// Returns the location of the icon given the location of the pointer or touch point
+    /// driving the drag'n'drop operation
+    pub fn location(&self, anchor: Point<f64, Logical>) -> Point<f64, Logical> {
+        anchor + self.offset.to_f64()
+    }
+
+    /// Returns the render elements of the icon surface tree
+    ///
+    /// `anchor` is the location of the pointer or touch point driving the drag'n'drop operation
+    /// relative to the output being rendered.
+    pub fn render_elements<R, C>(
+        &self,
+        renderer: &mut R,
+        anchor: Point<f64, Logical>,
+        scale: impl Into<Scale<f64>>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll,
+        <R as Renderer>::TextureId: Clone + 'static,
+        C: From<WaylandSurfaceRenderElement<R>>,
+    {
+        if !self.surface.is_alive() {
+            return Vec::new();
+        }
+
+        let scale = scale.into();
+        let location = self.location(anchor).to_physical(scale).to_i32_round();
+        render_elements_from_surface_tree(renderer, &self.surface, location, scale, alpha, Kind::Unspecified)
+    }
+}
+
+#[derive(Debug, Default)]
+struct DndIconSurfaceData {
+    offset: Point<i32, Logical>,
+}
+
+#[derive(Debug, Default)]
+struct SeatDndIcon(RefCell<Option<WlSurface>>);
+
+/// Returns the icon of the client drag'n'drop operation currently active on the seat, if any
+///
+/// The icon is set when a client starts a drag'n'drop operation providing an icon and
+/// removed once the operation ends.
+pub fn dnd_icon<D: SeatHandler + 'static>(seat: &Seat<D>) -> Option<DndIcon> {
+    let surface = seat
+        .user_data()
+        .get::<SeatDndIcon>()
+        .and_then(|icon| icon.0.borrow().clone())
+        .filter(|surface| surface.is_alive())?;
+    let offset = with_states(&surface, |states| {
+        states
+            .data_map
+            .get::<Mutex<DndIconSurfaceData>>()
+            .map(|data| data.lock().unwrap().offset)
+            .unwrap_or_default()
+    });
+    Some(DndIcon { surface, offset })
+}
+
+pub(super) fn set_dnd_icon<D: SeatHandler + 'static>(seat: &Seat<D>, icon: Option<WlSurface>) {
+    if let Some(surface) = icon.as_ref() {
+        let added = with_states(surface, |states| {
+            let added = states
+                .data_map
+                .insert_if_missing_threadsafe(|| Mutex::new(DndIconSurfaceData::default()));
+            // surfaces may be reused as icon for multiple operations
+            *states
+                .data_map
+                .get::<Mutex<DndIconSurfaceData>>()
+                .unwrap()
+                .lock()
+                .unwrap() = DndIconSurfaceData::default();
+            added
+        });
+
+        if added {
+            add_post_commit_hook::<D, _>(surface, |_, _, surface| {
+                with_states(surface, |states| {
+                    let buffer_delta = states
+                        .cached_state
+                        .get::<SurfaceAttributes>()
+                        .current()
+                        .buffer_delta
+                        .take();
+                    if let Some(buffer_delta) = buffer_delta {
+                        let mut data = states
+                            .data_map
+                            .get::<Mutex<DndIconSurfaceData>>()
+                            .unwrap()
+                            .lock()
+                            .unwrap();
+                        data.offset += buffer_delta;
+                    }
+                });
+            });
+        }
+    }
+
+    seat.user_data().insert_if_missing(SeatDndIcon::default);
+    *seat.user_data().get::<SeatDndIcon>().unwrap().0.borrow_mut() = icon;
+}
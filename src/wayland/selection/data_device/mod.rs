@@ -20,6 +20,8 @@
 //!   itself and receive interactions of clients with it via an other dedicated callback.
 //!
 //! The module defines the role `"dnd_icon"` that is assigned to surfaces used as drag'n'drop icons.
+//! The icon of the currently active drag'n'drop operation of a seat, including its offset,
+//! can be retrieved with [`dnd_icon`] and rendered using [`DndIcon::render_elements`].
 //!
 //! ## Initialization
 //!
@@ -96,11 +98,13 @@ use crate::{
 
 mod device;
 mod dnd_grab;
+mod icon;
 mod server_dnd_grab;
 mod source;
 
 pub use device::{DataDeviceUserData, DND_ICON_ROLE};
 pub use dnd_grab::DnDGrab;
+pub use icon::{dnd_icon, DndIcon};
 pub use server_dnd_grab::ServerDnDGrab;
 pub use source::{with_source_metadata, DataSourceUserData, SourceMetadata};
 
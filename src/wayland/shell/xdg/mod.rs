@@ -729,7 +729,7 @@ impl PositionerState {
                 geo.loc.x -= min(off_right, -off_left);
             }
 
-            (_, off_right, _, _) = compute_offsets(target, geo);
+            (off_left, off_right, _, _) = compute_offsets(target, geo);
             // off_top and off_bottom are the same since we're using rectangles.
         }
 
@@ -745,26 +745,32 @@ impl PositionerState {
                 geo.loc.y -= min(off_bottom, -off_top);
             }
 
-            (_, _, _, off_bottom) = compute_offsets(target, geo);
+            (_, _, off_top, off_bottom) = compute_offsets(target, geo);
             // off_left and off_right are the same since we're using rectangles.
         }
 
-        // Try to resize horizontally. This makes sense only if the popup is at least partially to the left
-        // of the right target edge, which is the same as checking that the offset is smaller than the width.
-        if off_right > 0
-            && off_right < geo.size.w
-            && self.constraint_adjustment.contains(ConstraintAdjustment::ResizeX)
-        {
-            geo.size.w -= off_right;
+        // Try to resize horizontally. This makes sense only if the popup is at least partially inside the
+        // target on that axis, which is the same as checking that the offset is smaller than the width.
+        // The left edge is handled first, as the right offset does not change by moving the left edge.
+        if self.constraint_adjustment.contains(ConstraintAdjustment::ResizeX) {
+            if off_left > 0 && off_left < geo.size.w {
+                geo.loc.x += off_left;
+                geo.size.w -= off_left;
+            }
+            if off_right > 0 && off_right < geo.size.w {
+                geo.size.w -= off_right;
+            }
         }
 
-        // Try to resize vertically. This makes sense only if the popup is at least partially to the top of
-        // the bottom target edge, which is the same as checking that the offset is smaller than the height.
-        if off_bottom > 0
-            && off_bottom < geo.size.h
-            && self.constraint_adjustment.contains(ConstraintAdjustment::ResizeY)
-        {
-            geo.size.h -= off_bottom;
+        // Try to resize vertically, following the same rules as the horizontal resize.
+        if self.constraint_adjustment.contains(ConstraintAdjustment::ResizeY) {
+            if off_top > 0 && off_top < geo.size.h {
+                geo.loc.y += off_top;
+                geo.size.h -= off_top;
+            }
+            if off_bottom > 0 && off_bottom < geo.size.h {
+                geo.size.h -= off_bottom;
+            }
         }
 
         geo
@@ -907,6 +913,11 @@ impl ToplevelStateSet {
         }
     }
 
+    /// Returns an iterator over the states.
+    pub fn iter(&self) -> impl Iterator<Item = xdg_toplevel::State> + '_ {
+        self.states.iter().copied()
+    }
+
     /// Filter the states according to the provided version
     /// of the [`XdgToplevel`]
     pub(crate) fn into_filtered_states(self, version: u32) -> Vec<xdg_toplevel::State> {
@@ -2164,3 +2175,96 @@ macro_rules! delegate_xdg_shell {
         ] => $crate::wayland::shell::xdg::XdgShellState);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PositionerState;
+    use crate::utils::{Logical, Rectangle};
+    use wayland_protocols::xdg::shell::server::xdg_positioner::{Anchor, ConstraintAdjustment, Gravity};
+
+    fn positioner(
+        anchor_rect: Rectangle<i32, Logical>,
+        anchor_edges: Anchor,
+        gravity: Gravity,
+        constraint_adjustment: ConstraintAdjustment,
+    ) -> PositionerState {
+        PositionerState {
+            rect_size: (100, 50).into(),
+            anchor_rect,
+            anchor_edges,
+            gravity,
+            constraint_adjustment,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unconstrain_resize_on_all_edges() {
+        let target = Rectangle::from_loc_and_size((0, 0), (500, 500));
+        let resize = ConstraintAdjustment::ResizeX | ConstraintAdjustment::ResizeY;
+
+        // overflowing the top left edges
+        let state = positioner(
+            Rectangle::from_loc_and_size((10, 10), (1, 1)),
+            Anchor::TopLeft,
+            Gravity::TopLeft,
+            resize,
+        );
+        assert_eq!(
+            state.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((0, 0), (10, 10))
+        );
+
+        // overflowing the bottom right edges
+        let state = positioner(
+            Rectangle::from_loc_and_size((480, 490), (1, 1)),
+            Anchor::TopLeft,
+            Gravity::BottomRight,
+            resize,
+        );
+        assert_eq!(
+            state.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((480, 490), (20, 10))
+        );
+    }
+
+    #[test]
+    fn unconstrain_slide_and_flip() {
+        let target = Rectangle::from_loc_and_size((0, 0), (500, 500));
+
+        let state = positioner(
+            Rectangle::from_loc_and_size((490, 490), (0, 0)),
+            Anchor::TopLeft,
+            Gravity::BottomRight,
+            ConstraintAdjustment::SlideX | ConstraintAdjustment::SlideY,
+        );
+        assert_eq!(
+            state.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((400, 450), (100, 50))
+        );
+
+        // flipping moves the popup to the other side of the anchor rectangle
+        let state = positioner(
+            Rectangle::from_loc_and_size((0, 480), (10, 10)),
+            Anchor::BottomLeft,
+            Gravity::BottomRight,
+            ConstraintAdjustment::FlipY,
+        );
+        assert_eq!(
+            state.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((0, 430), (100, 50))
+        );
+
+        // without any adjustment the geometry stays constrained
+        let state = positioner(
+            Rectangle::from_loc_and_size((0, 480), (10, 10)),
+            Anchor::BottomLeft,
+            Gravity::BottomRight,
+            ConstraintAdjustment::empty(),
+        );
+        assert_eq!(
+            state.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((0, 490), (100, 50))
+        );
+    }
+}
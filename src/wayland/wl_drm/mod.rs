@@ -0,0 +1,363 @@
+//! Legacy `wl_drm` protocol
+//!
+//! `wl_drm` is the protocol Mesa used to share gpu buffers between clients and compositors before
+//! the linux-dmabuf protocol existed. Some older or proprietary EGL stacks never adopted linux-dmabuf
+//! and still require it.
+//!
+//! This implementation only supports buffers created from prime file descriptors, the
+//! deprecated flink names are rejected. Buffers created through `wl_drm` are backed by a
+//! [`Dmabuf`] in the same way buffers of the [`dmabuf`](crate::wayland::dmabuf) module are, so
+//! they are imported by renderers through [`ImportDma`](crate::backend::renderer::ImportDma)
+//! and share the same texture cache. Accordingly the `wl_drm` global requires a working dmabuf
+//! setup and is created for an existing [`DmabufGlobal`].
+//!
+//! Buffers created through `wl_drm` carry no explicit modifier and use
+//! [`Modifier::Invalid`](crate::backend::allocator::Modifier::Invalid).
+//!
+//! ```no_run
+//! use smithay::{
+//!     delegate_dmabuf, delegate_wl_drm,
+//!     backend::allocator::dmabuf::Dmabuf,
+//!     reexports::wayland_server::protocol::wl_buffer::WlBuffer,
+//!     wayland::{
+//!         buffer::BufferHandler,
+//!         dmabuf::{DmabufGlobal, DmabufHandler, DmabufState, ImportNotifier},
+//!         wl_drm::{WlDrmHandler, WlDrmState},
+//!     },
+//! };
+//!
+//! pub struct State {
+//!     dmabuf_state: DmabufState,
+//! }
+//!
+//! impl BufferHandler for State {
+//!     fn buffer_destroyed(&mut self, buffer: &WlBuffer) {}
+//! }
+//!
+//! impl DmabufHandler for State {
+//!     fn dmabuf_state(&mut self) -> &mut DmabufState {
+//!         &mut self.dmabuf_state
+//!     }
+//!
+//!     fn dmabuf_imported(&mut self, global: &DmabufGlobal, dmabuf: Dmabuf, notifier: ImportNotifier) {
+//!         // import the dmabuf into your renderer
+//!         notifier.successful::<State>();
+//!     }
+//! }
+//!
+//! impl WlDrmHandler for State {
+//!     fn wl_drm_buffer_created(&mut self, global: &DmabufGlobal, dmabuf: &Dmabuf) -> bool {
+//!         // import the dmabuf into your renderer, the same way as in `dmabuf_imported`
+//!         true
+//!     }
+//! }
+//!
+//! delegate_dmabuf!(State);
+//! delegate_wl_drm!(State);
+//!
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! # let display_handle = display.handle();
+//! let mut dmabuf_state = DmabufState::new();
+//! # let formats: Vec<smithay::backend::allocator::Format> = { todo!() };
+//! let dmabuf_global = dmabuf_state.create_global::<State>(&display_handle, formats.clone());
+//!
+//! // advertise the render node the formats were queried from
+//! let wl_drm_state = WlDrmState::new::<State>(
+//!     &display_handle,
+//!     "/dev/dri/renderD128",
+//!     formats,
+//!     &dmabuf_global,
+//! );
+//! ```
+
+use std::{collections::HashSet, os::unix::io::OwnedFd, path::Path, sync::Arc};
+
+use wayland_server::{
+    backend::GlobalId, protocol::wl_buffer::WlBuffer, Client, DataInit, Dispatch, DisplayHandle,
+    GlobalDispatch, New, Resource,
+};
+
+use crate::backend::allocator::{
+    dmabuf::{Dmabuf, DmabufFlags},
+    Format, Fourcc, Modifier,
+};
+
+use super::dmabuf::{DmabufGlobal, DmabufHandler};
+
+/// Generated server-side API of the `wl_drm` protocol
+#[allow(missing_docs, non_upper_case_globals, non_camel_case_types, unused_imports)]
+#[allow(clippy::all)]
+pub mod protocol {
+    use wayland_server;
+    use wayland_server::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_server::{backend as wayland_backend, protocol::__interfaces::*};
+        wayland_scanner::generate_interfaces!("src/wayland/wl_drm/wayland-drm.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("src/wayland/wl_drm/wayland-drm.xml");
+}
+
+use protocol::wl_drm::{self, WlDrm};
+
+/// Handler trait for the `wl_drm` protocol
+pub trait WlDrmHandler: DmabufHandler {
+    /// A client created a buffer through `wl_drm`.
+    ///
+    /// `global` is the [`DmabufGlobal`] the `wl_drm` global was created for. You should import the
+    /// dmabuf into your renderer as you do in [`DmabufHandler::dmabuf_imported`]. The buffer has
+    /// already been created at this point, so returning `false` raises a protocol error
+    /// and disconnects the client.
+    fn wl_drm_buffer_created(&mut self, global: &DmabufGlobal, dmabuf: &Dmabuf) -> bool;
+}
+
+/// State of the `wl_drm` global
+#[derive(Debug)]
+pub struct WlDrmState {
+    global: GlobalId,
+}
+
+impl WlDrmState {
+    /// Create a new `wl_drm` global
+    ///
+    /// - `device_path` is advertised to clients, which will allocate their buffers on this device.
+    ///   It should be the render node of the device the `formats` were queried from, as clients
+    ///   opening a render node do not need to authenticate.
+    /// - `formats` are the formats supported for import, usually the same formats used for the
+    ///   `dmabuf_global`. Modifiers are ignored, as `wl_drm` only supports implicit modifiers.
+    pub fn new<D>(
+        display: &DisplayHandle,
+        device_path: impl AsRef<Path>,
+        formats: impl IntoIterator<Item = Format>,
+        dmabuf_global: &DmabufGlobal,
+    ) -> Self
+    where
+        D: GlobalDispatch<WlDrm, WlDrmGlobalData> + Dispatch<WlDrm, WlDrmData> + WlDrmHandler + 'static,
+    {
+        let mut seen = HashSet::new();
+        let formats = formats
+            .into_iter()
+            .map(|format| format.code)
+            .filter(|code| seen.insert(*code))
+            .collect::<Vec<_>>();
+
+        let data = WlDrmGlobalData {
+            device_path: device_path.as_ref().to_string_lossy().into_owned(),
+            formats: Arc::new(formats),
+            dmabuf_global: *dmabuf_global,
+        };
+        let global = display.create_global::<D, WlDrm, _>(2, data);
+
+        WlDrmState { global }
+    }
+
+    /// Returns the id of the `wl_drm` global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+/// Data associated with the `wl_drm` global
+#[derive(Debug)]
+pub struct WlDrmGlobalData {
+    device_path: String,
+    formats: Arc<Vec<Fourcc>>,
+    dmabuf_global: DmabufGlobal,
+}
+
+/// Data associated with a `wl_drm` instance
+#[derive(Debug)]
+pub struct WlDrmData {
+    formats: Arc<Vec<Fourcc>>,
+    dmabuf_global: DmabufGlobal,
+}
+
+impl<D> GlobalDispatch<WlDrm, WlDrmGlobalData, D> for WlDrmState
+where
+    D: GlobalDispatch<WlDrm, WlDrmGlobalData> + Dispatch<WlDrm, WlDrmData> + WlDrmHandler + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlDrm>,
+        global_data: &WlDrmGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let drm = data_init.init(
+            resource,
+            WlDrmData {
+                formats: global_data.formats.clone(),
+                dmabuf_global: global_data.dmabuf_global,
+            },
+        );
+
+        drm.device(global_data.device_path.clone());
+        if drm.version() >= 2 {
+            drm.capabilities(wl_drm::Capability::Prime as u32);
+        }
+        for format in global_data.formats.iter() {
+            drm.format(*format as u32);
+        }
+    }
+}
+
+impl<D> Dispatch<WlDrm, WlDrmData, D> for WlDrmState
+where
+    D: Dispatch<WlDrm, WlDrmData> + Dispatch<WlBuffer, Dmabuf> + WlDrmHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        drm: &WlDrm,
+        request: wl_drm::Request,
+        data: &WlDrmData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wl_drm::Request::Authenticate { .. } => {
+                // Clients are expected to open a render node, which needs no authentication.
+                drm.authenticated();
+            }
+
+            wl_drm::Request::CreateBuffer { .. } | wl_drm::Request::CreatePlanarBuffer { .. } => {
+                // The client is killed, so the buffer is never used and can stay uninitialized.
+                drm.post_error(wl_drm::Error::InvalidName, "flink names are not supported");
+            }
+
+            wl_drm::Request::CreatePrimeBuffer {
+                id,
+                name,
+                width,
+                height,
+                format,
+                offset0,
+                stride0,
+                offset1,
+                stride1,
+                offset2,
+                stride2,
+            } => {
+                let planes = [(offset0, stride0), (offset1, stride1), (offset2, stride2)];
+                let dmabuf = match create_dmabuf(&data.formats, name, width, height, format, planes) {
+                    Ok(dmabuf) => dmabuf,
+                    Err((error, message)) => {
+                        drm.post_error(error, message);
+                        return;
+                    }
+                };
+
+                data_init.init(id, dmabuf.clone());
+                if !state.wl_drm_buffer_created(&data.dmabuf_global, &dmabuf) {
+                    drm.post_error(wl_drm::Error::InvalidName, "failed to import the buffer");
+                }
+            }
+        }
+    }
+}
+
+fn create_dmabuf(
+    formats: &[Fourcc],
+    fd: OwnedFd,
+    width: i32,
+    height: i32,
+    format: u32,
+    planes: [(i32, i32); 3],
+) -> Result<Dmabuf, (wl_drm::Error, String)> {
+    let code = Fourcc::try_from(format)
+        .ok()
+        .filter(|code| formats.contains(code))
+        .ok_or_else(|| {
+            (
+                wl_drm::Error::InvalidFormat,
+                format!("Format {:x} is not supported", format),
+            )
+        })?;
+
+    if width < 1 || height < 1 {
+        return Err((
+            wl_drm::Error::InvalidName,
+            format!("Invalid buffer size {}x{}", width, height),
+        ));
+    }
+
+    let mut builder = Dmabuf::builder((width, height), code, Modifier::Invalid, DmabufFlags::empty());
+    // unused planes are passed with a zero stride, the first plane is always used
+    for (idx, (offset, stride)) in planes.into_iter().enumerate() {
+        if idx > 0 && stride == 0 {
+            continue;
+        }
+        if offset < 0 || stride < 1 {
+            return Err((
+                wl_drm::Error::InvalidName,
+                format!("Invalid offset {} or stride {} for plane {}", offset, stride, idx),
+            ));
+        }
+
+        let fd = fd.try_clone().map_err(|err| {
+            (
+                wl_drm::Error::InvalidName,
+                format!("Failed to duplicate the buffer fd: {}", err),
+            )
+        })?;
+        builder.add_plane(fd, idx as u32, offset as u32, stride as u32);
+    }
+
+    Ok(builder.build().unwrap())
+}
+
+/// Macro to delegate implementation of the `wl_drm` protocol to [`WlDrmState`].
+///
+/// You must also implement [`WlDrmHandler`] and delegate the dmabuf protocol with
+/// [`delegate_dmabuf`](crate::delegate_dmabuf) to use this.
+#[macro_export]
+macro_rules! delegate_wl_drm {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::wl_drm::protocol::wl_drm::WlDrm: $crate::wayland::wl_drm::WlDrmGlobalData
+        ] => $crate::wayland::wl_drm::WlDrmState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::wl_drm::protocol::wl_drm::WlDrm: $crate::wayland::wl_drm::WlDrmData
+        ] => $crate::wayland::wl_drm::WlDrmState);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_dmabuf, wl_drm};
+    use crate::backend::allocator::{Buffer, Fourcc, Modifier};
+
+    #[test]
+    fn prime_buffers_skip_unused_planes() {
+        let formats = [Fourcc::Nv12];
+        let fd = || std::fs::File::open("/dev/null").unwrap().into();
+
+        let dmabuf = create_dmabuf(
+            &formats,
+            fd(),
+            64,
+            32,
+            Fourcc::Nv12 as u32,
+            [(0, 64), (2048, 64), (0, 0)],
+        )
+        .unwrap();
+        assert_eq!(dmabuf.num_planes(), 2);
+        assert_eq!(dmabuf.offsets().collect::<Vec<_>>(), vec![0, 2048]);
+        assert_eq!(dmabuf.format().modifier, Modifier::Invalid);
+
+        let err = create_dmabuf(
+            &formats,
+            fd(),
+            64,
+            32,
+            Fourcc::Argb8888 as u32,
+            [(0, 256), (0, 0), (0, 0)],
+        )
+        .unwrap_err();
+        assert_eq!(err.0, wl_drm::Error::InvalidFormat);
+    }
+}
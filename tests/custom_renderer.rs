@@ -0,0 +1,342 @@
+//! A renderer implemented outside of smithay, only relying on its public api.
+//!
+//! It does not draw anything, but records the operations it is asked to perform.
+
+use std::fmt;
+
+use smithay::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, Buffer as _, Fourcc},
+        renderer::{
+            damage::OutputDamageTracker,
+            element::{solid::SolidColorRenderElement, Id, Kind},
+            sync::SyncPoint,
+            utils::CommitCounter,
+            Color32F, DebugFlags, Frame, ImportDma, ImportDmaWl, ImportMem, ImportMemWl, Renderer,
+            RendererId, Texture, TextureFilter,
+        },
+    },
+    desktop::{space::render_output, Space, Window},
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::wayland_server::protocol::wl_buffer::WlBuffer,
+    utils::{Buffer, Physical, Rectangle, Size, Transform},
+    wayland::{compositor::SurfaceData, shm},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Clear(Color32F),
+    Solid(Rectangle<i32, Physical>, Color32F),
+    Texture(Rectangle<i32, Physical>),
+}
+
+#[derive(Debug)]
+struct RecordingError;
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unsupported operation")
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+#[derive(Debug, Clone)]
+struct RecordingTexture {
+    size: Size<i32, Buffer>,
+}
+
+impl Texture for RecordingTexture {
+    fn width(&self) -> u32 {
+        self.size.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.size.h as u32
+    }
+
+    fn format(&self) -> Option<Fourcc> {
+        Some(Fourcc::Argb8888)
+    }
+}
+
+#[derive(Debug)]
+struct RecordingRenderer {
+    id: RendererId,
+    debug_flags: DebugFlags,
+    ops: Vec<Op>,
+}
+
+impl RecordingRenderer {
+    fn new() -> Self {
+        RecordingRenderer {
+            id: RendererId::new(),
+            debug_flags: DebugFlags::empty(),
+            ops: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RecordingFrame<'frame> {
+    id: usize,
+    transform: Transform,
+    ops: &'frame mut Vec<Op>,
+}
+
+impl Frame for RecordingFrame<'_> {
+    type Error = RecordingError;
+    type TextureId = RecordingTexture;
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn clear(&mut self, color: Color32F, _at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        self.ops.push(Op::Clear(color));
+        Ok(())
+    }
+
+    fn draw_solid(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        _damage: &[Rectangle<i32, Physical>],
+        color: Color32F,
+    ) -> Result<(), Self::Error> {
+        self.ops.push(Op::Solid(dst, color));
+        Ok(())
+    }
+
+    fn render_texture_from_to(
+        &mut self,
+        _texture: &Self::TextureId,
+        _src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        _damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+        _src_transform: Transform,
+        _alpha: f32,
+    ) -> Result<(), Self::Error> {
+        self.ops.push(Op::Texture(dst));
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn wait(&mut self, _sync: &SyncPoint) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn finish(self) -> Result<SyncPoint, Self::Error> {
+        Ok(SyncPoint::signaled())
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    type Error = RecordingError;
+    type TextureId = RecordingTexture;
+    type Frame<'frame> = RecordingFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id.get()
+    }
+
+    fn downscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn upscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_debug_flags(&mut self, flags: DebugFlags) {
+        self.debug_flags = flags;
+    }
+
+    fn debug_flags(&self) -> DebugFlags {
+        self.debug_flags
+    }
+
+    fn render(
+        &mut self,
+        _output_size: Size<i32, Physical>,
+        dst_transform: Transform,
+    ) -> Result<Self::Frame<'_>, Self::Error> {
+        Ok(RecordingFrame {
+            id: self.id.get(),
+            transform: dst_transform,
+            ops: &mut self.ops,
+        })
+    }
+
+    fn wait(&mut self, _sync: &SyncPoint) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ImportMem for RecordingRenderer {
+    fn import_memory(
+        &mut self,
+        _data: &[u8],
+        _format: Fourcc,
+        size: Size<i32, Buffer>,
+        _flipped: bool,
+    ) -> Result<Self::TextureId, Self::Error> {
+        Ok(RecordingTexture { size })
+    }
+
+    fn update_memory(
+        &mut self,
+        _texture: &Self::TextureId,
+        _data: &[u8],
+        _region: Rectangle<i32, Buffer>,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
+        Box::new([Fourcc::Argb8888, Fourcc::Xrgb8888].into_iter())
+    }
+}
+
+impl ImportMemWl for RecordingRenderer {
+    fn import_shm_buffer(
+        &mut self,
+        buffer: &WlBuffer,
+        _surface: Option<&SurfaceData>,
+        _damage: &[Rectangle<i32, Buffer>],
+    ) -> Result<Self::TextureId, Self::Error> {
+        let size = shm::with_buffer_contents(buffer, |_, _, data| (data.width, data.height))
+            .map_err(|_| RecordingError)?;
+        Ok(RecordingTexture { size: size.into() })
+    }
+}
+
+impl ImportDma for RecordingRenderer {
+    fn import_dmabuf(
+        &mut self,
+        dmabuf: &Dmabuf,
+        _damage: Option<&[Rectangle<i32, Buffer>]>,
+    ) -> Result<Self::TextureId, Self::Error> {
+        Ok(RecordingTexture { size: dmabuf.size() })
+    }
+}
+
+impl ImportDmaWl for RecordingRenderer {}
+
+#[cfg(all(feature = "backend_egl", feature = "use_system_lib"))]
+impl smithay::backend::renderer::ImportEgl for RecordingRenderer {
+    fn import_egl_buffer(
+        &mut self,
+        _buffer: &WlBuffer,
+        _surface: Option<&SurfaceData>,
+        _damage: &[Rectangle<i32, Buffer>],
+    ) -> Result<Self::TextureId, Self::Error> {
+        Err(RecordingError)
+    }
+}
+
+fn solid(geometry: Rectangle<i32, Physical>) -> SolidColorRenderElement {
+    SolidColorRenderElement::new(
+        Id::new(),
+        geometry,
+        CommitCounter::default(),
+        [1.0, 0.0, 0.0, 1.0],
+        Kind::Unspecified,
+    )
+}
+
+#[test]
+fn renderers_have_unique_ids() {
+    let first = RecordingRenderer::new();
+    let second = RecordingRenderer::new();
+    assert_ne!(first.id(), second.id());
+
+    // the renderers provided by smithay allocate from the same pool
+    #[cfg(feature = "renderer_test")]
+    {
+        let dummy = smithay::backend::renderer::test::DummyRenderer::new();
+        assert!(dummy.id() != first.id() && dummy.id() != second.id());
+    }
+    #[cfg(feature = "renderer_pixman")]
+    {
+        let pixman = smithay::backend::renderer::pixman::PixmanRenderer::new().unwrap();
+        assert!(pixman.id() != first.id() && pixman.id() != second.id());
+    }
+}
+
+#[test]
+fn damage_tracker_renders_custom_renderer() {
+    let mut renderer = RecordingRenderer::new();
+    let mut damage_tracker = OutputDamageTracker::new((64, 64), 1.0, Transform::Normal);
+    let geometry = Rectangle::from_loc_and_size((8, 8), (16, 16));
+    let element = solid(geometry);
+
+    damage_tracker
+        .render_output(&mut renderer, 0, std::slice::from_ref(&element), Color32F::BLACK)
+        .unwrap();
+    assert!(renderer
+        .ops
+        .contains(&Op::Solid(geometry, [1.0, 0.0, 0.0, 1.0].into())));
+
+    // nothing changed, so nothing has to be drawn
+    renderer.ops.clear();
+    damage_tracker
+        .render_output(&mut renderer, 1, &[element], Color32F::BLACK)
+        .unwrap();
+    assert!(renderer.ops.is_empty());
+}
+
+#[test]
+fn space_renders_with_custom_renderer() {
+    let mut renderer = RecordingRenderer::new();
+    let output = Output::new(
+        "custom".into(),
+        PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "smithay".into(),
+            model: "custom".into(),
+            serial_number: "Unknown".into(),
+        },
+    );
+    output.change_current_state(
+        Some(Mode {
+            size: (64, 64).into(),
+            refresh: 60_000,
+        }),
+        None,
+        None,
+        Some((0, 0).into()),
+    );
+    let mut space = Space::<Window>::default();
+    space.map_output(&output, (0, 0));
+
+    let mut damage_tracker = OutputDamageTracker::from_output(&output);
+    let element = solid(Rectangle::from_loc_and_size((0, 0), (64, 64)));
+    render_output(
+        &output,
+        &mut renderer,
+        1.0,
+        0,
+        [&space],
+        &[element],
+        &mut damage_tracker,
+        Color32F::BLACK,
+    )
+    .unwrap();
+    assert!(renderer.ops.iter().any(|op| matches!(op, Op::Solid(..))));
+    assert!(!renderer.ops.iter().any(|op| matches!(op, Op::Texture(..))));
+}
+
+#[test]
+fn custom_renderer_reports_default_capabilities() {
+    let renderer = RecordingRenderer::new();
+    let capabilities = renderer.renderer_capabilities();
+    assert!(capabilities.max_texture_size.is_none());
+    assert!(capabilities.supports_size((16384, 16384).into()));
+    assert!(capabilities.dmabuf_texture_formats.iter().next().is_none());
+    assert!(!capabilities.fencing);
+}